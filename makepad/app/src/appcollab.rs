@@ -0,0 +1,339 @@
+use makepad_render::*;
+use makepad_widget::*;
+use makepad_hub::*;
+use makepad_microserde::*;
+use crate::appstorage::*;
+
+// Editing workspace files together with other makepad instances on the same hub. All Collab
+// messages go to every UI and the router hands them out in one order, TextCollab relies on that.
+// A file joins when it loads: the first instance to have it open sends its state to the others,
+// if nobody answers within a session timer tick the file starts a session of its own. Instances
+// joining the same file at once tell each other their join, the lowest site starts the session
+// and sends its state to the rest.
+
+pub enum AppCollab {
+    Off,
+    // waiting for our join to come back, then for the state. changes after our join are kept,
+    // rivals are the others joining the same file without a session to join
+    Joining {uid: HubUid, echoed: bool, ticks: usize, rivals: Vec<HubUid>, changes: Vec<(u64, usize, TextChange)>},
+    Active(TextCollab),
+}
+
+// a collaborator is known by its hub address, the hash is stable on every instance
+pub fn collab_site(addr: &HubAddr) -> u64 {
+    let mut bytes = Vec::new();
+    addr.ser_bin(&mut bytes);
+    let mut hash = 0xcbf29ce484222325u64;
+    for byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+fn collab_msg(path: &str, send: TextCollabSend) -> HubMsg {
+    match send {
+        TextCollabSend::Change {rev, change} => {
+            let mut bytes = Vec::new();
+            change.ser_bin(&mut bytes);
+            HubMsg::CollabChange {path: path.to_string(), rev: rev as u64, change: bytes}
+        },
+        TextCollabSend::Cursors {rev, cursors} => HubMsg::CollabCursors {
+            path: path.to_string(),
+            rev: rev as u64,
+            cursors: cursors.iter().map( | (head, tail) | (*head as u64, *tail as u64)).collect()
+        }
+    }
+}
+
+impl AppStorage {
+    fn collab_send(&self, msg: HubMsg) {
+        if let Some(hub_ui) = &self.hub_ui {
+            hub_ui.route_send.send(ToHubMsg {to: HubMsgTo::UI, msg: msg});
+        }
+    }
+
+    pub fn collab_join(&mut self, tb_id: AppTextBufferId) {
+        if self.settings.collab_edit != Some(true) {
+            return
+        }
+        self.collab_rejoin(tb_id.0 as usize);
+    }
+
+    fn collab_rejoin(&mut self, index: usize) {
+        let uid = match &mut self.hub_ui {
+            Some(hub_ui) => hub_ui.route_send.alloc_uid(),
+            None => return
+        };
+        let atb = &mut self.text_buffers[index];
+        atb.collab = AppCollab::Joining {uid: uid, echoed: false, ticks: 0, rivals: Vec::new(), changes: Vec::new()};
+        let path = atb.full_path.clone();
+        self.collab_send(HubMsg::CollabJoin {uid: uid, path: path});
+    }
+
+    fn collab_start(cx: &mut Cx, atb: &mut AppTextBuffer, collab: TextCollab) {
+        atb.text_buffer.change_log = Some(Vec::new());
+        atb.text_buffer.change_log_base = None;
+        atb.collab = AppCollab::Active(collab);
+        Self::collab_update_markers(cx, atb);
+    }
+
+    fn collab_update_markers(cx: &mut Cx, atb: &mut AppTextBuffer) {
+        if let AppCollab::Active(collab) = &atb.collab {
+            let (cursors, peers) = collab.peer_cursors();
+            atb.text_buffer.markers.remote_cursors = cursors;
+            atb.text_buffer.markers.remote_cursor_peers = peers;
+            cx.send_signal(atb.text_buffer.signal, TextBuffer::status_data_update());
+        }
+    }
+
+    // joins still unanswered after a tick start on their own, unless a rival with a lower site
+    // is joining too. It sends us its state, if that never comes we join again
+    pub fn collab_timer_tick(&mut self, cx: &mut Cx) {
+        let mut msgs = Vec::new();
+        let mut rejoin = Vec::new();
+        for (index, atb) in self.text_buffers.iter_mut().enumerate() {
+            if let AppCollab::Joining {uid, echoed: true, ticks, rivals, ..} = &mut atb.collab {
+                *ticks += 1;
+                let site = collab_site(&uid.addr);
+                if rivals.iter().any( | rival | collab_site(&rival.addr) < site) {
+                    if *ticks > 5 {
+                        rejoin.push(index);
+                    }
+                }
+                else if *ticks > 1 {
+                    let chars = TextChange::lines_to_chars(&atb.text_buffer.lines.to_vec());
+                    let collab = TextCollab::new(site, chars);
+                    let mut state = Vec::new();
+                    collab.state().ser_bin(&mut state);
+                    for rival in rivals.iter() {
+                        msgs.push(ToHubMsg {
+                            to: HubMsgTo::Client(rival.addr),
+                            msg: HubMsg::CollabState {uid: *rival, path: atb.full_path.clone(), state: state.clone()}
+                        });
+                    }
+                    Self::collab_start(cx, atb, collab);
+                }
+            }
+        }
+        if let Some(hub_ui) = &self.hub_ui {
+            for msg in msgs {
+                hub_ui.route_send.send(msg);
+            }
+        }
+        for index in rejoin {
+            self.collab_rejoin(index);
+        }
+    }
+
+    // sends what our editors did since the last call
+    pub fn collab_flush(&mut self) {
+        let mut msgs = Vec::new();
+        for atb in &mut self.text_buffers {
+            if let AppCollab::Active(collab) = &mut atb.collab {
+                for change in atb.text_buffer.take_change_log() {
+                    if let Some(send) = collab.local_change(change) {
+                        msgs.push(collab_msg(&atb.full_path, send));
+                    }
+                }
+                if let Some(cursors) = atb.text_buffer.collab_cursors.take() {
+                    if let Some(send) = collab.local_cursors(&cursors) {
+                        msgs.push(collab_msg(&atb.full_path, send));
+                    }
+                }
+            }
+        }
+        for msg in msgs {
+            self.collab_send(msg);
+        }
+    }
+
+    pub fn handle_collab_msg(&mut self, cx: &mut Cx, htc: &FromHubMsg) {
+        // anything we did locally goes out before we look at what came in
+        self.collab_flush();
+        let is_own = match &self.hub_ui {
+            Some(hub_ui) => hub_ui.route_send.is_own_addr(&htc.from),
+            None => return
+        };
+        let site = collab_site(&htc.from);
+        let mut reply = None;
+        match &htc.msg {
+            HubMsg::CollabJoin {uid, path} => if let Some(tb_id) = self.text_buffer_path_to_id.get(path) {
+                match &mut self.text_buffers[tb_id.0 as usize].collab {
+                    AppCollab::Joining {uid: join_uid, echoed, rivals, ..} => if is_own {
+                        if *join_uid == *uid {
+                            *echoed = true;
+                        }
+                    }
+                    else if !rivals.contains(uid) {
+                        // they may not have seen our join, tell them once so both sides know
+                        rivals.push(*uid);
+                        if let Some(hub_ui) = &self.hub_ui {
+                            hub_ui.route_send.send(ToHubMsg {
+                                to: HubMsgTo::Client(htc.from),
+                                msg: HubMsg::CollabJoin {uid: *join_uid, path: path.clone()}
+                            });
+                        }
+                    },
+                    AppCollab::Active(collab) => if !is_own {
+                        let mut state = Vec::new();
+                        collab.state().ser_bin(&mut state);
+                        if let Some(hub_ui) = &self.hub_ui {
+                            hub_ui.route_send.send(ToHubMsg {
+                                to: HubMsgTo::Client(htc.from),
+                                msg: HubMsg::CollabState {uid: *uid, path: path.clone(), state: state}
+                            });
+                        }
+                    },
+                    AppCollab::Off => ()
+                }
+            },
+            HubMsg::CollabState {uid, path, state} => if let Some(tb_id) = self.text_buffer_path_to_id.get(path) {
+                let atb = &mut self.text_buffers[tb_id.0 as usize];
+                if let AppCollab::Joining {uid: join_uid, echoed: true, changes, ..} = &mut atb.collab {
+                    if *join_uid == *uid {
+                        let state: TextCollabState = match DeBin::deserialize_bin(state) {
+                            Ok(state) => state,
+                            Err(_) => return
+                        };
+                        let mut collab = TextCollab::from_state(collab_site(&uid.addr), state);
+                        // take over their text, then the changes that came in after our join
+                        let chars = TextChange::lines_to_chars(&atb.text_buffer.lines.to_vec());
+                        let change = TextChange::between(&chars, &collab.text);
+                        if !change.is_noop() {
+                            atb.text_buffer.apply_remote_change(&change);
+                        }
+                        for (site, rev, change) in std::mem::replace(changes, Vec::new()) {
+                            if let (Some(change), _) = collab.receive_change(site, rev, change) {
+                                atb.text_buffer.apply_remote_change(&change);
+                            }
+                        }
+                        Self::collab_start(cx, atb, collab);
+                    }
+                }
+            },
+            HubMsg::CollabChange {path, rev, change} => if let Some(tb_id) = self.text_buffer_path_to_id.get(path) {
+                let atb = &mut self.text_buffers[tb_id.0 as usize];
+                let change: TextChange = match DeBin::deserialize_bin(change) {
+                    Ok(change) => change,
+                    Err(_) => return
+                };
+                match &mut atb.collab {
+                    AppCollab::Joining {echoed: true, changes, ..} => changes.push((site, *rev as usize, change)),
+                    AppCollab::Active(collab) => {
+                        let (apply, send) = collab.receive_change(site, *rev as usize, change);
+                        if let Some(apply) = apply {
+                            atb.text_buffer.apply_remote_change(&apply);
+                        }
+                        reply = send.map( | send | collab_msg(path, send));
+                        Self::collab_update_markers(cx, atb);
+                    },
+                    _ => ()
+                }
+            },
+            HubMsg::CollabCursors {path, rev, cursors} => if let Some(tb_id) = self.text_buffer_path_to_id.get(path) {
+                let atb = &mut self.text_buffers[tb_id.0 as usize];
+                if let AppCollab::Active(collab) = &mut atb.collab {
+                    let cursors: Vec<(usize, usize)> = cursors.iter().map( | (head, tail) | (*head as usize, *tail as usize)).collect();
+                    collab.receive_cursors(site, *rev as usize, &cursors);
+                    Self::collab_update_markers(cx, atb);
+                }
+            },
+            HubMsg::DisconnectUI => for atb in &mut self.text_buffers {
+                if let AppCollab::Active(collab) = &mut atb.collab {
+                    collab.remove_peer(site);
+                    Self::collab_update_markers(cx, atb);
+                }
+            },
+            _ => ()
+        }
+        if let Some(reply) = reply {
+            self.collab_send(reply);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    struct Client {
+        route_send: HubRouteSend,
+        rx: mpsc::Receiver<FromHubMsg>,
+        text: Vec<char>,
+        collab: TextCollab
+    }
+
+    impl Client {
+        fn new(hub_router: &mut HubRouter, text: &str) -> Client {
+            let (tx, rx) = mpsc::channel();
+            let route_send = hub_router.connect_direct(HubRouteType::Unknown, tx);
+            route_send.send(ToHubMsg {to: HubMsgTo::Hub, msg: HubMsg::ConnectUI});
+            let site = match &route_send {
+                HubRouteSend::Direct {own_addr, ..} => collab_site(own_addr),
+                _ => panic!()
+            };
+            let text: Vec<char> = text.chars().collect();
+            Client {route_send: route_send, rx: rx, collab: TextCollab::new(site, text.clone()), text: text}
+        }
+
+        fn edit(&mut self, start: usize, len: usize, string: &str) {
+            let change = TextChange::replace(self.text.len(), start, len, string.chars().collect());
+            self.text = change.apply_to_chars(&self.text);
+            if let Some(send) = self.collab.local_change(change) {
+                self.route_send.send(ToHubMsg {to: HubMsgTo::UI, msg: collab_msg("file", send)});
+            }
+        }
+
+        // handles one message, false when nothing came in for a while
+        fn receive(&mut self) -> bool {
+            let htc = match self.rx.recv_timeout(Duration::from_millis(200)) {
+                Ok(htc) => htc,
+                Err(_) => return false
+            };
+            if let HubMsg::CollabChange {rev, change, ..} = htc.msg {
+                let change: TextChange = DeBin::deserialize_bin(&change).unwrap();
+                let (apply, send) = self.collab.receive_change(collab_site(&htc.from), rev as usize, change);
+                if let Some(apply) = apply {
+                    self.text = apply.apply_to_chars(&self.text);
+                }
+                if let Some(send) = send {
+                    self.route_send.send(ToHubMsg {to: HubMsgTo::UI, msg: collab_msg("file", send)});
+                }
+            }
+            true
+        }
+    }
+
+    #[test]
+    fn two_clients_converge_over_the_hub() {
+        let mut hub_router = HubRouter::start_hub_router(HubLog::None);
+        let mut a = Client::new(&mut hub_router, "hello world");
+        let mut b = Client::new(&mut hub_router, "hello world");
+        // edits on both sides before either sees the other, several in flight at once
+        a.edit(5, 0, ",");
+        a.edit(0, 0, "oh ");
+        b.edit(6, 5, "there");
+        b.edit(11, 0, "!");
+        a.edit(9, 1, "");
+        // and one while messages are going around
+        let mut more = true;
+        loop {
+            let got_a = a.receive();
+            let got_b = b.receive();
+            if !got_a && !got_b {
+                break;
+            }
+            if more && got_b {
+                b.edit(0, 1, "H");
+                more = false;
+            }
+        }
+        assert_eq!(a.text, b.text);
+        assert_eq!(a.text, a.collab.text);
+        assert_eq!(b.text, b.collab.text);
+        assert_eq!(a.collab.rev(), b.collab.rev());
+    }
+}
@@ -0,0 +1,115 @@
+use makepad_render::*;
+use makepad_widget::*;
+use makepad_microserde::*;
+
+// The session file stores everything that doesn't survive a restart through makepad_state.ron:
+// unsaved buffer contents, undo trees and the cursor sets and folding of open editors.
+// It is written periodically so a crash only loses the last few seconds of work.
+
+#[derive(Clone, Default, SerBin, DeBin)]
+pub struct AppSession {
+    pub text_buffers: Vec<AppTextBufferSession>,
+    pub editors: Vec<FileEditorSession>,
+}
+
+#[derive(Clone, SerBin, DeBin)]
+pub struct AppTextBufferSession {
+    pub path: String,
+    // hash of the file on disk the undo tree and unsaved contents are relative to
+    pub disk_hash: u64,
+    pub unsaved: Option<String>,
+    pub undo_tree: TextUndoTree,
+}
+
+#[derive(Clone, SerBin, DeBin)]
+pub struct FileEditorSession {
+    pub window_index: usize,
+    pub editor_id: u64,
+    pub path: String,
+    pub cursors: TextCursorSet,
+    pub folded: bool,
+}
+
+impl AppSession {
+    pub fn take_text_buffer(&mut self, path: &str) -> Option<AppTextBufferSession> {
+        if let Some(index) = self.text_buffers.iter().position( | tb | tb.path == path) {
+            return Some(self.text_buffers.remove(index))
+        }
+        None
+    }
+
+    pub fn get_editor(&self, window_index: usize, editor_id: u64, path: &str) -> Option<&FileEditorSession> {
+        self.editors.iter().find( | ed | ed.window_index == window_index && ed.editor_id == editor_id && ed.path == path)
+    }
+}
+
+// FNV-1a, stable across runs so we can compare against the hash stored in the session file
+pub fn session_hash_utf8(utf8_data: &str) -> u64 {
+    let mut hash = 0xcbf29ce484222325u64;
+    for byte in utf8_data.as_bytes() {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+#[derive(Clone)]
+pub struct HotExitPrompt {
+    pub view: View,
+    pub text: Text,
+    pub restore_button: NormalButton,
+    pub discard_button: NormalButton,
+}
+
+pub enum HotExitPromptEvent {
+    None,
+    Restore,
+    Discard
+}
+
+impl HotExitPrompt {
+    pub fn new(cx: &mut Cx) -> Self {
+        Self {
+            view: View::new(cx),
+            text: Text::new(cx),
+            restore_button: NormalButton::new(cx),
+            discard_button: NormalButton::new(cx),
+        }
+    }
+
+    pub fn layout_main() -> LayoutId {uid!()}
+    pub fn text_style_body() -> TextStyleId {uid!()}
+    pub fn text_color() -> ColorId {uid!()}
+
+    pub fn style(cx: &mut Cx, _opt: &StyleOptions) {
+        Self::layout_main().set(cx, Layout {
+            padding: Padding {l: 10., t: 10., r: 10., b: 10.},
+            new_line_padding: 15.,
+            line_wrap: LineWrap::NewLine,
+            ..Layout::default()
+        });
+        Self::text_style_body().set(cx, Theme::text_style_normal().get(cx));
+        Self::text_color().set(cx, Theme::color_text_deselected_focus().get(cx));
+    }
+
+    pub fn handle_hot_exit_prompt(&mut self, cx: &mut Cx, event: &mut Event) -> HotExitPromptEvent {
+        if let ButtonEvent::Clicked = self.restore_button.handle_normal_button(cx, event) {
+            return HotExitPromptEvent::Restore
+        }
+        if let ButtonEvent::Clicked = self.discard_button.handle_normal_button(cx, event) {
+            return HotExitPromptEvent::Discard
+        }
+        HotExitPromptEvent::None
+    }
+
+    pub fn draw_hot_exit_prompt(&mut self, cx: &mut Cx, path: &str) {
+        if self.view.begin_view(cx, Self::layout_main().get(cx)).is_err() {return};
+        self.text.text_style = Self::text_style_body().get(cx);
+        self.text.color = Self::text_color().get(cx);
+        self.text.draw_text(cx, &format!("{} has unsaved edits from a previous session, but the file on disk has changed since.", path));
+        cx.turtle_new_line();
+        self.restore_button.draw_normal_button(cx, "Restore unsaved edits");
+        self.discard_button.draw_normal_button(cx, "Keep file on disk");
+        self.view.end_view(cx);
+    }
+}
@@ -8,8 +8,13 @@ use crate::filetree::*;
 use crate::fileeditor::*;
 use crate::buildmanager::*;
 use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
 use crate::builder;
 use crate::livemacro::*;
+use crate::appsession::*;
+use crate::appcollab::*;
 
 #[derive(Debug, Clone, SerRon, DeRon)]
 pub struct AppSettings { 
@@ -21,6 +26,13 @@ pub struct AppSettings {
     pub builders: HashMap<String, HubBuilderConfig>,
     pub builds: Vec<BuildTarget>,
     pub sync: HashMap<String, Vec<String>>,
+    pub keymap: Option<Vec<KeyBinding>>,
+    // the session keeps every undo branch, false keeps only the current one
+    pub persist_undo_tree: Option<bool>,
+    // edit workspace files together with other makepad instances on the hub
+    pub collab_edit: Option<bool>,
+    // soft wrapped files wrap after this many columns, instead of at the view width
+    pub wrap_column: Option<usize>,
 }
 
 impl Default for AppSettings {
@@ -34,7 +46,11 @@ impl Default for AppSettings {
             hub_server: HubServerConfig::Offline,
             builders: HashMap::new(),
             sync: HashMap::new(),
-            builds: vec![]
+            builds: vec![],
+            keymap: None,
+            persist_undo_tree: None,
+            collab_edit: None,
+            wrap_column: None
         }
     }
 }
@@ -71,7 +87,11 @@ impl AppSettings {
                     package: "webgl_example_wasm".to_string(),
                     config: "release".to_string()
                 }
-            ]
+            ],
+            keymap: Some(vec![]),
+            persist_undo_tree: Some(true),
+            collab_edit: Some(false),
+            wrap_column: None
         }
     }
 }
@@ -99,9 +119,17 @@ pub struct AppStorage {
     pub file_tree_file_read: FileRead,
     pub app_state_file_read: FileRead,
     pub app_settings_file_read: FileRead,
+    pub session_file_read: FileRead,
+    pub snippet_file_reads: Vec<(String, FileRead)>,
+    pub session_timer: Timer,
+    pub session: AppSession,
+    pub session_last_written: Vec<u8>,
+    // a hash of what the last session was built from, it isn't built again until that changes
+    pub session_last_changes: Option<u64>,
     pub text_buffer_path_to_id: HashMap<String, AppTextBufferId>,
     pub text_buffer_id_to_path: HashMap<AppTextBufferId, String>,
     pub text_buffers: Vec<AppTextBuffer>,
+    pub languages: LanguageRegistry,
 }
 
 
@@ -111,7 +139,15 @@ pub struct AppTextBuffer {
     pub full_path: String,
     pub text_buffer: TextBuffer,
     pub text_buffer_id: AppTextBufferId,
-    pub live_macros: LiveMacros
+    pub live_macros: LiveMacros,
+    pub saved_mutation_id: u32,
+    pub disk_hash: u64,
+    // the file in git HEAD, the line change markers are against it. None when it isn't tracked
+    pub base_line_hashes: Option<Vec<u64>>,
+    pub line_changes_id: Option<u32>,
+    pub git_head_uid: Option<HubUid>,
+    pub hot_exit: Option<AppTextBufferSession>,
+    pub collab: AppCollab
 }
 
 #[derive(Clone, Copy, Default, PartialEq, Ord, PartialOrd, Hash, Eq)]
@@ -120,6 +156,84 @@ impl AppTextBufferId{
     pub fn as_index(&self )->usize{return self.0 as usize}
 }
 
+impl AppTextBuffer {
+    pub fn is_dirty(&self) -> bool {
+        self.text_buffer.mutation_id != self.saved_mutation_id
+    }
+    
+    pub fn mark_saved(&mut self, utf8_data: &str) {
+        self.saved_mutation_id = self.text_buffer.mutation_id;
+        self.disk_hash = session_hash_utf8(utf8_data);
+    }
+    
+    pub fn update_line_changes(&mut self) {
+        if self.text_buffer.is_loaded && self.line_changes_id != Some(self.text_buffer.mutation_id) {
+            self.line_changes_id = Some(self.text_buffer.mutation_id);
+            if let Some(base_line_hashes) = &self.base_line_hashes {
+                self.text_buffer.update_line_changes(base_line_hashes);
+            }
+            else {
+                self.text_buffer.markers.line_changes.truncate(0);
+            }
+        }
+    }
+    
+    pub fn load_from_disk(&mut self, cx: &mut Cx, utf8_data: &str, session: &mut AppSession) {
+        self.text_buffer.load_from_utf8(utf8_data);
+        self.mark_saved(utf8_data);
+        if let Some(tb_session) = session.take_text_buffer(&self.full_path) {
+            // only restore silently if the file on disk is still the one the session was made against
+            if tb_session.disk_hash == self.disk_hash {
+                self.restore_session(tb_session);
+            }
+            else if tb_session.unsaved.is_some() {
+                self.hot_exit = Some(tb_session);
+            }
+        }
+        self.text_buffer.send_textbuffer_loaded_signal(cx);
+    }
+    
+    pub fn restore_session(&mut self, tb_session: AppTextBufferSession) {
+        if let Some(unsaved) = &tb_session.unsaved {
+            self.text_buffer.load_from_utf8(unsaved);
+        }
+        self.text_buffer.undo_tree = tb_session.undo_tree;
+    }
+    
+    pub fn resolve_hot_exit(&mut self, cx: &mut Cx, restore: bool) {
+        if let Some(tb_session) = self.hot_exit.take() {
+            if restore {
+                self.restore_session(tb_session);
+            }
+            self.text_buffer.send_textbuffer_loaded_signal(cx);
+        }
+    }
+    
+    pub fn get_session(&self, persist_undo_tree: bool) -> Option<AppTextBufferSession> {
+        if !self.text_buffer.is_loaded {
+            return None
+        }
+        if let Some(tb_session) = &self.hot_exit {
+            return Some(tb_session.clone())
+        }
+        let unsaved = if self.is_dirty() {Some(self.text_buffer.get_as_string())} else {None};
+        if unsaved.is_none() && self.text_buffer.undo_tree.is_empty() {
+            return None
+        }
+        Some(AppTextBufferSession {
+            path: self.full_path.clone(),
+            disk_hash: self.disk_hash,
+            unsaved: unsaved,
+            undo_tree: if persist_undo_tree {
+                self.text_buffer.undo_tree.clone()
+            }
+            else {
+                self.text_buffer.undo_tree.current_branch()
+            },
+        })
+    }
+}
+
 impl AppStorage {
     pub fn new(cx: &mut Cx) -> Self {
         AppStorage {
@@ -138,9 +252,16 @@ impl AppStorage {
             text_buffer_path_to_id: HashMap::new(),
             text_buffer_id_to_path: HashMap::new(),
             text_buffers: Vec::new(),
+            languages: FileEditors::default_languages(),
             file_tree_file_read: FileRead::default(),
             app_state_file_read: FileRead::default(),
-            app_settings_file_read: FileRead::default()
+            app_settings_file_read: FileRead::default(),
+            session_file_read: FileRead::default(),
+            snippet_file_reads: Vec::new(),
+            session_timer: Timer::empty(),
+            session: AppSession::default(),
+            session_last_written: Vec::new(),
+            session_last_changes: None,
         }
     }
      
@@ -148,10 +269,18 @@ impl AppStorage {
     pub fn status_settings_changed() -> StatusId {uid!()}
     
     pub fn init(&mut self, cx: &mut Cx) {
+        for language in &self.languages.languages {
+            let name = language.name().to_string();
+            let file_read = cx.file_read(&format!("resources/snippets/{}.ron", name));
+            self.snippet_file_reads.push((name, file_read));
+        }
+        
         if cx.platform_type.is_desktop() {
             
             self.app_state_file_read = cx.file_read("makepad_state.ron");
             self.app_settings_file_read = cx.file_read("makepad_settings.ron");
+            self.session_file_read = cx.file_read("makepad_session.bin");
+            self.session_timer = cx.start_timer(2.0, true);
             
             // lets start the router
             let mut hub_router = HubRouter::start_hub_router(HubLog::None);
@@ -181,6 +310,10 @@ impl AppStorage {
                 self.settings.style_options.scale = self.settings.style_options.scale.min(3.0).max(0.3);
                 cx.send_signal(self.settings_changed, Self::status_settings_changed());
                 cx.live_macros_on_self = self.settings.live_macros_on_self;
+                let no_bindings = Vec::new();
+                for err in cx.keymap.set_user_bindings(self.settings.keymap.as_ref().unwrap_or(&no_bindings)) {
+                    println!("Cannot parse key binding {}", err);
+                }
                 // so now, here we restart our hub_server if need be.
                 if cx.platform_type.is_desktop() {
                     if self.settings_old.hub_server != self.settings.hub_server {
@@ -194,12 +327,39 @@ impl AppStorage {
         }
     }
     
+    // a missing snippet file just means the language has no snippets
+    pub fn resolve_snippet_file_read(&mut self, fr: &FileReadEvent) -> Option<(String, Option<Rc<Vec<Snippet>>>)> {
+        for (name, file_read) in &mut self.snippet_file_reads {
+            if let Some(utf8_data) = file_read.resolve_utf8(fr) {
+                let snippets = match utf8_data {
+                    Ok(utf8_data) => match DeRon::deserialize_ron(utf8_data) {
+                        Ok(snippets) => {
+                            let snippets: Vec<Snippet> = snippets;
+                            Some(Rc::new(snippets))
+                        },
+                        Err(e) => {
+                            println!("Cannot deserialize snippets for {} {:?}", name, e);
+                            None
+                        }
+                    },
+                    Err(_) => None
+                };
+                if let Some(snippets) = &snippets {
+                    self.languages.set_snippets(name, snippets.clone());
+                }
+                return Some((name.clone(), snippets))
+            }
+        }
+        None
+    }
+    
     pub fn save_settings(&mut self, cx: &mut Cx) {
         let utf8_data = self.settings.serialize_ron();
         let path = "makepad_settings.ron";
         if let Some(tb_id) = self.text_buffer_path_to_id.get(path) {
             let atb = &mut self.text_buffers[tb_id.0 as usize];
             atb.text_buffer.load_from_utf8(&utf8_data);
+            atb.mark_saved(&utf8_data);
             atb.text_buffer.send_textbuffer_loaded_signal(cx);
         }
         cx.file_write(path, utf8_data.as_bytes());
@@ -237,6 +397,102 @@ impl AppStorage {
         cx.file_write("makepad_state.ron", ron.as_bytes());
     }
     
+    pub fn load_session(&mut self, cx: &mut Cx, data: &[u8]) {
+        match DeBin::deserialize_bin(data) {
+            Ok(session) => {
+                self.session = session;
+                self.session_last_written = data.to_vec();
+                self.session_last_changes = None;
+                // buffers that were loaded before the session file came in
+                for atb in &mut self.text_buffers {
+                    if !atb.text_buffer.is_loaded || atb.is_dirty() {
+                        continue
+                    }
+                    let utf8_data = atb.text_buffer.get_as_string();
+                    atb.load_from_disk(cx, &utf8_data, &mut self.session);
+                }
+            },
+            Err(e) => {
+                println!("Cannot deserialize session {:?}", e);
+            }
+        }
+    }
+    
+    pub fn save_session(&mut self, cx: &mut Cx, windows: &mut Vec<AppWindow>, state: &mut AppState) {
+        // don't overwrite a session we haven't read back yet
+        if self.session_file_read.is_pending() || self.app_state_file_read.is_pending() {
+            return
+        }
+        let mut session = AppSession::default();
+        for (window_index, window) in windows.iter_mut().enumerate() {
+            if window_index >= state.windows.len() {
+                break
+            }
+            let mut dock_walker = window.dock.walker(&mut state.windows[window_index].dock_items);
+            while let Some((_ctrl_id, dock_item)) = dock_walker.walk_dock_item() {
+                if let DockItem::TabControl {tabs, ..} = dock_item {
+                    for tab in tabs {
+                        if let Panel::FileEditor {path, editor_id, ..} = &tab.item {
+                            let (cursors, folded) = if let Some(file_editor) = window.file_editors.editors.get(editor_id) {
+                                (file_editor.get_cursors().clone(), file_editor.is_folded())
+                            }
+                            else if let Some(ed) = self.session.get_editor(window_index, *editor_id, path) {
+                                (ed.cursors.clone(), ed.folded)
+                            }
+                            else {
+                                continue
+                            };
+                            session.editors.push(FileEditorSession {
+                                window_index: window_index,
+                                editor_id: *editor_id,
+                                path: path.clone(),
+                                cursors: cursors,
+                                folded: folded
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        self.session.editors = session.editors.clone();
+        // the buffers go in with their undo trees, only when a buffer, a cursor or a fold changed
+        let persist_undo_tree = self.settings.persist_undo_tree.unwrap_or(true);
+        let mut hasher = DefaultHasher::new();
+        persist_undo_tree.hash(&mut hasher);
+        self.session.text_buffers.len().hash(&mut hasher);
+        for atb in &self.text_buffers {
+            atb.full_path.hash(&mut hasher);
+            atb.text_buffer.is_loaded.hash(&mut hasher);
+            atb.text_buffer.mutation_id.hash(&mut hasher);
+            atb.saved_mutation_id.hash(&mut hasher);
+            atb.hot_exit.is_some().hash(&mut hasher);
+        }
+        session.editors.serialize_bin().hash(&mut hasher);
+        let changes = hasher.finish();
+        if self.session_last_changes == Some(changes) {
+            return
+        }
+        self.session_last_changes = Some(changes);
+        for atb in &self.text_buffers {
+            if let Some(tb_session) = atb.get_session(persist_undo_tree) {
+                session.text_buffers.push(tb_session);
+            }
+        }
+        // keep the entries for buffers we haven't opened yet this run
+        for tb_session in &self.session.text_buffers {
+            if session.text_buffers.iter().find( | tb | tb.path == tb_session.path).is_none() {
+                session.text_buffers.push(tb_session.clone());
+            }
+        }
+        let data = session.serialize_bin();
+        if data != self.session_last_written {
+            cx.file_write("makepad_session.bin", &data);
+            // scroll positions live in the state file
+            self.save_state(cx, state);
+            self.session_last_written = data;
+        }
+    }
+    
     pub fn remap_sync_path(&self, path: &str) -> String {
         let mut path = path.to_string();
         for (key, sync_to) in &self.settings.sync {
@@ -275,6 +531,13 @@ impl AppStorage {
                     full_path: path.to_string(),
                     text_buffer_id: tb_id,
                     live_macros: LiveMacros::new(cx),
+                    saved_mutation_id: 0,
+                    disk_hash: 0,
+                    base_line_hashes: None,
+                    line_changes_id: None,
+                    git_head_uid: None,
+                    hot_exit: None,
+                    collab: AppCollab::Off,
                     // write_msg: None,
                     text_buffer: TextBuffer {
                         signal: cx.new_signal(),
@@ -312,6 +575,13 @@ impl AppStorage {
                     full_path: path.to_string(),
                     text_buffer_id: tb_id,
                     live_macros: LiveMacros::new(cx),
+                    saved_mutation_id: 0,
+                    disk_hash: 0,
+                    base_line_hashes: None,
+                    line_changes_id: None,
+                    git_head_uid: None,
+                    hot_exit: None,
+                    collab: AppCollab::Off,
                     // write_msg: None,
                     text_buffer: TextBuffer {
                         signal: cx.new_signal(),
@@ -324,13 +594,33 @@ impl AppStorage {
         }
     }
     
+    // fetches the file from git HEAD for the line change markers
+    pub fn request_git_head(&mut self, tb_id: AppTextBufferId) {
+        let hub_ui = if let Some(hub_ui) = &mut self.hub_ui {hub_ui} else {return};
+        let atb = &mut self.text_buffers[tb_id.as_index()];
+        if let Some(builder_pos) = atb.full_path.find('/') {
+            let uid = hub_ui.route_send.alloc_uid();
+            let (builder, rest) = atb.full_path.split_at(builder_pos);
+            let (_, rest) = rest.split_at(1);
+            hub_ui.route_send.send(ToHubMsg {
+                to: HubMsgTo::Builder(builder.to_string()),
+                msg: HubMsg::GitHeadReadRequest {
+                    uid: uid,
+                    path: rest.to_string()
+                }
+            });
+            atb.git_head_uid = Some(uid);
+        }
+    }
+    
     pub fn text_buffer_file_write(&mut self, cx: &mut Cx, path: &str) {
         if cx.platform_type.is_desktop() {
             if path.find('/').is_some() {
                 if let Some(tb_id) = self.text_buffer_path_to_id.get(path) {
-                    let atb = &self.text_buffers[tb_id.0 as usize];
+                    let atb = &mut self.text_buffers[tb_id.0 as usize];
                     let hub_ui = self.hub_ui.as_mut().unwrap();
                     let utf8_data = atb.text_buffer.get_as_string();
+                    atb.mark_saved(&utf8_data);
                     fn send_file_write_request(hub_ui: &HubUI, uid: HubUid, path: &str, data: &Vec<u8>) {
                         if let Some(builder_pos) = path.find('/') {
                             let (builder, rest) = path.split_at(builder_pos);
@@ -360,12 +650,16 @@ impl AppStorage {
                             }
                         }
                     }
+                    // a commit may have moved HEAD since the file was loaded
+                    let tb_id = *tb_id;
+                    self.request_git_head(tb_id);
                 }
             }
             else { // its not a workspace, its a system (settings) file
                 if let Some(tb_id) = self.text_buffer_path_to_id.get(path) {
-                    let atb = &self.text_buffers[tb_id.0 as usize];
+                    let atb = &mut self.text_buffers[tb_id.0 as usize];
                     let utf8_data = atb.text_buffer.get_as_string();
+                    atb.mark_saved(&utf8_data);
                     cx.file_write(path, utf8_data.as_bytes());
                     // if its the settings, load it
                     if path == "makepad_settings.ron" {
@@ -424,23 +718,18 @@ impl AppStorage {
                 self.builders_request_uid = uid;
                 // add all workspace nodes
                 for window in windows {
-                    window.file_panel.file_tree.root_node = FileNode::Folder {
+                    window.file_panel.file_tree.set_root_node(cx, FileNode::Folder {
                         name: "".to_string(),
-                        draw: None,
-                        state: NodeState::Open,
                         folder: builders.iter().map( | v | FileNode::Folder {
                             name: v.clone(),
-                            draw: None,
-                            state: NodeState::Open,
                             folder: Vec::new()
                         }).chain(std::iter::once(
                             FileNode::File {
                                 name: "makepad_settings.ron".to_string(),
-                                draw: None
                             }
                         )).collect()
-                    };
-                    window.file_panel.file_tree.view.redraw_view_area(cx);
+                    });
+                    window.file_panel.file_tree.load_open_folders(cx, builders);
                 }
                 // lets resend the file load we haven't gotten
                 for atb in &mut self.text_buffers {
@@ -470,11 +759,16 @@ impl AppStorage {
                         for path in &paths {
                             self.text_buffer_from_path(cx, path);
                         }
+                        window.file_panel.file_tree.reload(cx);
                         window.file_panel.file_tree.load_open_folders(cx, &state.windows[window_index].open_folders);
                     }
                 }
             },
+            HubMsg::CollabJoin {..} | HubMsg::CollabState {..} | HubMsg::CollabChange {..} | HubMsg::CollabCursors {..} | HubMsg::DisconnectUI => {
+                self.handle_collab_msg(cx, htc);
+            },
             HubMsg::FileReadResponse {uid, data, ..} => {
+                let mut loaded = None;
                 for (path, tb_id) in &mut self.text_buffer_path_to_id {
                     let atb = &mut self.text_buffers[tb_id.0 as usize];
                     if let Some(cth_msg) = &atb.read_msg {
@@ -483,9 +777,9 @@ impl AppStorage {
                                 atb.read_msg = None;
                                 if let Some(data) = data {
                                     if let Ok(utf8_data) = std::str::from_utf8(data) {
-                                        atb.text_buffer.load_from_utf8(&utf8_data);
-                                        atb.text_buffer.send_textbuffer_loaded_signal(cx);
-                                        FileEditor::update_token_chunks(cx, &path, atb, &mut build_manager.search_index);
+                                        atb.load_from_disk(cx, &utf8_data, &mut self.session);
+                                        FileEditor::update_token_chunks(cx, &self.languages.for_path(&path), atb, Some(&mut build_manager.search_index));
+                                        loaded = Some(*tb_id);
                                     }
                                 }
                                 else {
@@ -497,6 +791,23 @@ impl AppStorage {
                         }
                     }
                 }
+                if let Some(tb_id) = loaded {
+                    self.collab_join(tb_id);
+                    self.request_git_head(tb_id);
+                }
+            },
+            HubMsg::GitHeadReadResponse {uid, data, ..} => {
+                for atb in &mut self.text_buffers {
+                    if atb.git_head_uid == Some(*uid) {
+                        atb.git_head_uid = None;
+                        atb.base_line_hashes = data.as_ref()
+                            .and_then( | data | std::str::from_utf8(data).ok())
+                            .map( | utf8_data | TextBuffer::utf8_line_hashes(utf8_data));
+                        atb.line_changes_id = None;
+                        cx.send_signal(atb.text_buffer.signal, TextBuffer::status_message_update());
+                        break
+                    }
+                }
             },
             _ => {}
         }
@@ -510,7 +821,6 @@ pub fn hub_to_tree(node: &BuilderFileTreeNode, base: &str, paths: &mut Vec<Strin
             paths.push(path);
             FileNode::File {
                 name: name.clone(),
-                draw: None
             }
         },
         BuilderFileTreeNode::Folder {name, folder, ..} => {
@@ -518,8 +828,6 @@ pub fn hub_to_tree(node: &BuilderFileTreeNode, base: &str, paths: &mut Vec<Strin
             FileNode::Folder {
                 name: name.clone(),
                 folder: folder.iter().map( | v | hub_to_tree(v, if base == "" {name}else {&path}, paths)).collect(),
-                draw: None,
-                state: NodeState::Closed
             }
         }
     }
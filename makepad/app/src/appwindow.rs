@@ -5,6 +5,7 @@ use makepad_microserde::*;
 use std::collections::HashMap;
 
 use crate::appstorage::*;
+use crate::appsession::*;
 use crate::fileeditor::*;
 use crate::filetree::*;
 use crate::filepanel::*;
@@ -14,10 +15,8 @@ use crate::keyboard::*;
 use crate::buildmanager::*;
 use crate::homepage::*;
 use crate::searchresults::*;
-use crate::rusteditor::*;
-use crate::jseditor::*;
-use crate::plaineditor::*;
 use crate::shaderview::*;
+use crate::makepadapp::*;
 
 #[derive(Debug, Clone, SerRon, DeRon)] 
 pub enum Panel {
@@ -31,6 +30,21 @@ pub enum Panel {
     FileEditor {path: String, scroll_pos: Vec2, editor_id: u64}
 }
 
+// what the open context menu acts on, when it isn't an editor
+#[derive(Clone)]
+pub enum ContextTarget {
+    Path(String),
+    Tab {tab_control_id: usize, tab_id: usize}
+}
+
+pub enum AppWindowEvent {
+    None,
+    AppCommand(String),
+    MenuCommand(CommandId),
+    // a tab dragged out of the dock goes into a new window at position
+    NewWindow {tab: DockTab<Panel>, position: Vec2}
+}
+
 #[derive(Clone)]
 pub struct AppWindow {
     pub desktop_window: DesktopWindow,
@@ -43,6 +57,12 @@ pub struct AppWindow {
     
     pub keyboard: Keyboard,
     pub file_editors: FileEditors,
+    pub hot_exit_prompts: Elements<u64, HotExitPrompt, HotExitPrompt>,
+    pub command_palette: CommandPalette,
+    pub context_menu: ContextMenu,
+    // the editor that had key focus when the palette or a context menu opened, editor commands go there
+    pub command_editor: Option<u64>,
+    pub context_target: Option<ContextTarget>,
     pub dock: Dock<Panel>,
 }
 
@@ -56,7 +76,9 @@ pub struct AppWindowState {
 
 #[derive(Default, Clone, SerRon, DeRon)]
 pub struct AppState {
-    pub windows: Vec<AppWindowState>
+    pub windows: Vec<AppWindowState>,
+    // an Option so state files from before it still load
+    pub dock_presets: Option<DockPresets<Panel>>
 }
 
 impl AppWindow {
@@ -67,12 +89,12 @@ impl AppWindow {
                 window: Window::new(cx),
                 ..DesktopWindow::new(cx)
             },
-            file_editors: FileEditors {
-                rust_editor: RustEditor::new(cx),
-                js_editor: JSEditor::new(cx),
-                plain_editor: PlainEditor::new(cx),
-                editors: HashMap::new(),
-            },
+            file_editors: FileEditors::new(cx),
+            hot_exit_prompts: Elements::new(HotExitPrompt::new(cx)),
+            command_palette: CommandPalette::new(cx),
+            context_menu: ContextMenu::new(cx),
+            command_editor: None,
+            context_target: None,
             shader_view: ShaderView::new(cx),
             home_page: HomePage::new(cx),
             keyboard: Keyboard::new(cx),
@@ -84,14 +106,14 @@ impl AppWindow {
         }
     }
     
-    pub fn handle_app_window(&mut self, cx: &mut Cx, event: &mut Event, window_index: usize, state: &mut AppState, storage: &mut AppStorage, build_manager: &mut BuildManager) {
+    pub fn handle_app_window(&mut self, cx: &mut Cx, event: &mut Event, menu: &Menu, window_index: usize, state: &mut AppState, storage: &mut AppStorage, build_manager: &mut BuildManager) -> AppWindowEvent {
         
         match self.desktop_window.handle_desktop_window(cx, event) {
             DesktopWindowEvent::EventForOtherWindow => {
-                return
+                return AppWindowEvent::None
             }
             DesktopWindowEvent::WindowClosed => {
-                return
+                return AppWindowEvent::None
             }
             DesktopWindowEvent::WindowGeomChange(wc) => {
                 if !storage.app_state_file_read.is_pending() {
@@ -104,24 +126,51 @@ impl AppWindow {
             _ => ()
         }
         
+        let mut app_window_event = AppWindowEvent::None;
+        // the search a file editor asked for: the text, the buffer to start at, focus the search panel, escape
+        let mut do_search = None;
+        
+        // the context menu is on top of everything, it gets the events first
+        if let ContextMenuEvent::Command(command_id) = self.context_menu.handle_context_menu(cx, event) {
+            // other commands reach the app as an Event::Command
+            if let Some(command) = MakepadApp::menu_command_to_named(command_id) {
+                if command.starts_with("editor.") {
+                    self.execute_command(cx, command, window_index, state, storage, build_manager, &mut do_search);
+                }
+            }
+            else {
+                self.execute_context_command(cx, command_id, window_index, state, storage);
+            }
+        }
+        
+        match self.command_palette.handle_command_palette(cx, event) {
+            CommandPaletteEvent::Execute(CommandPaletteCommand::Named(command)) => {
+                app_window_event = self.execute_command(cx, &command, window_index, state, storage, build_manager, &mut do_search);
+            }
+            CommandPaletteEvent::Execute(CommandPaletteCommand::Menu(command_id)) => {
+                if let Some(command) = MakepadApp::menu_command_to_named(command_id) {
+                    app_window_event = self.execute_command(cx, command, window_index, state, storage, build_manager, &mut do_search);
+                }
+                else {
+                    app_window_event = AppWindowEvent::MenuCommand(command_id);
+                }
+            }
+            CommandPaletteEvent::Input(command, value) => {
+                self.execute_prompt(cx, &command, &value, window_index, state, storage);
+            }
+            _ => ()
+        }
+        
         match event {
-            Event::KeyDown(ke) => match ke.key_code {
-                KeyCode::Backtick => if ke.modifiers.logo || ke.modifiers.control {
-                    if build_manager.active_builds.len() == 0 {
-                        build_manager.restart_build(cx, storage);
-                    }
-                    let mut clear = true;
-                    for ab in &build_manager.active_builds {
-                        if !ab.build_uid.is_none() {
-                            clear = false;
-                        }
-                    }
-                    if clear {
-                        build_manager.tail_log_items = true;
-                        build_manager.log_items.truncate(0);
-                    }
-                    build_manager.artifact_run(storage);
-                    self.show_log_tab(cx, window_index, state);
+            Event::KeyDown(ke) => match cx.keymap.match_key_down(ke, "app") {
+                KeyMapMatch::Command(command) => match command.as_ref() {
+                    "app.command_palette" => {
+                        self.open_command_palette(cx, menu);
+                    },
+                    "app.build_and_run" => {
+                        self.build_and_run(cx, window_index, state, storage, build_manager);
+                    },
+                    _ => ()
                 }
                 _ => ()
             }
@@ -137,7 +186,6 @@ impl AppWindow {
         let mut file_tree_event = FileTreeEvent::None;
         //let mut text_editor_event = TextEditorEvent::None;
         let mut set_last_cursor = None;
-        let mut do_search = None;
         let mut show_item_display_tab = false;
         let mut do_display_rust_file = None;
         
@@ -193,32 +241,30 @@ impl AppWindow {
                     file_tree_event = self.file_panel.handle_file_panel(cx, event);
                 }
                 Panel::FileEditor {path, scroll_pos, editor_id} => {
-                    if let Some(file_editor) = &mut self.file_editors.editors.get_mut(editor_id) {
-                        
-                        let atb = storage.text_buffer_from_path(cx, path);
-                        
-                        match file_editor.handle_file_editor(cx, event, atb, Some(&mut build_manager.search_index)) {
-                            TextEditorEvent::Search(search) => {
-                                do_search = Some((Some(search), atb.text_buffer_id, true, false));
-                            }
-                            TextEditorEvent::Decl(search) => {
-                                do_search = Some((Some(search), atb.text_buffer_id, false, false));
-                            }
-                            TextEditorEvent::Escape => {
-                                do_search = Some((Some("".to_string()), atb.text_buffer_id, false, true));
-                            }
-                            TextEditorEvent::Change => {
-                                do_search = Some((None, AppTextBufferId(0), false, false));
+                    let atb = storage.text_buffer_from_path(cx, path);
+                    if atb.hot_exit.is_some() {
+                        if let Some(prompt) = self.hot_exit_prompts.get(*editor_id) {
+                            match prompt.handle_hot_exit_prompt(cx, event) {
+                                HotExitPromptEvent::Restore => {
+                                    atb.resolve_hot_exit(cx, true);
+                                    FileEditor::update_token_chunks(cx, &self.file_editors.languages.for_path(path), atb, Some(&mut build_manager.search_index));
+                                    cx.redraw_child_area(Area::All);
+                                },
+                                HotExitPromptEvent::Discard => {
+                                    atb.resolve_hot_exit(cx, false);
+                                    cx.redraw_child_area(Area::All);
+                                },
+                                HotExitPromptEvent::None => ()
                             }
-                            TextEditorEvent::LagChange => {
-                                storage.text_buffer_file_write(cx, path);
-                                if storage.settings.build_on_save {
-                                    build_manager.restart_build(cx, storage);
-                                }
-                            },
-                            _ => ()
                         }
+                        continue
+                    }
+                    if let Some(file_editor) = &mut self.file_editors.editors.get_mut(editor_id) {
+                        
+                        let ce = file_editor.handle_file_editor(cx, event, atb, Some(&mut build_manager.search_index));
+                        let text_buffer_id = atb.text_buffer_id;
                         *scroll_pos = file_editor.get_scroll_pos(cx);
+                        Self::handle_file_editor_event(cx, ce, *editor_id, path, text_buffer_id, &mut self.context_menu, &mut self.command_editor, storage, build_manager, &mut do_search);
                     }
                 }
             }
@@ -286,6 +332,11 @@ impl AppWindow {
                 state.windows[window_index].open_folders = self.file_panel.file_tree.save_open_folders();
                 storage.save_state(cx, state);
             }
+            FileTreeEvent::ContextMenu {path, is_file, abs} => {
+                self.command_editor = None;
+                self.context_target = Some(ContextTarget::Path(path));
+                self.context_menu.open(cx, MakepadApp::file_tree_context_menu(is_file), abs);
+            }
             _ => {}
         }
         
@@ -298,6 +349,22 @@ impl AppWindow {
                 self.ensure_unique_tab_title_for_file_editors(cx, window_index, state);
                 storage.save_state(cx, state);
             }
+            DockEvent::DockTabDraggedOut {tab, abs} => {
+                self.ensure_unique_tab_title_for_file_editors(cx, window_index, state);
+                storage.save_state(cx, state);
+                let window_position = state.windows[window_index].window_position;
+                app_window_event = AppWindowEvent::NewWindow {
+                    tab: tab,
+                    position: Vec2 {x: window_position.x + abs.x, y: window_position.y + abs.y}
+                };
+            }
+            DockEvent::DockTabContextMenu {tab_control_id, tab_id, abs} => {
+                let closeable = Dock::get_tab(dock_items, tab_control_id, tab_id).map_or(false, | tab | tab.closeable);
+                MakepadApp::command_close_tab().set_enabled(cx, closeable);
+                self.command_editor = None;
+                self.context_target = Some(ContextTarget::Tab {tab_control_id, tab_id});
+                self.context_menu.open(cx, MakepadApp::tab_context_menu(), abs);
+            }
             DockEvent::DockTabCloned {tab_control_id, tab_id} => {
                 // lets change up our editor_id
                 let max_id = self.file_editors.highest_file_editor_id();
@@ -324,6 +391,214 @@ impl AppWindow {
             },
             _ => ()
         }
+        app_window_event
+    }
+    
+    pub fn build_and_run(&mut self, cx: &mut Cx, window_index: usize, state: &mut AppState, storage: &mut AppStorage, build_manager: &mut BuildManager) {
+        if build_manager.active_builds.len() == 0 {
+            build_manager.restart_build(cx, storage);
+        }
+        let mut clear = true;
+        for ab in &build_manager.active_builds {
+            if !ab.build_uid.is_none() {
+                clear = false;
+            }
+        }
+        if clear {
+            build_manager.tail_log_items = true;
+            build_manager.log_items.truncate(0);
+        }
+        build_manager.artifact_run(storage);
+        self.show_log_tab(cx, window_index, state);
+    }
+    
+    pub fn open_command_palette(&mut self, cx: &mut Cx, menu: &Menu) {
+        self.command_editor = None;
+        for (editor_id, file_editor) in self.file_editors.editors.iter_mut() {
+            if file_editor.has_key_focus(cx) {
+                self.command_editor = Some(*editor_id);
+            }
+        }
+        let mut items = Vec::new();
+        for command in &cx.keymap.commands {
+            // the log, search and palette contexts only make sense with their own panel focused
+            let runnable = command.name.starts_with("app.") && command.name != "app.command_palette"
+                || command.name.starts_with("editor.") && self.command_editor.is_some();
+            if !runnable {
+                continue
+            }
+            let shortcut = if let Some(binding) = cx.keymap.bindings_for_command(&command.name).first() {
+                binding.keys_to_string()
+            } else {
+                String::new()
+            };
+            items.push(CommandPaletteItem {
+                label: command.label.clone(),
+                shortcut: shortcut,
+                command: CommandPaletteCommand::Named(command.name.clone())
+            });
+        }
+        menu_palette_items(cx, menu, "", self.command_editor.is_some(), &mut items);
+        self.command_palette.open(cx, items);
+    }
+    
+    // what a file editor returns, from its events or from a command run on it
+    fn handle_file_editor_event(
+        cx: &mut Cx,
+        ce: TextEditorEvent,
+        editor_id: u64,
+        path: &str,
+        text_buffer_id: AppTextBufferId,
+        context_menu: &mut ContextMenu,
+        command_editor: &mut Option<u64>,
+        storage: &mut AppStorage,
+        build_manager: &mut BuildManager,
+        do_search: &mut Option<(Option<String>, AppTextBufferId, bool, bool)>
+    ) {
+        match ce {
+            TextEditorEvent::Search(search) => {
+                *do_search = Some((Some(search), text_buffer_id, true, false));
+            }
+            TextEditorEvent::Decl(search) => {
+                *do_search = Some((Some(search), text_buffer_id, false, false));
+            }
+            TextEditorEvent::Escape => {
+                *do_search = Some((Some("".to_string()), text_buffer_id, false, true));
+            }
+            TextEditorEvent::Change => {
+                *do_search = Some((None, AppTextBufferId(0), false, false));
+            }
+            TextEditorEvent::ContextMenu(pos) => {
+                *command_editor = Some(editor_id);
+                context_menu.open(cx, MakepadApp::editor_context_menu(), pos);
+            },
+            TextEditorEvent::LagChange => {
+                storage.text_buffer_file_write(cx, path);
+                if storage.settings.build_on_save {
+                    build_manager.restart_build(cx, storage);
+                }
+            },
+            _ => ()
+        }
+    }
+    
+    pub fn execute_command(
+        &mut self,
+        cx: &mut Cx,
+        command: &str,
+        window_index: usize,
+        state: &mut AppState,
+        storage: &mut AppStorage,
+        build_manager: &mut BuildManager,
+        do_search: &mut Option<(Option<String>, AppTextBufferId, bool, bool)>
+    ) -> AppWindowEvent {
+        if command.starts_with("editor.") {
+            if let Some(editor_id) = self.command_editor {
+                if let Some(path) = self.find_file_editor_path(window_index, state, editor_id) {
+                    if let Some(file_editor) = self.file_editors.editors.get_mut(&editor_id) {
+                        let atb = storage.text_buffer_from_path(cx, &path);
+                        let ce = file_editor.execute_command(cx, command, atb, &mut build_manager.search_index);
+                        let text_buffer_id = atb.text_buffer_id;
+                        file_editor.set_key_focus(cx);
+                        Self::handle_file_editor_event(cx, ce, editor_id, &path, text_buffer_id, &mut self.context_menu, &mut self.command_editor, storage, build_manager, do_search);
+                    }
+                }
+            }
+            return AppWindowEvent::None
+        }
+        match command {
+            "app.build_and_run" => self.build_and_run(cx, window_index, state, storage, build_manager),
+            "app.show_files" => self.show_files_tab(cx, window_index, state),
+            "app.show_search" => self.show_search_tab(cx, window_index, state),
+            "app.show_log" => self.show_log_tab(cx, window_index, state),
+            "app.show_item_display" => self.show_item_display_tab(cx, window_index, state),
+            "app.save_layout" => {
+                let names = state.dock_presets.as_ref().map_or(Vec::new(), | dock_presets | dock_presets.preset_names());
+                self.command_palette.open_prompt(cx, command, "name the layout, or pick one to replace", names, true);
+            },
+            "app.restore_layout" => {
+                let names = state.dock_presets.as_ref().map_or(Vec::new(), | dock_presets | dock_presets.preset_names());
+                self.command_palette.open_prompt(cx, command, if names.len() > 0 {"pick a layout"} else {"no saved layouts"}, names, false);
+            },
+            _ => return AppWindowEvent::AppCommand(command.to_string())
+        }
+        AppWindowEvent::None
+    }
+    
+    // a context menu command for the file tree or a dock tab
+    fn execute_context_command(&mut self, cx: &mut Cx, command_id: CommandId, window_index: usize, state: &mut AppState, storage: &mut AppStorage) {
+        let target = if let Some(target) = self.context_target.take() {target} else {return};
+        let changed = match target {
+            ContextTarget::Path(path) => {
+                if command_id == MakepadApp::command_open_file() {
+                    self.focus_or_new_editor(cx, window_index, state, &path, None)
+                }
+                else if command_id == MakepadApp::command_open_preview() {
+                    self.open_preview_editor_tab(cx, window_index, state, &path, None)
+                }
+                else if command_id == MakepadApp::command_collapse_folders() {
+                    self.file_panel.file_tree.load_open_folders(cx, &Vec::new());
+                    state.windows[window_index].open_folders = self.file_panel.file_tree.save_open_folders();
+                    true
+                }
+                else {false}
+            },
+            ContextTarget::Tab {tab_control_id, tab_id} => {
+                let dock_items = &mut state.windows[window_index].dock_items;
+                if command_id == MakepadApp::command_close_tab() {
+                    self.dock.close_tab(cx, dock_items, tab_control_id, tab_id)
+                }
+                else if command_id == MakepadApp::command_close_other_tabs() {
+                    // from the back, so the ids of the tabs left to close stay put
+                    let mut last = tab_id;
+                    while Dock::get_tab(dock_items, tab_control_id, last + 1).is_some() {
+                        last += 1;
+                    }
+                    let mut changed = false;
+                    for id in (0..=last).rev().filter( | id | *id != tab_id) {
+                        changed |= self.dock.close_tab(cx, dock_items, tab_control_id, id);
+                    }
+                    changed
+                }
+                else {false}
+            }
+        };
+        if changed {
+            self.ensure_unique_tab_title_for_file_editors(cx, window_index, state);
+            storage.save_state(cx, state);
+        }
+    }
+    
+    // the value a command asked for in the palette
+    fn execute_prompt(&mut self, cx: &mut Cx, command: &str, value: &str, window_index: usize, state: &mut AppState, storage: &mut AppStorage) {
+        match command {
+            "app.save_layout" => {
+                let dock_items = &state.windows[window_index].dock_items;
+                state.dock_presets.get_or_insert_with(DockPresets::new).save_preset(value, dock_items);
+                storage.save_state(cx, state);
+            },
+            "app.restore_layout" => {
+                let restored = if let Some(dock_presets) = &state.dock_presets {
+                    dock_presets.restore_preset(value, &mut state.windows[window_index].dock_items)
+                } else {false};
+                if restored {
+                    self.ensure_unique_tab_title_for_file_editors(cx, window_index, state);
+                    storage.save_state(cx, state);
+                }
+            },
+            _ => ()
+        }
+    }
+    
+    pub fn find_file_editor_path(&mut self, window_index: usize, state: &mut AppState, find_editor_id: u64) -> Option<String> {
+        let tab = state.windows[window_index].dock_items.find_tab( | panel | match panel {
+            Panel::FileEditor {editor_id, ..} => *editor_id == find_editor_id,
+            _ => false
+        });
+        if let Some(DockTab {item: Panel::FileEditor {path, ..}, ..}) = tab {
+            return Some(path.clone())
+        }
+        None
     }
     
     pub fn draw_app_window(
@@ -336,6 +611,7 @@ impl AppWindow {
         build_manager: &mut BuildManager
     ) {
         if self.desktop_window.begin_desktop_window(cx, Some(menu)).is_err() {return}
+        let window_rect = cx.get_turtle_rect();
         
         self.dock.draw_dock(cx);
         
@@ -388,14 +664,25 @@ impl AppWindow {
                 }
                 Panel::FileEditor {path, scroll_pos, editor_id} => {
                     let text_buffer = storage.text_buffer_from_path(cx, path);
+                    if text_buffer.hot_exit.is_some() {
+                        self.hot_exit_prompts.get_draw(cx, *editor_id, | _cx, templ | templ.clone()).draw_hot_exit_prompt(cx, path);
+                        continue
+                    }
                     let (file_editor, is_new) = self.file_editors.get_file_editor_for_path(path, *editor_id);
                     if is_new {
                         file_editor.set_scroll_pos_on_load(*scroll_pos);
+                        if let Some(ed) = storage.session.get_editor(window_index, *editor_id, path) {
+                            file_editor.set_cursors_on_load(ed.cursors.clone());
+                            file_editor.set_folded_on_load(ed.folded);
+                        }
                     }
+                    let text_buffer = storage.text_buffer_from_path(cx, path);
                     file_editor.draw_file_editor(cx, text_buffer, &mut build_manager.search_index);
                 }
             }
         }
+        self.command_palette.draw_command_palette(cx, window_rect);
+        self.context_menu.draw_context_menu(cx);
         self.desktop_window.end_desktop_window(cx);
     }
     
@@ -496,68 +783,27 @@ impl AppWindow {
         }
     }
     
-    pub fn show_log_tab(&mut self, cx: &mut Cx, window_index: usize, state: &mut AppState) {
-        let mut dock_walker = self.dock.walker(&mut state.windows[window_index].dock_items);
-        while let Some((_ctrl_id, dock_item)) = dock_walker.walk_dock_item() {
-            if let DockItem::TabControl {current, tabs, ..} = dock_item {
-                for (id, tab) in tabs.iter().enumerate() {
-                    if let Panel::LogList = &tab.item {
-                        if *current != id {
-                            *current = id;
-                            cx.redraw_child_area(Area::All);
-                        }
-                    }
-                }
-            }
+    pub fn show_panel_tab<F>(&mut self, cx: &mut Cx, window_index: usize, state: &mut AppState, pred: F)
+    where F: FnMut(&Panel) -> bool {
+        if state.windows[window_index].dock_items.focus_tab(pred) {
+            cx.redraw_child_area(Area::All);
         }
     }
     
+    pub fn show_log_tab(&mut self, cx: &mut Cx, window_index: usize, state: &mut AppState) {
+        self.show_panel_tab(cx, window_index, state, | panel | if let Panel::LogList = panel {true} else {false});
+    }
+    
     pub fn show_files_tab(&mut self, cx: &mut Cx, window_index: usize, state: &mut AppState) {
-        let mut dock_walker = self.dock.walker(&mut state.windows[window_index].dock_items);
-        while let Some((_ctrl_id, dock_item)) = dock_walker.walk_dock_item() {
-            if let DockItem::TabControl {current, tabs, ..} = dock_item {
-                for (id, tab) in tabs.iter().enumerate() {
-                    if let Panel::FileTree = &tab.item {
-                        if *current != id {
-                            *current = id;
-                            cx.redraw_child_area(Area::All);
-                        }
-                    }
-                }
-            }
-        }
+        self.show_panel_tab(cx, window_index, state, | panel | if let Panel::FileTree = panel {true} else {false});
     }
     
     pub fn show_search_tab(&mut self, cx: &mut Cx, window_index: usize, state: &mut AppState) {
-        let mut dock_walker = self.dock.walker(&mut state.windows[window_index].dock_items);
-        while let Some((_ctrl_id, dock_item)) = dock_walker.walk_dock_item() {
-            if let DockItem::TabControl {current, tabs, ..} = dock_item {
-                for (id, tab) in tabs.iter().enumerate() {
-                    if let Panel::SearchResults = &tab.item {
-                        if *current != id {
-                            *current = id;
-                            cx.redraw_child_area(Area::All);
-                        }
-                    }
-                }
-            }
-        }
+        self.show_panel_tab(cx, window_index, state, | panel | if let Panel::SearchResults = panel {true} else {false});
     }
     
     pub fn show_item_display_tab(&mut self, cx: &mut Cx, window_index: usize, state: &mut AppState) {
-        let mut dock_walker = self.dock.walker(&mut state.windows[window_index].dock_items);
-        while let Some((_ctrl_id, dock_item)) = dock_walker.walk_dock_item() {
-            if let DockItem::TabControl {current, tabs, ..} = dock_item {
-                for (id, tab) in tabs.iter().enumerate() {
-                    if let Panel::ItemDisplay = &tab.item {
-                        if *current != id {
-                            *current = id;
-                            cx.redraw_child_area(Area::All);
-                        }
-                    }
-                }
-            }
-        }
+        self.show_panel_tab(cx, window_index, state, | panel | if let Panel::ItemDisplay = panel {true} else {false});
     }
     
     pub fn focus_or_new_editor(&mut self, cx: &mut Cx, window_index: usize, state: &mut AppState, file_path: &str, set_last_cursor: Option<(usize, usize)>) -> bool {
@@ -681,3 +927,49 @@ impl AppWindow {
         return false
     }
 }
+
+// menu items show up as "File: New File" with the shortcut from the command settings. Only the ones
+// the palette can run, which are those with a named command that is not listed already
+fn menu_palette_items(cx: &Cx, menu: &Menu, prefix: &str, has_editor: bool, items: &mut Vec<CommandPaletteItem>) {
+    match menu {
+        Menu::Main {items: sub_items} => for sub in sub_items {
+            menu_palette_items(cx, sub, prefix, has_editor, items);
+        },
+        Menu::Sub {name, items: sub_items} => {
+            let prefix = format!("{}: ", name);
+            for sub in sub_items {
+                menu_palette_items(cx, sub, &prefix, has_editor, items);
+            }
+        },
+        Menu::Item {name, command} => {
+            let named = match MakepadApp::menu_command_to_named(*command) {
+                Some(named) => named,
+                None => return
+            };
+            if named.starts_with("editor.") && !has_editor {
+                return
+            }
+            if items.iter().any( | item | match &item.command {
+                CommandPaletteCommand::Menu(other) => *other == *command,
+                CommandPaletteCommand::Named(other) => other == named
+            }) {
+                return
+            }
+            let shortcut = match cx.command_settings.get(command) {
+                Some(setting) if setting.key_code != KeyCode::Unknown => KeyStroke {
+                    key_code: setting.key_code,
+                    shift: setting.shift,
+                    any_mod: true,
+                    ..KeyStroke::default()
+                }.to_string(),
+                _ => String::new()
+            };
+            items.push(CommandPaletteItem {
+                label: format!("{}{}", prefix, name),
+                shortcut: shortcut,
+                command: CommandPaletteCommand::Menu(*command)
+            });
+        },
+        Menu::Line => ()
+    }
+}
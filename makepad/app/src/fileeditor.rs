@@ -4,109 +4,213 @@ use makepad_widget::*;
 
 use crate::jseditor::*;
 use crate::rusteditor::*;
-use crate::plaineditor::*;
+use crate::tomleditor::*;
+use crate::jsoneditor::*;
+use crate::roneditor::*;
+use crate::markdowneditor::*;
+use crate::shadereditor::*;
+use crate::pythoneditor::*;
 use crate::searchindex::*;
 use crate::appstorage::*;
+use crate::livemacro::*;
 
 use std::collections::HashMap;
+use std::rc::Rc;
 
 #[derive(Clone)]
 pub struct FileEditors {
-    pub rust_editor: RustEditor,
-    pub js_editor: JSEditor,
-    pub plain_editor: PlainEditor,
+    pub languages: LanguageRegistry,
+    pub text_editor: TextEditor,
+    pub live_macros: LiveMacrosPane,
+    pub wrap_column: Option<usize>,
     pub editors: HashMap<u64, FileEditor>
-    //text_editor: TextEditor
 }
 
 #[derive(Clone)]
-pub enum FileEditor {
-    Rust(RustEditor),
-    JS(JSEditor),
-    Plain(PlainEditor)
-    //Text(TextEditor)
+pub struct FileEditor {
+    pub language: Rc<dyn Language>,
+    pub text_editor: TextEditor,
+    // only languages with live macros get the macro panel next to the editor
+    pub live_macros: Option<LiveMacrosPane>,
+}
+
+#[derive(Clone)]
+pub struct LiveMacrosPane {
+    pub view: View,
+    pub live_macros_view: LiveMacrosView,
+    pub splitter: Splitter,
+}
+
+impl LiveMacrosPane {
+    pub fn new(cx: &mut Cx) -> Self {
+        Self {
+            view: View::new(cx),
+            live_macros_view: LiveMacrosView::new(cx),
+            splitter: Splitter {
+                pos: 80.0,
+                _hit_state_margin: Some(Margin {
+                    l: 3.,
+                    t: 0.,
+                    r: 7.,
+                    b: 0.,
+                }),
+                ..Splitter::new(cx)
+            },
+        }
+    }
 }
 
 impl FileEditor {
-    pub fn handle_file_editor(&mut self, cx: &mut Cx, event: &mut Event, atb: &mut AppTextBuffer, search_index: Option<&mut SearchIndex>) -> TextEditorEvent {
-        match self {
-            FileEditor::Rust(re) => re.handle_rust_editor(cx, event, atb, search_index),
-            FileEditor::JS(re) => re.handle_js_editor(cx, event, atb),
-            FileEditor::Plain(re) => re.handle_plain_editor(cx, event, atb),
+    pub fn handle_file_editor(&mut self, cx: &mut Cx, event: &mut Event, atb: &mut AppTextBuffer, mut search_index: Option<&mut SearchIndex>) -> TextEditorEvent {
+        if let Some(pane) = &mut self.live_macros {
+            pane.live_macros_view.handle_live_macros(cx, event, atb, &mut self.text_editor);
+            
+            match pane.splitter.handle_splitter(cx, event) {
+                SplitterEvent::Moving {..} => {
+                    pane.view.redraw_view_parent_area(cx);
+                },
+                _ => ()
+            }
         }
+        
+        let ce = self.text_editor.handle_text_editor(cx, event, &mut atb.text_buffer);
+        if let Some(prefix) = self.text_editor.take_completion_query() {
+            if let Some(search_index) = &mut search_index {
+                let items = search_index.completions(&prefix);
+                self.text_editor.add_completion_items(cx, items);
+            }
+        }
+        if let TextEditorEvent::Change = ce {
+            Self::update_token_chunks(cx, &self.language, atb, search_index);
+        }
+        ce
     }
     
-    pub fn set_last_cursor(&mut self, cx: &mut Cx, cursor:(usize, usize), at_top:bool) {
-        match self {
-            FileEditor::Rust(re) => re.text_editor.set_last_cursor(cx, cursor, at_top),
-            FileEditor::JS(re) => re.text_editor.set_last_cursor(cx, cursor, at_top),
-            FileEditor::Plain(re) => re.text_editor.set_last_cursor(cx, cursor, at_top),
+    pub fn execute_command(&mut self, cx: &mut Cx, command: &str, atb: &mut AppTextBuffer, search_index: &mut SearchIndex) -> TextEditorEvent {
+        let ce = self.text_editor.execute_command(cx, command, &mut atb.text_buffer);
+        if let Some(prefix) = self.text_editor.take_completion_query() {
+            let items = search_index.completions(&prefix);
+            self.text_editor.add_completion_items(cx, items);
+        }
+        if let TextEditorEvent::Change = ce {
+            Self::update_token_chunks(cx, &self.language, atb, Some(search_index));
         }
+        ce
+    }
+    
+    pub fn set_last_cursor(&mut self, cx: &mut Cx, cursor:(usize, usize), at_top:bool) {
+        self.text_editor.set_last_cursor(cx, cursor, at_top)
     }
     
     pub fn set_key_focus(&mut self, cx: &mut Cx) {
-        match self {
-            FileEditor::Rust(re) => re.text_editor.set_key_focus(cx),
-            FileEditor::JS(re) => re.text_editor.set_key_focus(cx),
-            FileEditor::Plain(re) => re.text_editor.set_key_focus(cx),
-        }
+        self.text_editor.set_key_focus(cx)
     }
     
     pub fn has_key_focus(&mut self, cx: &mut Cx)->bool {
-        match self {
-            FileEditor::Rust(re) => re.text_editor.has_key_focus(cx),
-            FileEditor::JS(re) => re.text_editor.has_key_focus(cx),
-            FileEditor::Plain(re) => re.text_editor.has_key_focus(cx),
-        }
+        self.text_editor.has_key_focus(cx)
     }
     
     pub fn get_scroll_pos(&mut self, cx: &mut Cx) -> Vec2 {
-        match self {
-            FileEditor::Rust(re) => re.text_editor.view.get_scroll_pos(cx),
-            FileEditor::JS(re) => re.text_editor.view.get_scroll_pos(cx),
-            FileEditor::Plain(re) => re.text_editor.view.get_scroll_pos(cx),
-        }
+        self.text_editor.view.get_scroll_pos(cx)
     }
     
     pub fn set_scroll_pos_on_load(&mut self, pos: Vec2) {
-        match self {
-            FileEditor::Rust(re) => re.text_editor._scroll_pos_on_load = Some(pos),
-            FileEditor::JS(re) => re.text_editor._scroll_pos_on_load = Some(pos),
-            FileEditor::Plain(re) => re.text_editor._scroll_pos_on_load = Some(pos),
-        }
+        self.text_editor._scroll_pos_on_load = Some(pos)
     }
     
-    pub fn draw_file_editor(&mut self, cx: &mut Cx, atb: &mut AppTextBuffer, search_index: &mut SearchIndex) {
-        match self {
-            FileEditor::Rust(re) => re.draw_rust_editor(cx, atb, Some(search_index)),
-            FileEditor::JS(re) => re.draw_js_editor(cx, atb, Some(search_index)),
-            FileEditor::Plain(re) => re.draw_plain_editor(cx, atb, Some(search_index)),
-        }
+    pub fn get_cursors(&self) -> &TextCursorSet {
+        &self.text_editor.cursors
     }
     
-    pub fn update_token_chunks(cx:&mut Cx, path: &str,  atb: &mut AppTextBuffer, search_index: &mut SearchIndex) {
-        // check which file extension we have to spawn a new editor
-        if path.ends_with(".rs") || path.ends_with(".toml") || path.ends_with(".ron") {
-            RustEditor::update_token_chunks(cx, atb, Some(search_index));
+    pub fn set_cursors_on_load(&mut self, cursors: TextCursorSet) {
+        self.text_editor._set_cursors_on_load = Some(cursors)
+    }
+    
+    pub fn is_folded(&self) -> bool {
+        self.text_editor.is_folded()
+    }
+    
+    pub fn set_folded_on_load(&mut self, folded: bool) {
+        self.text_editor.set_folded(folded)
+    }
+    
+    pub fn draw_file_editor(&mut self, cx: &mut Cx, atb: &mut AppTextBuffer, search_index: &mut SearchIndex) {
+        if let Some(pane) = &mut self.live_macros {
+            if pane.view.begin_view(cx, Layout::default()).is_err() {
+                return
+            };
+            pane.splitter.begin_splitter(cx);
+            pane.live_macros_view.draw_live_macros(cx, atb, &mut self.text_editor);
+            pane.splitter.mid_splitter(cx);
         }
-        else if path.ends_with(".js") || path.ends_with(".html") {
-            JSTokenizer::update_token_chunks(atb, Some(search_index));
+        
+        Self::update_token_chunks(cx, &self.language, atb, Some(search_index));
+        atb.update_line_changes();
+        
+        if self.text_editor.begin_text_editor(cx, &mut atb.text_buffer).is_ok() {
+            let mut reader = TextLinesReader::new(&atb.text_buffer.lines);
+            let mut chunk = Vec::new();
+            for (index, token_chunk) in atb.text_buffer.token_chunks.iter().enumerate() {
+                reader.chars_into(token_chunk.offset, token_chunk.len, &mut chunk);
+                self.text_editor.draw_chunk(cx, index, &chunk, token_chunk, &atb.text_buffer.markers);
+            }
+            self.text_editor.end_text_editor(cx, &mut atb.text_buffer);
         }
-        else {
-            PlainTokenizer::update_token_chunks(&mut atb.text_buffer, Some(search_index));
+        
+        if let Some(pane) = &mut self.live_macros {
+            pane.splitter.end_splitter(cx);
+            pane.view.end_view(cx);
+        }
+    }
+    
+    pub fn update_token_chunks(cx: &mut Cx, language: &Rc<dyn Language>, atb: &mut AppTextBuffer, mut search_index: Option<&mut SearchIndex>) {
+        let text_buffer_id = atb.text_buffer_id;
+        let tokenized = language.update_token_chunks(&mut atb.text_buffer, &mut | text_buffer | {
+            if let Some(search_index) = search_index.as_mut() {
+                search_index.new_token(text_buffer, text_buffer_id);
+            }
+        });
+        if tokenized && language.has_live_macros() {
+            atb.parse_live_macros(cx);
         }
     }
 }
 
 impl FileEditors {
+    pub fn new(cx: &mut Cx) -> Self {
+        Self {
+            languages: Self::default_languages(),
+            text_editor: TextEditor {
+                folding_depth: 3,
+                minimap: Some(Minimap::new(cx)),
+                ..TextEditor::new(cx)
+            },
+            live_macros: LiveMacrosPane::new(cx),
+            wrap_column: None,
+            editors: HashMap::new()
+        }
+    }
+    
+    // the languages makepad knows about, anything not listed here opens as plain text
+    pub fn default_languages() -> LanguageRegistry {
+        let mut languages = LanguageRegistry::default();
+        languages.register(Rc::new(RustLanguage {}), &["rs"]);
+        languages.register(Rc::new(JSLanguage {}), &["js", "html"]);
+        languages.register(Rc::new(TomlLanguage {}), &["toml"]);
+        languages.register(Rc::new(JsonLanguage {}), &["json", "jsonc"]);
+        languages.register(Rc::new(RonLanguage {}), &["ron"]);
+        languages.register(Rc::new(MarkdownLanguage {}), &["md", "markdown"]);
+        languages.register(Rc::new(ShaderLanguage {}), &["glsl", "vert", "frag", "shader"]);
+        languages.register(Rc::new(PythonLanguage {}), &["py", "pyw"]);
+        languages
+    }
+    
     pub fn does_path_match_editor_type(&mut self, path: &str, editor_id:u64)->bool{
-        if !self.editors.contains_key(&editor_id){
-            return false;
+        if let Some(editor) = self.editors.get(&editor_id) {
+            editor.language.name() == self.languages.for_path(path).name()
         }
-        match self.editors.get(&editor_id).unwrap(){
-            FileEditor::Rust(_) =>path.ends_with(".rs") || path.ends_with(".toml") || path.ends_with(".ron"),
-            FileEditor::JS(_) => path.ends_with(".js") || path.ends_with(".html"),
-            FileEditor::Plain(_) => !(path.ends_with(".rs") || path.ends_with(".toml") || path.ends_with(".ron") || path.ends_with(".js") || path.ends_with(".html"))
+        else {
+            false
         }
     }
     
@@ -115,26 +219,52 @@ impl FileEditors {
         // check which file extension we have to spawn a new editor
         let is_new = !self.editors.contains_key(&editor_id);
         if is_new { 
-            let editor = if path.ends_with(".rs") || path.ends_with(".toml") || path.ends_with(".ron") {
-                FileEditor::Rust(RustEditor {
-                    ..self.rust_editor.clone()
-                }) 
-            }
-            else if path.ends_with(".js") || path.ends_with(".html") {
-                FileEditor::JS(JSEditor {
-                    ..self.js_editor.clone()
-                })
-            }
-            else {
-                FileEditor::Plain(PlainEditor {
-                    ..self.plain_editor.clone()
-                })
+            let language = self.languages.for_path(path);
+            let editor = FileEditor {
+                text_editor: TextEditor {
+                    folding_depth: language.folding_depth(),
+                    wrap: Self::wrap_for(&*language, self.wrap_column),
+                    language: Some(language.clone()),
+                    snippets: self.languages.snippets_for(&*language),
+                    ..self.text_editor.clone()
+                },
+                live_macros: if language.has_live_macros() {Some(self.live_macros.clone())} else {None},
+                language: language,
             };
             self.editors.insert(editor_id, editor);
         }
         (self.editors.get_mut(&editor_id).unwrap(), is_new)
     }
     
+    fn wrap_for(language: &dyn Language, wrap_column: Option<usize>) -> TextWrap {
+        if !language.soft_wrap() {
+            return TextWrap::None
+        }
+        match wrap_column {
+            Some(cols) => TextWrap::Column(cols),
+            None => TextWrap::Viewport
+        }
+    }
+    
+    // also updates the editors that are already open
+    pub fn set_wrap_column(&mut self, cx: &mut Cx, wrap_column: Option<usize>) {
+        self.wrap_column = wrap_column;
+        for (_, editor) in &mut self.editors {
+            editor.text_editor.wrap = Self::wrap_for(&*editor.language, wrap_column);
+            editor.text_editor.view.redraw_view_area(cx);
+        }
+    }
+    
+    // also updates the editors that are already open
+    pub fn set_snippets(&mut self, language_name: &str, snippets: Rc<Vec<Snippet>>) {
+        self.languages.set_snippets(language_name, snippets.clone());
+        for (_, editor) in &mut self.editors {
+            if editor.language.name() == language_name {
+                editor.text_editor.snippets = snippets.clone();
+            }
+        }
+    }
+    
     pub fn highest_file_editor_id(&self) -> u64 {
         let mut max_id = 0;
         for (id, _) in &self.editors {
@@ -5,7 +5,7 @@ use makepad_widget::*;
 pub struct HomePage {
     pub view: ScrollView,
     pub shadow: ScrollShadow,
-    pub text: Text,
+    pub texts: ElementsCounted<RichText>,
     pub email_input: TextInput,
     pub email_state: EmailState,
     pub email_signal: Signal,
@@ -27,7 +27,11 @@ impl HomePage {
     pub fn new(cx: &mut Cx) -> Self {
         Self {
             view: ScrollView::new(cx),
-            text: Text::new(cx),
+            texts: ElementsCounted::new(RichText {
+                font_scale: 1.25,
+                max_width: Some(530.),
+                ..RichText::new(cx)
+            }),
             shadow: ScrollShadow::new(cx),
             send_mail_button: NormalButton::new(cx),
             email_signal: cx.new_signal(),
@@ -47,35 +51,9 @@ impl HomePage {
         }
     }
     
-    pub fn text_color() -> ColorId {uid!()}
     pub fn layout_main() -> LayoutId {uid!()}
-    pub fn text_style_heading() -> TextStyleId {uid!()}
-    pub fn text_style_body() -> TextStyleId {uid!()}
-    pub fn text_style_point() -> TextStyleId {uid!()}
-    pub fn walk_paragraph() -> WalkId {uid!()}
     
-    pub fn style(cx: &mut Cx, opt: &StyleOptions) {
-        
-        Self::text_style_heading().set(cx, TextStyle {
-            font_size: 28.0 * opt.scale,
-            line_spacing: 2.0,
-            ..Theme::text_style_normal().get(cx)
-        });
-        
-        Self::text_style_body().set(cx, TextStyle {
-            font_size: 10.0 * opt.scale,
-            height_factor: 2.0,
-            line_spacing: 3.0,
-            ..Theme::text_style_normal().get(cx)
-        });
-        
-        Self::text_style_point().set(cx, TextStyle {
-            font_size: 8.0 * opt.scale,
-            line_spacing: 2.5,
-            ..Theme::text_style_normal().get(cx)
-        });
-        
-        Self::text_color().set(cx, pick!(#b).get(cx));
+    pub fn style(cx: &mut Cx, _opt: &StyleOptions) {
         Self::layout_main().set(cx, Layout {
             padding: Padding {l: 10., t: 10., r: 10., b: 10.},
             new_line_padding: 15.,
@@ -125,6 +103,10 @@ impl HomePage {
             }
         }
         
+        for text in self.texts.iter() {
+            text.handle_rich_text(cx, event);
+        }
+        
         for text_input in self.example_texts.iter(){
             text_input.handle_text_input(cx, event);
         }
@@ -135,22 +117,17 @@ impl HomePage {
     pub fn draw_home_page(&mut self, cx: &mut Cx) {
         if self.view.begin_view(cx, Self::layout_main().get(cx)).is_err() {return};
         
-        let t = &mut self.text;
-        
-        t.color = Self::text_color().get(cx);
-        
-        t.text_style = Self::text_style_heading().get(cx);
-        t.draw_text(cx, "Introducing Makepad\n");
-        
-        t.text_style = Self::text_style_body().get(cx);
-        t.draw_text(cx, "\
-            Makepad is a creative software development platform built around Rust. \
-            We aim to make the creative software development process as fun as possible! \
-            To do this we will provide a set of visual design tools that modify your \
-            application in real time, as well as a library ecosystem that allows you to \
-            write highly performant multimedia applications. Please note the following text \
-            input doesn't work on mobile-web yet. We also won't email you a confirmation right now, that will follow later.\n");
-        
+        self.texts.get_draw(cx).draw_rich_text(cx, &RichText::spans_from_markdown("
+            # Introducing Makepad
+            
+            Makepad is a creative software development platform built around Rust.
+            We aim to make the creative software development process as fun as possible!
+            To do this we will provide a set of visual design tools that modify your
+            application in real time, as well as a library ecosystem that allows you to
+            write highly performant multimedia applications. Please note the following text
+            input doesn't work on mobile-web yet. We also won't email you a confirmation right now, that will follow later.
+        "));
+        cx.turtle_new_line();
         
         self.email_input.draw_text_input(cx);
         self.send_mail_button.draw_normal_button(cx, match self.email_state {
@@ -163,52 +140,49 @@ impl HomePage {
         });
         cx.turtle_new_line();
         
-        t.draw_text(cx, "\
-            The Makepad development platform and library ecosystem are MIT licensed, \
-            and will be available for free as part of Makepad Basic. In the near future, \
-            we will also introduce Makepad Pro, which will be available as a subscription \
-            model. Makepad Pro will include the visual design tools. Because the library \
-            ecosystem is MIT licensed, all applications made with the Pro version are \
-            entirely free licensed.\n");
-        
-        t.draw_text(cx, "\
-            Today, we launch an early alpha of Makepad Basic. This version shows off \
-            the development platform, but does not include the visual design tools or \
-            library ecosystem yet. It is intended as a starting point for feedback \
-            from you! Although Makepad is primarily a native application, its UI \
-            is perfectly capable of running on the web. Try browsing the source code and pressing alt \
-            in a large code file!. To compile code yourself, you have to install \
-            the native version. Right now makepad is set up to compile a simple WASM example you run in a browser from a localhost url.\n");
-        
-        t.text_style = Self::text_style_heading().get(cx);
-        t.draw_text(cx, "How to use\n");
-        
-        t.text_style = Self::text_style_body().get(cx);
-        t.draw_text(cx, "\
-            After install (see below) you can open the following file in makepad, and when you change the rust code, \
-            the browser should live reload the wasm application as you type.\
-            \n");
+        self.texts.get_draw(cx).draw_rich_text(cx, &RichText::spans_from_markdown("
+            The Makepad development platform and library ecosystem are MIT licensed,
+            and will be available for free as part of **Makepad Basic**. In the near future,
+            we will also introduce **Makepad Pro**, which will be available as a subscription
+            model. Makepad Pro will include the visual design tools. Because the library
+            ecosystem is MIT licensed, all applications made with the Pro version are
+            entirely free licensed.
+            
+            Today, we launch an early alpha of Makepad Basic. This version shows off
+            the development platform, but does not include the visual design tools or
+            library ecosystem yet. It is intended as a starting point for feedback
+            from you! Although Makepad is primarily a native application, its UI
+            is perfectly capable of running on the web. Try browsing the source code and pressing `alt`
+            in a large code file!. To compile code yourself, you have to install
+            the native version. Right now makepad is set up to compile a simple WASM example you run in a browser from a localhost url.
+            
+            # How to use
+            
+            After install (see below) you can open the following file in makepad, and when you change the rust code,
+            the browser should live reload the wasm application as you type.
+        "));
+        cx.turtle_new_line();
         
         self.example_texts.get_draw(cx).draw_text_input_static(cx,"\
             open this file the makepad editor UI: main/makepad/examples/webgl_example_wasm/src/sierpinski.rs \n\
             open this url in your browser: http://127.0.0.1:8000/makepad/examples/webgl_example_wasm/");
         cx.turtle_new_line();
         
-        t.text_style = Self::text_style_heading().get(cx);
-        t.draw_text(cx, "How to install\n");
+        self.texts.get_draw(cx).draw_rich_text(cx, &RichText::spans_from_markdown("
+            # How to install
+            
+            On all platforms first install Rust.
+            On windows feel free to ignore the warnings about MSVC, makepad uses the gnu chain.
+            Copy this url to your favorite browser.
+        "));
+        cx.turtle_new_line();
         
-        t.text_style = Self::text_style_body().get(cx);
-        t.draw_text(cx, "\
-            On all platforms first install Rust. \
-            On windows feel free to ignore the warnings about MSVC, makepad uses the gnu chain. \
-            Copy this url to your favorite browser.\n");
-
         self.example_texts.get_draw(cx).draw_text_input_static(cx,"\
             https://www.rust-lang.org/tools/install");
         cx.turtle_new_line();
         
-        t.text_style = Self::text_style_heading().get(cx);
-        t.draw_text(cx, "MacOS\n");
+        self.texts.get_draw(cx).draw_rich_text(cx, &RichText::spans_from_markdown("# MacOS"));
+        cx.turtle_new_line();
         
         self.example_texts.get_draw(cx).draw_text_input_static(cx,"\
             git clone https://github.com/makepad/makepad\n\
@@ -216,20 +190,20 @@ impl HomePage {
             tools/macos_rustup.sh\n\
             cargo run -p makepad --release");
         cx.turtle_new_line();
-
-        t.text_style = Self::text_style_heading().get(cx);
-        t.draw_text(cx, "Windows\n");
-
+        
+        self.texts.get_draw(cx).draw_rich_text(cx, &RichText::spans_from_markdown("# Windows"));
+        cx.turtle_new_line();
+        
         self.example_texts.get_draw(cx).draw_text_input_static(cx,"\
             Clone this repo using either gitub desktop or commandline: https://github.com/makepad/makepad\n\
             Open a cmd.exe in the directory you just cloned. Gh desktop makes: Documents\\Github\\makepad\n\
             tools\\windows_rustup.bat\n\
             cargo run -p makepad --release --target x86_64-pc-windows-gnu");
         cx.turtle_new_line();
-
-        t.text_style = Self::text_style_heading().get(cx);
-        t.draw_text(cx, "Linux\n");
-
+        
+        self.texts.get_draw(cx).draw_rich_text(cx, &RichText::spans_from_markdown("# Linux"));
+        cx.turtle_new_line();
+        
         self.example_texts.get_draw(cx).draw_text_input_static(cx,"\
             git clone https://github.com/makepad/makepad\n\
             cd makepad\n\
@@ -237,9 +211,9 @@ impl HomePage {
             cargo run -p makepad --release");
         cx.turtle_new_line();
         
-        t.text_style = Self::text_style_heading().get(cx);
-        t.draw_text(cx, "Troubleshooting\n"); 
-
+        self.texts.get_draw(cx).draw_rich_text(cx, &RichText::spans_from_markdown("# Troubleshooting"));
+        cx.turtle_new_line();
+        
         self.example_texts.get_draw(cx).draw_text_input_static(cx,"\
             Delete old settings unix: rm *.ron\n\
             Delete old settings windows: del *.ron\n\
@@ -250,7 +224,7 @@ impl HomePage {
             If gnu chain for some reason doesn't work on windows, use the msvc chain\n\
             Still have a problem? Report here: https://github.com/makepad/makepad/issues");
         cx.turtle_new_line();
-
+        
         self.shadow.draw_shadow_top(cx);
         self.view.end_view(cx);
     }
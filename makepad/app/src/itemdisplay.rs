@@ -1,4 +1,3 @@
-use crate::plaineditor::*;
 use makepad_render::*;
 use makepad_hub::*;
 use makepad_widget::*;
@@ -38,6 +37,7 @@ impl ItemDisplay {
                 draw_cursor_row: false,
                 mark_unmatched_parens: false,
                 folding_depth: 3,
+                wrap: TextWrap::Viewport,
                 ..TextEditor::new(cx)
             },
             text_buffer: TextBuffer {
@@ -81,7 +81,7 @@ impl ItemDisplay {
     
     pub fn update_plain_text_buffer(text_buffer: &mut TextBuffer, text: &str) {
         text_buffer.load_from_utf8(text);
-        PlainTokenizer::update_token_chunks(text_buffer, None);
+        PlainLanguage {}.update_token_chunks(text_buffer, &mut | _ | ());
     }
     
     pub fn update_message_text_buffer(text_buffer: &mut TextBuffer, loc_message: &LocMessage) {
@@ -110,12 +110,13 @@ impl ItemDisplay {
             let mut first_block = false;
             let mut first_block_code_line = false;
             let mut message_type = TokenType::Warning;
+            let mut flat_text = Vec::new();
             loop {
-                let offset = text_buffer.flat_text.len();
-                let mut token_type = tokenizer.next_token(&mut state, &mut text_buffer.flat_text, &text_buffer.token_chunks);
+                let offset = flat_text.len();
+                let mut token_type = tokenizer.next_token(&mut state, &mut flat_text, &text_buffer.token_chunks);
                 let mut val = String::new();
-                for i in offset..text_buffer.flat_text.len() {
-                    val.push(text_buffer.flat_text[i]);
+                for i in offset..flat_text.len() {
+                    val.push(flat_text[i]);
                 }
                 if token_type == TokenType::Operator && val == "`" {
                     backtick_toggle = !backtick_toggle;
@@ -157,7 +158,7 @@ impl ItemDisplay {
                 }
                 //println!("{:?} {}", token_type, val);
                 
-                TokenChunk::push_with_pairing(&mut text_buffer.token_chunks, &mut pair_stack, state.next, offset, text_buffer.flat_text.len(), token_type);
+                TokenChunk::push_with_pairing(&mut text_buffer.token_chunks, &mut pair_stack, state.next, offset, flat_text.len(), token_type);
                 
                 token_count += 1;
                 if token_type == TokenType::Newline {
@@ -208,8 +209,11 @@ impl ItemDisplay {
                 cx.begin_style(Self::style_text_editor());
                 if self.text_disp.begin_text_editor(cx, text_buffer).is_err() {return cx.end_style();}
                 
-                for (index, token_chunk) in text_buffer.token_chunks.iter_mut().enumerate() {
-                    self.text_disp.draw_chunk(cx, index, &text_buffer.flat_text, token_chunk, &text_buffer.markers);
+                let mut reader = TextLinesReader::new(&text_buffer.lines);
+                let mut chunk = Vec::new();
+                for (index, token_chunk) in text_buffer.token_chunks.iter().enumerate() {
+                    reader.chars_into(token_chunk.offset, token_chunk.len, &mut chunk);
+                    self.text_disp.draw_chunk(cx, index, &chunk, token_chunk, &text_buffer.markers);
                 }
                 self.text_disp.end_text_editor(cx, text_buffer);
                 cx.end_style();
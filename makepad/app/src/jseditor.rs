@@ -1,51 +1,38 @@
-use makepad_render::*;
 use makepad_widget::*;
-use crate::searchindex::*;
-use crate::appstorage::*;
+use std::rc::Rc;
 use crate::mprstokenizer::*;
 
-#[derive(Clone)]
-pub struct JSEditor {
-    pub text_editor: TextEditor,
-}
+pub struct JSLanguage {}
 
-impl JSEditor {
-    pub fn new(cx: &mut Cx) -> Self {
-        Self {
-            text_editor: TextEditor {
-                folding_depth: 3,
-                ..TextEditor::new(cx)
-            }
-        }
+impl Language for JSLanguage {
+    fn name(&self) -> &str {"javascript"}
+    
+    fn new_tokenizer(&self) -> Box<dyn Tokenizer> {
+        Box::new(JSTokenizer::new())
     }
     
-    pub fn handle_js_editor(&mut self, cx: &mut Cx, event: &mut Event, atb: &mut AppTextBuffer) -> TextEditorEvent {
-        let ce = self.text_editor.handle_text_editor(cx, event, &mut atb.text_buffer);
-        match ce {
-            TextEditorEvent::AutoFormat => {
-                let formatted = JSTokenizer::auto_format(&mut atb.text_buffer).out_lines;
-                self.text_editor.cursors.replace_lines_formatted(formatted, &mut atb.text_buffer);
-                self.text_editor.view.redraw_view_area(cx);
-            },
-            _ => ()
-        }
-        ce
+    fn line_comment(&self) -> Option<&str> {Some("//")}
+    
+    fn auto_format(&self, text_buffer: &mut TextBuffer) -> Option<Vec<Vec<char>>> {
+        Some(JSTokenizer::auto_format(text_buffer).out_lines)
     }
     
-    pub fn draw_js_editor(&mut self, cx: &mut Cx, atb: &mut AppTextBuffer, search_index: Option<&mut SearchIndex>) {
-        
-        JSTokenizer::update_token_chunks(atb, search_index);
-        
-        if self.text_editor.begin_text_editor(cx, &mut atb.text_buffer).is_err() {return}
-        
-        for (index, token_chunk) in atb.text_buffer.token_chunks.iter_mut().enumerate() {
-            self.text_editor.draw_chunk(cx, index, &atb.text_buffer.flat_text, token_chunk, &atb.text_buffer.markers);
+    fn completion_provider(&self) -> Option<Rc<dyn CompletionProvider>> {
+        let mut words = Vec::new();
+        for word in &["function", "return", "const", "let", "var", "class", "extends", "constructor", "async", "await",
+            "if", "else", "for", "while", "switch", "case", "break", "continue", "new", "this", "typeof", "instanceof",
+            "null", "undefined", "true", "false"] {
+            words.push((*word, TokenType::Keyword));
         }
-        
-        self.text_editor.end_text_editor(cx, &mut atb.text_buffer);
+        for word in &["Array", "Object", "String", "Number", "Math", "JSON", "Promise", "Map", "Set", "Float32Array",
+            "Uint32Array", "Uint8Array", "ArrayBuffer", "WebAssembly", "console", "document", "window"] {
+            words.push((*word, TokenType::TypeName));
+        }
+        Some(Rc::new(WordListProvider {words: words, detail: "js"}))
     }
 }
 
+#[derive(Clone)]
 pub struct JSTokenizer {
     pub comment_single: bool,
     pub comment_depth: usize
@@ -58,25 +45,10 @@ impl JSTokenizer {
             comment_depth: 0
         }
     }
-    
-    pub fn update_token_chunks(atb: &mut AppTextBuffer, mut _search_index: Option<&mut SearchIndex>) {
-        let text_buffer = &mut atb.text_buffer;
-        if text_buffer.needs_token_chunks() && text_buffer.lines.len() >0 {
-            let mut state = TokenizerState::new(&text_buffer.lines);
-            let mut tokenizer = JSTokenizer::new();
-            let mut pair_stack = Vec::new();
-            loop {
-                let offset = text_buffer.flat_text.len();
-                let token_type = tokenizer.next_token(&mut state, &mut text_buffer.flat_text, &text_buffer.token_chunks);
-                TokenChunk::push_with_pairing(&mut text_buffer.token_chunks, &mut pair_stack, state.next, offset, text_buffer.flat_text.len(), token_type);
-                if token_type == TokenType::Eof {
-                    break
-                }
-            }
-        }
-    }
-    
-    pub fn next_token<'a>(&mut self, state: &mut TokenizerState<'a>, chunk: &mut Vec<char>, token_chunks: &Vec<TokenChunk>) -> TokenType {
+}
+
+impl Tokenizer for JSTokenizer {
+    fn next_token<'a>(&mut self, state: &mut TokenizerState<'a>, chunk: &mut Vec<char>, token_chunks: &Vec<TokenChunk>) -> TokenType {
         let start = chunk.len();
         if self.comment_depth >0 { // parse comments
             loop {
@@ -380,7 +352,9 @@ impl JSTokenizer {
             }
         }
     }
-    
+}
+
+impl JSTokenizer {
     fn parse_js_ident_tail<'a>(state: &mut TokenizerState<'a>, chunk: &mut Vec<char>) -> bool {
         let mut ret = false;
         while state.next_is_digit() || state.next_is_letter() || state.next == '_' || state.next == '$' {
@@ -643,7 +617,8 @@ impl JSTokenizer {
         let extra_spacey = false;
         let pre_spacey = true;
         let mut out = FormatOutput::new();
-        let mut tp = TokenParser::new(&text_buffer.flat_text, &text_buffer.token_chunks);
+        let flat_text = text_buffer.flat_text();
+        let mut tp = TokenParser::new(&flat_text, &text_buffer.token_chunks);
         
         struct ParenStack {
             expecting_newlines: bool,
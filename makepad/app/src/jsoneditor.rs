@@ -0,0 +1,104 @@
+use makepad_widget::*;
+
+pub struct JsonLanguage {}
+
+impl Language for JsonLanguage {
+    fn name(&self) -> &str {"json"}
+
+    fn new_tokenizer(&self) -> Box<dyn Tokenizer> {
+        Box::new(JsonTokenizer::new())
+    }
+
+    fn line_comment(&self) -> Option<&str> {Some("//")}
+}
+
+// also accepts the // and /* */ comments of jsonc files
+#[derive(Clone)]
+pub struct JsonTokenizer {
+    pub comment: bool,
+    pub comment_depth: usize,
+}
+
+impl JsonTokenizer {
+    pub fn new() -> JsonTokenizer {
+        JsonTokenizer {
+            comment: false,
+            comment_depth: 0
+        }
+    }
+}
+
+impl Tokenizer for JsonTokenizer {
+    fn next_token<'a>(&mut self, state: &mut TokenizerState<'a>, chunk: &mut Vec<char>, _token_chunks: &Vec<TokenChunk>) -> TokenType {
+        if self.comment {
+            if let Some(token_type) = state.comment_line_chunk(chunk) {
+                return token_type
+            }
+            self.comment = false;
+        }
+        if self.comment_depth > 0 {
+            if let Some(token_type) = state.comment_multi_chunk(chunk, &mut self.comment_depth, false) {
+                return token_type
+            }
+        }
+        state.advance_with_cur();
+        chunk.push(state.cur);
+        match state.cur {
+            '\0' => { // eof insert a terminating space and end
+                chunk.pop();
+                chunk.push(' ');
+                TokenType::Eof
+            },
+            '\n' => TokenType::Newline,
+            ' ' | '\t' => {
+                while state.next == ' ' || state.next == '\t' {
+                    chunk.push(state.next);
+                    state.advance();
+                }
+                TokenType::Whitespace
+            },
+            '/' => {
+                if state.next == '/' {
+                    chunk.push(state.next);
+                    state.advance();
+                    self.comment = true;
+                    return TokenType::CommentLine
+                }
+                if state.next == '*' {
+                    chunk.push(state.next);
+                    state.advance();
+                    self.comment_depth = 1;
+                    return TokenType::CommentMultiBegin
+                }
+                TokenType::Unexpected
+            },
+            '"' => {
+                state.string_rest(chunk, '"', true);
+                // object keys are directly followed by their colon
+                if state.next == ':' {
+                    return TokenType::Identifier
+                }
+                TokenType::String
+            },
+            '-' | '0'..='9' => {
+                state.number_rest(chunk);
+                TokenType::Number
+            },
+            '{' | '[' => TokenType::ParenOpen,
+            '}' | ']' => TokenType::ParenClose,
+            ':' => TokenType::Colon,
+            ',' => TokenType::Delimiter,
+            'a'..='z' | 'A'..='Z' => {
+                let start = chunk.len() - 1;
+                state.ident_rest(chunk, "");
+                let word: String = chunk[start..].iter().collect();
+                match word.as_ref() {
+                    "true" | "false" => TokenType::Bool,
+                    "null" => TokenType::Keyword,
+                    _ => TokenType::Unexpected
+                }
+            },
+            _ => TokenType::Unexpected
+        }
+    }
+}
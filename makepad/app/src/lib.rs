@@ -7,6 +7,8 @@ pub mod makepadapp;
 pub use crate::makepadapp::*;
 pub mod appwindow;
 pub mod appstorage;
+pub mod appsession;
+pub mod appcollab;
 pub mod filetree;
 pub mod buildmanager;
 pub mod makepadstyle;
@@ -16,7 +18,12 @@ pub mod searchresults;
 pub mod codeicon;
 pub mod rusteditor;
 pub mod jseditor;
-pub mod plaineditor;
+pub mod tomleditor;
+pub mod jsoneditor;
+pub mod roneditor;
+pub mod markdowneditor;
+pub mod shadereditor;
+pub mod pythoneditor;
 pub mod itemdisplay;
 pub mod livemacro;
 pub mod shaderview;
@@ -204,6 +204,7 @@ pub enum LogListEvent {
 
 impl LogList {
     pub fn new(cx: &mut Cx) -> Self {
+        Self::default_keymap(cx);
         Self {
             item_draw: LogItemDraw::new(cx),
             list: ListLogic {
@@ -216,6 +217,18 @@ impl LogList {
 
     pub fn style_text_input() -> StyleId {uid!()}
     
+    pub fn default_keymap(cx: &mut Cx) {
+        let ctx = Some("log");
+        let km = &mut cx.keymap;
+        km.register_command("log.next_item", "Next Log Item", &["mod+period"], ctx);
+        km.register_command("log.select_next_item", "Select Next Log Item at End", &["mod+shift+period"], ctx);
+        km.register_command("log.prev_item", "Previous Log Item", &["mod+comma"], ctx);
+        km.register_command("log.select_prev_item", "Select Previous Log Item at End", &["mod+shift+comma"], ctx);
+        km.register_command("log.select_all", "Select All Log Items", &["mod+m"], ctx);
+        km.register_command("log.tail", "Tail Log", &["mod+t"], ctx);
+        km.register_command("log.clear", "Clear Log", &["mod+k"], ctx);
+    }
+    
     pub fn style(cx: &mut Cx, opt: &StyleOptions) {
         cx.begin_style(Self::style_text_input());
         TextEditor::layout_bg().set(cx, Layout {
@@ -240,35 +253,37 @@ impl LogList {
         let mut select_at_end = false;
         // global key handle
         match event {
-            Event::KeyDown(ke) => match ke.key_code {
-                KeyCode::Period => if ke.modifiers.logo || ke.modifiers.control {
-                    select = self.list.get_next_single_selection();
-                    self.list.scroll_item_in_view = select.item_index();
-                    bm.tail_log_items = false;
-                    select_at_end = ke.modifiers.shift;
-                },
-                KeyCode::Comma => if ke.modifiers.logo || ke.modifiers.control {
-                    // lets find the
-                    select = self.list.get_prev_single_selection();
-                    bm.tail_log_items = false;
-                    self.list.scroll_item_in_view = select.item_index();
-                    select_at_end = ke.modifiers.shift;
-                },
-                KeyCode::KeyM => if ke.modifiers.logo || ke.modifiers.control {
-                    select = ListSelect::All;
-                },
-                KeyCode::KeyT => if ke.modifiers.logo || ke.modifiers.control {
-                    // lock scroll
-                    bm.tail_log_items = true;
-                    self.view.redraw_view_area(cx);
-                },
-                KeyCode::KeyK => if ke.modifiers.logo || ke.modifiers.control {
-                    // clear and tail log
-                    bm.tail_log_items = true;
-                    bm.log_items.truncate(0);
-                    self.view.redraw_view_area(cx);
-                },
-                _ => ()
+            Event::KeyDown(ke) => if let KeyMapMatch::Command(command) = cx.keymap.match_key_down(ke, "log") {
+                match command.as_ref() {
+                    "log.next_item" | "log.select_next_item" => {
+                        select = self.list.get_next_single_selection();
+                        self.list.scroll_item_in_view = select.item_index();
+                        bm.tail_log_items = false;
+                        select_at_end = command == "log.select_next_item";
+                    },
+                    "log.prev_item" | "log.select_prev_item" => {
+                        // lets find the
+                        select = self.list.get_prev_single_selection();
+                        bm.tail_log_items = false;
+                        self.list.scroll_item_in_view = select.item_index();
+                        select_at_end = command == "log.select_prev_item";
+                    },
+                    "log.select_all" => {
+                        select = ListSelect::All;
+                    },
+                    "log.tail" => {
+                        // lock scroll
+                        bm.tail_log_items = true;
+                        self.view.redraw_view_area(cx);
+                    },
+                    "log.clear" => {
+                        // clear and tail log
+                        bm.tail_log_items = true;
+                        bm.log_items.truncate(0);
+                        self.view.redraw_view_area(cx);
+                    },
+                    _ => ()
+                }
             },
             Event::Signal(se) => if let Some(_) = se.signals.get(&bm.signal) {
                 // we have new things
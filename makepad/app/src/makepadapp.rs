@@ -4,7 +4,6 @@ use makepad_widget::*;
 use makepad_microserde::*;
 use crate::appwindow::*;
 use crate::appstorage::*;
-use crate::filetree::*;
 use crate::buildmanager::*;
 use crate::makepadstyle::*;
 
@@ -40,6 +39,11 @@ impl MakepadApp {
     pub fn command_start_program() -> CommandId {uid!()}
     pub fn command_stop_program() -> CommandId {uid!()}
     pub fn command_bring_all_to_front() -> CommandId {uid!()}
+    pub fn command_open_file() -> CommandId {uid!()}
+    pub fn command_open_preview() -> CommandId {uid!()}
+    pub fn command_collapse_folders() -> CommandId {uid!()}
+    pub fn command_close_tab() -> CommandId {uid!()}
+    pub fn command_close_other_tabs() -> CommandId {uid!()}
     
     pub fn new(cx: &mut Cx) -> Self {
         let default_opt = StyleOptions {scale: 1.0, dark: true};
@@ -56,6 +60,7 @@ impl MakepadApp {
         Self::command_close_window().set_key_shift(cx, KeyCode::KeyW);
         
         cx.command_default_keymap();
+        Self::default_keymap(cx);
         
         Self {
             menu: Menu::main(vec![
@@ -224,17 +229,196 @@ impl MakepadApp {
         }
     }
     
+    // what a right click in an editor shows
+    pub fn editor_context_menu() -> Menu {
+        Menu::main(vec![
+            Menu::item("Undo", Cx::command_undo()),
+            Menu::item("Redo", Cx::command_redo()),
+            Menu::line(),
+            Menu::item("Cut", Cx::command_cut()),
+            Menu::item("Select All", Cx::command_select_all()),
+            Menu::line(),
+            Menu::item("Toggle Line Comment", Self::command_toggle_line_comment()),
+            Menu::sub("Find", vec![
+                Menu::item("Find", Self::command_find()),
+                Menu::item("Replace", Self::command_replace()),
+                Menu::line(),
+                Menu::item("Find in Files", Self::command_find_in_files()),
+            ]),
+        ])
+    }
+    
+    // what a right click in the file tree shows
+    pub fn file_tree_context_menu(is_file: bool) -> Menu {
+        if is_file {
+            Menu::main(vec![
+                Menu::item("Open", Self::command_open_file()),
+                Menu::item("Open as Preview", Self::command_open_preview()),
+                Menu::line(),
+                Menu::item("Collapse Folders", Self::command_collapse_folders()),
+            ])
+        }
+        else {
+            Menu::main(vec![
+                Menu::item("Collapse Folders", Self::command_collapse_folders()),
+            ])
+        }
+    }
+    
+    // what a right click on a dock tab shows
+    pub fn tab_context_menu() -> Menu {
+        Menu::main(vec![
+            Menu::item("Close Tab", Self::command_close_tab()),
+            Menu::item("Close Other Tabs", Self::command_close_other_tabs()),
+        ])
+    }
+    
     pub fn default_layout(&mut self, cx: &mut Cx) {
         self.state.windows = vec![self.app_window_state_template.clone()];
         self.windows = vec![self.app_window_template.clone()];
         cx.redraw_child_area(Area::All);
     }
     
+    // the window for state.windows[window_index], with its serialized position and size
+    pub fn new_app_window(&self, cx: &mut Cx, window_index: usize) -> AppWindow {
+        let window_state = &self.state.windows[window_index];
+        let mut size = window_state.window_inner_size;
+        
+        if size.x <= 10. {
+            size.x = 800.;
+        }
+        if size.y <= 10. {
+            size.y = 600.;
+        }
+        let last_pos = window_state.window_position;
+        let create_pos;
+        if last_pos.x < -1000. || last_pos.y < -1000. {
+            create_pos = None;
+        }
+        else {
+            create_pos = Some(last_pos);
+        }
+        AppWindow {
+            desktop_window: DesktopWindow {window: Window {
+                create_inner_size: Some(size),
+                create_position: create_pos,
+                ..Window::new(cx)
+            }, ..self.app_window_template.desktop_window.clone()},
+            ..self.app_window_template.clone()
+        }
+    }
+    
+    // a window holding just the tab, for tabs dragged out of a dock
+    pub fn new_window_with_tab(&mut self, cx: &mut Cx, tab: DockTab<Panel>, position: Vec2) {
+        self.state.windows.push(AppWindowState {
+            open_folders: Vec::new(),
+            window_position: position,
+            window_inner_size: Vec2 {x: 800., y: 600.},
+            dock_items: DockItem::TabControl {
+                current: 0,
+                previous: 0,
+                tabs: vec![tab]
+            }
+        });
+        let window_index = self.state.windows.len() - 1;
+        let window = self.new_app_window(cx, window_index);
+        self.windows.push(window);
+        self.windows[window_index].ensure_unique_tab_title_for_file_editors(cx, window_index, &mut self.state);
+        self.storage.save_state(cx, &self.state);
+        cx.redraw_child_area(Area::All);
+    }
+    
     pub fn reload_style(&mut self, cx: &mut Cx) {
         set_widget_style(cx, &self.storage.settings.style_options);
         set_makepad_style(cx, &self.storage.settings.style_options);
     }
     
+    pub fn default_keymap(cx: &mut Cx) {
+        let ctx = Some("app");
+        let km = &mut cx.keymap;
+        km.register_command("app.reload_builders", "Reload Builders", &["mod+r"], ctx);
+        km.register_command("app.zoom_reset", "Reset Zoom", &["mod+0"], ctx);
+        km.register_command("app.zoom_in", "Zoom In", &["mod+equals"], ctx);
+        km.register_command("app.zoom_out", "Zoom Out", &["mod+minus"], ctx);
+        km.register_command("app.build_and_run", "Build and Run", &["mod+backtick"], ctx);
+        km.register_command("app.command_palette", "Show Command Palette", &["mod+shift+p"], ctx);
+        km.register_command("app.show_files", "Show Files", &[], ctx);
+        km.register_command("app.show_search", "Show Search Results", &[], ctx);
+        km.register_command("app.show_log", "Show Log", &[], ctx);
+        km.register_command("app.show_item_display", "Show Item Display", &[], ctx);
+        km.register_command("app.toggle_dark_theme", "Toggle Dark Theme", &[], ctx);
+        km.register_command("app.save_layout", "Save Dock Layout", &[], ctx);
+        km.register_command("app.restore_layout", "Restore Dock Layout", &[], ctx);
+        km.register_command("app.quit", "Quit Makepad", &[], ctx);
+    }
+    
+    // menu commands that have a named equivalent, so the palette and the menu do the same thing
+    pub fn menu_command_to_named(command: CommandId) -> Option<&'static str> {
+        if command == Cx::command_undo() {Some("editor.undo")}
+        else if command == Cx::command_redo() {Some("editor.redo")}
+        else if command == Cx::command_cut() {Some("editor.cut")}
+        else if command == Cx::command_select_all() {Some("editor.select_all")}
+        else if command == Self::command_find() {Some("editor.find")}
+        else if command == Self::command_replace() {Some("editor.replace")}
+        else if command == Self::command_find_in_files() {Some("editor.find_in_files")}
+        else if command == Self::command_toggle_line_comment() {Some("editor.toggle_comment")}
+        else if command == Cx::command_zoom_in() {Some("app.zoom_in")}
+        else if command == Cx::command_zoom_out() {Some("app.zoom_out")}
+        else if command == Self::command_start_program() {Some("app.build_and_run")}
+        else if command == Cx::command_quit() {Some("app.quit")}
+        else {None}
+    }
+    
+    pub fn handle_menu_command(&mut self, cx: &mut Cx, command: CommandId) {
+        if let Some(command) = Self::menu_command_to_named(command) {
+            // editor commands arrive through the editors own key handling
+            if command.starts_with("app.") {
+                self.handle_app_command(cx, command);
+            }
+        }
+    }
+    
+    pub fn handle_app_command(&mut self, cx: &mut Cx, command: &str) {
+        match command {
+            "app.reload_builders" => {
+                self.storage.reload_builders();
+            },
+            "app.zoom_reset" => {
+                self.storage.settings.style_options.scale = 1.0;
+                self.reload_style(cx);
+                cx.reset_font_atlas_and_redraw();
+                self.storage.save_settings(cx);
+            },
+            "app.zoom_in" => {
+                let scale = self.storage.settings.style_options.scale * 1.1;
+                self.storage.settings.style_options.scale = scale.min(3.0).max(0.3);
+                self.reload_style(cx);
+                cx.reset_font_atlas_and_redraw();
+                self.storage.save_settings(cx);
+            },
+            "app.zoom_out" => {
+                let scale = self.storage.settings.style_options.scale / 1.1;
+                self.storage.settings.style_options.scale = scale.min(3.0).max(0.3);
+                self.reload_style(cx);
+                cx.reset_font_atlas_and_redraw();
+                self.storage.save_settings(cx);
+            },
+            "app.toggle_dark_theme" => {
+                self.storage.settings.style_options.dark = !self.storage.settings.style_options.dark;
+                self.reload_style(cx);
+                cx.redraw_child_area(Area::All);
+                self.storage.save_settings(cx);
+            },
+            "app.quit" => {
+                // the event loop ends once the last window is gone
+                for window in &mut self.windows {
+                    window.desktop_window.window.close_window(cx);
+                }
+            },
+            _ => ()
+        }
+    }
+    
     pub fn handle_app(&mut self, cx: &mut Cx, event: &mut Event) {
         match event {
             Event::Construct => {
@@ -243,35 +427,11 @@ impl MakepadApp {
                     self.default_layout(cx);
                 }
             },
-            Event::KeyDown(ke) => match ke.key_code {
-                KeyCode::KeyD => if ke.modifiers.logo || ke.modifiers.control {
-                    //let size = self.build_manager.search_index.calc_total_size();
-                    //println!("Text Index size {}", size);
-                },
-                KeyCode::KeyR => if ke.modifiers.logo || ke.modifiers.control {
-                    self.storage.reload_builders();
-                },
-                KeyCode::Key0 => if ke.modifiers.logo || ke.modifiers.control {
-                    self.storage.settings.style_options.scale = 1.0;
-                    self.reload_style(cx);
-                    cx.reset_font_atlas_and_redraw();
-                    self.storage.save_settings(cx);
-                },
-                KeyCode::Equals => if ke.modifiers.logo || ke.modifiers.control {
-                    let scale = self.storage.settings.style_options.scale * 1.1;
-                    self.storage.settings.style_options.scale = scale.min(3.0).max(0.3);
-                    self.reload_style(cx);
-                    cx.reset_font_atlas_and_redraw();
-                    self.storage.save_settings(cx);
-                },
-                KeyCode::Minus => if ke.modifiers.logo || ke.modifiers.control {
-                    let scale = self.storage.settings.style_options.scale / 1.1;
-                    self.storage.settings.style_options.scale = scale.min(3.0).max(0.3);
-                    self.reload_style(cx);
-                    cx.reset_font_atlas_and_redraw();
-                    self.storage.save_settings(cx);
-                },
-                _ => ()
+            Event::KeyDown(ke) => if let KeyMapMatch::Command(command) = cx.keymap.match_key_down(ke, "app") {
+                self.handle_app_command(cx, &command);
+            },
+            Event::Command(command) => {
+                self.handle_menu_command(cx, *command);
             },
             Event::Signal(se) => {
                 // process network messages for hub_ui
@@ -297,8 +457,23 @@ impl MakepadApp {
                     if self.storage.settings_old.builds != self.storage.settings.builds {
                         self.build_manager.restart_build(cx, &mut self.storage);
                     }
+                    if self.storage.settings_old.wrap_column != self.storage.settings.wrap_column {
+                        let wrap_column = self.storage.settings.wrap_column;
+                        self.app_window_template.file_editors.set_wrap_column(cx, wrap_column);
+                        for window in &mut self.windows {
+                            window.file_editors.set_wrap_column(cx, wrap_column);
+                        }
+                    }
                 }
             },
+            Event::Timer(te) => if self.storage.session_timer.is_timer(te) {
+                self.storage.save_session(cx, &mut self.windows, &mut self.state);
+                self.storage.collab_timer_tick(cx);
+            },
+            // closing a window or quitting shouldn't lose the edits since the last tick
+            Event::WindowClosed(_) => {
+                self.storage.save_session(cx, &mut self.windows, &mut self.state);
+            },
             Event::ShaderRecompile(re) => {
                 self.build_manager.handle_shader_recompile_event(cx, re, &mut self.storage);
             },
@@ -309,22 +484,11 @@ impl MakepadApp {
                         if let Ok(tree) = DeRon::deserialize_ron(utf8_data) {
                             for window in &mut self.windows {
                                 let mut paths = Vec::new();
-                                window.file_panel.file_tree.root_node = hub_to_tree(&tree, "", &mut paths);
-                                if let FileNode::Folder {folder, state, ..} = &mut window.file_panel.file_tree.root_node {
-                                    //*name = "".to_string();
-                                    *state = NodeState::Open;
-                                    for node in folder.iter_mut() {
-                                        if let FileNode::Folder {folder, state, ..} = node {
-                                            *state = NodeState::Open;
-                                            for node in folder.iter_mut() {
-                                                if let FileNode::Folder {state, ..} = node {
-                                                    *state = NodeState::Open
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                                window.file_panel.file_tree.view.redraw_view_area(cx);
+                                let root_node = hub_to_tree(&tree, "", &mut paths);
+                                let mut open_folders = Vec::new();
+                                root_node.folder_paths("", 2, &mut open_folders);
+                                window.file_panel.file_tree.set_root_node(cx, root_node);
+                                window.file_panel.file_tree.load_open_folders(cx, &open_folders);
                             }
                         }
                     }
@@ -335,31 +499,9 @@ impl MakepadApp {
                             self.state = state;
                             self.windows.truncate(0);
                             // create our windows with the serialized positions/size
-                            for window_state in &self.state.windows {
-                                let mut size = window_state.window_inner_size;
-                                
-                                if size.x <= 10. {
-                                    size.x = 800.;
-                                }
-                                if size.y <= 10. {
-                                    size.y = 600.;
-                                }
-                                let last_pos = window_state.window_position;
-                                let create_pos;
-                                if last_pos.x < -1000. || last_pos.y < -1000. {
-                                    create_pos = None;
-                                }
-                                else {
-                                    create_pos = Some(last_pos);
-                                }
-                                self.windows.push(AppWindow {
-                                    desktop_window: DesktopWindow {window: Window {
-                                        create_inner_size: Some(size),
-                                        create_position: create_pos,
-                                        ..Window::new(cx)
-                                    }, ..self.app_window_template.desktop_window.clone()},
-                                    ..self.app_window_template.clone()
-                                })
+                            for window_index in 0..self.state.windows.len() {
+                                let window = self.new_app_window(cx, window_index);
+                                self.windows.push(window);
                             }
                             cx.redraw_child_area(Area::All);
                             
@@ -372,6 +514,11 @@ impl MakepadApp {
                         window.ensure_unique_tab_title_for_file_editors(cx, window_index, &mut self.state);
                     }
                 }
+                else if let Some(data) = self.storage.session_file_read.resolve_bin(fr) {
+                    if let Ok(data) = data {
+                        self.storage.load_session(cx, data);
+                    }
+                }
                 else if let Some(utf8_data) = self.storage.app_settings_file_read.resolve_utf8(fr) {
                     if let Ok(utf8_data) = utf8_data {
                         self.storage.load_settings(cx, utf8_data);
@@ -383,12 +530,19 @@ impl MakepadApp {
                         self.storage.load_settings(cx, &ron);
                     }
                 }
+                else if let Some((language_name, snippets)) = self.storage.resolve_snippet_file_read(fr) {
+                    if let Some(snippets) = snippets {
+                        self.app_window_template.file_editors.set_snippets(&language_name, snippets.clone());
+                        for window in &mut self.windows {
+                            window.file_editors.set_snippets(&language_name, snippets.clone());
+                        }
+                    }
+                }
                 else {
                     for atb in &mut self.storage.text_buffers {
                         if let Some(utf8_data) = atb.file_read.resolve_utf8(fr) {
                             if let Ok(utf8_data) = utf8_data {
-                                atb.text_buffer.load_from_utf8(utf8_data);
-                                atb.text_buffer.send_textbuffer_loaded_signal(cx);
+                                atb.load_from_disk(cx, utf8_data, &mut self.storage.session);
                                 break;
                             }
                         }
@@ -398,10 +552,23 @@ impl MakepadApp {
             
             _ => ()
         }
+        let mut app_window_events = Vec::new();
         for (window_index, window) in self.windows.iter_mut().enumerate() {
-            window.handle_app_window(cx, event, window_index, &mut self.state, &mut self.storage, &mut self.build_manager);
+            match window.handle_app_window(cx, event, &self.menu, window_index, &mut self.state, &mut self.storage, &mut self.build_manager) {
+                AppWindowEvent::None => (),
+                app_window_event => app_window_events.push(app_window_event)
+            }
             // break;
         }
+        for app_window_event in app_window_events {
+            match app_window_event {
+                AppWindowEvent::AppCommand(command) => self.handle_app_command(cx, &command),
+                AppWindowEvent::MenuCommand(command) => self.handle_menu_command(cx, command),
+                AppWindowEvent::NewWindow {tab, position} => self.new_window_with_tab(cx, tab, position),
+                AppWindowEvent::None => ()
+            }
+        }
+        self.storage.collab_flush();
     }
     
     
@@ -1,20 +1,17 @@
 use makepad_render::*;
 use makepad_widget::*;
-use crate::filetree::*;
 use crate::loglist::*;
 use crate::homepage::*;
 use crate::codeicon::*;
 use crate::searchresults::*;
 use crate::itemdisplay::*;
-use crate::livemacro::*;
+use crate::appsession::*;
 
 pub fn set_makepad_style(cx: &mut Cx, opt:&StyleOptions) {
     CodeIcon::style(cx, opt);
     HomePage::style(cx, opt);
-    FileTree::style(cx, opt);
     LogList::style(cx, opt);
     SearchResults::style(cx, opt);
     ItemDisplay::style(cx, opt);
-    ColorPicker::style(cx, opt);
-    FloatSlider::style(cx, opt);
+    HotExitPrompt::style(cx, opt);
 }
@@ -0,0 +1,154 @@
+use makepad_widget::*;
+
+pub struct MarkdownLanguage {}
+
+impl Language for MarkdownLanguage {
+    fn name(&self) -> &str {"markdown"}
+
+    fn new_tokenizer(&self) -> Box<dyn Tokenizer> {
+        Box::new(MarkdownTokenizer::new())
+    }
+
+    fn soft_wrap(&self) -> bool {true}
+}
+
+// headings, lists, quotes, emphasis, links, inline code and fenced code blocks.
+// fenced blocks are emitted as a multiline string so folding and search treat them as one unit
+#[derive(Clone)]
+pub struct MarkdownTokenizer {
+    pub code_fence: bool,
+    pub line_begin: bool,
+    pub heading: bool,
+    pub quote: bool,
+}
+
+impl MarkdownTokenizer {
+    pub fn new() -> MarkdownTokenizer {
+        MarkdownTokenizer {
+            code_fence: false,
+            line_begin: true,
+            heading: false,
+            quote: false
+        }
+    }
+
+    fn parse_word<'a>(&self, state: &mut TokenizerState<'a>, chunk: &mut Vec<char>) -> TokenType {
+        while state.next != ' ' && state.next != '\t' && state.next != '\n' && state.next != '\0' && !"[]()`*~".contains(state.next) {
+            chunk.push(state.next);
+            state.advance();
+        }
+        if self.heading {
+            TokenType::TypeName
+        }
+        else if self.quote {
+            TokenType::CommentChunk
+        }
+        else {
+            TokenType::Identifier
+        }
+    }
+}
+
+impl Tokenizer for MarkdownTokenizer {
+    fn next_token<'a>(&mut self, state: &mut TokenizerState<'a>, chunk: &mut Vec<char>, _token_chunks: &Vec<TokenChunk>) -> TokenType {
+        if self.code_fence {
+            if let Some(token_type) = state.string_multi_chunk(chunk, '`', 3, false) {
+                if token_type == TokenType::StringMultiEnd {
+                    self.code_fence = false;
+                }
+                return token_type
+            }
+        }
+        state.advance_with_cur();
+        match state.cur {
+            '\0' => { // eof insert a terminating space and end
+                chunk.push(' ');
+                return TokenType::Eof
+            },
+            '\n' => {
+                self.line_begin = true;
+                self.heading = false;
+                self.quote = false;
+                chunk.push('\n');
+                return TokenType::Newline
+            },
+            ' ' | '\t' => {
+                chunk.push(state.cur);
+                while state.next == ' ' || state.next == '\t' {
+                    chunk.push(state.next);
+                    state.advance();
+                }
+                return TokenType::Whitespace
+            },
+            _ => ()
+        }
+        let line_begin = self.line_begin;
+        self.line_begin = false;
+        chunk.push(state.cur);
+        match state.cur {
+            '#' if line_begin => {
+                while state.next == '#' {
+                    chunk.push(state.next);
+                    state.advance();
+                }
+                self.heading = true;
+                TokenType::Hash
+            },
+            '>' if line_begin => {
+                self.quote = true;
+                // nested quotes stay at the start of the line
+                self.line_begin = true;
+                TokenType::Operator
+            },
+            '-' | '*' | '+' if line_begin && (state.next == ' ' || state.next == '\t') => {
+                TokenType::Operator
+            },
+            '0'..='9' if line_begin => {
+                while state.next_is_digit() {
+                    chunk.push(state.next);
+                    state.advance();
+                }
+                if state.next == '.' || state.next == ')' {
+                    chunk.push(state.next);
+                    state.advance();
+                    return TokenType::Number
+                }
+                self.parse_word(state, chunk)
+            },
+            '`' => {
+                if state.next == '`' {
+                    chunk.push(state.next);
+                    state.advance();
+                    if state.next == '`' {
+                        while state.next == '`' {
+                            chunk.push(state.next);
+                            state.advance();
+                        }
+                        // info string, like the language name
+                        while state.next != '\n' && state.next != '\0' && state.next != ' ' {
+                            chunk.push(state.next);
+                            state.advance();
+                        }
+                        self.code_fence = true;
+                        return TokenType::StringMultiBegin
+                    }
+                    // `` is an empty code span
+                    return TokenType::String
+                }
+                state.string_rest(chunk, '`', false);
+                TokenType::String
+            },
+            '*' | '_' | '~' => {
+                while state.next == state.cur {
+                    chunk.push(state.next);
+                    state.advance();
+                }
+                TokenType::Operator
+            },
+            '!' if state.next == '[' => TokenType::Operator,
+            '[' | '(' => TokenType::ParenOpen,
+            ']' | ')' => TokenType::ParenClose,
+            _ => self.parse_word(state, chunk)
+        }
+    }
+}
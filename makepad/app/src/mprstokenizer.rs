@@ -5,9 +5,24 @@ pub struct TokenParserItem {
     pub token_type: TokenType,
 }
 
+// the text the tokens index. Lines lets a parser that only looks at a few tokens skip the flat copy
+pub enum TokenText<'a> {
+    Flat(&'a Vec<char>),
+    Lines(&'a TextLines)
+}
+
+impl <'a>TokenText<'a> {
+    pub fn char_at(&self, offset: usize) -> char {
+        match self {
+            TokenText::Flat(flat_text) => flat_text[offset],
+            TokenText::Lines(lines) => lines.char_at(offset)
+        }
+    }
+}
+
 pub struct TokenParser<'a> {
     pub tokens: &'a Vec<TokenChunk>,
-    pub flat_text: &'a Vec<char>,
+    pub text: TokenText<'a>,
     pub index: usize,
     pub next_index: usize
 }
@@ -16,7 +31,16 @@ impl <'a>TokenParser<'a> {
     pub fn new(flat_text: &'a Vec<char>, token_chunks: &'a Vec<TokenChunk>) -> TokenParser<'a> {
         TokenParser {
             tokens: token_chunks,
-            flat_text: flat_text,
+            text: TokenText::Flat(flat_text),
+            index: 0,
+            next_index: 0
+        }
+    }
+    
+    pub fn new_lines(lines: &'a TextLines, token_chunks: &'a Vec<TokenChunk>) -> TokenParser<'a> {
+        TokenParser {
+            tokens: token_chunks,
+            text: TokenText::Lines(lines),
             index: 0,
             next_index: 0
         }
@@ -52,7 +76,7 @@ impl <'a>TokenParser<'a> {
             if off - chunk.offset > chunk.len {
                 return false
             }
-            if self.flat_text[off] != c {
+            if self.text.char_at(off) != c {
                 return false;
             }
             off += 1;
@@ -83,7 +107,7 @@ impl <'a>TokenParser<'a> {
         }
         let mut out_str = String::new();
         for i in self.cur_offset() + 1..self.cur_pair_offset() {
-            out_str.push(self.flat_text[i]);
+            out_str.push(self.text.char_at(i));
         }
         Some(out_str)
     }
@@ -92,7 +116,7 @@ impl <'a>TokenParser<'a> {
         let mut out_str = String::new();
         let tok = &self.tokens[self.index];
         for i in tok.offset..tok.offset + tok.len {
-            out_str.push(self.flat_text[i]);
+            out_str.push(self.text.char_at(i));
         }
         return out_str
     }
@@ -103,10 +127,16 @@ impl <'a>TokenParser<'a> {
     
     pub fn cur_line_col(&self) -> (usize, usize) {
         let off = self.cur_offset();
+        if let TokenText::Lines(lines) = &self.text {
+            // counts from the newline before the row, like the flat scan below
+            if let Some((row, row_offset)) = lines.offset_to_row(off) {
+                return (row, if row > 0 {off - row_offset + 1} else {off});
+            }
+        }
         let mut line = 0;
         let mut lc = 0;
         for i in 0..off {
-            if self.flat_text[i] == '\n' {
+            if self.text.char_at(i) == '\n' {
                 line = line + 1;
                 lc = i;
             }
@@ -155,7 +185,7 @@ impl <'a>TokenParser<'a> {
     pub fn prev_char(&self) -> char {
         if self.index > 0 {
             let len = self.tokens[self.index - 1].len;
-            let ch = self.flat_text[self.tokens[self.index - 1].offset];
+            let ch = self.text.char_at(self.tokens[self.index - 1].offset);
             if len == 1 || ch == ' ' {
                 return ch
             }
@@ -165,23 +195,26 @@ impl <'a>TokenParser<'a> {
     
     pub fn cur_char(&self) -> char {
         let len = self.tokens[self.index].len;
-        let ch = self.flat_text[self.tokens[self.index].offset];
+        let ch = self.text.char_at(self.tokens[self.index].offset);
         if len == 1 || ch == ' ' {
             return ch
         }
         '\0'
     }
     
-    pub fn cur_chunk(&self) -> &[char] {
+    pub fn cur_chunk(&self) -> Vec<char> {
         let offset = self.tokens[self.index].offset;
         let len = self.tokens[self.index].len;
-        &self.flat_text[offset..(offset + len)]
+        match &self.text {
+            TokenText::Flat(flat_text) => flat_text[offset..(offset + len)].to_vec(),
+            TokenText::Lines(lines) => (offset..(offset + len)).map( | i | lines.char_at(i)).collect()
+        }
     }
     
     pub fn next_char(&self) -> char {
         if self.index < self.tokens.len() - 1 {
             let len = self.tokens[self.index + 1].len;
-            let ch = self.flat_text[self.tokens[self.index + 1].offset];
+            let ch = self.text.char_at(self.tokens[self.index + 1].offset);
             if len == 1 || ch == ' ' {
                 return ch
             }
@@ -219,9 +252,9 @@ impl FormatOutput {
         self.out_lines.push(Vec::new());
     }
     
-    pub fn extend(&mut self, chunk: &[char]) {
+    pub fn extend(&mut self, chunk: Vec<char>) {
         let last_line = self.out_lines.last_mut().unwrap();
-        last_line.extend_from_slice(chunk);
+        last_line.extend(chunk);
     }
     
     pub fn add_space(&mut self) {
@@ -239,6 +272,7 @@ impl FormatOutput {
 }
 
 
+#[derive(Clone)]
 pub struct MprsTokenizer {
     pub comment_single: bool,
     pub comment_depth: usize,
@@ -256,8 +290,10 @@ impl MprsTokenizer {
             in_string_code: false
         }
     }
-    
-    pub fn next_token<'a>(&mut self, state: &mut TokenizerState<'a>, chunk: &mut Vec<char>, token_chunks: &Vec<TokenChunk>) -> TokenType {
+}
+
+impl Tokenizer for MprsTokenizer {
+    fn next_token<'a>(&mut self, state: &mut TokenizerState<'a>, chunk: &mut Vec<char>, token_chunks: &Vec<TokenChunk>) -> TokenType {
         let start = chunk.len();
         //chunk.truncate(0);
         if self.in_string {
@@ -670,7 +706,9 @@ impl MprsTokenizer {
             }
         }
     }
-    
+}
+
+impl MprsTokenizer {
     fn parse_rust_ident_tail<'a>(state: &mut TokenizerState<'a>, chunk: &mut Vec<char>) -> (bool, bool) {
         let mut ret = false;
         let mut has_underscores = false;
@@ -0,0 +1,163 @@
+use makepad_widget::*;
+
+pub struct PythonLanguage {}
+
+impl Language for PythonLanguage {
+    fn name(&self) -> &str {"python"}
+
+    fn new_tokenizer(&self) -> Box<dyn Tokenizer> {
+        Box::new(PythonTokenizer::new())
+    }
+
+    fn line_comment(&self) -> Option<&str> {Some("#")}
+
+    fn indent_rules(&self) -> IndentRules {
+        IndentRules {
+            indent_after: vec!['{', '(', '[', ':'],
+            ..IndentRules::default()
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct PythonTokenizer {
+    pub comment: bool,
+    pub multi_string: Option<char>,
+}
+
+impl PythonTokenizer {
+    pub fn new() -> PythonTokenizer {
+        PythonTokenizer {
+            comment: false,
+            multi_string: None
+        }
+    }
+
+    // the opening quote is already in the chunk
+    fn parse_string<'a>(&mut self, state: &mut TokenizerState<'a>, chunk: &mut Vec<char>, quote: char) -> TokenType {
+        if state.next == quote {
+            chunk.push(state.next);
+            state.advance();
+            if state.next == quote {
+                chunk.push(state.next);
+                state.advance();
+                self.multi_string = Some(quote);
+                return TokenType::StringMultiBegin
+            }
+            return TokenType::String
+        }
+        state.string_rest(chunk, quote, true);
+        TokenType::String
+    }
+
+    fn classify_word(word: &str, next: char) -> TokenType {
+        match word {
+            "if" | "elif" | "else" | "try" | "except" | "finally" | "raise" | "return" | "yield" | "break" | "continue"
+                | "pass" | "with" | "assert" => TokenType::Flow,
+            "for" | "while" => TokenType::Looping,
+            "def" | "lambda" => TokenType::Fn,
+            "class" => TokenType::TypeDef,
+            "True" | "False" => TokenType::Bool,
+            "import" | "from" | "as" | "global" | "nonlocal" | "del" | "in" | "is" | "not" | "and" | "or" | "async"
+                | "await" | "None" => TokenType::Keyword,
+            "self" | "cls" => TokenType::BuiltinType,
+            _ => if next == '(' {
+                TokenType::Call
+            }
+            else if word.chars().next().map_or(false, | c | c.is_ascii_uppercase()) {
+                TokenType::TypeName
+            }
+            else {
+                TokenType::Identifier
+            }
+        }
+    }
+}
+
+impl Tokenizer for PythonTokenizer {
+    fn next_token<'a>(&mut self, state: &mut TokenizerState<'a>, chunk: &mut Vec<char>, _token_chunks: &Vec<TokenChunk>) -> TokenType {
+        if self.comment {
+            if let Some(token_type) = state.comment_line_chunk(chunk) {
+                return token_type
+            }
+            self.comment = false;
+        }
+        if let Some(quote) = self.multi_string {
+            if let Some(token_type) = state.string_multi_chunk(chunk, quote, 3, true) {
+                if token_type == TokenType::StringMultiEnd {
+                    self.multi_string = None;
+                }
+                return token_type
+            }
+        }
+        state.advance_with_cur();
+        chunk.push(state.cur);
+        match state.cur {
+            '\0' => { // eof insert a terminating space and end
+                chunk.pop();
+                chunk.push(' ');
+                TokenType::Eof
+            },
+            '\n' => TokenType::Newline,
+            ' ' | '\t' => {
+                while state.next == ' ' || state.next == '\t' {
+                    chunk.push(state.next);
+                    state.advance();
+                }
+                TokenType::Whitespace
+            },
+            '#' => {
+                self.comment = true;
+                TokenType::CommentLine
+            },
+            '"' | '\'' => {
+                let quote = state.cur;
+                self.parse_string(state, chunk, quote)
+            },
+            '@' => {
+                // decorators
+                state.ident_rest(chunk, ".");
+                TokenType::Macro
+            },
+            '.' if state.next_is_digit() => {
+                state.number_rest(chunk);
+                TokenType::Number
+            },
+            '0'..='9' => {
+                state.number_rest(chunk);
+                TokenType::Number
+            },
+            '(' | '{' | '[' => TokenType::ParenOpen,
+            ')' | '}' | ']' => TokenType::ParenClose,
+            ',' | ';' => TokenType::Delimiter,
+            ':' => {
+                if state.next == '=' {
+                    chunk.push(state.next);
+                    state.advance();
+                    return TokenType::Operator
+                }
+                TokenType::Colon
+            },
+            'a'..='z' | 'A'..='Z' | '_' => {
+                let start = chunk.len() - 1;
+                state.ident_rest(chunk, "");
+                let word: String = chunk[start..].iter().collect();
+                // string prefixes like r"", b'', f"" and rb""
+                if (state.next == '"' || state.next == '\'') && word.len() <= 2 && word.chars().all( | c | "rRbBfFuU".contains(c)) {
+                    let quote = state.next;
+                    chunk.push(quote);
+                    state.advance();
+                    return self.parse_string(state, chunk, quote)
+                }
+                Self::classify_word(&word, state.next)
+            },
+            _ => {
+                while "=<>*/&|".contains(state.next) {
+                    chunk.push(state.next);
+                    state.advance();
+                }
+                TokenType::Operator
+            }
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use makepad_widget::*;
+
+pub struct RonLanguage {}
+
+impl Language for RonLanguage {
+    fn name(&self) -> &str {"ron"}
+
+    fn new_tokenizer(&self) -> Box<dyn Tokenizer> {
+        Box::new(RonTokenizer::new())
+    }
+
+    fn line_comment(&self) -> Option<&str> {Some("//")}
+}
+
+#[derive(Clone)]
+pub struct RonTokenizer {
+    pub comment: bool,
+    pub comment_depth: usize,
+}
+
+impl RonTokenizer {
+    pub fn new() -> RonTokenizer {
+        RonTokenizer {
+            comment: false,
+            comment_depth: 0
+        }
+    }
+
+    // r"..." and r#"..."#, the r is already in the chunk
+    fn parse_raw_string<'a>(state: &mut TokenizerState<'a>, chunk: &mut Vec<char>) {
+        let mut hashes = 0;
+        while state.next == '#' {
+            hashes += 1;
+            chunk.push(state.next);
+            state.advance();
+        }
+        if state.next != '"' {
+            return
+        }
+        chunk.push(state.next);
+        state.advance();
+        while state.next != '\0' && state.next != '\n' {
+            let is_quote = state.next == '"';
+            chunk.push(state.next);
+            state.advance();
+            if is_quote {
+                let mut closing = 0;
+                while closing < hashes && state.next == '#' {
+                    closing += 1;
+                    chunk.push(state.next);
+                    state.advance();
+                }
+                if closing == hashes {
+                    return
+                }
+            }
+        }
+    }
+}
+
+impl Tokenizer for RonTokenizer {
+    fn next_token<'a>(&mut self, state: &mut TokenizerState<'a>, chunk: &mut Vec<char>, _token_chunks: &Vec<TokenChunk>) -> TokenType {
+        if self.comment {
+            if let Some(token_type) = state.comment_line_chunk(chunk) {
+                return token_type
+            }
+            self.comment = false;
+        }
+        if self.comment_depth > 0 {
+            // block comments nest like in rust
+            if let Some(token_type) = state.comment_multi_chunk(chunk, &mut self.comment_depth, true) {
+                return token_type
+            }
+        }
+        state.advance_with_cur();
+        chunk.push(state.cur);
+        match state.cur {
+            '\0' => { // eof insert a terminating space and end
+                chunk.pop();
+                chunk.push(' ');
+                TokenType::Eof
+            },
+            '\n' => TokenType::Newline,
+            ' ' | '\t' => {
+                while state.next == ' ' || state.next == '\t' {
+                    chunk.push(state.next);
+                    state.advance();
+                }
+                TokenType::Whitespace
+            },
+            '/' => {
+                if state.next == '/' {
+                    chunk.push(state.next);
+                    state.advance();
+                    self.comment = true;
+                    return TokenType::CommentLine
+                }
+                if state.next == '*' {
+                    chunk.push(state.next);
+                    state.advance();
+                    self.comment_depth = 1;
+                    return TokenType::CommentMultiBegin
+                }
+                TokenType::Unexpected
+            },
+            '"' => {
+                state.string_rest(chunk, '"', true);
+                TokenType::String
+            },
+            '\'' => {
+                state.string_rest(chunk, '\'', true);
+                TokenType::String
+            },
+            'r' if state.next == '"' || state.next == '#' => {
+                Self::parse_raw_string(state, chunk);
+                TokenType::String
+            },
+            '#' => {
+                // #![enable(implicit_some)] style extensions
+                if state.next == '!' {
+                    chunk.push(state.next);
+                    state.advance();
+                }
+                TokenType::Hash
+            },
+            '-' | '+' if !state.next_is_digit() => TokenType::Operator,
+            '-' | '+' | '0'..='9' => {
+                state.number_rest(chunk);
+                TokenType::Number
+            },
+            '(' | '{' | '[' => TokenType::ParenOpen,
+            ')' | '}' | ']' => TokenType::ParenClose,
+            ':' => TokenType::Colon,
+            ',' => TokenType::Delimiter,
+            'a'..='z' | 'A'..='Z' | '_' => {
+                let start = chunk.len() - 1;
+                state.ident_rest(chunk, "");
+                let word: String = chunk[start..].iter().collect();
+                match word.as_ref() {
+                    "true" | "false" => TokenType::Bool,
+                    "Some" | "None" => TokenType::Keyword,
+                    _ => if state.cur.is_ascii_uppercase() {
+                        // struct names and enum variants
+                        TokenType::TypeName
+                    }
+                    else if state.next == '(' {
+                        TokenType::Call
+                    }
+                    else {
+                        TokenType::Identifier
+                    }
+                }
+            },
+            _ => TokenType::Operator
+        }
+    }
+}
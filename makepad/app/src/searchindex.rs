@@ -1,5 +1,7 @@
 use makepad_render::*;
 use std::collections::{HashMap};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
 use crate::appstorage::*;
 use makepad_widget::*;
 
@@ -19,19 +21,19 @@ impl SearchIndex {
         }
     }
     
-    pub fn new_rust_token(&mut self, atb: &AppTextBuffer) {
+    pub fn new_token(&mut self, text_buffer: &TextBuffer, text_buffer_id: AppTextBufferId) {
         // pass it to the textindex
-        if atb.text_buffer.token_chunks.len() <= 1 {
+        if text_buffer.token_chunks.len() <= 1 {
             return
         }
-        let chunk_id = atb.text_buffer.token_chunks.len() - 2;
+        let chunk_id = text_buffer.token_chunks.len() - 2;
         // lets figure out if its a decl, an impl or a use
-        match atb.text_buffer.token_chunks[chunk_id].token_type {
+        match text_buffer.token_chunks[chunk_id].token_type {
             TokenType::Identifier | TokenType::Call | TokenType::TypeName => {
                 let prev_tt = {
                     let mut i = if chunk_id > 0 {chunk_id - 1} else {0};
                     loop {
-                        let tt = atb.text_buffer.token_chunks[i].token_type;
+                        let tt = text_buffer.token_chunks[i].token_type;
                         if i == 0 || !tt.should_ignore() {
                             break tt;
                         }
@@ -41,22 +43,22 @@ impl SearchIndex {
                 let (next_tt, next_char) = {
                     let mut i = chunk_id + 1;
                     loop {
-                        if i >= atb.text_buffer.token_chunks.len() {
+                        if i >= text_buffer.token_chunks.len() {
                             break (TokenType::Unexpected, '\0');
                         }
-                        let tt = atb.text_buffer.token_chunks[i].token_type;
+                        let tt = text_buffer.token_chunks[i].token_type;
                         if !tt.should_ignore() {
-                            break (tt, atb.text_buffer.flat_text[atb.text_buffer.token_chunks[i].offset]);
+                            break (tt, text_buffer.lines.char_at(text_buffer.token_chunks[i].offset));
                         }
                         i = i + 1;
                     }
                 };
-                let offset = atb.text_buffer.token_chunks[chunk_id].offset;
-                let len = atb.text_buffer.token_chunks[chunk_id].len;
-                let chars = &atb.text_buffer.flat_text[offset..(offset + len)];
-                let mut_id = (atb.text_buffer.mutation_id & 0xffff) as u16;
+                let offset = text_buffer.token_chunks[chunk_id].offset;
+                let len = text_buffer.token_chunks[chunk_id].len;
+                let mut chars = Vec::new();
+                TextLinesReader::new(&text_buffer.lines).chars_into(offset, len, &mut chars);
                 
-                let prio = match atb.text_buffer.token_chunks[chunk_id].token_type {
+                let prio = match text_buffer.token_chunks[chunk_id].token_type {
                     TokenType::Identifier => {
                         match prev_tt {
                             TokenType::Keyword => 1,
@@ -90,10 +92,10 @@ impl SearchIndex {
                 };
 
                 self.identifiers.write(
-                    chars,
-                    atb.text_buffer_id,
-                    mut_id,
+                    &chars,
+                    text_buffer_id,
                     prio,
+                    text_buffer.token_chunks[chunk_id].token_type,
                     chunk_id as u32
                 );
             },
@@ -101,6 +103,19 @@ impl SearchIndex {
         }
     }
      
+    // identifiers from all indexed files, declarations rank higher
+    pub fn completions(&self, prefix: &str) -> Vec<CompletionItem> {
+        let prefix: Vec<char> = prefix.chars().collect();
+        let mut words = Vec::new();
+        self.identifiers.complete(&prefix, 200, &mut words);
+        words.into_iter().map( | (word, prio, token_type) | CompletionItem {
+            label: word,
+            detail: String::new(),
+            token_type: token_type,
+            score: if prio == 1 {4} else if prio <= 3 {2} else {0}
+        }).collect()
+    }
+    
     pub fn clear_markers(&mut self, cx: &mut Cx, storage: &mut AppStorage) {
         for atb in &mut storage.text_buffers {
             if atb.text_buffer.markers.search_cursors.len()>0 {
@@ -151,10 +166,19 @@ pub struct SearchResult {
     pub token: u32,
 }
 
-#[derive(Clone, Default)]
+// entries are checked against the token they point at, tokenizing only redoes the tokens after an edit
+// so tokens before it keep their index and stay valid
+#[derive(Clone)]
 pub struct TextIndexEntry {
-    mut_id: u16,
-    prio: u16
+    hash: u64,
+    prio: u16,
+    token_type: TokenType
+}
+
+fn hash_chars(chars: &[char]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    chars.hash(&mut hasher);
+    hasher.finish()
 }
 
 #[derive(Clone, Default)]
@@ -178,7 +202,7 @@ impl TextIndex {
         }
     }
     
-    pub fn write(&mut self, what: &[char], text_buffer_id: AppTextBufferId, mut_id: u16, prio: u16, token: u32) {
+    pub fn write(&mut self, what: &[char], text_buffer_id: AppTextBufferId, prio: u16, token_type: TokenType, token: u32) {
         let mut o = 0;
         let mut id = 0;
         loop {
@@ -236,15 +260,15 @@ impl TextIndex {
             };
         }
         
-        self.nodes[id].end.insert((text_buffer_id, token), TextIndexEntry {mut_id, prio});
+        self.nodes[id].end.insert((text_buffer_id, token), TextIndexEntry {hash: hash_chars(what), prio, token_type});
     }
     
-    pub fn _write_str(&mut self, what: &str, text_buffer_id: AppTextBufferId, mut_id: u16, prio: u16, token: u32) {
+    pub fn _write_str(&mut self, what: &str, text_buffer_id: AppTextBufferId, prio: u16, token_type: TokenType, token: u32) {
         let mut whatv = Vec::new();
         for c in what.chars() {
             whatv.push(c);
         }
-        self.write(&whatv, text_buffer_id, mut_id, prio, token);
+        self.write(&whatv, text_buffer_id, prio, token_type, token);
     }
     
     pub fn search(&mut self, what: &str, first_tbid:AppTextBufferId, storage: &mut AppStorage, out: &mut Vec<SearchResult>) {
@@ -291,7 +315,15 @@ impl TextIndex {
             cleanup.truncate(0);
             for ((text_buffer_id, token), entry) in &self.nodes[node_id].end {
                 let tb = &mut storage.text_buffers[text_buffer_id.as_index()].text_buffer;
-                if (tb.mutation_id & 0xffff) as u16 == entry.mut_id {
+                let valid = if let Some(tok) = tb.token_chunks.get(*token as usize) {
+                    let mut chars = Vec::new();
+                    TextLinesReader::new(&tb.lines).chars_into(tok.offset, tok.len, &mut chars);
+                    hash_chars(&chars) == entry.hash
+                }
+                else {
+                    false
+                };
+                if valid {
                     out.push(SearchResult {
                         text_buffer_id: *text_buffer_id,
                         token: *token,
@@ -330,6 +362,49 @@ impl TextIndex {
         }
     }
     
+    // every indexed word starting with prefix, with the best priority it was written with.
+    // entries are not checked against their tokens here, search cleans those up
+    pub fn complete(&self, prefix: &[char], max_words: usize, out: &mut Vec<(String, u16, TokenType)>) {
+        let mut node_id = 0;
+        let mut stem_eat = 0;
+        for c in prefix {
+            if stem_eat < self.nodes[node_id].used {
+                if *c != self.nodes[node_id].stem[stem_eat] {
+                    return
+                }
+                stem_eat += 1;
+            }
+            else {
+                node_id = if let Some(next_id) = self.nodes[node_id].map.get(c) {
+                    stem_eat = 0;
+                    *next_id
+                }
+                else {
+                    return
+                };
+            }
+        }
+        let mut word = prefix.to_vec();
+        word.extend(&self.nodes[node_id].stem[stem_eat..self.nodes[node_id].used]);
+        let mut stack = vec![(node_id, word)];
+        while let Some((node_id, word)) = stack.pop() {
+            let node = &self.nodes[node_id];
+            if let Some(entry) = node.end.values().min_by_key( | entry | entry.prio) {
+                out.push((word.iter().collect(), entry.prio, entry.token_type));
+                if out.len() >= max_words {
+                    return
+                }
+            }
+            for (key, next_id) in &node.map {
+                let next = &self.nodes[*next_id];
+                let mut next_word = word.clone();
+                next_word.push(*key);
+                next_word.extend(&next.stem[0..next.used]);
+                stack.push((*next_id, next_word));
+            }
+        }
+    }
+    
     pub fn _dump_tree(&self, key: char, id: usize, depth: usize) {
         let mut indent = String::new();
         for _ in 0..depth {indent.push_str(" - ");};
@@ -145,6 +145,8 @@ impl SearchResultDraw {
         self.text_editor.init_draw_state(cx, text_buffer);
         
         let mut first_ws = !selected;
+        let mut reader = TextLinesReader::new(&text_buffer.lines);
+        let mut chunk = Vec::new();
         for index in first_tok..last_tok {
             let token_chunk = &text_buffer.token_chunks[index];
             if first_ws && token_chunk.token_type == TokenType::Whitespace{
@@ -153,7 +155,8 @@ impl SearchResultDraw {
             else{
                 first_ws = false;
             }
-            self.text_editor.draw_chunk(cx, index, &text_buffer.flat_text, token_chunk, &text_buffer.markers);
+            reader.chars_into(token_chunk.offset, token_chunk.len, &mut chunk);
+            self.text_editor.draw_chunk(cx, index, &chunk, token_chunk, &text_buffer.markers);
         }
         
         self.text_editor.draw_search_markers(cx);
@@ -191,6 +194,7 @@ pub enum SearchResultEvent {
 
 impl SearchResults { 
     pub fn new(cx: &mut Cx) -> Self {
+        Self::default_keymap(cx);
         Self {
             first_tbid:AppTextBufferId(0),
             search_input: TextInput::new(cx, TextInputOptions{multiline:false,read_only:false, empty_message:"search".to_string()}),
@@ -271,6 +275,14 @@ impl SearchResults {
         return false
     }
     
+    pub fn default_keymap(cx: &mut Cx) {
+        let ctx = Some("search");
+        let km = &mut cx.keymap;
+        km.register_command("search.next_result", "Next Search Result", &["down"], ctx);
+        km.register_command("search.prev_result", "Previous Search Result", &["up"], ctx);
+        km.register_command("search.open_result", "Open Search Result", &["return"], ctx);
+    }
+    
     pub fn handle_search_results(&mut self, cx: &mut Cx, event: &mut Event, _search_index: &mut SearchIndex, storage: &mut AppStorage) -> SearchResultEvent {
         
         self.list.set_list_len(self.results.len());
@@ -283,17 +295,18 @@ impl SearchResults {
         // global key handle
         match event {
             Event::KeyDown(ke) => if self.search_input.text_editor.has_key_focus(cx) {
-                match ke.key_code {
-                    KeyCode::ArrowDown => {
+                let command = if let KeyMapMatch::Command(command) = cx.keymap.match_key_down(ke, "search") {command} else {String::new()};
+                match command.as_ref() {
+                    "search.next_result" => {
                         select = self.list.get_next_single_selection();
                         self.list.scroll_item_in_view = select.item_index();
                     },
-                    KeyCode::ArrowUp =>  {
+                    "search.prev_result" =>  {
                         // lets find the
                         select = self.list.get_prev_single_selection();
                         self.list.scroll_item_in_view = select.item_index();
                     },
-                    KeyCode::Return =>{
+                    "search.open_result" =>{
                         if self.list.selection.len()>0{
                             select = ListSelect::Single(self.list.selection[0]);
                             dblclick = true;
@@ -0,0 +1,169 @@
+use makepad_widget::*;
+use std::rc::Rc;
+
+pub struct ShaderLanguage {}
+
+impl Language for ShaderLanguage {
+    fn name(&self) -> &str {"shader"}
+
+    fn new_tokenizer(&self) -> Box<dyn Tokenizer> {
+        Box::new(ShaderTokenizer::new())
+    }
+
+    fn line_comment(&self) -> Option<&str> {Some("//")}
+
+    fn completion_provider(&self) -> Option<Rc<dyn CompletionProvider>> {
+        let mut words = Vec::new();
+        for word in &["fn", "let", "var", "struct", "return", "if", "else", "for", "while", "break", "continue", "discard",
+            "uniform", "instance", "geometry", "texture", "const", "true", "false"] {
+            words.push((*word, TokenType::Keyword));
+        }
+        for word in &["float", "int", "bool", "vec2", "vec3", "vec4", "ivec2", "ivec3", "ivec4", "bvec2", "bvec3", "bvec4",
+            "mat2", "mat3", "mat4", "sampler2D"] {
+            words.push((*word, TokenType::BuiltinType));
+        }
+        for word in &["abs", "sign", "floor", "ceil", "fract", "mod", "min", "max", "clamp", "mix", "step", "smoothstep",
+            "length", "distance", "dot", "cross", "normalize", "reflect", "refract", "sin", "cos", "tan", "asin", "acos",
+            "atan", "pow", "exp", "log", "exp2", "log2", "sqrt", "inversesqrt", "radians", "degrees", "sample2d", "dFdx", "dFdy"] {
+            words.push((*word, TokenType::Call));
+        }
+        Some(Rc::new(WordListProvider {words: words, detail: "shader"}))
+    }
+}
+
+// GLSL and the makepad shader language, which adds fn, let and the instance/geometry/texture qualifiers
+#[derive(Clone)]
+pub struct ShaderTokenizer {
+    pub comment: bool,
+    pub comment_depth: usize,
+}
+
+impl ShaderTokenizer {
+    pub fn new() -> ShaderTokenizer {
+        ShaderTokenizer {
+            comment: false,
+            comment_depth: 0
+        }
+    }
+
+    fn classify_word(word: &str, next: char) -> TokenType {
+        match word {
+            "if" | "else" | "return" | "break" | "continue" | "discard" | "switch" | "case" | "default" => TokenType::Flow,
+            "for" | "while" | "do" => TokenType::Looping,
+            "fn" => TokenType::Fn,
+            "struct" => TokenType::TypeDef,
+            "true" | "false" => TokenType::Bool,
+            "uniform" | "attribute" | "varying" | "in" | "out" | "inout" | "const" | "precision" | "highp" | "mediump" | "lowp"
+                | "layout" | "flat" | "smooth" | "invariant" | "let" | "var" | "instance" | "geometry" | "texture" => TokenType::Keyword,
+            "void" | "bool" | "int" | "uint" | "float" | "double" => TokenType::BuiltinType,
+            _ => if Self::is_builtin_type(word) {
+                TokenType::BuiltinType
+            }
+            else if next == '(' {
+                TokenType::Call
+            }
+            else if word.chars().next().map_or(false, | c | c.is_ascii_uppercase()) {
+                TokenType::TypeName
+            }
+            else {
+                TokenType::Identifier
+            }
+        }
+    }
+
+    // vec2..4, ivec/uvec/bvec/dvec, mat2..4 and matNxM, and the sampler types
+    fn is_builtin_type(word: &str) -> bool {
+        let sized = | prefix: &str | word.starts_with(prefix) && word[prefix.len()..].chars().next().map_or(false, | c | c >= '2' && c <= '4');
+        sized("vec") && word.len() == 4
+            || (sized("ivec") || sized("uvec") || sized("bvec") || sized("dvec")) && word.len() == 5
+            || sized("mat") && (word.len() == 4 || word.len() == 6 && word.as_bytes()[4] == b'x')
+            || word.starts_with("sampler")
+    }
+}
+
+impl Tokenizer for ShaderTokenizer {
+    fn next_token<'a>(&mut self, state: &mut TokenizerState<'a>, chunk: &mut Vec<char>, _token_chunks: &Vec<TokenChunk>) -> TokenType {
+        if self.comment {
+            if let Some(token_type) = state.comment_line_chunk(chunk) {
+                return token_type
+            }
+            self.comment = false;
+        }
+        if self.comment_depth > 0 {
+            if let Some(token_type) = state.comment_multi_chunk(chunk, &mut self.comment_depth, false) {
+                return token_type
+            }
+        }
+        state.advance_with_cur();
+        chunk.push(state.cur);
+        match state.cur {
+            '\0' => { // eof insert a terminating space and end
+                chunk.pop();
+                chunk.push(' ');
+                TokenType::Eof
+            },
+            '\n' => TokenType::Newline,
+            ' ' | '\t' => {
+                while state.next == ' ' || state.next == '\t' {
+                    chunk.push(state.next);
+                    state.advance();
+                }
+                TokenType::Whitespace
+            },
+            '/' => {
+                if state.next == '/' {
+                    chunk.push(state.next);
+                    state.advance();
+                    self.comment = true;
+                    return TokenType::CommentLine
+                }
+                if state.next == '*' {
+                    chunk.push(state.next);
+                    state.advance();
+                    self.comment_depth = 1;
+                    return TokenType::CommentMultiBegin
+                }
+                if state.next == '=' {
+                    chunk.push(state.next);
+                    state.advance();
+                }
+                TokenType::Operator
+            },
+            '#' => {
+                // preprocessor directives
+                state.ident_rest(chunk, "");
+                TokenType::Hash
+            },
+            '"' => {
+                state.string_rest(chunk, '"', true);
+                TokenType::String
+            },
+            '.' if state.next_is_digit() => {
+                state.number_rest(chunk);
+                TokenType::Number
+            },
+            '0'..='9' => {
+                state.number_rest(chunk);
+                TokenType::Number
+            },
+            '(' | '{' | '[' => TokenType::ParenOpen,
+            ')' | '}' | ']' => TokenType::ParenClose,
+            ';' | ',' => TokenType::Delimiter,
+            ':' => TokenType::Colon,
+            '+' | '-' | '*' | '%' | '=' | '!' | '<' | '>' | '&' | '|' | '^' | '~' | '?' | '.' => {
+                while "=<>&|+-".contains(state.next) {
+                    chunk.push(state.next);
+                    state.advance();
+                }
+                TokenType::Operator
+            },
+            'a'..='z' | 'A'..='Z' | '_' => {
+                let start = chunk.len() - 1;
+                state.ident_rest(chunk, "");
+                let word: String = chunk[start..].iter().collect();
+                Self::classify_word(&word, state.next)
+            },
+            _ => TokenType::Unexpected
+        }
+    }
+}
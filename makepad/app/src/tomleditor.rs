@@ -0,0 +1,134 @@
+use makepad_widget::*;
+
+pub struct TomlLanguage {}
+
+impl Language for TomlLanguage {
+    fn name(&self) -> &str {"toml"}
+
+    fn new_tokenizer(&self) -> Box<dyn Tokenizer> {
+        Box::new(TomlTokenizer::new())
+    }
+
+    fn line_comment(&self) -> Option<&str> {Some("#")}
+}
+
+#[derive(Clone)]
+pub struct TomlTokenizer {
+    pub comment: bool,
+    pub multi_string: Option<char>,
+    pub line_begin: bool,
+    pub in_header: bool,
+}
+
+impl TomlTokenizer {
+    pub fn new() -> TomlTokenizer {
+        TomlTokenizer {
+            comment: false,
+            multi_string: None,
+            line_begin: true,
+            in_header: false,
+        }
+    }
+}
+
+impl Tokenizer for TomlTokenizer {
+    fn next_token<'a>(&mut self, state: &mut TokenizerState<'a>, chunk: &mut Vec<char>, _token_chunks: &Vec<TokenChunk>) -> TokenType {
+        if self.comment {
+            if let Some(token_type) = state.comment_line_chunk(chunk) {
+                return token_type
+            }
+            self.comment = false;
+        }
+        if let Some(end_char) = self.multi_string {
+            // literal strings (''') have no escapes
+            if let Some(token_type) = state.string_multi_chunk(chunk, end_char, 3, end_char == '"') {
+                if token_type == TokenType::StringMultiEnd {
+                    self.multi_string = None;
+                }
+                return token_type
+            }
+        }
+        state.advance_with_cur();
+        match state.cur {
+            '\0' => { // eof insert a terminating space and end
+                chunk.push(' ');
+                return TokenType::Eof
+            },
+            '\n' => {
+                self.line_begin = true;
+                self.in_header = false;
+                chunk.push('\n');
+                return TokenType::Newline
+            },
+            ' ' | '\t' => {
+                chunk.push(state.cur);
+                while state.next == ' ' || state.next == '\t' {
+                    chunk.push(state.next);
+                    state.advance();
+                }
+                return TokenType::Whitespace
+            },
+            _ => ()
+        }
+        let line_begin = self.line_begin;
+        self.line_begin = false;
+        chunk.push(state.cur);
+        match state.cur {
+            '#' => {
+                self.comment = true;
+                TokenType::CommentLine
+            },
+            '"' | '\'' => {
+                let quote = state.cur;
+                if state.next == quote {
+                    chunk.push(state.next);
+                    state.advance();
+                    if state.next == quote {
+                        chunk.push(state.next);
+                        state.advance();
+                        self.multi_string = Some(quote);
+                        return TokenType::StringMultiBegin
+                    }
+                    return TokenType::String
+                }
+                state.string_rest(chunk, quote, quote == '"');
+                TokenType::String
+            },
+            '[' => {
+                // [table] and [[array.of.tables]] headers start a line
+                if line_begin {
+                    self.in_header = true;
+                }
+                TokenType::ParenOpen
+            },
+            '{' => TokenType::ParenOpen,
+            ']' | '}' => TokenType::ParenClose,
+            '=' => TokenType::Operator,
+            '.' => TokenType::Operator,
+            ',' => TokenType::Delimiter,
+            '+' | '-' if !state.next_is_digit() => TokenType::Operator,
+            '0'..='9' | '+' | '-' => {
+                // also eats dates and times like 1979-05-27T07:32:00Z
+                while state.next_is_letter() || state.next_is_digit() || "_.:-+".contains(state.next) {
+                    chunk.push(state.next);
+                    state.advance();
+                }
+                TokenType::Number
+            },
+            'a'..='z' | 'A'..='Z' | '_' => {
+                let start = chunk.len() - 1;
+                state.ident_rest(chunk, "-");
+                if self.in_header {
+                    return TokenType::TypeName
+                }
+                let word: String = chunk[start..].iter().collect();
+                match word.as_ref() {
+                    "true" | "false" => TokenType::Bool,
+                    "inf" | "nan" => TokenType::Number,
+                    _ => TokenType::Identifier
+                }
+            },
+            _ => TokenType::Operator
+        }
+    }
+}
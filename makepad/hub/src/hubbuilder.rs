@@ -415,6 +415,10 @@ impl HubBuilder {
                 ws.file_read(htc.from, uid, &path);
                 Ok(())
             },
+            HubMsg::GitHeadReadRequest {uid, path} => {
+                ws.git_head_read(htc.from, uid, &path);
+                Ok(())
+            },
             HubMsg::FileWriteRequest {uid, path, data} => {
                 ws.file_write(htc.from, uid, &path, data);
                 Ok(())
@@ -971,6 +975,36 @@ impl HubBuilder {
         }
     }
     
+    pub fn git_head_read(&mut self, from: HubAddr, uid: HubUid, path: &str) {
+        if let Ok((abs_dir, _workspace, sub_path)) = self.workspace_split_from_path(uid, path) {
+            
+            if let Some(_) = sub_path.find("..") {
+                self.error(uid, format!("git_head_read got relative path, ignoring {}", path));
+                return
+            }
+            
+            // ./ makes git resolve the path from the workspace dir instead of the repo root
+            let output = std::process::Command::new("git")
+                .args(&["show", &format!("HEAD:./{}", sub_path)])
+                .current_dir(&abs_dir)
+                .output();
+            
+            let data = match output {
+                Ok(output) if output.status.success() => Some(output.stdout),
+                _ => None
+            };
+            
+            self.route_send.send(ToHubMsg {
+                to: HubMsgTo::Client(from),
+                msg: HubMsg::GitHeadReadResponse {
+                    uid: uid,
+                    path: path.to_string(),
+                    data: data
+                }
+            });
+        }
+    }
+    
     pub fn file_write(&mut self, from: HubAddr, uid: HubUid, path: &str, data: Vec<u8>) {
         if let Ok((abs_dir, _project, sub_path)) = self.workspace_split_from_path(uid, path) {
             
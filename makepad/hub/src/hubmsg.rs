@@ -120,6 +120,18 @@ pub enum HubMsg {
         data: Option<Vec<u8>>
     },
     
+    // the file as it is in git HEAD, data is None when it isn't tracked
+    GitHeadReadRequest {
+        uid: HubUid,
+        path: String
+    },
+    
+    GitHeadReadResponse {
+        uid: HubUid,
+        path: String,
+        data: Option<Vec<u8>>
+    },
+    
     FileWriteRequest {
         uid: HubUid,
         path: String,
@@ -131,6 +143,31 @@ pub enum HubMsg {
         path: String,
         done: bool
     },
+    
+    // collaborative editing, these go to every UI so each one sees them in the same order.
+    // the change sets and states are serialized by the editor, the hub only passes them on
+    CollabJoin {
+        uid: HubUid,
+        path: String
+    },
+    
+    CollabState {
+        uid: HubUid,
+        path: String,
+        state: Vec<u8>
+    },
+    
+    CollabChange {
+        path: String,
+        rev: u64,
+        change: Vec<u8>
+    },
+    
+    CollabCursors {
+        path: String,
+        rev: u64,
+        cursors: Vec<(u64, u64)>
+    },
 }
 
 impl HubMsg{
@@ -48,7 +48,8 @@ macro_rules! impl_ser_de_bin_for {
                     return Err(DeBinErr{o:*o, l:l, s:d.len()})
                 } 
                 let mut m = [0 as $ty];
-                unsafe {std::ptr::copy_nonoverlapping(d.as_ptr().offset(*o as isize) as *const $ty, m.as_mut_ptr() as *mut $ty, 1)}
+                // the data is not aligned, copy it as bytes
+                unsafe {std::ptr::copy_nonoverlapping(d.as_ptr().offset(*o as isize), m.as_mut_ptr() as *mut u8, l)}
                 *o += l;
                 Ok(m[0])
             }
@@ -80,7 +81,7 @@ impl DeBin for usize {
             return Err(DeBinErr{o:*o, l:l, s:d.len()})
         } 
         let mut m = [0 as u64];
-        unsafe {std::ptr::copy_nonoverlapping(d.as_ptr().offset(*o as isize) as *const u64, m.as_mut_ptr() as *mut u64, 1)}
+        unsafe {std::ptr::copy_nonoverlapping(d.as_ptr().offset(*o as isize), m.as_mut_ptr() as *mut u8, l)}
         *o += l;
         Ok(m[0] as usize)
     }
@@ -120,6 +121,25 @@ impl DeBin for bool {
     }
 }
 
+impl SerBin for char {
+    fn ser_bin(&self, s: &mut Vec<u8>) {
+        (*self as u32).ser_bin(s);
+    }
+}
+
+impl DeBin for char {
+    fn de_bin(o:&mut usize, d:&[u8]) -> Result<char, DeBinErr> {
+        let l = std::mem::size_of::<u32>();
+        let m:u32 = DeBin::de_bin(o,d)?;
+        if let Some(ch) = std::char::from_u32(m){
+            Ok(ch)
+        }
+        else{
+            Err(DeBinErr{o:*o - l, l:l, s:d.len()})
+        }
+    }
+}
+
 impl SerBin for String {
     fn ser_bin(&self, s: &mut Vec<u8>) {
         let len = self.len();
@@ -0,0 +1,474 @@
+use crate::cx::*;
+use makepad_microserde::*;
+use std::collections::HashSet;
+
+// Accessibility
+// Everything is drawn with shaders, what a screen reader can know about the ui comes from here.
+// Widgets add a node for what they draw with the area they drew it in, containers begin and end
+// one around their children. Like the draw calls the nodes live in the view they were added in,
+// a view that is not drawn again keeps them. a11y_tree builds the tree of a window from its views
+// in draw order, a platform bridge or a test reads it there and sends actions back as events.
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum A11yRole {
+    Window,
+    Group,
+    Label,
+    Button,
+    CheckBox,
+    Switch,
+    RadioButton,
+    ComboBox,
+    Slider,
+    SpinButton,
+    TextInput,
+    TabList,
+    Tab,
+    List,
+    ListItem,
+    Tree,
+    TreeItem,
+    Table,
+    Row,
+    ColumnHeader,
+    Menu,
+    MenuItem,
+    Tooltip,
+}
+
+impl Default for A11yRole {
+    fn default() -> Self {
+        A11yRole::Group
+    }
+}
+
+impl A11yRole {
+    pub fn name(&self) -> &'static str {
+        match self {
+            A11yRole::Window => "window",
+            A11yRole::Group => "group",
+            A11yRole::Label => "label",
+            A11yRole::Button => "button",
+            A11yRole::CheckBox => "check_box",
+            A11yRole::Switch => "switch",
+            A11yRole::RadioButton => "radio_button",
+            A11yRole::ComboBox => "combo_box",
+            A11yRole::Slider => "slider",
+            A11yRole::SpinButton => "spin_button",
+            A11yRole::TextInput => "text_input",
+            A11yRole::TabList => "tab_list",
+            A11yRole::Tab => "tab",
+            A11yRole::List => "list",
+            A11yRole::ListItem => "list_item",
+            A11yRole::Tree => "tree",
+            A11yRole::TreeItem => "tree_item",
+            A11yRole::Table => "table",
+            A11yRole::Row => "row",
+            A11yRole::ColumnHeader => "column_header",
+            A11yRole::Menu => "menu",
+            A11yRole::MenuItem => "menu_item",
+            A11yRole::Tooltip => "tooltip",
+        }
+    }
+}
+
+impl SerJson for A11yRole {
+    fn ser_json(&self, d: usize, s: &mut SerJsonState) {
+        self.name().to_string().ser_json(d, s)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum A11yAction {
+    Press,
+    Focus,
+    Select,
+    Increment,
+    Decrement,
+    Expand,
+    Collapse,
+}
+
+impl A11yAction {
+    pub fn name(&self) -> &'static str {
+        match self {
+            A11yAction::Press => "press",
+            A11yAction::Focus => "focus",
+            A11yAction::Select => "select",
+            A11yAction::Increment => "increment",
+            A11yAction::Decrement => "decrement",
+            A11yAction::Expand => "expand",
+            A11yAction::Collapse => "collapse",
+        }
+    }
+}
+
+impl SerJson for A11yAction {
+    fn ser_json(&self, d: usize, s: &mut SerJsonState) {
+        self.name().to_string().ser_json(d, s)
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct A11yNode {
+    pub role: A11yRole,
+    pub name: String,
+    pub value: String,
+    pub area: Area,
+    pub actions: Vec<A11yAction>,
+    pub checked: Option<bool>,
+    pub expanded: Option<bool>,
+    pub selected: bool,
+    pub disabled: bool,
+}
+
+impl A11yNode {
+    pub fn new(role: A11yRole, name: &str, area: Area) -> Self {
+        Self {
+            role: role,
+            name: name.to_string(),
+            area: area,
+            ..Self::default()
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum CxA11yEntry {
+    Node(A11yNode),
+    Begin(A11yNode),
+    End
+}
+
+// a node as it is in the tree of a window, the id stays the same while it is drawn at the same place
+#[derive(Clone, Debug, Default)]
+pub struct A11yTreeNode {
+    pub id: u64,
+    pub role: A11yRole,
+    pub name: String,
+    pub value: String,
+    pub area: Area,
+    pub rect: Rect,
+    pub actions: Vec<A11yAction>,
+    pub checked: Option<bool>,
+    pub expanded: Option<bool>,
+    pub selected: bool,
+    pub disabled: bool,
+    pub focused: bool,
+    pub children: Vec<A11yTreeNode>,
+}
+
+impl A11yTreeNode {
+    pub fn find(&self, id: u64) -> Option<&A11yTreeNode> {
+        if self.id == id {
+            return Some(self)
+        }
+        for child in &self.children {
+            if let Some(node) = child.find(id) {
+                return Some(node)
+            }
+        }
+        None
+    }
+
+    pub fn find_focused(&self) -> Option<&A11yTreeNode> {
+        if self.focused {
+            return Some(self)
+        }
+        for child in &self.children {
+            if let Some(node) = child.find_focused() {
+                return Some(node)
+            }
+        }
+        None
+    }
+}
+
+// the area is not for outside eyes
+impl SerJson for A11yTreeNode {
+    fn ser_json(&self, d: usize, s: &mut SerJsonState) {
+        s.st_pre();
+        s.field(d + 1, "id");
+        self.id.ser_json(d + 1, s);
+        s.conl();
+        s.field(d + 1, "role");
+        self.role.ser_json(d + 1, s);
+        s.conl();
+        s.field(d + 1, "name");
+        self.name.ser_json(d + 1, s);
+        s.conl();
+        s.field(d + 1, "value");
+        self.value.ser_json(d + 1, s);
+        s.conl();
+        s.field(d + 1, "rect");
+        (self.rect.x, self.rect.y, self.rect.w, self.rect.h).ser_json(d + 1, s);
+        s.conl();
+        s.field(d + 1, "actions");
+        self.actions.ser_json(d + 1, s);
+        s.conl();
+        if let Some(checked) = self.checked {
+            s.field(d + 1, "checked");
+            checked.ser_json(d + 1, s);
+            s.conl();
+        }
+        if let Some(expanded) = self.expanded {
+            s.field(d + 1, "expanded");
+            expanded.ser_json(d + 1, s);
+            s.conl();
+        }
+        s.field(d + 1, "selected");
+        self.selected.ser_json(d + 1, s);
+        s.conl();
+        s.field(d + 1, "disabled");
+        self.disabled.ser_json(d + 1, s);
+        s.conl();
+        s.field(d + 1, "focused");
+        self.focused.ser_json(d + 1, s);
+        s.conl();
+        s.field(d + 1, "children");
+        self.children.ser_json(d + 1, s);
+        s.conl();
+        s.st_post(d);
+    }
+}
+
+impl Cx {
+    fn push_a11y_entry(&mut self, entry: CxA11yEntry) {
+        if let Some(view_id) = self.view_stack.last() {
+            let cxview = &mut self.views[*view_id];
+            cxview.a11y.push((cxview.draw_calls_len, entry));
+        }
+    }
+
+    pub fn add_a11y_node(&mut self, node: A11yNode) {
+        self.push_a11y_entry(CxA11yEntry::Node(node));
+    }
+
+    // the nodes added until end_a11y_node are its children, both go in the same view
+    pub fn begin_a11y_node(&mut self, node: A11yNode) {
+        self.push_a11y_entry(CxA11yEntry::Begin(node));
+    }
+
+    pub fn end_a11y_node(&mut self) {
+        self.push_a11y_entry(CxA11yEntry::End);
+    }
+
+    // the entries of a view and its sub views in draw order
+    fn collect_a11y<'a>(&'a self, view_id: usize, seen: &mut Vec<usize>, entries: &mut Vec<&'a CxA11yEntry>) {
+        if seen.contains(&view_id) {
+            return
+        }
+        seen.push(view_id);
+        let cxview = &self.views[view_id];
+        let mut next = 0;
+        for draw_call_id in 0..cxview.draw_calls_len {
+            let sub_view_id = cxview.draw_calls[draw_call_id].sub_view_id;
+            if sub_view_id != 0 {
+                while next < cxview.a11y.len() && cxview.a11y[next].0 <= draw_call_id {
+                    entries.push(&cxview.a11y[next].1);
+                    next += 1;
+                }
+                self.collect_a11y(sub_view_id, seen, entries);
+            }
+        }
+        for (_, entry) in &cxview.a11y[next..] {
+            entries.push(entry);
+        }
+    }
+
+    fn a11y_tree_node(&self, node: &A11yNode, ids: &mut HashSet<u64>) -> A11yTreeNode {
+        let mut id = match node.area {
+            Area::Instance(inst) => ((inst.view_id as u64) << 48) ^ ((inst.draw_call_id as u64) << 32) ^ (inst.instance_offset as u64),
+            Area::View(view_area) => ((view_area.view_id as u64) << 48) ^ 0xffff_ffff,
+            _ => 1
+        };
+        // the window is 0, and containers can share the area of a child
+        while id == 0 || !ids.insert(id) {
+            id = id.wrapping_add(1);
+        }
+        A11yTreeNode {
+            id: id,
+            role: node.role,
+            name: node.name.clone(),
+            value: node.value.clone(),
+            area: node.area,
+            rect: if node.area.is_valid(self) {node.area.get_rect(self)} else {Rect::default()},
+            actions: node.actions.clone(),
+            checked: node.checked,
+            expanded: node.expanded,
+            selected: node.selected,
+            disabled: node.disabled,
+            focused: node.area != Area::Empty && node.area == self.key_focus,
+            children: Vec::new()
+        }
+    }
+
+    // the tree of a window, the window itself is the root with id 0
+    pub fn a11y_tree(&self, window_id: usize) -> A11yTreeNode {
+        let cxwindow = &self.windows[window_id];
+        let mut stack = vec![A11yTreeNode {
+            id: 0,
+            role: A11yRole::Window,
+            name: cxwindow.window_title.clone(),
+            rect: Rect {x: 0., y: 0., w: cxwindow.window_geom.inner_size.x, h: cxwindow.window_geom.inner_size.y},
+            ..A11yTreeNode::default()
+        }];
+        let mut entries = Vec::new();
+        let mut seen = Vec::new();
+        if let Some(pass_id) = cxwindow.main_pass_id {
+            if let Some(main_view_id) = self.passes[pass_id].main_view_id {
+                self.collect_a11y(main_view_id, &mut seen, &mut entries);
+            }
+            for view_id in &self.passes[pass_id].overlay_view_ids {
+                self.collect_a11y(*view_id, &mut seen, &mut entries);
+            }
+        }
+        let mut ids = HashSet::new();
+        for entry in entries {
+            match entry {
+                CxA11yEntry::Node(node) => {
+                    let tree_node = self.a11y_tree_node(node, &mut ids);
+                    stack.last_mut().unwrap().children.push(tree_node);
+                },
+                CxA11yEntry::Begin(node) => {
+                    let tree_node = self.a11y_tree_node(node, &mut ids);
+                    stack.push(tree_node);
+                },
+                CxA11yEntry::End => if stack.len() > 1 {
+                    let tree_node = stack.pop().unwrap();
+                    stack.last_mut().unwrap().children.push(tree_node);
+                }
+            }
+        }
+        // a begin without an end
+        while stack.len() > 1 {
+            let tree_node = stack.pop().unwrap();
+            stack.last_mut().unwrap().children.push(tree_node);
+        }
+        stack.pop().unwrap()
+    }
+
+    pub fn status_a11y_action() -> StatusId {uid!()}
+
+    pub fn a11y_tree_json(&self, window_id: usize) -> String {
+        self.a11y_tree(window_id).serialize_json()
+    }
+
+    // what a platform does with an action of a node, the widget gets an A11yAction event for its area
+    pub fn call_a11y_action<F>(&mut self, event_handler: &mut F, area: Area, action: A11yAction)
+    where F: FnMut(&mut Cx, &mut Event)
+    {
+        if !area.is_valid(self) {
+            return
+        }
+        if action == A11yAction::Focus {
+            self.set_key_focus(area);
+        }
+        self.call_event_handler(event_handler, &mut Event::A11yAction(A11yActionEvent {area: area, action: action}));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a window drawn like widgets draw, a group with a check box and a list in a view of its own,
+    // a button after the list and a tooltip in an overlay
+    fn draw_tree(cx: &mut Cx) -> usize {
+        let mut window = Window {create_title: "Test".to_string(), ..Window::new(cx)};
+        let mut pass = Pass::default();
+        let mut main_view = View::new(cx);
+        let mut list_view = View::new(cx);
+        let mut tooltip_view = View::proto_overlay(cx);
+        cx.call_draw_event( | cx, _ | {
+            window.begin_window(cx);
+            pass.begin_pass(cx);
+            main_view.begin_view(cx, Layout::default()).unwrap();
+            cx.begin_a11y_node(A11yNode::new(A11yRole::Group, "settings", main_view.get_view_area(cx)));
+            cx.add_a11y_node(A11yNode {
+                checked: Some(true),
+                actions: vec![A11yAction::Press],
+                ..A11yNode::new(A11yRole::CheckBox, "Wrap lines", Area::Empty)
+            });
+            list_view.begin_view(cx, Layout::default()).unwrap();
+            cx.begin_a11y_node(A11yNode::new(A11yRole::List, "files", list_view.get_view_area(cx)));
+            cx.add_a11y_node(A11yNode {
+                selected: true,
+                ..A11yNode::new(A11yRole::ListItem, "a.rs", Area::Empty)
+            });
+            cx.add_a11y_node(A11yNode::new(A11yRole::ListItem, "b.rs", Area::Empty));
+            cx.end_a11y_node();
+            list_view.end_view(cx);
+            cx.add_a11y_node(A11yNode::new(A11yRole::Button, "OK", Area::Empty));
+            cx.end_a11y_node();
+            tooltip_view.begin_view(cx, Layout::default()).unwrap();
+            cx.add_a11y_node(A11yNode::new(A11yRole::Tooltip, "hint", Area::Empty));
+            tooltip_view.end_view(cx);
+            main_view.end_view(cx);
+            pass.end_pass(cx);
+            window.end_window(cx);
+        });
+        window.window_id.unwrap()
+    }
+
+    fn names(node: &A11yTreeNode) -> Vec<(&'static str, String)> {
+        node.children.iter().map( | child | (child.role.name(), child.name.clone())).collect()
+    }
+
+    #[test]
+    fn tree_nests_in_draw_order() {
+        let mut cx = Cx::default();
+        let window_id = draw_tree(&mut cx);
+        let tree = cx.a11y_tree(window_id);
+        assert_eq!(tree.id, 0);
+        assert_eq!(tree.role, A11yRole::Window);
+        assert_eq!(tree.name, "Test");
+        assert_eq!(names(&tree), vec![("group", "settings".to_string()), ("tooltip", "hint".to_string())]);
+        let group = &tree.children[0];
+        assert_eq!(names(group), vec![
+            ("check_box", "Wrap lines".to_string()),
+            ("list", "files".to_string()),
+            ("button", "OK".to_string())
+        ]);
+        let list = &group.children[1];
+        assert_eq!(names(list), vec![("list_item", "a.rs".to_string()), ("list_item", "b.rs".to_string())]);
+        assert_eq!(group.children[0].checked, Some(true));
+        assert_eq!(group.children[2].checked, None);
+        assert!(list.children[0].selected);
+        assert!(!list.children[1].selected);
+    }
+
+    #[test]
+    fn ids_are_unique() {
+        let mut cx = Cx::default();
+        let window_id = draw_tree(&mut cx);
+        fn collect(node: &A11yTreeNode, ids: &mut Vec<u64>) {
+            ids.push(node.id);
+            for child in &node.children {
+                collect(child, ids);
+            }
+        }
+        let mut ids = Vec::new();
+        collect(&cx.a11y_tree(window_id), &mut ids);
+        assert_eq!(ids.len(), 8);
+        let unique: HashSet<u64> = ids.iter().cloned().collect();
+        assert_eq!(unique.len(), ids.len());
+    }
+
+    #[test]
+    fn json_has_roles_names_and_states() {
+        let mut cx = Cx::default();
+        let window_id = draw_tree(&mut cx);
+        let json: String = cx.a11y_tree_json(window_id).lines().map( | line | line.trim()).collect();
+        assert!(json.starts_with("{\"id\":0,\"role\":\"window\",\"name\":\"Test\","));
+        assert!(json.contains("\"role\":\"check_box\",\"name\":\"Wrap lines\",\"value\":\"\",\"rect\":[0,0,0,0],\"actions\":[\"press\"],\"checked\":true,\"selected\":false,"));
+        assert!(json.contains("\"role\":\"list_item\",\"name\":\"a.rs\",\"value\":\"\",\"rect\":[0,0,0,0],\"actions\":[],\"selected\":true,"));
+        // a node that is not checkable has no checked field
+        assert!(json.contains("\"role\":\"button\",\"name\":\"OK\",\"value\":\"\",\"rect\":[0,0,0,0],\"actions\":[],\"selected\":false,"));
+        // children come in draw order, after their parent
+        let order: Vec<usize> = ["settings", "Wrap lines", "files", "a.rs", "b.rs", "OK", "hint"].iter().map( | name | {
+            json.find(&format!("\"name\":\"{}\"", name)).unwrap()
+        }).collect();
+        assert!(order.windows(2).all( | pair | pair[0] < pair[1]));
+    }
+}
@@ -22,6 +22,10 @@ pub use crate::events::*;
 pub use crate::animator::*;
 pub use crate::area::*;
 pub use crate::menu::*;
+pub use crate::popup::*;
+pub use crate::keymap::*;
+pub use crate::tooltips::*;
+pub use crate::accessibility::*;
 pub use crate::styling::*;
 pub use crate::liveclient::*;
 pub use crate::shader::*;
@@ -158,6 +162,11 @@ pub struct Cx {
     pub style_stack: Vec<usize>,
     
     pub command_settings: HashMap<CommandId, CxCommandSetting>,
+    // commands sent by the ui, they go out as Event::Command like the ones from a native menu
+    pub commands: Vec<CommandId>,
+    pub keymap: KeyMap,
+    
+    pub tooltips: Vec<CxTooltip>,
     
     pub panic_now: bool,
     pub panic_redraw: bool,
@@ -167,13 +176,19 @@ pub struct Cx {
     pub platform: CxPlatform,
 }
 
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Copy)]
 pub struct CxCommandSetting {
     pub shift: bool,
     pub key_code: KeyCode,
     pub enabled: bool
 }
 
+impl Default for CxCommandSetting {
+    fn default() -> Self {
+        CxCommandSetting {shift: false, key_code: KeyCode::Unknown, enabled: true}
+    }
+}
+
 #[derive(Default)]
 pub struct CxStyle {
     pub floats: HashMap<FloatId, f32>,
@@ -284,6 +299,10 @@ impl Default for Cx {
             style_stack: Vec::new(),
             
             command_settings: HashMap::new(),
+            commands: Vec::new(),
+            keymap: KeyMap::default(),
+            
+            tooltips: Vec::new(),
             
             playing_anim_areas: Vec::new(),
             ended_anim_areas: Vec::new(),
@@ -785,18 +804,27 @@ impl Cx {
         }
     }
     
+    pub fn send_command(&mut self, command: CommandId) {
+        self.commands.push(command);
+    }
+    
     pub fn call_signals<F>(&mut self, mut event_handler: F)
     where F: FnMut(&mut Cx, &mut Event)
     {
         let mut counter = 0;
-        while self.signals.len() != 0 {
+        while self.signals.len() != 0 || self.commands.len() != 0 {
             counter += 1;
-            let mut signals = HashMap::new();
-            std::mem::swap(&mut self.signals, &mut signals);
-            
-            self.call_event_handler(&mut event_handler, &mut Event::Signal(SignalEvent {
-                signals: signals,
-            }));
+            for command in std::mem::replace(&mut self.commands, Vec::new()) {
+                self.call_event_handler(&mut event_handler, &mut Event::Command(command));
+            }
+            if self.signals.len() != 0 {
+                let mut signals = HashMap::new();
+                std::mem::swap(&mut self.signals, &mut signals);
+                
+                self.call_event_handler(&mut event_handler, &mut Event::Signal(SignalEvent {
+                    signals: signals,
+                }));
+            }
             
             if counter > 100 {
                 println!("Signal feedback loop detected");
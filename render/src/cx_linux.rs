@@ -1,4 +1,5 @@
 use crate::cx_xlib::*;
+use crate::cx_linux_atspi::*;
 use crate::cx::*;
 
 impl Cx {
@@ -25,6 +26,8 @@ impl Cx {
         
         let mut passes_todo = Vec::new();
         
+        let atspi_bridge = AtspiBridge::start(self.new_signal());
+        
         xlib_app.event_loop( | xlib_app, events | {
             let mut paint_dirty = false;
             for mut event in events {
@@ -214,11 +217,21 @@ impl Cx {
                                     }
                                 }
                             }
+                            if let Some(atspi_bridge) = &atspi_bridge {
+                                atspi_bridge.update(self);
+                            }
                         }
                     },
                     Event::Signal {..} => {
                         self.call_event_handler(&mut event_handler, &mut event);
                         self.call_signals(&mut event_handler);
+                        // what a screen reader did
+                        if let Some(atspi_bridge) = &atspi_bridge {
+                            for (area, action) in atspi_bridge.take_actions() {
+                                self.call_a11y_action(&mut event_handler, area, action);
+                            }
+                            atspi_bridge.update(self);
+                        }
                     },
                     Event::None => {
                     },
@@ -251,6 +264,11 @@ impl Cx {
         self.platform.set_ime_position = Some(Vec2 {x: x, y: y});
     }
     
+    // rect is the text cursor, input methods compose at it and put their candidates under it
+    pub fn show_text_ime_rect(&mut self, rect: Rect) {
+        self.platform.set_ime_position = Some(Vec2 {x: rect.x, y: rect.y + rect.h});
+    }
+    
     pub fn hide_text_ime(&mut self) {
     }
     
@@ -0,0 +1,986 @@
+use crate::cx::*;
+use libc;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int, c_uint, c_void};
+use std::ptr;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+// AT-SPI
+// Screen readers on linux talk AT-SPI, DBus interfaces on a bus of its own. libdbus is loaded at runtime
+// so there is nothing to build against. A thread answers from the accessibility trees the event loop
+// stores after it painted, it only starts building them once something asked. Actions go back to the
+// event loop with a signal. NO_AT_BRIDGE=1 turns it off like it does for gtk.
+
+const DBUS_BUS_SESSION: c_int = 0;
+const DBUS_MESSAGE_TYPE_METHOD_CALL: c_int = 1;
+const DBUS_TYPE_BOOLEAN: c_int = b'b' as c_int;
+const DBUS_TYPE_INT16: c_int = b'n' as c_int;
+const DBUS_TYPE_INT32: c_int = b'i' as c_int;
+const DBUS_TYPE_UINT32: c_int = b'u' as c_int;
+const DBUS_TYPE_DOUBLE: c_int = b'd' as c_int;
+const DBUS_TYPE_STRING: c_int = b's' as c_int;
+const DBUS_TYPE_OBJECT_PATH: c_int = b'o' as c_int;
+const DBUS_TYPE_ARRAY: c_int = b'a' as c_int;
+const DBUS_TYPE_STRUCT: c_int = b'r' as c_int;
+const DBUS_TYPE_VARIANT: c_int = b'v' as c_int;
+const DBUS_TYPE_DICT_ENTRY: c_int = b'e' as c_int;
+
+const ROOT_PATH: &str = "/org/a11y/atspi/accessible/root";
+const NULL_PATH: &str = "/org/a11y/atspi/null";
+const NODE_PATH: &str = "/org/makepad/a11y/";
+
+#[repr(C)]
+struct DBusError {
+    name: *const c_char,
+    message: *const c_char,
+    dummy: c_uint,
+    padding1: *mut c_void,
+}
+
+// larger than the one of libdbus, it only needs the room
+#[repr(C)]
+struct DBusMessageIter {
+    data: [usize; 16]
+}
+
+type DBusConnection = c_void;
+type DBusMessage = c_void;
+
+struct LibDbus {
+    error_init: unsafe extern "C" fn(*mut DBusError),
+    error_is_set: unsafe extern "C" fn(*const DBusError) -> u32,
+    error_free: unsafe extern "C" fn(*mut DBusError),
+    bus_get_private: unsafe extern "C" fn(c_int, *mut DBusError) -> *mut DBusConnection,
+    bus_register: unsafe extern "C" fn(*mut DBusConnection, *mut DBusError) -> u32,
+    bus_get_unique_name: unsafe extern "C" fn(*mut DBusConnection) -> *const c_char,
+    connection_open_private: unsafe extern "C" fn(*const c_char, *mut DBusError) -> *mut DBusConnection,
+    connection_set_exit_on_disconnect: unsafe extern "C" fn(*mut DBusConnection, u32),
+    connection_close: unsafe extern "C" fn(*mut DBusConnection),
+    connection_unref: unsafe extern "C" fn(*mut DBusConnection),
+    connection_send: unsafe extern "C" fn(*mut DBusConnection, *mut DBusMessage, *mut u32) -> u32,
+    connection_send_with_reply_and_block: unsafe extern "C" fn(*mut DBusConnection, *mut DBusMessage, c_int, *mut DBusError) -> *mut DBusMessage,
+    connection_flush: unsafe extern "C" fn(*mut DBusConnection),
+    connection_read_write: unsafe extern "C" fn(*mut DBusConnection, c_int) -> u32,
+    connection_pop_message: unsafe extern "C" fn(*mut DBusConnection) -> *mut DBusMessage,
+    message_new_method_call: unsafe extern "C" fn(*const c_char, *const c_char, *const c_char, *const c_char) -> *mut DBusMessage,
+    message_new_method_return: unsafe extern "C" fn(*mut DBusMessage) -> *mut DBusMessage,
+    message_new_error: unsafe extern "C" fn(*mut DBusMessage, *const c_char, *const c_char) -> *mut DBusMessage,
+    message_new_signal: unsafe extern "C" fn(*const c_char, *const c_char, *const c_char) -> *mut DBusMessage,
+    message_unref: unsafe extern "C" fn(*mut DBusMessage),
+    message_get_type: unsafe extern "C" fn(*mut DBusMessage) -> c_int,
+    message_get_no_reply: unsafe extern "C" fn(*mut DBusMessage) -> u32,
+    message_get_path: unsafe extern "C" fn(*mut DBusMessage) -> *const c_char,
+    message_get_interface: unsafe extern "C" fn(*mut DBusMessage) -> *const c_char,
+    message_get_member: unsafe extern "C" fn(*mut DBusMessage) -> *const c_char,
+    message_iter_init: unsafe extern "C" fn(*mut DBusMessage, *mut DBusMessageIter) -> u32,
+    message_iter_init_append: unsafe extern "C" fn(*mut DBusMessage, *mut DBusMessageIter),
+    message_iter_get_arg_type: unsafe extern "C" fn(*mut DBusMessageIter) -> c_int,
+    message_iter_get_basic: unsafe extern "C" fn(*mut DBusMessageIter, *mut c_void),
+    message_iter_next: unsafe extern "C" fn(*mut DBusMessageIter) -> u32,
+    message_iter_recurse: unsafe extern "C" fn(*mut DBusMessageIter, *mut DBusMessageIter),
+    message_iter_append_basic: unsafe extern "C" fn(*mut DBusMessageIter, c_int, *const c_void) -> u32,
+    message_iter_open_container: unsafe extern "C" fn(*mut DBusMessageIter, c_int, *const c_char, *mut DBusMessageIter) -> u32,
+    message_iter_close_container: unsafe extern "C" fn(*mut DBusMessageIter, *mut DBusMessageIter) -> u32,
+}
+
+unsafe fn dbus_sym<T>(lib: *mut c_void, name: &str) -> Option<T> {
+    let name = CString::new(name).unwrap();
+    let sym = libc::dlsym(lib, name.as_ptr());
+    if sym.is_null() {
+        return None
+    }
+    Some(std::mem::transmute_copy(&sym))
+}
+
+impl LibDbus {
+    fn load() -> Option<LibDbus> {
+        unsafe {
+            let lib = libc::dlopen(b"libdbus-1.so.3\0".as_ptr() as *const c_char, libc::RTLD_NOW);
+            if lib.is_null() {
+                return None
+            }
+            Some(LibDbus {
+                error_init: dbus_sym(lib, "dbus_error_init") ?,
+                error_is_set: dbus_sym(lib, "dbus_error_is_set") ?,
+                error_free: dbus_sym(lib, "dbus_error_free") ?,
+                bus_get_private: dbus_sym(lib, "dbus_bus_get_private") ?,
+                bus_register: dbus_sym(lib, "dbus_bus_register") ?,
+                bus_get_unique_name: dbus_sym(lib, "dbus_bus_get_unique_name") ?,
+                connection_open_private: dbus_sym(lib, "dbus_connection_open_private") ?,
+                connection_set_exit_on_disconnect: dbus_sym(lib, "dbus_connection_set_exit_on_disconnect") ?,
+                connection_close: dbus_sym(lib, "dbus_connection_close") ?,
+                connection_unref: dbus_sym(lib, "dbus_connection_unref") ?,
+                connection_send: dbus_sym(lib, "dbus_connection_send") ?,
+                connection_send_with_reply_and_block: dbus_sym(lib, "dbus_connection_send_with_reply_and_block") ?,
+                connection_flush: dbus_sym(lib, "dbus_connection_flush") ?,
+                connection_read_write: dbus_sym(lib, "dbus_connection_read_write") ?,
+                connection_pop_message: dbus_sym(lib, "dbus_connection_pop_message") ?,
+                message_new_method_call: dbus_sym(lib, "dbus_message_new_method_call") ?,
+                message_new_method_return: dbus_sym(lib, "dbus_message_new_method_return") ?,
+                message_new_error: dbus_sym(lib, "dbus_message_new_error") ?,
+                message_new_signal: dbus_sym(lib, "dbus_message_new_signal") ?,
+                message_unref: dbus_sym(lib, "dbus_message_unref") ?,
+                message_get_type: dbus_sym(lib, "dbus_message_get_type") ?,
+                message_get_no_reply: dbus_sym(lib, "dbus_message_get_no_reply") ?,
+                message_get_path: dbus_sym(lib, "dbus_message_get_path") ?,
+                message_get_interface: dbus_sym(lib, "dbus_message_get_interface") ?,
+                message_get_member: dbus_sym(lib, "dbus_message_get_member") ?,
+                message_iter_init: dbus_sym(lib, "dbus_message_iter_init") ?,
+                message_iter_init_append: dbus_sym(lib, "dbus_message_iter_init_append") ?,
+                message_iter_get_arg_type: dbus_sym(lib, "dbus_message_iter_get_arg_type") ?,
+                message_iter_get_basic: dbus_sym(lib, "dbus_message_iter_get_basic") ?,
+                message_iter_next: dbus_sym(lib, "dbus_message_iter_next") ?,
+                message_iter_recurse: dbus_sym(lib, "dbus_message_iter_recurse") ?,
+                message_iter_append_basic: dbus_sym(lib, "dbus_message_iter_append_basic") ?,
+                message_iter_open_container: dbus_sym(lib, "dbus_message_iter_open_container") ?,
+                message_iter_close_container: dbus_sym(lib, "dbus_message_iter_close_container") ?,
+            })
+        }
+    }
+
+    fn new_error() -> DBusError {
+        DBusError {name: ptr::null(), message: ptr::null(), dummy: 0, padding1: ptr::null_mut()}
+    }
+
+    // calls a method and waits for the reply, None when there is an error
+    unsafe fn call(&self, conn: *mut DBusConnection, msg: *mut DBusMessage) -> Option<*mut DBusMessage> {
+        let mut err = Self::new_error();
+        (self.error_init)(&mut err);
+        let reply = (self.connection_send_with_reply_and_block)(conn, msg, 1000, &mut err);
+        (self.message_unref)(msg);
+        if (self.error_is_set)(&err) != 0 {
+            (self.error_free)(&mut err);
+            return None
+        }
+        if reply.is_null() {None} else {Some(reply)}
+    }
+
+    unsafe fn str_of(ptr: *const c_char) -> String {
+        if ptr.is_null() {String::new()} else {CStr::from_ptr(ptr).to_string_lossy().into_owned()}
+    }
+}
+
+#[derive(Clone, Debug)]
+enum DbusValue {
+    Bool(bool),
+    Int(i64),
+    Str(String),
+    Struct(Vec<DbusValue>),
+    Other
+}
+
+impl DbusValue {
+    fn int(&self) -> i64 {
+        if let DbusValue::Int(v) = self {*v} else {0}
+    }
+
+    fn str(&self) -> &str {
+        if let DbusValue::Str(v) = self {v} else {""}
+    }
+}
+
+unsafe fn read_iter(dbus: &LibDbus, iter: &mut DBusMessageIter) -> Vec<DbusValue> {
+    let mut values = Vec::new();
+    loop {
+        let ty = (dbus.message_iter_get_arg_type)(iter);
+        let value = match ty {
+            0 => break,
+            DBUS_TYPE_BOOLEAN => {
+                let mut v: u32 = 0;
+                (dbus.message_iter_get_basic)(iter, &mut v as *mut u32 as *mut c_void);
+                DbusValue::Bool(v != 0)
+            },
+            DBUS_TYPE_INT32 => {
+                let mut v: i32 = 0;
+                (dbus.message_iter_get_basic)(iter, &mut v as *mut i32 as *mut c_void);
+                DbusValue::Int(v as i64)
+            },
+            DBUS_TYPE_UINT32 => {
+                let mut v: u32 = 0;
+                (dbus.message_iter_get_basic)(iter, &mut v as *mut u32 as *mut c_void);
+                DbusValue::Int(v as i64)
+            },
+            DBUS_TYPE_STRING | DBUS_TYPE_OBJECT_PATH => {
+                let mut v: *const c_char = ptr::null();
+                (dbus.message_iter_get_basic)(iter, &mut v as *mut *const c_char as *mut c_void);
+                DbusValue::Str(LibDbus::str_of(v))
+            },
+            DBUS_TYPE_STRUCT | DBUS_TYPE_VARIANT => {
+                let mut sub = DBusMessageIter {data: [0; 16]};
+                (dbus.message_iter_recurse)(iter, &mut sub);
+                let mut inner = read_iter(dbus, &mut sub);
+                if ty == DBUS_TYPE_VARIANT && inner.len() == 1 {inner.pop().unwrap()} else {DbusValue::Struct(inner)}
+            },
+            _ => DbusValue::Other
+        };
+        values.push(value);
+        if (dbus.message_iter_next)(iter) == 0 {
+            break
+        }
+    }
+    values
+}
+
+unsafe fn read_args(dbus: &LibDbus, msg: *mut DBusMessage) -> Vec<DbusValue> {
+    let mut iter = DBusMessageIter {data: [0; 16]};
+    if (dbus.message_iter_init)(msg, &mut iter) == 0 {
+        return Vec::new()
+    }
+    read_iter(dbus, &mut iter)
+}
+
+// appends to a message, the iterators of open containers are boxed as libdbus points into them
+struct DbusWriter<'a> {
+    dbus: &'a LibDbus,
+    stack: Vec<Box<DBusMessageIter>>
+}
+
+impl<'a> DbusWriter<'a> {
+    fn new(dbus: &'a LibDbus, msg: *mut DBusMessage) -> Self {
+        let mut iter = Box::new(DBusMessageIter {data: [0; 16]});
+        unsafe {(dbus.message_iter_init_append)(msg, &mut *iter)};
+        Self {dbus: dbus, stack: vec![iter]}
+    }
+
+    fn top(&mut self) -> *mut DBusMessageIter {
+        &mut **self.stack.last_mut().unwrap()
+    }
+
+    fn basic<T>(&mut self, ty: c_int, v: T) {
+        let top = self.top();
+        unsafe {(self.dbus.message_iter_append_basic)(top, ty, &v as *const T as *const c_void)};
+    }
+
+    fn string(&mut self, v: &str) {
+        let s = CString::new(v.replace('\0', "")).unwrap();
+        self.basic(DBUS_TYPE_STRING, s.as_ptr());
+    }
+
+    fn path(&mut self, v: &str) {
+        let s = CString::new(v).unwrap();
+        self.basic(DBUS_TYPE_OBJECT_PATH, s.as_ptr());
+    }
+
+    fn bool(&mut self, v: bool) {
+        self.basic(DBUS_TYPE_BOOLEAN, if v {1u32} else {0u32});
+    }
+
+    fn i32(&mut self, v: i32) {
+        self.basic(DBUS_TYPE_INT32, v);
+    }
+
+    fn u32(&mut self, v: u32) {
+        self.basic(DBUS_TYPE_UINT32, v);
+    }
+
+    fn open(&mut self, ty: c_int, sig: Option<&str>) {
+        let sig = sig.map( | sig | CString::new(sig).unwrap());
+        let mut sub = Box::new(DBusMessageIter {data: [0; 16]});
+        let top = self.top();
+        unsafe {(self.dbus.message_iter_open_container)(top, ty, sig.as_ref().map_or(ptr::null(), | s | s.as_ptr()), &mut *sub)};
+        self.stack.push(sub);
+    }
+
+    fn close(&mut self) {
+        let mut sub = self.stack.pop().unwrap();
+        let top = self.top();
+        unsafe {(self.dbus.message_iter_close_container)(top, &mut *sub)};
+    }
+
+    // an object reference, (so)
+    fn reference(&mut self, bus_name: &str, path: &str) {
+        self.open(DBUS_TYPE_STRUCT, None);
+        self.string(bus_name);
+        self.path(path);
+        self.close();
+    }
+}
+
+#[derive(Clone)]
+pub struct AtspiTree {
+    pub window_id: usize,
+    pub position: Vec2,
+    pub dpi_factor: f32,
+    pub root: A11yTreeNode,
+}
+
+#[derive(Default)]
+pub struct AtspiShared {
+    // set when a screen reader asked something, the trees are only built from then on
+    pub active: bool,
+    pub trees: Vec<AtspiTree>,
+    pub focus: Option<(usize, u64)>,
+    pub actions: Vec<(Area, A11yAction)>,
+}
+
+pub struct AtspiBridge {
+    pub shared: Arc<Mutex<AtspiShared>>,
+}
+
+impl AtspiBridge {
+    pub fn start(signal: Signal) -> Option<AtspiBridge> {
+        if std::env::var("NO_AT_BRIDGE").map_or(false, | v | v == "1") {
+            return None
+        }
+        let shared = Arc::new(Mutex::new(AtspiShared::default()));
+        let thread_shared = shared.clone();
+        thread::spawn(move || {
+            if let Some(dbus) = LibDbus::load() {
+                unsafe {AtspiServer::run(dbus, thread_shared, signal)};
+            }
+        });
+        Some(AtspiBridge {shared: shared})
+    }
+
+    // after a paint, with what was drawn
+    pub fn update(&self, cx: &Cx) {
+        if let Ok(mut shared) = self.shared.lock() {
+            if !shared.active {
+                return
+            }
+            shared.trees.truncate(0);
+            shared.focus = None;
+            for (window_id, window) in cx.windows.iter().enumerate() {
+                match window.window_state {
+                    CxWindowState::Created if window.main_pass_id.is_some() => (),
+                    _ => continue
+                }
+                let root = cx.a11y_tree(window_id);
+                if let Some(focused) = root.find_focused() {
+                    shared.focus = Some((window_id, focused.id));
+                }
+                shared.trees.push(AtspiTree {
+                    window_id: window_id,
+                    position: window.window_geom.position,
+                    dpi_factor: window.window_geom.dpi_factor,
+                    root: root
+                });
+            }
+        }
+    }
+
+    pub fn take_actions(&self) -> Vec<(Area, A11yAction)> {
+        if let Ok(mut shared) = self.shared.lock() {
+            return std::mem::replace(&mut shared.actions, Vec::new())
+        }
+        Vec::new()
+    }
+}
+
+enum AtspiTarget<'a> {
+    Root,
+    Node {
+        tree: &'a AtspiTree,
+        node: &'a A11yTreeNode,
+        parent: Option<&'a A11yTreeNode>,
+        index: usize,
+    }
+}
+
+enum AtspiProp {
+    Str(String),
+    Int(i32),
+    Ref(String, String)
+}
+
+fn atspi_role(role: A11yRole) -> u32 {
+    match role {
+        A11yRole::Window => 23, // frame
+        A11yRole::Group => 39, // panel
+        A11yRole::Label => 29,
+        A11yRole::Button => 43, // push button
+        A11yRole::CheckBox => 7,
+        A11yRole::Switch => 62, // toggle button
+        A11yRole::RadioButton => 44,
+        A11yRole::ComboBox => 11,
+        A11yRole::Slider => 51,
+        A11yRole::SpinButton => 52,
+        A11yRole::TextInput => 79, // entry
+        A11yRole::TabList => 38, // page tab list
+        A11yRole::Tab => 37, // page tab
+        A11yRole::List => 31,
+        A11yRole::ListItem => 32,
+        A11yRole::Tree => 65,
+        A11yRole::TreeItem => 91,
+        A11yRole::Table => 55,
+        A11yRole::Row => 90, // table row
+        A11yRole::ColumnHeader => 57, // table column header
+        A11yRole::Menu => 33,
+        A11yRole::MenuItem => 35,
+        A11yRole::Tooltip => 64,
+    }
+}
+
+fn atspi_states(node: &A11yTreeNode, active: bool) -> [u32; 2] {
+    let mut bits: u64 = 0;
+    let mut set = | bit: u32 | bits |= 1 << bit;
+    set(25); // showing
+    set(30); // visible
+    if !node.disabled {
+        set(8); // enabled
+        set(24); // sensitive
+    }
+    if node.id == 0 && active {
+        set(1); // active
+    }
+    if node.actions.contains(&A11yAction::Focus) || node.focused {
+        set(11); // focusable
+    }
+    if node.focused {
+        set(12);
+    }
+    if node.checked == Some(true) {
+        set(4);
+    }
+    if let Some(expanded) = node.expanded {
+        set(9); // expandable
+        set(if expanded {10} else {5}); // expanded, collapsed
+    }
+    if node.actions.contains(&A11yAction::Select) {
+        set(22); // selectable
+    }
+    if node.selected {
+        set(23);
+    }
+    if node.role == A11yRole::TextInput {
+        set(7); // editable
+        set(26); // single line
+    }
+    [bits as u32, (bits >> 32) as u32]
+}
+
+fn node_path(window_id: usize, id: u64) -> String {
+    format!("{}{}/{}", NODE_PATH, window_id, id)
+}
+
+fn find_node<'a>(node: &'a A11yTreeNode, id: u64) -> Option<(&'a A11yTreeNode, Option<&'a A11yTreeNode>, usize)> {
+    if node.id == id {
+        return Some((node, None, 0))
+    }
+    for (index, child) in node.children.iter().enumerate() {
+        if child.id == id {
+            return Some((child, Some(node), index))
+        }
+        if let Some(found) = find_node(child, id) {
+            return Some(found)
+        }
+    }
+    None
+}
+
+// the deepest node at a point of the window, what is drawn last is on top
+fn node_at<'a>(node: &'a A11yTreeNode, x: f32, y: f32) -> Option<&'a A11yTreeNode> {
+    for child in node.children.iter().rev() {
+        if let Some(found) = node_at(child, x, y) {
+            return Some(found)
+        }
+    }
+    if node.rect.w > 0. && node.rect.contains(x, y) {Some(node)} else {None}
+}
+
+struct AtspiServer {
+    dbus: LibDbus,
+    conn: *mut DBusConnection,
+    bus_name: String,
+    parent: (String, String),
+    shared: Arc<Mutex<AtspiShared>>,
+    signal: Signal,
+    focus_sent: Option<(usize, u64)>,
+}
+
+impl AtspiServer {
+    unsafe fn connect(dbus: &LibDbus) -> Option<*mut DBusConnection> {
+        // the session bus knows the address of the accessibility bus
+        let mut err = LibDbus::new_error();
+        (dbus.error_init)(&mut err);
+        let session = (dbus.bus_get_private)(DBUS_BUS_SESSION, &mut err);
+        if session.is_null() {
+            (dbus.error_free)(&mut err);
+            return None
+        }
+        (dbus.connection_set_exit_on_disconnect)(session, 0);
+        let msg = (dbus.message_new_method_call)(
+            b"org.a11y.Bus\0".as_ptr() as *const c_char,
+            b"/org/a11y/bus\0".as_ptr() as *const c_char,
+            b"org.a11y.Bus\0".as_ptr() as *const c_char,
+            b"GetAddress\0".as_ptr() as *const c_char
+        );
+        let reply = dbus.call(session, msg);
+        (dbus.connection_close)(session);
+        (dbus.connection_unref)(session);
+        let reply = reply ?;
+        let address = read_args(dbus, reply).get(0).map( | v | v.str().to_string());
+        (dbus.message_unref)(reply);
+        let address = CString::new(address ?).ok() ?;
+
+        let conn = (dbus.connection_open_private)(address.as_ptr(), &mut err);
+        if conn.is_null() {
+            (dbus.error_free)(&mut err);
+            return None
+        }
+        if (dbus.bus_register)(conn, &mut err) == 0 {
+            (dbus.error_free)(&mut err);
+            (dbus.connection_close)(conn);
+            (dbus.connection_unref)(conn);
+            return None
+        }
+        (dbus.connection_set_exit_on_disconnect)(conn, 0);
+        Some(conn)
+    }
+
+    unsafe fn run(dbus: LibDbus, shared: Arc<Mutex<AtspiShared>>, signal: Signal) {
+        let conn = if let Some(conn) = Self::connect(&dbus) {conn} else {return};
+        let bus_name = LibDbus::str_of((dbus.bus_get_unique_name)(conn));
+
+        // our root goes into the desktop of the registry, which answers with the parent of it
+        let root = CString::new(ROOT_PATH).unwrap();
+        let msg = (dbus.message_new_method_call)(
+            b"org.a11y.atspi.Registry\0".as_ptr() as *const c_char,
+            root.as_ptr(),
+            b"org.a11y.atspi.Socket\0".as_ptr() as *const c_char,
+            b"Embed\0".as_ptr() as *const c_char
+        );
+        DbusWriter::new(&dbus, msg).reference(&bus_name, ROOT_PATH);
+        let parent = match dbus.call(conn, msg) {
+            Some(reply) => {
+                let args = read_args(&dbus, reply);
+                (dbus.message_unref)(reply);
+                match args.get(0) {
+                    Some(DbusValue::Struct(r)) if r.len() == 2 => (r[0].str().to_string(), r[1].str().to_string()),
+                    _ => (String::new(), NULL_PATH.to_string())
+                }
+            },
+            None => (String::new(), NULL_PATH.to_string())
+        };
+
+        let mut server = AtspiServer {
+            dbus: dbus,
+            conn: conn,
+            bus_name: bus_name,
+            parent: parent,
+            shared: shared,
+            signal: signal,
+            focus_sent: None,
+        };
+        while (server.dbus.connection_read_write)(server.conn, 100) != 0 {
+            loop {
+                let msg = (server.dbus.connection_pop_message)(server.conn);
+                if msg.is_null() {
+                    break
+                }
+                if (server.dbus.message_get_type)(msg) == DBUS_MESSAGE_TYPE_METHOD_CALL {
+                    server.handle_call(msg);
+                }
+                (server.dbus.message_unref)(msg);
+            }
+            server.send_focus();
+            (server.dbus.connection_flush)(server.conn);
+        }
+    }
+
+    fn target<'a>(&self, shared: &'a AtspiShared, path: &str) -> Option<AtspiTarget<'a>> {
+        if path == ROOT_PATH {
+            return Some(AtspiTarget::Root)
+        }
+        let mut parts = path.strip_prefix(NODE_PATH) ?.split('/');
+        let window_id: usize = parts.next() ?.parse().ok() ?;
+        let id: u64 = parts.next() ?.parse().ok() ?;
+        let (tree_index, tree) = shared.trees.iter().enumerate().find( | (_, tree) | tree.window_id == window_id) ?;
+        let (node, parent, index) = find_node(&tree.root, id) ?;
+        Some(AtspiTarget::Node {
+            tree: tree,
+            node: node,
+            parent: parent,
+            index: if parent.is_none() {tree_index} else {index}
+        })
+    }
+
+    fn interfaces(target: &AtspiTarget) -> Vec<&'static str> {
+        match target {
+            AtspiTarget::Root => vec!["org.a11y.atspi.Accessible", "org.a11y.atspi.Application"],
+            AtspiTarget::Node {node, ..} => {
+                let mut ifaces = vec!["org.a11y.atspi.Accessible", "org.a11y.atspi.Component"];
+                if node.actions.len() > 0 {
+                    ifaces.push("org.a11y.atspi.Action");
+                }
+                if node.role == A11yRole::TextInput {
+                    ifaces.push("org.a11y.atspi.Text");
+                }
+                ifaces
+            }
+        }
+    }
+
+    fn props(iface: &str) -> &'static [&'static str] {
+        match iface {
+            "org.a11y.atspi.Accessible" => &["Name", "Description", "Parent", "ChildCount", "Locale", "AccessibleId"],
+            "org.a11y.atspi.Application" => &["ToolkitName", "Version", "AtspiVersion", "Id"],
+            "org.a11y.atspi.Action" => &["NActions"],
+            "org.a11y.atspi.Text" => &["CharacterCount", "CaretOffset"],
+            _ => &[]
+        }
+    }
+
+    fn prop(&self, shared: &AtspiShared, target: &AtspiTarget, iface: &str, name: &str) -> Option<AtspiProp> {
+        match (target, iface, name) {
+            (AtspiTarget::Root, "org.a11y.atspi.Accessible", "Name") => Some(AtspiProp::Str(
+                std::env::current_exe().ok().and_then( | exe | exe.file_name().map( | n | n.to_string_lossy().into_owned())).unwrap_or_default()
+            )),
+            (AtspiTarget::Root, "org.a11y.atspi.Accessible", "Parent") => Some(AtspiProp::Ref(self.parent.0.clone(), self.parent.1.clone())),
+            (AtspiTarget::Root, "org.a11y.atspi.Accessible", "ChildCount") => Some(AtspiProp::Int(shared.trees.len() as i32)),
+            (AtspiTarget::Root, "org.a11y.atspi.Application", "ToolkitName") => Some(AtspiProp::Str("makepad".to_string())),
+            (AtspiTarget::Root, "org.a11y.atspi.Application", "Version") => Some(AtspiProp::Str(env!("CARGO_PKG_VERSION").to_string())),
+            (AtspiTarget::Root, "org.a11y.atspi.Application", "AtspiVersion") => Some(AtspiProp::Str("2.1".to_string())),
+            (AtspiTarget::Root, "org.a11y.atspi.Application", "Id") => Some(AtspiProp::Int(0)),
+            (AtspiTarget::Root, "org.a11y.atspi.Accessible", _) => Some(AtspiProp::Str(String::new())),
+            (AtspiTarget::Node {tree, node, parent, ..}, "org.a11y.atspi.Accessible", _) => Some(match name {
+                "Name" => AtspiProp::Str(node.name.clone()),
+                "Description" => AtspiProp::Str(if node.role == A11yRole::TextInput {String::new()} else {node.value.clone()}),
+                "Parent" => match parent {
+                    Some(parent) => AtspiProp::Ref(self.bus_name.clone(), node_path(tree.window_id, parent.id)),
+                    None => AtspiProp::Ref(self.bus_name.clone(), ROOT_PATH.to_string())
+                },
+                "ChildCount" => AtspiProp::Int(node.children.len() as i32),
+                "AccessibleId" => AtspiProp::Str(node.id.to_string()),
+                _ => AtspiProp::Str(String::new())
+            }),
+            (AtspiTarget::Node {node, ..}, "org.a11y.atspi.Action", "NActions") => Some(AtspiProp::Int(node.actions.len() as i32)),
+            (AtspiTarget::Node {node, ..}, "org.a11y.atspi.Text", "CharacterCount") => Some(AtspiProp::Int(node.value.chars().count() as i32)),
+            (AtspiTarget::Node {..}, "org.a11y.atspi.Text", "CaretOffset") => Some(AtspiProp::Int(0)),
+            _ => None
+        }
+    }
+
+    fn write_variant(w: &mut DbusWriter, prop: &AtspiProp) {
+        match prop {
+            AtspiProp::Str(v) => {
+                w.open(DBUS_TYPE_VARIANT, Some("s"));
+                w.string(v);
+            },
+            AtspiProp::Int(v) => {
+                w.open(DBUS_TYPE_VARIANT, Some("i"));
+                w.i32(*v);
+            },
+            AtspiProp::Ref(bus_name, path) => {
+                w.open(DBUS_TYPE_VARIANT, Some("(so)"));
+                w.reference(bus_name, path);
+            }
+        }
+        w.close();
+    }
+
+    // the rect of a node on the screen or in the window, in pixels
+    fn extents(tree: &AtspiTree, rect: Rect, coord_type: i64) -> (i32, i32, i32, i32) {
+        let origin = if coord_type == 0 {tree.position} else {Vec2::default()};
+        let dpi = tree.dpi_factor;
+        (
+            ((origin.x + rect.x) * dpi) as i32,
+            ((origin.y + rect.y) * dpi) as i32,
+            (rect.w * dpi) as i32,
+            (rect.h * dpi) as i32
+        )
+    }
+
+    fn post_action(&self, shared: &mut AtspiShared, area: Area, action: A11yAction) {
+        shared.actions.push((area, action));
+        Cx::post_signal(self.signal, Cx::status_a11y_action());
+    }
+
+    unsafe fn reply_error(&self, msg: *mut DBusMessage, text: &str) {
+        let text = CString::new(text).unwrap();
+        let reply = (self.dbus.message_new_error)(msg, b"org.freedesktop.DBus.Error.UnknownMethod\0".as_ptr() as *const c_char, text.as_ptr());
+        (self.dbus.connection_send)(self.conn, reply, ptr::null_mut());
+        (self.dbus.message_unref)(reply);
+    }
+
+    unsafe fn handle_call(&mut self, msg: *mut DBusMessage) {
+        let path = LibDbus::str_of((self.dbus.message_get_path)(msg));
+        let iface = LibDbus::str_of((self.dbus.message_get_interface)(msg));
+        let member = LibDbus::str_of((self.dbus.message_get_member)(msg));
+        let args = read_args(&self.dbus, msg);
+        let arg = | index: usize | args.get(index).cloned().unwrap_or(DbusValue::Other);
+
+        let shared_arc = self.shared.clone();
+        let mut shared = if let Ok(shared) = shared_arc.lock() {shared} else {return};
+        // the trees are there from the next paint on
+        if !shared.active {
+            shared.active = true;
+            Cx::post_signal(self.signal, Cx::status_a11y_action());
+        }
+        let reply = (self.dbus.message_new_method_return)(msg);
+        let handled = {
+            let target = match self.target(&shared, &path) {
+                Some(target) => target,
+                None => {
+                    (self.dbus.message_unref)(reply);
+                    return self.reply_error(msg, "Unknown object")
+                }
+            };
+            let mut w = DbusWriter::new(&self.dbus, reply);
+            let mut action = None;
+            let handled = match (iface.as_ref(), member.as_ref()) {
+                ("org.freedesktop.DBus.Properties", "Get") => {
+                    match self.prop(&shared, &target, arg(0).str(), arg(1).str()) {
+                        Some(prop) => {Self::write_variant(&mut w, &prop); true},
+                        None => false
+                    }
+                },
+                ("org.freedesktop.DBus.Properties", "GetAll") => {
+                    w.open(DBUS_TYPE_ARRAY, Some("{sv}"));
+                    for name in Self::props(arg(0).str()) {
+                        if let Some(prop) = self.prop(&shared, &target, arg(0).str(), name) {
+                            w.open(DBUS_TYPE_DICT_ENTRY, None);
+                            w.string(name);
+                            Self::write_variant(&mut w, &prop);
+                            w.close();
+                        }
+                    }
+                    w.close();
+                    true
+                },
+                ("org.freedesktop.DBus.Properties", "Set") => true,
+                ("org.a11y.atspi.Accessible", "GetChildren") => {
+                    w.open(DBUS_TYPE_ARRAY, Some("(so)"));
+                    match &target {
+                        AtspiTarget::Root => for tree in &shared.trees {
+                            w.reference(&self.bus_name, &node_path(tree.window_id, 0));
+                        },
+                        AtspiTarget::Node {tree, node, ..} => for child in &node.children {
+                            w.reference(&self.bus_name, &node_path(tree.window_id, child.id));
+                        }
+                    }
+                    w.close();
+                    true
+                },
+                ("org.a11y.atspi.Accessible", "GetChildAtIndex") => {
+                    let index = arg(0).int() as usize;
+                    match &target {
+                        AtspiTarget::Root => match shared.trees.get(index) {
+                            Some(tree) => w.reference(&self.bus_name, &node_path(tree.window_id, 0)),
+                            None => w.reference(&self.bus_name, NULL_PATH)
+                        },
+                        AtspiTarget::Node {tree, node, ..} => match node.children.get(index) {
+                            Some(child) => w.reference(&self.bus_name, &node_path(tree.window_id, child.id)),
+                            None => w.reference(&self.bus_name, NULL_PATH)
+                        }
+                    }
+                    true
+                },
+                ("org.a11y.atspi.Accessible", "GetIndexInParent") => {
+                    w.i32(match &target {AtspiTarget::Root => -1, AtspiTarget::Node {index, ..} => *index as i32});
+                    true
+                },
+                ("org.a11y.atspi.Accessible", "GetRelationSet") => {
+                    w.open(DBUS_TYPE_ARRAY, Some("(ua(so))"));
+                    w.close();
+                    true
+                },
+                ("org.a11y.atspi.Accessible", "GetRole") => {
+                    w.u32(match &target {AtspiTarget::Root => 75, AtspiTarget::Node {node, ..} => atspi_role(node.role)});
+                    true
+                },
+                ("org.a11y.atspi.Accessible", "GetRoleName") | ("org.a11y.atspi.Accessible", "GetLocalizedRoleName") => {
+                    w.string(match &target {AtspiTarget::Root => "application", AtspiTarget::Node {node, ..} => node.role.name()});
+                    true
+                },
+                ("org.a11y.atspi.Accessible", "GetState") => {
+                    let states = match &target {
+                        AtspiTarget::Root => [0, 0],
+                        AtspiTarget::Node {tree, node, ..} => {
+                            let active = shared.focus.map_or(shared.trees.len() == 1, | (window_id, _) | window_id == tree.window_id);
+                            atspi_states(node, active)
+                        }
+                    };
+                    w.open(DBUS_TYPE_ARRAY, Some("u"));
+                    w.u32(states[0]);
+                    w.u32(states[1]);
+                    w.close();
+                    true
+                },
+                ("org.a11y.atspi.Accessible", "GetAttributes") => {
+                    w.open(DBUS_TYPE_ARRAY, Some("{ss}"));
+                    if let AtspiTarget::Node {node, ..} = &target {
+                        if let Some(expanded) = node.expanded {
+                            w.open(DBUS_TYPE_DICT_ENTRY, None);
+                            w.string("expanded");
+                            w.string(if expanded {"true"} else {"false"});
+                            w.close();
+                        }
+                    }
+                    w.close();
+                    true
+                },
+                ("org.a11y.atspi.Accessible", "GetApplication") => {
+                    w.reference(&self.bus_name, ROOT_PATH);
+                    true
+                },
+                ("org.a11y.atspi.Accessible", "GetInterfaces") => {
+                    w.open(DBUS_TYPE_ARRAY, Some("s"));
+                    for iface in Self::interfaces(&target) {
+                        w.string(iface);
+                    }
+                    w.close();
+                    true
+                },
+                ("org.a11y.atspi.Application", "GetLocale") => {
+                    w.string("");
+                    true
+                },
+                ("org.a11y.atspi.Component", member) => match &target {
+                    AtspiTarget::Node {tree, node, ..} => match member {
+                        "GetExtents" => {
+                            let (x, y, width, height) = Self::extents(tree, node.rect, arg(0).int());
+                            w.open(DBUS_TYPE_STRUCT, None);
+                            w.i32(x);
+                            w.i32(y);
+                            w.i32(width);
+                            w.i32(height);
+                            w.close();
+                            true
+                        },
+                        "GetPosition" => {
+                            let (x, y, _, _) = Self::extents(tree, node.rect, arg(0).int());
+                            w.i32(x);
+                            w.i32(y);
+                            true
+                        },
+                        "GetSize" => {
+                            let (_, _, width, height) = Self::extents(tree, node.rect, 1);
+                            w.i32(width);
+                            w.i32(height);
+                            true
+                        },
+                        "Contains" | "GetAccessibleAtPoint" => {
+                            let origin = if arg(2).int() == 0 {tree.position} else {Vec2::default()};
+                            let x = arg(0).int() as f32 / tree.dpi_factor - origin.x;
+                            let y = arg(1).int() as f32 / tree.dpi_factor - origin.y;
+                            if member == "Contains" {
+                                w.bool(node.rect.contains(x, y));
+                            }
+                            else {
+                                match node_at(node, x, y) {
+                                    Some(found) => w.reference(&self.bus_name, &node_path(tree.window_id, found.id)),
+                                    None => w.reference(&self.bus_name, NULL_PATH)
+                                }
+                            }
+                            true
+                        },
+                        "GetLayer" => {
+                            w.u32(if node.id == 0 {7} else {3}); // window, widget
+                            true
+                        },
+                        "GetMDIZOrder" => {
+                            w.basic(DBUS_TYPE_INT16, 0i16);
+                            true
+                        },
+                        "GetAlpha" => {
+                            w.basic(DBUS_TYPE_DOUBLE, 1.0f64);
+                            true
+                        },
+                        "GrabFocus" => {
+                            let can_focus = node.actions.contains(&A11yAction::Focus);
+                            if can_focus {
+                                action = Some((node.area, A11yAction::Focus));
+                            }
+                            w.bool(can_focus);
+                            true
+                        },
+                        _ => false
+                    },
+                    _ => false
+                },
+                ("org.a11y.atspi.Action", member) => match &target {
+                    AtspiTarget::Node {node, ..} => match member {
+                        "GetActions" => {
+                            w.open(DBUS_TYPE_ARRAY, Some("(sss)"));
+                            for node_action in &node.actions {
+                                w.open(DBUS_TYPE_STRUCT, None);
+                                w.string(node_action.name());
+                                w.string("");
+                                w.string("");
+                                w.close();
+                            }
+                            w.close();
+                            true
+                        },
+                        "GetName" | "GetLocalizedName" => {
+                            w.string(node.actions.get(arg(0).int() as usize).map_or("", | a | a.name()));
+                            true
+                        },
+                        "GetDescription" | "GetKeyBinding" => {
+                            w.string("");
+                            true
+                        },
+                        "DoAction" => {
+                            let node_action = node.actions.get(arg(0).int() as usize).cloned();
+                            if let Some(node_action) = node_action {
+                                action = Some((node.area, node_action));
+                            }
+                            w.bool(node_action.is_some());
+                            true
+                        },
+                        _ => false
+                    },
+                    _ => false
+                },
+                ("org.a11y.atspi.Text", "GetText") => match &target {
+                    AtspiTarget::Node {node, ..} => {
+                        let len = node.value.chars().count() as i64;
+                        let start = arg(0).int().max(0).min(len);
+                        let end = if arg(1).int() < 0 {len} else {arg(1).int().min(len)};
+                        w.string(&node.value.chars().skip(start as usize).take((end - start).max(0) as usize).collect::<String>());
+                        true
+                    },
+                    _ => false
+                },
+                _ => false
+            };
+            drop(w);
+            if let Some((area, node_action)) = action {
+                self.post_action(&mut shared, area, node_action);
+            }
+            handled
+        };
+        drop(shared);
+        if handled {
+            if (self.dbus.message_get_no_reply)(msg) == 0 {
+                (self.dbus.connection_send)(self.conn, reply, ptr::null_mut());
+            }
+            (self.dbus.message_unref)(reply);
+        }
+        else {
+            (self.dbus.message_unref)(reply);
+            self.reply_error(msg, &format!("{}.{} is not supported", iface, member));
+        }
+    }
+
+    // screen readers follow the focus by its state change
+    unsafe fn send_focus(&mut self) {
+        let focus = if let Ok(shared) = self.shared.lock() {shared.focus} else {return};
+        if focus == self.focus_sent {
+            return
+        }
+        self.focus_sent = focus;
+        let (window_id, id) = if let Some(focus) = focus {focus} else {return};
+        let path = CString::new(node_path(window_id, id)).unwrap();
+        let msg = (self.dbus.message_new_signal)(
+            path.as_ptr(),
+            b"org.a11y.atspi.Event.Object\0".as_ptr() as *const c_char,
+            b"StateChanged\0".as_ptr() as *const c_char
+        );
+        {
+            let mut w = DbusWriter::new(&self.dbus, msg);
+            w.string("focused");
+            w.i32(1);
+            w.i32(0);
+            w.open(DBUS_TYPE_VARIANT, Some("i"));
+            w.i32(0);
+            w.close();
+            w.open(DBUS_TYPE_ARRAY, Some("{sv}"));
+            w.close();
+        }
+        (self.dbus.connection_send)(self.conn, msg, ptr::null_mut());
+        (self.dbus.message_unref)(msg);
+    }
+}
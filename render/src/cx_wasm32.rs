@@ -347,6 +347,19 @@ impl Cx {
                         _=>Cx::status_http_send_fail()
                     }]);
                 },
+                23 => { // text composition
+                    let state = match to_wasm.mu32() {
+                        0 => TextCompositionState::Start,
+                        1 => TextCompositionState::Update,
+                        _ => TextCompositionState::Commit
+                    };
+                    let cursor = to_wasm.mu32() as usize;
+                    self.call_event_handler(&mut event_handler, &mut Event::TextComposition(TextCompositionEvent {
+                        state: state,
+                        cursor: cursor,
+                        text: to_wasm.parse_string(),
+                    }));
+                },
                 _ => {
                     panic!("Message unknown")
                 }
@@ -488,6 +501,11 @@ impl Cx {
         self.platform.from_wasm.show_text_ime(x, y);
     }
     
+    // rect is the text cursor, input methods compose at it and put their candidates under it
+    pub fn show_text_ime_rect(&mut self, rect: Rect) {
+        self.show_text_ime(rect.x, rect.y + rect.h);
+    }
+    
     pub fn hide_text_ime(&mut self) {
         self.platform.from_wasm.hide_text_ime();
     }
@@ -242,6 +242,14 @@
             this.mu32[pos ++] = success?1:2;
         }
         
+        text_composition(data) {
+            let pos = this.fit(3);
+            this.mu32[pos ++] = 23;
+            this.mu32[pos ++] = data.state;
+            this.mu32[pos ++] = data.cursor;
+            this.send_string(data.text);
+        }
+        
         end() {
             let pos = this.fit(1);
             this.mu32[pos] = 0;
@@ -1117,7 +1125,37 @@
                 
             })
             
+            // while the IME composes the textarea holds its text, we get it as composition events
+            // and the result as text input when it ends
+            var composing = false;
+            ta.addEventListener('compositionstart', e => {
+                composing = true;
+                this.to_wasm.text_composition({state: 0, cursor: 0, text: ""})
+                this.do_wasm_io();
+            })
+            ta.addEventListener('compositionupdate', e => {
+                this.to_wasm.text_composition({state: 1, cursor: e.data.length, text: e.data})
+                this.do_wasm_io();
+            })
+            ta.addEventListener('compositionend', e => {
+                composing = false;
+                this.to_wasm.text_composition({state: 2, cursor: 0, text: ""})
+                if (e.data.length > 0) {
+                    this.to_wasm.text_input({
+                        was_paste: false,
+                        input: e.data,
+                        replace_last: false,
+                    })
+                }
+                ta.value = "";
+                last_len = 0;
+                this.do_wasm_io();
+            })
+            
             ta.addEventListener('input', e => {
+                if (composing) {
+                    return
+                }
                 if (ta.value.length > 0) {
                     if (was_paste) {
                         was_paste = false;
@@ -1170,6 +1208,10 @@
             
             ta.addEventListener('keydown', e => {
                 let code = e.keyCode;
+                // keys that go to the IME, like the enter that picks a candidate
+                if (composing || code == 229) {
+                    return
+                }
                 
                 //if (code == 91) {firefox_logo_key = true; e.preventDefault();}
                 if (code == 18 || code == 17 || code == 16) e.preventDefault(); // alt
@@ -269,6 +269,11 @@ impl Cx {
         self.platform.set_ime_position = Some(Vec2 {x: x, y: y});
     }
     
+    // rect is the text cursor, input methods compose at it and put their candidates under it
+    pub fn show_text_ime_rect(&mut self, rect: Rect) {
+        self.show_text_ime(rect.x, rect.y);
+    }
+    
     pub fn hide_text_ime(&mut self) {
     }
     
@@ -22,7 +22,7 @@ pub struct XlibApp {
     pub xim: X11_sys::XIM,
     pub clipboard: String,
     pub display_fd: c_int,
-    pub signal_fd: c_int,
+    pub signal_fds: [c_int; 2],
     pub window_map: HashMap<c_ulong, *mut XlibWindow>,
     pub time_start: u64,
     pub last_scroll_time: f64,
@@ -69,6 +69,9 @@ pub struct XlibWindow {
     pub time_start: u64,
     
     pub ime_spot: Vec2,
+    pub ime_callbacks: Vec<X11_sys::XIMCallback>,
+    pub ime_preedit: Vec<char>,
+    pub ime_preedit_caret: usize,
     pub current_cursor: MouseCursor,
     pub last_mouse_pos: Vec2,
     pub fingers_down: Vec<bool>,
@@ -103,8 +106,16 @@ impl XlibApp {
         unsafe {
             let display = X11_sys::XOpenDisplay(ptr::null());
             let display_fd = X11_sys::XConnectionNumber(display);
+            // the input method server is only found with the locale and its modifiers set
+            libc::setlocale(libc::LC_CTYPE, b"\0".as_ptr() as *const c_char);
+            X11_sys::XSetLocaleModifiers(b"\0".as_ptr() as *const c_char);
             let xim = X11_sys::XOpenIM(display, ptr::null_mut(), ptr::null_mut(), ptr::null_mut());
-            let signal_fd = 0i32; //libc::pipe();
+            // post_signal writes to this pipe to wake up the select
+            let mut signal_fds = [0 as c_int; 2];
+            libc::pipe(signal_fds.as_mut_ptr());
+            for fd in &signal_fds {
+                libc::fcntl(*fd, libc::F_SETFL, libc::fcntl(*fd, libc::F_GETFL) | libc::O_NONBLOCK);
+            }
             XlibApp {
                 atom_clipboard: X11_sys::XInternAtom(display, CString::new("CLIPBOARD").unwrap().as_ptr(), 0),
                 atom_net_wm_moveresize: X11_sys::XInternAtom(display, CString::new("_NET_WM_MOVERESIZE").unwrap().as_ptr(), 0),
@@ -122,7 +133,7 @@ impl XlibApp {
                 xim,
                 display,
                 display_fd,
-                signal_fd,
+                signal_fds,
                 clipboard: String::new(),
                 last_scroll_time: 0.0,
                 last_click_time: 0.0,
@@ -155,10 +166,12 @@ impl XlibApp {
     where F: FnMut(&mut XlibApp, &mut Vec<Event>) -> bool,
     {
         unsafe {
-            self.event_callback = Some(
-                &mut event_handler as *const dyn FnMut(&mut XlibApp, &mut Vec<Event>) -> bool
-                as *mut dyn FnMut(&mut XlibApp, &mut Vec<Event>) -> bool
-            );
+            // the field wants a 'static closure but event_handler lives on this stack frame.
+            // Erasing its lifetime is sound because the pointer is set back to None before this
+            // function returns, and do_callback does nothing without it
+            self.event_callback = Some(std::mem::transmute(
+                &mut event_handler as *mut dyn FnMut(&mut XlibApp, &mut Vec<Event>) -> bool
+            ));
             
             self.do_callback(&mut vec![
                 Event::Paint,
@@ -172,6 +185,7 @@ impl XlibApp {
                     let mut fds = mem::MaybeUninit::uninit();
                     libc::FD_ZERO(fds.as_mut_ptr());
                     libc::FD_SET(self.display_fd, fds.as_mut_ptr());
+                    libc::FD_SET(self.signal_fds[0], fds.as_mut_ptr());
                     // If there are any timers, we set the timeout for select to the `delta_timeout`
                     // of the first timer that should be fired. Otherwise, we set the timeout to
                     // None, so that select will block indefinitely.
@@ -189,12 +203,14 @@ impl XlibApp {
                         None
                     };
                     let _nfds = libc::select(
-                        self.display_fd + 1,
+                        self.display_fd.max(self.signal_fds[0]) + 1,
                         fds.as_mut_ptr(),
                         ptr::null_mut(),
                         ptr::null_mut(),
                         if let Some(mut timeout) = timeout {&mut timeout} else {ptr::null_mut()}
                     );
+                    let mut buf = [0u8; 64];
+                    while libc::read(self.signal_fds[0], buf.as_mut_ptr() as *mut c_void, buf.len()) > 0 {}
                 }
                 // Update the current time, and compute the amount of time that elapsed since we
                 // last recorded the current time.
@@ -229,6 +245,10 @@ impl XlibApp {
                     let mut event = mem::MaybeUninit::uninit();
                     X11_sys::XNextEvent(self.display, event.as_mut_ptr());
                     let mut event = event.assume_init();
+                    // keys the input method composes with are not ours
+                    if X11_sys::XFilterEvent(&mut event, 0) != 0 {
+                        continue;
+                    }
                     match event.type_ as u32 {
                         X11_sys::SelectionNotify => {
                             let selection = event.xselection;
@@ -607,12 +627,12 @@ impl XlibApp {
                                     block_text
                                 }else{false};
                                 
-                                if !block_text{
-                                    // decode the character
-                                    let mut buffer = [0u8; 32];
+                                if !block_text && window.xic.is_some() {
+                                    // decode the character, or what the input method committed
+                                    let mut buffer = vec![0u8; 32];
                                     let mut keysym = mem::MaybeUninit::uninit();
                                     let mut status = mem::MaybeUninit::uninit();
-                                    let count = X11_sys::Xutf8LookupString(
+                                    let mut count = X11_sys::Xutf8LookupString(
                                         window.xic.unwrap(),
                                         &mut event.xkey,
                                         buffer.as_mut_ptr() as *mut c_char,
@@ -620,6 +640,17 @@ impl XlibApp {
                                         keysym.as_mut_ptr(),
                                         status.as_mut_ptr(),
                                     );
+                                    if status.assume_init() == X11_sys::XBufferOverflow {
+                                        buffer.resize(count as usize, 0);
+                                        count = X11_sys::Xutf8LookupString(
+                                            window.xic.unwrap(),
+                                            &mut event.xkey,
+                                            buffer.as_mut_ptr() as *mut c_char,
+                                            buffer.len() as c_int,
+                                            keysym.as_mut_ptr(),
+                                            status.as_mut_ptr(),
+                                        );
+                                    }
                                     //let keysym = keysym.assume_init();
                                     let status = status.assume_init();
                                     if status != X11_sys::XBufferOverflow {
@@ -638,6 +669,20 @@ impl XlibApp {
                                 }
                             }
                         },
+                        X11_sys::FocusIn => {
+                            if let Some(window_ptr) = self.window_map.get(&event.xfocus.window) {
+                                if let Some(xic) = (**window_ptr).xic {
+                                    X11_sys::XSetICFocus(xic);
+                                }
+                            }
+                        },
+                        X11_sys::FocusOut => {
+                            if let Some(window_ptr) = self.window_map.get(&event.xfocus.window) {
+                                if let Some(xic) = (**window_ptr).xic {
+                                    X11_sys::XUnsetICFocus(xic);
+                                }
+                            }
+                        },
                         X11_sys::KeyRelease => {
                             self.do_callback(&mut vec![Event::KeyUp(KeyEvent {
                                 key_code: self.xkeyevent_to_keycode(&mut event.xkey),
@@ -779,9 +824,7 @@ impl XlibApp {
                 let mut signals = HashMap::new();
                 signals.insert(signal, vec![status]);
                 signals_locked.push(Event::Signal(SignalEvent {signals}));
-                //let mut f = unsafe { File::from_raw_fd((*GLOBAL_XLIB_APP).display_fd) };
-                //let _ = write!(&mut f, "\0");
-                // !TODO unblock the select!
+                libc::write((*GLOBAL_XLIB_APP).signal_fds[1], [0u8].as_ptr() as *const c_void, 1);
             }
         }
     }
@@ -1037,6 +1080,9 @@ impl XlibWindow {
             time_start: xlib_app.time_start,
             last_nc_mode: None,
             ime_spot: Vec2::default(),
+            ime_callbacks: Vec::new(),
+            ime_preedit: Vec::new(),
+            ime_preedit_caret: 0,
             current_cursor: MouseCursor::Default,
             last_mouse_pos: Vec2::default(),
             fingers_down: fingers_down,
@@ -1099,7 +1145,7 @@ impl XlibWindow {
             X11_sys::XMapWindow(display, window);
             X11_sys::XFlush(display);
             
-            let xic = X11_sys::XCreateIC((*self.xlib_app).xim, CStr::from_bytes_with_nul(X11_sys::XNInputStyle.as_ref()).unwrap().as_ptr(), (X11_sys::XIMPreeditNothing | X11_sys::XIMStatusNothing) as i32, CStr::from_bytes_with_nul(X11_sys::XNClientWindow.as_ref()).unwrap().as_ptr(), window, CStr::from_bytes_with_nul(X11_sys::XNFocusWindow.as_ref()).unwrap().as_ptr(), window, ptr::null_mut() as *mut c_void);
+            let xic = self.create_ime_context(window);
             
             // Create a window
             (*self.xlib_app).window_map.insert(window, self);
@@ -1107,7 +1153,7 @@ impl XlibWindow {
             self.attributes = Some(attributes);
             self.visual_info = Some(visual_info);
             self.window = Some(window);
-            self.xic = Some(xic);
+            self.xic = xic;
             self.last_window_geom = self.get_window_geom();
             
             (*self.xlib_app).event_recur_block = false;
@@ -1344,8 +1390,116 @@ impl XlibWindow {
         (time_now - self.time_start) as f64 / 1_000_000_000.0
     }
     
+    // on the spot when the input method can, then we draw what it composes. otherwise over the spot,
+    // where it draws that itself at the spot we give it
+    unsafe fn create_ime_context(&mut self, window: c_ulong) -> Option<X11_sys::XIC> {
+        let xim = (*self.xlib_app).xim;
+        if xim == ptr::null_mut() {
+            return None
+        }
+        let mut styles: *mut X11_sys::XIMStyles = ptr::null_mut();
+        X11_sys::XGetIMValues(xim, X11_sys::XNQueryInputStyle.as_ptr() as *const c_char, &mut styles, ptr::null_mut::<c_void>());
+        let mut supported = Vec::new();
+        if styles != ptr::null_mut() {
+            supported.extend_from_slice(slice::from_raw_parts((*styles).supported_styles, (*styles).count_styles as usize));
+            X11_sys::XFree(styles as *mut c_void);
+        }
+        let create = | style: c_ulong, preedit_attributes: X11_sys::XVaNestedList | {
+            let xic = X11_sys::XCreateIC(
+                xim,
+                X11_sys::XNInputStyle.as_ptr() as *const c_char,
+                style,
+                X11_sys::XNClientWindow.as_ptr() as *const c_char,
+                window,
+                X11_sys::XNFocusWindow.as_ptr() as *const c_char,
+                window,
+                X11_sys::XNPreeditAttributes.as_ptr() as *const c_char,
+                preedit_attributes,
+                ptr::null_mut::<c_void>()
+            );
+            X11_sys::XFree(preedit_attributes);
+            if xic == ptr::null_mut() {None} else {Some(xic)}
+        };
+        
+        let style = (X11_sys::XIMPreeditCallbacks | X11_sys::XIMStatusNothing) as c_ulong;
+        if supported.contains(&style) {
+            let client_data = window as X11_sys::XPointer;
+            let start: X11_sys::XICProc = Some(xim_preedit_start);
+            self.ime_callbacks = vec![
+                X11_sys::XIMCallback {client_data: client_data, callback: mem::transmute(start)},
+                X11_sys::XIMCallback {client_data: client_data, callback: Some(xim_preedit_done)},
+                X11_sys::XIMCallback {client_data: client_data, callback: Some(xim_preedit_draw)},
+                X11_sys::XIMCallback {client_data: client_data, callback: Some(xim_preedit_caret)},
+            ];
+            let preedit_attributes = X11_sys::XVaCreateNestedList(
+                0,
+                X11_sys::XNPreeditStartCallback.as_ptr() as *const c_char,
+                &self.ime_callbacks[0] as *const X11_sys::XIMCallback,
+                X11_sys::XNPreeditDoneCallback.as_ptr() as *const c_char,
+                &self.ime_callbacks[1] as *const X11_sys::XIMCallback,
+                X11_sys::XNPreeditDrawCallback.as_ptr() as *const c_char,
+                &self.ime_callbacks[2] as *const X11_sys::XIMCallback,
+                X11_sys::XNPreeditCaretCallback.as_ptr() as *const c_char,
+                &self.ime_callbacks[3] as *const X11_sys::XIMCallback,
+                ptr::null_mut::<c_void>()
+            );
+            if let Some(xic) = create(style, preedit_attributes) {
+                return Some(xic)
+            }
+        }
+        let style = (X11_sys::XIMPreeditPosition | X11_sys::XIMStatusNothing) as c_ulong;
+        if supported.contains(&style) {
+            let spot = X11_sys::XPoint {x: 0, y: 0};
+            let preedit_attributes = X11_sys::XVaCreateNestedList(
+                0,
+                X11_sys::XNSpotLocation.as_ptr() as *const c_char,
+                &spot as *const X11_sys::XPoint,
+                ptr::null_mut::<c_void>()
+            );
+            if let Some(xic) = create(style, preedit_attributes) {
+                return Some(xic)
+            }
+        }
+        let xic = X11_sys::XCreateIC(
+            xim,
+            X11_sys::XNInputStyle.as_ptr() as *const c_char,
+            (X11_sys::XIMPreeditNothing | X11_sys::XIMStatusNothing) as c_ulong,
+            X11_sys::XNClientWindow.as_ptr() as *const c_char,
+            window,
+            X11_sys::XNFocusWindow.as_ptr() as *const c_char,
+            window,
+            ptr::null_mut::<c_void>()
+        );
+        if xic == ptr::null_mut() {None} else {Some(xic)}
+    }
+    
+    // spot is the bottom left of the text cursor, the input method puts its windows under it
     pub fn set_ime_spot(&mut self, spot: Vec2) {
         self.ime_spot = spot;
+        if let Some(xic) = self.xic {
+            unsafe {
+                let dpi_factor = self.last_window_geom.dpi_factor;
+                let spot = X11_sys::XPoint {x: (spot.x * dpi_factor) as i16, y: (spot.y * dpi_factor) as i16};
+                let preedit_attributes = X11_sys::XVaCreateNestedList(
+                    0,
+                    X11_sys::XNSpotLocation.as_ptr() as *const c_char,
+                    &spot as *const X11_sys::XPoint,
+                    ptr::null_mut::<c_void>()
+                );
+                X11_sys::XSetICValues(xic, X11_sys::XNPreeditAttributes.as_ptr() as *const c_char, preedit_attributes, ptr::null_mut::<c_void>());
+                X11_sys::XFree(preedit_attributes);
+            }
+        }
+    }
+    
+    pub fn send_text_composition(&mut self, state: TextCompositionState) {
+        let text = self.ime_preedit.iter().collect();
+        let cursor = self.ime_preedit_caret.min(self.ime_preedit.len());
+        self.do_callback(&mut vec![Event::TextComposition(TextCompositionEvent {
+            state: state,
+            text: text,
+            cursor: cursor
+        })])
     }
     
     pub fn get_position(&self) -> Vec2 {
@@ -1564,6 +1718,83 @@ impl XlibWindow {
     
 }
 
+// the preedit callbacks of an on the spot input context, client_data is the window
+
+unsafe fn xim_window(client_data: X11_sys::XPointer) -> Option<&'static mut XlibWindow> {
+    if GLOBAL_XLIB_APP == ptr::null_mut() {
+        return None
+    }
+    (*GLOBAL_XLIB_APP).window_map.get(&(client_data as c_ulong)).map( | window | &mut **window)
+}
+
+unsafe extern "C" fn xim_preedit_start(_xic: X11_sys::XIC, client_data: X11_sys::XPointer, _call_data: X11_sys::XPointer) -> c_int {
+    if let Some(window) = xim_window(client_data) {
+        window.ime_preedit.truncate(0);
+        window.ime_preedit_caret = 0;
+        window.send_text_composition(TextCompositionState::Start);
+    }
+    // no limit on the length of the preedit
+    -1
+}
+
+unsafe extern "C" fn xim_preedit_done(_xim: X11_sys::XIM, client_data: X11_sys::XPointer, _call_data: X11_sys::XPointer) {
+    if let Some(window) = xim_window(client_data) {
+        window.ime_preedit.truncate(0);
+        window.ime_preedit_caret = 0;
+        window.send_text_composition(TextCompositionState::Commit);
+    }
+}
+
+unsafe extern "C" fn xim_preedit_draw(_xim: X11_sys::XIM, client_data: X11_sys::XPointer, call_data: X11_sys::XPointer) {
+    let window = match xim_window(client_data) {
+        Some(window) => window,
+        None => return
+    };
+    let draw = &*(call_data as *const X11_sys::XIMPreeditDrawCallbackStruct);
+    let len = window.ime_preedit.len();
+    let first = (draw.chg_first.max(0) as usize).min(len);
+    let end = (first + draw.chg_length.max(0) as usize).min(len);
+    let mut chars = Vec::new();
+    if draw.text != ptr::null_mut() {
+        let text = &*draw.text;
+        if text.encoding_is_wchar != 0 {
+            if text.string.wide_char != ptr::null_mut() {
+                let wide = slice::from_raw_parts(text.string.wide_char, text.length as usize);
+                chars.extend(wide.iter().filter_map( | ch | std::char::from_u32(*ch as u32)));
+            }
+        }
+        else if text.string.multi_byte != ptr::null_mut() {
+            chars.extend(CStr::from_ptr(text.string.multi_byte).to_string_lossy().chars());
+        }
+        else {
+            // only the looks of the changed part changed
+            chars.extend_from_slice(&window.ime_preedit[first..end]);
+        }
+    }
+    window.ime_preedit.splice(first..end, chars);
+    window.ime_preedit_caret = draw.caret.max(0) as usize;
+    window.send_text_composition(TextCompositionState::Update);
+}
+
+unsafe extern "C" fn xim_preedit_caret(_xim: X11_sys::XIM, client_data: X11_sys::XPointer, call_data: X11_sys::XPointer) {
+    let window = match xim_window(client_data) {
+        Some(window) => window,
+        None => return
+    };
+    let caret = &mut *(call_data as *mut X11_sys::XIMPreeditCaretCallbackStruct);
+    let len = window.ime_preedit.len();
+    window.ime_preedit_caret = match caret.direction {
+        X11_sys::XIMCaretDirection_XIMForwardChar => (window.ime_preedit_caret + 1).min(len),
+        X11_sys::XIMCaretDirection_XIMBackwardChar => window.ime_preedit_caret.max(1) - 1,
+        X11_sys::XIMCaretDirection_XIMLineStart => 0,
+        X11_sys::XIMCaretDirection_XIMLineEnd => len,
+        X11_sys::XIMCaretDirection_XIMAbsolutePosition => (caret.position.max(0) as usize).min(len),
+        _ => window.ime_preedit_caret
+    };
+    caret.position = window.ime_preedit_caret as c_int;
+    window.send_text_composition(TextCompositionState::Update);
+}
+
 #[derive(Clone, Copy, PartialEq)]
 #[repr(C)]
 struct MwmHints {
@@ -25,6 +25,21 @@ pub struct FingerDownEvent {
     pub time: f64
 }
 
+impl FingerDownEvent {
+    // the right mouse button, or control click on macos. platforms number their buttons differently
+    pub fn is_context_click(&self) -> bool {
+        if self.is_touch {
+            return false
+        }
+        if cfg!(target_os = "macos") && self.digit == 0 && self.modifiers.control {
+            return true
+        }
+        if cfg!(target_os = "linux") {self.digit == 3}
+        else if cfg!(target_arch = "wasm32") {self.digit == 2}
+        else {self.digit == 1}
+    }
+}
+
 #[derive(Clone, Default, Debug, PartialEq)]
 pub struct FingerMoveEvent {
     pub window_id: usize,
@@ -181,6 +196,12 @@ pub struct KeyFocusEvent {
     pub focus: Area,
 }
 
+#[derive(Clone, Debug, PartialEq)]
+pub struct A11yActionEvent {
+    pub area: Area,
+    pub action: A11yAction,
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct TextInputEvent {
     pub input: String,
@@ -188,6 +209,23 @@ pub struct TextInputEvent {
     pub was_paste: bool
 }
 
+#[derive(Clone, Debug, PartialEq)]
+pub enum TextCompositionState {
+    Start,
+    Update,
+    Commit
+}
+
+// text an input method is composing, not yet part of the document. the composed text
+// itself arrives as a TextInput right after the Commit, so widgets can ignore these
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextCompositionEvent {
+    pub state: TextCompositionState,
+    pub text: String,
+    // the caret inside text, in chars
+    pub cursor: usize
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct TextCopyEvent {
     pub response: Option<String>
@@ -258,8 +296,10 @@ pub enum Event {
     KeyDown(KeyEvent),
     KeyUp(KeyEvent),
     TextInput(TextInputEvent),
+    TextComposition(TextCompositionEvent),
     TextCopy(TextCopyEvent),
-    ShaderRecompile(ShaderRecompileEvent)
+    ShaderRecompile(ShaderRecompileEvent),
+    A11yAction(A11yActionEvent)
 }
 
 impl Default for Event {
@@ -306,6 +346,11 @@ impl Event {
                     return self.clone();
                 }
             },
+            Event::TextComposition(_) => {
+                if area == cx.key_focus {
+                    return self.clone();
+                }
+            },
             Event::TextCopy(_) => {
                 if area == cx.key_focus {
                     return Event::TextCopy(
@@ -313,6 +358,11 @@ impl Event {
                     );
                 }
             },
+            Event::A11yAction(ae) => {
+                if area == ae.area {
+                    return self.clone();
+                }
+            },
             Event::Animate(_) => {
                 for anim in &cx.playing_anim_areas {
                     if anim.area == area {
@@ -533,6 +583,19 @@ impl FileRead {
         }
         return None
     }
+
+    pub fn resolve_bin<'a>(&mut self, fr: &'a FileReadEvent) -> Option<Result<&'a [u8],String>> {
+        if fr.read_id == self.read_id {
+            self.read_id = 0;
+            if let Ok(data) = &fr.data {
+                return Some(Ok(&data))
+            }
+            else if let Err(err) = &fr.data {
+                return Some(Err(format!("can't load file {} {}", self.path, err)))
+            }
+        }
+        return None
+    }
 }
 
 #[derive(Clone, Debug, Default)]
@@ -0,0 +1,79 @@
+use crate::cx::*;
+
+// Key focus traversal
+// Widgets that take the key focus with Tab add their area while they draw, to the view they draw in.
+// Tab and shift+Tab walk them in draw order, overlays last. When the key focus is inside a view
+// with is_focus_scope, like a dialog, Tab keeps it among the focusables of that view.
+
+impl Cx {
+    pub fn add_focusable(&mut self, area: Area) {
+        if let Some(view_id) = self.view_stack.last() {
+            let cxview = &mut self.views[*view_id];
+            cxview.focusables.push((cxview.draw_calls_len, area));
+        }
+    }
+    
+    // the focusables of a view and its sub views in draw order, with the focus scope they are in
+    fn collect_focusables(&self, view_id: usize, scope: Option<usize>, seen: &mut Vec<usize>, focusables: &mut Vec<(Area, Option<usize>)>) {
+        // overlays are sub views of the main view once it was drawn with them
+        if seen.contains(&view_id) {
+            return
+        }
+        seen.push(view_id);
+        let cxview = &self.views[view_id];
+        let scope = if cxview.is_focus_scope {Some(view_id)} else {scope};
+        let mut next = 0;
+        for draw_call_id in 0..cxview.draw_calls_len {
+            let sub_view_id = cxview.draw_calls[draw_call_id].sub_view_id;
+            if sub_view_id != 0 {
+                // the ones added before the sub view was
+                while next < cxview.focusables.len() && cxview.focusables[next].0 <= draw_call_id {
+                    focusables.push((cxview.focusables[next].1, scope));
+                    next += 1;
+                }
+                self.collect_focusables(sub_view_id, scope, seen, focusables);
+            }
+        }
+        for (_, area) in &cxview.focusables[next..] {
+            focusables.push((*area, scope));
+        }
+    }
+    
+    // moves the key focus to the next or previous focusable of a window. It does nothing when
+    // something that is not a focusable has the key focus, a code editor wants its Tab key.
+    pub fn focus_next(&mut self, window_id: usize, backward: bool) -> bool {
+        let pass_id = if let Some(pass_id) = self.windows[window_id].main_pass_id {pass_id} else {
+            return false
+        };
+        let mut focusables = Vec::new();
+        let mut seen = Vec::new();
+        if let Some(main_view_id) = self.passes[pass_id].main_view_id {
+            self.collect_focusables(main_view_id, None, &mut seen, &mut focusables);
+        }
+        for view_id in &self.passes[pass_id].overlay_view_ids {
+            self.collect_focusables(*view_id, None, &mut seen, &mut focusables);
+        }
+        focusables.retain( | (area, _) | area.is_valid(self));
+        if focusables.len() == 0 {
+            return false
+        }
+        let current = focusables.iter().position( | (area, _) | *area == self.key_focus);
+        if current.is_none() && self.key_focus != Area::Empty && self.key_focus.is_valid(self) {
+            return false
+        }
+        // without a focus it starts in what was drawn on top
+        let scope = match current {
+            Some(index) => focusables[index].1,
+            None => focusables[focusables.len() - 1].1
+        };
+        let in_scope: Vec<Area> = focusables.iter().filter( | (_, s) | *s == scope).map( | (area, _) | *area).collect();
+        let pos = in_scope.iter().position( | area | *area == self.key_focus);
+        let len = in_scope.len();
+        let next = match pos {
+            None => if backward {len - 1} else {0},
+            Some(pos) => if backward {(pos + len - 1) % len} else {(pos + 1) % len}
+        };
+        self.set_key_focus(in_scope[next]);
+        true
+    }
+}
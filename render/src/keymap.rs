@@ -0,0 +1,408 @@
+use crate::cx::*;
+use makepad_microserde::*;
+
+// Keymap
+// Commands are registered by name ("editor.undo") with a label and default keys.
+// Bindings map one or more key strokes (a chord like "mod+k mod+c") to a command,
+// optionally restricted to a context such as "editor" or "log".
+
+#[derive(Clone, Debug, PartialEq, SerRon, DeRon)]
+pub struct KeyBinding {
+    pub keys: String,
+    pub command: String,
+    pub context: Option<String>
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub struct KeyStroke {
+    pub key_code: KeyCode,
+    pub shift: bool,
+    pub control: bool,
+    pub alt: bool,
+    pub logo: bool,
+    // matches either control or logo, so one binding works on all platforms
+    pub any_mod: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyMapBinding {
+    pub strokes: Vec<KeyStroke>,
+    pub command: String,
+    pub context: Option<String>
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyMapCommand {
+    pub name: String,
+    pub label: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum KeyMapMatch {
+    None,
+    Pending,
+    Command(String)
+}
+
+#[derive(Clone, Default)]
+pub struct KeyMap {
+    pub commands: Vec<KeyMapCommand>,
+    pub default_bindings: Vec<KeyMapBinding>,
+    pub user_bindings: Vec<KeyMapBinding>,
+    pub pending: Vec<KeyStroke>,
+    pub _prev_pending: Vec<KeyStroke>,
+    pub _event_time: f64,
+}
+
+impl KeyStroke {
+    pub fn from_key_event(ke: &KeyEvent) -> KeyStroke {
+        KeyStroke {
+            key_code: ke.key_code,
+            shift: ke.modifiers.shift,
+            control: ke.modifiers.control,
+            alt: ke.modifiers.alt,
+            logo: ke.modifiers.logo,
+            any_mod: false
+        }
+    }
+
+    pub fn parse(stroke: &str) -> Result<KeyStroke, String> {
+        let mut ks = KeyStroke::default();
+        let mut key_code = None;
+        for part in stroke.split('+') {
+            match part.trim().to_lowercase().as_ref() {
+                "shift" => ks.shift = true,
+                "ctrl" | "control" => ks.control = true,
+                "alt" | "option" => ks.alt = true,
+                "logo" | "cmd" | "super" | "meta" => ks.logo = true,
+                "mod" => ks.any_mod = true,
+                name => if let Some(kc) = keycode_from_name(name) {
+                    if key_code.is_some() {
+                        return Err(format!("key stroke {} has more than one key", stroke))
+                    }
+                    key_code = Some(kc);
+                }
+                else {
+                    return Err(format!("unknown key {} in {}", name, stroke))
+                }
+            }
+        }
+        if let Some(key_code) = key_code {
+            ks.key_code = key_code;
+            Ok(ks)
+        }
+        else {
+            Err(format!("key stroke {} has no key", stroke))
+        }
+    }
+
+    pub fn matches(&self, other: &KeyStroke) -> bool {
+        if self.key_code != other.key_code || self.shift != other.shift || self.alt != other.alt {
+            return false
+        }
+        if self.any_mod {
+            return (other.control || other.logo) && !(self.control && !other.control) && !(self.logo && !other.logo)
+        }
+        self.control == other.control && self.logo == other.logo
+    }
+}
+
+impl std::fmt::Display for KeyStroke {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.any_mod {write!(f, "Mod+")?;}
+        if self.control {write!(f, "Ctrl+")?;}
+        if self.logo {write!(f, "Cmd+")?;}
+        if self.alt {write!(f, "Alt+")?;}
+        if self.shift {write!(f, "Shift+")?;}
+        write!(f, "{}", keycode_to_name(self.key_code))
+    }
+}
+
+impl KeyMapBinding {
+    pub fn parse(keys: &str, command: &str, context: Option<&str>) -> Result<KeyMapBinding, String> {
+        let mut strokes = Vec::new();
+        for stroke in keys.split_whitespace() {
+            strokes.push(KeyStroke::parse(stroke) ?);
+        }
+        if strokes.len() == 0 {
+            return Err(format!("empty key binding for {}", command))
+        }
+        Ok(KeyMapBinding {
+            strokes: strokes,
+            command: command.to_string(),
+            context: context.map( | c | c.to_string())
+        })
+    }
+
+    pub fn keys_to_string(&self) -> String {
+        self.strokes.iter().map( | s | s.to_string()).collect::<Vec<String>>().join(" ")
+    }
+
+    fn in_context(&self, context: &str) -> bool {
+        if let Some(own) = &self.context {own == context} else {true}
+    }
+}
+
+impl KeyMap {
+    pub fn has_command(&self, name: &str) -> bool {
+        self.commands.iter().find( | c | c.name == name).is_some()
+    }
+
+    pub fn register_command(&mut self, name: &str, label: &str, default_keys: &[&str], context: Option<&str>) {
+        if self.has_command(name) {
+            return
+        }
+        self.commands.push(KeyMapCommand {name: name.to_string(), label: label.to_string()});
+        for keys in default_keys {
+            match KeyMapBinding::parse(keys, name, context) {
+                Ok(binding) => self.default_bindings.push(binding),
+                Err(err) => println!("Invalid default key binding {}", err)
+            }
+        }
+    }
+
+    // user bindings are searched before the defaults, an empty command unbinds the keys
+    pub fn set_user_bindings(&mut self, bindings: &Vec<KeyBinding>) -> Vec<String> {
+        let mut errors = Vec::new();
+        self.user_bindings.truncate(0);
+        self.pending.truncate(0);
+        for binding in bindings {
+            match KeyMapBinding::parse(&binding.keys, &binding.command, binding.context.as_ref().map( | c | c.as_ref())) {
+                Ok(binding) => self.user_bindings.push(binding),
+                Err(err) => errors.push(err)
+            }
+        }
+        errors
+    }
+
+    pub fn bindings_for_command(&self, name: &str) -> Vec<&KeyMapBinding> {
+        self.user_bindings.iter().rev().chain(self.default_bindings.iter().rev()).filter( | b | b.command == name).collect()
+    }
+
+    pub fn match_key_down(&mut self, ke: &KeyEvent, context: &str) -> KeyMapMatch {
+        match ke.key_code {
+            KeyCode::Shift | KeyCode::Control | KeyCode::Alt | KeyCode::Logo => return KeyMapMatch::None,
+            _ => ()
+        }
+        // several contexts look at the same key event, they all have to see the chord state from before it
+        if ke.time != self._event_time {
+            self._event_time = ke.time;
+            self._prev_pending = std::mem::replace(&mut self.pending, Vec::new());
+        }
+        let mut strokes = self._prev_pending.clone();
+        strokes.push(KeyStroke::from_key_event(ke));
+
+        let mut found: Option<&KeyMapBinding> = None;
+        let mut is_prefix = false;
+        for binding in self.user_bindings.iter().rev().chain(self.default_bindings.iter().rev()) {
+            if !binding.in_context(context) || binding.strokes.len() < strokes.len() {
+                continue
+            }
+            if !binding.strokes.iter().zip(strokes.iter()).all( | (a, b) | a.matches(b)) {
+                continue
+            }
+            if binding.strokes.len() == strokes.len() {
+                // context specific bindings win over global ones
+                if found.is_none() || found.unwrap().context.is_none() && binding.context.is_some() {
+                    found = Some(binding);
+                }
+            }
+            else {
+                is_prefix = true;
+            }
+        }
+        // a chord starting with these keys waits for its next stroke, even if they are a binding of their own
+        if is_prefix {
+            self.pending = strokes;
+            return KeyMapMatch::Pending
+        }
+        if let Some(binding) = found {
+            if binding.command.len() == 0 {
+                return KeyMapMatch::None
+            }
+            return KeyMapMatch::Command(binding.command.clone())
+        }
+        KeyMapMatch::None
+    }
+}
+
+pub fn keycode_from_name(name: &str) -> Option<KeyCode> {
+    Some(match name {
+        "escape" | "esc" => KeyCode::Escape,
+        "backtick" | "`" => KeyCode::Backtick,
+        "0" => KeyCode::Key0,
+        "1" => KeyCode::Key1,
+        "2" => KeyCode::Key2,
+        "3" => KeyCode::Key3,
+        "4" => KeyCode::Key4,
+        "5" => KeyCode::Key5,
+        "6" => KeyCode::Key6,
+        "7" => KeyCode::Key7,
+        "8" => KeyCode::Key8,
+        "9" => KeyCode::Key9,
+        "minus" | "-" => KeyCode::Minus,
+        "equals" | "=" => KeyCode::Equals,
+        "backspace" => KeyCode::Backspace,
+        "tab" => KeyCode::Tab,
+        "q" => KeyCode::KeyQ,
+        "w" => KeyCode::KeyW,
+        "e" => KeyCode::KeyE,
+        "r" => KeyCode::KeyR,
+        "t" => KeyCode::KeyT,
+        "y" => KeyCode::KeyY,
+        "u" => KeyCode::KeyU,
+        "i" => KeyCode::KeyI,
+        "o" => KeyCode::KeyO,
+        "p" => KeyCode::KeyP,
+        "lbracket" | "[" => KeyCode::LBracket,
+        "rbracket" | "]" => KeyCode::RBracket,
+        "return" | "enter" => KeyCode::Return,
+        "a" => KeyCode::KeyA,
+        "s" => KeyCode::KeyS,
+        "d" => KeyCode::KeyD,
+        "f" => KeyCode::KeyF,
+        "g" => KeyCode::KeyG,
+        "h" => KeyCode::KeyH,
+        "j" => KeyCode::KeyJ,
+        "k" => KeyCode::KeyK,
+        "l" => KeyCode::KeyL,
+        "semicolon" | ";" => KeyCode::Semicolon,
+        "quote" | "'" => KeyCode::Quote,
+        "backslash" | "\\" => KeyCode::Backslash,
+        "z" => KeyCode::KeyZ,
+        "x" => KeyCode::KeyX,
+        "c" => KeyCode::KeyC,
+        "v" => KeyCode::KeyV,
+        "b" => KeyCode::KeyB,
+        "n" => KeyCode::KeyN,
+        "m" => KeyCode::KeyM,
+        "comma" | "," => KeyCode::Comma,
+        "period" | "." => KeyCode::Period,
+        "slash" | "/" => KeyCode::Slash,
+        "space" => KeyCode::Space,
+        "f1" => KeyCode::F1,
+        "f2" => KeyCode::F2,
+        "f3" => KeyCode::F3,
+        "f4" => KeyCode::F4,
+        "f5" => KeyCode::F5,
+        "f6" => KeyCode::F6,
+        "f7" => KeyCode::F7,
+        "f8" => KeyCode::F8,
+        "f9" => KeyCode::F9,
+        "f10" => KeyCode::F10,
+        "f11" => KeyCode::F11,
+        "f12" => KeyCode::F12,
+        "insert" => KeyCode::Insert,
+        "delete" | "del" => KeyCode::Delete,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        "up" => KeyCode::ArrowUp,
+        "down" => KeyCode::ArrowDown,
+        "left" => KeyCode::ArrowLeft,
+        "right" => KeyCode::ArrowRight,
+        _ => return None
+    })
+}
+
+pub fn keycode_to_name(key_code: KeyCode) -> String {
+    match key_code {
+        KeyCode::Key0 => "0".to_string(),
+        KeyCode::Key1 => "1".to_string(),
+        KeyCode::Key2 => "2".to_string(),
+        KeyCode::Key3 => "3".to_string(),
+        KeyCode::Key4 => "4".to_string(),
+        KeyCode::Key5 => "5".to_string(),
+        KeyCode::Key6 => "6".to_string(),
+        KeyCode::Key7 => "7".to_string(),
+        KeyCode::Key8 => "8".to_string(),
+        KeyCode::Key9 => "9".to_string(),
+        KeyCode::ArrowUp => "Up".to_string(),
+        KeyCode::ArrowDown => "Down".to_string(),
+        KeyCode::ArrowLeft => "Left".to_string(),
+        KeyCode::ArrowRight => "Right".to_string(),
+        _ => {
+            let name = format!("{:?}", key_code);
+            if name.starts_with("Key") {name[3..].to_string()} else {name}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(key_code: KeyCode, control: bool, logo: bool, time: f64) -> KeyEvent {
+        KeyEvent {
+            key_code: key_code,
+            is_repeat: false,
+            modifiers: KeyModifiers {control: control, logo: logo, ..KeyModifiers::default()},
+            time: time
+        }
+    }
+
+    #[test]
+    fn mod_is_control_or_logo() {
+        let binding = KeyStroke::parse("mod+s").unwrap();
+        assert!(binding.any_mod && !binding.control && !binding.logo);
+        // ctrl on linux and windows, cmd on macos
+        assert!(binding.matches(&KeyStroke::from_key_event(&key(KeyCode::KeyS, true, false, 0.))));
+        assert!(binding.matches(&KeyStroke::from_key_event(&key(KeyCode::KeyS, false, true, 0.))));
+        assert!(!binding.matches(&KeyStroke::from_key_event(&key(KeyCode::KeyS, false, false, 0.))));
+        // an explicit modifier next to mod still has to be held
+        let binding = KeyStroke::parse("mod+ctrl+s").unwrap();
+        assert!(!binding.matches(&KeyStroke::from_key_event(&key(KeyCode::KeyS, false, true, 0.))));
+        assert!(binding.matches(&KeyStroke::from_key_event(&key(KeyCode::KeyS, true, true, 0.))));
+    }
+
+    #[test]
+    fn parse_strokes() {
+        let ks = KeyStroke::parse("Ctrl + Shift + F5").unwrap();
+        assert_eq!(ks, KeyStroke {key_code: KeyCode::F5, control: true, shift: true, ..KeyStroke::default()});
+        assert_eq!(KeyStroke::parse("cmd+[").unwrap().key_code, KeyCode::LBracket);
+        assert!(KeyStroke::parse("ctrl+shift").is_err());
+        assert!(KeyStroke::parse("ctrl+a+b").is_err());
+        assert!(KeyStroke::parse("ctrl+nokey").is_err());
+        assert!(KeyMapBinding::parse("  ", "editor.undo", None).is_err());
+        assert!(KeyMapBinding::parse("mod+k bogus", "editor.undo", None).is_err());
+        let binding = KeyMapBinding::parse("mod+k mod+c", "editor.comment", Some("editor")).unwrap();
+        assert_eq!(binding.strokes.len(), 2);
+        assert_eq!(binding.context, Some("editor".to_string()));
+    }
+
+    #[test]
+    fn chord_prefix_waits_for_its_second_stroke() {
+        let mut key_map = KeyMap::default();
+        key_map.register_command("log.clear", "Clear Log", &["mod+k"], None);
+        key_map.register_command("editor.comment", "Comment", &["mod+k mod+c"], None);
+        assert_eq!(key_map.match_key_down(&key(KeyCode::KeyK, true, false, 1.), "editor"), KeyMapMatch::Pending);
+        assert_eq!(
+            key_map.match_key_down(&key(KeyCode::KeyC, true, false, 2.), "editor"),
+            KeyMapMatch::Command("editor.comment".to_string())
+        );
+        // a stroke that doesn't continue the chord drops it
+        assert_eq!(key_map.match_key_down(&key(KeyCode::KeyK, true, false, 3.), "editor"), KeyMapMatch::Pending);
+        assert_eq!(key_map.match_key_down(&key(KeyCode::KeyX, true, false, 4.), "editor"), KeyMapMatch::None);
+        assert_eq!(key_map.match_key_down(&key(KeyCode::KeyC, true, false, 5.), "editor"), KeyMapMatch::None);
+        // every context looking at the same key event sees the chord state from before it
+        assert_eq!(key_map.match_key_down(&key(KeyCode::KeyK, true, false, 6.), "editor"), KeyMapMatch::Pending);
+        assert_eq!(key_map.match_key_down(&key(KeyCode::KeyK, true, false, 6.), "log"), KeyMapMatch::Pending);
+    }
+
+    #[test]
+    fn context_bindings_win_over_global_ones() {
+        let mut key_map = KeyMap::default();
+        key_map.register_command("app.find", "Find", &["mod+f"], None);
+        key_map.register_command("log.find", "Find in Log", &["mod+f"], Some("log"));
+        assert_eq!(key_map.match_key_down(&key(KeyCode::KeyF, true, false, 1.), "log"), KeyMapMatch::Command("log.find".to_string()));
+        assert_eq!(key_map.match_key_down(&key(KeyCode::KeyF, true, false, 2.), "editor"), KeyMapMatch::Command("app.find".to_string()));
+        // user bindings go before the defaults, an empty command unbinds
+        key_map.set_user_bindings(&vec![
+            KeyBinding {keys: "mod+f".to_string(), command: "editor.find".to_string(), context: Some("editor".to_string())},
+            KeyBinding {keys: "mod+f".to_string(), command: "".to_string(), context: Some("log".to_string())},
+        ]);
+        assert_eq!(key_map.match_key_down(&key(KeyCode::KeyF, true, false, 3.), "editor"), KeyMapMatch::Command("editor.find".to_string()));
+        assert_eq!(key_map.match_key_down(&key(KeyCode::KeyF, true, false, 4.), "log"), KeyMapMatch::None);
+    }
+}
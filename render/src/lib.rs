@@ -16,6 +16,8 @@ mod cx_opengl;
 mod cx_xlib;
 #[cfg(all(not(feature="ipc"),any(target_os = "linux")))]
 mod cx_linux;
+#[cfg(all(not(feature="ipc"),any(target_os = "linux")))]
+mod cx_linux_atspi;
 
 #[cfg(all(not(feature="ipc"),target_os = "macos"))]
 mod cx_metal;
@@ -83,6 +85,11 @@ mod blit;
 mod text;
 mod events;
 mod menu; 
+mod popup;
+mod keymap;
+mod tooltips;
+mod focus;
+mod accessibility;
 mod shader;
 
 pub use crate::cx::*;
@@ -102,6 +102,15 @@ impl Pass {
     
     
     pub fn end_pass(&mut self, cx: &mut Cx) {
+        let pass_id = self.pass_id.unwrap();
+        if let Some(main_view_id) = cx.passes[pass_id].main_view_id {
+            // the overlays go last so they end up on top, if the root view was redrawn they need adding
+            if cx.views[main_view_id].redraw_id == cx.redraw_id {
+                for overlay_view_id in cx.passes[pass_id].overlay_view_ids.clone() {
+                    cx.views[main_view_id].push_sub_view(main_view_id, overlay_view_id, cx.redraw_id);
+                }
+            }
+        }
         cx.pass_stack.pop();
         if cx.pass_stack.len()>0{
             cx.current_dpi_factor = cx.get_delegated_dpi_factor(*cx.pass_stack.last().unwrap());
@@ -166,6 +175,8 @@ pub struct CxPass {
     pub clear_color: Color,
     pub override_dpi_factor: Option<f32>,
     pub main_view_id: Option<usize>,
+    // overlay views in the order they were drawn, topmost last
+    pub overlay_view_ids: Vec<usize>,
     pub dep_of: CxPassDepOf,
     pub paint_dirty: bool,
     pub pass_size: Vec2,
@@ -186,6 +197,7 @@ impl Default for CxPass {
             clear_color: Color::default(),
             depth_init: 1.0,
             main_view_id: None,
+            overlay_view_ids: Vec::new(),
             dep_of: CxPassDepOf::None,
             paint_dirty: false,
             pass_size: Vec2::default(),
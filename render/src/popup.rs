@@ -0,0 +1,297 @@
+use crate::cx::*;
+use crate::blit::*;
+
+// Content drawn above everything else in the window and outside the clip of whoever draws it,
+// for menus, tooltips and dropdowns. It is placed next to an anchor, a rect or the area of what
+// opened it, and moves to the other side when it would run off the window.
+// Handle the events of what is in the popup first, then handle_popup, then the rest of the ui,
+// so clicks on the popup don't reach what is below it.
+// With own_pass set the popup draws into a pass of its own the size of the window, and only a
+// blit of its texture goes into the overlay of the window. Its content then repaints without
+// repainting the window, and as the pass has the window's size, areas and hits keep window coords.
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PopupPlace {
+    Below,
+    Above,
+    Right,
+    Left,
+    // at the top left of the anchor, like a context menu at the mouse
+    Over,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PopupEvent {
+    None,
+    // clicked outside or escape, the popup is closed
+    Dismissed,
+    // a modal popup stays open, the click outside went nowhere
+    ClickedOutside,
+}
+
+#[derive(Clone)]
+pub struct Popup {
+    pub view: View,
+    pub place: PopupPlace,
+    // between the anchor and the popup
+    pub gap: f32,
+    // keeps the ui below from getting any finger events while open
+    pub modal: bool,
+    pub is_open: bool,
+    pub _anchor: Rect,
+    pub _anchor_area: Area,
+    // the size the placement used, the popup is placed again when the drawn size differs
+    pub _size: Vec2,
+    pub own_pass: Option<PopupPass>,
+}
+
+#[derive(Clone)]
+pub struct PopupPass {
+    pub pass: Pass,
+    pub texture: Texture,
+    // the root of the pass, the popup view is an overlay on it
+    pub root_view: View,
+    // the overlay in the window the texture is blitted into
+    pub blit_view: View,
+    pub blit: Blit,
+}
+
+impl PopupPass {
+    pub fn new(cx: &mut Cx) -> Self {
+        Self {
+            pass: Pass::default(),
+            texture: Texture::default(),
+            root_view: View {
+                always_redraw: true,
+                ..View::new(cx)
+            },
+            blit_view: View::proto_overlay(cx),
+            blit: Blit::style(cx),
+        }
+    }
+
+    fn begin_pass(&mut self, cx: &mut Cx, pos: Vec2, size: Vec2) {
+        if self.blit_view.begin_view(cx, Layout {
+            abs_origin: Some(pos),
+            walk: Walk::wh(Width::Fix(size.x), Height::Fix(size.y)),
+            ..Layout::default()
+        }).is_ok() {
+            // only the part of the texture under the popup
+            let pass_id = cx.views[self.blit_view.view_id.unwrap()].pass_id;
+            let window_size = cx.passes[pass_id].pass_size;
+            self.blit.min_x = pos.x / window_size.x;
+            self.blit.min_y = pos.y / window_size.y;
+            self.blit.max_x = (pos.x + size.x) / window_size.x;
+            self.blit.max_y = (pos.y + size.y) / window_size.y;
+            self.blit.draw_blit_abs(cx, &self.texture, Rect {x: pos.x, y: pos.y, w: size.x, h: size.y});
+            self.blit_view.end_view(cx);
+        }
+        self.pass.begin_pass(cx);
+        self.pass.add_color_texture(cx, &mut self.texture, ClearColor::ClearWith(Color::default()));
+        let _ = self.root_view.begin_view(cx, Layout::default());
+    }
+
+    fn end_pass(&mut self, cx: &mut Cx, redrawn: bool) {
+        self.root_view.end_view(cx);
+        self.pass.end_pass(cx);
+        // the window shows the new texture only when it paints again
+        if redrawn {
+            if let Some(view_id) = self.blit_view.view_id {
+                let pass_id = cx.views[view_id].pass_id;
+                cx.passes[pass_id].paint_dirty = true;
+            }
+        }
+    }
+}
+
+impl Popup {
+    pub fn new(cx: &mut Cx) -> Self {
+        Self {
+            view: View::proto_overlay(cx),
+            place: PopupPlace::Below,
+            gap: 0.,
+            modal: false,
+            is_open: false,
+            _anchor: Rect::default(),
+            _anchor_area: Area::Empty,
+            _size: Vec2::default(),
+            own_pass: None,
+        }
+    }
+
+    pub fn open(&mut self, cx: &mut Cx, anchor: Rect) {
+        self._anchor = anchor;
+        self._anchor_area = Area::Empty;
+        self.is_open = true;
+        self.redraw_popup(cx);
+    }
+
+    // follows the area while it is drawn, when it isn't the last rect it had is used
+    pub fn open_at_area(&mut self, cx: &mut Cx, area: Area) {
+        self.open(cx, area.get_rect(cx));
+        self._anchor_area = area;
+    }
+
+    // for an anchor that moves with what is drawn, call it before begin_popup
+    pub fn set_anchor(&mut self, anchor: Rect) {
+        self._anchor = anchor;
+        self._anchor_area = Area::Empty;
+    }
+
+    pub fn close(&mut self, cx: &mut Cx) {
+        if self.is_open {
+            self.is_open = false;
+            self._anchor_area = Area::Empty;
+            self.redraw_popup(cx);
+        }
+    }
+
+    pub fn redraw_popup(&self, cx: &mut Cx) {
+        self.view.redraw_view_area(cx);
+        if let Some(own_pass) = &self.own_pass {
+            own_pass.blit_view.redraw_view_area(cx);
+        }
+    }
+
+    // the view that is an overlay of the window
+    fn window_view(&self) -> &View {
+        if let Some(own_pass) = &self.own_pass {
+            return &own_pass.blit_view
+        }
+        &self.view
+    }
+
+    pub fn get_rect(&mut self, cx: &Cx) -> Rect {
+        if !self.is_open {
+            return Rect::default()
+        }
+        self.view.get_rect(cx)
+    }
+
+    // the last popup drawn in its pass gets the escape key
+    pub fn is_topmost(&self, cx: &Cx) -> bool {
+        if let Some(view_id) = self.window_view().view_id {
+            let pass_id = cx.views[view_id].pass_id;
+            return cx.passes[pass_id].overlay_view_ids.last() == Some(&view_id)
+        }
+        false
+    }
+
+    pub fn handle_popup(&mut self, cx: &mut Cx, event: &mut Event) -> PopupEvent {
+        if !self.is_open {
+            return PopupEvent::None
+        }
+        let rect = self.view.get_rect(cx);
+        match event {
+            Event::FingerDown(fe) => if !fe.handled {
+                if rect.contains(fe.abs.x, fe.abs.y) {
+                    fe.handled = true;
+                    return PopupEvent::None
+                }
+                if self.modal {
+                    fe.handled = true;
+                    return PopupEvent::ClickedOutside
+                }
+                // a click on what opened the popup only closes it, elsewhere it goes on to what is below
+                if self._anchor.contains(fe.abs.x, fe.abs.y) {
+                    fe.handled = true;
+                }
+                self.close(cx);
+                return PopupEvent::Dismissed
+            },
+            Event::FingerHover(fe) => if self.modal || rect.contains(fe.abs.x, fe.abs.y) {
+                fe.handled = true;
+            },
+            Event::FingerScroll(fe) => if self.modal || rect.contains(fe.abs.x, fe.abs.y) {
+                fe.handled = true;
+            },
+            Event::KeyDown(ke) => if ke.key_code == KeyCode::Escape && self.is_topmost(cx) {
+                self.close(cx);
+                return PopupEvent::Dismissed
+            },
+            _ => ()
+        }
+        PopupEvent::None
+    }
+
+    // where a popup of size goes next to anchor in a window of window_size
+    pub fn compute_pos(place: PopupPlace, anchor: Rect, size: Vec2, gap: f32, window_size: Vec2) -> Vec2 {
+        // along the side it opens to, flip when it doesn't fit and there is more room on the other side
+        fn flip(start: f32, end: f32, size: f32, gap: f32, window: f32, after: bool) -> f32 {
+            let room_after = window - end - gap;
+            let room_before = start - gap;
+            let after = if after {size <= room_after || room_after >= room_before} else {size > room_before && room_after > room_before};
+            if after {end + gap} else {start - gap - size}
+        }
+        // across, slide back into the window
+        fn slide(pos: f32, size: f32, window: f32) -> f32 {
+            pos.min(window - size).max(0.)
+        }
+        let pos = match place {
+            PopupPlace::Below | PopupPlace::Above => Vec2 {
+                x: slide(anchor.x, size.x, window_size.x),
+                y: flip(anchor.y, anchor.y + anchor.h, size.y, gap, window_size.y, place == PopupPlace::Below)
+            },
+            PopupPlace::Right | PopupPlace::Left => Vec2 {
+                x: flip(anchor.x, anchor.x + anchor.w, size.x, gap, window_size.x, place == PopupPlace::Right),
+                y: slide(anchor.y, size.y, window_size.y)
+            },
+            PopupPlace::Over => Vec2 {
+                x: if anchor.x + size.x > window_size.x && anchor.x - size.x >= 0. {anchor.x - size.x} else {slide(anchor.x, size.x, window_size.x)},
+                y: if anchor.y + size.y > window_size.y && anchor.y - size.y >= 0. {anchor.y - size.y} else {slide(anchor.y, size.y, window_size.y)},
+            }
+        };
+        Vec2 {x: pos.x.max(0.), y: pos.y.max(0.)}
+    }
+
+    fn window_size(&self, cx: &Cx) -> Vec2 {
+        let pass_id = if let Some(view_id) = self.window_view().view_id {
+            cx.views[view_id].pass_id
+        }
+        else {
+            *cx.pass_stack.last().expect("No pass found when begin_popup")
+        };
+        cx.passes[pass_id].pass_size
+    }
+
+    // the walk of the layout sizes the popup, Compute works as it is measured on the first draw
+    pub fn begin_popup(&mut self, cx: &mut Cx, layout: Layout) -> ViewRedraw {
+        if !self.is_open {
+            return Err(())
+        }
+        let anchor = self._anchor_area.get_rect(cx);
+        if anchor.w > 0. || anchor.h > 0. {
+            self._anchor = anchor;
+        }
+        let pos = Self::compute_pos(self.place, self._anchor, self._size, self.gap, self.window_size(cx));
+        // Tab stays inside a modal popup
+        self.view.is_focus_scope = self.modal;
+        if let Some(own_pass) = &mut self.own_pass {
+            own_pass.begin_pass(cx, pos, self._size);
+        }
+        let redraw = self.view.begin_view(cx, Layout {
+            abs_origin: Some(pos),
+            ..layout
+        });
+        if redraw.is_err() {
+            if let Some(own_pass) = &mut self.own_pass {
+                own_pass.end_pass(cx, false);
+            }
+        }
+        redraw
+    }
+
+    pub fn end_popup(&mut self, cx: &mut Cx) -> Area {
+        let area = self.view.end_view(cx);
+        if let Some(own_pass) = &mut self.own_pass {
+            own_pass.end_pass(cx, true);
+        }
+        let rect = self.view.get_rect(cx);
+        let size = Vec2 {x: rect.w, y: rect.h};
+        if size != self._size {
+            self._size = size;
+            self.redraw_popup(cx);
+        }
+        area
+    }
+}
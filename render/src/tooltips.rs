@@ -0,0 +1,66 @@
+use crate::cx::*;
+use std::rc::Rc;
+
+// Tooltips
+// Widgets register what to show for an area while they draw it, the Tooltip widget of the
+// window shows it when the mouse rests on that area. An area that is drawn again gets a new
+// Area, so registering again on every draw is what keeps a tooltip alive.
+
+#[derive(Clone)]
+pub enum TooltipContent {
+    Text(String),
+    // draws into the tooltip background, a turtle sized by what is drawn
+    Draw(Rc<dyn Fn(&mut Cx)>),
+}
+
+#[derive(Clone)]
+pub struct CxTooltip {
+    pub area: Area,
+    pub content: TooltipContent
+}
+
+impl Cx {
+    pub fn set_tooltip(&mut self, area: Area, content: TooltipContent) {
+        // the areas of an earlier draw are gone
+        let mut tooltips = std::mem::replace(&mut self.tooltips, Vec::new());
+        tooltips.retain( | tooltip | tooltip.area != area && tooltip.area.is_valid(self));
+        tooltips.push(CxTooltip {area: area, content: content});
+        self.tooltips = tooltips;
+    }
+
+    pub fn set_tooltip_text(&mut self, area: Area, text: &str) {
+        self.set_tooltip(area, TooltipContent::Text(text.to_string()));
+    }
+
+    pub fn clear_tooltip(&mut self, area: Area) {
+        self.tooltips.retain( | tooltip | tooltip.area != area);
+    }
+
+    fn area_window_id(&self, area: Area) -> Option<usize> {
+        let view_id = match area {
+            Area::Instance(inst) => inst.view_id,
+            Area::View(view_area) => view_area.view_id,
+            _ => return None
+        };
+        let mut pass_id = self.views[view_id].pass_id;
+        loop {
+            match self.passes[pass_id].dep_of {
+                CxPassDepOf::Window(window_id) => return Some(window_id),
+                CxPassDepOf::Pass(parent_id) => pass_id = parent_id,
+                CxPassDepOf::None => return None
+            }
+        }
+    }
+
+    // the tooltip under abs in a window, what was registered last is drawn on top
+    pub fn tooltip_at(&self, window_id: usize, abs: Vec2) -> Option<CxTooltip> {
+        for tooltip in self.tooltips.iter().rev() {
+            if tooltip.area.is_valid(self)
+                && self.area_window_id(tooltip.area) == Some(window_id)
+                && tooltip.area.get_rect(self).contains(abs.x, abs.y) {
+                return Some(tooltip.clone())
+            }
+        }
+        None
+    }
+}
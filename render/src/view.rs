@@ -15,6 +15,7 @@ pub struct View { // draw info per UI element
     pub is_clipped: bool,
     pub is_overlay: bool, // this view is an overlay, rendered last
     pub always_redraw: bool,
+    pub is_focus_scope: bool, // Tab keeps the key focus among the focusables inside this view
 }
 
 impl View {
@@ -23,6 +24,7 @@ impl View {
             is_clipped: true,
             is_overlay: true,
             always_redraw: false,
+            is_focus_scope: false,
             view_id: None,
         }
     }
@@ -32,6 +34,7 @@ impl View {
             is_clipped: true,
             is_overlay: false,
             always_redraw: false,
+            is_focus_scope: false,
             view_id: None,
         }
     }
@@ -82,47 +85,30 @@ impl View {
         };
         
         let cxpass = &mut cx.passes[pass_id];
-        // find the parent draw list id
-        let parent_view_id = if self.is_overlay {
+        if self.is_overlay {
             if cxpass.main_view_id.is_none() {
                 panic!("Cannot make overlay inside window without root view")
             };
-            let main_view_id = cxpass.main_view_id.unwrap();
-            main_view_id
+            // overlays go on top of the root view when the pass ends, whatever gets drawn after them
+            if !cxpass.overlay_view_ids.contains(&view_id) {
+                cxpass.overlay_view_ids.push(view_id);
+            }
         }
         else {
-            if let Some(last_view_id) = cx.view_stack.last() {
+            // find the parent draw list id
+            // the root of a pass begun inside a view of another pass is drawn by its own pass only
+            let parent_view_id = if is_root_for_pass {
+                view_id
+            }
+            else if let Some(last_view_id) = cx.view_stack.last() {
                 *last_view_id
             }
             else { // we have no parent
                 view_id
-            }
-        };
-        
-        // push ourselves up the parent draw_stack
-        if view_id != parent_view_id {
-            // we need a new draw
-            let parent_cxview = &mut cx.views[parent_view_id];
-            
-            let id = parent_cxview.draw_calls_len;
-            parent_cxview.draw_calls_len = parent_cxview.draw_calls_len + 1;
-            
-            // see if we need to add a new one
-            if parent_cxview.draw_calls_len > parent_cxview.draw_calls.len() {
-                parent_cxview.draw_calls.push({
-                    DrawCall {
-                        view_id: parent_view_id,
-                        draw_call_id: parent_cxview.draw_calls.len(),
-                        redraw_id: cx.redraw_id,
-                        sub_view_id: view_id,
-                        ..Default::default()
-                    }
-                })
-            }
-            else { // or reuse a sub list node
-                let draw = &mut parent_cxview.draw_calls[id];
-                draw.sub_view_id = view_id;
-                draw.redraw_id = cx.redraw_id;
+            };
+            // push ourselves up the parent draw_stack
+            if view_id != parent_view_id {
+                cx.views[parent_view_id].push_sub_view(parent_view_id, view_id, cx.redraw_id);
             }
         }
         
@@ -145,6 +131,9 @@ impl View {
         let last_redraw_id = cxview.redraw_id;
         cxview.redraw_id = cx.redraw_id;
         cxview.draw_calls_len = 0;
+        cxview.focusables.truncate(0);
+        cxview.a11y.truncate(0);
+        cxview.is_focus_scope = self.is_focus_scope;
         
         cx.view_stack.push(view_id);
         
@@ -156,6 +145,8 @@ impl View {
         
         if is_root_for_pass {
             cx.passes[pass_id].paint_dirty = true;
+            // the overlays drawn this time around are added again
+            cx.passes[pass_id].overlay_view_ids.truncate(0);
         }
         
         Ok(())
@@ -478,7 +469,10 @@ pub struct CxView {
     pub snapped_scroll: Vec2,
     pub platform: CxPlatformView,
     pub rect: Rect,
-    pub clipped: bool
+    pub clipped: bool,
+    pub focusables: Vec<(usize, Area)>, // with the draw_calls_len when added, to order them among the sub views
+    pub is_focus_scope: bool,
+    pub a11y: Vec<(usize, CxA11yEntry)>, // like focusables
 }
 
 impl CxView {
@@ -488,6 +482,31 @@ impl CxView {
         self.pass_id = pass_id;
     }
     
+    // view_id is the id of this view
+    pub fn push_sub_view(&mut self, view_id: usize, sub_view_id: usize, redraw_id: u64) {
+        let id = self.draw_calls_len;
+        self.draw_calls_len = self.draw_calls_len + 1;
+        
+        // see if we need to add a new one
+        if self.draw_calls_len > self.draw_calls.len() {
+            let draw_call_id = self.draw_calls.len();
+            self.draw_calls.push({
+                DrawCall {
+                    view_id: view_id,
+                    draw_call_id: draw_call_id,
+                    redraw_id: redraw_id,
+                    sub_view_id: sub_view_id,
+                    ..Default::default()
+                }
+            })
+        }
+        else { // or reuse a sub list node
+            let draw = &mut self.draw_calls[id];
+            draw.sub_view_id = sub_view_id;
+            draw.redraw_id = redraw_id;
+        }
+    }
+    
     pub fn get_scrolled_rect(&self) -> Rect {
         Rect {
             x: self.rect.x + self.parent_scroll.x,
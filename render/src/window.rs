@@ -32,6 +32,7 @@ impl Window {
                     },
                     position: self.create_position,
                 },
+                window_title: self.create_title.clone(),
                 ..Default::default()
             };
             let window_id;
@@ -204,6 +205,7 @@ pub struct CxWindow {
     pub window_topmost: Option<bool>,
     pub window_geom: WindowGeom,
     pub main_pass_id: Option<usize>,
+    pub window_title: String,
 }
 
 impl CxWindow {
@@ -2,6 +2,7 @@ use makepad_render::*;
 
 #[derive(Default, Clone)]
 pub struct ButtonLogic {
+    pub key_down: bool, // return or space went down while we had the key focus
 }
 
 #[derive(Clone, PartialEq)]
@@ -23,6 +24,33 @@ pub enum ButtonEvent {
 
 impl ButtonLogic {
     
+    // with the key focus, return or space press it and click it when they come up again. Widgets
+    // that are not buttons use this for their keyboard activation as well
+    pub fn handle_key_activation(&mut self, cx: &mut Cx, event: &mut Event, area: Area) -> ButtonEvent {
+        match event.hits(cx, area, HitOpt::default()) {
+            Event::KeyDown(ke) => match ke.key_code {
+                KeyCode::Return | KeyCode::Space => if !ke.is_repeat {
+                    self.key_down = true;
+                    return ButtonEvent::Down;
+                },
+                _ => ()
+            },
+            Event::KeyUp(ke) => match ke.key_code {
+                KeyCode::Return | KeyCode::Space => if self.key_down {
+                    self.key_down = false;
+                    return ButtonEvent::Clicked;
+                },
+                _ => ()
+            },
+            Event::KeyFocusLost(_) => if self.key_down {
+                self.key_down = false;
+                return ButtonEvent::Up;
+            },
+            _ => ()
+        }
+        ButtonEvent::None
+    }
+    
     pub fn handle_button_logic<F>(&mut self, cx: &mut Cx, event: &mut Event, area:Area, mut cb:F) -> ButtonEvent
     where F: FnMut(&mut Cx, ButtonLogicEvent, Area)
     {
@@ -46,6 +74,25 @@ impl ButtonLogic {
                     _ => ()
                 }
             },
+            Event::KeyDown(_) | Event::KeyUp(_) | Event::KeyFocusLost(_) => match self.handle_key_activation(cx, event, area) {
+                ButtonEvent::Down => {
+                    cb(cx, ButtonLogicEvent::Down, area);
+                    return ButtonEvent::Down;
+                },
+                ButtonEvent::Clicked => {
+                    cb(cx, ButtonLogicEvent::Default, area);
+                    return ButtonEvent::Clicked;
+                },
+                ButtonEvent::Up => {
+                    cb(cx, ButtonLogicEvent::Default, area);
+                    return ButtonEvent::Up;
+                },
+                _ => ()
+            },
+            Event::A11yAction(ae) => if ae.action == A11yAction::Press {
+                cb(cx, ButtonLogicEvent::Default, area);
+                return ButtonEvent::Clicked;
+            },
             Event::FingerUp(fe) => if fe.is_over {
                 if !fe.is_touch {cb(cx, ButtonLogicEvent::Over, area)}
                 else {cb(cx, ButtonLogicEvent::Default, area)}
@@ -0,0 +1,167 @@
+use makepad_render::*;
+use crate::buttonlogic::*;
+use crate::widgetstyle::*;
+
+// A box with a check mark in front of a label. The caller owns the value,
+// it is passed to draw_check_box and Toggled brings the new one back, which is shown right away.
+// Clicking it or pressing space when it has the key focus flips it.
+
+#[derive(Clone)]
+pub struct CheckBox {
+    pub button: ButtonLogic,
+    pub bg: Quad,
+    pub text: Text,
+    pub animator: Animator,
+    pub checked: bool,
+    pub _bg_area: Area,
+    pub _text_area: Area
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CheckBoxEvent {
+    None,
+    Toggled(bool)
+}
+
+impl CheckBox {
+    pub fn new(cx: &mut Cx) -> Self {
+        Self {
+            button: ButtonLogic::default(),
+            bg: Quad::new(cx),
+            text: Text {
+                wrapping: Wrapping::None,
+                ..Text::new(cx)
+            },
+            animator: Animator::default(),
+            checked: false,
+            _bg_area: Area::Empty,
+            _text_area: Area::Empty,
+        }
+    }
+
+    pub fn layout_bg() -> LayoutId {uid!()}
+    pub fn text_style_label() -> TextStyleId {uid!()}
+    pub fn anim_default() -> AnimId {uid!()}
+    pub fn anim_over() -> AnimId {uid!()}
+    pub fn anim_down() -> AnimId {uid!()}
+    pub fn shader_bg() -> ShaderId {uid!()}
+    pub fn hover() -> FloatId {uid!()}
+    pub fn down() -> FloatId {uid!()}
+    pub fn checked() -> FloatId {uid!()}
+    pub fn focus() -> FloatId {uid!()}
+    pub fn focus_color() -> ColorId {uid!()}
+
+    pub fn style(cx: &mut Cx, opt: &StyleOptions) {
+        Self::layout_bg().set(cx, Layout {
+            align: Align::left_center(),
+            walk: Walk::wh(Width::Compute, Height::Fix(22. * opt.scale)),
+            padding: Padding {l: 22. * opt.scale, t: 0., r: 4., b: 0.},
+            line_wrap: LineWrap::None,
+            ..Default::default()
+        });
+
+        Self::text_style_label().set(cx, Theme::text_style_normal().get(cx));
+
+        Self::anim_default().set(cx, Anim::new(Play::Cut {duration: 0.1}, vec![
+            Track::float(Self::hover(), Ease::Lin, vec![(1., 0.)]),
+            Track::float(Self::down(), Ease::Lin, vec![(1., 0.)]),
+            Track::color(Text::color(), Ease::Lin, vec![(1., Theme::color_text_deselected_focus().get(cx))]),
+        ]));
+
+        Self::anim_over().set(cx, Anim::new(Play::Cut {duration: 0.1}, vec![
+            Track::float(Self::down(), Ease::Lin, vec![(0., 0.)]),
+            Track::float(Self::hover(), Ease::Lin, vec![(0.0, 1.0), (1.0, 1.0)]),
+            Track::color(Text::color(), Ease::Lin, vec![(0., Theme::color_text_selected_focus().get(cx))]),
+        ]));
+
+        Self::anim_down().set(cx, Anim::new(Play::Cut {duration: 0.2}, vec![
+            Track::float(Self::down(), Ease::OutExp, vec![(0.0, 1.0), (1.0, 1.0)]),
+            Track::float(Self::hover(), Ease::Lin, vec![(1.0, 1.0)]),
+            Track::color(Text::color(), Ease::Lin, vec![(0., Theme::color_text_selected_focus().get(cx))]),
+        ]));
+
+        Self::shader_bg().set(cx, Quad::def_quad_shader().compose(shader!{"
+
+            instance hover: Self::hover();
+            instance down: Self::down();
+            instance checked: Self::checked();
+            instance focus: Self::focus();
+            instance focus_color: Self::focus_color();
+
+            fn pixel() -> vec4 {
+                let df = Df::viewport(pos * vec2(w, h));
+                let sz = 12.;
+                let bx = 3.;
+                let by = h * 0.5 - sz * 0.5;
+                df.box(bx, by, sz, sz, 2.);
+                df.fill(mix(mix(pick!(#3), pick!(#4), hover), pick!(#2a), down));
+                df.move_to(bx + 3., by + sz * 0.5);
+                df.line_to(bx + sz * 0.45, by + sz - 3.);
+                df.line_to(bx + sz - 2.5, by + 3.);
+                let mark = mix(pick!(#b), pick!(#f), hover);
+                df.stroke(vec4(mark.rgb, checked), 1.5);
+                df.box(1., 1., w - 2., h - 2., 2.5);
+                return df.stroke(vec4(focus_color.rgb, focus_color.a * focus), 1.5);
+            }
+        "}));
+    }
+
+    pub fn toggle(&mut self, cx: &mut Cx) -> CheckBoxEvent {
+        self.checked = !self.checked;
+        self._bg_area.write_float(cx, Self::checked(), if self.checked {1.} else {0.});
+        CheckBoxEvent::Toggled(self.checked)
+    }
+
+    pub fn handle_check_box(&mut self, cx: &mut Cx, event: &mut Event) -> CheckBoxEvent {
+        let animator = &mut self.animator;
+        let text_area = self._text_area;
+        let bg_area = self._bg_area;
+        match event.hits(cx, bg_area, HitOpt::default()) {
+            Event::KeyFocus(_) | Event::KeyFocusLost(_) => cx.redraw_child_area(bg_area),
+            _ => ()
+        }
+        match self.button.handle_button_logic(cx, event, self._bg_area, | cx, logic_event, area | match logic_event {
+            ButtonLogicEvent::Animate(ae) => {
+                animator.calc_area(cx, area, ae.time);
+                animator.calc_area(cx, text_area, ae.time);
+            },
+            ButtonLogicEvent::AnimEnded(_) => animator.end(),
+            ButtonLogicEvent::Down => animator.play_anim(cx, Self::anim_down().get(cx)),
+            ButtonLogicEvent::Default => animator.play_anim(cx, Self::anim_default().get(cx)),
+            ButtonLogicEvent::Over => animator.play_anim(cx, Self::anim_over().get(cx))
+        }) {
+            ButtonEvent::Down => cx.set_key_focus(bg_area),
+            ButtonEvent::Clicked => return self.toggle(cx),
+            _ => ()
+        }
+        CheckBoxEvent::None
+    }
+
+    pub fn draw_check_box(&mut self, cx: &mut Cx, checked: bool, label: &str) {
+        self.checked = checked;
+        self.bg.shader = Self::shader_bg().get(cx);
+
+        self.animator.init(cx, | cx | Self::anim_default().get(cx));
+
+        let bg_inst = self.bg.begin_quad(cx, Self::layout_bg().get(cx));
+
+        bg_inst.push_last_float(cx, &self.animator, Self::hover());
+        bg_inst.push_last_float(cx, &self.animator, Self::down());
+        bg_inst.push_float(cx, if checked {1.} else {0.});
+        bg_inst.push_float(cx, if cx.has_key_focus(self._bg_area) {1.} else {0.});
+        bg_inst.push_color(cx, Theme::color_focus_ring().get(cx));
+
+        self.text.text_style = Self::text_style_label().get(cx);
+        self.text.color = self.animator.last_color(cx, Text::color());
+        self._text_area = self.text.draw_text(cx, label);
+
+        self._bg_area = self.bg.end_quad(cx, &bg_inst);
+        self.animator.set_area(cx, self._bg_area);
+        cx.add_focusable(self._bg_area);
+        cx.add_a11y_node(A11yNode {
+            checked: Some(checked),
+            actions: vec![A11yAction::Press],
+            ..A11yNode::new(A11yRole::CheckBox, label, self._bg_area)
+        });
+    }
+}
@@ -0,0 +1,324 @@
+use makepad_render::*;
+use crate::textinput::*;
+use crate::texteditor::*;
+use crate::widgetstyle::*;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CommandPaletteCommand {
+    Named(String),
+    Menu(CommandId)
+}
+
+#[derive(Clone)]
+pub struct CommandPaletteItem {
+    pub label: String,
+    pub shortcut: String,
+    pub command: CommandPaletteCommand
+}
+
+pub enum CommandPaletteEvent {
+    None,
+    Execute(CommandPaletteCommand),
+    // a prompt was answered, the command it was opened for and the value
+    Input(String, String),
+    Closed
+}
+
+// what the palette asks a value for, instead of picking a command
+#[derive(Clone)]
+pub struct CommandPalettePrompt {
+    pub command: String,
+    // the typed text is the value, otherwise only the suggestions are
+    pub free_input: bool
+}
+
+#[derive(Clone)]
+pub struct CommandPalette {
+    pub view: View,
+    pub bg: Quad,
+    pub item_bg: Quad,
+    pub text: Text,
+    pub search_input: TextInput,
+    pub items: Vec<CommandPaletteItem>,
+    pub filtered: Vec<usize>,
+    pub selected: usize,
+    pub max_visible: usize,
+    pub is_open: bool,
+    pub prompt: Option<CommandPalettePrompt>,
+    pub _item_areas: Vec<(usize, Area)>,
+}
+
+impl CommandPalette {
+    pub fn new(cx: &mut Cx) -> Self {
+        Self::default_keymap(cx);
+        Self {
+            view: View::proto_overlay(cx),
+            bg: Quad::new(cx),
+            item_bg: Quad::new(cx),
+            text: Text::new(cx),
+            search_input: TextInput::new(cx, TextInputOptions {
+                multiline: false,
+                read_only: false,
+                empty_message: "type a command".to_string()
+            }),
+            items: Vec::new(),
+            filtered: Vec::new(),
+            selected: 0,
+            max_visible: 14,
+            is_open: false,
+            prompt: None,
+            _item_areas: Vec::new(),
+        }
+    }
+
+    pub fn layout_bg() -> LayoutId {uid!()}
+    pub fn layout_item() -> LayoutId {uid!()}
+    pub fn text_style_item() -> TextStyleId {uid!()}
+    pub fn color_bg() -> ColorId {uid!()}
+    pub fn color_item_selected() -> ColorId {uid!()}
+    pub fn color_label() -> ColorId {uid!()}
+    pub fn color_shortcut() -> ColorId {uid!()}
+    pub fn width() -> FloatId {uid!()}
+    pub fn item_height() -> FloatId {uid!()}
+
+    pub fn style(cx: &mut Cx, opt: &StyleOptions) {
+        Self::width().set(cx, 500. * opt.scale);
+        Self::item_height().set(cx, 22. * opt.scale);
+        Self::layout_bg().set(cx, Layout {
+            walk: Walk::wh(Width::Fill, Height::Compute),
+            padding: Padding::all(6.),
+            ..Layout::default()
+        });
+        Self::layout_item().set(cx, Layout {
+            walk: Walk::wh(Width::Fill, Height::Fix(Self::item_height().get(cx))),
+            align: Align::left_center(),
+            padding: Padding {l: 8., t: 0., r: 8., b: 0.},
+            line_wrap: LineWrap::None,
+            ..Layout::default()
+        });
+        Self::text_style_item().set(cx, Theme::text_style_normal().get(cx));
+        Self::color_bg().set(cx, Theme::color_bg_normal().get(cx));
+        Self::color_item_selected().set(cx, Theme::color_bg_marked().get(cx));
+        Self::color_label().set(cx, Theme::color_text_selected_focus().get(cx));
+        Self::color_shortcut().set(cx, Theme::color_text_deselected_defocus().get(cx));
+    }
+
+    pub fn default_keymap(cx: &mut Cx) {
+        let ctx = Some("palette");
+        let km = &mut cx.keymap;
+        km.register_command("palette.next", "Next Command", &["down"], ctx);
+        km.register_command("palette.prev", "Previous Command", &["up"], ctx);
+        km.register_command("palette.execute", "Execute Command", &["return"], ctx);
+        km.register_command("palette.close", "Close Command Palette", &["escape"], ctx);
+    }
+
+    pub fn open(&mut self, cx: &mut Cx, items: Vec<CommandPaletteItem>) {
+        self.prompt = None;
+        self.search_input.empty_message = "type a command".to_string();
+        self.open_items(cx, items);
+    }
+
+    // asks for a value for command, the suggestions are listed to pick from
+    pub fn open_prompt(&mut self, cx: &mut Cx, command: &str, empty_message: &str, suggestions: Vec<String>, free_input: bool) {
+        self.prompt = Some(CommandPalettePrompt {command: command.to_string(), free_input: free_input});
+        self.search_input.empty_message = empty_message.to_string();
+        self.open_items(cx, suggestions.into_iter().map( | suggestion | CommandPaletteItem {
+            label: suggestion,
+            shortcut: String::new(),
+            command: CommandPaletteCommand::Named(command.to_string())
+        }).collect());
+    }
+
+    fn open_items(&mut self, cx: &mut Cx, items: Vec<CommandPaletteItem>) {
+        self.items = items;
+        self.is_open = true;
+        self.search_input.set_value(cx, "");
+        self.search_input.text_editor.set_key_focus(cx);
+        self.filter();
+        self.view.redraw_view_area(cx);
+        cx.redraw_child_area(Area::All);
+    }
+
+    pub fn close(&mut self, cx: &mut Cx) {
+        if self.is_open {
+            self.is_open = false;
+            cx.revert_key_focus();
+            cx.redraw_child_area(Area::All);
+        }
+    }
+
+    pub fn filter(&mut self) {
+        let query = self.search_input.get_value();
+        let mut scored = Vec::new();
+        for (index, item) in self.items.iter().enumerate() {
+            if let Some(score) = fuzzy_match(&query, &item.label) {
+                scored.push((score, index));
+            }
+        }
+        // stable sort keeps registration order between equal scores
+        scored.sort_by( | a, b | b.0.cmp(&a.0));
+        self.filtered = scored.into_iter().map( | (_, index) | index).collect();
+        self.selected = 0;
+    }
+
+    fn execute_selected(&mut self, cx: &mut Cx) -> CommandPaletteEvent {
+        if let Some(index) = self.filtered.get(self.selected) {
+            let item = &self.items[*index];
+            let event = if let Some(prompt) = &self.prompt {
+                CommandPaletteEvent::Input(prompt.command.clone(), item.label.clone())
+            }
+            else {
+                CommandPaletteEvent::Execute(item.command.clone())
+            };
+            self.close(cx);
+            return event
+        }
+        CommandPaletteEvent::None
+    }
+
+    fn execute_input(&mut self, cx: &mut Cx) -> CommandPaletteEvent {
+        if let Some(prompt) = &self.prompt {
+            let value = self.search_input.get_value();
+            if prompt.free_input && value.len() > 0 {
+                let event = CommandPaletteEvent::Input(prompt.command.clone(), value);
+                self.close(cx);
+                return event
+            }
+        }
+        self.execute_selected(cx)
+    }
+
+    pub fn handle_command_palette(&mut self, cx: &mut Cx, event: &mut Event) -> CommandPaletteEvent {
+        if !self.is_open {
+            return CommandPaletteEvent::None
+        }
+        if let Event::KeyDown(ke) = event {
+            if self.search_input.text_editor.has_key_focus(cx) {
+                if let KeyMapMatch::Command(command) = cx.keymap.match_key_down(ke, "palette") {
+                    match command.as_ref() {
+                        "palette.next" => if self.filtered.len() > 0 {
+                            self.selected = (self.selected + 1) % self.filtered.len();
+                            self.view.redraw_view_area(cx);
+                        },
+                        "palette.prev" => if self.filtered.len() > 0 {
+                            self.selected = (self.selected + self.filtered.len() - 1) % self.filtered.len();
+                            self.view.redraw_view_area(cx);
+                        },
+                        "palette.execute" => {
+                            return self.execute_input(cx)
+                        },
+                        "palette.close" => {
+                            self.close(cx);
+                            return CommandPaletteEvent::Closed
+                        },
+                        _ => ()
+                    }
+                }
+            }
+        }
+
+        for (index, area) in &self._item_areas {
+            if let Event::FingerDown(_) = event.hits(cx, *area, HitOpt::default()) {
+                self.selected = *index;
+                return self.execute_selected(cx)
+            }
+        }
+
+        match self.search_input.handle_text_input(cx, event) {
+            TextEditorEvent::Change => {
+                self.filter();
+                self.view.redraw_view_area(cx);
+            },
+            TextEditorEvent::KeyFocusLost => {
+                self.is_open = false;
+                cx.redraw_child_area(Area::All);
+                return CommandPaletteEvent::Closed
+            },
+            _ => ()
+        }
+        CommandPaletteEvent::None
+    }
+
+    pub fn draw_command_palette(&mut self, cx: &mut Cx, rect: Rect) {
+        if !self.is_open {
+            return
+        }
+        let width = Self::width().get(cx).min(rect.w);
+        if self.view.begin_view(cx, Layout {
+            abs_origin: Some(Vec2 {x: rect.x + (rect.w - width) * 0.5, y: rect.y + 30.}),
+            walk: Walk::wh(Width::Fix(width), Height::Compute),
+            ..Layout::default()
+        }).is_err() {
+            return
+        };
+
+        self.bg.color = Self::color_bg().get(cx);
+        let bg_inst = self.bg.begin_quad(cx, Self::layout_bg().get(cx));
+
+        self.search_input.draw_text_input(cx);
+        cx.turtle_new_line();
+
+        // keep the selection in the visible window
+        let start = if self.selected >= self.max_visible {self.selected + 1 - self.max_visible} else {0};
+        let end = (start + self.max_visible).min(self.filtered.len());
+        self._item_areas.truncate(0);
+        self.text.text_style = Self::text_style_item().get(cx);
+        for pos in start..end {
+            let item = &self.items[self.filtered[pos]];
+            self.item_bg.color = if pos == self.selected {Self::color_item_selected().get(cx)} else {Self::color_bg().get(cx)};
+            let item_inst = self.item_bg.begin_quad(cx, Self::layout_item().get(cx));
+            self.text.color = Self::color_label().get(cx);
+            self.text.draw_text(cx, &item.label);
+            if item.shortcut.len() > 0 {
+                cx.move_turtle(12., 0.);
+                self.text.color = Self::color_shortcut().get(cx);
+                self.text.draw_text(cx, &item.shortcut);
+            }
+            let item_area = self.item_bg.end_quad(cx, &item_inst);
+            self._item_areas.push((pos, item_area));
+            cx.turtle_new_line();
+        }
+
+        self.bg.end_quad(cx, &bg_inst);
+        self.view.end_view(cx);
+    }
+}
+
+// subsequence match, rewarding consecutive characters and word starts
+pub fn fuzzy_match(query: &str, label: &str) -> Option<isize> {
+    let query: Vec<char> = query.to_lowercase().chars().filter( | c | !c.is_whitespace()).collect();
+    if query.len() == 0 {
+        return Some(0)
+    }
+    let label: Vec<char> = label.chars().collect();
+    let mut score = 0isize;
+    let mut qi = 0;
+    let mut last_match: Option<usize> = None;
+    for (li, ch) in label.iter().enumerate() {
+        if qi >= query.len() {
+            break
+        }
+        if ch.to_lowercase().next() == Some(query[qi]) {
+            score += 1;
+            if let Some(last) = last_match {
+                if last + 1 == li {
+                    score += 5;
+                }
+                else {
+                    score -= (li - last) as isize / 4;
+                }
+            }
+            if li == 0 || !label[li - 1].is_alphanumeric() || label[li - 1].is_lowercase() && ch.is_uppercase() {
+                score += 8;
+            }
+            last_match = Some(li);
+            qi += 1;
+        }
+    }
+    if qi == query.len() {
+        Some(score)
+    }
+    else {
+        None
+    }
+}
@@ -0,0 +1,344 @@
+use makepad_render::*;
+use crate::textbuffer::*;
+use crate::textlines::*;
+use crate::tokentype::*;
+use crate::commandpalette::*;
+use crate::widgetstyle::*;
+use std::collections::HashMap;
+
+// Completion candidates come from providers: the buffer itself, the language and whatever
+// the host adds, like an identifier index. The popup ranks them against the typed prefix.
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CompletionItem {
+    pub label: String,
+    // shown dimmed after the label, like where the item came from
+    pub detail: String,
+    pub token_type: TokenType,
+    // provider bonus, for instance for words close to the cursor or declarations
+    pub score: isize,
+}
+
+impl CompletionItem {
+    pub fn new(label: &str, token_type: TokenType, score: isize) -> Self {
+        CompletionItem {label: label.to_string(), detail: String::new(), token_type, score}
+    }
+}
+
+pub trait CompletionProvider {
+    // adds candidates for the word starting at word_start, the popup filters and ranks them
+    fn complete(&self, prefix: &str, word_start: usize, text_buffer: &TextBuffer, out: &mut Vec<CompletionItem>);
+}
+
+// identifiers, calls and type names in the buffer, nearer ones first
+pub struct BufferWordsProvider {}
+
+impl CompletionProvider for BufferWordsProvider {
+    fn complete(&self, prefix: &str, word_start: usize, text_buffer: &TextBuffer, out: &mut Vec<CompletionItem>) {
+        let first = match prefix.chars().next() {
+            Some(first) => first.to_ascii_lowercase(),
+            None => return
+        };
+        let mut words: HashMap<Vec<char>, (TokenType, usize)> = HashMap::new();
+        let mut reader = TextLinesReader::new(&text_buffer.lines);
+        for tok in &text_buffer.token_chunks {
+            match tok.token_type {
+                TokenType::Identifier | TokenType::Call | TokenType::TypeName => (),
+                _ => continue
+            }
+            // the word being typed is not a candidate
+            if tok.offset == word_start || tok.len < 2 {
+                continue
+            }
+            let mut word = Vec::new();
+            reader.chars_into(tok.offset, tok.len, &mut word);
+            if word[0].to_ascii_lowercase() != first {
+                continue
+            }
+            let distance = if tok.offset > word_start {tok.offset - word_start} else {word_start - tok.offset};
+            let entry = words.entry(word).or_insert((tok.token_type, distance));
+            if distance < entry.1 {
+                *entry = (tok.token_type, distance);
+            }
+        }
+        for (word, (token_type, distance)) in words {
+            let label: String = word.iter().collect();
+            out.push(CompletionItem {
+                label: label,
+                detail: String::new(),
+                token_type: token_type,
+                score: 8 - (distance / 400).min(8) as isize
+            });
+        }
+    }
+}
+
+// a fixed list, for keywords and builtins of a language
+pub struct WordListProvider {
+    pub words: Vec<(&'static str, TokenType)>,
+    pub detail: &'static str,
+}
+
+impl CompletionProvider for WordListProvider {
+    fn complete(&self, prefix: &str, _word_start: usize, _text_buffer: &TextBuffer, out: &mut Vec<CompletionItem>) {
+        for (word, token_type) in &self.words {
+            if fuzzy_match(prefix, word).is_some() {
+                out.push(CompletionItem {
+                    label: word.to_string(),
+                    detail: self.detail.to_string(),
+                    token_type: *token_type,
+                    score: 0
+                });
+            }
+        }
+    }
+}
+
+// fuzzy matches the prefix, favouring real prefixes, type names and calls
+pub fn rank_completions(prefix: &str, candidates: Vec<CompletionItem>, max_items: usize) -> Vec<CompletionItem> {
+    let mut best: HashMap<String, (isize, CompletionItem)> = HashMap::new();
+    let lower_prefix = prefix.to_lowercase();
+    for item in candidates {
+        if item.label == prefix {
+            continue
+        }
+        let fuzzy = match fuzzy_match(prefix, &item.label) {
+            Some(fuzzy) => fuzzy,
+            None => continue
+        };
+        let prefix_bonus = if item.label.starts_with(prefix) {40}
+        else if item.label.to_lowercase().starts_with(&lower_prefix) {30}
+        else {0};
+        let type_bonus = match item.token_type {
+            TokenType::TypeName => 6,
+            TokenType::Call => 4,
+            TokenType::Identifier => 2,
+            _ => 0
+        };
+        let score = fuzzy + prefix_bonus + type_bonus + item.score;
+        if let Some((old_score, _)) = best.get(&item.label) {
+            if *old_score >= score {
+                continue
+            }
+        }
+        best.insert(item.label.clone(), (score, item));
+    }
+    let mut ranked: Vec<(isize, CompletionItem)> = best.into_iter().map( | (_, v) | v).collect();
+    // shorter labels first between equal scores, then alphabetical so the order is stable
+    ranked.sort_by( | a, b | b.0.cmp(&a.0).then(a.1.label.len().cmp(&b.1.label.len())).then(a.1.label.cmp(&b.1.label)));
+    ranked.truncate(max_items);
+    ranked.into_iter().map( | (_, item) | item).collect()
+}
+
+pub enum CompletionPopupEvent {
+    None,
+    Accept(usize),
+}
+
+#[derive(Clone)]
+pub struct CompletionPopup {
+    pub popup: Popup,
+    pub bg: Quad,
+    pub item_bg: Quad,
+    pub text: Text,
+    pub items: Vec<CompletionItem>,
+    pub selected: usize,
+    pub max_visible: usize,
+    pub max_items: usize,
+    pub is_open: bool,
+    // where the completed word starts in the buffer, and what of it is typed so far
+    pub word_start: usize,
+    pub prefix: String,
+    pub _candidates: Vec<CompletionItem>,
+    pub _item_areas: Vec<(usize, Area)>,
+}
+
+impl CompletionPopup {
+    pub fn new(cx: &mut Cx) -> Self {
+        Self::default_keymap(cx);
+        Self {
+            popup: Popup::new(cx),
+            bg: Quad::new(cx),
+            item_bg: Quad::new(cx),
+            text: Text::new(cx),
+            items: Vec::new(),
+            selected: 0,
+            max_visible: 10,
+            max_items: 50,
+            is_open: false,
+            word_start: 0,
+            prefix: String::new(),
+            _candidates: Vec::new(),
+            _item_areas: Vec::new(),
+        }
+    }
+
+    pub fn layout_bg() -> LayoutId {uid!()}
+    pub fn layout_item() -> LayoutId {uid!()}
+    pub fn text_style_item() -> TextStyleId {uid!()}
+    pub fn color_bg() -> ColorId {uid!()}
+    pub fn color_item_selected() -> ColorId {uid!()}
+    pub fn color_label() -> ColorId {uid!()}
+    pub fn color_detail() -> ColorId {uid!()}
+    pub fn width() -> FloatId {uid!()}
+    pub fn item_height() -> FloatId {uid!()}
+
+    pub fn style(cx: &mut Cx, opt: &StyleOptions) {
+        Self::width().set(cx, 280. * opt.scale);
+        Self::item_height().set(cx, 20. * opt.scale);
+        Self::layout_bg().set(cx, Layout {
+            walk: Walk::wh(Width::Fill, Height::Compute),
+            padding: Padding::all(2.),
+            ..Layout::default()
+        });
+        Self::layout_item().set(cx, Layout {
+            walk: Walk::wh(Width::Fill, Height::Fix(Self::item_height().get(cx))),
+            align: Align::left_center(),
+            padding: Padding {l: 6., t: 0., r: 6., b: 0.},
+            line_wrap: LineWrap::None,
+            ..Layout::default()
+        });
+        Self::text_style_item().set(cx, Theme::text_style_fixed().get(cx));
+        Self::color_bg().set(cx, Theme::color_bg_normal().get(cx));
+        Self::color_item_selected().set(cx, Theme::color_bg_marked().get(cx));
+        Self::color_label().set(cx, Theme::color_text_selected_focus().get(cx));
+        Self::color_detail().set(cx, Theme::color_text_deselected_defocus().get(cx));
+    }
+
+    pub fn default_keymap(cx: &mut Cx) {
+        let ctx = Some("completion");
+        let km = &mut cx.keymap;
+        km.register_command("completion.next", "Next Completion", &["down"], ctx);
+        km.register_command("completion.prev", "Previous Completion", &["up"], ctx);
+        km.register_command("completion.page_down", "Completion Page Down", &["pagedown"], ctx);
+        km.register_command("completion.page_up", "Completion Page Up", &["pageup"], ctx);
+        km.register_command("completion.accept", "Accept Completion", &["return", "tab"], ctx);
+        km.register_command("completion.close", "Close Completions", &["escape"], ctx);
+    }
+
+    // ranks the candidates against the prefix, stays closed when nothing matches
+    pub fn open(&mut self, cx: &mut Cx, word_start: usize, prefix: &str, candidates: Vec<CompletionItem>) {
+        let selected_label = if self.is_open {self.items.get(self.selected).map( | item | item.label.clone())} else {None};
+        self.word_start = word_start;
+        self.prefix = prefix.to_string();
+        self._candidates = candidates;
+        self.items = rank_completions(prefix, self._candidates.clone(), self.max_items);
+        // typing on keeps the selected item if it still matches
+        self.selected = selected_label.and_then( | label | self.items.iter().position( | item | item.label == label)).unwrap_or(0);
+        self.is_open = self.items.len() > 0;
+        if self.is_open {
+            let anchor = self.popup._anchor;
+            self.popup.open(cx, anchor);
+        }
+        else {
+            self.popup.close(cx);
+        }
+    }
+
+    // candidates that arrive after opening, like the ones from a host side index
+    pub fn add_candidates(&mut self, cx: &mut Cx, candidates: Vec<CompletionItem>) {
+        let mut all = std::mem::replace(&mut self._candidates, Vec::new());
+        all.extend(candidates);
+        let (word_start, prefix) = (self.word_start, self.prefix.clone());
+        self.open(cx, word_start, &prefix, all);
+    }
+
+    pub fn close(&mut self, cx: &mut Cx) {
+        if self.is_open {
+            self.is_open = false;
+            self._candidates.truncate(0);
+            self.items.truncate(0);
+            self.popup.close(cx);
+        }
+    }
+
+    pub fn selected_item(&self) -> Option<&CompletionItem> {
+        self.items.get(self.selected)
+    }
+
+    // keyboard commands come from the editor, which keeps the key focus
+    pub fn handle_command(&mut self, cx: &mut Cx, command: &str) -> CompletionPopupEvent {
+        let len = self.items.len();
+        if len == 0 {
+            return CompletionPopupEvent::None
+        }
+        match command {
+            "completion.next" => self.selected = (self.selected + 1) % len,
+            "completion.prev" => self.selected = (self.selected + len - 1) % len,
+            "completion.page_down" => self.selected = (self.selected + self.max_visible).min(len - 1),
+            "completion.page_up" => self.selected = self.selected.max(self.max_visible) - self.max_visible,
+            "completion.accept" => return CompletionPopupEvent::Accept(self.selected),
+            "completion.close" => self.close(cx),
+            _ => ()
+        }
+        self.popup.view.redraw_view_area(cx);
+        CompletionPopupEvent::None
+    }
+
+    pub fn handle_completion_popup(&mut self, cx: &mut Cx, event: &mut Event) -> CompletionPopupEvent {
+        if !self.is_open {
+            return CompletionPopupEvent::None
+        }
+        for (index, area) in &self._item_areas {
+            match event.hits(cx, *area, HitOpt::default()) {
+                Event::FingerDown(_) => {
+                    self.selected = *index;
+                    return CompletionPopupEvent::Accept(*index)
+                },
+                Event::FingerHover(_) => {
+                    cx.set_hover_mouse_cursor(MouseCursor::Hand);
+                },
+                _ => ()
+            }
+        }
+        // the keys stay with the editor, a click elsewhere closes
+        if let Event::KeyDown(_) = event {
+            return CompletionPopupEvent::None
+        }
+        if let PopupEvent::Dismissed = self.popup.handle_popup(cx, event) {
+            self.close(cx);
+        }
+        CompletionPopupEvent::None
+    }
+
+    // rect is the cursor the word is typed at, the popup goes below it or above when there is no room
+    pub fn draw_completion_popup(&mut self, cx: &mut Cx, rect: Rect) {
+        if !self.is_open {
+            return
+        }
+        self.popup.set_anchor(rect);
+        if self.popup.begin_popup(cx, Layout {
+            walk: Walk::wh(Width::Fix(Self::width().get(cx)), Height::Compute),
+            ..Layout::default()
+        }).is_err() {
+            return
+        };
+
+        self.bg.color = Self::color_bg().get(cx);
+        let bg_inst = self.bg.begin_quad(cx, Self::layout_bg().get(cx));
+
+        // keep the selection in the visible window
+        let start = if self.selected >= self.max_visible {self.selected + 1 - self.max_visible} else {0};
+        let end = (start + self.max_visible).min(self.items.len());
+        self._item_areas.truncate(0);
+        self.text.text_style = Self::text_style_item().get(cx);
+        for pos in start..end {
+            let item = &self.items[pos];
+            self.item_bg.color = if pos == self.selected {Self::color_item_selected().get(cx)} else {Self::color_bg().get(cx)};
+            let item_inst = self.item_bg.begin_quad(cx, Self::layout_item().get(cx));
+            self.text.color = Self::color_label().get(cx);
+            self.text.draw_text(cx, &item.label);
+            if item.detail.len() > 0 {
+                cx.move_turtle(12., 0.);
+                self.text.color = Self::color_detail().get(cx);
+                self.text.draw_text(cx, &item.detail);
+            }
+            let item_area = self.item_bg.end_quad(cx, &item_inst);
+            self._item_areas.push((pos, item_area));
+            cx.turtle_new_line();
+        }
+
+        self.bg.end_quad(cx, &bg_inst);
+        self.popup.end_popup(cx);
+    }
+}
@@ -0,0 +1,413 @@
+use makepad_render::*;
+use crate::widgetstyle::*;
+
+// A Menu tree shown as a popup at the mouse, submenus open to the side of their item.
+// Picking an item sends its CommandId as an Event::Command, like a native menu would.
+// Items of commands that are disabled with CommandId::set_enabled are drawn dimmed and can't be picked.
+
+#[derive(Clone)]
+pub struct ContextMenuLevel {
+    pub popup: Popup,
+    pub selected: Option<usize>,
+    pub _item_areas: Vec<(usize, Area)>,
+}
+
+#[derive(Clone)]
+pub struct ContextMenu {
+    pub levels: Vec<ContextMenuLevel>,
+    pub bg: Quad,
+    pub item_bg: Quad,
+    pub line: Quad,
+    pub text: Text,
+    pub menu: Menu,
+    pub is_open: bool,
+    pub _open_levels: usize,
+    pub _prev_key_focus: Area,
+    pub _grab_key_focus: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ContextMenuEvent {
+    None,
+    Command(CommandId),
+    Closed,
+}
+
+impl ContextMenu {
+    pub fn new(cx: &mut Cx) -> Self {
+        Self::default_keymap(cx);
+        Self {
+            levels: Vec::new(),
+            bg: Quad::new(cx),
+            item_bg: Quad::new(cx),
+            line: Quad::new(cx),
+            text: Text {
+                wrapping: Wrapping::None,
+                ..Text::new(cx)
+            },
+            menu: Menu::main(Vec::new()),
+            is_open: false,
+            _open_levels: 0,
+            _prev_key_focus: Area::Empty,
+            _grab_key_focus: false,
+        }
+    }
+
+    pub fn layout_bg() -> LayoutId {uid!()}
+    pub fn layout_item() -> LayoutId {uid!()}
+    pub fn text_style_item() -> TextStyleId {uid!()}
+    pub fn color_bg() -> ColorId {uid!()}
+    pub fn color_item_selected() -> ColorId {uid!()}
+    pub fn color_label() -> ColorId {uid!()}
+    pub fn color_label_disabled() -> ColorId {uid!()}
+    pub fn color_shortcut() -> ColorId {uid!()}
+    pub fn color_line() -> ColorId {uid!()}
+    pub fn width() -> FloatId {uid!()}
+    pub fn item_height() -> FloatId {uid!()}
+
+    pub fn style(cx: &mut Cx, opt: &StyleOptions) {
+        Self::width().set(cx, 220. * opt.scale);
+        Self::item_height().set(cx, 22. * opt.scale);
+        Self::layout_bg().set(cx, Layout {
+            walk: Walk::wh(Width::Fill, Height::Compute),
+            padding: Padding::all(3.),
+            ..Layout::default()
+        });
+        Self::layout_item().set(cx, Layout {
+            walk: Walk::wh(Width::Fill, Height::Fix(Self::item_height().get(cx))),
+            align: Align::left_center(),
+            padding: Padding {l: 10., t: 0., r: 10., b: 0.},
+            line_wrap: LineWrap::None,
+            ..Layout::default()
+        });
+        Self::text_style_item().set(cx, Theme::text_style_normal().get(cx));
+        Self::color_bg().set(cx, Theme::color_bg_normal().get(cx));
+        Self::color_item_selected().set(cx, Theme::color_bg_marked().get(cx));
+        Self::color_label().set(cx, Theme::color_text_selected_focus().get(cx));
+        Self::color_label_disabled().set(cx, Theme::color_text_deselected_defocus().get(cx));
+        Self::color_shortcut().set(cx, Theme::color_text_deselected_focus().get(cx));
+        Self::color_line().set(cx, Theme::color_bg_odd().get(cx));
+    }
+
+    pub fn default_keymap(cx: &mut Cx) {
+        let ctx = Some("context_menu");
+        let km = &mut cx.keymap;
+        km.register_command("context_menu.next", "Next Menu Item", &["down"], ctx);
+        km.register_command("context_menu.prev", "Previous Menu Item", &["up"], ctx);
+        km.register_command("context_menu.open_sub", "Open Submenu", &["right"], ctx);
+        km.register_command("context_menu.close_sub", "Close Submenu", &["left"], ctx);
+        km.register_command("context_menu.execute", "Pick Menu Item", &["return", "space"], ctx);
+        km.register_command("context_menu.close", "Close Menu", &["escape"], ctx);
+    }
+
+    // pos is where the mouse went down, the menu takes the key focus until it closes
+    pub fn open(&mut self, cx: &mut Cx, menu: Menu, pos: Vec2) {
+        self.menu = menu;
+        if self.levels.len() == 0 {
+            self.levels.push(Self::new_level(cx));
+        }
+        if !self.is_open {
+            self._prev_key_focus = cx.key_focus;
+        }
+        self.levels[0].selected = None;
+        self.levels[0].popup.place = PopupPlace::Over;
+        self.levels[0].popup.open(cx, Rect {x: pos.x, y: pos.y, w: 0., h: 0.});
+        self._open_levels = 1;
+        self.is_open = true;
+        self._grab_key_focus = true;
+    }
+
+    pub fn close(&mut self, cx: &mut Cx) {
+        if !self.is_open {
+            return
+        }
+        self.is_open = false;
+        for level in &mut self.levels {
+            level.popup.close(cx);
+        }
+        self._open_levels = 0;
+        cx.set_key_focus(self._prev_key_focus);
+    }
+
+    fn new_level(cx: &mut Cx) -> ContextMenuLevel {
+        ContextMenuLevel {
+            popup: Popup::new(cx),
+            selected: None,
+            _item_areas: Vec::new(),
+        }
+    }
+
+    // the items of a level, following the selected submenus of the levels above it
+    fn level_items<'a>(menu: &'a Menu, levels: &[ContextMenuLevel], level: usize) -> Option<&'a Vec<Menu>> {
+        let mut items = match menu {
+            Menu::Main {items} | Menu::Sub {items, ..} => items,
+            _ => return None
+        };
+        for parent in &levels[0..level] {
+            items = match parent.selected.and_then( | index | items.get(index)) {
+                Some(Menu::Sub {items, ..}) => items,
+                _ => return None
+            };
+        }
+        Some(items)
+    }
+
+    fn is_enabled(cx: &Cx, item: &Menu) -> bool {
+        match item {
+            Menu::Item {command, ..} => cx.command_settings.get(command).map_or(true, | setting | setting.enabled),
+            Menu::Sub {items, ..} => items.len() > 0,
+            _ => false
+        }
+    }
+
+    // the key equivalent of a command as a hint next to its item
+    fn shortcut(cx: &Cx, command: &CommandId) -> String {
+        if let Some(setting) = cx.command_settings.get(command) {
+            if setting.key_code != KeyCode::Unknown {
+                return KeyStroke {
+                    key_code: setting.key_code,
+                    shift: setting.shift,
+                    control: !cfg!(target_os = "macos"),
+                    logo: cfg!(target_os = "macos"),
+                    ..KeyStroke::default()
+                }.to_string()
+            }
+        }
+        String::new()
+    }
+
+    fn set_open_levels(&mut self, cx: &mut Cx, open_levels: usize) {
+        while self.levels.len() < open_levels {
+            self.levels.push(Self::new_level(cx));
+        }
+        for level in open_levels..self.levels.len() {
+            self.levels[level].popup.close(cx);
+            self.levels[level].selected = None;
+        }
+        if open_levels > self._open_levels {
+            let level = open_levels - 1;
+            self.levels[level].selected = None;
+            self.levels[level].popup.place = PopupPlace::Right;
+            let anchor = self.levels[level].popup._anchor;
+            self.levels[level].popup.open(cx, anchor);
+        }
+        self._open_levels = open_levels;
+        for level in 0..open_levels {
+            self.levels[level].popup.view.redraw_view_area(cx);
+        }
+    }
+
+    // selects an item, a submenu item opens its submenu
+    fn select(&mut self, cx: &mut Cx, level: usize, index: usize) {
+        let is_sub = match Self::level_items(&self.menu, &self.levels, level).and_then( | items | items.get(index)) {
+            Some(item @ Menu::Sub {..}) => Self::is_enabled(cx, item),
+            _ => false
+        };
+        let open_levels = if is_sub {level + 2} else {level + 1};
+        if self.levels[level].selected == Some(index) && self._open_levels == open_levels {
+            return
+        }
+        if self.levels[level].selected != Some(index) {
+            // another submenu opens in place of the one that was open
+            self.set_open_levels(cx, level + 1);
+            self.levels[level].selected = Some(index);
+        }
+        self.set_open_levels(cx, open_levels);
+    }
+
+    fn execute(&mut self, cx: &mut Cx, level: usize, index: usize) -> ContextMenuEvent {
+        let command = match Self::level_items(&self.menu, &self.levels, level).and_then( | items | items.get(index)) {
+            Some(item @ Menu::Item {..}) if Self::is_enabled(cx, item) => match item {
+                Menu::Item {command, ..} => *command,
+                _ => return ContextMenuEvent::None
+            },
+            Some(Menu::Sub {..}) => {
+                self.select(cx, level, index);
+                self.move_selection(cx, level + 1, 1);
+                return ContextMenuEvent::None
+            },
+            _ => return ContextMenuEvent::None
+        };
+        self.close(cx);
+        cx.send_command(command);
+        ContextMenuEvent::Command(command)
+    }
+
+    // moves the selection in a level to the next enabled item in a direction
+    fn move_selection(&mut self, cx: &mut Cx, level: usize, dir: isize) {
+        let len = match Self::level_items(&self.menu, &self.levels, level) {
+            Some(items) => items.len(),
+            None => return
+        };
+        let mut index = match self.levels[level].selected {
+            Some(index) => index as isize,
+            None => if dir > 0 {-1} else {len as isize}
+        };
+        for _ in 0..len {
+            index = (index + dir + len as isize) % len as isize;
+            let enabled = match Self::level_items(&self.menu, &self.levels, level).and_then( | items | items.get(index as usize)) {
+                Some(item) => Self::is_enabled(cx, item),
+                None => false
+            };
+            if enabled {
+                self.levels[level].selected = Some(index as usize);
+                // walking the items doesn't open submenus, right or return does
+                self.set_open_levels(cx, level + 1);
+                return
+            }
+        }
+    }
+
+    pub fn handle_context_menu(&mut self, cx: &mut Cx, event: &mut Event) -> ContextMenuEvent {
+        if !self.is_open {
+            return ContextMenuEvent::None
+        }
+        let focus_area = self.levels[0].popup.view.get_view_area(cx);
+        if let Event::KeyDown(ke) = if focus_area == Area::Empty {Event::None} else {event.hits(cx, focus_area, HitOpt::default())} {
+            let level = self._open_levels - 1;
+            if let KeyMapMatch::Command(command) = cx.keymap.match_key_down(&ke, "context_menu") {
+                match command.as_ref() {
+                    "context_menu.next" => self.move_selection(cx, level, 1),
+                    "context_menu.prev" => self.move_selection(cx, level, -1),
+                    "context_menu.open_sub" => if let Some(index) = self.levels[level].selected {
+                        if let Some(Menu::Sub {..}) = Self::level_items(&self.menu, &self.levels, level).and_then( | items | items.get(index)) {
+                            self.execute(cx, level, index);
+                        }
+                    },
+                    "context_menu.close_sub" => if level > 0 {
+                        self.set_open_levels(cx, level);
+                    },
+                    "context_menu.execute" => if let Some(index) = self.levels[level].selected {
+                        return self.execute(cx, level, index)
+                    },
+                    "context_menu.close" => {
+                        self.close(cx);
+                        return ContextMenuEvent::Closed
+                    },
+                    _ => ()
+                }
+            }
+            return ContextMenuEvent::None
+        }
+
+        for level in (0..self._open_levels).rev() {
+            for (index, area) in self.levels[level]._item_areas.clone() {
+                match event.hits(cx, area, HitOpt::default()) {
+                    Event::FingerHover(_) => {
+                        self.select(cx, level, index);
+                    },
+                    Event::FingerDown(_) => {
+                        return self.execute(cx, level, index)
+                    },
+                    Event::A11yAction(ae) => if ae.action == A11yAction::Press {
+                        return self.execute(cx, level, index)
+                    },
+                    _ => ()
+                }
+            }
+        }
+        // a click outside the deepest submenu closes it, outside all of them closes the menu
+        if let Event::KeyDown(_) = event {
+            return ContextMenuEvent::None
+        }
+        for level in (0..self._open_levels).rev() {
+            if let PopupEvent::Dismissed = self.levels[level].popup.handle_popup(cx, event) {
+                if level == 0 {
+                    self.close(cx);
+                    return ContextMenuEvent::Closed
+                }
+                self.set_open_levels(cx, level);
+            }
+        }
+        ContextMenuEvent::None
+    }
+
+    pub fn draw_context_menu(&mut self, cx: &mut Cx) {
+        if !self.is_open {
+            return
+        }
+        for level in 0..self._open_levels {
+            // a submenu goes next to its item in the level above, which was just drawn
+            if level > 0 {
+                let parent = &self.levels[level - 1];
+                if let Some((_, area)) = parent._item_areas.iter().find( | (index, _) | Some(*index) == parent.selected) {
+                    let rect = area.get_rect(cx);
+                    self.levels[level].popup.set_anchor(rect);
+                }
+            }
+            self.draw_level(cx, level);
+        }
+        if self._grab_key_focus {
+            self._grab_key_focus = false;
+            let area = self.levels[0].popup.view.get_view_area(cx);
+            cx.set_key_focus(area);
+        }
+    }
+
+    fn draw_level(&mut self, cx: &mut Cx, level: usize) {
+        let items = match Self::level_items(&self.menu, &self.levels, level) {
+            Some(items) => items.clone(),
+            None => return
+        };
+        let lvl = &mut self.levels[level];
+        if lvl.popup.begin_popup(cx, Layout {
+            walk: Walk::wh(Width::Fix(Self::width().get(cx)), Height::Compute),
+            ..Layout::default()
+        }).is_err() {
+            return
+        };
+        self.bg.color = Self::color_bg().get(cx);
+        let bg_inst = self.bg.begin_quad(cx, Self::layout_bg().get(cx));
+        lvl._item_areas.truncate(0);
+        cx.begin_a11y_node(A11yNode::new(A11yRole::Menu, "", bg_inst.clone().into()));
+        self.text.text_style = Self::text_style_item().get(cx);
+        for (index, item) in items.iter().enumerate() {
+            match item {
+                Menu::Line => {
+                    self.line.color = Self::color_line().get(cx);
+                    self.line.draw_quad(cx, Walk {
+                        width: Width::Fill,
+                        height: Height::Fix(1.),
+                        margin: Margin {l: 6., t: 3., r: 6., b: 3.},
+                    });
+                },
+                Menu::Item {name, ..} | Menu::Sub {name, ..} => {
+                    let enabled = Self::is_enabled(cx, item);
+                    self.item_bg.color = if enabled && lvl.selected == Some(index) {
+                        Self::color_item_selected().get(cx)
+                    } else {
+                        Self::color_bg().get(cx)
+                    };
+                    let item_inst = self.item_bg.begin_quad(cx, Self::layout_item().get(cx));
+                    self.text.color = if enabled {Self::color_label().get(cx)} else {Self::color_label_disabled().get(cx)};
+                    self.text.draw_text(cx, name);
+                    let hint = match item {
+                        Menu::Item {command, ..} => Self::shortcut(cx, command),
+                        _ => ">".to_string()
+                    };
+                    if hint.len() > 0 {
+                        cx.change_turtle_align_x(1.0);
+                        self.text.color = Self::color_shortcut().get(cx);
+                        self.text.draw_text(cx, &hint);
+                    }
+                    let item_area = self.item_bg.end_quad(cx, &item_inst);
+                    if enabled {
+                        lvl._item_areas.push((index, item_area));
+                    }
+                    cx.add_a11y_node(A11yNode {
+                        expanded: if let Menu::Sub {..} = item {Some(level + 1 < self._open_levels && lvl.selected == Some(index))} else {None},
+                        selected: enabled && lvl.selected == Some(index),
+                        disabled: !enabled,
+                        actions: if enabled {vec![A11yAction::Press]} else {vec![]},
+                        ..A11yNode::new(A11yRole::MenuItem, name, item_area)
+                    });
+                },
+                Menu::Main {..} => ()
+            }
+            cx.turtle_new_line();
+        }
+        cx.end_a11y_node();
+        self.bg.end_quad(cx, &bg_inst);
+        lvl.popup.end_popup(cx);
+    }
+}
@@ -0,0 +1,602 @@
+use makepad_render::*;
+use std::cmp::Ordering;
+use crate::scrollview::*;
+use crate::listlogic::*;
+use crate::buttonlogic::*;
+use crate::widgetstyle::*;
+use crate::focusring::*;
+
+// The rows of a DataTableSource in columns, only the rows in view are drawn.
+// Clicking a header sorts by its column, dragging its right edge resizes the column and dragging it elsewhere moves it.
+// Rows select like the log list, shift for a range and ctrl or cmd to toggle, and copy as tab separated text.
+
+pub trait DataTableSource {
+    fn row_count(&self) -> usize;
+    fn cell_text(&self, row: usize, column: usize) -> String;
+    // numbers compare as numbers, the rest as text
+    fn compare(&self, column: usize, a: usize, b: usize) -> Ordering {
+        let a = self.cell_text(a, column);
+        let b = self.cell_text(b, column);
+        match (a.trim().parse::<f64>(), b.trim().parse::<f64>()) {
+            (Ok(a), Ok(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+            _ => a.to_lowercase().cmp(&b.to_lowercase())
+        }
+    }
+    // draws the cell in its own way inside the turtle of the cell, false leaves it to the table to draw cell_text
+    fn draw_cell(&mut self, _cx: &mut Cx, _row: usize, _column: usize, _text: &mut Text) -> bool {
+        false
+    }
+}
+
+#[derive(Clone)]
+pub struct DataColumn {
+    pub label: String,
+    pub width: f32,
+    pub min_width: f32,
+    pub sortable: bool,
+}
+
+impl DataColumn {
+    pub fn new(label: &str, width: f32) -> Self {
+        Self {
+            label: label.to_string(),
+            width: width,
+            min_width: 30.,
+            sortable: true,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SortOrder {
+    Ascending,
+    Descending
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DataTableDrag {
+    None,
+    // pressed on the header at this position, a click when it doesn't move
+    Press(usize),
+    Move(usize),
+    Resize {column: usize, start_width: f32},
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DataTableEvent {
+    None,
+    // the selected rows of the source
+    Select(Vec<usize>),
+    // double click or return on a row of the source
+    Activate(usize),
+    Sort(usize, SortOrder),
+    // widths or order of the columns changed, to store them
+    ColumnsChanged,
+}
+
+#[derive(Clone)]
+pub struct DataTable {
+    pub view: ScrollView,
+    pub header: View,
+    pub list: ListLogic,
+    pub key_button: ButtonLogic,
+    pub columns: Vec<DataColumn>,
+    // the columns from left to right
+    pub column_order: Vec<usize>,
+    pub sort: Option<(usize, SortOrder)>,
+    // the row of the source at each position
+    pub row_order: Vec<usize>,
+    pub header_bg: Quad,
+    pub sort_arrow: Quad,
+    pub item_bg: Quad,
+    pub text: Text,
+    pub drag: DataTableDrag,
+    pub focus_ring: FocusRing,
+    pub _header_areas: Vec<Area>,
+    pub _order_dirty: bool,
+}
+
+impl DataTable {
+    pub fn new(cx: &mut Cx) -> Self {
+        Self {
+            view: ScrollView::new(cx),
+            header: View::new(cx),
+            list: ListLogic {
+                multi_select: true,
+                ..ListLogic::default()
+            },
+            key_button: ButtonLogic::default(),
+            columns: Vec::new(),
+            column_order: Vec::new(),
+            sort: None,
+            row_order: Vec::new(),
+            header_bg: Quad::new(cx),
+            sort_arrow: Quad::new(cx),
+            item_bg: Quad::new(cx),
+            text: Text {
+                wrapping: Wrapping::None,
+                ..Text::new(cx)
+            },
+            drag: DataTableDrag::None,
+            focus_ring: FocusRing::new(cx),
+            _header_areas: Vec::new(),
+            _order_dirty: true,
+        }
+    }
+
+    pub fn layout_header() -> LayoutId {uid!()}
+    pub fn layout_row() -> LayoutId {uid!()}
+    pub fn layout_cell() -> LayoutId {uid!()}
+    pub fn walk_sort_arrow() -> WalkId {uid!()}
+    pub fn text_style_header() -> TextStyleId {uid!()}
+    pub fn text_style_cell() -> TextStyleId {uid!()}
+    pub fn color_header() -> ColorId {uid!()}
+    pub fn color_header_drag() -> ColorId {uid!()}
+    pub fn color_header_label() -> ColorId {uid!()}
+    pub fn color_cell() -> ColorId {uid!()}
+    pub fn shader_header() -> ShaderId {uid!()}
+    pub fn shader_sort_arrow() -> ShaderId {uid!()}
+    pub fn descending() -> FloatId {uid!()}
+
+    pub fn style(cx: &mut Cx, opt: &StyleOptions) {
+        Self::layout_header().set(cx, Layout {
+            walk: Walk::wh(Width::Fill, Height::Fix(22. * opt.scale)),
+            line_wrap: LineWrap::None,
+            ..Layout::default()
+        });
+        Self::layout_row().set(cx, Layout {
+            walk: Walk::wh(Width::Fill, Height::Fix(20. * opt.scale)),
+            line_wrap: LineWrap::None,
+            ..Layout::default()
+        });
+        // the width is the one of the column
+        Self::layout_cell().set(cx, Layout {
+            walk: Walk::wh(Width::Fill, Height::Fill),
+            align: Align::left_center(),
+            padding: Padding {l: 6., t: 0., r: 6., b: 0.},
+            line_wrap: LineWrap::None,
+            ..Layout::default()
+        });
+        Self::walk_sort_arrow().set(cx, Walk {
+            width: Width::Fix(8.),
+            height: Height::Fix(8.),
+            margin: Margin::left(4.),
+        });
+        Self::text_style_header().set(cx, Theme::text_style_normal().get(cx));
+        Self::text_style_cell().set(cx, Theme::text_style_normal().get(cx));
+        Self::color_header().set(cx, Theme::color_bg_normal().get(cx));
+        Self::color_header_drag().set(cx, Theme::color_bg_marked().get(cx));
+        Self::color_header_label().set(cx, Theme::color_text_selected_focus().get(cx));
+        Self::color_cell().set(cx, Theme::color_text_deselected_focus().get(cx));
+
+        Self::shader_header().set(cx, Quad::def_quad_shader().compose(shader!{"
+            fn pixel() -> vec4 {
+                let df = Df::viewport(pos * vec2(w, h));
+                df.rect(0., 0., w, h);
+                df.fill(color);
+                df.rect(w - 1., 4., 1., h - 8.);
+                df.fill(pick!(#5));
+                return df.result;
+            }
+        "}));
+
+        Self::shader_sort_arrow().set(cx, Quad::def_quad_shader().compose(shader!{"
+            instance descending: Self::descending();
+            fn pixel() -> vec4 {
+                let df = Df::viewport(pos * vec2(w, h));
+                let top = mix(2., h - 2., descending);
+                let bottom = mix(h - 2., 2., descending);
+                df.move_to(1., bottom);
+                df.line_to(w * 0.5, top);
+                df.line_to(w - 1., bottom);
+                return df.stroke(color, 1.2);
+            }
+        "}));
+    }
+
+    pub fn get_default_anim(cx: &Cx, counter: usize, marked: bool) -> Anim {
+        Anim::new(Play::Chain {duration: 0.01}, vec![
+            Track::color(Quad::color(), Ease::Lin, vec![
+                (1.0, if marked {Theme::color_bg_marked().get(cx)} else if counter & 1 == 0 {Theme::color_bg_selected().get(cx)}else {Theme::color_bg_odd().get(cx)})
+            ])
+        ])
+    }
+
+    pub fn get_over_anim(cx: &Cx, counter: usize, marked: bool) -> Anim {
+        let over_color = if marked {Theme::color_bg_marked_over().get(cx)} else if counter & 1 == 0 {Theme::color_bg_selected_over().get(cx)}else {Theme::color_bg_odd_over().get(cx)};
+        Anim::new(Play::Cut {duration: 0.02}, vec![
+            Track::color(Quad::color(), Ease::Lin, vec![
+                (0., over_color),
+            ])
+        ])
+    }
+
+    pub fn set_columns(&mut self, cx: &mut Cx, columns: Vec<DataColumn>) {
+        self.column_order = (0..columns.len()).collect();
+        self.columns = columns;
+        self.sort = None;
+        self._order_dirty = true;
+        self.redraw_data_table(cx);
+    }
+
+    // call when the rows of the source changed, they are sorted again on the next draw
+    pub fn refresh(&mut self, cx: &mut Cx) {
+        self._order_dirty = true;
+        self.redraw_data_table(cx);
+    }
+
+    pub fn redraw_data_table(&mut self, cx: &mut Cx) {
+        self.header.redraw_view_area(cx);
+        self.view.redraw_view_area(cx);
+    }
+
+    pub fn set_sort(&mut self, cx: &mut Cx, column: usize, order: SortOrder) {
+        self.sort = Some((column, order));
+        self.refresh(cx);
+    }
+
+    // the rows of the source that are selected, in the order they are shown
+    pub fn selected_rows(&self) -> Vec<usize> {
+        let mut positions = self.list.selection.clone();
+        positions.sort();
+        positions.iter().filter_map( | pos | self.row_order.get(*pos).cloned()).collect()
+    }
+
+    // the selected rows, a line each with the cells split by tabs in the order of the columns
+    pub fn selection_as_tsv(&self, source: &dyn DataTableSource) -> String {
+        let mut out = String::new();
+        for row in self.selected_rows() {
+            for (index, column) in self.column_order.iter().enumerate() {
+                if index > 0 {
+                    out.push('\t');
+                }
+                let cell = source.cell_text(row, *column);
+                out.push_str(&cell.replace( | c | c == '\t' || c == '\n' || c == '\r', " "));
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    // puts the rows in the order of the sort and keeps the same rows selected
+    fn update_row_order(&mut self, cx: &Cx, source: &dyn DataTableSource) {
+        let row_count = source.row_count();
+        if !self._order_dirty && self.row_order.len() == row_count {
+            return
+        }
+        self._order_dirty = false;
+        // indexed by source row, so keeping the selection stays linear after select all
+        let mut selected = vec![false; row_count];
+        if self.row_order.len() == row_count {
+            for row in self.selected_rows() {
+                selected[row] = true;
+            }
+        }
+
+        self.row_order = (0..row_count).collect();
+        if let Some((column, order)) = self.sort {
+            self.row_order.sort_by( | a, b | {
+                let ord = source.compare(column, *a, *b);
+                if order == SortOrder::Descending {ord.reverse()} else {ord}
+            });
+        }
+
+        self.list.set_list_len(row_count);
+        self.list.selection.truncate(0);
+        for (pos, row) in self.row_order.iter().enumerate() {
+            let is_selected = selected[*row];
+            if is_selected {
+                self.list.selection.push(pos);
+            }
+            let item = &mut self.list.list_items[pos];
+            item.is_selected = is_selected;
+            item.animator.end_and_set(Self::get_default_anim(cx, pos, is_selected));
+        }
+    }
+
+    fn row_height(cx: &Cx) -> f32 {
+        Self::layout_row().get(cx).walk.height.fixed()
+    }
+
+    // the position in the header under abs_x, for dropping a moved column
+    fn header_pos_at(&self, cx: &Cx, abs_x: f32) -> usize {
+        for (pos, area) in self._header_areas.iter().enumerate() {
+            let rect = area.get_rect(cx);
+            if abs_x < rect.x + rect.w {
+                return pos
+            }
+        }
+        self._header_areas.len().max(1) - 1
+    }
+
+    fn sync_header_scroll(&mut self, cx: &mut Cx) {
+        if let Some(view_id) = self.header.view_id {
+            let scroll_x = self.view.get_scroll_pos(cx).x;
+            cx.set_view_scroll_x(view_id, scroll_x);
+        }
+    }
+
+    fn toggle_sort(&mut self, cx: &mut Cx, column: usize) -> DataTableEvent {
+        if !self.columns[column].sortable {
+            return DataTableEvent::None
+        }
+        let order = match self.sort {
+            Some((sort_column, SortOrder::Ascending)) if sort_column == column => SortOrder::Descending,
+            _ => SortOrder::Ascending
+        };
+        self.set_sort(cx, column, order);
+        DataTableEvent::Sort(column, order)
+    }
+
+    fn handle_header(&mut self, cx: &mut Cx, event: &mut Event) -> DataTableEvent {
+        for pos in 0..self._header_areas.len() {
+            let column = self.column_order[pos];
+            match event.hits(cx, self._header_areas[pos], HitOpt::default()) {
+                Event::FingerHover(fe) => {
+                    if fe.rel.x >= fe.rect.w - 5. {
+                        cx.set_hover_mouse_cursor(MouseCursor::ColResize);
+                    }
+                    else {
+                        cx.set_hover_mouse_cursor(MouseCursor::Default);
+                    }
+                },
+                Event::FingerDown(fe) => {
+                    cx.set_key_focus(self.view.get_view_area(cx));
+                    self.drag = if fe.rel.x >= fe.rect.w - 5. {
+                        cx.set_down_mouse_cursor(MouseCursor::ColResize);
+                        DataTableDrag::Resize {column, start_width: self.columns[column].width}
+                    }
+                    else {
+                        DataTableDrag::Press(pos)
+                    };
+                },
+                Event::FingerMove(fe) => match self.drag {
+                    DataTableDrag::Resize {column, start_width} => {
+                        let col = &mut self.columns[column];
+                        col.width = (start_width + fe.abs.x - fe.abs_start.x).max(col.min_width);
+                        self.redraw_data_table(cx);
+                    },
+                    DataTableDrag::Press(pos) => if (fe.abs.x - fe.abs_start.x).abs() > 4. {
+                        cx.set_down_mouse_cursor(MouseCursor::Move);
+                        self.drag = DataTableDrag::Move(pos);
+                        self.header.redraw_view_area(cx);
+                    },
+                    _ => ()
+                },
+                Event::A11yAction(ae) => if ae.action == A11yAction::Press {
+                    return self.toggle_sort(cx, column)
+                },
+                Event::FingerUp(fe) => {
+                    let drag = std::mem::replace(&mut self.drag, DataTableDrag::None);
+                    match drag {
+                        DataTableDrag::Press(_) => return self.toggle_sort(cx, column),
+                        DataTableDrag::Move(from) => {
+                            let to = self.header_pos_at(cx, fe.abs.x);
+                            self.redraw_data_table(cx);
+                            if to != from {
+                                let moved = self.column_order.remove(from);
+                                self.column_order.insert(to, moved);
+                                return DataTableEvent::ColumnsChanged
+                            }
+                        },
+                        DataTableDrag::Resize {..} => return DataTableEvent::ColumnsChanged,
+                        DataTableDrag::None => ()
+                    }
+                },
+                _ => ()
+            }
+        }
+        DataTableEvent::None
+    }
+
+    pub fn handle_data_table(&mut self, cx: &mut Cx, event: &mut Event, source: &dyn DataTableSource) -> DataTableEvent {
+        self.update_row_order(cx, source);
+
+        if self.view.handle_scroll_view(cx, event) {
+            self.view.redraw_view_area(cx);
+            self.sync_header_scroll(cx);
+        }
+
+        let header_event = self.handle_header(cx, event);
+        if header_event != DataTableEvent::None {
+            return header_event
+        }
+
+        let mut select = ListSelect::None;
+        for pos in self.list.start_item..self.list.end_item.min(self.list.list_items.len()) {
+            if let Event::A11yAction(ae) = event.hits(cx, self.list.list_items[pos].animator.area, HitOpt::default()) {
+                match ae.action {
+                    A11yAction::Select => select = ListSelect::Single(pos),
+                    A11yAction::Press => return DataTableEvent::Activate(self.row_order[pos]),
+                    _ => ()
+                }
+            }
+        }
+        let view_area = self.view.get_view_area(cx);
+        match self.key_button.handle_key_activation(cx, event, view_area) {
+            ButtonEvent::Clicked => if let Some(cursor) = self.list.selection.last() {
+                return DataTableEvent::Activate(self.row_order[*cursor])
+            },
+            ButtonEvent::Down => return DataTableEvent::None,
+            _ => ()
+        }
+        match event.hits(cx, view_area, HitOpt::default()) {
+            Event::KeyFocus(_) | Event::KeyFocusLost(_) => self.view.redraw_view_area(cx),
+            Event::KeyDown(ke) => {
+                let len = self.row_order.len();
+                let cursor = self.list.selection.last().cloned();
+                let page = ((self.view.get_rect(cx).h / Self::row_height(cx)) as usize).max(1);
+                let next = match ke.key_code {
+                    KeyCode::ArrowDown => Some(cursor.map( | c | c + 1).unwrap_or(0)),
+                    KeyCode::ArrowUp => Some(cursor.map( | c | c.max(1) - 1).unwrap_or(0)),
+                    KeyCode::PageDown => Some(cursor.map( | c | c + page).unwrap_or(0)),
+                    KeyCode::PageUp => Some(cursor.map( | c | c.max(page) - page).unwrap_or(0)),
+                    KeyCode::Home => Some(0),
+                    KeyCode::End => Some(len),
+                    KeyCode::KeyA if ke.modifiers.control || ke.modifiers.logo => {
+                        select = ListSelect::All;
+                        None
+                    },
+                    _ => None
+                };
+                if let Some(next) = next {
+                    if len > 0 {
+                        let next = next.min(len - 1);
+                        select = if ke.modifiers.shift {ListSelect::Range(next)} else {ListSelect::Single(next)};
+                        self.list.scroll_item_in_view = Some(next);
+                    }
+                }
+            },
+            Event::TextCopy(_) => match event {
+                Event::TextCopy(req) => {
+                    req.response = Some(self.selection_as_tsv(source));
+                },
+                _ => ()
+            },
+            _ => ()
+        }
+
+        let le = self.list.handle_list_logic(cx, event, select, false, | cx, item_event, item, item_index | match item_event {
+            ListLogicEvent::Animate(ae) => {
+                item.animator.calc_area(cx, item.animator.area, ae.time);
+            },
+            ListLogicEvent::AnimEnded => {
+                item.animator.end();
+            },
+            ListLogicEvent::Select => {
+                item.animator.play_anim(cx, Self::get_over_anim(cx, item_index, true));
+            },
+            ListLogicEvent::Deselect => {
+                item.animator.play_anim(cx, Self::get_default_anim(cx, item_index, false));
+            },
+            ListLogicEvent::Cleanup => {
+                item.animator.play_anim(cx, Self::get_default_anim(cx, item_index, item.is_selected));
+            },
+            ListLogicEvent::Over => {
+                item.animator.play_anim(cx, Self::get_over_anim(cx, item_index, item.is_selected));
+            },
+            ListLogicEvent::Out => {
+                item.animator.play_anim(cx, Self::get_default_anim(cx, item_index, item.is_selected));
+            }
+        });
+
+        match le {
+            ListEvent::SelectSingle(_) | ListEvent::SelectMultiple => {
+                cx.set_key_focus(view_area);
+                self.view.redraw_view_area(cx);
+                DataTableEvent::Select(self.selected_rows())
+            },
+            ListEvent::SelectDouble(pos) => {
+                cx.set_key_focus(view_area);
+                DataTableEvent::Activate(self.row_order[pos])
+            },
+            ListEvent::None => DataTableEvent::None
+        }
+    }
+
+    fn draw_header(&mut self, cx: &mut Cx) {
+        if self.header.begin_view(cx, Self::layout_header().get(cx)).is_err() {
+            return
+        }
+        self.header_bg.shader = Self::shader_header().get(cx);
+        self.sort_arrow.shader = Self::shader_sort_arrow().get(cx);
+        self.text.text_style = Self::text_style_header().get(cx);
+        let dragged = if let DataTableDrag::Move(pos) = self.drag {Some(pos)} else {None};
+        for (pos, column) in self.column_order.iter().enumerate() {
+            let col = &self.columns[*column];
+            self.header_bg.color = if dragged == Some(pos) {Self::color_header_drag().get(cx)} else {Self::color_header().get(cx)};
+            let inst = self.header_bg.begin_quad(cx, Layout {
+                walk: Walk::wh(Width::Fix(col.width), Height::Fill),
+                ..Self::layout_cell().get(cx)
+            });
+            let sort_order = match self.sort {
+                Some((sort_column, order)) if sort_column == *column => Some(order),
+                _ => None
+            };
+            // leave room for the arrow
+            let arrow_width = if sort_order.is_some() {12.} else {0.};
+            self.text.color = Self::color_header_label().get(cx);
+            self.text.wrapping = Wrapping::Ellipsis(cx.get_width_left() - arrow_width);
+            self.text.draw_text(cx, &col.label);
+            if let Some(order) = sort_order {
+                self.sort_arrow.color = Self::color_header_label().get(cx);
+                let arrow = self.sort_arrow.draw_quad(cx, Self::walk_sort_arrow().get(cx));
+                arrow.push_float(cx, if order == SortOrder::Descending {1.} else {0.});
+            }
+            let area = self.header_bg.end_quad(cx, &inst);
+            cx.add_a11y_node(A11yNode {
+                actions: if col.sortable {vec![A11yAction::Press]} else {vec![]},
+                ..A11yNode::new(A11yRole::ColumnHeader, &col.label, area)
+            });
+            if pos < self._header_areas.len() {
+                self._header_areas[pos] = cx.update_area_refs(self._header_areas[pos], area);
+            }
+            else {
+                self._header_areas.push(area);
+            }
+        }
+        self._header_areas.truncate(self.column_order.len());
+        self.text.wrapping = Wrapping::None;
+        self.header.end_view(cx);
+    }
+
+    pub fn draw_data_table(&mut self, cx: &mut Cx, source: &mut dyn DataTableSource) {
+        self.update_row_order(cx, source);
+
+        cx.begin_turtle(Layout {
+            direction: Direction::Down,
+            walk: Walk::wh(Width::Fill, Height::Fill),
+            ..Layout::default()
+        }, Area::Empty);
+
+        // the header and the rows are in views of their own, the table node goes around both
+        cx.begin_a11y_node(A11yNode::new(A11yRole::Table, "", Area::Empty));
+        self.draw_header(cx);
+
+        let row_height = Self::row_height(cx);
+        if self.list.begin_list(cx, &mut self.view, false, row_height).is_ok() {
+            cx.add_focusable(self.view.get_view_area(cx));
+            let columns_width: f32 = self.column_order.iter().map( | column | self.columns[*column].width).sum();
+            let row_width = columns_width.max(cx.get_width_total());
+            self.text.text_style = Self::text_style_cell().get(cx);
+            for pos in self.list.start_item..self.list.end_item {
+                let row = self.row_order[pos];
+                let item = &mut self.list.list_items[pos];
+                item.animator.init(cx, | cx | Self::get_default_anim(cx, pos, false));
+                self.item_bg.color = item.animator.last_color(cx, Quad::color());
+                let inst = self.item_bg.begin_quad(cx, Layout {
+                    walk: Walk::wh(Width::Fix(row_width), Height::Fix(row_height)),
+                    ..Self::layout_row().get(cx)
+                });
+                for column in &self.column_order {
+                    cx.begin_turtle(Layout {
+                        walk: Walk::wh(Width::Fix(self.columns[*column].width), Height::Fill),
+                        ..Self::layout_cell().get(cx)
+                    }, Area::Empty);
+                    self.text.color = Self::color_cell().get(cx);
+                    if !source.draw_cell(cx, row, *column, &mut self.text) {
+                        self.text.wrapping = Wrapping::Ellipsis(cx.get_width_left());
+                        self.text.draw_text(cx, &source.cell_text(row, *column));
+                        self.text.wrapping = Wrapping::None;
+                    }
+                    cx.end_turtle(Area::Empty);
+                }
+                let area = self.item_bg.end_quad(cx, &inst);
+                item.animator.set_area(cx, area);
+                let cells: Vec<String> = self.column_order.iter().map( | column | source.cell_text(row, *column)).collect();
+                cx.add_a11y_node(A11yNode {
+                    selected: item.is_selected,
+                    actions: vec![A11yAction::Select, A11yAction::Press],
+                    ..A11yNode::new(A11yRole::Row, &cells.join(", "), area)
+                });
+            }
+            self.list.walk_turtle_to_end(cx, row_height);
+            self.focus_ring.draw_focus_ring(cx, self.view.get_view_area(cx));
+            self.list.end_list(cx, &mut self.view);
+        }
+        cx.end_a11y_node();
+        self.sync_header_scroll(cx);
+
+        cx.end_turtle(Area::Empty);
+    }
+}
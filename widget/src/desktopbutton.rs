@@ -173,5 +173,16 @@ impl DesktopButton {
         bg_inst.push_float(cx, ty.shader_float());
         self._bg_area = bg_inst.into();
         self.animator.set_area(cx, self._bg_area); // if our area changed, update animation
+        let name = match ty {
+            DesktopButtonType::WindowsMin => "Minimize",
+            DesktopButtonType::WindowsMax => "Maximize",
+            DesktopButtonType::WindowsMaxToggled => "Restore",
+            DesktopButtonType::WindowsClose => "Close",
+            DesktopButtonType::VRMode => "VR Mode",
+        };
+        cx.add_a11y_node(A11yNode {
+            actions: vec![A11yAction::Press],
+            ..A11yNode::new(A11yRole::Button, name, self._bg_area)
+        });
     }
 }
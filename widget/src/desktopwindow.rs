@@ -2,6 +2,7 @@ use makepad_render::*;
 use crate::buttonlogic::*;
 use crate::desktopbutton::*;
 use crate::windowmenu::*;
+use crate::tooltip::*;
 use crate::widgetstyle::*;
 
 #[derive(Clone)]
@@ -25,6 +26,7 @@ pub struct DesktopWindow {
     
     pub window_menu: WindowMenu,
     pub default_menu: Menu,
+    pub tooltip: Tooltip,
     
     pub _last_menu: Option<Menu>,
     
@@ -62,6 +64,7 @@ impl DesktopWindow {
                     Menu::item("Quit App",  Cx::command_quit()),
                 ]),
             ]),
+            tooltip: Tooltip::new(cx),
             caption_text: Text::new(cx),
             //caption_bg_color: Color_bg_selected_over::id(cx),
             caption_bg: Quad::new(cx),
@@ -142,6 +145,15 @@ impl DesktopWindow {
                 DesktopWindowEvent::EventForOtherWindow
             }
             else {
+                // Tab moves the key focus between focusables, the focused one doesn't get the key
+                if let Event::KeyDown(ke) = event {
+                    if ke.key_code == KeyCode::Tab && !ke.modifiers.control && !ke.modifiers.alt && !ke.modifiers.logo {
+                        if cx.focus_next(window_id, ke.modifiers.shift) {
+                            *event = Event::None;
+                        }
+                    }
+                }
+                self.tooltip.handle_tooltip(cx, event, window_id);
                 DesktopWindowEvent::None
             }
         }
@@ -156,6 +168,8 @@ impl DesktopWindow {
             return Err(())
         }
         
+        // the title of a new window, and the name of its accessibility root
+        self.window.create_title = self.caption.clone();
         self.window.begin_window(cx);
         self.pass.begin_pass(cx);
         self.pass.add_color_texture(cx, &mut self.color_texture, ClearColor::ClearWith(pick!(30, 30, 30).get(cx)));
@@ -249,6 +263,7 @@ impl DesktopWindow {
             cx.move_turtle(cx.get_width_total() - 50.0, 0.);
             self.vr_btn.draw_desktop_button(cx, DesktopButtonType::VRMode);
         }
+        self.tooltip.draw_tooltip(cx);
         self.main_view.end_view(cx);
         
         self.pass.end_pass(cx);
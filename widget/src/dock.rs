@@ -22,6 +22,7 @@ where TItem: Clone
     pub _drag_end: Option<DockDragEnd<TItem>>,
     pub _close_tab: Option<DockTabIdent>,
     pub _tab_select: Option<(usize, usize)>,
+    pub _tab_context_menu: Option<(usize, usize, Vec2)>,
     pub _tweening_quad: Option<(usize, Rect, f32)>
 }
 
@@ -67,6 +68,209 @@ where TItem: Clone
 }
 
 
+// where split_tab_control puts the new tab control next to the old one
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DockSplit {
+    Left,
+    Right,
+    Top,
+    Bottom
+}
+
+// Manipulating the dock tree by the identity of its tabs. The predicates see the items,
+// the first tab (in drawing order) that matches is the one meant. The caller redraws.
+impl<TItem> DockItem<TItem>
+where TItem: Clone
+{
+    pub fn find_tab<F>(&self, mut pred: F) -> Option<&DockTab<TItem>>
+    where F: FnMut(&TItem) -> bool {
+        self.recur_find_tab(&mut pred)
+    }
+    
+    fn recur_find_tab<F>(&self, pred: &mut F) -> Option<&DockTab<TItem>>
+    where F: FnMut(&TItem) -> bool {
+        match self {
+            DockItem::Single(_) => None,
+            DockItem::TabControl {tabs, ..} => tabs.iter().find( | tab | pred(&tab.item)),
+            DockItem::Splitter {first, last, ..} => {
+                if let Some(tab) = first.recur_find_tab(pred) {
+                    return Some(tab)
+                }
+                last.recur_find_tab(pred)
+            }
+        }
+    }
+    
+    pub fn find_tab_mut<F>(&mut self, mut pred: F) -> Option<&mut DockTab<TItem>>
+    where F: FnMut(&TItem) -> bool {
+        if let Some((DockItem::TabControl {tabs, ..}, tab_id)) = self.find_tab_control(&mut pred) {
+            return Some(&mut tabs[tab_id])
+        }
+        None
+    }
+    
+    // the tab control holding the first tab that matches, and where in it
+    fn find_tab_control<F>(&mut self, pred: &mut F) -> Option<(&mut DockItem<TItem>, usize)>
+    where F: FnMut(&TItem) -> bool {
+        match self {
+            DockItem::Single(_) => None,
+            DockItem::TabControl {tabs, ..} => {
+                if let Some(tab_id) = tabs.iter().position( | tab | pred(&tab.item)) {
+                    return Some((self, tab_id))
+                }
+                None
+            },
+            DockItem::Splitter {first, last, ..} => {
+                if let Some(found) = first.find_tab_control(pred) {
+                    return Some(found)
+                }
+                last.find_tab_control(pred)
+            }
+        }
+    }
+    
+    // makes the tab the current one of its tab control, false if there is no such tab
+    pub fn focus_tab<F>(&mut self, mut pred: F) -> bool
+    where F: FnMut(&TItem) -> bool {
+        if let Some((DockItem::TabControl {current, previous, ..}, tab_id)) = self.find_tab_control(&mut pred) {
+            if *current != tab_id {
+                *previous = *current;
+                *current = tab_id;
+            }
+            return true
+        }
+        false
+    }
+    
+    // puts tab in the tab control of the tab that matches, before or after it, and focuses it
+    pub fn insert_tab<F>(&mut self, mut pred: F, tab: DockTab<TItem>, after: bool) -> bool
+    where F: FnMut(&TItem) -> bool {
+        if let Some((DockItem::TabControl {current, previous, tabs}, tab_id)) = self.find_tab_control(&mut pred) {
+            let tab_id = if after {tab_id + 1} else {tab_id};
+            tabs.insert(tab_id, tab);
+            *previous = *current;
+            *current = tab_id;
+            return true
+        }
+        false
+    }
+    
+    // splits the tab control of the tab that matches in half, the new half gets tabs
+    pub fn split_tab_control<F>(&mut self, mut pred: F, side: DockSplit, tabs: Vec<DockTab<TItem>>) -> bool
+    where F: FnMut(&TItem) -> bool {
+        if let Some((dock_item, _)) = self.find_tab_control(&mut pred) {
+            let old = Box::new(dock_item.clone());
+            let new = Box::new(DockItem::TabControl {current: 0, previous: 0, tabs: tabs});
+            let (axis, first, last) = match side {
+                DockSplit::Left => (Axis::Vertical, new, old),
+                DockSplit::Right => (Axis::Vertical, old, new),
+                DockSplit::Top => (Axis::Horizontal, new, old),
+                DockSplit::Bottom => (Axis::Horizontal, old, new),
+            };
+            *dock_item = DockItem::Splitter {
+                align: SplitterAlign::Weighted,
+                pos: 0.5,
+                axis: axis,
+                first: first,
+                last: last
+            };
+            return true
+        }
+        false
+    }
+    
+    fn take_tab<F>(&mut self, pred: &mut F) -> Option<DockTab<TItem>>
+    where F: FnMut(&TItem) -> bool {
+        if let Some((DockItem::TabControl {current, previous, tabs}, tab_id)) = self.find_tab_control(pred) {
+            let tab = tabs.remove(tab_id);
+            if tab_id < *current || *current >= tabs.len() && *current > 0 {
+                *current -= 1;
+            }
+            *previous = *current;
+            return Some(tab)
+        }
+        None
+    }
+    
+    // removes the tab that matches, tab controls left empty go away with their splitter
+    pub fn close_tab<F>(&mut self, mut pred: F) -> Option<DockTab<TItem>>
+    where F: FnMut(&TItem) -> bool {
+        let tab = self.take_tab(&mut pred);
+        if tab.is_some() {
+            Dock::recur_collapse_empty(self);
+        }
+        tab
+    }
+    
+    // moves the tab that matches next to the target tab, false if either is missing
+    pub fn move_tab<F, G>(&mut self, mut pred: F, mut target: G, after: bool) -> bool
+    where F: FnMut(&TItem) -> bool, G: FnMut(&TItem) -> bool {
+        match (self.recur_find_tab(&mut pred), self.recur_find_tab(&mut target)) {
+            (Some(tab), Some(target_tab)) if !std::ptr::eq(tab, target_tab) => (),
+            _ => return false
+        }
+        let tab = self.take_tab(&mut pred).unwrap();
+        self.insert_tab(target, tab, after);
+        Dock::recur_collapse_empty(self);
+        true
+    }
+}
+
+// Named dock layouts an app saves and goes back to, it serializes with its state
+#[derive(Clone, SerRon, DeRon)]
+pub struct DockPreset<TItem>
+where TItem: Clone
+{
+    pub name: String,
+    pub dock_items: DockItem<TItem>
+}
+
+#[derive(Clone, SerRon, DeRon)]
+pub struct DockPresets<TItem>
+where TItem: Clone
+{
+    pub presets: Vec<DockPreset<TItem>>
+}
+
+impl<TItem> DockPresets<TItem>
+where TItem: Clone
+{
+    pub fn new() -> Self {
+        Self {presets: Vec::new()}
+    }
+    
+    // replaces a preset of the same name
+    pub fn save_preset(&mut self, name: &str, dock_items: &DockItem<TItem>) {
+        if let Some(preset) = self.presets.iter_mut().find( | preset | preset.name == name) {
+            preset.dock_items = dock_items.clone();
+            return
+        }
+        self.presets.push(DockPreset {name: name.to_string(), dock_items: dock_items.clone()});
+    }
+    
+    pub fn get_preset(&self, name: &str) -> Option<&DockItem<TItem>> {
+        self.presets.iter().find( | preset | preset.name == name).map( | preset | &preset.dock_items)
+    }
+    
+    // puts the preset in dock_items, false if there is none of that name
+    pub fn restore_preset(&self, name: &str, dock_items: &mut DockItem<TItem>) -> bool {
+        if let Some(preset) = self.get_preset(name) {
+            *dock_items = preset.clone();
+            return true
+        }
+        false
+    }
+    
+    pub fn remove_preset(&mut self, name: &str) {
+        self.presets.retain( | preset | preset.name != name);
+    }
+    
+    pub fn preset_names(&self) -> Vec<String> {
+        self.presets.iter().map( | preset | preset.name.clone()).collect()
+    }
+}
+
+
 struct DockWalkStack<'a, TItem>
 where TItem: Clone
 {
@@ -75,10 +279,15 @@ where TItem: Clone
     item: &'a mut DockItem<TItem>
 }
 
-pub enum DockEvent {
+pub enum DockEvent<TItem>
+where TItem: Clone
+{
     None,
     DockTabClosed,
     DockTabCloned {tab_control_id: usize, tab_id: usize},
+    // a closeable tab let go outside of the dock, it is removed already. abs is relative to the window
+    DockTabDraggedOut {tab: DockTab<TItem>, abs: Vec2},
+    DockTabContextMenu {tab_control_id: usize, tab_id: usize, abs: Vec2},
     DockChanged
 }
 
@@ -94,7 +303,8 @@ where TItem: Clone
     _drag_move: &'a mut Option<FingerMoveEvent>,
     _drag_end: &'a mut Option<DockDragEnd<TItem>>,
     _close_tab: &'a mut Option<DockTabIdent>,
-    _tab_select: &'a mut Option<(usize, usize)>
+    _tab_select: &'a mut Option<(usize, usize)>,
+    _tab_context_menu: &'a mut Option<(usize, usize, Vec2)>
 }
 
 impl<'a, TItem> DockWalker<'a, TItem>
@@ -230,6 +440,9 @@ where TItem: Clone
                                     }
                                     cx.redraw_child_area(Area::All);
                                 },
+                                TabControlEvent::TabContextMenu {tab_id, abs} => {
+                                    *self._tab_context_menu = Some((stack_top.uid, tab_id, abs));
+                                },
                                 _ => ()
                             }
                         }
@@ -406,10 +619,30 @@ where TItem: Clone
             _drag_move: None,
             _drag_end: None,
             _tab_select: None,
+            _tab_context_menu: None,
             _tweening_quad: None
         }
     }
     
+    pub fn get_tab<'a>(dock_items: &'a DockItem<TItem>, tab_control_id: usize, tab_id: usize) -> Option<&'a DockTab<TItem>> {
+        Self::recur_get_tab(dock_items, tab_control_id, tab_id, &mut 0)
+    }
+    
+    // closes a tab without its close animation, like from a context menu. false if it isn't closeable
+    pub fn close_tab(&mut self, cx: &mut Cx, dock_items: &mut DockItem<TItem>, tab_control_id: usize, tab_id: usize) -> bool {
+        match Self::get_tab(dock_items, tab_control_id, tab_id) {
+            Some(tab) if tab.closeable => (),
+            _ => return false
+        }
+        Self::recur_remove_tab(dock_items, tab_control_id, tab_id, &mut 0, false, false);
+        if let Some(tab_control) = self.tab_controls.get(tab_control_id) {
+            tab_control.tabs.clear(cx, | _, _ | ());
+        }
+        Self::recur_collapse_empty(dock_items);
+        cx.redraw_child_area(Area::All);
+        true
+    }
+    
     fn recur_remove_tab(dock_walk: &mut DockItem<TItem>, control_id: usize, tab_id: usize, counter: &mut usize, clone: bool, select_previous:bool) -> Option<DockTab<TItem>>
     where TItem: Clone
     {
@@ -453,6 +686,29 @@ where TItem: Clone
         None
     }
     
+    fn recur_get_tab<'a>(dock_walk: &'a DockItem<TItem>, control_id: usize, tab_id: usize, counter: &mut usize) -> Option<&'a DockTab<TItem>>
+    where TItem: Clone
+    {
+        match dock_walk {
+            DockItem::Single(_) => {},
+            DockItem::TabControl {tabs, ..} => {
+                let id = *counter;
+                *counter += 1;
+                if id == control_id {
+                    return tabs.get(tab_id)
+                }
+            },
+            DockItem::Splitter {first, last, ..} => {
+                *counter += 1;
+                if let Some(tab) = Self::recur_get_tab(first, control_id, tab_id, counter) {
+                    return Some(tab)
+                }
+                return Self::recur_get_tab(last, control_id, tab_id, counter)
+            }
+        }
+        None
+    }
+    
     fn recur_collapse_empty(dock_walk: &mut DockItem<TItem>) -> bool
     where TItem: Clone
     {
@@ -642,7 +898,7 @@ where TItem: Clone
         });
     }
     
-    pub fn handle_dock(&mut self, cx: &mut Cx, _event: &mut Event, dock_items: &mut DockItem<TItem>) -> DockEvent {
+    pub fn handle_dock(&mut self, cx: &mut Cx, _event: &mut Event, dock_items: &mut DockItem<TItem>) -> DockEvent<TItem> {
         if let Some(close_tab) = &self._close_tab {
             Self::recur_remove_tab(dock_items, close_tab.tab_control_id, close_tab.tab_id, &mut 0, false, false);
             Self::recur_collapse_empty(dock_items);
@@ -650,14 +906,24 @@ where TItem: Clone
             self._close_tab = None;
             return DockEvent::DockTabClosed
         }
+        if let Some((tab_control_id, tab_id, abs)) = self._tab_context_menu.take() {
+            return DockEvent::DockTabContextMenu {tab_control_id, tab_id, abs}
+        }
         if let Some(drag_end) = self._drag_end.clone() {
             self._drag_end = None;
             let mut tab_clone_ident = None;
             let fe = match &drag_end {DockDragEnd::OldTab {fe, ..} => fe, DockDragEnd::NewItems {fe, ..} => fe};
+            // the rect around all tab controls, letting go outside of it drags the tab out
+            let mut dock_min = Vec2 {x: std::f32::INFINITY, y: std::f32::INFINITY};
+            let mut dock_max = Vec2 {x: -std::f32::INFINITY, y: -std::f32::INFINITY};
             for (target_id, tab_control) in self.tab_controls.enumerate() {
                 
                 let cdr = tab_control.get_content_drop_rect(cx);
                 let tvr = tab_control.get_tabs_view_rect(cx);
+                for r in &[cdr, tvr] {
+                    dock_min = Vec2 {x: dock_min.x.min(r.x), y: dock_min.y.min(r.y)};
+                    dock_max = Vec2 {x: dock_max.x.max(r.x + r.w), y: dock_max.y.max(r.y + r.h)};
+                }
                 if tvr.contains(fe.abs.x, fe.abs.y) || cdr.contains(fe.abs.x, fe.abs.y) { // we might got dropped elsewhere
                     // ok now, we ask the tab_controls rect
                     let tab_rects = tab_control.get_tab_rects(cx);
@@ -703,6 +969,18 @@ where TItem: Clone
                     };
                 }
             }
+            if let DockDragEnd::OldTab {ident, ..} = &drag_end {
+                if fe.abs.x < dock_min.x || fe.abs.y < dock_min.y || fe.abs.x > dock_max.x || fe.abs.y > dock_max.y {
+                    let closeable = Self::recur_get_tab(dock_items, ident.tab_control_id, ident.tab_id, &mut 0).map_or(false, | tab | tab.closeable);
+                    if closeable {
+                        if let Some(tab) = Self::recur_remove_tab(dock_items, ident.tab_control_id, ident.tab_id, &mut 0, false, true) {
+                            Self::recur_collapse_empty(dock_items);
+                            cx.redraw_child_area(Area::All);
+                            return DockEvent::DockTabDraggedOut {tab: tab, abs: fe.abs}
+                        }
+                    }
+                }
+            }
             Self::recur_collapse_empty(dock_items);
             cx.redraw_child_area(Area::All);
             //Self::recur_debug_dock(self.dock_items.as_mut().unwrap(), &mut 0, 0);
@@ -792,6 +1070,7 @@ where TItem: Clone
             _drag_end: &mut self._drag_end,
             _close_tab: &mut self._close_tab,
             _tab_select: &mut self._tab_select,
+            _tab_context_menu: &mut self._tab_context_menu,
             drop_quad_view: &mut self.drop_quad_view
         }
     }
@@ -823,3 +1102,108 @@ Self::recur_debug_dock(last, counter, depth + 1);
 }
 }
 }*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tab_control(items: &[u32]) -> DockItem<u32> {
+        DockItem::TabControl {
+            current: 0,
+            previous: 0,
+            tabs: items.iter().map( | item | DockTab {closeable: true, title: item.to_string(), item: *item}).collect()
+        }
+    }
+
+    fn split(axis: Axis, first: DockItem<u32>, last: DockItem<u32>) -> DockItem<u32> {
+        DockItem::Splitter {pos: 0.5, align: SplitterAlign::Weighted, axis: axis, first: Box::new(first), last: Box::new(last)}
+    }
+
+    // [1* 2] is a tab control with 1 current, (a|b) splits it side by side and (a-b) above each other
+    fn layout(dock_item: &DockItem<u32>) -> String {
+        match dock_item {
+            DockItem::Single(item) => item.to_string(),
+            DockItem::TabControl {current, tabs, ..} => {
+                let tabs: Vec<String> = tabs.iter().enumerate().map( | (index, tab) | {
+                    if index == *current {format!("{}*", tab.item)} else {tab.item.to_string()}
+                }).collect();
+                format!("[{}]", tabs.join(" "))
+            },
+            DockItem::Splitter {axis, first, last, ..} => {
+                format!("({}{}{})", layout(first), if let Axis::Vertical = axis {"|"} else {"-"}, layout(last))
+            }
+        }
+    }
+
+    fn new_tab(item: u32) -> Vec<DockTab<u32>> {
+        vec![DockTab {closeable: true, title: item.to_string(), item: item}]
+    }
+
+    #[test]
+    fn split_tab_control() {
+        for (side, expect) in vec![
+            (DockSplit::Left, "([3*]|[1* 2])"),
+            (DockSplit::Right, "([1* 2]|[3*])"),
+            (DockSplit::Top, "([3*]-[1* 2])"),
+            (DockSplit::Bottom, "([1* 2]-[3*])"),
+        ] {
+            let mut dock_item = tab_control(&[1, 2]);
+            assert!(dock_item.split_tab_control( | item | *item == 2, side, new_tab(3)));
+            assert_eq!(layout(&dock_item), expect);
+        }
+        // only the tab control of the tab is split
+        let mut dock_item = split(Axis::Vertical, tab_control(&[1]), tab_control(&[2]));
+        assert!(dock_item.split_tab_control( | item | *item == 2, DockSplit::Bottom, new_tab(3)));
+        assert_eq!(layout(&dock_item), "([1*]|([2*]-[3*]))");
+        assert!(!dock_item.split_tab_control( | item | *item == 4, DockSplit::Left, new_tab(5)));
+    }
+
+    #[test]
+    fn move_tab_within_a_tab_control() {
+        let mut dock_item = tab_control(&[1, 2, 3]);
+        assert!(dock_item.move_tab( | item | *item == 1, | item | *item == 3, true));
+        assert_eq!(layout(&dock_item), "[2 3 1*]");
+        assert!(dock_item.move_tab( | item | *item == 3, | item | *item == 2, false));
+        assert_eq!(layout(&dock_item), "[3* 2 1]");
+        // onto itself or a tab that isn't there
+        assert!(!dock_item.move_tab( | item | *item == 2, | item | *item == 2, true));
+        assert!(!dock_item.move_tab( | item | *item == 2, | item | *item == 4, true));
+        assert_eq!(layout(&dock_item), "[3* 2 1]");
+    }
+
+    #[test]
+    fn move_tab_across_tab_controls() {
+        let mut dock_item = split(Axis::Vertical, tab_control(&[1, 2]), tab_control(&[3]));
+        assert!(dock_item.move_tab( | item | *item == 2, | item | *item == 3, true));
+        assert_eq!(layout(&dock_item), "([1*]|[3 2*])");
+        // the tab control left empty goes, with its splitter
+        assert!(dock_item.move_tab( | item | *item == 1, | item | *item == 3, false));
+        assert_eq!(layout(&dock_item), "[1* 3 2]");
+    }
+
+    #[test]
+    fn close_tab() {
+        let mut dock_item = tab_control(&[1, 2, 3]);
+        assert!(dock_item.focus_tab( | item | *item == 3));
+        // the current tab stays current when a tab before it goes
+        assert_eq!(dock_item.close_tab( | item | *item == 1).map( | tab | tab.item), Some(1));
+        assert_eq!(layout(&dock_item), "[2 3*]");
+        // closing the last one makes the one before it current
+        assert_eq!(dock_item.close_tab( | item | *item == 3).map( | tab | tab.item), Some(3));
+        assert_eq!(layout(&dock_item), "[2*]");
+        assert!(dock_item.close_tab( | item | *item == 3).is_none());
+    }
+
+    #[test]
+    fn close_tab_collapses_empty_tab_controls() {
+        let mut dock_item = split(
+            Axis::Horizontal,
+            split(Axis::Vertical, tab_control(&[1]), tab_control(&[2])),
+            tab_control(&[3, 4])
+        );
+        assert!(dock_item.close_tab( | item | *item == 1).is_some());
+        assert_eq!(layout(&dock_item), "([2*]-[3* 4])");
+        assert!(dock_item.close_tab( | item | *item == 2).is_some());
+        assert_eq!(layout(&dock_item), "[3* 4]");
+    }
+}
@@ -0,0 +1,310 @@
+use makepad_render::*;
+use crate::buttonlogic::*;
+use crate::widgetstyle::*;
+
+// A button showing the picked item of a list, pressing it opens the list in a popup below.
+// The caller owns the selected index, Select brings the new one back.
+// With the key focus the up and down keys pick the item next to it, space or return opens the list.
+
+#[derive(Clone)]
+pub struct DropDown {
+    pub button: ButtonLogic,
+    pub bg: Quad,
+    pub text: Text,
+    pub animator: Animator,
+    pub popup: Popup,
+    pub list_bg: Quad,
+    pub item_bg: Quad,
+    pub selected: usize,
+    // the item under the mouse or the arrow keys while the list is open
+    pub highlighted: usize,
+    pub _bg_area: Area,
+    pub _text_area: Area,
+    pub _item_areas: Vec<Area>,
+    pub _item_count: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DropDownEvent {
+    None,
+    Select(usize)
+}
+
+impl DropDown {
+    pub fn new(cx: &mut Cx) -> Self {
+        Self {
+            button: ButtonLogic::default(),
+            bg: Quad::new(cx),
+            text: Text {
+                wrapping: Wrapping::None,
+                ..Text::new(cx)
+            },
+            animator: Animator::default(),
+            popup: Popup {
+                gap: 1.,
+                ..Popup::new(cx)
+            },
+            list_bg: Quad::new(cx),
+            item_bg: Quad::new(cx),
+            selected: 0,
+            highlighted: 0,
+            _bg_area: Area::Empty,
+            _text_area: Area::Empty,
+            _item_areas: Vec::new(),
+            _item_count: 0,
+        }
+    }
+
+    pub fn layout_bg() -> LayoutId {uid!()}
+    pub fn layout_list() -> LayoutId {uid!()}
+    pub fn layout_item() -> LayoutId {uid!()}
+    pub fn text_style_label() -> TextStyleId {uid!()}
+    pub fn color_list_bg() -> ColorId {uid!()}
+    pub fn color_item_highlighted() -> ColorId {uid!()}
+    pub fn color_item_label() -> ColorId {uid!()}
+    pub fn anim_default() -> AnimId {uid!()}
+    pub fn anim_over() -> AnimId {uid!()}
+    pub fn anim_down() -> AnimId {uid!()}
+    pub fn shader_bg() -> ShaderId {uid!()}
+    pub fn hover() -> FloatId {uid!()}
+    pub fn down() -> FloatId {uid!()}
+    pub fn focus() -> FloatId {uid!()}
+    pub fn focus_color() -> ColorId {uid!()}
+
+    pub fn style(cx: &mut Cx, opt: &StyleOptions) {
+        Self::layout_bg().set(cx, Layout {
+            align: Align::left_center(),
+            walk: Walk {
+                width: Width::Fix(160. * opt.scale),
+                height: Height::Fix(24. * opt.scale),
+                margin: Margin::all(1.0),
+            },
+            padding: Padding {l: 8., t: 0., r: 22. * opt.scale, b: 0.},
+            line_wrap: LineWrap::None,
+            ..Default::default()
+        });
+        Self::layout_list().set(cx, Layout {
+            walk: Walk::wh(Width::Fill, Height::Compute),
+            padding: Padding::all(2.),
+            ..Layout::default()
+        });
+        Self::layout_item().set(cx, Layout {
+            walk: Walk::wh(Width::Fill, Height::Fix(22. * opt.scale)),
+            align: Align::left_center(),
+            padding: Padding {l: 6., t: 0., r: 6., b: 0.},
+            line_wrap: LineWrap::None,
+            ..Layout::default()
+        });
+
+        Self::text_style_label().set(cx, Theme::text_style_normal().get(cx));
+        Self::color_list_bg().set(cx, Theme::color_bg_normal().get(cx));
+        Self::color_item_highlighted().set(cx, Theme::color_bg_marked().get(cx));
+        Self::color_item_label().set(cx, Theme::color_text_selected_focus().get(cx));
+
+        Self::anim_default().set(cx, Anim::new(Play::Cut {duration: 0.1}, vec![
+            Track::float(Self::hover(), Ease::Lin, vec![(1., 0.)]),
+            Track::float(Self::down(), Ease::Lin, vec![(1., 0.)]),
+            Track::color(Text::color(), Ease::Lin, vec![(1., pick!(#9).get(cx))]),
+        ]));
+
+        Self::anim_over().set(cx, Anim::new(Play::Cut {duration: 0.1}, vec![
+            Track::float(Self::down(), Ease::Lin, vec![(0., 0.)]),
+            Track::float(Self::hover(), Ease::Lin, vec![(0.0, 1.0), (1.0, 1.0)]),
+            Track::color(Text::color(), Ease::Lin, vec![(0., pick!(#f).get(cx))]),
+        ]));
+
+        Self::anim_down().set(cx, Anim::new(Play::Cut {duration: 0.2}, vec![
+            Track::float(Self::down(), Ease::OutExp, vec![(0.0, 1.0), (1.0, 1.0)]),
+            Track::float(Self::hover(), Ease::Lin, vec![(1.0, 1.0)]),
+            Track::color(Text::color(), Ease::Lin, vec![(0., pick!(#c).get(cx))]),
+        ]));
+
+        Self::shader_bg().set(cx, Quad::def_quad_shader().compose(shader!{"
+
+            instance hover: Self::hover();
+            instance down: Self::down();
+            instance focus: Self::focus();
+            instance focus_color: Self::focus_color();
+
+            fn pixel() -> vec4 {
+                let df = Df::viewport(pos * vec2(w, h));
+                df.box(1., 1., w - 2., h - 2., 2.5);
+                df.fill_keep(mix(mix(pick!(#3), pick!(#4), hover), pick!(#2a), down));
+                df.stroke(vec4(focus_color.rgb, focus_color.a * focus), 1.5);
+                let ax = w - 14.;
+                let ay = h * 0.5;
+                df.move_to(ax - 4., ay - 2.);
+                df.line_to(ax, ay + 2.);
+                df.line_to(ax + 4., ay - 2.);
+                return df.stroke(mix(pick!(#9), pick!(#f), hover), 1.2);
+            }
+        "}));
+    }
+
+    pub fn open(&mut self, cx: &mut Cx) {
+        self.highlighted = self.selected;
+        self.popup.open_at_area(cx, self._bg_area);
+    }
+
+    pub fn close(&mut self, cx: &mut Cx) {
+        self.popup.close(cx);
+    }
+
+    pub fn select(&mut self, cx: &mut Cx, index: usize) -> DropDownEvent {
+        self.close(cx);
+        if index >= self._item_count || index == self.selected {
+            return DropDownEvent::None
+        }
+        self.selected = index;
+        DropDownEvent::Select(index)
+    }
+
+    fn highlight(&mut self, cx: &mut Cx, index: usize) {
+        if index != self.highlighted {
+            self.highlighted = index;
+            self.popup.view.redraw_view_area(cx);
+        }
+    }
+
+    pub fn handle_drop_down(&mut self, cx: &mut Cx, event: &mut Event) -> DropDownEvent {
+        if self.popup.is_open {
+            for index in 0..self._item_areas.len() {
+                match event.hits(cx, self._item_areas[index], HitOpt::default()) {
+                    Event::FingerDown(_) => return self.select(cx, index),
+                    Event::A11yAction(ae) if ae.action == A11yAction::Select => return self.select(cx, index),
+                    Event::FingerHover(_) => {
+                        cx.set_hover_mouse_cursor(MouseCursor::Hand);
+                        self.highlight(cx, index);
+                    },
+                    _ => ()
+                }
+            }
+            self.popup.handle_popup(cx, event);
+        }
+
+        match event.hits(cx, self._bg_area, HitOpt::default()) {
+            Event::KeyFocus(_) | Event::KeyFocusLost(_) => cx.redraw_child_area(self._bg_area),
+            Event::A11yAction(ae) => match ae.action {
+                A11yAction::Press | A11yAction::Expand => self.open(cx),
+                A11yAction::Collapse => self.close(cx),
+                _ => ()
+            },
+            _ => ()
+        }
+
+        if let Event::KeyDown(ke) = event.hits(cx, self._bg_area, HitOpt::default()) {
+            let last = self._item_count.max(1) - 1;
+            if self.popup.is_open {
+                match ke.key_code {
+                    KeyCode::ArrowUp => self.highlight(cx, self.highlighted.max(1) - 1),
+                    KeyCode::ArrowDown => self.highlight(cx, (self.highlighted + 1).min(last)),
+                    KeyCode::Home => self.highlight(cx, 0),
+                    KeyCode::End => self.highlight(cx, last),
+                    KeyCode::Space | KeyCode::Return => return self.select(cx, self.highlighted),
+                    _ => ()
+                }
+            }
+            else {
+                match ke.key_code {
+                    KeyCode::ArrowDown if ke.modifiers.alt => self.open(cx),
+                    KeyCode::ArrowUp => return self.select(cx, self.selected.max(1) - 1),
+                    KeyCode::ArrowDown => return self.select(cx, (self.selected + 1).min(last)),
+                    KeyCode::Home => return self.select(cx, 0),
+                    KeyCode::End => return self.select(cx, last),
+                    // return and space open it through the button logic
+                    KeyCode::Space | KeyCode::Return => (),
+                    _ => return DropDownEvent::None
+                }
+            }
+            if self.popup.is_open {
+                return DropDownEvent::None
+            }
+        }
+
+        let animator = &mut self.animator;
+        let text_area = self._text_area;
+        match self.button.handle_button_logic(cx, event, self._bg_area, | cx, logic_event, area | match logic_event {
+            ButtonLogicEvent::Animate(ae) => {
+                animator.calc_area(cx, area, ae.time);
+                animator.calc_area(cx, text_area, ae.time);
+            },
+            ButtonLogicEvent::AnimEnded(_) => animator.end(),
+            ButtonLogicEvent::Down => animator.play_anim(cx, Self::anim_down().get(cx)),
+            ButtonLogicEvent::Default => animator.play_anim(cx, Self::anim_default().get(cx)),
+            ButtonLogicEvent::Over => animator.play_anim(cx, Self::anim_over().get(cx))
+        }) {
+            ButtonEvent::Down => {
+                cx.set_key_focus(self._bg_area);
+                if !self.button.key_down {
+                    self.open(cx);
+                }
+            },
+            ButtonEvent::Clicked => if !self.popup.is_open {
+                self.open(cx);
+            },
+            _ => ()
+        }
+        DropDownEvent::None
+    }
+
+    pub fn draw_drop_down(&mut self, cx: &mut Cx, selected: usize, items: &[&str]) {
+        self.selected = selected;
+        self._item_count = items.len();
+        self.bg.shader = Self::shader_bg().get(cx);
+
+        self.animator.init(cx, | cx | Self::anim_default().get(cx));
+
+        let bg_inst = self.bg.begin_quad(cx, Self::layout_bg().get(cx));
+        bg_inst.push_last_float(cx, &self.animator, Self::hover());
+        bg_inst.push_last_float(cx, &self.animator, Self::down());
+        bg_inst.push_float(cx, if cx.has_key_focus(self._bg_area) {1.} else {0.});
+        bg_inst.push_color(cx, Theme::color_focus_ring().get(cx));
+
+        self.text.text_style = Self::text_style_label().get(cx);
+        self.text.color = self.animator.last_color(cx, Text::color());
+        self.text.wrapping = Wrapping::Ellipsis(cx.get_width_left());
+        self._text_area = self.text.draw_text(cx, items.get(selected).unwrap_or(&""));
+        self.text.wrapping = Wrapping::None;
+
+        self._bg_area = self.bg.end_quad(cx, &bg_inst);
+        self.animator.set_area(cx, self._bg_area);
+        cx.add_focusable(self._bg_area);
+        cx.add_a11y_node(A11yNode {
+            value: items.get(selected).unwrap_or(&"").to_string(),
+            expanded: Some(self.popup.is_open),
+            actions: vec![A11yAction::Press, A11yAction::Expand, A11yAction::Collapse],
+            ..A11yNode::new(A11yRole::ComboBox, "", self._bg_area)
+        });
+
+        // the list is as wide as the button
+        let width = self._bg_area.get_rect(cx).w;
+        if self.popup.begin_popup(cx, Layout {
+            walk: Walk::wh(Width::Fix(width), Height::Compute),
+            ..Layout::default()
+        }).is_err() {
+            return
+        };
+
+        self.list_bg.color = Self::color_list_bg().get(cx);
+        let list_inst = self.list_bg.begin_quad(cx, Self::layout_list().get(cx));
+        self._item_areas.truncate(0);
+        cx.begin_a11y_node(A11yNode::new(A11yRole::List, "", list_inst.clone().into()));
+        for (index, item) in items.iter().enumerate() {
+            self.item_bg.color = if index == self.highlighted {Self::color_item_highlighted().get(cx)} else {Self::color_list_bg().get(cx)};
+            let item_inst = self.item_bg.begin_quad(cx, Self::layout_item().get(cx));
+            self.text.color = Self::color_item_label().get(cx);
+            self.text.draw_text(cx, item);
+            let item_area = self.item_bg.end_quad(cx, &item_inst);
+            self._item_areas.push(item_area);
+            cx.add_a11y_node(A11yNode {
+                selected: index == selected,
+                actions: vec![A11yAction::Select],
+                ..A11yNode::new(A11yRole::ListItem, item, item_area)
+            });
+            cx.turtle_new_line();
+        }
+        cx.end_a11y_node();
+        self.list_bg.end_quad(cx, &list_inst);
+        self.popup.end_popup(cx);
+    }
+}
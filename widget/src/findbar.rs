@@ -0,0 +1,387 @@
+use makepad_render::*;
+use crate::textinput::*;
+use crate::texteditor::*;
+use crate::textbuffer::*;
+use crate::textcursor::*;
+use crate::regex::*;
+use crate::widgetstyle::*;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FindOptions {
+    pub case_sensitive: bool,
+    pub whole_word: bool,
+    pub regex: bool
+}
+
+impl Default for FindOptions {
+    fn default() -> Self {
+        FindOptions {case_sensitive: false, whole_word: false, regex: false}
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum FindBarEvent {
+    None,
+    Changed,
+    Next,
+    Prev,
+    Replace,
+    ReplaceAll,
+    Close
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum FindBarButton {
+    Case,
+    Word,
+    Regex,
+    Prev,
+    Next,
+    Replace,
+    ReplaceAll,
+    Close
+}
+
+#[derive(Clone)]
+pub struct FindBar {
+    pub view: View,
+    pub bg: Quad,
+    pub button_bg: Quad,
+    pub text: Text,
+    pub find_input: TextInput,
+    pub replace_input: TextInput,
+    pub options: FindOptions,
+    pub show_replace: bool,
+    pub is_open: bool,
+    // matches in the flat text of the buffer, which is kept for expanding replacements
+    pub matches: Vec<RegexMatch>,
+    pub current: Option<usize>,
+    pub error: Option<String>,
+    pub _flat_text: Vec<char>,
+    pub _matched_mutation_id: u32,
+    pub _bg_area: Area,
+    _button_areas: Vec<(FindBarButton, Area)>,
+}
+
+impl FindBar {
+    pub fn new(cx: &mut Cx) -> Self {
+        Self::default_keymap(cx);
+        Self {
+            view: View::proto_overlay(cx),
+            bg: Quad::new(cx),
+            button_bg: Quad::new(cx),
+            text: Text::new(cx),
+            find_input: TextInput::new(cx, TextInputOptions {
+                multiline: false,
+                read_only: false,
+                empty_message: "find".to_string()
+            }),
+            replace_input: TextInput::new(cx, TextInputOptions {
+                multiline: false,
+                read_only: false,
+                empty_message: "replace".to_string()
+            }),
+            options: FindOptions::default(),
+            show_replace: false,
+            is_open: false,
+            matches: Vec::new(),
+            current: None,
+            error: None,
+            _flat_text: Vec::new(),
+            _matched_mutation_id: 0,
+            _bg_area: Area::Empty,
+            _button_areas: Vec::new(),
+        }
+    }
+
+    pub fn layout_bg() -> LayoutId {uid!()}
+    pub fn layout_button() -> LayoutId {uid!()}
+    pub fn text_style_button() -> TextStyleId {uid!()}
+    pub fn color_bg() -> ColorId {uid!()}
+    pub fn color_button() -> ColorId {uid!()}
+    pub fn color_button_active() -> ColorId {uid!()}
+    pub fn color_label() -> ColorId {uid!()}
+    pub fn color_count() -> ColorId {uid!()}
+    pub fn color_error() -> ColorId {uid!()}
+    pub fn width() -> FloatId {uid!()}
+
+    pub fn style(cx: &mut Cx, opt: &StyleOptions) {
+        Self::width().set(cx, 420. * opt.scale);
+        Self::layout_bg().set(cx, Layout {
+            walk: Walk::wh(Width::Fill, Height::Compute),
+            align: Align::left_center(),
+            padding: Padding {l: 6., t: 2., r: 6., b: 6.},
+            ..Layout::default()
+        });
+        Self::layout_button().set(cx, Layout {
+            walk: Walk {width: Width::Compute, height: Height::Compute, margin: Margin {l: 4., t: 4., r: 0., b: 0.}},
+            padding: Padding {l: 6., t: 5., r: 6., b: 5.},
+            line_wrap: LineWrap::None,
+            ..Layout::default()
+        });
+        Self::text_style_button().set(cx, Theme::text_style_normal().get(cx));
+        Self::color_bg().set(cx, Theme::color_bg_normal().get(cx));
+        Self::color_button().set(cx, Theme::color_bg_odd().get(cx));
+        Self::color_button_active().set(cx, Theme::color_bg_marked().get(cx));
+        Self::color_label().set(cx, Theme::color_text_selected_focus().get(cx));
+        Self::color_count().set(cx, Theme::color_text_deselected_defocus().get(cx));
+        Self::color_error().set(cx, pick!(#c44).get(cx));
+    }
+
+    pub fn default_keymap(cx: &mut Cx) {
+        let ctx = Some("findbar");
+        let km = &mut cx.keymap;
+        km.register_command("findbar.next", "Find Next", &["return", "f3"], ctx);
+        km.register_command("findbar.prev", "Find Previous", &["shift+return", "shift+f3"], ctx);
+        km.register_command("findbar.replace_all", "Replace All", &["mod+alt+return"], ctx);
+        km.register_command("findbar.toggle_case", "Toggle Match Case", &["alt+c"], ctx);
+        km.register_command("findbar.toggle_word", "Toggle Whole Word", &["alt+w"], ctx);
+        km.register_command("findbar.toggle_regex", "Toggle Regex", &["alt+r"], ctx);
+        km.register_command("findbar.close", "Close Find", &["escape"], ctx);
+    }
+
+    pub fn open(&mut self, cx: &mut Cx, query: &str, show_replace: bool) {
+        self.is_open = true;
+        self.show_replace = show_replace;
+        if query.len() > 0 {
+            self.find_input.set_value(cx, query);
+        }
+        self.find_input.select_all(cx);
+        self.find_input.text_editor.set_key_focus(cx);
+        self._matched_mutation_id = 0;
+        cx.redraw_child_area(Area::All);
+    }
+
+    pub fn close(&mut self, cx: &mut Cx, text_buffer: &mut TextBuffer) {
+        if self.is_open {
+            self.is_open = false;
+            self.matches.truncate(0);
+            self.current = None;
+            text_buffer.markers.search_cursors.truncate(0);
+            cx.redraw_child_area(Area::All);
+        }
+    }
+
+    pub fn has_key_focus(&self, cx: &Cx) -> bool {
+        self.find_input.text_editor.has_key_focus(cx) || self.replace_input.text_editor.has_key_focus(cx)
+    }
+
+    fn compile(&self, query: &str) -> Result<Regex, RegexError> {
+        let pattern = if self.options.regex {query.to_string()} else {Regex::escape(query)};
+        Regex::with_case(&pattern, !self.options.case_sensitive)
+    }
+
+    // reruns the query over the buffer and publishes the matches as search markers
+    pub fn update_matches(&mut self, text_buffer: &mut TextBuffer, selection: (usize, usize)) {
+        self._matched_mutation_id = text_buffer.mutation_id;
+        self.matches.truncate(0);
+        self.error = None;
+        let query = self.find_input.get_value();
+        if query.len() > 0 {
+            match self.compile(&query) {
+                Ok(regex) => {
+                    self._flat_text = text_buffer.get_as_string().chars().collect();
+                    let text = &self._flat_text;
+                    let whole_word = self.options.whole_word;
+                    self.matches = regex.find_all(text).into_iter().filter( | m | {
+                        m.end > m.start && (!whole_word || (m.start == 0 || !is_word_char(text[m.start - 1]))
+                            && (m.end == text.len() || !is_word_char(text[m.end])))
+                    }).collect();
+                },
+                Err(err) => {
+                    self.error = Some(err.message);
+                }
+            }
+        }
+        text_buffer.markers.search_cursors = self.matches.iter().map( | m | TextCursor {
+            head: m.end,
+            tail: m.start,
+            max: 0
+        }).collect();
+        self.set_current(selection);
+    }
+
+    pub fn set_current(&mut self, selection: (usize, usize)) {
+        self.current = self.matches.iter().position( | m | m.start == selection.0 && m.end == selection.1);
+    }
+
+    // the first match at or after the start of the selection, wrapping around
+    pub fn match_from(&self, selection: (usize, usize)) -> Option<usize> {
+        if self.matches.len() == 0 {
+            return None
+        }
+        Some(self.matches.iter().position( | m | m.start >= selection.0).unwrap_or(0))
+    }
+
+    pub fn next_match(&self, selection: (usize, usize)) -> Option<usize> {
+        if self.matches.len() == 0 {
+            return None
+        }
+        Some(self.matches.iter().position( | m | m.start >= selection.1 && (m.start, m.end) != selection).unwrap_or(0))
+    }
+
+    pub fn prev_match(&self, selection: (usize, usize)) -> Option<usize> {
+        if self.matches.len() == 0 {
+            return None
+        }
+        Some(self.matches.iter().rposition( | m | m.start < selection.0).unwrap_or(self.matches.len() - 1))
+    }
+
+    // the text replacing a match, regex mode fills in capture groups
+    pub fn replacement_for(&self, index: usize) -> String {
+        let replace = self.replace_input.get_value();
+        if self.options.regex {
+            self.matches[index].expand(&self._flat_text, &replace)
+        }
+        else {
+            replace
+        }
+    }
+
+    fn toggle(&mut self, button: FindBarButton) -> FindBarEvent {
+        match button {
+            FindBarButton::Case => self.options.case_sensitive = !self.options.case_sensitive,
+            FindBarButton::Word => self.options.whole_word = !self.options.whole_word,
+            FindBarButton::Regex => self.options.regex = !self.options.regex,
+            _ => ()
+        }
+        self._matched_mutation_id = 0;
+        FindBarEvent::Changed
+    }
+
+    fn handle_command(&mut self, command: &str) -> FindBarEvent {
+        match command {
+            "findbar.next" => FindBarEvent::Next,
+            "findbar.prev" => FindBarEvent::Prev,
+            "findbar.replace_all" => if self.show_replace {FindBarEvent::ReplaceAll} else {FindBarEvent::None},
+            "findbar.toggle_case" => self.toggle(FindBarButton::Case),
+            "findbar.toggle_word" => self.toggle(FindBarButton::Word),
+            "findbar.toggle_regex" => self.toggle(FindBarButton::Regex),
+            "findbar.close" => FindBarEvent::Close,
+            _ => FindBarEvent::None
+        }
+    }
+
+    pub fn handle_find_bar(&mut self, cx: &mut Cx, event: &mut Event) -> FindBarEvent {
+        if !self.is_open {
+            return FindBarEvent::None
+        }
+        if let Event::KeyDown(ke) = event {
+            if self.has_key_focus(cx) {
+                if let KeyMapMatch::Command(command) = cx.keymap.match_key_down(ke, "findbar") {
+                    // return in the replace field replaces instead of skipping ahead
+                    if command == "findbar.next" && self.replace_input.text_editor.has_key_focus(cx) {
+                        return FindBarEvent::Replace
+                    }
+                    let find_event = self.handle_command(&command);
+                    if find_event != FindBarEvent::None {
+                        self.view.redraw_view_area(cx);
+                        return find_event
+                    }
+                }
+            }
+        }
+
+        if let TextEditorEvent::Change = self.find_input.handle_text_input(cx, event) {
+            return FindBarEvent::Changed
+        }
+        if self.show_replace {
+            self.replace_input.handle_text_input(cx, event);
+        }
+
+        for (button, area) in self._button_areas.clone() {
+            match event.hits(cx, area, HitOpt::default()) {
+                Event::FingerDown(_) => {
+                    self.view.redraw_view_area(cx);
+                    return match button {
+                        FindBarButton::Case | FindBarButton::Word | FindBarButton::Regex => self.toggle(button),
+                        FindBarButton::Prev => FindBarEvent::Prev,
+                        FindBarButton::Next => FindBarEvent::Next,
+                        FindBarButton::Replace => FindBarEvent::Replace,
+                        FindBarButton::ReplaceAll => FindBarEvent::ReplaceAll,
+                        FindBarButton::Close => FindBarEvent::Close
+                    }
+                },
+                Event::FingerHover(_) => {
+                    cx.set_hover_mouse_cursor(MouseCursor::Hand);
+                },
+                _ => ()
+            }
+        }
+
+        // the bar sits on top of the editor, keep clicks from falling through
+        match event.hits(cx, self._bg_area, HitOpt::default()) {
+            Event::FingerDown(_) => {
+                self.find_input.text_editor.set_key_focus(cx);
+            },
+            Event::FingerHover(_) => {
+                cx.set_hover_mouse_cursor(MouseCursor::Default);
+            },
+            _ => ()
+        }
+        FindBarEvent::None
+    }
+
+    fn draw_button(&mut self, cx: &mut Cx, button: FindBarButton, label: &str, active: bool) {
+        self.button_bg.color = if active {Self::color_button_active().get(cx)} else {Self::color_button().get(cx)};
+        let inst = self.button_bg.begin_quad(cx, Self::layout_button().get(cx));
+        self.text.color = Self::color_label().get(cx);
+        self.text.draw_text(cx, label);
+        let area = self.button_bg.end_quad(cx, &inst);
+        self._button_areas.push((button, area));
+    }
+
+    pub fn draw_find_bar(&mut self, cx: &mut Cx, rect: Rect) {
+        if !self.is_open {
+            return
+        }
+        let width = Self::width().get(cx).min(rect.w);
+        if self.view.begin_view(cx, Layout {
+            abs_origin: Some(Vec2 {x: rect.x + rect.w - width - 16., y: rect.y}),
+            walk: Walk::wh(Width::Fix(width), Height::Compute),
+            ..Layout::default()
+        }).is_err() {
+            return
+        };
+
+        self.bg.color = Self::color_bg().get(cx);
+        let bg_inst = self.bg.begin_quad(cx, Self::layout_bg().get(cx));
+        self._button_areas.truncate(0);
+        self.text.text_style = Self::text_style_button().get(cx);
+
+        self.find_input.draw_text_input(cx);
+        let options = self.options;
+        self.draw_button(cx, FindBarButton::Case, "Aa", options.case_sensitive);
+        self.draw_button(cx, FindBarButton::Word, "W", options.whole_word);
+        self.draw_button(cx, FindBarButton::Regex, ".*", options.regex);
+
+        cx.move_turtle(8., 0.);
+        let status = if let Some(error) = &self.error {
+            self.text.color = Self::color_error().get(cx);
+            error.clone()
+        }
+        else {
+            self.text.color = Self::color_count().get(cx);
+            match self.current {
+                _ if self.matches.len() == 0 => "No results".to_string(),
+                Some(current) => format!("{} of {}", current + 1, self.matches.len()),
+                None => format!("{} results", self.matches.len())
+            }
+        };
+        self.text.draw_text(cx, &status);
+
+        self.draw_button(cx, FindBarButton::Prev, "<", false);
+        self.draw_button(cx, FindBarButton::Next, ">", false);
+        self.draw_button(cx, FindBarButton::Close, "x", false);
+
+        if self.show_replace {
+            cx.turtle_new_line();
+            self.replace_input.draw_text_input(cx);
+            self.draw_button(cx, FindBarButton::Replace, "Replace", false);
+            self.draw_button(cx, FindBarButton::ReplaceAll, "All", false);
+        }
+
+        self._bg_area = self.bg.end_quad(cx, &bg_inst);
+        self.view.end_view(cx);
+    }
+}
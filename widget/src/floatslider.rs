@@ -0,0 +1,219 @@
+use makepad_render::*;
+use crate::widgetstyle::*;
+
+pub enum FloatSliderEvent {
+    Change {scaled_value: f32},
+    DoneChanging,
+    None
+}
+
+#[derive(Clone)]
+pub struct FloatSlider {
+    pub scaled_value: f32,
+    pub norm_value: f32,
+    pub animator: Animator,
+    pub min: f32,
+    pub max: f32,
+    pub step: f32,
+    pub size: f32,
+    pub slider: Quad,
+    pub dragging: bool
+}
+
+impl FloatSlider {
+    
+    pub fn slider() -> ShaderId {uid!()}
+    
+    pub fn new(cx: &mut Cx) -> Self {
+        Self {
+            norm_value: 0.0,
+            scaled_value: 0.0,
+            animator: Animator::default(),
+            min: 0.0,
+            max: 1.0,
+            step: 0.0,
+            size: 0.0,
+            slider: Quad::new(cx),
+            dragging: false
+        }
+    }
+    
+    pub fn handle_finger(&mut self, cx: &mut Cx, rel: Vec2) -> FloatSliderEvent {
+        let norm_value = (rel.x / self.size).max(0.0).min(1.0);
+        let scaled_value = norm_value * (self.max - self.min) + self.min;
+        self.set_scaled_value(cx, scaled_value)
+    }
+    
+    pub fn set_scaled_value(&mut self, cx: &mut Cx, scaled_value: f32) -> FloatSliderEvent {
+        let mut scaled_value = scaled_value.max(self.min).min(self.max);
+        if self.step > 0.0 {
+            scaled_value = (scaled_value / self.step).round() * self.step;
+        }
+        let norm_value = if self.max > self.min {(scaled_value - self.min) / (self.max - self.min)} else {0.0};
+        let mut changed = false;
+        if scaled_value != self.scaled_value {
+            self.scaled_value = scaled_value;
+            self.norm_value = norm_value;
+            self.animator.area.write_float(cx, Self::norm_value(), self.norm_value);
+            changed = true;
+        }
+        if changed {
+            FloatSliderEvent::Change {scaled_value}
+        }
+        else {
+            FloatSliderEvent::None
+        }
+    }
+    
+    pub fn handle_float_slider(&mut self, cx: &mut Cx, event: &mut Event) -> FloatSliderEvent {
+        match event.hits(cx, self.animator.area, HitOpt::default()) {
+            Event::Animate(ae) => {
+                self.animator.calc_area(cx, self.animator.area, ae.time);
+            },
+            Event::AnimEnded(_) => self.animator.end(),
+            Event::FingerHover(fe) => {
+                cx.set_hover_mouse_cursor(MouseCursor::Arrow);
+                match fe.hover_state {
+                    HoverState::In => {
+                        self.animator.play_anim(cx, Self::anim_hover().get(cx));
+                    },
+                    HoverState::Out => {
+                        self.animator.play_anim(cx, Self::anim_default().get(cx));
+                    },
+                    _ => ()
+                }
+            },
+            Event::FingerDown(fe) => {
+                self.animator.play_anim(cx, Self::anim_down().get(cx));
+                cx.set_down_mouse_cursor(MouseCursor::Arrow);
+                self.dragging = true;
+                cx.set_key_focus(self.animator.area);
+                return self.handle_finger(cx, fe.rel);
+                // lets check where we clicked!
+            },
+            Event::FingerUp(fe) => {
+                if fe.is_over {
+                    if !fe.is_touch {
+                        self.animator.play_anim(cx, Self::anim_hover().get(cx));
+                    }
+                    else {
+                        self.animator.play_anim(cx, Self::anim_default().get(cx));
+                    }
+                }
+                else {
+                    self.animator.play_anim(cx, Self::anim_default().get(cx));
+                }
+                self.dragging = false;
+                return FloatSliderEvent::DoneChanging;
+            }
+            Event::FingerMove(fe) => {
+                return self.handle_finger(cx, fe.rel)
+                
+            },
+            Event::KeyDown(ke) => {
+                // the step, or a hundredth of the range when it slides freely. shift moves 10 times as far
+                let step = if self.step > 0.0 {self.step} else {(self.max - self.min) / 100.0};
+                let step = if ke.modifiers.shift {step * 10.0} else {step};
+                let scaled_value = match ke.key_code {
+                    KeyCode::ArrowLeft | KeyCode::ArrowDown => self.scaled_value - step,
+                    KeyCode::ArrowRight | KeyCode::ArrowUp => self.scaled_value + step,
+                    KeyCode::PageDown => self.scaled_value - step * 10.0,
+                    KeyCode::PageUp => self.scaled_value + step * 10.0,
+                    KeyCode::Home => self.min,
+                    KeyCode::End => self.max,
+                    _ => return FloatSliderEvent::None
+                };
+                return self.set_scaled_value(cx, scaled_value)
+            },
+            Event::KeyUp(ke) => match ke.key_code {
+                KeyCode::ArrowLeft | KeyCode::ArrowDown | KeyCode::ArrowRight | KeyCode::ArrowUp |
+                KeyCode::PageDown | KeyCode::PageUp | KeyCode::Home | KeyCode::End => {
+                    return FloatSliderEvent::DoneChanging;
+                },
+                _ => ()
+            },
+            _ => ()
+        }
+        FloatSliderEvent::None
+    }
+    
+    pub fn draw_float_slider(&mut self, cx: &mut Cx, scaled_value: f32, min: f32, max: f32, step: f32) {
+        self.animator.init(cx, | cx | Self::anim_default().get(cx));
+        if !self.dragging {
+            self.scaled_value = scaled_value;
+            self.min = min;
+            self.max = max;
+            self.step = step;
+            self.norm_value = (scaled_value - min) / (max - min);
+        }
+        
+        self.slider.shader = Self::slider().get(cx);
+        // i wanna draw a wheel with 'width' set but height a fixed height.
+        
+        let pad = 10.;
+        
+        self.size = cx.get_turtle_rect().w - 2. * pad;
+        let k = self.slider.draw_quad(cx, Walk {
+            margin: Margin::left(pad),
+            width: Width::FillPad(pad),
+            height: Height::Fix(20.0)
+        });
+        // lets put a hsv int here
+        k.push_float(cx, self.norm_value);
+        k.push_last_float(cx, &self.animator, Self::hover());
+        k.push_last_float(cx, &self.animator, Self::down());
+        self.animator.set_area(cx, k.into());
+    }
+    
+    pub fn norm_value() -> FloatId {uid!()}
+    pub fn hover() -> FloatId {uid!()}
+    pub fn down() -> FloatId {uid!()}
+    
+    pub fn anim_default() -> AnimId {uid!()}
+    pub fn anim_hover() -> AnimId {uid!()}
+    pub fn anim_down() -> AnimId {uid!()}
+    
+    pub fn style(cx: &mut Cx, _opt: &StyleOptions) {
+        Self::anim_default().set(cx, Anim::new(Play::Cut {duration: 0.2}, vec![
+            Track::float(Self::hover(), Ease::Lin, vec![(1.0, 0.)]),
+            Track::float(Self::down(), Ease::Lin, vec![(1.0, 0.)]),
+        ]));
+        
+        Self::anim_hover().set(cx, Anim::new(Play::Cut {duration: 0.2}, vec![
+            Track::float(Self::down(), Ease::Lin, vec![(1.0, 0.)]),
+            Track::float(Self::hover(), Ease::Lin, vec![(0.0, 1.0), (1.0, 1.0)]),
+        ]));
+        
+        Self::anim_down().set(cx, Anim::new(Play::Cut {duration: 0.2}, vec![
+            Track::float(Self::down(), Ease::OutExp, vec![(0.0, 0.0), (1.0, 3.1415 * 0.5)]),
+            Track::float(Self::hover(), Ease::Lin, vec![(1.0, 1.0)]),
+        ]));
+        
+        Self::slider().set(cx, Quad::def_quad_shader().compose(shader!{"
+            
+            instance norm_value: Self::norm_value();
+            instance hover: Self::hover();
+            instance down: Self::down();
+            
+            fn pixel() -> vec4 {
+                let df = Df::viewport(pos * vec2(w, h));
+                
+                let cy = h * 0.5;
+                let height = 5.;
+                df.box(4., cy - 0.5 * height, w - 10., height, 1.);
+                
+                df.fill(pick!(#4));
+                
+                let bheight = 15.; 
+                let bwidth = 10.;
+                
+                df.box((w - bwidth) * norm_value, cy - 0.5 * bheight, bwidth, bheight, 2.);
+                ////
+                let color = mix(mix(pick!(#5), pick!(#B),hover),pick!(#F),down);
+                df.fill(color);
+                
+                return df.result;
+            }
+        "})) 
+    }
+}
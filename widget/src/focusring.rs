@@ -0,0 +1,48 @@
+use makepad_render::*;
+use crate::widgetstyle::*;
+
+// The ring around a widget with the key focus, for widgets that draw into a view of their own
+// instead of a quad that strokes it. Drawn last inside the view, so it stays put when it scrolls.
+
+#[derive(Clone)]
+pub struct FocusRing {
+    pub bg: Quad,
+    pub z: f32,
+}
+
+impl FocusRing {
+    pub fn new(cx: &mut Cx) -> Self {
+        Self {
+            bg: Quad::new(cx),
+            z: 10.,
+        }
+    }
+
+    pub fn shader_bg() -> ShaderId {uid!()}
+
+    pub fn style(cx: &mut Cx, _opt: &StyleOptions) {
+        Self::shader_bg().set(cx, Quad::def_quad_shader().compose(shader!{"
+            fn pixel() -> vec4 {
+                let df = Df::viewport(pos * vec2(w, h));
+                df.box(1., 1., w - 2., h - 2., 2.5);
+                return df.stroke(color, 1.5);
+            }
+        "}));
+    }
+
+    pub fn draw_focus_ring(&mut self, cx: &mut Cx, view_area: Area) {
+        if !cx.has_key_focus(view_area) {
+            return
+        }
+        self.bg.shader = Self::shader_bg().get(cx);
+        self.bg.color = Theme::color_focus_ring().get(cx);
+        self.bg.z = self.z;
+        let inst = self.bg.draw_quad_rel(cx, Rect {
+            x: 0.,
+            y: 0.,
+            w: cx.get_width_total(),
+            h: cx.get_height_total()
+        });
+        inst.set_do_scroll(cx, false, false);
+    }
+}
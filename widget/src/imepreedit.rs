@@ -0,0 +1,85 @@
+use makepad_render::*;
+
+// What an input method is composing in a TextEditor, before it becomes text in the buffer.
+// It is drawn over the text at the cursor, underlined, with a caret where the input method edits.
+
+#[derive(Clone)]
+pub struct ImePreedit {
+    pub view: View,
+    pub bg: Quad,
+    pub underline: Quad,
+    pub text: Text,
+    pub preedit: String,
+    // in chars
+    pub cursor: usize,
+}
+
+impl ImePreedit {
+    pub fn new(cx: &mut Cx) -> Self {
+        Self {
+            view: View::proto_overlay(cx),
+            bg: Quad::new(cx),
+            underline: Quad::new(cx),
+            text: Text {
+                wrapping: Wrapping::None,
+                ..Text::new(cx)
+            },
+            preedit: String::new(),
+            cursor: 0,
+        }
+    }
+
+    pub fn is_composing(&self) -> bool {
+        self.preedit.len() > 0
+    }
+
+    // the committed text comes in as a TextInput, so a commit just clears
+    pub fn set_composition(&mut self, tc: &TextCompositionEvent) {
+        match tc.state {
+            TextCompositionState::Update => {
+                self.preedit = tc.text.clone();
+                self.cursor = tc.cursor.min(tc.text.chars().count());
+            },
+            TextCompositionState::Start | TextCompositionState::Commit => self.clear()
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.preedit.truncate(0);
+        self.cursor = 0;
+    }
+
+    // rect is the text cursor the composition starts at
+    pub fn draw_ime_preedit(&mut self, cx: &mut Cx, rect: Rect, text_style: &TextStyle, color_bg: Color, color_text: Color) {
+        if !self.is_composing() {
+            return
+        }
+        if self.view.begin_view(cx, Layout {
+            abs_origin: Some(Vec2 {x: rect.x, y: rect.y}),
+            walk: Walk::wh(Width::Compute, Height::Fix(rect.h)),
+            ..Layout::default()
+        }).is_err() {
+            return
+        };
+        // covers the text the preedit is drawn over
+        self.bg.color = color_bg;
+        let bg_inst = self.bg.begin_quad(cx, Layout {
+            walk: Walk::wh(Width::Compute, Height::Fix(rect.h)),
+            ..Layout::default()
+        });
+        self.text.text_style = text_style.clone();
+        self.text.color = color_text;
+        let split = self.preedit.char_indices().nth(self.cursor).map_or(self.preedit.len(), | (index, _) | index);
+        let start_x = cx.get_turtle_pos().x;
+        self.text.draw_text(cx, &self.preedit[..split]);
+        let caret_x = cx.get_turtle_pos().x;
+        self.text.draw_text(cx, &self.preedit[split..]);
+        let end_x = cx.get_turtle_pos().x;
+        self.bg.end_quad(cx, &bg_inst);
+
+        self.underline.color = color_text;
+        self.underline.draw_quad_abs(cx, Rect {x: start_x, y: rect.y + rect.h - 2., w: end_x - start_x, h: 1.});
+        self.underline.draw_quad_abs(cx, Rect {x: caret_x, y: rect.y, w: 1., h: rect.h});
+        self.view.end_view(cx);
+    }
+}
@@ -0,0 +1,271 @@
+use std::rc::Rc;
+use std::collections::HashMap;
+use crate::textbuffer::*;
+use crate::tokentype::*;
+use crate::completion::*;
+use crate::snippet::*;
+
+// A Language bundles everything the editor needs to know about a file type:
+// how to tokenize it, how to comment and indent lines and optionally how to format it.
+// Languages are looked up by file extension through a LanguageRegistry.
+
+// how often the tokenizer state is saved, an edit retokenizes from the last checkpoint before it.
+// rows longer than CHECKPOINT_CHARS, like minified files, get checkpoints between their tokens
+const CHECKPOINT_LINES: usize = 64;
+const CHECKPOINT_CHARS: usize = 4096;
+// the chars tokenizers see of the tokens before the current one
+const CHUNK_HISTORY: usize = 64;
+
+pub trait Tokenizer: TokenizerClone {
+    fn next_token<'a>(&mut self, state: &mut TokenizerState<'a>, chunk: &mut Vec<char>, token_chunks: &Vec<TokenChunk>) -> TokenType;
+}
+
+// tokenizers are cloned into checkpoints, deriving Clone is enough to get this
+pub trait TokenizerClone {
+    fn clone_tokenizer(&self) -> Box<dyn Tokenizer>;
+}
+
+impl<T: 'static + Tokenizer + Clone> TokenizerClone for T {
+    fn clone_tokenizer(&self) -> Box<dyn Tokenizer> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn Tokenizer> {
+    fn clone(&self) -> Self {
+        self.clone_tokenizer()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct IndentRules {
+    pub tab_size: usize,
+    // a line ending in one of these indents the next line
+    pub indent_after: Vec<char>,
+    // these close a block, typing a newline right before one outdents it.
+    // they pair up by index with indent_after to split a newline typed between an open and close
+    pub outdent_before: Vec<char>,
+}
+
+impl Default for IndentRules {
+    fn default() -> Self {
+        IndentRules {
+            tab_size: 4,
+            indent_after: vec!['{', '(', '['],
+            outdent_before: vec!['}', ')', ']'],
+        }
+    }
+}
+
+pub trait Language {
+    fn name(&self) -> &str;
+
+    fn new_tokenizer(&self) -> Box<dyn Tokenizer>;
+
+    // the string toggle_comment puts in front of lines, None if the language has no line comments
+    fn line_comment(&self) -> Option<&str> {None}
+
+    fn indent_rules(&self) -> IndentRules {IndentRules::default()}
+
+    fn folding_depth(&self) -> usize {3}
+
+    // prose reads better soft wrapped at the view width than scrolled sideways
+    fn soft_wrap(&self) -> bool {false}
+
+    // returns the formatted lines, or None when the language has no formatter
+    fn auto_format(&self, _text_buffer: &mut TextBuffer) -> Option<Vec<Vec<char>>> {None}
+
+    // whether the host should show its live macro panel next to the editor
+    fn has_live_macros(&self) -> bool {false}
+
+    // completions on top of the words in the buffer, like keywords and builtins
+    fn completion_provider(&self) -> Option<Rc<dyn CompletionProvider>> {None}
+
+    // retokenizes the buffer from the first edited row onward if its token chunks are stale,
+    // on_token sees the buffer after every new token. returns true when it tokenized
+    fn update_token_chunks(&self, text_buffer: &mut TextBuffer, on_token: &mut dyn FnMut(&TextBuffer)) -> bool {
+        if text_buffer.lines.len() == 0 {
+            return false
+        }
+        let (mut state, mut tokenizer, mut pair_stack, mut row, mut offset) = match text_buffer.resume_token_chunks() {
+            TokenResume::Current => return false,
+            TokenResume::Start => (TokenizerState::new(&text_buffer.lines), self.new_tokenizer(), Vec::new(), 0, 0),
+            TokenResume::Checkpoint(cp) => (
+                TokenizerState::new_at(&text_buffer.lines, cp.row, cp.col, cp.offset, cp.prev, cp.cur),
+                cp.tokenizer,
+                cp.pair_stack,
+                cp.row,
+                cp.offset
+            )
+        };
+        let mut checkpoint_row = row;
+        let mut checkpoint_offset = offset;
+        // the chars of the last tokens only, the tokens point into the lines
+        let mut chunk = Vec::new();
+        loop {
+            if chunk.len() > CHECKPOINT_CHARS {
+                chunk.drain(0..chunk.len() - CHUNK_HISTORY);
+            }
+            let start = chunk.len();
+            let token_type = tokenizer.next_token(&mut state, &mut chunk, &text_buffer.token_chunks);
+            let end = offset + chunk.len() - start;
+            if TokenChunk::push_with_pairing(&mut text_buffer.token_chunks, &mut pair_stack, state.next, offset, end, token_type) {
+                text_buffer.was_invalid_pair = true;
+            }
+            offset = end;
+            if token_type == TokenType::Eof {
+                break
+            }
+            row += chunk[start..].iter().filter( | ch | **ch == '\n').count();
+            let at_row_start = token_type == TokenType::Newline && chunk.last() == Some(&'\n');
+            let checkpoint_col = if at_row_start && row >= checkpoint_row + CHECKPOINT_LINES {
+                Some(0)
+            }
+            else if !at_row_start && offset >= checkpoint_offset + CHECKPOINT_CHARS {
+                // only where the tokenizer stopped on the char it peeked at
+                match text_buffer.lines.offset_to_row(offset) {
+                    Some((at_row, row_offset)) if at_row == row && text_buffer.lines.char_at(offset) == state.next => Some(offset - row_offset),
+                    _ => None
+                }
+            }
+            else {
+                None
+            };
+            if let Some(col) = checkpoint_col {
+                checkpoint_row = row;
+                checkpoint_offset = offset;
+                text_buffer.token_checkpoints.push(TokenCheckpoint {
+                    row: row,
+                    col: col,
+                    offset: offset,
+                    token_count: text_buffer.token_chunks.len(),
+                    pair_stack: pair_stack.clone(),
+                    was_invalid_pair: text_buffer.was_invalid_pair,
+                    prev: state.prev,
+                    cur: state.cur,
+                    tokenizer: tokenizer.clone(),
+                });
+            }
+            on_token(text_buffer);
+        }
+        if pair_stack.len() > 0 {
+            text_buffer.was_invalid_pair = true;
+        }
+        true
+    }
+}
+
+#[derive(Clone)]
+pub struct LanguageRegistry {
+    pub languages: Vec<Rc<dyn Language>>,
+    pub extensions: HashMap<String, usize>,
+    pub plain: Rc<dyn Language>,
+    // by language name, loaded separately from a ron file per language
+    pub snippets: HashMap<String, Rc<Vec<Snippet>>>,
+}
+
+impl Default for LanguageRegistry {
+    fn default() -> Self {
+        LanguageRegistry {
+            languages: Vec::new(),
+            extensions: HashMap::new(),
+            plain: Rc::new(PlainLanguage {}),
+            snippets: HashMap::new(),
+        }
+    }
+}
+
+impl LanguageRegistry {
+    // registering an extension twice makes the last language win
+    pub fn register(&mut self, language: Rc<dyn Language>, extensions: &[&str]) {
+        let index = self.languages.len();
+        self.languages.push(language);
+        for ext in extensions {
+            self.extensions.insert(ext.to_lowercase(), index);
+        }
+    }
+
+    pub fn for_extension(&self, ext: &str) -> Rc<dyn Language> {
+        if let Some(index) = self.extensions.get(&ext.to_lowercase()) {
+            return self.languages[*index].clone()
+        }
+        self.plain.clone()
+    }
+
+    pub fn set_snippets(&mut self, language_name: &str, snippets: Rc<Vec<Snippet>>) {
+        self.snippets.insert(language_name.to_string(), snippets);
+    }
+
+    pub fn snippets_for(&self, language: &dyn Language) -> Rc<Vec<Snippet>> {
+        if let Some(snippets) = self.snippets.get(language.name()) {
+            return snippets.clone()
+        }
+        Rc::new(Vec::new())
+    }
+
+    pub fn for_path(&self, path: &str) -> Rc<dyn Language> {
+        let file_name = if let Some(pos) = path.rfind('/') {&path[pos + 1..]} else {path};
+        if let Some(pos) = file_name.rfind('.') {
+            self.for_extension(&file_name[pos + 1..])
+        }
+        else {
+            self.plain.clone()
+        }
+    }
+}
+
+// the fallback for unknown extensions, splits on whitespace only
+
+pub struct PlainLanguage {}
+
+impl Language for PlainLanguage {
+    fn name(&self) -> &str {"plain"}
+
+    fn new_tokenizer(&self) -> Box<dyn Tokenizer> {
+        Box::new(PlainTokenizer {})
+    }
+}
+
+#[derive(Clone)]
+pub struct PlainTokenizer {
+}
+
+impl Tokenizer for PlainTokenizer {
+    fn next_token<'a>(&mut self, state: &mut TokenizerState<'a>, chunk: &mut Vec<char>, _token_chunks: &Vec<TokenChunk>) -> TokenType {
+        let start = chunk.len();
+        loop {
+            if state.next == '\0' {
+                if (chunk.len() - start)>0 {
+                    return TokenType::Identifier
+                }
+                state.advance();
+                chunk.push(' ');
+                return TokenType::Eof
+            }
+            else if state.next == '\n' {
+                // output current line
+                if (chunk.len() - start)>0 {
+                    return TokenType::Identifier
+                }
+
+                chunk.push(state.next);
+                state.advance();
+                return TokenType::Newline
+            }
+            else if state.next == ' ' {
+                if (chunk.len() - start)>0 {
+                    return TokenType::Identifier
+                }
+                while state.next == ' ' {
+                    chunk.push(state.next);
+                    state.advance();
+                }
+                return TokenType::Whitespace
+            }
+            else {
+                chunk.push(state.next);
+                state.advance();
+            }
+        }
+    }
+}
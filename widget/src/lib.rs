@@ -4,6 +4,20 @@ mod buttonlogic;
 pub use crate::buttonlogic::*;
 mod normalbutton;
 pub use crate::normalbutton::*;
+mod checkbox;
+pub use crate::checkbox::*;
+mod toggle;
+pub use crate::toggle::*;
+mod radiogroup;
+pub use crate::radiogroup::*;
+mod dropdown;
+pub use crate::dropdown::*;
+mod numberstepper;
+pub use crate::numberstepper::*;
+mod floatslider;
+pub use crate::floatslider::*;
+mod colorpicker;
+pub use crate::colorpicker::*;
 mod scrollbar;
 pub use crate::scrollbar::*;
 mod splitter;
@@ -24,8 +38,18 @@ mod desktopbutton;
 pub use crate::desktopbutton::*;
 mod windowmenu;
 pub use crate::windowmenu::*;
+mod contextmenu;
+pub use crate::contextmenu::*;
+mod tooltip;
+pub use crate::tooltip::*;
 mod listlogic;
 pub use crate::listlogic::*;
+mod datatable;
+pub use crate::datatable::*;
+mod treeview;
+pub use crate::treeview::*;
+mod richtext;
+pub use crate::richtext::*;
 
 mod textbuffer;
 pub use crate::textbuffer::*;
@@ -38,9 +62,39 @@ mod textinput;
 pub use crate::textinput::*;
 mod scrollshadow;
 pub use crate::scrollshadow::*;
+mod focusring;
+pub use crate::focusring::*;
+mod commandpalette;
+pub use crate::commandpalette::*;
+mod findbar;
+pub use crate::findbar::*;
+mod completion;
+pub use crate::completion::*;
+mod imepreedit;
+pub use crate::imepreedit::*;
+mod snippet;
+pub use crate::snippet::*;
+mod minimap;
+pub use crate::minimap::*;
+mod undohistory;
+pub use crate::undohistory::*;
 
 mod tokentype;
 pub use crate::tokentype::*;
+mod language;
+pub use crate::language::*;
+mod textlines;
+pub use crate::textlines::*;
+mod regex;
+pub use crate::regex::*;
+mod linediff;
+pub use crate::linediff::*;
+mod undotree;
+pub use crate::undotree::*;
+mod textchange;
+pub use crate::textchange::*;
+mod textcollab;
+pub use crate::textcollab::*;
 
 //mod texteditor;
 //pub use crate::texteditor::*;
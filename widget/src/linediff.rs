@@ -0,0 +1,162 @@
+// Line diff on hashed lines, Myers' O(ND) algorithm. Used to mark the lines
+// that changed against an earlier version of a buffer.
+
+// beyond this many inserted plus removed lines the whole differing span is one hunk
+const MAX_DIFF_EDITS: isize = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LineHunk {
+    pub old_start: usize,
+    pub old_len: usize,
+    pub new_start: usize,
+    pub new_len: usize,
+}
+
+pub fn diff_line_hunks(old: &[u64], new: &[u64]) -> Vec<LineHunk> {
+    // the common head and tail are free, most edits only touch a few lines in between
+    let mut head = 0;
+    while head < old.len() && head < new.len() && old[head] == new[head] {
+        head += 1;
+    }
+    let mut tail = 0;
+    while tail < old.len() - head && tail < new.len() - head && old[old.len() - 1 - tail] == new[new.len() - 1 - tail] {
+        tail += 1;
+    }
+    let old_mid = &old[head..old.len() - tail];
+    let new_mid = &new[head..new.len() - tail];
+    if old_mid.len() == 0 && new_mid.len() == 0 {
+        return Vec::new()
+    }
+    let mut hunks = match myers_hunks(old_mid, new_mid) {
+        Some(hunks) => hunks,
+        None => vec![LineHunk {old_start: 0, old_len: old_mid.len(), new_start: 0, new_len: new_mid.len()}]
+    };
+    for hunk in &mut hunks {
+        hunk.old_start += head;
+        hunk.new_start += head;
+    }
+    hunks
+}
+
+fn myers_hunks(old: &[u64], new: &[u64]) -> Option<Vec<LineHunk>> {
+    let n = old.len() as isize;
+    let m = new.len() as isize;
+    let max = (n + m).min(MAX_DIFF_EDITS);
+    let offset = max + 1;
+    let mut v = vec![0isize; (2 * max + 3) as usize];
+    // the furthest x per diagonal after each number of edits, for walking back
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+    let mut edits = None;
+    'search: for d in 0..=max {
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || k != d && v[(offset + k - 1) as usize] < v[(offset + k + 1) as usize] {
+                v[(offset + k + 1) as usize]
+            }
+            else {
+                v[(offset + k - 1) as usize] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[(offset + k) as usize] = x;
+            if x >= n && y >= m {
+                trace.push(v[(offset - d) as usize..=(offset + d) as usize].to_vec());
+                edits = Some(d);
+                break 'search;
+            }
+        }
+        trace.push(v[(offset - d) as usize..=(offset + d) as usize].to_vec());
+    }
+    let edits = edits?;
+
+    // walk back collecting the edits, then merge adjacent ones into hunks
+    let mut ops = Vec::new();
+    let mut x = n;
+    let mut y = m;
+    for d in (1..=edits).rev() {
+        let prev = &trace[(d - 1) as usize];
+        let at = | k: isize | prev[(k + d - 1) as usize];
+        let k = x - y;
+        let prev_k = if k == -d || k != d && at(k - 1) < at(k + 1) {k + 1} else {k - 1};
+        let prev_x = at(prev_k);
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            x -= 1;
+            y -= 1;
+        }
+        // an insert moved down a diagonal, a remove moved right
+        if prev_k == k + 1 {
+            ops.push((x as usize, prev_y as usize, false));
+        }
+        else {
+            ops.push((prev_x as usize, y as usize, true));
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+    ops.reverse();
+    let mut hunks: Vec<LineHunk> = Vec::new();
+    for (old_pos, new_pos, removed) in ops {
+        if let Some(last) = hunks.last_mut() {
+            if last.old_start + last.old_len == old_pos && last.new_start + last.new_len == new_pos {
+                if removed {last.old_len += 1} else {last.new_len += 1}
+                continue
+            }
+        }
+        hunks.push(LineHunk {
+            old_start: old_pos,
+            old_len: if removed {1} else {0},
+            new_start: new_pos,
+            new_len: if removed {0} else {1},
+        });
+    }
+    Some(hunks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hunk(old_start: usize, old_len: usize, new_start: usize, new_len: usize) -> LineHunk {
+        LineHunk {old_start, old_len, new_start, new_len}
+    }
+
+    #[test]
+    fn same_lines_have_no_hunks() {
+        assert_eq!(diff_line_hunks(&[1, 2, 3], &[1, 2, 3]), vec![]);
+        assert_eq!(diff_line_hunks(&[], &[]), vec![]);
+    }
+
+    #[test]
+    fn pure_insert() {
+        assert_eq!(diff_line_hunks(&[1, 2, 3], &[1, 2, 7, 8, 3]), vec![hunk(2, 0, 2, 2)]);
+        assert_eq!(diff_line_hunks(&[], &[1, 2]), vec![hunk(0, 0, 0, 2)]);
+    }
+
+    #[test]
+    fn pure_delete() {
+        assert_eq!(diff_line_hunks(&[1, 2, 3, 4], &[1, 4]), vec![hunk(1, 2, 1, 0)]);
+        assert_eq!(diff_line_hunks(&[1, 2], &[]), vec![hunk(0, 2, 0, 0)]);
+    }
+
+    #[test]
+    fn modify() {
+        assert_eq!(diff_line_hunks(&[1, 2, 3], &[1, 9, 3]), vec![hunk(1, 1, 1, 1)]);
+        // two separate changes with common lines between them stay two hunks
+        assert_eq!(
+            diff_line_hunks(&[1, 2, 3, 4, 5, 6], &[1, 9, 3, 4, 5, 7, 8]),
+            vec![hunk(1, 1, 1, 1), hunk(5, 1, 5, 2)]
+        );
+    }
+
+    #[test]
+    fn too_many_edits_is_one_hunk() {
+        // every other line differs, so more than MAX_DIFF_EDITS edits are needed
+        let old: Vec<u64> = (0..400).collect();
+        let new: Vec<u64> = (0..400).map( | i | if i % 2 == 0 {i} else {i + 1000}).collect();
+        assert!(myers_hunks(&old[1..], &new[1..]).is_none());
+        assert_eq!(diff_line_hunks(&old, &new), vec![hunk(1, 399, 1, 399)]);
+    }
+}
@@ -125,23 +125,24 @@ impl ListLogic {
         // the maximum scroll position given the amount of log items
         let max_scroll_y = ((self.list_items.len() + 1) as f32 * row_height - view_rect.h).max(0.);
         
+        // the horizontal scroll stays where it is, for rows wider than the view
+        let sp = view.get_scroll_pos(cx);
+        
         // tail the log
         let (scroll_pos, set_scroll_pos) = if tail_list {
-            (Vec2 {x: 0., y: max_scroll_y}, true)
+            (Vec2 {x: sp.x, y: max_scroll_y}, true)
         }
         else {
-            let sp = view.get_scroll_pos(cx);
-            
             // scroll item into view
             if let Some(scroll_item_in_view) = self.scroll_item_in_view {
                 self.scroll_item_in_view = None;
                 let item_y = scroll_item_in_view as f32 * row_height;
                 let dy = (item_y + row_height) - (sp.y + view_rect.h);
                 if item_y < sp.y {
-                    (Vec2 {x: 0., y: item_y}, true)
+                    (Vec2 {x: sp.x, y: item_y}, true)
                 }
                 else if dy > 0. {
-                    (Vec2 {x: 0., y: sp.y + dy}, true)
+                    (Vec2 {x: sp.x, y: sp.y + dy}, true)
                 }
                 else {
                     (sp, false)
@@ -150,7 +151,7 @@ impl ListLogic {
             else {
                 // clamp the scrollbar to our max list size
                 if sp.y > max_scroll_y {
-                    (Vec2 {x: 0., y: max_scroll_y}, false)
+                    (Vec2 {x: sp.x, y: max_scroll_y}, false)
                 }
                 else {
                     (sp, false)
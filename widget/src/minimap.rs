@@ -0,0 +1,212 @@
+use makepad_render::*;
+use crate::textbuffer::*;
+use crate::textcursor::*;
+use crate::texteditor::*;
+use crate::tokentype::*;
+use crate::widgetstyle::*;
+
+// The overview strip on the right of a TextEditor. Every buffer row is a thin line of
+// token colored blocks, long files show the rows around the viewport. Markers for messages,
+// search matches and changed lines are drawn over it, the viewport can be dragged.
+
+#[derive(Clone)]
+pub struct Minimap {
+    pub view: View,
+    pub bg: Quad,
+    pub token: Quad,
+    pub viewport: Quad,
+    pub marker: Quad,
+    pub _bg_area: Area,
+    pub _first_row: usize,
+    pub _viewport_rows: (usize, usize),
+    // rows between the finger and the top of the viewport while dragging
+    pub _drag_rows: f32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum MinimapEvent {
+    None,
+    ScrollToRow(usize)
+}
+
+impl Minimap {
+    pub fn new(cx: &mut Cx) -> Self {
+        Self {
+            view: View::proto_overlay(cx),
+            bg: Quad::new(cx),
+            token: Quad::new(cx),
+            viewport: Quad::new(cx),
+            marker: Quad::new(cx),
+            _bg_area: Area::Empty,
+            _first_row: 0,
+            _viewport_rows: (0, 0),
+            _drag_rows: 0.,
+        }
+    }
+
+    pub fn width() -> FloatId {uid!()}
+    pub fn row_height() -> FloatId {uid!()}
+    pub fn char_width() -> FloatId {uid!()}
+    pub fn color_bg() -> ColorId {uid!()}
+    pub fn color_viewport() -> ColorId {uid!()}
+    pub fn color_search() -> ColorId {uid!()}
+    pub fn color_error() -> ColorId {uid!()}
+    pub fn color_warning() -> ColorId {uid!()}
+    pub fn color_added() -> ColorId {uid!()}
+    pub fn color_modified() -> ColorId {uid!()}
+    pub fn color_removed() -> ColorId {uid!()}
+
+    pub fn style(cx: &mut Cx, opt: &StyleOptions) {
+        Self::width().set(cx, 90. * opt.scale);
+        Self::row_height().set(cx, 2. * opt.scale);
+        Self::char_width().set(cx, 1. * opt.scale);
+        Self::color_bg().set(cx, Theme::color_bg_normal().get(cx));
+        Self::color_viewport().set(cx, pick!(#fff2).get(cx));
+        Self::color_search().set(cx, pick!(#8888).get(cx));
+        Self::color_error().set(cx, pick!(#c00).get(cx));
+        Self::color_warning().set(cx, pick!(#0c0).get(cx));
+        Self::color_added().set(cx, pick!(#587c0c).get(cx));
+        Self::color_modified().set(cx, pick!(#0c7d9d).get(cx));
+        Self::color_removed().set(cx, pick!(#94151b).get(cx));
+    }
+
+    fn row_at(&self, cx: &Cx, rel_y: f32) -> f32 {
+        self._first_row as f32 + rel_y / Self::row_height().get(cx)
+    }
+
+    pub fn handle_minimap(&mut self, cx: &mut Cx, event: &mut Event) -> MinimapEvent {
+        match event.hits(cx, self._bg_area, HitOpt::default()) {
+            Event::FingerDown(fe) => {
+                let row = self.row_at(cx, fe.rel.y);
+                let (top, bottom) = self._viewport_rows;
+                // grabbing the viewport keeps it under the finger, elsewhere it jumps to center there
+                self._drag_rows = if row >= top as f32 && row < bottom as f32 {
+                    row - top as f32
+                }
+                else {
+                    (bottom - top) as f32 * 0.5
+                };
+                return MinimapEvent::ScrollToRow((row - self._drag_rows).max(0.) as usize)
+            },
+            Event::FingerMove(fe) => {
+                let row = self.row_at(cx, fe.rel.y);
+                return MinimapEvent::ScrollToRow((row - self._drag_rows).max(0.) as usize)
+            },
+            Event::FingerHover(_) => {
+                cx.set_hover_mouse_cursor(MouseCursor::Default);
+            },
+            _ => ()
+        }
+        MinimapEvent::None
+    }
+
+    // viewport_rows are the first and the end row visible in the editor
+    pub fn draw_minimap(&mut self, cx: &mut Cx, rect: Rect, text_buffer: &TextBuffer, colors: &CodeEditorColors, search_cursors: &[TextCursor], viewport_rows: (usize, usize)) {
+        let width = Self::width().get(cx).min(rect.w);
+        if self.view.begin_view(cx, Layout {
+            abs_origin: Some(Vec2 {x: rect.x + rect.w - width, y: rect.y}),
+            walk: Walk::wh(Width::Fix(width), Height::Fix(rect.h)),
+            ..Layout::default()
+        }).is_err() {
+            return
+        };
+        let row_height = Self::row_height().get(cx);
+        let char_width = Self::char_width().get(cx);
+        let total_rows = text_buffer.lines.len();
+        let shown_rows = (rect.h / row_height).max(1.) as usize;
+        let visible_rows = viewport_rows.1.saturating_sub(viewport_rows.0);
+
+        // scroll the strip along in proportion when the file is longer than it
+        self._first_row = if total_rows <= shown_rows {
+            0
+        }
+        else {
+            let scrollable = total_rows.saturating_sub(visible_rows).max(1);
+            let fraction = (viewport_rows.0 as f32 / scrollable as f32).min(1.);
+            (fraction * (total_rows - shown_rows) as f32) as usize
+        };
+        let first_row = self._first_row;
+        let end_row = (first_row + shown_rows).min(total_rows);
+        self._viewport_rows = viewport_rows;
+
+        self.bg.color = Self::color_bg().get(cx);
+        self._bg_area = self.bg.draw_quad_rel(cx, Rect {x: 0., y: 0., w: width, h: rect.h}).into();
+
+        // the token blocks, tokenizers put each newline in a token of its own
+        let mut row = 0;
+        let mut col = 0;
+        for token_chunk in &text_buffer.token_chunks {
+            if row >= end_row {
+                break;
+            }
+            match token_chunk.token_type {
+                TokenType::Newline => {
+                    row += 1;
+                    col = 0;
+                    continue
+                },
+                TokenType::Whitespace => (),
+                token_type => if row >= first_row && (col as f32) * char_width < width {
+                    self.token.color = colors.token_color(token_type);
+                    self.token.draw_quad_rel(cx, Rect {
+                        x: (col as f32) * char_width,
+                        y: ((row - first_row) as f32) * row_height,
+                        w: ((token_chunk.len as f32) * char_width).min(width - (col as f32) * char_width),
+                        h: row_height * 0.75
+                    });
+                }
+            }
+            col += token_chunk.len;
+        }
+
+        let row_rect = | row: usize, x: f32, w: f32 | Rect {x: x, y: ((row - first_row) as f32) * row_height, w: w, h: row_height};
+
+        self.marker.color = Self::color_search().get(cx);
+        for cursor in search_cursors {
+            let row = text_buffer.offset_to_text_pos(cursor.head).row;
+            if row >= first_row && row < end_row {
+                self.marker.draw_quad_rel(cx, row_rect(row, 0., width));
+            }
+        }
+
+        let markers = &text_buffer.markers;
+        if markers.mutation_id == text_buffer.mutation_id {
+            for (cursor, body) in markers.message_cursors.iter().zip(markers.message_bodies.iter()) {
+                let row = text_buffer.offset_to_text_pos(cursor.head).row;
+                if row < first_row || row >= end_row {
+                    continue
+                }
+                self.marker.color = match body.level {
+                    TextBufferMessageLevel::Error => Self::color_error().get(cx),
+                    TextBufferMessageLevel::Warning => Self::color_warning().get(cx),
+                    TextBufferMessageLevel::Log => continue
+                };
+                self.marker.draw_quad_rel(cx, row_rect(row, width - 6., 6.));
+            }
+        }
+
+        for (row, change) in &markers.line_changes {
+            if *row < first_row || *row >= end_row {
+                continue
+            }
+            self.marker.color = match change {
+                TextBufferLineChange::Added => Self::color_added().get(cx),
+                TextBufferLineChange::Modified => Self::color_modified().get(cx),
+                TextBufferLineChange::Removed => Self::color_removed().get(cx),
+            };
+            self.marker.draw_quad_rel(cx, row_rect(*row, 0., 2.));
+        }
+
+        let top = viewport_rows.0.max(first_row).min(end_row);
+        let bottom = viewport_rows.1.max(top).min(end_row.max(top + 1));
+        self.viewport.color = Self::color_viewport().get(cx);
+        self.viewport.draw_quad_rel(cx, Rect {
+            x: 0.,
+            y: ((top - first_row) as f32) * row_height,
+            w: width,
+            h: ((bottom - top) as f32) * row_height
+        });
+
+        self.view.end_view(cx);
+    }
+}
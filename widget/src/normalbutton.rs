@@ -32,6 +32,8 @@ impl NormalButton {
     pub fn shader_bg() -> ShaderId {uid!()}
     pub fn hover() -> FloatId {uid!()}
     pub fn down() -> FloatId {uid!()}
+    pub fn focus() -> FloatId {uid!()}
+    pub fn focus_color() -> ColorId {uid!()}
     
     pub fn style(cx: &mut Cx, _opt: &StyleOptions) {
         Self::layout_bg().set(cx, Layout {
@@ -72,6 +74,8 @@ impl NormalButton {
             
             instance hover: Self::hover();
             instance down: Self::down();
+            instance focus: Self::focus();
+            instance focus_color: Self::focus_color();
             const shadow: float = 3.0;
             const border_radius: float = 2.5;
             fn pixel() -> vec4 {
@@ -81,7 +85,8 @@ impl NormalButton {
                 cx.fill(mix(pick!(#0007), pick!(#0), hover));
                 cx.blur = 0.001;
                 cx.box(shadow, shadow, w - shadow*2., h - shadow*2., border_radius);
-                return cx.fill(mix(mix(pick!(#3),pick!(#4),hover), pick!(#2a), down));
+                cx.fill_keep(mix(mix(pick!(#3),pick!(#4),hover), pick!(#2a), down));
+                return cx.stroke(vec4(focus_color.rgb, focus_color.a * focus), 1.5);
             }
         "}));
     }
@@ -90,6 +95,10 @@ impl NormalButton {
         //let mut ret_event = ButtonEvent::None;
         let animator = &mut self.animator;
         let text_area = self._text_area;
+        match event.hits(cx, self._bg_area, HitOpt::default()) {
+            Event::KeyFocus(_) | Event::KeyFocusLost(_) => cx.redraw_child_area(self._bg_area),
+            _ => ()
+        }
         self.button.handle_button_logic(cx, event, self._bg_area, | cx, logic_event, area | match logic_event {
             ButtonLogicEvent::Animate(ae) => {
                 animator.calc_area(cx, area, ae.time);
@@ -113,6 +122,8 @@ impl NormalButton {
         
         bg_inst.push_last_float(cx, &self.animator, Self::hover());
         bg_inst.push_last_float(cx, &self.animator, Self::down());
+        bg_inst.push_float(cx, if cx.has_key_focus(self._bg_area) {1.} else {0.});
+        bg_inst.push_color(cx, Theme::color_focus_ring().get(cx));
         
         self.text.text_style = Self::text_style_label().get(cx);
         self.text.color = self.animator.last_color(cx, Text::color());
@@ -121,5 +132,10 @@ impl NormalButton {
         
         self._bg_area = self.bg.end_quad(cx, &bg_inst);
         self.animator.set_area(cx, self._bg_area);
+        cx.add_focusable(self._bg_area);
+        cx.add_a11y_node(A11yNode {
+            actions: vec![A11yAction::Press, A11yAction::Focus],
+            ..A11yNode::new(A11yRole::Button, label, self._bg_area)
+        });
     }
 }
@@ -0,0 +1,265 @@
+use makepad_render::*;
+use crate::buttonlogic::*;
+use crate::widgetstyle::*;
+
+// A number between a minus and a plus button, each press moves it one step within min and max.
+// The caller owns the value, Change brings the new one back.
+// With the key focus the arrow keys step it, page up and down 10 steps, home and end go to min and max.
+
+#[derive(Clone)]
+pub struct StepperButton {
+    pub animator: Animator,
+    pub _area: Area,
+}
+
+#[derive(Clone)]
+pub struct NumberStepper {
+    pub button: ButtonLogic,
+    pub bg: Quad,
+    pub step_bg: Quad,
+    pub text: Text,
+    pub minus: StepperButton,
+    pub plus: StepperButton,
+    pub value: f32,
+    pub min: f32,
+    pub max: f32,
+    pub step: f32,
+    pub _bg_area: Area,
+    pub _text_area: Area,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum NumberStepperEvent {
+    None,
+    Change {value: f32}
+}
+
+impl NumberStepper {
+    pub fn new(cx: &mut Cx) -> Self {
+        Self {
+            button: ButtonLogic::default(),
+            bg: Quad::new(cx),
+            step_bg: Quad::new(cx),
+            text: Text {
+                wrapping: Wrapping::None,
+                ..Text::new(cx)
+            },
+            minus: StepperButton {animator: Animator::default(), _area: Area::Empty},
+            plus: StepperButton {animator: Animator::default(), _area: Area::Empty},
+            value: 0.,
+            min: 0.,
+            max: 1.,
+            step: 1.,
+            _bg_area: Area::Empty,
+            _text_area: Area::Empty,
+        }
+    }
+
+    pub fn layout_bg() -> LayoutId {uid!()}
+    pub fn layout_value() -> LayoutId {uid!()}
+    pub fn walk_button() -> WalkId {uid!()}
+    pub fn text_style_value() -> TextStyleId {uid!()}
+    pub fn color_bg() -> ColorId {uid!()}
+    pub fn color_value() -> ColorId {uid!()}
+    pub fn anim_default() -> AnimId {uid!()}
+    pub fn anim_over() -> AnimId {uid!()}
+    pub fn anim_down() -> AnimId {uid!()}
+    pub fn shader_bg() -> ShaderId {uid!()}
+    pub fn shader_step() -> ShaderId {uid!()}
+    pub fn hover() -> FloatId {uid!()}
+    pub fn down() -> FloatId {uid!()}
+    pub fn is_plus() -> FloatId {uid!()}
+    pub fn focus() -> FloatId {uid!()}
+    pub fn focus_color() -> ColorId {uid!()}
+
+    pub fn style(cx: &mut Cx, opt: &StyleOptions) {
+        Self::layout_bg().set(cx, Layout {
+            align: Align::left_center(),
+            walk: Walk {
+                width: Width::Compute,
+                height: Height::Fix(24. * opt.scale),
+                margin: Margin::all(1.0),
+            },
+            line_wrap: LineWrap::None,
+            ..Default::default()
+        });
+        Self::layout_value().set(cx, Layout {
+            align: Align::center(),
+            walk: Walk::wh(Width::Fix(56. * opt.scale), Height::Fill),
+            line_wrap: LineWrap::None,
+            ..Default::default()
+        });
+        Self::walk_button().set(cx, Walk::wh(Width::Fix(22. * opt.scale), Height::Fill));
+
+        Self::text_style_value().set(cx, Theme::text_style_normal().get(cx));
+        Self::color_bg().set(cx, pick!(#222).get(cx));
+        Self::color_value().set(cx, Theme::color_text_selected_focus().get(cx));
+
+        Self::anim_default().set(cx, Anim::new(Play::Cut {duration: 0.1}, vec![
+            Track::float(Self::hover(), Ease::Lin, vec![(1., 0.)]),
+            Track::float(Self::down(), Ease::Lin, vec![(1., 0.)]),
+        ]));
+
+        Self::anim_over().set(cx, Anim::new(Play::Cut {duration: 0.1}, vec![
+            Track::float(Self::down(), Ease::Lin, vec![(0., 0.)]),
+            Track::float(Self::hover(), Ease::Lin, vec![(0.0, 1.0), (1.0, 1.0)]),
+        ]));
+
+        Self::anim_down().set(cx, Anim::new(Play::Cut {duration: 0.2}, vec![
+            Track::float(Self::down(), Ease::OutExp, vec![(0.0, 1.0), (1.0, 1.0)]),
+            Track::float(Self::hover(), Ease::Lin, vec![(1.0, 1.0)]),
+        ]));
+
+        Self::shader_bg().set(cx, Quad::def_quad_shader().compose(shader!{"
+
+            instance focus: Self::focus();
+            instance focus_color: Self::focus_color();
+
+            fn pixel() -> vec4 {
+                let df = Df::viewport(pos * vec2(w, h));
+                df.rect(0., 0., w, h);
+                df.fill(color);
+                df.box(1., 1., w - 2., h - 2., 2.5);
+                return df.stroke(vec4(focus_color.rgb, focus_color.a * focus), 1.5);
+            }
+        "}));
+
+        Self::shader_step().set(cx, Quad::def_quad_shader().compose(shader!{"
+
+            instance hover: Self::hover();
+            instance down: Self::down();
+            instance is_plus: Self::is_plus();
+
+            fn pixel() -> vec4 {
+                let df = Df::viewport(pos * vec2(w, h));
+                df.box(1., 1., w - 2., h - 2., 2.5);
+                df.fill(mix(mix(pick!(#3), pick!(#4), hover), pick!(#2a), down));
+                let c = vec2(w, h) * 0.5;
+                df.move_to(c.x - 4., c.y);
+                df.line_to(c.x + 4., c.y);
+                df.move_to(c.x, c.y - 4. * is_plus);
+                df.line_to(c.x, c.y + 4. * is_plus);
+                return df.stroke(mix(pick!(#9), pick!(#f), hover), 1.2);
+            }
+        "}));
+    }
+
+    // the digits after the dot that the step needs
+    fn decimals(&self) -> usize {
+        let mut decimals = 0;
+        let mut step = self.step.abs();
+        while decimals < 6 && step > 0. && (step - step.round()).abs() > 0.0001 {
+            step *= 10.;
+            decimals += 1;
+        }
+        decimals
+    }
+
+    pub fn set_value(&mut self, cx: &mut Cx, value: f32) -> NumberStepperEvent {
+        let mut value = value.max(self.min).min(self.max);
+        if self.step > 0. {
+            value = ((value - self.min) / self.step).round() * self.step + self.min;
+        }
+        if value == self.value {
+            return NumberStepperEvent::None
+        }
+        self.value = value;
+        cx.redraw_child_area(self._bg_area);
+        NumberStepperEvent::Change {value}
+    }
+
+    fn handle_step_button(button_logic: &mut ButtonLogic, button: &mut StepperButton, cx: &mut Cx, event: &mut Event) -> ButtonEvent {
+        let animator = &mut button.animator;
+        button_logic.handle_button_logic(cx, event, button._area, | cx, logic_event, area | match logic_event {
+            ButtonLogicEvent::Animate(ae) => animator.calc_area(cx, area, ae.time),
+            ButtonLogicEvent::AnimEnded(_) => animator.end(),
+            ButtonLogicEvent::Down => animator.play_anim(cx, Self::anim_down().get(cx)),
+            ButtonLogicEvent::Default => animator.play_anim(cx, Self::anim_default().get(cx)),
+            ButtonLogicEvent::Over => animator.play_anim(cx, Self::anim_over().get(cx))
+        })
+    }
+
+    pub fn handle_number_stepper(&mut self, cx: &mut Cx, event: &mut Event) -> NumberStepperEvent {
+        match Self::handle_step_button(&mut self.button, &mut self.minus, cx, event) {
+            ButtonEvent::Down => {
+                cx.set_key_focus(self._bg_area);
+                return self.set_value(cx, self.value - self.step)
+            },
+            _ => ()
+        }
+        match Self::handle_step_button(&mut self.button, &mut self.plus, cx, event) {
+            ButtonEvent::Down => {
+                cx.set_key_focus(self._bg_area);
+                return self.set_value(cx, self.value + self.step)
+            },
+            _ => ()
+        }
+        match event.hits(cx, self._bg_area, HitOpt::default()) {
+            Event::FingerDown(_) => cx.set_key_focus(self._bg_area),
+            Event::KeyFocus(_) | Event::KeyFocusLost(_) => cx.redraw_child_area(self._bg_area),
+            Event::A11yAction(ae) => match ae.action {
+                A11yAction::Increment => return self.set_value(cx, self.value + self.step),
+                A11yAction::Decrement => return self.set_value(cx, self.value - self.step),
+                _ => ()
+            },
+            Event::KeyDown(ke) => {
+                let step = if ke.modifiers.shift {self.step * 10.} else {self.step};
+                let value = match ke.key_code {
+                    KeyCode::ArrowDown | KeyCode::ArrowLeft => self.value - step,
+                    KeyCode::ArrowUp | KeyCode::ArrowRight => self.value + step,
+                    KeyCode::PageDown => self.value - step * 10.,
+                    KeyCode::PageUp => self.value + step * 10.,
+                    KeyCode::Home => self.min,
+                    KeyCode::End => self.max,
+                    _ => return NumberStepperEvent::None
+                };
+                return self.set_value(cx, value)
+            },
+            _ => ()
+        }
+        NumberStepperEvent::None
+    }
+
+    fn draw_step_button(&mut self, cx: &mut Cx, is_plus: bool) {
+        let button = if is_plus {&mut self.plus} else {&mut self.minus};
+        button.animator.init(cx, | cx | Self::anim_default().get(cx));
+        let inst = self.step_bg.draw_quad(cx, Self::walk_button().get(cx));
+        inst.push_last_float(cx, &button.animator, Self::hover());
+        inst.push_last_float(cx, &button.animator, Self::down());
+        inst.push_float(cx, if is_plus {1.} else {0.});
+        button._area = inst.into();
+        button.animator.set_area(cx, button._area);
+    }
+
+    pub fn draw_number_stepper(&mut self, cx: &mut Cx, value: f32, min: f32, max: f32, step: f32) {
+        self.value = value;
+        self.min = min;
+        self.max = max;
+        self.step = step;
+        self.step_bg.shader = Self::shader_step().get(cx);
+
+        self.bg.shader = Self::shader_bg().get(cx);
+        self.bg.color = Self::color_bg().get(cx);
+        let bg_inst = self.bg.begin_quad(cx, Self::layout_bg().get(cx));
+        bg_inst.push_float(cx, if cx.has_key_focus(self._bg_area) {1.} else {0.});
+        bg_inst.push_color(cx, Theme::color_focus_ring().get(cx));
+
+        self.draw_step_button(cx, false);
+
+        cx.begin_turtle(Self::layout_value().get(cx), Area::Empty);
+        self.text.text_style = Self::text_style_value().get(cx);
+        self.text.color = Self::color_value().get(cx);
+        self._text_area = self.text.draw_text(cx, &format!("{:.*}", self.decimals(), value));
+        cx.end_turtle(Area::Empty);
+
+        self.draw_step_button(cx, true);
+
+        self._bg_area = self.bg.end_quad(cx, &bg_inst);
+        cx.add_focusable(self._bg_area);
+        cx.add_a11y_node(A11yNode {
+            value: format!("{:.*}", self.decimals(), value),
+            actions: vec![A11yAction::Increment, A11yAction::Decrement, A11yAction::Focus],
+            ..A11yNode::new(A11yRole::SpinButton, "", self._bg_area)
+        });
+    }
+}
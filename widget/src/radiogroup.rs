@@ -0,0 +1,220 @@
+use makepad_render::*;
+use crate::buttonlogic::*;
+use crate::widgetstyle::*;
+
+// A list of labels of which one is picked, drawn from top to bottom.
+// The caller owns the selected index, Select brings the new one back.
+// The picked item takes the key focus, the arrow keys move the pick to the item next to it.
+
+#[derive(Clone)]
+pub struct RadioItem {
+    pub animator: Animator,
+    pub _bg_area: Area,
+    pub _text_area: Area,
+}
+
+#[derive(Clone)]
+pub struct RadioGroup {
+    pub button: ButtonLogic,
+    pub bg: Quad,
+    pub text: Text,
+    pub items: Vec<RadioItem>,
+    pub selected: usize,
+    pub _item_count: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RadioGroupEvent {
+    None,
+    Select(usize)
+}
+
+impl RadioGroup {
+    pub fn new(cx: &mut Cx) -> Self {
+        Self {
+            button: ButtonLogic::default(),
+            bg: Quad::new(cx),
+            text: Text {
+                wrapping: Wrapping::None,
+                ..Text::new(cx)
+            },
+            items: Vec::new(),
+            selected: 0,
+            _item_count: 0,
+        }
+    }
+
+    pub fn layout_group() -> LayoutId {uid!()}
+    pub fn layout_item() -> LayoutId {uid!()}
+    pub fn text_style_label() -> TextStyleId {uid!()}
+    pub fn anim_default() -> AnimId {uid!()}
+    pub fn anim_over() -> AnimId {uid!()}
+    pub fn anim_down() -> AnimId {uid!()}
+    pub fn shader_bg() -> ShaderId {uid!()}
+    pub fn hover() -> FloatId {uid!()}
+    pub fn down() -> FloatId {uid!()}
+    pub fn selected() -> FloatId {uid!()}
+    pub fn focus() -> FloatId {uid!()}
+    pub fn focus_color() -> ColorId {uid!()}
+
+    pub fn style(cx: &mut Cx, opt: &StyleOptions) {
+        Self::layout_group().set(cx, Layout {
+            direction: Direction::Down,
+            walk: Walk::wh(Width::Compute, Height::Compute),
+            ..Default::default()
+        });
+
+        Self::layout_item().set(cx, Layout {
+            align: Align::left_center(),
+            walk: Walk::wh(Width::Compute, Height::Fix(22. * opt.scale)),
+            padding: Padding {l: 22. * opt.scale, t: 0., r: 4., b: 0.},
+            line_wrap: LineWrap::None,
+            ..Default::default()
+        });
+
+        Self::text_style_label().set(cx, Theme::text_style_normal().get(cx));
+
+        Self::anim_default().set(cx, Anim::new(Play::Cut {duration: 0.1}, vec![
+            Track::float(Self::hover(), Ease::Lin, vec![(1., 0.)]),
+            Track::float(Self::down(), Ease::Lin, vec![(1., 0.)]),
+            Track::color(Text::color(), Ease::Lin, vec![(1., Theme::color_text_deselected_focus().get(cx))]),
+        ]));
+
+        Self::anim_over().set(cx, Anim::new(Play::Cut {duration: 0.1}, vec![
+            Track::float(Self::down(), Ease::Lin, vec![(0., 0.)]),
+            Track::float(Self::hover(), Ease::Lin, vec![(0.0, 1.0), (1.0, 1.0)]),
+            Track::color(Text::color(), Ease::Lin, vec![(0., Theme::color_text_selected_focus().get(cx))]),
+        ]));
+
+        Self::anim_down().set(cx, Anim::new(Play::Cut {duration: 0.2}, vec![
+            Track::float(Self::down(), Ease::OutExp, vec![(0.0, 1.0), (1.0, 1.0)]),
+            Track::float(Self::hover(), Ease::Lin, vec![(1.0, 1.0)]),
+            Track::color(Text::color(), Ease::Lin, vec![(0., Theme::color_text_selected_focus().get(cx))]),
+        ]));
+
+        Self::shader_bg().set(cx, Quad::def_quad_shader().compose(shader!{"
+
+            instance hover: Self::hover();
+            instance down: Self::down();
+            instance selected: Self::selected();
+            instance focus: Self::focus();
+            instance focus_color: Self::focus_color();
+
+            fn pixel() -> vec4 {
+                let df = Df::viewport(pos * vec2(w, h));
+                let r = 6.;
+                let cx = 3. + r;
+                let cy = h * 0.5;
+                df.circle(cx, cy, r);
+                df.fill(mix(mix(pick!(#3), pick!(#4), hover), pick!(#2a), down));
+                df.circle(cx, cy, 3.);
+                let dot = mix(pick!(#b), pick!(#f), hover);
+                df.fill(vec4(dot.rgb, selected));
+                df.box(1., 1., w - 2., h - 2., 2.5);
+                return df.stroke(vec4(focus_color.rgb, focus_color.a * focus), 1.5);
+            }
+        "}));
+    }
+
+    pub fn select(&mut self, cx: &mut Cx, index: usize) -> RadioGroupEvent {
+        if index >= self._item_count || index == self.selected {
+            return RadioGroupEvent::None
+        }
+        if let Some(item) = self.items.get(self.selected) {
+            item._bg_area.write_float(cx, Self::selected(), 0.);
+        }
+        self.selected = index;
+        self.items[index]._bg_area.write_float(cx, Self::selected(), 1.);
+        RadioGroupEvent::Select(index)
+    }
+
+    pub fn handle_radio_group(&mut self, cx: &mut Cx, event: &mut Event) -> RadioGroupEvent {
+        let mut ret = RadioGroupEvent::None;
+        for index in 0..self._item_count {
+            let item = &mut self.items[index];
+            match event.hits(cx, item._bg_area, HitOpt::default()) {
+                Event::KeyFocus(_) | Event::KeyFocusLost(_) => cx.redraw_child_area(item._bg_area),
+                _ => ()
+            }
+            if let Event::KeyDown(ke) = event.hits(cx, item._bg_area, HitOpt::default()) {
+                let next = match ke.key_code {
+                    KeyCode::ArrowUp | KeyCode::ArrowLeft if index > 0 => index - 1,
+                    KeyCode::ArrowDown | KeyCode::ArrowRight => index + 1,
+                    KeyCode::Home => 0,
+                    KeyCode::End => self._item_count.max(1) - 1,
+                    _ => usize::max_value()
+                };
+                if next != usize::max_value() {
+                    let next = next.min(self._item_count - 1);
+                    if next != self.selected {
+                        ret = self.select(cx, next);
+                    }
+                    cx.set_key_focus(self.items[next]._bg_area);
+                    continue;
+                }
+            }
+            let animator = &mut item.animator;
+            let text_area = item._text_area;
+            match self.button.handle_button_logic(cx, event, item._bg_area, | cx, logic_event, area | match logic_event {
+                ButtonLogicEvent::Animate(ae) => {
+                    animator.calc_area(cx, area, ae.time);
+                    animator.calc_area(cx, text_area, ae.time);
+                },
+                ButtonLogicEvent::AnimEnded(_) => animator.end(),
+                ButtonLogicEvent::Down => animator.play_anim(cx, Self::anim_down().get(cx)),
+                ButtonLogicEvent::Default => animator.play_anim(cx, Self::anim_default().get(cx)),
+                ButtonLogicEvent::Over => animator.play_anim(cx, Self::anim_over().get(cx))
+            }) {
+                ButtonEvent::Down => cx.set_key_focus(item._bg_area),
+                ButtonEvent::Clicked => ret = self.select(cx, index),
+                _ => ()
+            }
+        }
+        ret
+    }
+
+    pub fn draw_radio_group(&mut self, cx: &mut Cx, selected: usize, labels: &[&str]) {
+        self.selected = selected;
+        self._item_count = labels.len();
+        self.bg.shader = Self::shader_bg().get(cx);
+        self.text.text_style = Self::text_style_label().get(cx);
+
+        cx.begin_turtle(Self::layout_group().get(cx), Area::Empty);
+        cx.begin_a11y_node(A11yNode::new(A11yRole::Group, "", Area::Empty));
+        for (index, label) in labels.iter().enumerate() {
+            if index >= self.items.len() {
+                self.items.push(RadioItem {
+                    animator: Animator::default(),
+                    _bg_area: Area::Empty,
+                    _text_area: Area::Empty,
+                });
+            }
+            let item = &mut self.items[index];
+            item.animator.init(cx, | cx | Self::anim_default().get(cx));
+
+            let bg_inst = self.bg.begin_quad(cx, Self::layout_item().get(cx));
+            bg_inst.push_last_float(cx, &item.animator, Self::hover());
+            bg_inst.push_last_float(cx, &item.animator, Self::down());
+            bg_inst.push_float(cx, if index == selected {1.} else {0.});
+            bg_inst.push_float(cx, if cx.has_key_focus(item._bg_area) {1.} else {0.});
+            bg_inst.push_color(cx, Theme::color_focus_ring().get(cx));
+
+            self.text.color = item.animator.last_color(cx, Text::color());
+            item._text_area = self.text.draw_text(cx, label);
+
+            item._bg_area = self.bg.end_quad(cx, &bg_inst);
+            item.animator.set_area(cx, item._bg_area);
+            // Tab goes to the group, the arrow keys move inside it
+            if index == selected || selected >= labels.len() && index == 0 {
+                cx.add_focusable(item._bg_area);
+            }
+            cx.add_a11y_node(A11yNode {
+                checked: Some(index == selected),
+                actions: vec![A11yAction::Press],
+                ..A11yNode::new(A11yRole::RadioButton, label, item._bg_area)
+            });
+        }
+        cx.end_a11y_node();
+        cx.end_turtle(Area::Empty);
+    }
+}
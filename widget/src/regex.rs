@@ -0,0 +1,706 @@
+// A small regex engine for find and replace, self contained so it works the same in the wasm build.
+// Patterns compile to a program for a pike vm, which runs all alternatives in lockstep:
+// matching is O(text * pattern) without backtracking, so no pattern can hang the editor.
+// Counted repeats copy their body, a pattern whose program or thread state grows too large is an error.
+//
+// Supported: literals, ., [classes], \d \w \s \D \W \S, \b \B, ^ $ (per line), groups (..) and (?:..),
+// alternation, and the * + ? {n} {n,} {n,m} quantifiers with their lazy ? variants
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RegexError {
+    pub message: String,
+    pub offset: usize
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RegexMatch {
+    pub start: usize,
+    pub end: usize,
+    // capture groups, group 0 is the whole match
+    pub groups: Vec<Option<(usize, usize)>>
+}
+
+#[derive(Clone)]
+pub struct Regex {
+    prog: Vec<Inst>,
+    slots: usize,
+    case_insensitive: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Assertion {
+    LineStart,
+    LineEnd,
+    WordBoundary,
+    NotWordBoundary
+}
+
+#[derive(Clone, Debug)]
+struct CharClass {
+    ranges: Vec<(char, char)>,
+    // \w, the unicode letters and digits is_word_char knows
+    word: bool,
+    negated: bool
+}
+
+#[derive(Clone, Debug)]
+enum Inst {
+    Char(char),
+    Any,
+    Class(CharClass),
+    Assert(Assertion),
+    Split(usize, usize),
+    Jump(usize),
+    Save(usize),
+    Match
+}
+
+#[derive(Clone, Debug)]
+enum Node {
+    Empty,
+    Char(char),
+    Any,
+    Class(CharClass),
+    Assert(Assertion),
+    Group(Box<Node>, Option<usize>),
+    Concat(Vec<Node>),
+    Alt(Vec<Node>),
+    Repeat {node: Box<Node>, min: usize, max: Option<usize>, greedy: bool}
+}
+
+const MAX_REPEAT: usize = 1000;
+const MAX_PROG: usize = 100_000;
+// program length times capture slots, every thread has its own slots
+const MAX_STATE: usize = 2_000_000;
+const MAX_NESTING: usize = 250;
+
+pub fn is_word_char(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_'
+}
+
+impl CharClass {
+    fn digit(negated: bool) -> Self {
+        Self {ranges: vec![('0', '9')], word: false, negated}
+    }
+
+    fn word(negated: bool) -> Self {
+        Self {ranges: Vec::new(), word: true, negated}
+    }
+
+    fn space(negated: bool) -> Self {
+        Self {ranges: vec![(' ', ' '), ('\t', '\r')], word: false, negated}
+    }
+
+    fn contains(&self, ch: char, case_insensitive: bool) -> bool {
+        let in_ranges = | ch: char | self.ranges.iter().any( | (lo, hi) | ch >= *lo && ch <= *hi);
+        let mut found = self.word && is_word_char(ch) || in_ranges(ch);
+        if !found && case_insensitive {
+            found = ch.to_lowercase().any( | ch | in_ranges(ch)) || ch.to_uppercase().any( | ch | in_ranges(ch));
+        }
+        found != self.negated
+    }
+}
+
+struct Parser<'a> {
+    chars: &'a [char],
+    pos: usize,
+    groups: usize,
+    depth: usize
+}
+
+impl<'a> Parser<'a> {
+    fn error<T>(&self, message: &str) -> Result<T, RegexError> {
+        Err(RegexError {message: message.to_string(), offset: self.pos})
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn eat(&mut self, ch: char) -> bool {
+        if self.peek() == Some(ch) {
+            self.pos += 1;
+            return true
+        }
+        false
+    }
+
+    fn parse_alt(&mut self) -> Result<Node, RegexError> {
+        let mut alts = vec![self.parse_concat() ?];
+        while self.eat('|') {
+            alts.push(self.parse_concat() ?);
+        }
+        if alts.len() == 1 {
+            return Ok(alts.pop().unwrap())
+        }
+        Ok(Node::Alt(alts))
+    }
+
+    fn parse_concat(&mut self) -> Result<Node, RegexError> {
+        let mut nodes = Vec::new();
+        while let Some(ch) = self.peek() {
+            if ch == '|' || ch == ')' {
+                break;
+            }
+            let atom = self.parse_atom() ?;
+            nodes.push(self.parse_repeat(atom) ?);
+        }
+        match nodes.len() {
+            0 => Ok(Node::Empty),
+            1 => Ok(nodes.pop().unwrap()),
+            _ => Ok(Node::Concat(nodes))
+        }
+    }
+
+    fn parse_number(&mut self) -> Option<usize> {
+        let start = self.pos;
+        while let Some(ch) = self.peek() {
+            if !ch.is_ascii_digit() {
+                break;
+            }
+            self.pos += 1;
+        }
+        if start == self.pos {
+            return None
+        }
+        self.chars[start..self.pos].iter().collect::<String>().parse().ok()
+    }
+
+    fn parse_repeat(&mut self, atom: Node) -> Result<Node, RegexError> {
+        let mut node = atom;
+        loop {
+            let (min, max) = match self.peek() {
+                Some('*') => {self.pos += 1; (0, None)},
+                Some('+') => {self.pos += 1; (1, None)},
+                Some('?') => {self.pos += 1; (0, Some(1))},
+                Some('{') => {
+                    let start = self.pos;
+                    self.pos += 1;
+                    if let Some(min) = self.parse_number() {
+                        let max = if self.eat(',') {self.parse_number()} else {Some(min)};
+                        if !self.eat('}') {
+                            return self.error("unclosed repetition")
+                        }
+                        if max.map_or(false, | max | max < min) || min > MAX_REPEAT || max.map_or(false, | max | max > MAX_REPEAT) {
+                            return self.error("invalid repetition count")
+                        }
+                        (min, max)
+                    }
+                    else { // not a repetition, a literal brace
+                        self.pos = start;
+                        return Ok(node)
+                    }
+                },
+                _ => return Ok(node)
+            };
+            if let Node::Assert(_) | Node::Empty = node {
+                return self.error("nothing to repeat")
+            }
+            let greedy = !self.eat('?');
+            node = Node::Repeat {node: Box::new(node), min, max, greedy};
+        }
+    }
+
+    fn parse_escape(&mut self) -> Result<Node, RegexError> {
+        let ch = if let Some(ch) = self.peek() {ch} else {return self.error("trailing backslash")};
+        self.pos += 1;
+        Ok(match ch {
+            'd' => Node::Class(CharClass::digit(false)),
+            'D' => Node::Class(CharClass::digit(true)),
+            'w' => Node::Class(CharClass::word(false)),
+            'W' => Node::Class(CharClass::word(true)),
+            's' => Node::Class(CharClass::space(false)),
+            'S' => Node::Class(CharClass::space(true)),
+            'b' => Node::Assert(Assertion::WordBoundary),
+            'B' => Node::Assert(Assertion::NotWordBoundary),
+            'n' => Node::Char('\n'),
+            't' => Node::Char('\t'),
+            'r' => Node::Char('\r'),
+            _ if ch.is_alphanumeric() => {
+                self.pos -= 1;
+                return self.error("unknown escape")
+            },
+            _ => Node::Char(ch)
+        })
+    }
+
+    fn parse_class_char(&mut self) -> Result<char, RegexError> {
+        match self.peek() {
+            None => self.error("unclosed character class"),
+            Some('\\') => {
+                self.pos += 1;
+                match self.peek() {
+                    Some('n') => {self.pos += 1; Ok('\n')},
+                    Some('t') => {self.pos += 1; Ok('\t')},
+                    Some('r') => {self.pos += 1; Ok('\r')},
+                    Some(ch) if !ch.is_alphanumeric() => {self.pos += 1; Ok(ch)},
+                    _ => self.error("unknown escape")
+                }
+            },
+            Some(ch) => {
+                self.pos += 1;
+                Ok(ch)
+            }
+        }
+    }
+
+    fn parse_class(&mut self) -> Result<Node, RegexError> {
+        let negated = self.eat('^');
+        let mut ranges = Vec::new();
+        let mut word = false;
+        let mut first = true;
+        loop {
+            match self.peek() {
+                None => return self.error("unclosed character class"),
+                Some(']') if !first => {
+                    self.pos += 1;
+                    break;
+                },
+                Some('\\') => {
+                    let shorthand = match self.chars.get(self.pos + 1) {
+                        Some('d') => Some(CharClass::digit(false)),
+                        Some('w') => Some(CharClass::word(false)),
+                        Some('s') => Some(CharClass::space(false)),
+                        _ => None
+                    };
+                    if let Some(class) = shorthand {
+                        self.pos += 2;
+                        ranges.extend(class.ranges);
+                        word |= class.word;
+                        first = false;
+                        continue;
+                    }
+                },
+                _ => ()
+            }
+            first = false;
+            let lo = self.parse_class_char() ?;
+            if self.peek() == Some('-') && self.chars.get(self.pos + 1).map_or(false, | ch | *ch != ']') {
+                self.pos += 1;
+                let hi = self.parse_class_char() ?;
+                if hi < lo {
+                    return self.error("invalid class range")
+                }
+                ranges.push((lo, hi));
+            }
+            else {
+                ranges.push((lo, lo));
+            }
+        }
+        Ok(Node::Class(CharClass {ranges, word, negated}))
+    }
+
+    fn parse_atom(&mut self) -> Result<Node, RegexError> {
+        let ch = self.peek().unwrap();
+        self.pos += 1;
+        Ok(match ch {
+            '.' => Node::Any,
+            '^' => Node::Assert(Assertion::LineStart),
+            '$' => Node::Assert(Assertion::LineEnd),
+            '\\' => return self.parse_escape(),
+            '[' => return self.parse_class(),
+            '(' => {
+                if self.depth >= MAX_NESTING {
+                    self.pos -= 1;
+                    return self.error("groups nested too deep")
+                }
+                let index = if self.eat('?') {
+                    if !self.eat(':') {
+                        return self.error("unsupported group flag")
+                    }
+                    None
+                }
+                else {
+                    self.groups += 1;
+                    Some(self.groups)
+                };
+                self.depth += 1;
+                let inner = self.parse_alt() ?;
+                self.depth -= 1;
+                if !self.eat(')') {
+                    return self.error("unclosed group")
+                }
+                Node::Group(Box::new(inner), index)
+            },
+            '*' | '+' | '?' => {
+                self.pos -= 1;
+                return self.error("nothing to repeat")
+            },
+            _ => Node::Char(ch)
+        })
+    }
+}
+
+struct Compiler {
+    prog: Vec<Inst>
+}
+
+impl Compiler {
+    fn emit(&mut self, inst: Inst) -> Result<usize, RegexError> {
+        if self.prog.len() >= MAX_PROG {
+            return Err(RegexError {message: "pattern too large".to_string(), offset: 0})
+        }
+        self.prog.push(inst);
+        Ok(self.prog.len() - 1)
+    }
+
+    fn patch_split(&mut self, at: usize, body: usize, exit: usize, greedy: bool) {
+        self.prog[at] = if greedy {Inst::Split(body, exit)} else {Inst::Split(exit, body)};
+    }
+
+    // nodes that compile to no instructions at all
+    fn is_empty(node: &Node) -> bool {
+        match node {
+            Node::Empty => true,
+            Node::Group(inner, None) => Self::is_empty(inner),
+            Node::Concat(nodes) => nodes.iter().all( | node | Self::is_empty(node)),
+            Node::Repeat {node, ..} => Self::is_empty(node),
+            _ => false
+        }
+    }
+
+    fn compile(&mut self, node: &Node) -> Result<(), RegexError> {
+        match node {
+            Node::Empty => (),
+            Node::Char(ch) => {self.emit(Inst::Char(*ch)) ?;},
+            Node::Any => {self.emit(Inst::Any) ?;},
+            Node::Class(class) => {self.emit(Inst::Class(class.clone())) ?;},
+            Node::Assert(assertion) => {self.emit(Inst::Assert(*assertion)) ?;},
+            Node::Group(inner, index) => {
+                if let Some(index) = index {
+                    self.emit(Inst::Save(index * 2)) ?;
+                    self.compile(inner) ?;
+                    self.emit(Inst::Save(index * 2 + 1)) ?;
+                }
+                else {
+                    self.compile(inner) ?;
+                }
+            },
+            Node::Concat(nodes) => for node in nodes {
+                self.compile(node) ?;
+            },
+            Node::Alt(alts) => {
+                let mut jumps = Vec::new();
+                for (i, alt) in alts.iter().enumerate() {
+                    if i + 1 < alts.len() {
+                        let split = self.emit(Inst::Split(0, 0)) ?;
+                        self.compile(alt) ?;
+                        jumps.push(self.emit(Inst::Jump(0)) ?);
+                        let next = self.prog.len();
+                        self.prog[split] = Inst::Split(split + 1, next);
+                    }
+                    else {
+                        self.compile(alt) ?;
+                    }
+                }
+                let end = self.prog.len();
+                for jump in jumps {
+                    self.prog[jump] = Inst::Jump(end);
+                }
+            },
+            Node::Repeat {node, min, max, greedy} => {
+                if Self::is_empty(node) {
+                    return Ok(())
+                }
+                // x+ loops back on its last copy, x* is compiled as (x+)?
+                // so a body that matches empty still gets its one iteration
+                let copies = if max.is_none() && *min > 0 {min - 1} else {*min};
+                for _ in 0..copies {
+                    self.compile(node) ?;
+                }
+                match max {
+                    None => {
+                        let split = if *min == 0 {Some(self.emit(Inst::Split(0, 0)) ?)} else {None};
+                        let body = self.prog.len();
+                        self.compile(node) ?;
+                        let again = self.emit(Inst::Split(0, 0)) ?;
+                        let exit = self.prog.len();
+                        if let Some(split) = split {
+                            self.patch_split(split, body, exit, *greedy);
+                        }
+                        self.patch_split(again, body, exit, *greedy);
+                    },
+                    Some(max) => { // nested optionals for the rest
+                        let mut splits = Vec::new();
+                        for _ in *min..*max {
+                            let split = self.emit(Inst::Split(0, 0)) ?;
+                            splits.push(split);
+                            self.compile(node) ?;
+                        }
+                        let exit = self.prog.len();
+                        for split in splits {
+                            self.patch_split(split, split + 1, exit, *greedy);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+struct Thread {
+    pc: usize,
+    slots: Vec<Option<usize>>
+}
+
+impl Regex {
+    pub fn new(pattern: &str) -> Result<Regex, RegexError> {
+        Self::with_case(pattern, false)
+    }
+
+    pub fn with_case(pattern: &str, case_insensitive: bool) -> Result<Regex, RegexError> {
+        let chars: Vec<char> = pattern.chars().collect();
+        let mut parser = Parser {chars: &chars, pos: 0, groups: 0, depth: 0};
+        let node = parser.parse_alt() ?;
+        if parser.pos < chars.len() { // only a stray ) stops the parser early
+            return parser.error("unmatched )")
+        }
+        let mut compiler = Compiler {prog: Vec::new()};
+        compiler.emit(Inst::Save(0)) ?;
+        compiler.compile(&node) ?;
+        compiler.emit(Inst::Save(1)) ?;
+        compiler.emit(Inst::Match) ?;
+        let slots = (parser.groups + 1) * 2;
+        if compiler.prog.len() * slots > MAX_STATE {
+            return Err(RegexError {message: "pattern too large".to_string(), offset: 0})
+        }
+        Ok(Regex {
+            prog: compiler.prog,
+            slots,
+            case_insensitive
+        })
+    }
+
+    // escapes all regex syntax so text matches literally
+    pub fn escape(text: &str) -> String {
+        let mut out = String::new();
+        for ch in text.chars() {
+            if "\\.+*?()|[]{}^$".contains(ch) {
+                out.push('\\');
+            }
+            out.push(ch);
+        }
+        out
+    }
+
+    pub fn group_count(&self) -> usize {
+        self.slots / 2 - 1
+    }
+
+    fn chars_equal(&self, a: char, b: char) -> bool {
+        a == b || self.case_insensitive && a.to_lowercase().eq(b.to_lowercase())
+    }
+
+    fn check_assert(assertion: Assertion, text: &[char], pos: usize) -> bool {
+        let prev = if pos > 0 {Some(text[pos - 1])} else {None};
+        let next = text.get(pos).cloned();
+        match assertion {
+            Assertion::LineStart => prev.map_or(true, | ch | ch == '\n'),
+            Assertion::LineEnd => next.map_or(true, | ch | ch == '\n'),
+            Assertion::WordBoundary | Assertion::NotWordBoundary => {
+                let boundary = prev.map_or(false, is_word_char) != next.map_or(false, is_word_char);
+                boundary == (assertion == Assertion::WordBoundary)
+            }
+        }
+    }
+
+    // follows the empty transitions from pc, adding the threads that wait on a char in priority order.
+    // a split pushes its second branch first so the first one is followed through before it
+    fn add_thread(&self, list: &mut Vec<Thread>, marks: &mut Vec<usize>, generation: usize, pc: usize, slots: Vec<Option<usize>>, text: &[char], pos: usize) {
+        let mut stack = vec![(pc, slots)];
+        while let Some((pc, mut slots)) = stack.pop() {
+            if marks[pc] == generation {
+                continue;
+            }
+            marks[pc] = generation;
+            match &self.prog[pc] {
+                Inst::Jump(to) => stack.push((*to, slots)),
+                Inst::Split(a, b) => {
+                    stack.push((*b, slots.clone()));
+                    stack.push((*a, slots));
+                },
+                Inst::Save(slot) => {
+                    slots[*slot] = Some(pos);
+                    stack.push((pc + 1, slots));
+                },
+                Inst::Assert(assertion) => if Self::check_assert(*assertion, text, pos) {
+                    stack.push((pc + 1, slots));
+                },
+                _ => list.push(Thread {pc, slots})
+            }
+        }
+    }
+
+    // the leftmost match starting at or after start
+    pub fn find_at(&self, text: &[char], start: usize) -> Option<RegexMatch> {
+        let mut clist = Vec::new();
+        let mut nlist = Vec::new();
+        let mut marks = vec![usize::MAX; self.prog.len()];
+        let mut generation = 0;
+        let mut matched: Option<Vec<Option<usize>>> = None;
+        let mut pos = start;
+        loop {
+            // start a new attempt at every position until something matched, at the lowest priority
+            if matched.is_none() {
+                self.add_thread(&mut clist, &mut marks, generation, 0, vec![None; self.slots], text, pos);
+            }
+            if clist.len() == 0 && (matched.is_some() || pos >= text.len()) {
+                break;
+            }
+            generation += 1;
+            let ch = text.get(pos).cloned();
+            for thread in clist.drain(..) {
+                let advance = match &self.prog[thread.pc] {
+                    Inst::Match => {
+                        matched = Some(thread.slots);
+                        // lower priority threads lose to this one
+                        break;
+                    },
+                    Inst::Char(c) => ch.map_or(false, | ch | self.chars_equal(*c, ch)),
+                    Inst::Any => ch.map_or(false, | ch | ch != '\n'),
+                    Inst::Class(class) => ch.map_or(false, | ch | class.contains(ch, self.case_insensitive)),
+                    _ => false
+                };
+                if advance {
+                    self.add_thread(&mut nlist, &mut marks, generation, thread.pc + 1, thread.slots, text, pos + 1);
+                }
+            }
+            if pos >= text.len() {
+                break;
+            }
+            std::mem::swap(&mut clist, &mut nlist);
+            pos += 1;
+        }
+        let slots = matched ?;
+        let groups = slots.chunks(2).map( | pair | match (pair[0], pair[1]) {
+            (Some(start), Some(end)) => Some((start, end)),
+            _ => None
+        }).collect::<Vec<_>>();
+        let (start, end) = groups[0] ?;
+        Some(RegexMatch {start, end, groups})
+    }
+
+    // all non overlapping matches. an empty match moves the search on by one char,
+    // and isn't reported right where the previous match ended
+    pub fn find_all(&self, text: &[char]) -> Vec<RegexMatch> {
+        let mut out = Vec::new();
+        let mut pos = 0;
+        let mut last_end = None;
+        while pos <= text.len() {
+            if let Some(m) = self.find_at(text, pos) {
+                if m.end == m.start {
+                    pos = m.end + 1;
+                    if last_end == Some(m.end) {
+                        continue;
+                    }
+                }
+                else {
+                    pos = m.end;
+                }
+                last_end = Some(m.end);
+                out.push(m);
+            }
+            else {
+                break;
+            }
+        }
+        out
+    }
+}
+
+impl RegexMatch {
+    // fills in $0-$9, ${n} and $$ in a replacement, \n and \t become newline and tab
+    pub fn expand(&self, text: &[char], replacement: &str) -> String {
+        let rep: Vec<char> = replacement.chars().collect();
+        let mut out = String::new();
+        let mut i = 0;
+        while i < rep.len() {
+            let ch = rep[i];
+            if ch == '$' && i + 1 < rep.len() {
+                let next = rep[i + 1];
+                if next == '$' {
+                    out.push('$');
+                    i += 2;
+                    continue;
+                }
+                let (group, len) = if next.is_ascii_digit() {
+                    (next.to_digit(10).map(| d | d as usize), 2)
+                }
+                else if next == '{' {
+                    let close = rep[i + 2..].iter().position( | ch | *ch == '}');
+                    match close {
+                        Some(close) => (rep[i + 2..i + 2 + close].iter().collect::<String>().parse().ok(), close + 3),
+                        None => (None, 0)
+                    }
+                }
+                else {
+                    (None, 0)
+                };
+                if let Some(group) = group {
+                    if let Some(Some((start, end))) = self.groups.get(group) {
+                        out.extend(&text[*start..*end]);
+                    }
+                    i += len;
+                    continue;
+                }
+            }
+            else if ch == '\\' && i + 1 < rep.len() {
+                match rep[i + 1] {
+                    'n' => {out.push('\n'); i += 2; continue;},
+                    't' => {out.push('\t'); i += 2; continue;},
+                    '\\' => {out.push('\\'); i += 2; continue;},
+                    _ => ()
+                }
+            }
+            out.push(ch);
+            i += 1;
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find(pattern: &str, text: &str) -> Option<(usize, usize)> {
+        let chars: Vec<char> = text.chars().collect();
+        Regex::new(pattern).unwrap().find_at(&chars, 0).map( | m | (m.start, m.end))
+    }
+
+    #[test]
+    fn large_programs_are_an_error() {
+        assert!(Regex::new("((a{1000}){1000}){1000}").is_err());
+        assert!(Regex::new("(?:(?:a?){1000}){1000}").is_err());
+        assert!(Regex::new(&"(".repeat(10_000)).is_err());
+        // repeats of nothing compile to nothing
+        assert_eq!(find("x(?:){1000}{1000}{1000}y", "xy"), Some((0, 2)));
+    }
+
+    #[test]
+    fn long_empty_chains_match() {
+        // this used to recurse once per instruction
+        let pattern = "(?:(?:a?){1000}){20}";
+        assert_eq!(find(pattern, "aaa"), Some((0, 3)));
+    }
+
+    #[test]
+    fn priorities() {
+        assert_eq!(find("a+?", "aaa"), Some((0, 1)));
+        assert_eq!(find("a+", "aaa"), Some((0, 3)));
+        assert_eq!(find("ab|a", "ab"), Some((0, 2)));
+        let chars: Vec<char> = "xaby".chars().collect();
+        let m = Regex::new("(a)(b)?").unwrap().find_at(&chars, 0).unwrap();
+        assert_eq!(m.groups, vec![Some((1, 3)), Some((1, 2)), Some((2, 3))]);
+    }
+
+    #[test]
+    fn word_class_agrees_with_word_boundary() {
+        assert_eq!(find("\\w+", "  été_1 "), Some((2, 7)));
+        assert_eq!(find("\\bété\\b", "à été."), Some((2, 5)));
+        assert_eq!(find("[\\w-]+", "ß-x"), Some((0, 3)));
+        assert_eq!(find("\\W", "日本 x"), Some((2, 3)));
+    }
+}
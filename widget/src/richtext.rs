@@ -0,0 +1,600 @@
+use makepad_render::*;
+use crate::widgetstyle::*;
+
+// Text made of spans that each have their own style, laid out as one block that wraps words
+// across the spans. The spans are drawn with a single text draw call, the font and color change
+// per span, brightness and curve come from the normal style. The mouse selects, ctrl/cmd+A selects
+// all and the platform copy gets the selection. A press and release on a link returns it.
+
+#[derive(Clone, Default, Debug)]
+pub struct RichSpan {
+    pub text: String,
+    pub bold: bool,
+    pub italic: bool,
+    pub mono: bool,
+    pub underline: bool,
+    // times the font size of the style
+    pub size: Option<f32>,
+    pub color: Option<Color>,
+    pub link: Option<String>,
+}
+
+impl RichSpan {
+    pub fn text(text: &str) -> Self {
+        Self {
+            text: text.to_string(),
+            ..Self::default()
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RichTextEvent {
+    None,
+    LinkClicked(String)
+}
+
+#[derive(Clone, Default)]
+pub struct RichTextChar {
+    pub ch: char,
+    pub span: usize,
+    // relative to the top left of the text
+    pub rect: Rect,
+}
+
+#[derive(Clone, Default)]
+pub struct RichTextLine {
+    pub y: f32,
+    pub h: f32,
+    // from the top of the line to the baseline
+    pub drop: f32,
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Clone)]
+pub struct RichTextSpanStyle {
+    pub text_style: TextStyle,
+    pub color: Color,
+    pub underline: bool,
+    pub link: Option<usize>,
+}
+
+#[derive(Clone)]
+pub struct RichText {
+    pub bg: Quad,
+    pub text: Text,
+    pub font_scale: f32,
+    pub max_width: Option<f32>,
+    pub _bg_area: Area,
+    pub _chars: Vec<RichTextChar>,
+    pub _lines: Vec<RichTextLine>,
+    pub _styles: Vec<RichTextSpanStyle>,
+    pub _links: Vec<String>,
+    // anchor and head as char offsets
+    pub _select: (usize, usize),
+    pub _down_link: Option<usize>,
+}
+
+impl RichText {
+    pub fn new(cx: &mut Cx) -> Self {
+        Self {
+            bg: Quad::new(cx),
+            text: Text::new(cx),
+            font_scale: 1.0,
+            max_width: None,
+            _bg_area: Area::Empty,
+            _chars: Vec::new(),
+            _lines: Vec::new(),
+            _styles: Vec::new(),
+            _links: Vec::new(),
+            _select: (0, 0),
+            _down_link: None,
+        }
+    }
+
+    pub fn text_style_normal() -> TextStyleId {uid!()}
+    pub fn text_style_bold() -> TextStyleId {uid!()}
+    pub fn text_style_italic() -> TextStyleId {uid!()}
+    pub fn text_style_mono() -> TextStyleId {uid!()}
+    pub fn color_text() -> ColorId {uid!()}
+    pub fn color_link() -> ColorId {uid!()}
+    pub fn color_select() -> ColorId {uid!()}
+
+    pub fn style(cx: &mut Cx, _opt: &StyleOptions) {
+        Self::text_style_normal().set(cx, TextStyle {
+            line_spacing: 1.6,
+            ..Theme::text_style_normal().get(cx)
+        });
+        // there is only a regular font in the resources, a theme that loads a bold and italic one sets them here
+        Self::text_style_bold().set(cx, Self::text_style_normal().get(cx));
+        Self::text_style_italic().set(cx, Self::text_style_normal().get(cx));
+        Self::text_style_mono().set(cx, TextStyle {
+            line_spacing: 1.6,
+            ..Theme::text_style_fixed().get(cx)
+        });
+        Self::color_text().set(cx, Theme::color_text_deselected_focus().get(cx));
+        Self::color_link().set(cx, pick!(#6fa8dc).get(cx));
+        Self::color_select().set(cx, pick!(#294e75).get(cx));
+    }
+
+    // a few lines of markdown. # headings, - bullets, ``` blocks, **bold**, *italic*, `code` and [links](url)
+    pub fn spans_from_markdown(markdown: &str) -> Vec<RichSpan> {
+        let mut spans = Vec::new();
+        let mut paragraph = String::new();
+        let mut in_code = false;
+        // a blank line since the last block
+        let mut gap = false;
+        for line in markdown.lines() {
+            let trimmed = line.trim();
+            if trimmed.starts_with("```") {
+                Self::markdown_paragraph(&mut spans, &mut paragraph, &mut gap);
+                in_code = !in_code;
+                continue;
+            }
+            if in_code {
+                Self::markdown_block(&mut spans, &mut gap);
+                spans.push(RichSpan {mono: true, ..RichSpan::text(&format!("{}\n", line))});
+                continue;
+            }
+            if trimmed.len() == 0 {
+                Self::markdown_paragraph(&mut spans, &mut paragraph, &mut gap);
+                gap = true;
+                continue;
+            }
+            let level = trimmed.chars().take_while( | c | *c == '#').count();
+            if level > 0 && trimmed[level..].starts_with(' ') {
+                Self::markdown_paragraph(&mut spans, &mut paragraph, &mut gap);
+                Self::markdown_block(&mut spans, &mut gap);
+                let size = match level {1 => 2.0, 2 => 1.5, _ => 1.2};
+                for span in Self::spans_from_markdown_inline(trimmed[level..].trim()) {
+                    spans.push(RichSpan {bold: true, size: Some(size), ..span});
+                }
+                spans.push(RichSpan {size: Some(size), ..RichSpan::text("\n")});
+            }
+            else if trimmed.starts_with("- ") || trimmed.starts_with("* ") {
+                Self::markdown_paragraph(&mut spans, &mut paragraph, &mut gap);
+                Self::markdown_block(&mut spans, &mut gap);
+                spans.push(RichSpan::text("  \u{2022} "));
+                spans.extend(Self::spans_from_markdown_inline(trimmed[2..].trim()));
+                spans.push(RichSpan::text("\n"));
+            }
+            else {
+                if paragraph.len() > 0 {
+                    paragraph.push(' ');
+                }
+                paragraph.push_str(trimmed);
+            }
+        }
+        Self::markdown_paragraph(&mut spans, &mut paragraph, &mut gap);
+        spans
+    }
+
+    fn markdown_block(spans: &mut Vec<RichSpan>, gap: &mut bool) {
+        if *gap && spans.len() > 0 {
+            spans.push(RichSpan::text("\n"));
+        }
+        *gap = false;
+    }
+
+    fn markdown_paragraph(spans: &mut Vec<RichSpan>, paragraph: &mut String, gap: &mut bool) {
+        if paragraph.len() > 0 {
+            Self::markdown_block(spans, gap);
+            spans.extend(Self::spans_from_markdown_inline(paragraph));
+            spans.push(RichSpan::text("\n"));
+            paragraph.truncate(0);
+        }
+    }
+
+    pub fn spans_from_markdown_inline(text: &str) -> Vec<RichSpan> {
+        let mut spans = Vec::new();
+        let mut span = RichSpan::default();
+        let chars: Vec<char> = text.chars().collect();
+        let mut i = 0;
+        fn flush(spans: &mut Vec<RichSpan>, span: &mut RichSpan) {
+            if span.text.len() > 0 {
+                spans.push(span.clone());
+                span.text.truncate(0);
+            }
+        }
+        while i < chars.len() {
+            let c = chars[i];
+            if span.mono && c != '`' {
+                span.text.push(c);
+                i += 1;
+                continue;
+            }
+            match c {
+                '\\' if i + 1 < chars.len() => {
+                    span.text.push(chars[i + 1]);
+                    i += 2;
+                },
+                '`' => {
+                    flush(&mut spans, &mut span);
+                    span.mono = !span.mono;
+                    i += 1;
+                },
+                '*' if i + 1 < chars.len() && chars[i + 1] == '*' => {
+                    flush(&mut spans, &mut span);
+                    span.bold = !span.bold;
+                    i += 2;
+                },
+                '*' => {
+                    flush(&mut spans, &mut span);
+                    span.italic = !span.italic;
+                    i += 1;
+                },
+                '[' => {
+                    // [text](url), or just a bracket
+                    let close = chars[i..].iter().position( | c | *c == ']').map( | p | p + i);
+                    let link = close.and_then( | close | {
+                        if close + 1 < chars.len() && chars[close + 1] == '(' {
+                            chars[close..].iter().position( | c | *c == ')').map( | end | (close, end + close))
+                        }
+                        else {
+                            None
+                        }
+                    });
+                    if let Some((close, end)) = link {
+                        flush(&mut spans, &mut span);
+                        spans.push(RichSpan {
+                            text: chars[i + 1..close].iter().collect(),
+                            link: Some(chars[close + 2..end].iter().collect()),
+                            ..span.clone()
+                        });
+                        i = end + 1;
+                    }
+                    else {
+                        span.text.push(c);
+                        i += 1;
+                    }
+                },
+                _ => {
+                    span.text.push(c);
+                    i += 1;
+                }
+            }
+        }
+        flush(&mut spans, &mut span);
+        spans
+    }
+
+    fn span_style(&self, cx: &Cx, span: &RichSpan, links: &mut Vec<String>) -> RichTextSpanStyle {
+        let text_style = if span.mono {
+            Self::text_style_mono().get(cx)
+        }
+        else if span.bold {
+            Self::text_style_bold().get(cx)
+        }
+        else if span.italic {
+            Self::text_style_italic().get(cx)
+        }
+        else {
+            Self::text_style_normal().get(cx)
+        };
+        let link = span.link.as_ref().map( | link | {
+            links.push(link.clone());
+            links.len() - 1
+        });
+        RichTextSpanStyle {
+            text_style: TextStyle {
+                font_size: text_style.font_size * span.size.unwrap_or(1.0) * self.font_scale,
+                ..text_style
+            },
+            color: span.color.unwrap_or_else( || if link.is_some() {
+                Self::color_link().get(cx)
+            }
+            else {
+                Self::color_text().get(cx)
+            }),
+            underline: span.underline || link.is_some(),
+            link: link
+        }
+    }
+
+    fn end_line(&mut self, end: usize, y: f32) -> f32 {
+        let start = self._lines.last().map_or(0, | line | line.end);
+        let mut h = 0.0f32;
+        let mut drop = 0.0f32;
+        for i in start..end {
+            let text_style = &self._styles[self._chars[i].span].text_style;
+            h = h.max(text_style.font_size * text_style.line_spacing);
+            drop = drop.max(text_style.font_size * text_style.top_drop);
+        }
+        for i in start..end {
+            self._chars[i].rect.y = y;
+            self._chars[i].rect.h = h;
+        }
+        self._lines.push(RichTextLine {y: y, h: h, drop: drop, start: start, end: end});
+        y + h
+    }
+
+    // wraps after whitespace, words longer than a line wrap per char. Returns the size
+    fn layout(&mut self, cx: &Cx, spans: &[RichSpan], max_width: f32) -> Vec2 {
+        self._chars.truncate(0);
+        self._lines.truncate(0);
+        self._styles.truncate(0);
+        self._links.truncate(0);
+        let mut links = Vec::new();
+        for (span_id, span) in spans.iter().enumerate() {
+            let style = self.span_style(cx, span, &mut links);
+            let font = cx.fonts[style.text_style.font.font_id.unwrap()].font_loaded.as_ref().unwrap();
+            let font_size_logical = style.text_style.font_size * 96.0 / (72.0 * font.units_per_em);
+            let advance_of = | c: char | {
+                if c >= '\u{10000}' {
+                    return 0.0
+                }
+                let glyph_id = font.char_code_to_glyph_index_map[c as usize];
+                if glyph_id >= font.glyphs.len() {
+                    return 0.0
+                }
+                font.glyphs[glyph_id].horizontal_metrics.advance_width * font_size_logical
+            };
+            for c in span.text.chars() {
+                let w = match c {
+                    '\n' => 0.0,
+                    '\t' => advance_of(' ') * 4.0,
+                    _ => advance_of(c)
+                };
+                self._chars.push(RichTextChar {ch: c, span: span_id, rect: Rect {w: w, ..Rect::default()}});
+            }
+            self._styles.push(style);
+        }
+        self._links = links;
+
+        let mut width = 0.0f32;
+        let mut y = 0.0;
+        let mut x = 0.0;
+        let mut i = 0;
+        let len = self._chars.len();
+        while i < len {
+            if self._chars[i].ch == '\n' {
+                self._chars[i].rect.x = x;
+                width = width.max(x);
+                y = self.end_line(i + 1, y);
+                x = 0.0;
+                i += 1;
+                continue;
+            }
+            // a word with the whitespace after it, which may hang over the edge
+            let mut j = i;
+            let mut word_width = 0.0;
+            let mut fit_width = 0.0;
+            while j < len && self._chars[j].ch != '\n' {
+                let ch = self._chars[j].ch;
+                if !ch.is_whitespace() && j > i && self._chars[j - 1].ch.is_whitespace() {
+                    break;
+                }
+                word_width += self._chars[j].rect.w;
+                if !ch.is_whitespace() {
+                    fit_width = word_width;
+                }
+                j += 1;
+            }
+            if x > 0.0 && x + fit_width > max_width {
+                y = self.end_line(i, y);
+                x = 0.0;
+            }
+            for k in i..j {
+                let w = self._chars[k].rect.w;
+                if x > 0.0 && x + w > max_width && !self._chars[k].ch.is_whitespace() {
+                    y = self.end_line(k, y);
+                    x = 0.0;
+                }
+                self._chars[k].rect.x = x;
+                x += w;
+                if !self._chars[k].ch.is_whitespace() {
+                    width = width.max(x);
+                }
+            }
+            i = j;
+        }
+        if self._lines.last().map_or(0, | line | line.end) < len {
+            y = self.end_line(len, y);
+        }
+        Vec2 {x: width, y: y}
+    }
+
+    fn selection(&self) -> (usize, usize) {
+        let len = self._chars.len();
+        let (anchor, head) = (self._select.0.min(len), self._select.1.min(len));
+        (anchor.min(head), anchor.max(head))
+    }
+
+    pub fn selected_text(&self) -> String {
+        let (start, end) = self.selection();
+        self._chars[start..end].iter().map( | c | c.ch).collect()
+    }
+
+    pub fn select_all(&mut self, cx: &mut Cx) {
+        self._select = (0, self._chars.len());
+        cx.redraw_child_area(self._bg_area);
+    }
+
+    // the char offset closest to a position relative to the text
+    fn offset_at(&self, pos: Vec2) -> usize {
+        let line = match self._lines.iter().find( | line | pos.y < line.y + line.h).or(self._lines.last()) {
+            Some(line) => line,
+            None => return 0
+        };
+        for i in line.start..line.end {
+            let rect = &self._chars[i].rect;
+            if self._chars[i].ch == '\n' || pos.x < rect.x + 0.5 * rect.w {
+                return i
+            }
+        }
+        line.end
+    }
+
+    fn link_at(&self, pos: Vec2) -> Option<usize> {
+        self._chars.iter().find( | c | c.rect.contains(pos.x, pos.y)).and_then( | c | self._styles[c.span].link)
+    }
+
+    fn select_word(&mut self, offset: usize) {
+        let is_word = | c: &RichTextChar | c.ch.is_alphanumeric() || c.ch == '_';
+        let mut start = offset.min(self._chars.len());
+        let mut end = start;
+        while start > 0 && is_word(&self._chars[start - 1]) {
+            start -= 1;
+        }
+        while end < self._chars.len() && is_word(&self._chars[end]) {
+            end += 1;
+        }
+        self._select = (start, end);
+    }
+
+    pub fn handle_rich_text(&mut self, cx: &mut Cx, event: &mut Event) -> RichTextEvent {
+        match event.hits(cx, self._bg_area, HitOpt::default()) {
+            Event::FingerHover(fe) => {
+                cx.set_hover_mouse_cursor(if self.link_at(fe.rel).is_some() {MouseCursor::Hand} else {MouseCursor::Text});
+            },
+            Event::FingerDown(fe) => {
+                cx.set_key_focus(self._bg_area);
+                let offset = self.offset_at(fe.rel);
+                if fe.tap_count == 2 {
+                    self.select_word(offset);
+                }
+                else if fe.modifiers.shift {
+                    self._select.1 = offset;
+                }
+                else {
+                    self._select = (offset, offset);
+                }
+                self._down_link = self.link_at(fe.rel);
+                cx.set_down_mouse_cursor(if self._down_link.is_some() {MouseCursor::Hand} else {MouseCursor::Text});
+                cx.redraw_child_area(self._bg_area);
+            },
+            Event::FingerMove(fe) => {
+                let offset = self.offset_at(fe.rel);
+                if offset != self._select.1 {
+                    self._select.1 = offset;
+                    cx.redraw_child_area(self._bg_area);
+                }
+            },
+            Event::FingerUp(fe) => {
+                // a link is clicked when nothing was selected on the way
+                if let Some(link) = self._down_link.take() {
+                    if fe.is_over && self._select.0 == self._select.1 && self.link_at(fe.rel) == Some(link) {
+                        return RichTextEvent::LinkClicked(self._links[link].clone())
+                    }
+                }
+            },
+            Event::KeyDown(ke) => match ke.key_code {
+                KeyCode::KeyA if ke.modifiers.control || ke.modifiers.logo => {
+                    self.select_all(cx);
+                },
+                KeyCode::Escape => {
+                    self._select = (0, 0);
+                    cx.redraw_child_area(self._bg_area);
+                },
+                _ => ()
+            },
+            Event::KeyFocusLost(_) => {
+                self._select = (0, 0);
+                cx.redraw_child_area(self._bg_area);
+            },
+            Event::TextCopy(_) => match event { // access the original event
+                Event::TextCopy(req) => {
+                    req.response = Some(self.selected_text());
+                },
+                _ => ()
+            },
+            _ => ()
+        }
+        RichTextEvent::None
+    }
+
+    pub fn draw_rich_text(&mut self, cx: &mut Cx, spans: &[RichSpan]) {
+        let max_width = match self.max_width {
+            Some(max_width) => max_width.min(cx.get_width_left()),
+            None => cx.get_width_left()
+        };
+        let size = self.layout(cx, spans, max_width);
+        let rect = cx.walk_turtle(Walk::wh(Width::Fix(size.x), Height::Fix(size.y)));
+
+        // what the mouse hits
+        self.bg.color = Color::default();
+        let inst = self.bg.draw_quad_abs(cx, rect);
+        cx.align_instance(inst);
+        self._bg_area = inst.into();
+
+        let (start, end) = self.selection();
+        if start != end {
+            self.bg.color = Self::color_select().get(cx);
+            for line in &self._lines {
+                let (a, b) = (start.max(line.start), end.min(line.end));
+                if a < b {
+                    let x0 = self._chars[a].rect.x;
+                    let x1 = self._chars[b - 1].rect.x + self._chars[b - 1].rect.w;
+                    let inst = self.bg.draw_quad_abs(cx, Rect {x: rect.x + x0, y: rect.y + line.y, w: (x1 - x0).max(3.), h: line.h});
+                    cx.align_instance(inst);
+                }
+            }
+        }
+
+        if let Some(style) = self._styles.first() {
+            self.text.text_style = style.text_style;
+        }
+        let mut aligned = self.text.begin_text(cx);
+        let mut chunk = Vec::new();
+        for line in &self._lines {
+            // runs of chars of one span
+            let mut i = line.start;
+            while i < line.end {
+                let span = self._chars[i].span;
+                let style = &self._styles[span];
+                let x = self._chars[i].rect.x;
+                chunk.truncate(0);
+                while i < line.end && self._chars[i].span == span {
+                    let ch = self._chars[i].ch;
+                    if ch >= '\u{10000}' || ch.is_whitespace() {
+                        i += 1;
+                        break;
+                    }
+                    chunk.push(ch);
+                    i += 1;
+                }
+                if chunk.len() > 0 {
+                    self.text.text_style = style.text_style;
+                    self.text.color = style.color;
+                    let y = rect.y + line.y + line.drop - style.text_style.font_size * style.text_style.top_drop;
+                    self.text.add_text(cx, rect.x + x, y, 0, &mut aligned, &chunk, | _, _, _, _ | {0.0});
+                }
+            }
+        }
+        self.text.end_text(cx, &aligned);
+
+        for line in &self._lines {
+            let mut i = line.start;
+            while i < line.end {
+                let span = self._chars[i].span;
+                let style = &self._styles[span];
+                let x0 = self._chars[i].rect.x;
+                let mut x1 = x0;
+                while i < line.end && self._chars[i].span == span {
+                    if !self._chars[i].ch.is_whitespace() {
+                        x1 = self._chars[i].rect.x + self._chars[i].rect.w;
+                    }
+                    i += 1;
+                }
+                if style.underline && x1 > x0 {
+                    let font_size = style.text_style.font_size;
+                    self.bg.color = style.color;
+                    let inst = self.bg.draw_quad_abs(cx, Rect {
+                        x: rect.x + x0,
+                        y: rect.y + line.y + line.drop + 0.15 * font_size,
+                        w: x1 - x0,
+                        h: (0.08 * font_size).max(1.)
+                    });
+                    cx.align_instance(inst);
+                }
+            }
+        }
+
+        let text: String = self._chars.iter().map( | c | c.ch).collect();
+        cx.add_a11y_node(A11yNode {
+            value: self.selected_text(),
+            ..A11yNode::new(A11yRole::Label, &text, self._bg_area)
+        });
+    }
+}
@@ -0,0 +1,348 @@
+use makepad_microserde::*;
+use crate::textcursor::*;
+use crate::regex::*;
+
+// Snippets are read from a ron file per language, a list of
+// (prefix: "fn", description: "function", body: "fn ${1:name}(${2}) {\n\t$0\n}")
+// $1, $2.. are tab stops visited in order, ${1:text} gives one a placeholder and a number
+// used twice is mirrored: both are edited at once by a cursor each. $0 is where the cursor ends,
+// the end of the snippet when there is none. \t indents and \$ is a literal dollar.
+
+#[derive(Clone, Debug, SerRon, DeRon, PartialEq)]
+pub struct Snippet {
+    pub prefix: String,
+    pub description: String,
+    pub body: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SnippetExpansion {
+    pub text: String,
+    // char ranges relative to the start of text per tab stop, in visiting order, the final cursor last
+    pub stops: Vec<Vec<(usize, usize)>>,
+}
+
+enum SnippetPart {
+    Text(String),
+    Stop(usize, Option<String>),
+}
+
+impl Snippet {
+    fn parse(&self) -> Vec<SnippetPart> {
+        let body: Vec<char> = self.body.chars().collect();
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut i = 0;
+        while i < body.len() {
+            let ch = body[i];
+            if ch == '\\' && i + 1 < body.len() && (body[i + 1] == '$' || body[i + 1] == '}' || body[i + 1] == '\\') {
+                text.push(body[i + 1]);
+                i += 2;
+                continue
+            }
+            if ch == '$' {
+                let braced = i + 1 < body.len() && body[i + 1] == '{';
+                let mut j = if braced {i + 2} else {i + 1};
+                let digits_start = j;
+                while j < body.len() && body[j].is_ascii_digit() {
+                    j += 1;
+                }
+                if j > digits_start {
+                    let number: usize = body[digits_start..j].iter().collect::<String>().parse().unwrap_or(0);
+                    if !braced {
+                        parts.push(SnippetPart::Text(std::mem::replace(&mut text, String::new())));
+                        parts.push(SnippetPart::Stop(number, None));
+                        i = j;
+                        continue
+                    }
+                    if j < body.len() && body[j] == '}' {
+                        parts.push(SnippetPart::Text(std::mem::replace(&mut text, String::new())));
+                        parts.push(SnippetPart::Stop(number, None));
+                        i = j + 1;
+                        continue
+                    }
+                    if j < body.len() && body[j] == ':' {
+                        let mut placeholder = String::new();
+                        let mut k = j + 1;
+                        while k < body.len() && body[k] != '}' {
+                            if body[k] == '\\' && k + 1 < body.len() {
+                                k += 1;
+                            }
+                            placeholder.push(body[k]);
+                            k += 1;
+                        }
+                        if k < body.len() {
+                            parts.push(SnippetPart::Text(std::mem::replace(&mut text, String::new())));
+                            parts.push(SnippetPart::Stop(number, Some(placeholder)));
+                            i = k + 1;
+                            continue
+                        }
+                    }
+                }
+            }
+            text.push(ch);
+            i += 1;
+        }
+        parts.push(SnippetPart::Text(text));
+        parts
+    }
+
+    // lays the body out at the given indent, every line after the first starts with it
+    pub fn expand(&self, indent: &str, tab: &str) -> SnippetExpansion {
+        let parts = self.parse();
+        // mirrors show the placeholder of whichever occurrence has one
+        let mut placeholders: Vec<(usize, String)> = Vec::new();
+        for part in &parts {
+            if let SnippetPart::Stop(number, Some(placeholder)) = part {
+                if placeholders.iter().find( | (n, _) | n == number).is_none() {
+                    placeholders.push((*number, placeholder.clone()));
+                }
+            }
+        }
+        let mut out = String::new();
+        let mut len = 0;
+        let mut numbered: Vec<(usize, Vec<(usize, usize)>)> = Vec::new();
+        // the indent after a newline is held back until something follows it, so blank lines stay empty
+        let mut pending_indent = false;
+        let push = | out: &mut String, len: &mut usize, pending_indent: &mut bool, text: &str | {
+            for ch in text.chars() {
+                if ch == '\n' {
+                    out.push('\n');
+                    *len += 1;
+                    *pending_indent = true;
+                    continue
+                }
+                if *pending_indent {
+                    out.push_str(indent);
+                    *len += indent.chars().count();
+                    *pending_indent = false;
+                }
+                if ch == '\t' {
+                    out.push_str(tab);
+                    *len += tab.chars().count();
+                }
+                else {
+                    out.push(ch);
+                    *len += 1;
+                }
+            }
+        };
+        for part in &parts {
+            match part {
+                SnippetPart::Text(text) => push(&mut out, &mut len, &mut pending_indent, text),
+                SnippetPart::Stop(number, _) => {
+                    if pending_indent {
+                        out.push_str(indent);
+                        len += indent.chars().count();
+                        pending_indent = false;
+                    }
+                    let start = len;
+                    if let Some((_, placeholder)) = placeholders.iter().find( | (n, _) | n == number) {
+                        push(&mut out, &mut len, &mut pending_indent, placeholder);
+                    }
+                    if let Some((_, ranges)) = numbered.iter_mut().find( | (n, _) | n == number) {
+                        ranges.push((start, len));
+                    }
+                    else {
+                        numbered.push((*number, vec![(start, len)]));
+                    }
+                }
+            }
+        }
+        if pending_indent {
+            out.push_str(indent);
+            len += indent.chars().count();
+        }
+        // $0 goes last, the others in numeric order
+        numbered.sort_by_key( | (number, _) | if *number == 0 {usize::max_value()} else {*number});
+        let mut stops: Vec<Vec<(usize, usize)>> = numbered.into_iter().map( | (_, ranges) | ranges).collect();
+        if stops.len() == 0 || parts.iter().find( | part | if let SnippetPart::Stop(0, _) = part {true} else {false}).is_none() {
+            stops.push(vec![(len, len)]);
+        }
+        SnippetExpansion {text: out, stops}
+    }
+}
+
+// an expanded snippet whose tab stops are being visited
+#[derive(Clone, Debug, PartialEq)]
+pub struct SnippetSession {
+    pub stops: Vec<Vec<(usize, usize)>>,
+    pub current: usize,
+}
+
+impl SnippetSession {
+    pub fn new(start: usize, expansion: &SnippetExpansion) -> Self {
+        SnippetSession {
+            stops: expansion.stops.iter().map( | ranges | ranges.iter().map( | (s, e) | (start + s, start + e)).collect()).collect(),
+            current: 0,
+        }
+    }
+
+    pub fn current_ranges(&self) -> &Vec<(usize, usize)> {
+        &self.stops[self.current]
+    }
+
+    pub fn is_final(&self) -> bool {
+        self.current + 1 >= self.stops.len()
+    }
+
+    // after an edit that grew the buffer by growth: the cursors of the current stop each made the
+    // same edit inside their range. shifts every stop along, false when the edit went elsewhere
+    pub fn update_after_edit(&mut self, growth: isize, cursors: &[TextCursor]) -> bool {
+        let current = self.stops[self.current].clone();
+        let n = current.len() as isize;
+        if cursors.len() != current.len() || growth % n != 0 {
+            return false
+        }
+        let per = growth / n;
+        let shift = | pos: usize, count: usize | (pos as isize + per * count as isize).max(0) as usize;
+        for (index, stop) in self.stops.iter_mut().enumerate() {
+            for (i, range) in stop.iter_mut().enumerate() {
+                if index == self.current {
+                    *range = (shift(range.0, i), shift(range.1, i + 1));
+                }
+                else {
+                    let before_start = current.iter().filter( | r | r.1 <= range.0).count();
+                    let before_end = current.iter().filter( | r | r.1 <= range.1).count();
+                    *range = (shift(range.0, before_start), shift(range.1, before_end));
+                }
+            }
+        }
+        self.contains(cursors)
+    }
+
+    // whether every cursor is still inside its own range of the current stop
+    pub fn contains(&self, cursors: &[TextCursor]) -> bool {
+        let current = &self.stops[self.current];
+        if cursors.len() != current.len() {
+            return false
+        }
+        cursors.iter().zip(current.iter()).all( | (cursor, range) | {
+            let (start, end) = cursor.order();
+            start >= range.0 && end <= range.1
+        })
+    }
+
+    pub fn next(&mut self) -> bool {
+        if self.is_final() {
+            return false
+        }
+        self.current += 1;
+        true
+    }
+
+    pub fn prev(&mut self) -> bool {
+        if self.current == 0 {
+            return false
+        }
+        self.current -= 1;
+        true
+    }
+}
+
+// the longest prefix that ends the text before the cursor, and doesnt start halfway a word
+pub fn find_snippet<'a>(snippets: &'a [Snippet], before: &[char]) -> Option<&'a Snippet> {
+    let mut best: Option<&Snippet> = None;
+    for snippet in snippets {
+        let prefix: Vec<char> = snippet.prefix.chars().collect();
+        if prefix.len() == 0 || prefix.len() > before.len() || before[before.len() - prefix.len()..] != prefix[..] {
+            continue
+        }
+        let start = before.len() - prefix.len();
+        if start > 0 && is_word_char(before[start - 1]) && is_word_char(prefix[0]) {
+            continue
+        }
+        if best.map_or(true, | best | best.prefix.len() < snippet.prefix.len()) {
+            best = Some(snippet);
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snippet(prefix: &str, body: &str) -> Snippet {
+        Snippet {prefix: prefix.to_string(), description: String::new(), body: body.to_string()}
+    }
+
+    fn expand(body: &str) -> SnippetExpansion {
+        snippet("", body).expand("", "\t")
+    }
+
+    fn cursor(pos: usize) -> TextCursor {
+        TextCursor {head: pos, tail: pos, max: 0}
+    }
+
+    #[test]
+    fn stops_and_placeholders() {
+        assert_eq!(expand("a$1b"), SnippetExpansion {text: "ab".to_string(), stops: vec![vec![(1, 1)], vec![(2, 2)]]});
+        assert_eq!(expand("f(${2}, ${1:x})"), SnippetExpansion {
+            text: "f(, x)".to_string(),
+            stops: vec![vec![(4, 5)], vec![(2, 2)], vec![(6, 6)]]
+        });
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(expand("\\$1 \\} \\\\$0"), SnippetExpansion {text: "$1 } \\".to_string(), stops: vec![vec![(6, 6)]]});
+        assert_eq!(expand("${1:a\\}b}"), SnippetExpansion {text: "a}b".to_string(), stops: vec![vec![(0, 3)], vec![(3, 3)]]});
+        // not a stop, so it stays as it is
+        assert_eq!(expand("${x} $"), SnippetExpansion {text: "${x} $".to_string(), stops: vec![vec![(6, 6)]]});
+    }
+
+    #[test]
+    fn mirrors_share_the_placeholder() {
+        assert_eq!(expand("$1 = ${1:name};"), SnippetExpansion {
+            text: "name = name;".to_string(),
+            stops: vec![vec![(0, 4), (7, 11)], vec![(12, 12)]]
+        });
+    }
+
+    #[test]
+    fn final_stop() {
+        assert_eq!(expand("a$0b"), SnippetExpansion {text: "ab".to_string(), stops: vec![vec![(1, 1)]]});
+        assert_eq!(expand("ab"), SnippetExpansion {text: "ab".to_string(), stops: vec![vec![(2, 2)]]});
+    }
+
+    #[test]
+    fn indent() {
+        assert_eq!(snippet("fn", "fn ${1:f}() {\n\t$0\n}").expand("    ", "  "), SnippetExpansion {
+            text: "fn f() {\n      \n    }".to_string(),
+            stops: vec![vec![(3, 4)], vec![(15, 15)]]
+        });
+        // blank lines get no indent
+        assert_eq!(snippet("", "a\n\nb").expand("  ", "  ").text, "a\n\n  b");
+    }
+
+    #[test]
+    fn edits_in_two_mirrors() {
+        let mut session = SnippetSession::new(10, &expand("${1:name} = $1;"));
+        assert_eq!(session.stops, vec![vec![(10, 14), (17, 21)], vec![(22, 22)]]);
+        // both placeholders typed over with an x
+        assert!(session.update_after_edit(-6, &[cursor(11), cursor(15)]));
+        assert_eq!(session.stops, vec![vec![(10, 11), (14, 15)], vec![(16, 16)]]);
+        // and a y after each
+        assert!(session.update_after_edit(2, &[cursor(12), cursor(17)]));
+        assert_eq!(session.stops, vec![vec![(10, 12), (15, 17)], vec![(18, 18)]]);
+        // an edit that isn't the same in both, or a cursor that left its range
+        assert!(!session.update_after_edit(1, &[cursor(13), cursor(17)]));
+        let mut session = SnippetSession::new(0, &expand("$1 $1"));
+        assert!(!session.update_after_edit(2, &[cursor(1), cursor(5)]));
+        assert!(session.next());
+        assert!(session.is_final());
+        assert!(!session.next());
+    }
+
+    #[test]
+    fn prefix_at_the_start_of_a_word() {
+        let snippets = vec![snippet("fn", "fn"), snippet("pfn", "pub fn")];
+        let before: Vec<char> = "x pfn".chars().collect();
+        assert_eq!(find_snippet(&snippets, &before).map( | s | s.prefix.as_str()), Some("pfn"));
+        let before: Vec<char> = "(fn".chars().collect();
+        assert_eq!(find_snippet(&snippets, &before).map( | s | s.prefix.as_str()), Some("fn"));
+        let before: Vec<char> = "xfn".chars().collect();
+        assert_eq!(find_snippet(&snippets, &before), None);
+    }
+}
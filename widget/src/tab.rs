@@ -10,6 +10,7 @@ pub struct Tab {
     pub tab_close: TabClose,
     pub label: String,
     pub is_closeable: bool,
+    pub key_button: ButtonLogic,
     pub animator: Animator,
     pub z: f32,
     pub abs_origin: Option<Vec2>,
@@ -31,6 +32,7 @@ pub enum TabEvent {
     Closing,
     Close,
     Select,
+    ContextMenu(Vec2),
 }
 
 impl Tab {
@@ -38,6 +40,7 @@ impl Tab {
         let mut tab = Self {
             label: "Tab".to_string(),
             is_closeable: true,
+            key_button: ButtonLogic::default(),
             z: 0.,
             bg: Quad ::new(cx),
             tab_close: TabClose::new(cx),
@@ -61,6 +64,8 @@ impl Tab {
     pub fn text_style_title() -> TextStyleId {uid!()}
     pub fn border_color() -> ColorId {uid!()}
     pub fn tab_closing() -> FloatId {uid!()}
+    pub fn focus() -> FloatId {uid!()}
+    pub fn focus_color() -> ColorId {uid!()}
     pub fn shader_bg() -> ShaderId {uid!()}
     
     pub fn style(cx: &mut Cx, opt: &StyleOptions) {
@@ -77,6 +82,8 @@ impl Tab {
         Self::shader_bg().set(cx, Quad::def_quad_shader().compose(shader!{"
             
             instance border_color: Self::border_color();
+            instance focus: Self::focus();
+            instance focus_color: Self::focus_color();
             const border_width: float = 1.0;
             
             fn pixel() -> vec4 {
@@ -87,7 +94,9 @@ impl Tab {
                 cx.line_to(w, h);
                 cx.move_to(0., 0.);
                 cx.line_to(0., h);
-                return cx.stroke(border_color, 1.);
+                cx.stroke(border_color, 1.);
+                cx.box(1.5, 1.5, w - 3., h - 3., 2.);
+                return cx.stroke(vec4(focus_color.rgb, focus_color.a * focus), 1.5);
             }
         "}));
     }
@@ -186,7 +195,23 @@ impl Tab {
             }
         }
         
+        if let ButtonEvent::Clicked = self.key_button.handle_key_activation(cx, event, self._bg_area) {
+            if !self.animator.term_anim_playing() {
+                self._is_selected = true;
+                self._is_focussed = true;
+                return TabEvent::Select;
+            }
+        }
+        
         match event.hits(cx, self._bg_area, HitOpt::default()) {
+            Event::KeyFocus(_) | Event::KeyFocusLost(_) => {
+                cx.redraw_child_area(self._bg_area);
+            },
+            Event::A11yAction(ae) => if ae.action == A11yAction::Select && !self.animator.term_anim_playing() {
+                self._is_selected = true;
+                self._is_focussed = true;
+                return TabEvent::Select;
+            },
             Event::Animate(ae) => {
                 // its playing the term anim, run a redraw
                 if self.animator.term_anim_playing() {
@@ -206,10 +231,15 @@ impl Tab {
                     self.animator.end();
                 }
             },
-            Event::FingerDown(_fe) => {
+            Event::FingerDown(fe) => {
                 if self.animator.term_anim_playing() {
                     return TabEvent::None
                 }
+                if fe.is_context_click() {
+                    // a right click doesn't drag the tab
+                    cx.captured_fingers[fe.digit] = Area::Empty;
+                    return TabEvent::ContextMenu(fe.abs)
+                }
                 cx.set_down_mouse_cursor(MouseCursor::Hand);
                 self._is_down = true;
                 self._is_drag = false;
@@ -292,6 +322,8 @@ impl Tab {
                 )
             );
             bg_inst.push_last_color(cx, &self.animator, Self::border_color());
+            bg_inst.push_float(cx, 0.);
+            bg_inst.push_color(cx, Theme::color_focus_ring().get(cx));
             self._bg_area = bg_inst.into();
             self.animator.set_area(cx, self._bg_area);
             return Err(())
@@ -305,6 +337,8 @@ impl Tab {
             };
             let bg_inst = self.bg.begin_quad(cx, layout);
             bg_inst.push_last_color(cx, &self.animator, Self::border_color());
+            bg_inst.push_float(cx, if cx.has_key_focus(self._bg_area) {1.} else {0.});
+            bg_inst.push_color(cx, Theme::color_focus_ring().get(cx));
             if self.is_closeable {
                 self.tab_close.draw_tab_close(cx);
                 cx.turtle_align_y();
@@ -325,6 +359,15 @@ impl Tab {
         if let Some(bg_inst) = self._bg_inst.take() {
             self._bg_area = self.bg.end_quad(cx, &bg_inst);
             self.animator.set_area(cx, self._bg_area); // if our area changed, update animation
+            // the tab dragged around is drawn at an abs_origin
+            if self.abs_origin.is_none() {
+                cx.add_focusable(self._bg_area);
+                cx.add_a11y_node(A11yNode {
+                    selected: self._is_selected,
+                    actions: vec![A11yAction::Select, A11yAction::Focus],
+                    ..A11yNode::new(A11yRole::Tab, &self.label, self._bg_area)
+                });
+            }
         }
     }
     
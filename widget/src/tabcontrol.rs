@@ -30,7 +30,8 @@ pub enum TabControlEvent {
     TabDragMove {fe: FingerMoveEvent, tab_id: usize},
     TabDragEnd {fe: FingerUpEvent, tab_id: usize},
     TabSelect {tab_id: usize},
-    TabClose {tab_id: usize}
+    TabClose {tab_id: usize},
+    TabContextMenu {tab_id: usize, abs: Vec2}
 }
 
 impl TabControl {
@@ -119,6 +120,9 @@ impl TabControl {
                     // Sooooo someone wants to close the tab
                     tab_control_event = TabControlEvent::TabClose {tab_id: *id};
                 },
+                TabEvent::ContextMenu(abs) => {
+                    tab_control_event = TabControlEvent::TabContextMenu {tab_id: *id, abs: abs};
+                },
                 _ => ()
             }
         };
@@ -187,6 +191,7 @@ impl TabControl {
         }) {
             return Err(())
         }
+        cx.begin_a11y_node(A11yNode::new(A11yRole::TabList, "", self.tabs_view.get_view_area(cx)));
         self._tab_now_selected = None;
         self._tab_id_alloc = 0;
         Ok(())
@@ -234,6 +239,7 @@ impl TabControl {
                 self.drag_tab_view.end_view(cx);
             }
         }
+        cx.end_a11y_node();
         cx.begin_style(Self::tab_control_style());
         self.tabs_view.end_view(cx);
         cx.end_style();
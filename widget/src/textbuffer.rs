@@ -1,18 +1,20 @@
 use makepad_render::*;
+use makepad_microserde::*;
 
 use crate::textcursor::*;
 use crate::tokentype::*;
+use crate::language::*;
+use crate::textlines::*;
+use crate::linediff::*;
+use crate::undotree::*;
+use crate::textchange::*;
 
 #[derive(Clone, Default)]
 pub struct TextBuffer {
-    // Vec<Vec<char>> was chosen because, for all practical use (code) most lines are short
-    // Concatenating the total into a utf8 string is trivial, and O(1) for windowing into the lines is handy.
-    // Also inserting lines is pretty cheap even approaching 100k lines.
-    // If you want to load a 100 meg single line file or something with >100k lines
-    // other options are better. But these are not usecases for this editor.
-    pub lines: Vec<Vec<char>>,
-    pub undo_stack: Vec<TextUndo>,
-    pub redo_stack: Vec<TextUndo>,
+    // lines are stored as a tree of line chunks, see TextLines.
+    // mapping offsets and rows is O(log n) so generated code, logs and minified files stay editable
+    pub lines: TextLines,
+    pub undo_tree: TextUndoTree,
     
     //pub load_file_read: FileRead,
     pub is_loaded: bool,
@@ -21,13 +23,23 @@ pub struct TextBuffer {
     pub mutation_id: u32,
     pub is_crlf: bool,
     pub markers: TextBufferMarkers,
-    pub flat_text: Vec<char>,
+    // the tokens index into the lines by char offset, TextLinesReader gets their chars
     pub token_chunks: Vec<TokenChunk>,
     pub was_invalid_pair: bool,
-    pub old_flat_text: Vec<char>,
-    pub old_token_chunks: Vec<TokenChunk>,
     pub token_chunks_id: u32,
+    // the first offset edited since the last tokenize, tokenizing resumes from the checkpoint before it
+    pub token_dirty_offset: usize,
+    pub token_checkpoints: Vec<TokenCheckpoint>,
     pub keyboard: TextBufferKeyboard,
+    // when Some every edit is also recorded as a change set, for collaborative editing to send out
+    pub change_log: Option<Vec<TextChange>>,
+    // the text from where the change log stopped, an edit it could not express sends the difference instead
+    pub change_log_base: Option<Vec<char>>,
+    // the last changes made by collaborators, editors move their cursors along with them
+    pub remote_changes: Vec<TextChange>,
+    pub remote_change_count: usize,
+    // the cursors of the focused editor, set while collaborating and taken when sent
+    pub collab_cursors: Option<Vec<TextCursor>>,
 } 
 
 impl TextBuffer {
@@ -40,6 +52,27 @@ impl TextBuffer {
 
 
 
+// the tokenizer state between two tokens, so tokenizing can resume there. They are at the start
+// of a row, or in the middle of one when rows are very long
+#[derive(Clone)]
+pub struct TokenCheckpoint {
+    pub row: usize,
+    pub col: usize,
+    pub offset: usize,
+    pub token_count: usize,
+    pub pair_stack: Vec<usize>,
+    pub was_invalid_pair: bool,
+    pub prev: char,
+    pub cur: char,
+    pub tokenizer: Box<dyn Tokenizer>,
+}
+
+pub enum TokenResume {
+    Current,
+    Start,
+    Checkpoint(TokenCheckpoint)
+}
+
 #[derive(Clone, Default)]
 pub struct TextBufferKeyboard {
     pub modifiers: KeyModifiers,
@@ -54,6 +87,19 @@ pub struct TextBufferMarkers {
     pub search_cursors: Vec<TextCursor>,
     pub message_cursors: Vec<TextCursor>,
     pub message_bodies: Vec<TextBufferMessage>,
+    // rows that differ from an earlier version of the file, see update_line_changes
+    pub line_changes: Vec<(usize, TextBufferLineChange)>,
+    // cursors of collaborators sorted on position, with the collaborator each belongs to
+    pub remote_cursors: Vec<TextCursor>,
+    pub remote_cursor_peers: Vec<usize>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextBufferLineChange {
+    Added,
+    Modified,
+    // lines were removed before this row
+    Removed
 }
 
 #[derive(Clone, PartialEq)]
@@ -87,7 +133,7 @@ impl TextPos {
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, SerBin, DeBin)]
 pub enum TextUndoGrouping {
     Space,
     LiveEdit(u64),
@@ -99,6 +145,7 @@ pub enum TextUndoGrouping {
     Tab,
     Cut,
     Format,
+    Remote(u64),
     Other
 }
 
@@ -109,7 +156,7 @@ impl Default for TextUndoGrouping {
 }
 
 impl TextUndoGrouping {
-    fn wants_grouping(&self) -> bool {
+    pub fn wants_grouping(&self) -> bool {
         match self {
             TextUndoGrouping::Space => true,
             TextUndoGrouping::LiveEdit(_)=>true,
@@ -120,20 +167,21 @@ impl TextUndoGrouping {
             TextUndoGrouping::Block => false,
             TextUndoGrouping::Tab => false,
             TextUndoGrouping::Format => false,
+            TextUndoGrouping::Remote(_) => true,
             TextUndoGrouping::Cut => false,
             TextUndoGrouping::Other => false
         }
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, SerBin, DeBin)]
 pub struct TextUndo {
     pub ops: Vec<TextOp>,
     pub grouping: TextUndoGrouping,
     pub cursors: TextCursorSet
 }
 
-#[derive(Clone)]
+#[derive(Clone, SerBin, DeBin)]
 pub struct TextOp {
     pub start: usize,
     pub len: usize,
@@ -161,18 +209,54 @@ impl TextBuffer {
     pub fn needs_token_chunks(&mut self) -> bool {
         if self.token_chunks_id != self.mutation_id && self.is_loaded {
             self.token_chunks_id = self.mutation_id;
-            if !self.was_invalid_pair{
-                std::mem::swap(&mut self.token_chunks, &mut self.old_token_chunks);
-                std::mem::swap(&mut self.flat_text, &mut self.old_flat_text);
-            }
+            self.token_dirty_offset = usize::max_value();
+            self.token_checkpoints.truncate(0);
             self.was_invalid_pair = false;
             self.token_chunks.truncate(0);
-            self.flat_text.truncate(0);
             return true
         }
         return false
     }
     
+    // like needs_token_chunks, but keeps the token chunks before the first edit
+    // and hands back the checkpoint to continue tokenizing from
+    pub fn resume_token_chunks(&mut self) -> TokenResume {
+        if self.token_chunks_id == self.mutation_id || !self.is_loaded {
+            return TokenResume::Current
+        }
+        self.token_chunks_id = self.mutation_id;
+        let dirty_offset = self.token_dirty_offset;
+        self.token_dirty_offset = usize::max_value();
+        // a checkpoint right at the edit is stale, the token before it peeked at the old char
+        let valid = self.token_checkpoints.iter().position( | cp | cp.offset >= dirty_offset).unwrap_or(self.token_checkpoints.len());
+        self.token_checkpoints.truncate(valid);
+        if let Some(cp) = self.token_checkpoints.last() {
+            self.token_chunks.truncate(cp.token_count);
+            // parens still open at the checkpoint lost their closing token
+            for index in &cp.pair_stack {
+                self.token_chunks[*index].pair_token = *index;
+            }
+            self.was_invalid_pair = cp.was_invalid_pair;
+            return TokenResume::Checkpoint(cp.clone())
+        }
+        self.was_invalid_pair = false;
+        self.token_chunks.truncate(0);
+        TokenResume::Start
+    }
+    
+    fn mark_dirty(&mut self, offset: usize) {
+        self.mutation_id += 1;
+        self.token_dirty_offset = self.token_dirty_offset.min(offset);
+    }
+    
+    // the chars the token chunks index, the eof token included. Built on demand for parsers
+    // that want random access, the buffer itself doesn't keep them
+    pub fn flat_text(&self) -> Vec<char> {
+        let mut chars = TextChange::lines_to_chars(&self.lines.to_vec());
+        chars.push(' ');
+        chars
+    }
+    
     pub fn scan_token_chunks_prev_line(&self, token:usize, lines:usize)->(usize, isize){
         let mut nls = 0;
         for i in (0..token).rev(){
@@ -201,44 +285,26 @@ impl TextBuffer {
 
     
     pub fn offset_to_text_pos(&self, char_offset: usize) -> TextPos {
-        let mut char_count = 0;
-        for (row, line) in self.lines.iter().enumerate() {
-            let next_char_count = char_count + line.len() + 1;
-            if next_char_count > char_offset {
-                return TextPos {row: row, col: char_offset - char_count}
-            }
-            char_count = next_char_count;
+        if let Some((row, row_offset)) = self.lines.offset_to_row(char_offset) {
+            return TextPos {row: row, col: char_offset - row_offset}
         }
         TextPos {row: self.lines.len().max(1) - 1, col: 0}
     }
     
+    // like offset_to_text_pos, old_pos being a known position at old_off before it.
+    // cursors are walked in order so the next one is often on the same row
     pub fn offset_to_text_pos_next(&self, query_off: usize, old_pos: TextPos, old_off: usize) -> TextPos {
-        let mut row = old_pos.row;
-        let mut iter_off = old_off - old_pos.col;
-        while row < self.lines.len() {
-            let line = &self.lines[row];
-            let next_off = iter_off + line.len() + 1;
-            if next_off > query_off {
-                return TextPos {row: row, col: query_off - iter_off}
-            }
-            iter_off = next_off;
-            row += 1;
+        if query_off >= old_off && old_pos.row < self.lines.len() && old_pos.col + (query_off - old_off) <= self.lines[old_pos.row].len() {
+            return TextPos {row: old_pos.row, col: old_pos.col + (query_off - old_off)}
         }
-        TextPos {row: self.lines.len().max(1) - 1, col: 0}
+        self.offset_to_text_pos(query_off)
     }
     
     pub fn text_pos_to_offset(&self, pos: TextPos) -> usize {
-        let mut char_count = 0;
         if pos.row >= self.lines.len() {
             return self.calc_char_count()
         }
-        for (ln_row, line) in self.lines.iter().enumerate() {
-            if ln_row == pos.row {
-                return char_count + (line.len()).min(pos.col);
-            }
-            char_count += line.len() + 1;
-        }
-        0
+        self.lines.row_offset(pos.row) + self.lines[pos.row].len().min(pos.col)
     }
     
     pub fn get_nearest_line_range(&self, offset: usize) -> (usize, usize) {
@@ -247,7 +313,7 @@ impl TextBuffer {
         return (offset - pos.col, line.len() + if pos.row < (line.len().max(1) - 1) {1}else {0})
     }
     
-    pub fn calc_next_line_indent_depth(&self, offset: usize, tabsize: usize) -> (usize, usize) {
+    pub fn calc_next_line_indent_depth(&self, offset: usize, rules: &IndentRules) -> (usize, usize) {
         let pos = self.offset_to_text_pos(offset);
         let line = &self.lines[pos.row];
         let mut prev_index = pos.col;
@@ -258,11 +324,11 @@ impl TextBuffer {
         let mut instep = 0;
         while prev_index > 0 {
             let prev = line[prev_index - 1];
-            if prev == ')' || prev == '}' || prev == ']' {
+            if rules.outdent_before.contains(&prev) {
                 break;
             }
-            if prev == '{' || prev == '(' || prev == '[' {
-                instep = tabsize;
+            if rules.indent_after.contains(&prev) {
+                instep = rules.tab_size;
                 break;
             }
             prev_index -= 1;
@@ -355,7 +421,32 @@ impl TextBuffer {
     }
     
     pub fn calc_char_count(&self) -> usize {
-        calc_char_count(&self.lines)
+        self.lines.char_count()
+    }
+    
+    pub fn line_hashes(&self) -> Vec<u64> {
+        self.lines.line_hashes()
+    }
+    
+    // the same hashes as line_hashes, for text that isn't in a textbuffer
+    pub fn utf8_line_hashes(utf8: &str) -> Vec<u64> {
+        TextBuffer::split_string_to_lines(utf8).iter().map( | line | TextLines::hash_line(line)).collect()
+    }
+    
+    // diffs against the line hashes of an earlier version, like the file in git HEAD
+    pub fn update_line_changes(&mut self, base: &[u64]) {
+        let lines = self.line_hashes();
+        let line_changes = &mut self.markers.line_changes;
+        line_changes.truncate(0);
+        for hunk in diff_line_hunks(base, &lines) {
+            if hunk.new_len == 0 {
+                line_changes.push((hunk.new_start, TextBufferLineChange::Removed));
+            }
+            for i in 0..hunk.new_len {
+                let change = if i < hunk.old_len {TextBufferLineChange::Modified} else {TextBufferLineChange::Added};
+                line_changes.push((hunk.new_start + i, change));
+            }
+        }
     }
     
     pub fn get_line_count(&self) -> usize {
@@ -368,8 +459,8 @@ impl TextBuffer {
     
     pub fn get_range_as_string(&self, start: usize, len: usize, ret: &mut String) {
         let mut pos = self.offset_to_text_pos(start);
+        let mut line = &self.lines[pos.row];
         for _ in 0..len {
-            let line = &self.lines[pos.row];
             if pos.col >= line.len() {
                 ret.push('\n');
                 pos.col = 0;
@@ -377,6 +468,7 @@ impl TextBuffer {
                 if pos.row >= self.lines.len() {
                     return;
                 }
+                line = &self.lines[pos.row];
             }
             else {
                 ret.push(line[pos.col]);
@@ -400,8 +492,7 @@ impl TextBuffer {
     
     pub fn get_as_string(&self) -> String {
         let mut ret = String::new();
-        for i in 0..self.lines.len() {
-            let line = &self.lines[i];
+        for (i, line) in self.lines.iter().enumerate() {
             for ch in line {
                 ret.push(*ch);
             }
@@ -425,13 +516,22 @@ impl TextBuffer {
     pub fn load_from_utf8(&mut self, utf8: &str) {
         self.is_loaded = true;
         self.is_crlf = !utf8.find("\r\n").is_none();
-        self.lines = TextBuffer::split_string_to_lines(utf8);
-        self.mutation_id += 1;
+        let lines = TextLines::from_lines(TextBuffer::split_string_to_lines(utf8));
+        if let (Some(change_log), None) = (&mut self.change_log, &self.change_log_base) {
+            let old = TextChange::lines_to_chars(&self.lines.to_vec());
+            change_log.push(TextChange::between(&old, &TextChange::lines_to_chars(&lines.to_vec())));
+        }
+        self.lines = lines;
+        self.mark_dirty(0);
     }
     
     pub fn replace_line(&mut self, row: usize, start_col: usize, len: usize, rep_line: Vec<char>) -> Vec<char> {
-        self.mutation_id += 1;
-        self.lines[row].splice(start_col..(start_col + len), rep_line).collect()
+        let start = self.text_pos_to_offset(TextPos {row: row, col: start_col});
+        if self.change_log.is_some() {
+            self.log_change(start, len, &rep_line);
+        }
+        self.mark_dirty(start);
+        self.lines.splice_line(row, start_col, start_col + len, rep_line)
     }
     
     pub fn copy_line(&self, row: usize, start_col: usize, len: usize) -> Vec<char> {
@@ -447,10 +547,40 @@ impl TextBuffer {
         }
     }
     
+    fn log_change(&mut self, start: usize, len: usize, inserted: &[char]) {
+        if self.change_log.is_none() || self.change_log_base.is_some() {
+            return
+        }
+        let doc_len = self.lines.char_count();
+        if start + len > doc_len {
+            println!("TextBuffer - edit {}..{} is outside the text of {} chars, resyncing the change log", start, start + len, doc_len);
+            self.change_log_base = Some(TextChange::lines_to_chars(&self.lines.to_vec()));
+            return
+        }
+        if let Some(change_log) = &mut self.change_log {
+            change_log.push(TextChange::replace(doc_len, start, len, inserted.to_vec()));
+        }
+    }
+    
+    // the changes since the last call, ending at the current text
+    pub fn take_change_log(&mut self) -> Vec<TextChange> {
+        let mut changes = match &mut self.change_log {
+            Some(change_log) => std::mem::replace(change_log, Vec::new()),
+            None => return Vec::new()
+        };
+        if let Some(base) = self.change_log_base.take() {
+            changes.push(TextChange::between(&base, &TextChange::lines_to_chars(&self.lines.to_vec())));
+        }
+        changes
+    }
+    
     pub fn replace_range(&mut self, start: usize, len: usize, mut rep_lines: Vec<Vec<char>>) -> Vec<Vec<char>> {
-        self.mutation_id += 1;
+        if self.change_log.is_some() {
+            self.log_change(start, len, &TextChange::lines_to_chars(&rep_lines));
+        }
         let start_pos = self.offset_to_text_pos(start);
-        let end_pos = self.offset_to_text_pos_next(start + len, start_pos, start);
+        let end_pos = self.offset_to_text_pos(start + len);
+        self.mark_dirty(start);
         
         if start_pos.row == end_pos.row && rep_lines.len() == 1 { // replace in one line
             let rep_line_zero = rep_lines.drain(0..1).next().unwrap();
@@ -458,72 +588,39 @@ impl TextBuffer {
             if start_pos.col>end_pos.col {
                 return vec![];
             }
-            let line = self.lines[start_pos.row].splice(start_pos.col..end_pos.col, rep_line_zero).collect();
+            let line = self.lines.splice_line(start_pos.row, start_pos.col, end_pos.col, rep_line_zero);
             return vec![line];
         }
+        // cut off whatever follows the range on the last line, it goes behind the last replacement line
+        let end_line_len = self.lines[end_pos.row].len();
+        let last_bit = self.lines.splice_line(end_pos.row, end_pos.col, end_line_len, Vec::new());
+        
+        // take out the range itself
+        let old_lines = if start_pos.row == end_pos.row {
+            vec![self.lines.splice_line(start_pos.row, start_pos.col, end_pos.col, Vec::new())]
+        }
         else {
-            if rep_lines.len() == 1 { // we are replacing multiple lines with one line
-                // drain first line
-                let rep_line_zero = rep_lines.drain(0..1).next().unwrap();
-                
-                // replace it in the first
-                let first = self.lines[start_pos.row].splice(start_pos.col.., rep_line_zero).collect();
-                
-                // collect the middle ones
-                let mut middle: Vec<Vec<char>> = self.lines.drain((start_pos.row + 1)..(end_pos.row)).collect();
-                
-                // cut out the last bit
-                let last: Vec<char> = self.lines[start_pos.row + 1].drain(0..end_pos.col).collect();
-                
-                // last line bit
-                let mut last_line = self.lines.drain((start_pos.row + 1)..(start_pos.row + 2)).next().unwrap();
-                
-                // merge start_row+1 into start_row
-                self.lines[start_pos.row].append(&mut last_line);
-                
-                // concat it all together
-                middle.insert(0, first);
-                middle.push(last);
-                
-                return middle
-            }
-            else if start_pos.row == end_pos.row { // replacing single line with multiple lines
-                let mut last_bit: Vec<char> = self.lines[start_pos.row].drain(end_pos.col..).collect();
-                // but we have co drain end_col..
-                
-                // replaced first line
-                let rep_lines_len = rep_lines.len();
-                let rep_line_first: Vec<char> = rep_lines.drain(0..1).next().unwrap();
-                let line = self.lines[start_pos.row].splice(start_pos.col.., rep_line_first).collect();
-                
-                // splice in middle rest
-                let rep_line_mid = rep_lines.drain(0..(rep_lines.len()));
-                self.lines.splice((start_pos.row + 1)..(start_pos.row + 1), rep_line_mid);
-                
-                // append last bit
-                self.lines[start_pos.row + rep_lines_len - 1].append(&mut last_bit);
-                
-                return vec![line];
-            }
-            else { // replaceing multiple lines with multiple lines
-                // drain and replace last line
-                let rep_line_last = rep_lines.drain((rep_lines.len() - 1)..(rep_lines.len())).next().unwrap();
-                let last = self.lines[end_pos.row].splice(..end_pos.col, rep_line_last).collect();
-                
-                // swap out middle lines and drain them
-                let rep_line_mid = rep_lines.drain(1..(rep_lines.len()));
-                let mut middle: Vec<Vec<char>> = self.lines.splice((start_pos.row + 1)..end_pos.row, rep_line_mid).collect();
-                
-                // drain and replace first line
-                let rep_line_zero = rep_lines.drain(0..1).next().unwrap();
-                let first = self.lines[start_pos.row].splice(start_pos.col.., rep_line_zero).collect();
-                
-                // concat it all together
-                middle.insert(0, first);
-                middle.push(last);
-                return middle
-            }
+            let last = self.lines.splice_line(end_pos.row, 0, end_pos.col, Vec::new());
+            let mut middle = self.lines.splice_lines(start_pos.row + 1, end_pos.row + 1, Vec::new());
+            middle.pop();
+            let start_line_len = self.lines[start_pos.row].len();
+            let first = self.lines.splice_line(start_pos.row, start_pos.col, start_line_len, Vec::new());
+            middle.insert(0, first);
+            middle.push(last);
+            middle
+        };
+        
+        // and put in the replacement
+        let rep_lines_len = rep_lines.len();
+        let rep_line_zero = rep_lines.drain(0..1).next().unwrap();
+        self.lines.splice_line(start_pos.row, start_pos.col, start_pos.col, rep_line_zero);
+        if rep_lines.len() > 0 {
+            self.lines.splice_lines(start_pos.row + 1, start_pos.row + 1, rep_lines);
         }
+        let last_row = start_pos.row + rep_lines_len - 1;
+        let last_len = self.lines[last_row].len();
+        self.lines.splice_line(last_row, last_len, last_len, last_bit);
+        old_lines
     }
     
     pub fn replace_lines(&mut self, start_row: usize, end_row: usize, rep_lines: Vec<Vec<char>>) -> TextOp {
@@ -602,27 +699,42 @@ impl TextBuffer {
         text_undo_inverse
     }
     
-    // todo make more reuse in these functions
+    // a new edit, when we are not at the newest state this starts a branch
+    pub fn push_undo(&mut self, text_undo: TextUndo) {
+        self.undo_tree.add_child(text_undo, undo_time_now());
+    }
+    
+    fn undo_step_up(&mut self, cursor_set: &mut TextCursorSet) {
+        let node = self.undo_tree.current;
+        let text_undo = self.undo_tree.nodes[node].edit.take().unwrap();
+        let text_redo = self.undoredo(text_undo, cursor_set);
+        let parent = self.undo_tree.nodes[node].parent;
+        self.undo_tree.nodes[node].edit = Some(text_redo);
+        self.undo_tree.nodes[parent].redo_child = Some(node);
+        self.undo_tree.current = parent;
+    }
+    
+    fn undo_step_down(&mut self, child: usize, cursor_set: &mut TextCursorSet) {
+        let text_redo = self.undo_tree.nodes[child].edit.take().unwrap();
+        let text_undo = self.undoredo(text_redo, cursor_set);
+        self.undo_tree.nodes[child].edit = Some(text_undo);
+        let current = self.undo_tree.current;
+        self.undo_tree.nodes[current].redo_child = Some(child);
+        self.undo_tree.current = child;
+    }
+    
     pub fn undo(&mut self, grouped: bool, cursor_set: &mut TextCursorSet) {
-        
-        if self.undo_stack.len() == 0 {
-            return;
-        }
-        let mut last_grouping = TextUndoGrouping::Other;
-        let mut first = true;
-        while self.undo_stack.len() > 0 {
-            if !first && !grouped {
-                break
-            }
-            if self.undo_stack.last().unwrap().grouping != last_grouping && !first {
-                break
+        let mut last_grouping = None;
+        while self.undo_tree.can_undo() {
+            let grouping = self.undo_tree.grouping(self.undo_tree.current).cloned();
+            if let Some(last_grouping) = &last_grouping {
+                if !grouped || grouping.as_ref() != Some(last_grouping) {
+                    break
+                }
             }
-            first = false;
-            let text_undo = self.undo_stack.pop().unwrap();
-            let wants_grouping = text_undo.grouping.wants_grouping();
-            last_grouping = text_undo.grouping.clone();
-            let text_redo = self.undoredo(text_undo, cursor_set);
-            self.redo_stack.push(text_redo);
+            let wants_grouping = grouping.as_ref().map_or(false, | grouping | grouping.wants_grouping());
+            last_grouping = grouping;
+            self.undo_step_up(cursor_set);
             if !wants_grouping {
                 break;
             }
@@ -630,29 +742,162 @@ impl TextBuffer {
     }
     
     pub fn redo(&mut self, grouped: bool, cursor_set: &mut TextCursorSet) {
-        if self.redo_stack.len() == 0 {
-            return;
-        }
-        let mut last_grouping = TextUndoGrouping::Other;
-        let mut first = true;
-        while self.redo_stack.len() > 0 {
-            if !first {
-                if self.redo_stack.last().unwrap().grouping != last_grouping || !grouped {
+        let mut last_grouping = None;
+        while let Some(child) = self.undo_tree.nodes[self.undo_tree.current].redo_child {
+            let grouping = self.undo_tree.grouping(child).cloned();
+            if let Some(last_grouping) = &last_grouping {
+                if !grouped || grouping.as_ref() != Some(last_grouping) {
                     break
                 }
             }
-            first = false;
-            let text_redo = self.redo_stack.pop().unwrap();
-            let wants_grouping = text_redo.grouping.wants_grouping();
-            last_grouping = text_redo.grouping.clone();
-            let text_undo = self.undoredo(text_redo, cursor_set);
-            self.undo_stack.push(text_undo);
+            let wants_grouping = grouping.as_ref().map_or(false, | grouping | grouping.wants_grouping());
+            last_grouping = grouping;
+            self.undo_step_down(child, cursor_set);
             if !wants_grouping {
                 break;
             }
         }
     }
     
+    // travels to any state in the undo tree, through the closest common state
+    pub fn undo_jump(&mut self, target: usize, cursor_set: &mut TextCursorSet) {
+        if target >= self.undo_tree.nodes.len() {
+            return
+        }
+        let (up, down) = self.undo_tree.route_to(target);
+        for _ in up {
+            self.undo_step_up(cursor_set);
+        }
+        for child in down {
+            self.undo_step_down(child, cursor_set);
+        }
+    }
+    
+    // a change made by a collaborator. It stays off the undo tree, instead every state in the tree
+    // gets it too, so undo and redo only take back our own edits
+    pub fn apply_remote_change(&mut self, change: &TextChange) {
+        let change_log = self.change_log.take();
+        let mut pos = 0;
+        for op in &change.ops {
+            match op {
+                TextChangeOp::Retain(n) => pos += n,
+                TextChangeOp::Insert(chars) => {
+                    self.replace_range(pos, 0, TextChange::chars_to_lines(chars));
+                    pos += chars.len();
+                },
+                TextChangeOp::Delete(n) => {
+                    self.replace_range(pos, *n, vec![vec![]]);
+                }
+            }
+        }
+        self.change_log = change_log;
+        self.transform_undo_tree(change);
+        self.remote_changes.push(change.clone());
+        if self.remote_changes.len() > 64 {
+            self.remote_changes.remove(0);
+        }
+        self.remote_change_count += 1;
+    }
+    
+    // an undo edit as one change set on a text of doc_len chars, its ops apply last to first
+    fn undo_to_change(text_undo: &TextUndo, doc_len: usize) -> TextChange {
+        let mut change = TextChange::new();
+        change.retain(doc_len);
+        for op in text_undo.ops.iter().rev() {
+            let step = TextChange::replace(change.len_after, op.start, op.len, TextChange::lines_to_chars(&op.lines));
+            change = change.compose(&step);
+        }
+        change
+    }
+    
+    // and back, every op at its offset before the change so applying them last to first works out
+    fn change_to_undo_ops(change: &TextChange) -> Vec<TextOp> {
+        let mut ops = Vec::new();
+        let mut pos = 0;
+        let mut iter = change.ops.iter().peekable();
+        while let Some(op) = iter.next() {
+            match op {
+                TextChangeOp::Retain(n) => pos += n,
+                TextChangeOp::Insert(chars) => {
+                    let len = if let Some(TextChangeOp::Delete(n)) = iter.peek() {*n} else {0};
+                    if len > 0 {
+                        iter.next();
+                    }
+                    ops.push(TextOp {start: pos, len: len, lines: TextChange::chars_to_lines(chars)});
+                    pos += len;
+                },
+                TextChangeOp::Delete(n) => {
+                    ops.push(TextOp {start: pos, len: *n, lines: vec![vec![]]});
+                    pos += n;
+                }
+            }
+        }
+        ops
+    }
+    
+    fn transform_undo(text_undo: &mut TextUndo, doc_len: usize, remote: &TextChange) -> (TextChange, TextChange) {
+        let edit = Self::undo_to_change(text_undo, doc_len);
+        let (edit_t, remote_t) = TextChange::transform(&edit, remote);
+        text_undo.ops = Self::change_to_undo_ops(&edit_t);
+        // the cursors are where the edit leaves them
+        for cursor in &mut text_undo.cursors.set {
+            cursor.head = remote_t.transform_offset(cursor.head, false);
+            cursor.tail = remote_t.transform_offset(cursor.tail, false);
+        }
+        (edit, remote_t)
+    }
+    
+    // the remote change is on the current state. Going up the path to the root each undo edit is
+    // transformed over it and it over the edit, which gives it on the parent. Branches off the path
+    // hold the edit from their parent, going down them works the same way
+    fn transform_undo_tree(&mut self, change: &TextChange) {
+        let tree = &mut self.undo_tree;
+        let path = tree.path_to(tree.current);
+        let mut remote = change.clone();
+        let mut doc_len = change.len_before;
+        let mut branches = Vec::new();
+        for (index, node) in path.iter().enumerate().rev() {
+            let on_path = path.get(index + 1).cloned();
+            for child in &tree.nodes[*node].children {
+                if Some(*child) != on_path {
+                    branches.push((*child, doc_len, remote.clone()));
+                }
+            }
+            if *node == 0 {
+                break;
+            }
+            if let Some(edit) = &mut tree.nodes[*node].edit {
+                let (edit, remote_t) = Self::transform_undo(edit, doc_len, &remote);
+                doc_len = edit.len_after;
+                remote = remote_t;
+            }
+        }
+        while let Some((node, doc_len, remote)) = branches.pop() {
+            if let Some(edit) = &mut tree.nodes[node].edit {
+                let (edit, remote_t) = Self::transform_undo(edit, doc_len, &remote);
+                for child in &tree.nodes[node].children {
+                    branches.push((*child, edit.len_after, remote_t.clone()));
+                }
+            }
+        }
+    }
+    
+    // the text at a state in the undo tree, without moving there
+    pub fn lines_at_undo_node(&self, target: usize) -> TextLines {
+        let mut scratch = TextBuffer {
+            lines: self.lines.clone(),
+            ..TextBuffer::default()
+        };
+        let mut cursor_set = TextCursorSet::new();
+        let (up, down) = self.undo_tree.route_to(target);
+        for node in up.into_iter().chain(down.into_iter()) {
+            if let Some(edit) = &self.undo_tree.nodes[node].edit {
+                scratch.undoredo(edit.clone(), &mut cursor_set);
+            }
+        }
+        scratch.lines
+    }
+    
 }
 
 pub struct LineTokenizer<'a> {
@@ -731,4 +976,98 @@ impl<'a> LineTokenizer<'a> {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edit(text_buffer: &mut TextBuffer, start: usize, len: usize, string: &str) {
+        let op = text_buffer.replace_lines_with_string(start, len, string);
+        text_buffer.push_undo(TextUndo {ops: vec![op], grouping: TextUndoGrouping::Other, cursors: TextCursorSet::new()});
+    }
+
+    fn remote(text_buffer: &mut TextBuffer, start: usize, len: usize, string: &str) {
+        let doc_len = text_buffer.lines.char_count();
+        text_buffer.apply_remote_change(&TextChange::replace(doc_len, start, len, string.chars().collect()));
+    }
+
+    #[test]
+    fn undo_skips_remote_changes() {
+        let mut text_buffer = TextBuffer::default();
+        let mut cursors = TextCursorSet::new();
+        text_buffer.load_from_utf8("hello world");
+        edit(&mut text_buffer, 5, 0, ",");
+        remote(&mut text_buffer, 0, 0, "oh ");
+        edit(&mut text_buffer, 10, 1, "W");
+        remote(&mut text_buffer, 12, 2, "\nthere");
+        assert_eq!(text_buffer.get_as_string(), "oh hello, Wo\nthered");
+        text_buffer.undo(false, &mut cursors);
+        assert_eq!(text_buffer.get_as_string(), "oh hello, wo\nthered");
+        text_buffer.undo(false, &mut cursors);
+        assert_eq!(text_buffer.get_as_string(), "oh hello wo\nthered");
+        text_buffer.redo(false, &mut cursors);
+        text_buffer.redo(false, &mut cursors);
+        assert_eq!(text_buffer.get_as_string(), "oh hello, Wo\nthered");
+    }
+
+    #[test]
+    fn undo_branches_get_remote_changes() {
+        let mut text_buffer = TextBuffer::default();
+        let mut cursors = TextCursorSet::new();
+        text_buffer.load_from_utf8("abc");
+        edit(&mut text_buffer, 3, 0, "d");
+        text_buffer.undo(false, &mut cursors);
+        edit(&mut text_buffer, 0, 1, "A");
+        remote(&mut text_buffer, 1, 1, "");
+        assert_eq!(text_buffer.get_as_string(), "Ac");
+        let branch = 1;
+        text_buffer.undo_jump(branch, &mut cursors);
+        assert_eq!(text_buffer.get_as_string(), "acd");
+    }
+
+    #[test]
+    fn out_of_range_edits_resync_the_change_log() {
+        let mut text_buffer = TextBuffer::default();
+        text_buffer.load_from_utf8("abc");
+        text_buffer.change_log = Some(Vec::new());
+        text_buffer.replace_lines_with_string(1, 1, "X");
+        text_buffer.log_change(10, 1, &[]);
+        text_buffer.replace_lines_with_string(0, 0, "Y");
+        let mut chars: Vec<char> = "abc".chars().collect();
+        for change in text_buffer.take_change_log() {
+            chars = change.apply_to_chars(&chars);
+        }
+        assert_eq!(chars.into_iter().collect::<String>(), "YaXc");
+        assert!(text_buffer.change_log_base.is_none());
+    }
+
+    fn tokens(text_buffer: &TextBuffer) -> Vec<(usize, usize, TokenType, char)> {
+        text_buffer.token_chunks.iter().map( | tok | (tok.offset, tok.len, tok.token_type, tok.next)).collect()
+    }
 
+    #[test]
+    fn long_rows_retokenize_from_mid_row_checkpoints() {
+        let language = PlainLanguage {};
+        let text = format!("{}\nlast row", "word ".repeat(3000));
+        let mut text_buffer = TextBuffer::default();
+        text_buffer.load_from_utf8(&text);
+        language.update_token_chunks(&mut text_buffer, &mut | _ | ());
+        assert!(text_buffer.token_checkpoints.iter().any( | cp | cp.row == 0 && cp.col > 0));
+        
+        text_buffer.replace_lines_with_string(14000, 4, "x y");
+        let mut retokenized = 0;
+        language.update_token_chunks(&mut text_buffer, &mut | _ | retokenized += 1);
+        assert!(retokenized < text_buffer.token_chunks.len() / 4);
+        
+        let mut fresh = TextBuffer::default();
+        fresh.load_from_utf8(&text_buffer.get_as_string());
+        language.update_token_chunks(&mut fresh, &mut | _ | ());
+        assert_eq!(tokens(&text_buffer), tokens(&fresh));
+        
+        let mut reader = TextLinesReader::new(&text_buffer.lines);
+        let mut chars = Vec::new();
+        reader.chars_into(14000, 3, &mut chars);
+        assert_eq!(chars, vec!['x', ' ', 'y']);
+        reader.chars_into(text_buffer.lines.char_count() - 3, 5, &mut chars);
+        assert_eq!(chars, vec!['r', 'o', 'w', ' ', ' ']);
+    }
+}
@@ -0,0 +1,337 @@
+use makepad_microserde::*;
+
+// A change set over the chars of a buffer, newlines count as one char like the buffer offsets.
+// Unlike a TextOp it can be transformed against a concurrent change and composed with a later one,
+// which is what collaborative editing needs to get every copy of a buffer to the same text.
+
+#[derive(Clone, Debug, PartialEq, SerBin, DeBin)]
+pub enum TextChangeOp {
+    Retain(usize),
+    Insert(Vec<char>),
+    Delete(usize),
+}
+
+#[derive(Clone, Debug, Default, PartialEq, SerBin, DeBin)]
+pub struct TextChange {
+    pub ops: Vec<TextChangeOp>,
+    pub len_before: usize,
+    pub len_after: usize,
+}
+
+impl TextChange {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // replaces len chars at start in a text of doc_len chars
+    pub fn replace(doc_len: usize, start: usize, len: usize, inserted: Vec<char>) -> Self {
+        let mut change = TextChange::new();
+        change.retain(start);
+        change.insert(inserted);
+        change.delete(len);
+        change.retain(doc_len - start - len);
+        change
+    }
+
+    // from old to new as one replace of the part between their common start and end
+    pub fn between(old: &[char], new: &[char]) -> Self {
+        let mut head = 0;
+        while head < old.len() && head < new.len() && old[head] == new[head] {
+            head += 1;
+        }
+        let mut tail = 0;
+        while tail < old.len() - head && tail < new.len() - head && old[old.len() - 1 - tail] == new[new.len() - 1 - tail] {
+            tail += 1;
+        }
+        Self::replace(old.len(), head, old.len() - head - tail, new[head..new.len() - tail].to_vec())
+    }
+
+    pub fn lines_to_chars(lines: &[Vec<char>]) -> Vec<char> {
+        let mut chars = Vec::new();
+        for (index, line) in lines.iter().enumerate() {
+            if index > 0 {
+                chars.push('\n');
+            }
+            chars.extend_from_slice(line);
+        }
+        chars
+    }
+
+    pub fn chars_to_lines(chars: &[char]) -> Vec<Vec<char>> {
+        chars.split( | ch | *ch == '\n').map( | line | line.to_vec()).collect()
+    }
+
+    pub fn is_noop(&self) -> bool {
+        self.ops.iter().all( | op | if let TextChangeOp::Retain(_) = op {true} else {false})
+    }
+
+    pub fn retain(&mut self, n: usize) {
+        if n == 0 {
+            return
+        }
+        self.len_before += n;
+        self.len_after += n;
+        if let Some(TextChangeOp::Retain(last)) = self.ops.last_mut() {
+            *last += n;
+            return
+        }
+        self.ops.push(TextChangeOp::Retain(n));
+    }
+
+    // an insert next to a delete always goes first, so equal changes have equal ops
+    pub fn insert(&mut self, chars: Vec<char>) {
+        if chars.len() == 0 {
+            return
+        }
+        self.len_after += chars.len();
+        let len = self.ops.len();
+        if let Some(TextChangeOp::Insert(last)) = self.ops.last_mut() {
+            last.extend(chars);
+            return
+        }
+        if let Some(TextChangeOp::Delete(_)) = self.ops.last() {
+            if len > 1 {
+                if let TextChangeOp::Insert(prev) = &mut self.ops[len - 2] {
+                    prev.extend(chars);
+                    return
+                }
+            }
+            self.ops.insert(len - 1, TextChangeOp::Insert(chars));
+            return
+        }
+        self.ops.push(TextChangeOp::Insert(chars));
+    }
+
+    pub fn delete(&mut self, n: usize) {
+        if n == 0 {
+            return
+        }
+        self.len_before += n;
+        if let Some(TextChangeOp::Delete(last)) = self.ops.last_mut() {
+            *last += n;
+            return
+        }
+        self.ops.push(TextChangeOp::Delete(n));
+    }
+
+    pub fn apply_to_chars(&self, chars: &[char]) -> Vec<char> {
+        let mut out = Vec::with_capacity(self.len_after);
+        let mut pos = 0;
+        for op in &self.ops {
+            match op {
+                TextChangeOp::Retain(n) => {
+                    out.extend_from_slice(&chars[pos..pos + n]);
+                    pos += n;
+                },
+                TextChangeOp::Insert(ins) => out.extend_from_slice(ins),
+                TextChangeOp::Delete(n) => pos += n,
+            }
+        }
+        out
+    }
+
+    // where an offset ends up, an insert right at the offset pushes it along when push_on_insert
+    pub fn transform_offset(&self, offset: usize, push_on_insert: bool) -> usize {
+        let mut old = 0;
+        let mut new = 0;
+        for op in &self.ops {
+            match op {
+                TextChangeOp::Retain(n) => {
+                    if offset < old + n {
+                        return new + offset - old
+                    }
+                    old += n;
+                    new += n;
+                },
+                TextChangeOp::Insert(ins) => {
+                    if old == offset && !push_on_insert {
+                        return new
+                    }
+                    new += ins.len();
+                },
+                TextChangeOp::Delete(n) => {
+                    if offset < old + n {
+                        return new
+                    }
+                    old += n;
+                }
+            }
+        }
+        new + offset.saturating_sub(old)
+    }
+
+    // a change doing self and then other
+    pub fn compose(&self, other: &TextChange) -> TextChange {
+        let mut out = TextChange::new();
+        let mut a = OpIter::new(&self.ops);
+        let mut b = OpIter::new(&other.ops);
+        loop {
+            match (a.peek(), b.peek()) {
+                (None, None) => break,
+                (Some(TextChangeOp::Delete(n)), _) => {
+                    out.delete(*n);
+                    a.next();
+                },
+                (_, Some(TextChangeOp::Insert(ins))) => {
+                    out.insert(ins.clone());
+                    b.next();
+                },
+                (Some(op_a), Some(op_b)) => {
+                    let n = a.remaining().min(b.remaining());
+                    match (op_a, op_b) {
+                        (TextChangeOp::Retain(_), TextChangeOp::Retain(_)) => out.retain(n),
+                        (TextChangeOp::Insert(ins), TextChangeOp::Retain(_)) => out.insert(ins[a.offset..a.offset + n].to_vec()),
+                        (TextChangeOp::Retain(_), TextChangeOp::Delete(_)) => out.delete(n),
+                        // text inserted by self and deleted by other never was
+                        _ => ()
+                    }
+                    a.advance(n);
+                    b.advance(n);
+                },
+                _ => panic!("TextChange::compose on changes of different lengths")
+            }
+        }
+        out
+    }
+
+    // changes a and b were made on the same text, gives a' to apply after b and b' to apply after a
+    // so both orders end at the same text. at the same position the insert of a goes first.
+    pub fn transform(a: &TextChange, b: &TextChange) -> (TextChange, TextChange) {
+        let mut a_out = TextChange::new();
+        let mut b_out = TextChange::new();
+        let mut a_iter = OpIter::new(&a.ops);
+        let mut b_iter = OpIter::new(&b.ops);
+        loop {
+            match (a_iter.peek(), b_iter.peek()) {
+                (None, None) => break,
+                (Some(TextChangeOp::Insert(ins)), _) => {
+                    let ins = ins[a_iter.offset..].to_vec();
+                    b_out.retain(ins.len());
+                    a_out.insert(ins);
+                    a_iter.next();
+                },
+                (_, Some(TextChangeOp::Insert(ins))) => {
+                    let ins = ins[b_iter.offset..].to_vec();
+                    a_out.retain(ins.len());
+                    b_out.insert(ins);
+                    b_iter.next();
+                },
+                (Some(op_a), Some(op_b)) => {
+                    let n = a_iter.remaining().min(b_iter.remaining());
+                    match (op_a, op_b) {
+                        (TextChangeOp::Retain(_), TextChangeOp::Retain(_)) => {
+                            a_out.retain(n);
+                            b_out.retain(n);
+                        },
+                        (TextChangeOp::Delete(_), TextChangeOp::Retain(_)) => a_out.delete(n),
+                        (TextChangeOp::Retain(_), TextChangeOp::Delete(_)) => b_out.delete(n),
+                        // both deleted it
+                        _ => ()
+                    }
+                    a_iter.advance(n);
+                    b_iter.advance(n);
+                },
+                _ => panic!("TextChange::transform on changes of different lengths")
+            }
+        }
+        (a_out, b_out)
+    }
+}
+
+fn op_len(op: &TextChangeOp) -> usize {
+    match op {
+        TextChangeOp::Retain(n) | TextChangeOp::Delete(n) => *n,
+        TextChangeOp::Insert(ins) => ins.len()
+    }
+}
+
+// walks ops, splitting them where the other side has a shorter op
+struct OpIter<'a> {
+    ops: &'a [TextChangeOp],
+    index: usize,
+    offset: usize,
+}
+
+impl<'a> OpIter<'a> {
+    fn new(ops: &'a [TextChangeOp]) -> Self {
+        OpIter {ops: ops, index: 0, offset: 0}
+    }
+
+    fn peek(&self) -> Option<&'a TextChangeOp> {
+        self.ops.get(self.index)
+    }
+
+    fn remaining(&self) -> usize {
+        op_len(&self.ops[self.index]) - self.offset
+    }
+
+    fn next(&mut self) {
+        self.index += 1;
+        self.offset = 0;
+    }
+
+    fn advance(&mut self, n: usize) {
+        self.offset += n;
+        if self.offset >= op_len(&self.ops[self.index]) {
+            self.next();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // every insert and delete on a short text, single chars and runs
+    fn all_changes(doc_len: usize) -> Vec<TextChange> {
+        let mut changes = Vec::new();
+        for start in 0..=doc_len {
+            changes.push(TextChange::replace(doc_len, start, 0, vec!['x']));
+            changes.push(TextChange::replace(doc_len, start, 0, vec!['y', 'z']));
+            for len in 1..=doc_len - start {
+                changes.push(TextChange::replace(doc_len, start, len, vec![]));
+                changes.push(TextChange::replace(doc_len, start, len, vec!['w']));
+            }
+        }
+        changes
+    }
+
+    #[test]
+    fn concurrent_changes_converge() {
+        let text: Vec<char> = "abcd".chars().collect();
+        let changes = all_changes(text.len());
+        for a in &changes {
+            for b in &changes {
+                let (a_t, b_t) = TextChange::transform(a, b);
+                let ab = b_t.apply_to_chars(&a.apply_to_chars(&text));
+                let ba = a_t.apply_to_chars(&b.apply_to_chars(&text));
+                assert_eq!(ab, ba, "{:?} against {:?}", a, b);
+                assert_eq!(a.compose(&b_t).apply_to_chars(&text), ab);
+                assert_eq!(b.compose(&a_t).apply_to_chars(&text), ba);
+            }
+        }
+    }
+
+    #[test]
+    fn compose_is_sequential_application() {
+        let text: Vec<char> = "abc".chars().collect();
+        for a in &all_changes(text.len()) {
+            let after_a = a.apply_to_chars(&text);
+            for b in &all_changes(after_a.len()) {
+                let composed = a.compose(b);
+                assert_eq!(composed.apply_to_chars(&text), b.apply_to_chars(&after_a), "{:?} then {:?}", a, b);
+                assert_eq!(composed.len_before, text.len());
+                assert_eq!(composed.len_after, b.len_after);
+            }
+        }
+    }
+
+    #[test]
+    fn ties_put_the_first_insert_first() {
+        let text: Vec<char> = "ab".chars().collect();
+        let a = TextChange::replace(2, 1, 0, vec!['x']);
+        let b = TextChange::replace(2, 1, 0, vec!['y']);
+        let (_, b_t) = TextChange::transform(&a, &b);
+        assert_eq!(b_t.apply_to_chars(&a.apply_to_chars(&text)), "axyb".chars().collect::<Vec<char>>());
+    }
+}
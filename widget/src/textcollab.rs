@@ -0,0 +1,192 @@
+use makepad_microserde::*;
+use crate::textchange::*;
+use crate::textcursor::*;
+
+// One copy of a buffer that is edited together with other copies. Changes are broadcast and every
+// copy receives all of them, its own included, in one order. Each copy replays that order the same
+// way: a change made at revision rev is transformed over the changes that came in since rev, and the
+// result is appended to the history. So the history and the text it gives are the same everywhere.
+// A copy keeps at most one change in flight, edits made meanwhile wait in buffer until it comes back.
+
+#[derive(Clone, SerBin, DeBin)]
+pub struct TextCollabEntry {
+    pub site: u64,
+    pub change: TextChange,
+}
+
+// what a copy joining late starts from
+#[derive(Clone, SerBin, DeBin)]
+pub struct TextCollabState {
+    pub text: Vec<char>,
+    pub history: Vec<TextCollabEntry>,
+}
+
+#[derive(Clone)]
+pub struct TextCollabPeer {
+    pub site: u64,
+    // in the coordinates of our local text
+    pub cursors: Vec<TextCursor>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TextCollabSend {
+    Change {rev: usize, change: TextChange},
+    Cursors {rev: usize, cursors: Vec<(usize, usize)>},
+}
+
+#[derive(Clone)]
+pub struct TextCollab {
+    pub site: u64,
+    pub history: Vec<TextCollabEntry>,
+    // the text at the end of the history
+    pub text: Vec<char>,
+    pub pending: Option<TextChange>,
+    pub pending_rev: usize,
+    pub buffer: Option<TextChange>,
+    pub cursors: Vec<(usize, usize)>,
+    pub cursors_unsent: bool,
+    pub peers: Vec<TextCollabPeer>,
+}
+
+impl TextCollab {
+    pub fn new(site: u64, text: Vec<char>) -> Self {
+        Self::from_state(site, TextCollabState {text: text, history: Vec::new()})
+    }
+
+    pub fn from_state(site: u64, state: TextCollabState) -> Self {
+        TextCollab {
+            site: site,
+            history: state.history,
+            text: state.text,
+            pending: None,
+            pending_rev: 0,
+            buffer: None,
+            cursors: Vec::new(),
+            cursors_unsent: false,
+            peers: Vec::new(),
+        }
+    }
+
+    pub fn state(&self) -> TextCollabState {
+        TextCollabState {text: self.text.clone(), history: self.history.clone()}
+    }
+
+    pub fn rev(&self) -> usize {
+        self.history.len()
+    }
+
+    // keeps our unsent cursors and those of the peers on the same text as our copy
+    fn transform_cursors(&mut self, change: &TextChange, local: bool) {
+        for (head, tail) in &mut self.cursors {
+            *head = change.transform_offset(*head, local);
+            *tail = change.transform_offset(*tail, local);
+        }
+        for peer in &mut self.peers {
+            for cursor in &mut peer.cursors {
+                cursor.head = change.transform_offset(cursor.head, false);
+                cursor.tail = change.transform_offset(cursor.tail, false);
+            }
+        }
+    }
+
+    // an edit made to our copy
+    pub fn local_change(&mut self, change: TextChange) -> Option<TextCollabSend> {
+        if change.is_noop() {
+            return None
+        }
+        self.transform_cursors(&change, true);
+        if self.pending.is_none() {
+            self.pending = Some(change.clone());
+            self.pending_rev = self.rev();
+            return Some(TextCollabSend::Change {rev: self.pending_rev, change: change})
+        }
+        self.buffer = Some(match self.buffer.take() {
+            Some(buffer) => buffer.compose(&change),
+            None => change
+        });
+        None
+    }
+
+    // cursors are only sent while nothing is in flight, then their revision says where they are
+    pub fn local_cursors(&mut self, cursors: &[TextCursor]) -> Option<TextCollabSend> {
+        self.cursors = cursors.iter().map( | cursor | (cursor.head, cursor.tail)).collect();
+        self.cursors_unsent = true;
+        self.send_cursors()
+    }
+
+    fn send_cursors(&mut self) -> Option<TextCollabSend> {
+        if !self.cursors_unsent || self.pending.is_some() || self.buffer.is_some() {
+            return None
+        }
+        self.cursors_unsent = false;
+        Some(TextCollabSend::Cursors {rev: self.rev(), cursors: self.cursors.clone()})
+    }
+
+    // a change in broadcast order, returns what to apply to our copy and what to send next
+    pub fn receive_change(&mut self, site: u64, rev: usize, change: TextChange) -> (Option<TextChange>, Option<TextCollabSend>) {
+        let mut change = change;
+        for entry in &self.history[rev.min(self.history.len())..] {
+            change = TextChange::transform(&entry.change, &change).1;
+        }
+        self.text = change.apply_to_chars(&self.text);
+        self.history.push(TextCollabEntry {site: site, change: change.clone()});
+
+        if site == self.site {
+            // our own change came back, the buffered edits go out next
+            self.pending = None;
+            if let Some(buffer) = self.buffer.take() {
+                self.pending = Some(buffer.clone());
+                self.pending_rev = self.rev();
+                return (None, Some(TextCollabSend::Change {rev: self.pending_rev, change: buffer}))
+            }
+            return (None, self.send_cursors())
+        }
+        if let Some(pending) = self.pending.take() {
+            let (change_t, pending_t) = TextChange::transform(&change, &pending);
+            self.pending = Some(pending_t);
+            change = change_t;
+        }
+        if let Some(buffer) = self.buffer.take() {
+            let (change_t, buffer_t) = TextChange::transform(&change, &buffer);
+            self.buffer = Some(buffer_t);
+            change = change_t;
+        }
+        self.transform_cursors(&change, false);
+        (Some(change), None)
+    }
+
+    pub fn receive_cursors(&mut self, site: u64, rev: usize, cursors: &[(usize, usize)]) {
+        if site == self.site {
+            return
+        }
+        let mut cursors: Vec<TextCursor> = cursors.iter().map( | (head, tail) | TextCursor {head: *head, tail: *tail, max: 0}).collect();
+        let local = self.history[rev.min(self.history.len())..].iter().map( | entry | &entry.change)
+            .chain(self.pending.iter()).chain(self.buffer.iter());
+        for change in local {
+            for cursor in &mut cursors {
+                cursor.head = change.transform_offset(cursor.head, false);
+                cursor.tail = change.transform_offset(cursor.tail, false);
+            }
+        }
+        match self.peers.iter_mut().find( | peer | peer.site == site) {
+            Some(peer) => peer.cursors = cursors,
+            None => self.peers.push(TextCollabPeer {site: site, cursors: cursors})
+        }
+    }
+
+    pub fn remove_peer(&mut self, site: u64) {
+        self.peers.retain( | peer | peer.site != site);
+    }
+
+    // the cursors of every peer sorted on position, with the index of the peer they belong to
+    pub fn peer_cursors(&self) -> (Vec<TextCursor>, Vec<usize>) {
+        let mut all: Vec<(TextCursor, usize)> = Vec::new();
+        for (index, peer) in self.peers.iter().enumerate() {
+            for cursor in &peer.cursors {
+                all.push((cursor.clone(), index));
+            }
+        }
+        all.sort_by_key( | (cursor, _) | cursor.order());
+        all.into_iter().unzip()
+    }
+}
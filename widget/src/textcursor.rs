@@ -1,9 +1,11 @@
 use makepad_render::*;
+use makepad_microserde::*;
 
 use crate::textbuffer::*;
 use crate::tokentype::*;
+use crate::language::*;
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, SerBin, DeBin)]
 pub struct TextCursor {
     pub head: usize,
     pub tail: usize,
@@ -113,9 +115,46 @@ impl TextCursor {
             self.head = total_char_count;
         }
     }
+    
+    // moves by visual rows when lines are soft wrapped, visual_rows holds where each row starts
+    pub fn move_visual(&mut self, delta: isize, visual_rows: &[TextPos], total_char_count: usize, text_buffer: &TextBuffer) {
+        let pos = text_buffer.offset_to_text_pos(self.head);
+        let row_of = | pos: TextPos | visual_rows.partition_point( | start | start.row < pos.row || start.row == pos.row && start.col <= pos.col).max(1) - 1;
+        // keep the column within the visual row the max column is on
+        let max_start = visual_rows[row_of(TextPos {row: pos.row, col: self.max})];
+        let col = if max_start.row == pos.row {self.max - max_start.col} else {self.max};
+        
+        let target = row_of(pos) as isize + delta;
+        if target < 0 {
+            self.head = 0;
+            return
+        }
+        let target = target as usize;
+        if target >= visual_rows.len() || visual_rows[target].row >= text_buffer.get_line_count() {
+            self.head = total_char_count;
+            return
+        }
+        let start = visual_rows[target];
+        let mut col = start.col + col;
+        // remember the column on the row we land on, so short rows in between dont lose it
+        self.max = col;
+        if let Some(next) = visual_rows.get(target + 1) {
+            if next.row == start.row && col >= next.col {
+                col = next.col - 1;
+            }
+        }
+        self.head = text_buffer.text_pos_to_offset(TextPos {row: start.row, col: col});
+    }
 }
 
-#[derive(Clone)]
+// the selections expand_selection grew from, valid while the cursors are still what it left
+#[derive(Clone, Default)]
+pub struct TextSelectStack {
+    pub stack: Vec<Vec<TextCursor>>,
+    pub expanded: Vec<TextCursor>,
+}
+
+#[derive(Clone, SerBin, DeBin)]
 pub struct TextCursorSet {
     pub set: Vec<TextCursor>,
     pub last_cursor: usize,
@@ -416,7 +455,7 @@ impl TextCursorSet {
         self.last_clamp_range = None;
     }
     
-    pub fn insert_newline_with_indent(&mut self, text_buffer: &mut TextBuffer) {
+    pub fn insert_newline_with_indent(&mut self, text_buffer: &mut TextBuffer, rules: &IndentRules) {
         let mut delta: isize = 0;
         // rolling delta to displace cursors
         let mut ops = Vec::new();
@@ -425,20 +464,21 @@ impl TextCursorSet {
         for cursor in &mut self.set {
             let (start, end) = cursor.delta(delta);
             // lets find where we are as a cursor in the textbuffer
-            if start == end && start > 0 && start < text_buffer.flat_text.len() {
+            if start == end && start > 0 && start <= text_buffer.lines.char_count() {
                 // insert spaces till indent level
-                let (pre_base, pre_spaces) = text_buffer.calc_next_line_indent_depth(start, 4);
+                let (pre_base, pre_spaces) = text_buffer.calc_next_line_indent_depth(start, rules);
                 
-                let pch = text_buffer.flat_text[start - 1];
-                let nch = text_buffer.flat_text[start];
-                // we have to insert more newlines and spaces because we were between () {} or []
-                if pch == '{' && nch == '}' || pch == '(' && nch == ')' || pch == '[' && nch == ']' {
+                let pch = text_buffer.lines.char_at(start - 1);
+                let nch = text_buffer.lines.char_at(start);
+                // we have to insert more newlines and spaces because we were between a pair like () {} or []
+                let between_pair = rules.indent_after.iter().zip(rules.outdent_before.iter()).any( | (open, close) | pch == *open && nch == *close);
+                if between_pair {
                     let mut text = String::new();
                     text.push_str("\n");
                     for _ in 0..pre_spaces {
                         text.push_str(" ");
                     }
-                    let post_spaces = pre_spaces.max(4) - 4;
+                    let post_spaces = pre_spaces.max(rules.tab_size) - rules.tab_size;
                     text.push_str("\n");
                     for _ in 0..post_spaces {
                         text.push_str(" ");
@@ -449,10 +489,10 @@ impl TextCursorSet {
                     delta += (pre_spaces + post_spaces + 2) as isize;
                     ops.push(op);
                 }
-                else if pre_spaces != (start - pre_base) && nch == '}' || nch == ')' || nch == ']' { // deindent next one
+                else if pre_spaces != (start - pre_base) && rules.outdent_before.contains(&nch) { // deindent next one
                     let mut text = String::new();
                     text.push_str("\n");
-                    for _ in 0..(pre_spaces.max(4) - 4) {
+                    for _ in 0..(pre_spaces.max(rules.tab_size) - rules.tab_size) {
                         text.push_str(" ");
                     }
                     let op = text_buffer.replace_lines_with_string(start, end - start, &text);
@@ -478,8 +518,7 @@ impl TextCursorSet {
             
             old_max = cursor.calc_max(text_buffer, old_max);
         }
-        text_buffer.redo_stack.truncate(0);
-        text_buffer.undo_stack.push(TextUndo {
+        text_buffer.push_undo(TextUndo {
             ops: ops,
             grouping: TextUndoGrouping::Newline,
             cursors: cursors_clone
@@ -522,14 +561,37 @@ impl TextCursorSet {
             ops.push(op);
             old_max = cursor.calc_max(text_buffer, old_max);
         }
-        text_buffer.redo_stack.truncate(0);
-        text_buffer.undo_stack.push(TextUndo {
+        text_buffer.push_undo(TextUndo {
             ops: ops,
             grouping: grouping,
             cursors: cursors_clone
         })
     }
     
+    // replaces sorted, non overlapping ranges as one undo step, leaves a cursor after the last one
+    pub fn replace_ranges(&mut self, ranges: &[(usize, usize, String)], text_buffer: &mut TextBuffer) {
+        if ranges.len() == 0 {
+            return
+        }
+        let mut delta: isize = 0;
+        let mut ops = Vec::new();
+        let cursors_clone = self.clone();
+        let mut last = 0;
+        for (start, end, text) in ranges {
+            let at = (*start as isize + delta) as usize;
+            let op = text_buffer.replace_lines_with_string(at, end - start, text);
+            delta += op.len as isize - (end - start) as isize;
+            last = at + op.len;
+            ops.push(op);
+        }
+        text_buffer.push_undo(TextUndo {
+            ops: ops,
+            grouping: TextUndoGrouping::Block,
+            cursors: cursors_clone
+        });
+        self.clear_and_set_last_cursor_head_and_tail(last, last, text_buffer);
+    }
+
     pub fn insert_around(&mut self, pre: &str, post: &str, text_buffer: &mut TextBuffer) {
         let mut delta: isize = 0;
         // rolling delta to displace cursors
@@ -579,8 +641,7 @@ impl TextCursorSet {
             }
             old_max = cursor.calc_max(text_buffer, old_max);
         }
-        text_buffer.redo_stack.truncate(0);
-        text_buffer.undo_stack.push(TextUndo {
+        text_buffer.push_undo(TextUndo {
             ops: ops,
             grouping: TextUndoGrouping::Block,
             cursors: cursors_clone
@@ -641,8 +702,7 @@ impl TextCursorSet {
             };
             old_max = cursor.calc_max(text_buffer, old_max);
         }
-        text_buffer.redo_stack.truncate(0);
-        text_buffer.undo_stack.push(TextUndo {
+        text_buffer.push_undo(TextUndo {
             ops: ops,
             grouping: TextUndoGrouping::Block,
             cursors: cursors_clone
@@ -692,9 +752,8 @@ impl TextCursorSet {
             old_max = cursor.calc_max(text_buffer, old_max);
         }
         let del_pos = self.set[self.last_cursor].head;
-        text_buffer.redo_stack.truncate(0);
         if ops.len()>0 {
-            text_buffer.undo_stack.push(TextUndo {
+            text_buffer.push_undo(TextUndo {
                 ops: ops,
                 grouping: TextUndoGrouping::Delete(del_pos),
                 cursors: cursors_clone
@@ -729,9 +788,8 @@ impl TextCursorSet {
             }
             old_max = cursor.calc_max(text_buffer, old_max);
         }
-        text_buffer.redo_stack.truncate(0);
         if ops.len()>0 {
-            text_buffer.undo_stack.push(TextUndo {
+            text_buffer.push_undo(TextUndo {
                 ops: ops,
                 grouping: TextUndoGrouping::Backspace(undo_id),
                 cursors: cursors_clone
@@ -790,8 +848,7 @@ impl TextCursorSet {
             //}
             old_max = cursor.calc_max(text_buffer, old_max);
         }
-        text_buffer.redo_stack.truncate(0);
-        text_buffer.undo_stack.push(TextUndo {
+        text_buffer.push_undo(TextUndo {
             ops: ops,
             grouping: TextUndoGrouping::Tab,
             cursors: cursors_clone
@@ -817,54 +874,99 @@ impl TextCursorSet {
             
             let cursors_clone = self.clone();
             let op = text_buffer.replace_lines(top_row, bottom_row_old + 1, changed);
-            text_buffer.redo_stack.truncate(0);
-            text_buffer.undo_stack.push(TextUndo {
+            text_buffer.push_undo(TextUndo {
                 ops: vec![op],
                 grouping: TextUndoGrouping::Format,
                 cursors: cursors_clone
             })
         }
     }
-    /*
-    pub fn toggle_comment(&mut self, text_buffer:&mut TextBuffer, comment_str:&str){
-        let mut delta:usize = 0; // rolling delta to displace cursors
+    // comments out the lines touched by each cursor, or uncomments them when all of them already are
+    pub fn toggle_comment(&mut self, text_buffer: &mut TextBuffer, comment_str: &str) {
+        let mut delta: isize = 0;
+        // rolling delta to displace cursors
         let mut ops = Vec::new();
-        let comment_str_chars = comment_str.chars().count();
+        let comment: Vec<char> = comment_str.chars().collect();
         let cursors_clone = self.clone();
-        let mut old_max = (TextPos{row:0,col:0},0);
-        for cursor in &mut self.set{
-        let (start, end) = cursor.delta(delta as isize);
-
-        let start_pos = text_buffer.offset_to_text_pos_next(start, old_max.0, old_max.1);
-        let end_pos = text_buffer.offset_to_text_pos_next(end, start_pos, start);
-        let mut off = start - start_pos.col;
-        let last_line = if start_pos.row == end_pos.row || end_pos.col>0{1}else{0};
-
-        for row in start_pos.row..(end_pos.row+last_line){
-        // ok so how do we compute the actual op offset of this line
-        let op = text_buffer.replace_line_with_string(off, row, 0, 0, tab_str);
-        off += text_buffer.lines[row].len() + 1;
-        ops.push(op);
-        }
-        // figure out which way the cursor is
-        if cursor.head > cursor.tail{
-        cursor.tail += tab_str_chars + delta;
-        cursor.head += (end_pos.row - start_pos.row + last_line) * tab_str_chars + delta;
-        }
-        else{
-        cursor.tail += (end_pos.row - start_pos.row + last_line) * tab_str_chars + delta;
-        cursor.head += tab_str_chars + delta;
-        }
-        delta += ((end_pos.row - start_pos.row) + 1) * tab_str_chars;
-        old_max = cursor.calc_max(text_buffer, old_max);
-        }
-        text_buffer.redo_stack.truncate(0);
-        text_buffer.undo_stack.push(TextUndo{
-        ops:ops,
-        grouping:TextUndoGrouping::Tab,
-        cursors:cursors_clone
+        let mut old_max = (TextPos {row: 0, col: 0}, 0);
+        let mut next_row = 0;
+        for cursor in &mut self.set {
+            cursor.head = (cursor.head as isize + delta) as usize;
+            cursor.tail = (cursor.tail as isize + delta) as usize;
+            let (start, end) = cursor.order();
+            let start_pos = text_buffer.offset_to_text_pos(start);
+            let end_pos = text_buffer.offset_to_text_pos(end);
+            // a selection ending at the start of a line doesnt include that line
+            let last_row = if end_pos.row > start_pos.row && end_pos.col == 0 {end_pos.row - 1} else {end_pos.row};
+            // cursors sharing a line only toggle it once
+            let first_row = start_pos.row.max(next_row);
+            if first_row > last_row {
+                continue;
+            }
+            next_row = last_row + 1;
+            
+            let mut min_indent = None;
+            let mut all_commented = true;
+            for row in first_row..=last_row {
+                let line = &text_buffer.lines[row];
+                let indent = line.iter().take_while( | ch | **ch == ' ' || **ch == '\t').count();
+                if indent == line.len() { // blank lines are left alone
+                    continue;
+                }
+                min_indent = Some(min_indent.unwrap_or(indent).min(indent));
+                if !line[indent..].starts_with(&comment) {
+                    all_commented = false;
+                }
+            }
+            let min_indent = if let Some(min_indent) = min_indent {min_indent} else {continue};
+            
+            let mut off = text_buffer.text_pos_to_offset(TextPos {row: first_row, col: 0});
+            for row in first_row..=last_row {
+                let line = &text_buffer.lines[row];
+                let indent = line.iter().take_while( | ch | **ch == ' ' || **ch == '\t').count();
+                if indent != line.len() {
+                    if all_commented {
+                        let mut len = comment.len();
+                        if line.get(indent + len) == Some(&' ') {
+                            len += 1;
+                        }
+                        let at = off + indent;
+                        let op = text_buffer.replace_line_with_string(at, row, indent, len, "");
+                        for pos in &mut [&mut cursor.head, &mut cursor.tail] {
+                            if **pos > at {
+                                **pos -= len.min(**pos - at);
+                            }
+                        }
+                        delta -= len as isize;
+                        ops.push(op);
+                    }
+                    else {
+                        let text = format!("{} ", comment_str);
+                        let len = text.chars().count();
+                        let at = off + min_indent;
+                        let op = text_buffer.replace_line_with_string(at, row, min_indent, 0, &text);
+                        for pos in &mut [&mut cursor.head, &mut cursor.tail] {
+                            if **pos >= at {
+                                **pos += len;
+                            }
+                        }
+                        delta += len as isize;
+                        ops.push(op);
+                    }
+                }
+                off += text_buffer.lines[row].len() + 1;
+            }
+            old_max = cursor.calc_max(text_buffer, old_max);
+        }
+        if ops.len() == 0 {
+            return
+        }
+        text_buffer.push_undo(TextUndo {
+            ops: ops,
+            grouping: TextUndoGrouping::Other,
+            cursors: cursors_clone
         })
-    }*/
+    }
     
     pub fn remove_tab(&mut self, text_buffer: &mut TextBuffer, num_spaces: usize) {
         
@@ -902,8 +1004,7 @@ impl TextCursorSet {
             delta += total_cut_len;
             old_max = cursor.calc_max(text_buffer, old_max);
         }
-        text_buffer.redo_stack.truncate(0);
-        text_buffer.undo_stack.push(TextUndo {
+        text_buffer.push_undo(TextUndo {
             ops: ops,
             grouping: TextUndoGrouping::Tab,
             cursors: cursors_clone
@@ -962,6 +1063,16 @@ impl TextCursorSet {
         self.fuse_adjacent(text_buffer)
     }
     
+    pub fn move_visual(&mut self, delta: isize, only_head: bool, visual_rows: &[TextPos], text_buffer: &TextBuffer) {
+        self.insert_undo_group += 1;
+        let total_char_count = text_buffer.calc_char_count();
+        for cursor in &mut self.set {
+            cursor.move_visual(delta, visual_rows, total_char_count, text_buffer);
+            if !only_head {cursor.tail = cursor.head}
+        }
+        self.fuse_adjacent(text_buffer)
+    }
+    
     pub fn move_left(&mut self, char_count: usize, only_head: bool, text_buffer: &TextBuffer) {
         self.insert_undo_group += 1;
         let mut old_max = (TextPos {row: 0, col: 0}, 0);
@@ -1052,18 +1163,18 @@ impl TextCursorSet {
                         let mut scan_left = offset;
                         let boundary_tokens = "' :(){}[]+-|/<,.>;\"'!%^&*=";
                         while scan_left > 0 && scan_left > token_chunks[i].offset {
-                            if let Some(_) = boundary_tokens.find(text_buffer.flat_text[scan_left]) {
+                            if let Some(_) = boundary_tokens.find(text_buffer.lines.char_at(scan_left)) {
                                 scan_left += 1;
                                 break
                             }
                             scan_left -= 1;
                         }
-                        if let Some(_) = boundary_tokens.find(text_buffer.flat_text[scan_left]) {
+                        if let Some(_) = boundary_tokens.find(text_buffer.lines.char_at(scan_left)) {
                             scan_left += 1;
                         }
                         let mut scan_right = offset;
                         while scan_right < token_chunks[i].offset + token_chunks[i].len {
-                            if let Some(_) = boundary_tokens.find(text_buffer.flat_text[scan_right]) {
+                            if let Some(_) = boundary_tokens.find(text_buffer.lines.char_at(scan_right)) {
                                 //scan_left += 1;
                                 break
                             }
@@ -1105,6 +1216,223 @@ impl TextCursorSet {
         self.fuse_adjacent(text_buffer)
     }
     
+    // the next structural range around start..end: the token, a word in a string, the expression
+    // between delimiters, the contents of the brackets around it, the brackets themselves, and so on outward
+    pub fn get_expanded_range(start: usize, end: usize, text_buffer: &TextBuffer) -> Option<(usize, usize)> {
+        let tok = &text_buffer.token_chunks;
+        let mut ranges = Vec::new();
+        
+        // the token, for a cursor between two tokens a word goes before a bracket or operator
+        let mut token = None;
+        for (index, chunk) in tok.iter().enumerate() {
+            if chunk.offset > end {
+                break
+            }
+            if chunk.offset <= start && end <= chunk.offset + chunk.len && !chunk.token_type.should_ignore() {
+                match chunk.token_type {
+                    TokenType::ParenOpen | TokenType::ParenClose | TokenType::Delimiter | TokenType::Operator => if token.is_none() {
+                        token = Some(index)
+                    },
+                    _ => {
+                        token = Some(index);
+                        break
+                    }
+                }
+            }
+        }
+        if let Some(index) = token {
+            let chunk = &tok[index];
+            ranges.push((chunk.offset, chunk.offset + chunk.len));
+            if chunk.token_type == TokenType::String && chunk.len > 2 {
+                let (inner_start, inner_end) = (chunk.offset + 1, chunk.offset + chunk.len - 1);
+                ranges.push((inner_start, inner_end));
+                let is_word = | ch: char | ch.is_alphanumeric() || ch == '_';
+                let mut word_start = start.max(inner_start).min(inner_end);
+                while word_start > inner_start && is_word(text_buffer.lines.char_at(word_start - 1)) {
+                    word_start -= 1;
+                }
+                let mut word_end = end.max(inner_start).min(inner_end);
+                while word_end < inner_end && is_word(text_buffer.lines.char_at(word_end)) {
+                    word_end += 1;
+                }
+                ranges.push((word_start, word_end));
+            }
+        }
+        
+        // every bracket pair around the selection, then the buffer as a whole
+        for open in 0..tok.len() {
+            if tok[open].offset > start {
+                break
+            }
+            let close = tok[open].pair_token;
+            if tok[open].token_type != TokenType::ParenOpen || close <= open || close >= tok.len() {
+                continue
+            }
+            let close_end = tok[close].offset + tok[close].len;
+            if end > close_end {
+                continue
+            }
+            ranges.push((tok[open].offset, close_end));
+            ranges.push((tok[open].offset + tok[open].len, tok[close].offset));
+            Self::push_level_ranges(open + 1, close, text_buffer, &mut ranges);
+        }
+        Self::push_level_ranges(0, tok.len(), text_buffer, &mut ranges);
+        ranges.push((0, text_buffer.lines.char_count() + 1));
+        
+        ranges.into_iter()
+            .filter( | (range_start, range_end) | *range_start <= start && end <= *range_end && range_end - range_start > end - start)
+            .min_by_key( | (range_start, range_end) | range_end - range_start)
+    }
+    
+    // the trimmed contents of the tokens first..last, and the expressions in it. an expression ends at
+    // a delimiter or after a curly bracket block, nested brackets are skipped over
+    fn push_level_ranges(first: usize, last: usize, text_buffer: &TextBuffer, ranges: &mut Vec<(usize, usize)>) {
+        let tok = &text_buffer.token_chunks;
+        let trimmed = | from: usize, to: usize | -> Option<(usize, usize)> {
+            let first = (from..to).find( | index | !tok[*index].token_type.should_ignore())?;
+            let last = (from..to).rev().find( | index | !tok[*index].token_type.should_ignore())?;
+            Some((tok[first].offset, tok[last].offset + tok[last].len))
+        };
+        if let Some(range) = trimmed(first, last) {
+            ranges.push(range);
+        }
+        let mut segment_start = first;
+        let mut index = first;
+        while index < last {
+            let chunk = &tok[index];
+            if chunk.token_type == TokenType::ParenOpen && chunk.pair_token > index && chunk.pair_token < last {
+                index = chunk.pair_token + 1;
+                if text_buffer.lines.char_at(chunk.offset) == '{' {
+                    if let Some(range) = trimmed(segment_start, index) {
+                        ranges.push(range);
+                    }
+                    segment_start = index;
+                }
+                continue
+            }
+            if chunk.token_type == TokenType::Delimiter {
+                if let Some(range) = trimmed(segment_start, index) {
+                    ranges.push(range);
+                    // a statement with its semicolon
+                    if text_buffer.lines.char_at(chunk.offset) == ';' {
+                        ranges.push((range.0, chunk.offset + chunk.len));
+                    }
+                }
+                segment_start = index + 1;
+            }
+            index += 1;
+        }
+        if let Some(range) = trimmed(segment_start, last) {
+            ranges.push(range);
+        }
+    }
+    
+    // grows every selection one structural step, the steps are kept so shrink_selection can go back
+    pub fn expand_selection(&mut self, select_stack: &mut TextSelectStack, text_buffer: &TextBuffer) -> bool {
+        if select_stack.expanded != self.set {
+            select_stack.stack.truncate(0);
+        }
+        let before = self.set.clone();
+        let mut changed = false;
+        let mut old_calc = (TextPos {row: 0, col: 0}, 0);
+        for cursor in &mut self.set {
+            let (start, end) = cursor.order();
+            if let Some((new_start, new_end)) = Self::get_expanded_range(start, end, text_buffer) {
+                cursor.tail = new_start;
+                cursor.head = new_end;
+                old_calc = cursor.calc_max(text_buffer, old_calc);
+                changed = true;
+            }
+        }
+        if !changed {
+            return false
+        }
+        self.insert_undo_group += 1;
+        self.fuse_adjacent(text_buffer);
+        select_stack.stack.push(before);
+        select_stack.expanded = self.set.clone();
+        true
+    }
+    
+    pub fn shrink_selection(&mut self, select_stack: &mut TextSelectStack) -> bool {
+        if select_stack.expanded != self.set {
+            select_stack.stack.truncate(0);
+            return false
+        }
+        if let Some(set) = select_stack.stack.pop() {
+            self.insert_undo_group += 1;
+            self.last_cursor = self.last_cursor.min(set.len() - 1);
+            self.set = set;
+            select_stack.expanded = self.set.clone();
+            return true
+        }
+        false
+    }
+    
+    // the bracket pair a cursor at offset jumps between: the bracket just before or after it,
+    // otherwise the brackets around it. gives the token indices of the open and close bracket
+    pub fn get_bracket_pair_at(offset: usize, text_buffer: &TextBuffer) -> Option<(usize, usize)> {
+        let tok = &text_buffer.token_chunks;
+        let pair_of = | index: usize | -> Option<(usize, usize)> {
+            let pair = tok[index].pair_token;
+            match tok[index].token_type {
+                TokenType::ParenOpen if pair > index && pair < tok.len() => Some((index, pair)),
+                TokenType::ParenClose if pair < index && tok[pair].token_type == TokenType::ParenOpen => Some((pair, index)),
+                _ => None
+            }
+        };
+        let before = tok.iter().position( | chunk | chunk.offset + chunk.len == offset && chunk.len > 0);
+        let after = tok.iter().position( | chunk | chunk.offset == offset && chunk.len > 0);
+        if let Some(pair) = before.and_then(pair_of).or_else( || after.and_then(pair_of)) {
+            return Some(pair)
+        }
+        let mut around = None;
+        for (index, chunk) in tok.iter().enumerate() {
+            if chunk.offset >= offset {
+                break
+            }
+            if let Some((open, close)) = pair_of(index) {
+                if open == index && tok[close].offset >= offset {
+                    around = Some((open, close));
+                }
+            }
+        }
+        around
+    }
+    
+    // from an opening bracket to after its closing one and back to before the opening one.
+    // with select the pair is selected with the head where jumping would go
+    pub fn jump_to_matching_bracket(&mut self, select: bool, text_buffer: &TextBuffer) -> bool {
+        let mut changed = false;
+        let mut old_calc = (TextPos {row: 0, col: 0}, 0);
+        for cursor in &mut self.set {
+            if let Some((open, close)) = Self::get_bracket_pair_at(cursor.head, text_buffer) {
+                let tok = &text_buffer.token_chunks;
+                let open_start = tok[open].offset;
+                let close_end = tok[close].offset + tok[close].len;
+                let at_close = cursor.head == close_end || (cursor.head > tok[close].offset && cursor.head < close_end);
+                if at_close {
+                    cursor.head = open_start;
+                    if select {cursor.tail = close_end}
+                }
+                else {
+                    cursor.head = close_end;
+                    if select {cursor.tail = open_start}
+                }
+                if !select {
+                    cursor.tail = cursor.head;
+                }
+                old_calc = cursor.calc_max(text_buffer, old_calc);
+                changed = true;
+            }
+        }
+        if changed {
+            self.insert_undo_group += 1;
+            self.fuse_adjacent(text_buffer);
+        }
+        changed
+    }
+    
     pub fn get_token_highlight(&self, text_buffer: &TextBuffer) -> Vec<char> {
         let cursor = &self.set[self.last_cursor];
         if cursor.head != cursor.tail {
@@ -1213,7 +1541,9 @@ pub struct CursorRect {
     pub y: f32,
     pub w: f32,
     pub h: f32,
-    pub z: f32
+    pub z: f32,
+    // the cursor in the set it was drawn for
+    pub index: usize
 }
 
 impl DrawCursors {
@@ -1264,7 +1594,8 @@ impl DrawCursors {
             y: y,
             w: 1.5,
             h: h,
-            z: z
+            z: z,
+            index: self.next_index.max(1) - 1
         })
     }
     
@@ -4,7 +4,17 @@ use crate::textbuffer::*;
 use crate::textcursor::*;
 use crate::widgetstyle::*;
 use crate::scrollshadow::*;
+use crate::focusring::*;
 use crate::tokentype::*;
+use crate::language::*;
+use crate::findbar::*;
+use crate::minimap::*;
+use crate::undohistory::*;
+use crate::completion::*;
+use crate::imepreedit::*;
+use crate::snippet::*;
+use crate::regex::*;
+use std::rc::Rc;
 
 #[derive(Clone)]
 pub struct TextEditor {
@@ -20,8 +30,13 @@ pub struct TextEditor {
     pub paren_pair: Quad,
     pub indent_lines: Quad,
     pub shadow: ScrollShadow,
+    // text inputs want a ring when they have the key focus, the code editor doesn't
+    pub focus_ring: Option<FocusRing>,
     pub message_marker: Quad,
     pub search_marker: Quad,
+    // cursors and selections of collaborators, a color per collaborator
+    pub remote_marker: Quad,
+    pub remote_colors: Vec<Color>,
     pub text: Text,
     pub line_number_text: Text,
     pub cursors: TextCursorSet,
@@ -42,7 +57,27 @@ pub struct TextEditor {
     pub draw_cursor_row: bool,
     pub search_markers_bypass: Vec<TextCursor>,
     pub folding_depth: usize,
+    // soft wraps long lines over several visual rows, the buffer keeps its lines
+    pub wrap: TextWrap,
     pub colors: CodeEditorColors,
+    // supplies comment, indent and format rules, None edits as plain text
+    pub language: Option<Rc<dyn Language>>,
+    // in buffer find and replace, created on first use
+    pub find_bar: Option<Box<FindBar>>,
+    pub minimap: Option<Minimap>,
+    // browse and jump through the undo tree, created on first use
+    pub undo_history: Option<UndoHistory>,
+    // opens while typing a word when on, ctrl+space always opens it
+    pub auto_complete: bool,
+    pub completion_providers: Vec<Rc<dyn CompletionProvider>>,
+    pub completion: Option<CompletionPopup>,
+    pub _completion_query: Option<String>,
+    // expanded with tab after their prefix, tab then walks the tab stops
+    pub snippets: Rc<Vec<Snippet>>,
+    pub _snippet_session: Option<SnippetSession>,
+    pub _snippet_sync: (u32, usize),
+    // what an input method is composing at the cursor, created on first use
+    pub ime_preedit: Option<ImePreedit>,
     
     pub read_only: bool,
     pub multiline: bool,
@@ -53,6 +88,7 @@ pub struct TextEditor {
     pub _scroll_pos_on_load: Option<Vec2>,
     pub _set_key_focus_on_load: bool,
     pub _set_last_cursor: Option<((usize, usize),bool)>,
+    pub _set_cursors_on_load: Option<TextCursorSet>,
     pub _view_area: Area,
     pub _highlight_area: Area,
     pub _text_inst: Option<AlignedInstance>,
@@ -69,11 +105,21 @@ pub struct TextEditor {
     pub _last_indent_color: Color,
     
     pub _line_geometry: Vec<LineGeom>,
+    pub _line_start_offset: usize,
+    pub _line_start_y: f32,
+    pub _line_visual_rows: usize,
+    pub _wrap_x: Option<f32>,
+    pub _wrap_indent: f32,
+    pub _wrap_row_empty: bool,
+    // where each visual row starts when wrapping, empty otherwise
+    pub _visual_rows: Vec<TextPos>,
+    pub _visual_row_walks: Vec<Vec2>,
     pub _anim_select: Vec<AnimSelect>,
     pub _visible_lines: usize,
     
     pub _select_scroll: Option<SelectScroll>,
     pub _grid_select_corner: Option<TextPos>,
+    pub _select_stack: TextSelectStack,
     pub _is_row_select: bool,
     //pub _line_chunk: Vec<(f32, char)>,
     
@@ -95,6 +141,10 @@ pub struct TextEditor {
     pub _draw_cursors: DrawCursors,
     pub _draw_search: DrawCursors,
     pub _draw_messages: DrawCursors,
+    pub _draw_remote: DrawCursors,
+    // how many remote changes of the buffer the cursors were moved along with
+    pub _remote_changes_seen: Option<usize>,
+    pub _collab_cursors: Vec<TextCursor>,
     
     pub _cursor_blink_timer: Timer,
     pub _cursor_blink_flipflop: f32,
@@ -117,7 +167,9 @@ pub enum TextEditorEvent {
     KeyFocusLost,
     Escape,
     Search(String),
-    Decl(String)
+    Decl(String),
+    // a right click, the host decides what menu to show at the position
+    ContextMenu(Vec2)
 }
 
 #[derive(Default, Clone)]
@@ -156,9 +208,39 @@ pub struct CodeEditorColors {
     defocus: Color,
 }
 
+impl CodeEditorColors {
+    // the plain color of a token, without the paren depth and marker logic of drawing
+    pub fn token_color(&self, token_type: TokenType) -> Color {
+        match token_type {
+            TokenType::Whitespace | TokenType::Newline => self.whitespace,
+            TokenType::BuiltinType | TokenType::Keyword | TokenType::Bool => self.keyword,
+            TokenType::TypeDef | TokenType::Impl | TokenType::Fn => self.keyword,
+            TokenType::Error => self.error,
+            TokenType::Warning => self.warning,
+            TokenType::Defocus => self.defocus,
+            TokenType::Flow => self.flow,
+            TokenType::Looping => self.looping,
+            TokenType::Identifier => self.identifier,
+            TokenType::Macro | TokenType::Call => self.call,
+            TokenType::TypeName => self.type_name,
+            TokenType::ThemeName => self.theme_name,
+            TokenType::Color | TokenType::Regex | TokenType::String => self.string,
+            TokenType::StringMultiBegin | TokenType::StringChunk | TokenType::StringMultiEnd => self.string,
+            TokenType::Number => self.number,
+            TokenType::CommentMultiBegin | TokenType::CommentMultiEnd => self.comment,
+            TokenType::CommentLine | TokenType::CommentChunk => self.comment,
+            TokenType::ParenOpen | TokenType::ParenClose => self.paren_d1,
+            TokenType::Operator | TokenType::Namespace | TokenType::Hash | TokenType::Splat => self.operator,
+            TokenType::Delimiter | TokenType::Colon => self.delimiter,
+            TokenType::Eof | TokenType::Unexpected => self.unexpected,
+        }
+    }
+}
+
 impl TextEditor {
     
     pub fn new(cx: &mut Cx) -> Self {
+        Self::default_keymap(cx);
         Self {
             read_only: false,
             multiline: true,
@@ -173,6 +255,7 @@ impl TextEditor {
                 z: 10.,
                 ..ScrollShadow::new(cx)
             },
+            focus_ring: None,
             gutter_bg: Quad {
                 z: 9.0,
                 ..Quad::new(cx)
@@ -188,6 +271,8 @@ impl TextEditor {
             paren_pair: Quad::new(cx),
             message_marker: Quad::new(cx),
             search_marker: Quad::new(cx),
+            remote_marker: Quad::new(cx),
+            remote_colors: Vec::new(),
             //code_icon: CodeIcon::proto(cx),
             //view_layout: Layout::default(),
             text: Text {
@@ -216,6 +301,7 @@ impl TextEditor {
             _scroll_pos_on_load: None,
             _set_key_focus_on_load: false,
             _set_last_cursor: None,
+            _set_cursors_on_load: None,
             _monospace_size: Vec2::default(),
             _monospace_base: Vec2::default(),
             _last_finger_move: None,
@@ -228,9 +314,18 @@ impl TextEditor {
             
             
             _line_geometry: Vec::new(),
+            _line_start_offset: 0,
+            _line_start_y: 0.,
+            _line_visual_rows: 1,
+            _wrap_x: None,
+            _wrap_indent: 0.,
+            _wrap_row_empty: true,
+            _visual_rows: Vec::new(),
+            _visual_row_walks: Vec::new(),
             
             _anim_select: Vec::new(),
             _grid_select_corner: None,
+            _select_stack: TextSelectStack::default(),
             _is_row_select: false,
             _view_area: Area::Empty,
             //_bg_area: Area::Empty,
@@ -246,6 +341,19 @@ impl TextEditor {
             _line_largest_font: 0.,
             _final_fill_height: 0.,
             folding_depth: 2,
+            wrap: TextWrap::None,
+            language: None,
+            find_bar: None,
+            minimap: None,
+            undo_history: None,
+            ime_preedit: None,
+            auto_complete: true,
+            completion_providers: vec![Rc::new(BufferWordsProvider {})],
+            completion: None,
+            _completion_query: None,
+            snippets: Rc::new(Vec::new()),
+            _snippet_session: None,
+            _snippet_sync: (0, 0),
             _anim_folding: AnimFolding {
                 state: AnimFoldingState::Open,
                 focussed_line: 0,
@@ -255,6 +363,9 @@ impl TextEditor {
             _draw_cursors: DrawCursors::new(),
             _draw_search: DrawCursors::new(),
             _draw_messages: DrawCursors::new(),
+            _draw_remote: DrawCursors::new(),
+            _remote_changes_seen: None,
+            _collab_cursors: Vec::new(),
             
             _paren_stack: Vec::new(),
             _indent_stack: Vec::new(),
@@ -303,6 +414,10 @@ impl TextEditor {
     pub fn color_message_marker_warning() -> ColorId {uid!()}
     pub fn color_message_marker_log() -> ColorId {uid!()}
     pub fn color_search_marker() -> ColorId {uid!()}
+    pub fn color_remote_1() -> ColorId {uid!()}
+    pub fn color_remote_2() -> ColorId {uid!()}
+    pub fn color_remote_3() -> ColorId {uid!()}
+    pub fn color_remote_4() -> ColorId {uid!()}
     pub fn color_line_number_normal() -> ColorId {uid!()}
     pub fn color_line_number_highlight() -> ColorId {uid!()}
     
@@ -511,6 +626,12 @@ impl TextEditor {
         self.colors.indent_line_looping = Self::color_indent_line_looping().get(cx);
         self.colors.indent_line_flow = Self::color_indent_line_flow().get(cx);
         self.search_marker.color = Self::color_search_marker().get(cx);
+        self.remote_colors = vec![
+            Self::color_remote_1().get(cx),
+            Self::color_remote_2().get(cx),
+            Self::color_remote_3().get(cx),
+            Self::color_remote_4().get(cx),
+        ];
         self.colors.paren_pair_match = Self::color_paren_pair_match().get(cx);
         self.colors.paren_pair_fail = Self::color_paren_pair_fail().get(cx);
         self.colors.message_marker_error = Self::color_message_marker_error().get(cx);
@@ -695,134 +816,193 @@ impl TextEditor {
         self.reset_cursor_blinker(cx);
     }
     
-    fn handle_key_down(&mut self, cx: &mut Cx, ke: &KeyEvent, text_buffer: &mut TextBuffer) {
-        let cursor_moved = match ke.key_code {
-            KeyCode::KeyE =>{
-                if ke.modifiers.logo || ke.modifiers.control {
-                    let pos = self.cursors.get_last_cursor_head();
-                    let mut moved = false;
-                    for result in text_buffer.markers.search_cursors.iter().rev(){
-                        if result.head < pos{
-                            if ke.modifiers.shift{
-                                self.cursors.add_last_cursor_head_and_tail(result.head, result.tail, text_buffer);
-                            }
-                            else{
-                                self.cursors.set_last_cursor_head_and_tail(result.head, result.tail, text_buffer);
-                            }
-                            moved = true;
-                            break;
-                        }
-                    }
-                    
-                    moved
-                }
-                else{
-                    false
-                }
+    pub fn default_keymap(cx: &mut Cx) {
+        let ctx = Some("editor");
+        let km = &mut cx.keymap;
+        km.register_command("editor.cursor_up", "Cursor Up", &["up", "alt+up"], ctx);
+        km.register_command("editor.select_up", "Select Up", &["shift+up", "alt+shift+up"], ctx);
+        km.register_command("editor.cursor_down", "Cursor Down", &["down", "alt+down"], ctx);
+        km.register_command("editor.select_down", "Select Down", &["shift+down", "alt+shift+down"], ctx);
+        km.register_command("editor.cursor_left", "Cursor Left", &["left", "alt+left"], ctx);
+        km.register_command("editor.select_left", "Select Left", &["shift+left", "alt+shift+left"], ctx);
+        km.register_command("editor.cursor_right", "Cursor Right", &["right", "alt+right"], ctx);
+        km.register_command("editor.select_right", "Select Right", &["shift+right", "alt+shift+right"], ctx);
+        km.register_command("editor.cursor_word_left", "Cursor Word Left", &["mod+left"], ctx);
+        km.register_command("editor.select_word_left", "Select Word Left", &["mod+shift+left"], ctx);
+        km.register_command("editor.cursor_word_right", "Cursor Word Right", &["mod+right"], ctx);
+        km.register_command("editor.select_word_right", "Select Word Right", &["mod+shift+right"], ctx);
+        km.register_command("editor.page_up", "Page Up", &["pageup"], ctx);
+        km.register_command("editor.select_page_up", "Select Page Up", &["shift+pageup"], ctx);
+        km.register_command("editor.page_down", "Page Down", &["pagedown"], ctx);
+        km.register_command("editor.select_page_down", "Select Page Down", &["shift+pagedown"], ctx);
+        km.register_command("editor.line_start", "Go to Line Start", &["home"], ctx);
+        km.register_command("editor.select_line_start", "Select to Line Start", &["shift+home"], ctx);
+        km.register_command("editor.line_end", "Go to Line End", &["end"], ctx);
+        km.register_command("editor.select_line_end", "Select to Line End", &["shift+end"], ctx);
+        km.register_command("editor.delete_left", "Delete Left", &["backspace", "shift+backspace"], ctx);
+        km.register_command("editor.delete_right", "Delete Right", &["delete", "shift+delete"], ctx);
+        km.register_command("editor.undo", "Undo", &["mod+z"], ctx);
+        km.register_command("editor.redo", "Redo", &["mod+shift+z"], ctx);
+        km.register_command("editor.cut", "Cut", &["mod+x"], ctx);
+        km.register_command("editor.select_all", "Select All", &["mod+a"], ctx);
+        km.register_command("editor.expand_selection", "Expand Selection", &["mod+shift+up"], ctx);
+        km.register_command("editor.shrink_selection", "Shrink Selection", &["mod+shift+down"], ctx);
+        km.register_command("editor.jump_to_bracket", "Go to Matching Bracket", &["mod+shift+backslash"], ctx);
+        km.register_command("editor.select_to_bracket", "Select to Matching Bracket", &["mod+alt+backslash"], ctx);
+        km.register_command("editor.indent", "Indent", &["tab"], ctx);
+        km.register_command("editor.outdent", "Outdent", &["shift+tab"], ctx);
+        km.register_command("editor.newline", "Insert Newline", &["return", "shift+return"], ctx);
+        km.register_command("editor.prev_match", "Previous Search Match", &["mod+e"], ctx);
+        km.register_command("editor.add_prev_match", "Add Cursor at Previous Match", &["mod+shift+e"], ctx);
+        km.register_command("editor.next_match", "Next Search Match", &["mod+d"], ctx);
+        km.register_command("editor.add_next_match", "Add Cursor at Next Match", &["mod+shift+d"], ctx);
+        km.register_command("editor.find", "Find", &["mod+f"], ctx);
+        km.register_command("editor.replace", "Replace", &["mod+h"], ctx);
+        km.register_command("editor.find_in_files", "Find in Files", &["mod+shift+f"], ctx);
+        km.register_command("editor.complete", "Show Completions", &["ctrl+space"], ctx);
+        km.register_command("editor.goto_declaration", "Go to Declaration", &["mod+s"], ctx);
+        km.register_command("editor.auto_format", "Auto Format", &["mod+return"], ctx);
+        km.register_command("editor.toggle_comment", "Toggle Line Comment", &["mod+slash"], ctx);
+        km.register_command("editor.toggle_wrap", "Toggle Word Wrap", &["alt+z"], ctx);
+        km.register_command("editor.toggle_minimap", "Toggle Minimap", &["alt+m"], ctx);
+        km.register_command("editor.undo_history", "Undo History", &["mod+alt+z"], ctx);
+        km.register_command("editor.escape", "Clear Selection", &["escape"], ctx);
+    }
+    
+    fn jump_to_search_match(&mut self, next: bool, add: bool, text_buffer: &mut TextBuffer) -> bool {
+        let pos = self.cursors.get_last_cursor_head();
+        let result = if next {
+            text_buffer.markers.search_cursors.iter().find( | result | result.tail > pos)
+        }
+        else {
+            text_buffer.markers.search_cursors.iter().rev().find( | result | result.head < pos)
+        };
+        if let Some(result) = result {
+            if add {
+                self.cursors.add_last_cursor_head_and_tail(result.head, result.tail, text_buffer);
             }
-            KeyCode::KeyD =>{
-                if ke.modifiers.logo || ke.modifiers.control {
-                    let pos = self.cursors.get_last_cursor_head();
-                    let mut moved = false;
-                    for result in text_buffer.markers.search_cursors.iter(){
-                        if result.tail > pos{
-                            if ke.modifiers.shift{
-                                self.cursors.add_last_cursor_head_and_tail(result.head, result.tail, text_buffer);
-                            }
-                            else{
-                                self.cursors.set_last_cursor_head_and_tail(result.head, result.tail, text_buffer);
-                            }
-                            moved = true;
-                            break;
-                        }
-                    }
-                    moved
-                }
-                else{
-                    false
-                }
+            else {
+                self.cursors.set_last_cursor_head_and_tail(result.head, result.tail, text_buffer);
             }
-            KeyCode::ArrowUp => {
+            return true
+        }
+        false
+    }
+    
+    // runs a named editor command, returns an event if the command needs to bubble up
+    pub fn handle_command(&mut self, cx: &mut Cx, command: &str, text_buffer: &mut TextBuffer) -> Option<TextEditorEvent> {
+        let cursor_moved = match command {
+            "editor.prev_match" => self.jump_to_search_match(false, false, text_buffer),
+            "editor.add_prev_match" => self.jump_to_search_match(false, true, text_buffer),
+            "editor.next_match" => self.jump_to_search_match(true, false, text_buffer),
+            "editor.add_next_match" => self.jump_to_search_match(true, true, text_buffer),
+            "editor.cursor_up" | "editor.select_up" => {
                 if !self.multiline {
                     false
                 }
-                else if ke.modifiers.logo || ke.modifiers.control {
-                    false
-                }
                 else {
+                    let only_head = command == "editor.select_up";
                     if self._anim_folding.state.is_folded() && self.cursors.set.len() == 1 {
-                        // compute the nearest nonfolded line up
-                        let delta = self.compute_next_unfolded_line_up(text_buffer);
-                        self.cursors.move_up(delta, ke.modifiers.shift, text_buffer);
+                        if self._visual_rows.len() > 0 {
+                            let visual_rows = self.unfolded_visual_rows(text_buffer);
+                            self.cursors.move_visual(-1, only_head, &visual_rows, text_buffer);
+                        }
+                        else {
+                            // compute the nearest nonfolded line up
+                            let delta = self.compute_next_unfolded_line_up(text_buffer);
+                            self.cursors.move_up(delta, only_head, text_buffer);
+                        }
+                    }
+                    else if self._visual_rows.len() > 0 {
+                        self.cursors.move_visual(-1, only_head, &self._visual_rows, text_buffer);
                     }
                     else {
-                        self.cursors.move_up(1, ke.modifiers.shift, text_buffer);
+                        self.cursors.move_up(1, only_head, text_buffer);
                     }
                     self._undo_id += 1;
                     true
                 }
             },
-            KeyCode::ArrowDown => {
-                if !self.multiline{
-                    false
-                }
-                else if ke.modifiers.logo || ke.modifiers.control {
+            "editor.cursor_down" | "editor.select_down" => {
+                if !self.multiline {
                     false
                 }
                 else {
+                    let only_head = command == "editor.select_down";
                     if self._anim_folding.state.is_folded() && self.cursors.set.len() == 1 {
-                        // compute the nearest nonfolded line down
-                        let delta = self.compute_next_unfolded_line_down(text_buffer);
-                        self.cursors.move_down(delta, ke.modifiers.shift, text_buffer);
+                        if self._visual_rows.len() > 0 {
+                            let visual_rows = self.unfolded_visual_rows(text_buffer);
+                            self.cursors.move_visual(1, only_head, &visual_rows, text_buffer);
+                        }
+                        else {
+                            // compute the nearest nonfolded line down
+                            let delta = self.compute_next_unfolded_line_down(text_buffer);
+                            self.cursors.move_down(delta, only_head, text_buffer);
+                        }
+                    }
+                    else if self._visual_rows.len() > 0 {
+                        self.cursors.move_visual(1, only_head, &self._visual_rows, text_buffer);
                     }
                     else {
-                        self.cursors.move_down(1, ke.modifiers.shift, text_buffer);
+                        self.cursors.move_down(1, only_head, text_buffer);
                     }
                     self._undo_id += 1;
                     true
                 }
             },
-            KeyCode::ArrowLeft => {
-                if ke.modifiers.logo || ke.modifiers.control { // token skipping
-                    self.cursors.move_left_nearest_token(ke.modifiers.shift, text_buffer)
-                }
-                else {
-                    self.cursors.move_left(1, ke.modifiers.shift, text_buffer);
-                }
+            "editor.cursor_left" | "editor.select_left" => {
+                self.cursors.move_left(1, command == "editor.select_left", text_buffer);
                 self._undo_id += 1;
                 true
             },
-            KeyCode::ArrowRight => {
-                if ke.modifiers.logo || ke.modifiers.control { // token skipping
-                    self.cursors.move_right_nearest_token(ke.modifiers.shift, text_buffer)
-                }
-                else {
-                    self.cursors.move_right(1, ke.modifiers.shift, text_buffer);
-                }
+            "editor.cursor_right" | "editor.select_right" => {
+                self.cursors.move_right(1, command == "editor.select_right", text_buffer);
                 self._undo_id += 1;
                 true
             },
-            KeyCode::PageUp => {
-                self.cursors.move_up(self._visible_lines.max(5) - 4, ke.modifiers.shift, text_buffer);
+            "editor.cursor_word_left" | "editor.select_word_left" => { // token skipping
+                self.cursors.move_left_nearest_token(command == "editor.select_word_left", text_buffer);
                 self._undo_id += 1;
                 true
             },
-            KeyCode::PageDown => {
-                self.cursors.move_down(self._visible_lines.max(5) - 4, ke.modifiers.shift, text_buffer);
+            "editor.cursor_word_right" | "editor.select_word_right" => {
+                self.cursors.move_right_nearest_token(command == "editor.select_word_right", text_buffer);
                 self._undo_id += 1;
                 true
             },
-            KeyCode::Home => {
-                self.cursors.move_home(ke.modifiers.shift, text_buffer);
+            "editor.page_up" | "editor.select_page_up" => {
+                let only_head = command == "editor.select_page_up";
+                if self._visual_rows.len() > 0 {
+                    self.cursors.move_visual(4 - self._visible_lines.max(5) as isize, only_head, &self._visual_rows, text_buffer);
+                }
+                else {
+                    self.cursors.move_up(self._visible_lines.max(5) - 4, only_head, text_buffer);
+                }
                 self._undo_id += 1;
                 true
             },
-            KeyCode::End => {
-                self.cursors.move_end(ke.modifiers.shift, text_buffer);
+            "editor.page_down" | "editor.select_page_down" => {
+                let only_head = command == "editor.select_page_down";
+                if self._visual_rows.len() > 0 {
+                    self.cursors.move_visual(self._visible_lines.max(5) as isize - 4, only_head, &self._visual_rows, text_buffer);
+                }
+                else {
+                    self.cursors.move_down(self._visible_lines.max(5) - 4, only_head, text_buffer);
+                }
+                self._undo_id += 1;
+                true
+            },
+            "editor.line_start" | "editor.select_line_start" => {
+                self.cursors.move_home(command == "editor.select_line_start", text_buffer);
+                self._undo_id += 1;
+                true
+            },
+            "editor.line_end" | "editor.select_line_end" => {
+                self.cursors.move_end(command == "editor.select_line_end", text_buffer);
                 self._undo_id += 1;
                 true
             },
-            KeyCode::Backspace => {
+            "editor.delete_left" => {
                 if !self.read_only {
                     self.cursors.backspace(text_buffer, self._undo_id);
                     true
@@ -831,7 +1011,7 @@ impl TextEditor {
                     false
                 }
             },
-            KeyCode::Delete => {
+            "editor.delete_right" => {
                 if !self.read_only {
                     self.cursors.delete(text_buffer);
                     true
@@ -840,78 +1020,153 @@ impl TextEditor {
                     false
                 }
             },
-            KeyCode::KeyZ => {
+            "editor.undo" => {
                 if !self.read_only {
-                    if ke.modifiers.logo || ke.modifiers.control {
-                        if ke.modifiers.shift { // redo
-                            text_buffer.redo(true, &mut self.cursors);
-                            true
-                        }
-                        else { // undo
-                            text_buffer.undo(true, &mut self.cursors);
-                            true
-                        }
-                    }
-                    else {
-                        false
-                    }
+                    text_buffer.undo(true, &mut self.cursors);
+                    true
                 }
                 else {
                     false
                 }
             },
-            KeyCode::KeyX => { // cut, the actual copy comes from the TextCopy event from the platform layer
-                if !self.read_only && (ke.modifiers.logo || ke.modifiers.control) { // cut
-                    self.cursors.replace_text("", text_buffer, None);
+            "editor.redo" => {
+                if !self.read_only {
+                    text_buffer.redo(true, &mut self.cursors);
                     true
                 }
                 else {
                     false
                 }
             },
-            KeyCode::KeyA => { // select all
-                if ke.modifiers.logo || ke.modifiers.control { // cut
-                    self.cursors.select_all(text_buffer);
-                    // don't scroll!
-                    self.view.redraw_view_area(cx);
-                    false
+            "editor.cut" => { // cut, the actual copy comes from the TextCopy event from the platform layer
+                if !self.read_only {
+                    self.cursors.replace_text("", text_buffer, None);
+                    true
                 }
                 else {
                     false
                 }
             },
-            KeyCode::Alt => {
-                // how do we find the center line of the view
-                // its simply the top line
-                self.start_code_folding(cx, text_buffer);
+            "editor.select_all" => {
+                self.cursors.select_all(text_buffer);
+                // don't scroll!
+                self.view.redraw_view_area(cx);
                 false
-                //return CodeEditorEvent::FoldStart
             },
-            KeyCode::Tab => {
-                if !self.read_only {
-                    if ke.modifiers.shift {
-                        self.cursors.remove_tab(text_buffer, 4);
-                    }
-                    else {
-                        self.cursors.insert_tab(text_buffer, "    ");
-                    }
+            "editor.expand_selection" => self.cursors.expand_selection(&mut self._select_stack, text_buffer),
+            "editor.shrink_selection" => self.cursors.shrink_selection(&mut self._select_stack),
+            "editor.jump_to_bracket" | "editor.select_to_bracket" => {
+                self.cursors.jump_to_matching_bracket(command == "editor.select_to_bracket", text_buffer)
+            },
+            "editor.indent" => {
+                if self.read_only {
+                    false
+                }
+                else if self.move_snippet_stop(true, text_buffer) || self.expand_snippet(text_buffer) {
                     true
                 }
                 else {
+                    self.cursors.insert_tab(text_buffer, "    ");
+                    true
+                }
+            },
+            "editor.outdent" => {
+                if self.read_only {
                     false
                 }
+                else if self.move_snippet_stop(false, text_buffer) {
+                    true
+                }
+                else {
+                    self.cursors.remove_tab(text_buffer, 4);
+                    true
+                }
             },
-            KeyCode::Return => {
+            "editor.newline" => {
                 if !self.read_only && self.multiline {
-                    if !ke.modifiers.control && !ke.modifiers.logo {
-                        self.cursors.insert_newline_with_indent(text_buffer);
-                    }
+                    let rules = if let Some(language) = &self.language {language.indent_rules()} else {IndentRules::default()};
+                    self.cursors.insert_newline_with_indent(text_buffer, &rules);
+                    true
+                }
+                else {
+                    false
+                }
+            },
+            "editor.escape" => {
+                self._snippet_session = None;
+                self.close_find_bar(cx, text_buffer);
+                let pos = self.cursors.get_last_cursor_head();
+                self.cursors.clear_and_set_last_cursor_head_and_tail(pos, pos, text_buffer);
+                return Some(TextEditorEvent::Escape)
+            },
+            "editor.find" | "editor.replace" => {
+                if !self.multiline {
+                    let search = self.cursors.get_ident_around_last_cursor_and_set(text_buffer);
+                    return Some(TextEditorEvent::Search(search))
+                }
+                let query = self.find_query_around_last_cursor(text_buffer);
+                self.open_find_bar(cx, &query, command == "editor.replace" && !self.read_only);
+                self.view.redraw_view_area(cx);
+                false
+            },
+            "editor.complete" => {
+                if self.multiline {
+                    self.open_completion(cx, text_buffer);
+                }
+                false
+            },
+            "editor.find_in_files" => {
+                let search = self.cursors.get_ident_around_last_cursor_and_set(text_buffer);
+                return Some(TextEditorEvent::Search(search))
+            },
+            "editor.goto_declaration" => {
+                let search = self.cursors.get_ident_around_last_cursor_and_set(text_buffer);
+                return Some(TextEditorEvent::Decl(search))
+            },
+            "editor.toggle_comment" => {
+                if self.read_only {
+                    false
+                }
+                else if let Some(comment) = self.language.as_ref().and_then( | language | language.line_comment().map( | s | s.to_string())) {
+                    self.cursors.toggle_comment(text_buffer, &comment);
                     true
                 }
                 else {
                     false
                 }
             },
+            "editor.toggle_wrap" => {
+                self.wrap = if self.wrap == TextWrap::None {TextWrap::Viewport} else {TextWrap::None};
+                self.view.redraw_view_area(cx);
+                false
+            },
+            "editor.toggle_minimap" => {
+                self.minimap = if self.minimap.is_none() {Some(Minimap::new(cx))} else {None};
+                self.view.redraw_view_area(cx);
+                false
+            },
+            "editor.undo_history" => {
+                let undo_history = self.undo_history.get_or_insert_with( || UndoHistory::new(cx));
+                if undo_history.is_open {
+                    undo_history.close(cx);
+                }
+                else {
+                    undo_history.open(cx);
+                }
+                self.view.redraw_view_area(cx);
+                false
+            },
+            "editor.auto_format" => {
+                if !self.read_only {
+                    if let Some(language) = self.language.clone() {
+                        if let Some(formatted) = language.auto_format(text_buffer) {
+                            self.cursors.replace_lines_formatted(formatted, text_buffer);
+                            self.view.redraw_view_area(cx);
+                        }
+                    }
+                }
+                return Some(TextEditorEvent::AutoFormat)
+            },
             _ => false
         };
         if cursor_moved {
@@ -920,6 +1175,7 @@ impl TextEditor {
             self.view.redraw_view_area(cx);
             self.reset_cursor_blinker(cx);
         }
+        None
     }
     
     fn handle_text_input(&mut self, cx: &mut Cx, te: &TextInputEvent, text_buffer: &mut TextBuffer) {
@@ -983,7 +1239,396 @@ impl TextEditor {
         self.reset_cursor_blinker(cx);
     }
     
+    // the word characters before the last cursor and the offset they start at
+    fn word_before_last_cursor(&self, text_buffer: &TextBuffer) -> Option<(usize, String)> {
+        let (start, end) = self.cursors.get_last_cursor_order();
+        if start != end {
+            return None
+        }
+        let pos = text_buffer.offset_to_text_pos(end);
+        let line = &text_buffer.lines[pos.row];
+        let mut col = pos.col.min(line.len());
+        while col > 0 && is_word_char(line[col - 1]) {
+            col -= 1;
+        }
+        Some((end - (pos.col - col), line[col..pos.col].iter().collect()))
+    }
+    
+    pub fn open_completion(&mut self, cx: &mut Cx, text_buffer: &TextBuffer) {
+        let (word_start, prefix) = match self.word_before_last_cursor(text_buffer) {
+            Some((word_start, prefix)) if prefix.len() > 0 => (word_start, prefix),
+            _ => return self.close_completion(cx)
+        };
+        let mut candidates = Vec::new();
+        for provider in &self.completion_providers {
+            provider.complete(&prefix, word_start, text_buffer, &mut candidates);
+        }
+        if let Some(provider) = self.language.as_ref().and_then( | language | language.completion_provider()) {
+            provider.complete(&prefix, word_start, text_buffer, &mut candidates);
+        }
+        if self.completion.is_none() {
+            self.completion = Some(CompletionPopup::new(cx));
+        }
+        if let Some(completion) = &mut self.completion {
+            completion.open(cx, word_start, &prefix, candidates);
+        }
+        self._completion_query = Some(prefix);
+        self.view.redraw_view_area(cx);
+    }
+    
+    // single characters dont make useful queries, unless the popup is already showing
+    fn open_completion_while_typing(&mut self, cx: &mut Cx, text_buffer: &TextBuffer) {
+        let is_open = self.completion.as_ref().map_or(false, | completion | completion.is_open);
+        match self.word_before_last_cursor(text_buffer) {
+            Some((_, prefix)) if is_open || prefix.chars().count() >= 2 => self.open_completion(cx, text_buffer),
+            _ => ()
+        }
+    }
+    
+    pub fn close_completion(&mut self, cx: &mut Cx) {
+        if let Some(completion) = &mut self.completion {
+            if completion.is_open {
+                completion.close(cx);
+                self.view.redraw_view_area(cx);
+            }
+        }
+    }
+    
+    // the prefix the popup (re)opened with since the last call, so the host can add its own candidates
+    pub fn take_completion_query(&mut self) -> Option<String> {
+        self._completion_query.take()
+    }
+    
+    // call right after take_completion_query, this can open the popup when the editor had nothing
+    pub fn add_completion_items(&mut self, cx: &mut Cx, items: Vec<CompletionItem>) {
+        if let Some(completion) = &mut self.completion {
+            completion.add_candidates(cx, items);
+            self.view.redraw_view_area(cx);
+        }
+    }
+    
+    // keeps the popup on the word at the cursor, it closes when the cursor leaves the word
+    fn sync_completion(&mut self, cx: &mut Cx, text_buffer: &TextBuffer) {
+        let (word_start, prefix) = match &self.completion {
+            Some(completion) if completion.is_open => (completion.word_start, completion.prefix.clone()),
+            _ => return
+        };
+        match self.word_before_last_cursor(text_buffer) {
+            Some((start, new_prefix)) if start == word_start && new_prefix.len() > 0 => if new_prefix != prefix {
+                self.open_completion(cx, text_buffer);
+            },
+            _ => self.close_completion(cx)
+        }
+    }
+    
+    fn handle_completion_key(&mut self, cx: &mut Cx, ke: &KeyEvent, text_buffer: &mut TextBuffer) -> bool {
+        let completion = match &mut self.completion {
+            Some(completion) if completion.is_open => completion,
+            _ => return false
+        };
+        if let KeyMapMatch::Command(command) = cx.keymap.match_key_down(ke, "completion") {
+            if let CompletionPopupEvent::Accept(index) = completion.handle_command(cx, &command) {
+                self.accept_completion(cx, index, text_buffer);
+            }
+            self.view.redraw_view_area(cx);
+            return true
+        }
+        false
+    }
+    
+    fn handle_undo_history_key(&mut self, cx: &mut Cx, ke: &KeyEvent, text_buffer: &mut TextBuffer) -> bool {
+        let undo_history = match &mut self.undo_history {
+            Some(undo_history) if undo_history.is_open => undo_history,
+            _ => return false
+        };
+        if let KeyMapMatch::Command(command) = cx.keymap.match_key_down(ke, "undohistory") {
+            let event = undo_history.handle_command(cx, &command);
+            self.handle_undo_history_event(cx, event, text_buffer);
+            return true
+        }
+        false
+    }
+    
+    fn handle_undo_history_event(&mut self, cx: &mut Cx, event: UndoHistoryEvent, text_buffer: &mut TextBuffer) {
+        match event {
+            UndoHistoryEvent::Jump(node) => {
+                text_buffer.undo_jump(node, &mut self.cursors);
+                self.view.redraw_view_area(cx);
+            },
+            UndoHistoryEvent::Close => {
+                if let Some(undo_history) = &mut self.undo_history {
+                    undo_history.close(cx);
+                }
+                self.view.redraw_view_area(cx);
+            },
+            UndoHistoryEvent::None => ()
+        }
+    }
+    
+    fn accept_completion(&mut self, cx: &mut Cx, index: usize, text_buffer: &mut TextBuffer) {
+        let (label, prefix) = match &self.completion {
+            Some(completion) if index < completion.items.len() => (completion.items[index].label.clone(), completion.prefix.clone()),
+            _ => return
+        };
+        // every cursor behind the same prefix gets completed
+        let prefix_len = prefix.chars().count();
+        for cursor in &mut self.cursors.set {
+            if cursor.head == cursor.tail && cursor.head >= prefix_len {
+                let mut before = String::new();
+                text_buffer.get_range_as_string(cursor.head - prefix_len, prefix_len, &mut before);
+                if before == prefix {
+                    cursor.tail = cursor.head - prefix_len;
+                }
+            }
+        }
+        self.cursors.replace_text(&label, text_buffer, Some(TextUndoGrouping::Block));
+        self.close_completion(cx);
+        self.scroll_last_cursor_visible(cx, text_buffer, 0.);
+        self.view.redraw_view_area(cx);
+        self.reset_cursor_blinker(cx);
+        cx.send_signal(text_buffer.signal, TextBuffer::status_data_update());
+    }
+    
+    // replaces the snippet prefix before a lone cursor with the snippet, and selects its first stop
+    fn expand_snippet(&mut self, text_buffer: &mut TextBuffer) -> bool {
+        let (start, end) = self.cursors.get_last_cursor_order();
+        if start != end || self.cursors.set.len() != 1 {
+            return false
+        }
+        let pos = text_buffer.offset_to_text_pos(end);
+        let line = &text_buffer.lines[pos.row];
+        let mut col = pos.col.min(line.len());
+        while col > 0 && !line[col - 1].is_whitespace() {
+            col -= 1;
+        }
+        let snippet = match find_snippet(&self.snippets, &line[col..pos.col]) {
+            Some(snippet) => snippet.clone(),
+            None => return false
+        };
+        let indent: String = line.iter().take_while( | ch | **ch == ' ' || **ch == '\t').collect();
+        let tab_size = self.language.as_ref().map_or(4, | language | language.indent_rules().tab_size);
+        let expansion = snippet.expand(&indent, &" ".repeat(tab_size));
+        let word_start = end - snippet.prefix.chars().count();
+        self.cursors.clear_and_set_last_cursor_head_and_tail(end, word_start, text_buffer);
+        self.cursors.replace_text(&expansion.text, text_buffer, Some(TextUndoGrouping::Block));
+        self.select_snippet_stop(SnippetSession::new(word_start, &expansion), text_buffer);
+        true
+    }
+    
+    fn move_snippet_stop(&mut self, next: bool, text_buffer: &TextBuffer) -> bool {
+        if let Some(mut session) = self._snippet_session.take() {
+            if if next {session.next()} else {session.prev()} {
+                self.select_snippet_stop(session, text_buffer);
+            }
+            else {
+                self._snippet_session = Some(session);
+            }
+            return true
+        }
+        false
+    }
+    
+    // puts a cursor on every range of the current stop, the session ends at the final one
+    fn select_snippet_stop(&mut self, session: SnippetSession, text_buffer: &TextBuffer) {
+        let ranges = session.current_ranges().clone();
+        self.cursors.clear_and_set_last_cursor_head_and_tail(ranges[0].1, ranges[0].0, text_buffer);
+        for (start, end) in &ranges[1..] {
+            self.cursors.add_last_cursor_head_and_tail(*end, *start, text_buffer);
+        }
+        self._undo_id += 1;
+        self._snippet_sync = (text_buffer.mutation_id, text_buffer.calc_char_count());
+        self._snippet_session = if session.is_final() {None} else {Some(session)};
+    }
+    
+    // moves the stops along with edits, the session ends when the cursors leave the current stop
+    fn sync_snippet_session(&mut self, text_buffer: &TextBuffer) {
+        if let Some(session) = &mut self._snippet_session {
+            let keep = if self._snippet_sync.0 != text_buffer.mutation_id {
+                let total = text_buffer.calc_char_count();
+                let growth = total as isize - self._snippet_sync.1 as isize;
+                self._snippet_sync = (text_buffer.mutation_id, total);
+                session.update_after_edit(growth, &self.cursors.set)
+            }
+            else {
+                session.contains(&self.cursors.set)
+            };
+            if !keep {
+                self._snippet_session = None;
+            }
+        }
+    }
+    
+    // a selection on one line, or else the identifier around the cursor
+    fn find_query_around_last_cursor(&mut self, text_buffer: &TextBuffer) -> String {
+        let (start, end) = self.cursors.get_last_cursor_order();
+        if start == end {
+            return self.cursors.get_ident_around_last_cursor_and_set(text_buffer)
+        }
+        let mut ret = String::new();
+        text_buffer.get_range_as_string(start, end - start, &mut ret);
+        if ret.contains('\n') {String::new()} else {ret}
+    }
+    
+    pub fn open_find_bar(&mut self, cx: &mut Cx, query: &str, show_replace: bool) {
+        if self.find_bar.is_none() {
+            self.find_bar = Some(Box::new(FindBar::new(cx)));
+        }
+        if let Some(find_bar) = &mut self.find_bar {
+            find_bar.open(cx, query, show_replace);
+        }
+    }
+    
+    pub fn close_find_bar(&mut self, cx: &mut Cx, text_buffer: &mut TextBuffer) {
+        if let Some(find_bar) = &mut self.find_bar {
+            if find_bar.is_open {
+                find_bar.close(cx, text_buffer);
+                self.set_key_focus(cx);
+            }
+        }
+    }
+    
+    fn refresh_find_matches(&mut self, text_buffer: &mut TextBuffer) {
+        if let Some(find_bar) = &mut self.find_bar {
+            if find_bar.is_open {
+                find_bar.update_matches(text_buffer, self.cursors.get_last_cursor_order());
+            }
+        }
+    }
+    
+    fn select_find_match(&mut self, cx: &mut Cx, find_bar: &mut FindBar, index: Option<usize>, text_buffer: &TextBuffer) {
+        find_bar.current = index;
+        if let Some(index) = index {
+            let (start, end) = (find_bar.matches[index].start, find_bar.matches[index].end);
+            self.cursors.clear_and_set_last_cursor_head_and_tail(end, start, text_buffer);
+            self.scroll_last_cursor_visible(cx, text_buffer, 0.);
+            self.reset_cursor_blinker(cx);
+        }
+        self.view.redraw_view_area(cx);
+    }
+    
+    fn handle_find_bar(&mut self, cx: &mut Cx, event: &mut Event, text_buffer: &mut TextBuffer) -> Option<TextEditorEvent> {
+        let mut find_bar = self.find_bar.take()?;
+        let last_mutation_id = text_buffer.mutation_id;
+        let selection = self.cursors.get_last_cursor_order();
+        if find_bar.is_open && find_bar._matched_mutation_id != text_buffer.mutation_id {
+            find_bar.update_matches(text_buffer, selection);
+            self.view.redraw_view_area(cx);
+        }
+        match find_bar.handle_find_bar(cx, event) {
+            FindBarEvent::Changed => {
+                find_bar.update_matches(text_buffer, selection);
+                let index = find_bar.match_from(selection);
+                self.select_find_match(cx, &mut find_bar, index, text_buffer);
+            },
+            FindBarEvent::Next => {
+                let index = find_bar.next_match(selection);
+                self.select_find_match(cx, &mut find_bar, index, text_buffer);
+            },
+            FindBarEvent::Prev => {
+                let index = find_bar.prev_match(selection);
+                self.select_find_match(cx, &mut find_bar, index, text_buffer);
+            },
+            FindBarEvent::Replace => if !self.read_only {
+                find_bar.set_current(selection);
+                // the first replace only selects, so you see what is replaced
+                if let Some(current) = find_bar.current {
+                    let replacement = find_bar.replacement_for(current);
+                    self.cursors.replace_text(&replacement, text_buffer, Some(TextUndoGrouping::Block));
+                    let pos = self.cursors.get_last_cursor_head();
+                    find_bar.update_matches(text_buffer, (pos, pos));
+                    let index = find_bar.match_from((pos, pos));
+                    self.select_find_match(cx, &mut find_bar, index, text_buffer);
+                }
+                else {
+                    let index = find_bar.next_match(selection);
+                    self.select_find_match(cx, &mut find_bar, index, text_buffer);
+                }
+            },
+            FindBarEvent::ReplaceAll => if !self.read_only {
+                let ranges: Vec<(usize, usize, String)> = (0..find_bar.matches.len()).map( | index | {
+                    (find_bar.matches[index].start, find_bar.matches[index].end, find_bar.replacement_for(index))
+                }).collect();
+                self.cursors.replace_ranges(&ranges, text_buffer);
+                find_bar.update_matches(text_buffer, self.cursors.get_last_cursor_order());
+                self.scroll_last_cursor_visible(cx, text_buffer, 0.);
+                self.view.redraw_view_area(cx);
+            },
+            FindBarEvent::Close => {
+                find_bar.close(cx, text_buffer);
+                self.set_key_focus(cx);
+            },
+            FindBarEvent::None => ()
+        }
+        self.find_bar = Some(find_bar);
+        if last_mutation_id != text_buffer.mutation_id {
+            cx.send_signal(text_buffer.signal, TextBuffer::status_data_update());
+            return Some(TextEditorEvent::Change)
+        }
+        None
+    }
+    
+    // moves our cursors along with the changes collaborators made to the buffer
+    fn follow_remote_changes(&mut self, text_buffer: &TextBuffer) {
+        let seen = match self._remote_changes_seen {
+            Some(seen) => seen,
+            None => text_buffer.remote_change_count
+        };
+        self._remote_changes_seen = Some(text_buffer.remote_change_count);
+        let missed = text_buffer.remote_change_count - seen;
+        if missed == 0 {
+            return
+        }
+        let changes = &text_buffer.remote_changes;
+        if missed > changes.len() {
+            let char_count = text_buffer.lines.char_count();
+            for cursor in &mut self.cursors.set {
+                cursor.head = cursor.head.min(char_count);
+                cursor.tail = cursor.tail.min(char_count);
+            }
+            return
+        }
+        for change in &changes[changes.len() - missed..] {
+            for cursor in &mut self.cursors.set {
+                cursor.head = change.transform_offset(cursor.head, false);
+                cursor.tail = change.transform_offset(cursor.tail, false);
+            }
+        }
+    }
+    
+    // while collaborating the focused editor hands its cursors to the buffer to be sent
+    fn share_collab_cursors(&mut self, cx: &Cx, text_buffer: &mut TextBuffer) {
+        if text_buffer.change_log.is_none() || !self.has_key_focus(cx) || self._collab_cursors == self.cursors.set {
+            return
+        }
+        self._collab_cursors = self.cursors.set.clone();
+        text_buffer.collab_cursors = Some(self.cursors.set.clone());
+    }
+    
     pub fn handle_text_editor(&mut self, cx: &mut Cx, event: &mut Event, text_buffer: &mut TextBuffer) -> TextEditorEvent {
+        self.follow_remote_changes(text_buffer);
+        self.share_collab_cursors(cx, text_buffer);
+        // the find bar and completions are drawn over the editor so they get the events first
+        if let Some(editor_event) = self.handle_find_bar(cx, event, text_buffer) {
+            return editor_event
+        }
+        if let Some(completion) = &mut self.completion {
+            if let CompletionPopupEvent::Accept(index) = completion.handle_completion_popup(cx, event) {
+                self.accept_completion(cx, index, text_buffer);
+                return TextEditorEvent::Change
+            }
+        }
+        if let Some(minimap) = &mut self.minimap {
+            if let MinimapEvent::ScrollToRow(row) = minimap.handle_minimap(cx, event) {
+                self.scroll_to_row(cx, row);
+            }
+        }
+        if let Some(undo_history) = &mut self.undo_history {
+            let undo_event = undo_history.handle_undo_history(cx, event);
+            if let UndoHistoryEvent::Jump(_) = undo_event {
+                self.handle_undo_history_event(cx, undo_event, text_buffer);
+                return TextEditorEvent::Change
+            }
+        }
         if self.view.handle_scroll_view(cx, event) {
             if let Some(last_finger_move) = self._last_finger_move {
                 if let Some(grid_select_corner) = self._grid_select_corner {
@@ -1072,10 +1717,20 @@ impl TextEditor {
                 return TextEditorEvent::KeyFocus
             },
             Event::KeyFocusLost(_kf) => {
+                self.close_completion(cx);
+                if let Some(ime_preedit) = &mut self.ime_preedit {
+                    ime_preedit.clear();
+                }
                 self.view.redraw_view_area(cx);
                 return TextEditorEvent::KeyFocusLost
             },
             Event::FingerDown(fe) => {
+                if fe.is_context_click() {
+                    // the moves and the up of this finger don't select anything
+                    cx.captured_fingers[fe.digit] = Area::Empty;
+                    self.set_key_focus(cx);
+                    return TextEditorEvent::ContextMenu(fe.abs)
+                }
                 self.handle_finger_down(cx, &fe, text_buffer);
             },
             Event::FingerHover(_fe) => {
@@ -1088,41 +1743,18 @@ impl TextEditor {
                 self.handle_finger_move(cx, &fe, text_buffer);
             },
             Event::KeyDown(ke) => {
-                if ke.key_code == KeyCode::Escape {
-                    let pos =  self.cursors.get_last_cursor_head();
-                    self.cursors.clear_and_set_last_cursor_head_and_tail(pos, pos, text_buffer);
-                    return TextEditorEvent::Escape
-                }/*
-                if ke.key_code == KeyCode::KeyD && (ke.modifiers.logo || ke.modifiers.control) {
-                    // check if d is inside a search cursor
-                    let pos = self.cursors.get_last_cursor_head();
-                    let mut repeat = false;
-                    for result in text_buffer.markers.search_cursors.iter().rev(){
-                        if pos >= result.tail && pos <= result.head {
-                            repeat = true;
-                            break;
+                if ke.key_code == KeyCode::Alt {
+                    // how do we find the center line of the view
+                    // its simply the top line
+                    self.start_code_folding(cx, text_buffer);
+                }
+                if !self.handle_completion_key(cx, &ke, text_buffer) && !self.handle_undo_history_key(cx, &ke, text_buffer) {
+                    if let KeyMapMatch::Command(command) = cx.keymap.match_key_down(&ke, "editor") {
+                        if let Some(editor_event) = self.handle_command(cx, &command, text_buffer) {
+                            return editor_event
                         }
-                    }/*
-                    if !repeat{
-                        let search = self.cursors.get_ident_around_last_cursor_and_set(text_buffer);
-                        // lets select the ident
-                        
-                        return TextEditorEvent::Decl(search)
-                    }*/
-                }*/
-                if ke.key_code == KeyCode::KeyF && (ke.modifiers.logo || ke.modifiers.control) {
-                    let search = self.cursors.get_ident_around_last_cursor_and_set(text_buffer);
-                    return TextEditorEvent::Search(search)
-                }
-                if ke.key_code == KeyCode::KeyS && (ke.modifiers.logo || ke.modifiers.control) {
-                    let search = self.cursors.get_ident_around_last_cursor_and_set(text_buffer);
-                    return TextEditorEvent::Decl(search)
-                }
-                
-                if ke.key_code == KeyCode::Return && (ke.modifiers.logo || ke.modifiers.control) {
-                    return TextEditorEvent::AutoFormat
+                    }
                 }
-                self.handle_key_down(cx, &ke, text_buffer);
             },
             Event::KeyUp(ke) => {
                 match ke.key_code {
@@ -1133,9 +1765,25 @@ impl TextEditor {
                 }
                 self.reset_cursor_blinker(cx);
             },
+            Event::TextComposition(tc) => {
+                if !self.read_only {
+                    self.ime_preedit.get_or_insert_with( || ImePreedit::new(cx)).set_composition(&tc);
+                    self.view.redraw_view_area(cx);
+                }
+            },
             Event::TextInput(te) => {
                 if !self.read_only {
+                    if let Some(ime_preedit) = &mut self.ime_preedit {
+                        ime_preedit.clear();
+                    }
                     self.handle_text_input(cx, &te, text_buffer);
+                    if self.auto_complete && self.multiline && !te.was_paste {
+                        let mut chars = te.input.chars();
+                        match (chars.next(), chars.next()) {
+                            (Some(ch), None) if is_word_char(ch) => self.open_completion_while_typing(cx, text_buffer),
+                            _ => self.close_completion(cx)
+                        }
+                    }
                 }
             },
             Event::TextCopy(_) => match event { // access the original event
@@ -1146,9 +1794,12 @@ impl TextEditor {
             },
             _ => ()
         };
+        self.sync_completion(cx, text_buffer);
+        self.sync_snippet_session(text_buffer);
         // i need to know if selection changed, ifso
         //
         if last_mutation_id != text_buffer.mutation_id {
+            self.refresh_find_matches(text_buffer);
             TextEditorEvent::Change
         }
         else {
@@ -1156,6 +1807,22 @@ impl TextEditor {
         }
     }
     
+    // runs a named command outside of a key event, for instance from the command palette
+    pub fn execute_command(&mut self, cx: &mut Cx, command: &str, text_buffer: &mut TextBuffer) -> TextEditorEvent {
+        let last_mutation_id = text_buffer.mutation_id;
+        if let Some(editor_event) = self.handle_command(cx, command, text_buffer) {
+            return editor_event
+        }
+        self.sync_snippet_session(text_buffer);
+        if last_mutation_id != text_buffer.mutation_id {
+            self.refresh_find_matches(text_buffer);
+            TextEditorEvent::Change
+        }
+        else {
+            TextEditorEvent::None
+        }
+    }
+
     pub fn has_key_focus(&self, cx: &Cx) -> bool {
         cx.has_key_focus(self._view_area)
     }
@@ -1177,6 +1844,7 @@ impl TextEditor {
         cx.new_instance_draw_call(&self.selection.shader, 0);
         cx.new_instance_draw_call(&self.message_marker.shader, 0);
         cx.new_instance_draw_call(&self.search_marker.shader, 0);
+        cx.new_instance_draw_call(&self.remote_marker.shader, 0);
         cx.new_instance_draw_call(&self.paren_pair.shader, 0);
         
         // force next begin_text in another drawcall
@@ -1202,6 +1870,7 @@ impl TextEditor {
         self._draw_cursors = DrawCursors::new();
         self._draw_messages = DrawCursors::new();
         self._draw_search = DrawCursors::new();
+        self._draw_remote = DrawCursors::new();
         self._tokens_on_line = 0;
         self._visible_lines = 0;
         self._newline_tabs = 0;
@@ -1213,14 +1882,78 @@ impl TextEditor {
         self._draw_search.set_next(
             if self.search_markers_bypass.len() > 0 {&self.search_markers_bypass}else {&text_buffer.markers.search_cursors}
         );
+        self._draw_remote.set_next(&text_buffer.markers.remote_cursors);
         self._line_geometry.truncate(0);
         self._line_largest_font = self.text.text_style.font_size;
         self._last_indent_color = self.colors.indent_line_unknown;
+        self._visual_rows.truncate(0);
+        self._visual_row_walks.truncate(0);
         // indent
         cx.move_turtle(self.line_number_width, self.top_padding);
+        self._wrap_x = match self.wrap {
+            TextWrap::None => None,
+            TextWrap::Viewport => {
+                let minimap_width = if self.minimap.is_some() {Minimap::width().get(cx)} else {0.};
+                let width = cx.get_width_total() - self._monospace_size.x - minimap_width;
+                if width.is_nan() {None} else {Some(width)}
+            },
+            TextWrap::Column(cols) => Some(cx.get_rel_turtle_pos().x + self._monospace_size.x * (cols.max(1) as f32))
+        };
+        self.begin_line(cx);
+    }
+    
+    fn begin_line(&mut self, cx: &mut Cx) {
+        let walk = cx.get_rel_turtle_pos();
+        self._line_start_y = walk.y;
+        self._line_visual_rows = 1;
+        self._wrap_indent = walk.x;
+        self._wrap_row_empty = true;
+        if self._wrap_x.is_some() {
+            self._visual_rows.push(TextPos {row: self._line_geometry.len(), col: 0});
+            self._visual_row_walks.push(walk);
+        }
+    }
+    
+    // continues the current line on a new visual row, col is where in the line it continues
+    fn draw_wrap_line(&mut self, cx: &mut Cx, col: usize) {
+        self._draw_cursors.process_newline();
+        self._draw_messages.process_newline();
+        self._draw_search.process_newline();
+        self._draw_remote.process_newline();
+        
+        cx.turtle_new_line_min_height(self._monospace_size.y);
+        let x = cx.get_rel_turtle_pos().x;
+        cx.move_turtle(self._wrap_indent - x, 0.);
+        
+        self._line_visual_rows += 1;
+        self._wrap_row_empty = true;
+        self._visual_rows.push(TextPos {row: self._line_geometry.len(), col: col});
+        self._visual_row_walks.push(cx.get_rel_turtle_pos());
+    }
+    
+    // how many chars of a token go on the current visual row. zero means wrap first,
+    // which we do when the token fits whole on a new row. whitespace fills the row and breaks anywhere
+    fn fit_wrap(&self, cx: &Cx, token_type: TokenType, len: usize) -> usize {
+        let wrap_x = if let Some(wrap_x) = self._wrap_x {wrap_x} else {return len};
+        if token_type == TokenType::Newline {
+            return len
+        }
+        let avail = ((wrap_x - cx.get_rel_turtle_pos().x) / self._monospace_size.x).max(0.) as usize;
+        if len <= avail {
+            return len
+        }
+        if token_type == TokenType::Whitespace {
+            return if self._wrap_row_empty {avail.max(1)} else {avail}
+        }
+        let row_cols = ((wrap_x - self._wrap_indent) / self._monospace_size.x).max(1.) as usize;
+        if !self._wrap_row_empty && len <= row_cols {
+            return 0
+        }
+        if self._wrap_row_empty {avail.max(1)} else {avail}
     }
     
     pub fn begin_text_editor(&mut self, cx: &mut Cx, text_buffer: &TextBuffer) -> Result<(), ()> {
+        self.follow_remote_changes(text_buffer);
         // adjust dilation based on DPI factor
         self.view.begin_view(cx, Self::layout_bg().get(cx)) ?;
         
@@ -1317,7 +2050,7 @@ impl TextEditor {
                     }
                     else {
                         self._monospace_base.y * self.text.text_style.font_size
-                    }
+                    } * (geom.visual_rows as f32)
                 }
                 ypos += self._final_fill_height;
                 let dy = self._line_geometry[focus_line].walk.y - ypos_at_line;
@@ -1345,15 +2078,24 @@ impl TextEditor {
     fn draw_new_line(&mut self, cx: &mut Cx) {
         // line geometry is used for scrolling look up of cursors
         let line_geom = LineGeom {
-            walk: cx.get_rel_turtle_pos(),
+            walk: Vec2 {x: cx.get_rel_turtle_pos().x, y: self._line_start_y},
             font_size: self._line_largest_font,
             was_folded: self._line_was_folded,
+            visual_rows: self._line_visual_rows,
             indent_id: if let Some((_, id)) = self._indent_stack.last() {*id}else {0.}
         };
         
-        // draw a linenumber if we are visible
+        // draw a linenumber if we are visible, wrapped lines get it on their first row
         let origin = cx.get_turtle_origin();
-        if self.draw_line_numbers && cx.turtle_line_is_visible(self._monospace_size.y, self._scroll_pos) {
+        let line_is_visible = if self._line_visual_rows > 1 {
+            let top = origin.y + self._scroll_pos.y;
+            let y = origin.y + self._line_start_y;
+            y < top + cx.get_height_total() && y + self._monospace_size.y * (self._line_visual_rows as f32) > top
+        }
+        else {
+            cx.turtle_line_is_visible(self._monospace_size.y, self._scroll_pos)
+        };
+        if self.draw_line_numbers && line_is_visible {
             // lets format a number, we go to 4 numbers
             // yes this is dumb as rocks. but we need to be cheapnfast
             let chunk = &mut self._line_number_chunk;
@@ -1396,6 +2138,7 @@ impl TextEditor {
         
         self._draw_cursors.process_newline();
         self._draw_messages.process_newline();
+        self._draw_remote.process_newline();
         
         // highlighting the selection
         /*let hl_len = self._highlight_selection.len();
@@ -1426,6 +2169,7 @@ impl TextEditor {
         // search for all markings
         self._line_geometry.push(line_geom);
         self._line_largest_font = self.text.text_style.font_size;
+        self.begin_line(cx);
     }
     
     fn draw_indent_lines(&mut self, cx: &mut Cx, geom_y: f32, tabs: usize) {
@@ -1450,13 +2194,12 @@ impl TextEditor {
         }
     }
     
-    pub fn draw_chunk(&mut self, cx: &mut Cx, token_chunks_index: usize, flat_text: &Vec<char>, token_chunk: &TokenChunk, markers: &TextBufferMarkers) {
+    pub fn draw_chunk(&mut self, cx: &mut Cx, token_chunks_index: usize, chunk: &[char], token_chunk: &TokenChunk, markers: &TextBufferMarkers) {
         if token_chunk.len == 0 {
             return
         }
         
         let token_type = token_chunk.token_type;
-        let chunk = &flat_text[token_chunk.offset..(token_chunk.offset + token_chunk.len)]; //chunk;
         let offset = token_chunk.offset; // end_offset - chunk.len() - 1;
         let next_char = token_chunk.next;
         
@@ -1467,6 +2210,7 @@ impl TextEditor {
         
         // do indent depth walking
         if self._tokens_on_line == 0 {
+            self._line_start_offset = offset;
             let font_scale = match token_type {
                 TokenType::Whitespace => {
                     let tabs = chunk.len()>>2;
@@ -1525,6 +2269,45 @@ impl TextEditor {
                 _ => ()
             }
         }
+        // soft wrapping splits the chunk over visual rows
+        let mut piece_start = 0;
+        loop {
+            let piece_len = self.fit_wrap(cx, token_type, chunk.len() - piece_start);
+            if piece_len == 0 {
+                self.draw_wrap_line(cx, offset + piece_start - self._line_start_offset);
+                continue;
+            }
+            let piece = &chunk[piece_start..(piece_start + piece_len)];
+            let piece_offset = offset + piece_start;
+            self.draw_chunk_piece(cx, token_type, next_char, piece, piece_offset, markers);
+            self._wrap_row_empty = false;
+            piece_start += piece_len;
+            if piece_start >= chunk.len() {
+                break;
+            }
+            self.draw_wrap_line(cx, piece_start + offset - self._line_start_offset);
+        }
+        if let Some(wrap_x) = self._wrap_x {
+            if self._tokens_on_line == 0 && token_type == TokenType::Whitespace {
+                // continuation rows line up with the indentation, up to half the row
+                let line_x = self._visual_row_walks.last().map_or(0., | walk | walk.x);
+                self._wrap_indent = cx.get_rel_turtle_pos().x.min(line_x + 0.5 * (wrap_x - line_x));
+            }
+        }
+        self._tokens_on_line += 1;
+        
+        // Do all the Paren matching highlighting drawing
+        if token_chunk.token_type == TokenType::ParenClose {
+            self.draw_paren_close(cx, token_chunks_index, offset, next_char, chunk);
+        }
+        else {
+            if token_type == TokenType::Newline {
+                self.draw_new_line(cx);
+            }
+        }
+    }
+    
+    fn draw_chunk_piece(&mut self, cx: &mut Cx, token_type: TokenType, next_char: char, chunk: &[char], offset: usize, markers: &TextBufferMarkers) {
         // lets check if the geom is visible
         if let Some(geom) = cx.walk_turtle_right_no_wrap(
             self._monospace_size.x * (chunk.len() as f32),
@@ -1661,6 +2444,8 @@ impl TextEditor {
             let draw_cursors = &mut self._draw_cursors;
             let draw_messages = &mut self._draw_messages;
             let draw_search = &mut self._draw_search;
+            let draw_remote = &mut self._draw_remote;
+            let remote_cursors = &markers.remote_cursors;
             
             let height = self._monospace_size.y;
             let search_cursors = if self.search_markers_bypass.len()>0 {&self.search_markers_bypass} else {&markers.search_cursors};
@@ -1673,6 +2458,7 @@ impl TextEditor {
                     //line_chunk.push((x, ch));
                     draw_search.mark_text_select_only(search_cursors, offset, x, geom.y, w, height);
                     draw_messages.mark_text_select_only(&markers.message_cursors, offset, x, geom.y, w, height);
+                    draw_remote.mark_text_with_cursor(remote_cursors, ch, offset, x, geom.y, w, height, z, usize::max_value(), 0.);
                     draw_cursors.mark_text_with_cursor(cursors, ch, offset, x, geom.y, w, height, z, last_cursor, mark_spaces)
                 });
             }
@@ -1680,22 +2466,13 @@ impl TextEditor {
                 self.text.add_text(cx, geom.x, geom.y, offset, self._text_inst.as_mut().unwrap(), &chunk, | ch, offset, x, w | {
                     //line_chunk.push((x, ch));
                     draw_messages.mark_text_select_only(&markers.message_cursors, offset, x, geom.y, w, height);
+                    draw_remote.mark_text_with_cursor(remote_cursors, ch, offset, x, geom.y, w, height, z, usize::max_value(), 0.);
                     draw_cursors.mark_text_with_cursor(cursors, ch, offset, x, geom.y, w, height, z, last_cursor, mark_spaces)
                 });
             }
         }
-        self._tokens_on_line += 1;
-        
-        // Do all the Paren matching highlighting drawing
-        if token_chunk.token_type == TokenType::ParenClose {
-            self.draw_paren_close(cx, token_chunks_index, offset, next_char, chunk);
-        }
-        else {
-            if token_type == TokenType::Newline {
-                self.draw_new_line(cx);
-            }
-        }
     }
+    
     /*
     fn draw_token_highlight_quad(&mut self, cx: &mut Cx, geom: Rect) {
         let inst = self.token_highlight.draw_quad_abs(cx, geom);
@@ -1806,6 +2583,7 @@ impl TextEditor {
         //self.do_selection_animations(cx);
         self.draw_selections(cx);
         self.draw_search_markers(cx);
+        self.draw_remote_markers(cx, text_buffer);
         self.draw_message_markers(cx, text_buffer);
         
         // inject a final page
@@ -1820,6 +2598,42 @@ impl TextEditor {
         self.draw_shadows(cx);
         self.bg.end_quad_fill(cx, &self._bg_inst.take().unwrap());
         
+        // drawn inside our view so redrawing the bar redraws the editor under it
+        if self.minimap.is_some() {
+            let mut rect = cx.get_turtle_rect();
+            if let Some(scroll_v) = &self.view.scroll_v {
+                rect.w -= scroll_v.bar_size;
+            }
+            let viewport_rows = self.visible_row_range(self.view.get_scroll_pos(cx).y, rect.h);
+            let search_cursors = if self.search_markers_bypass.len() > 0 {&self.search_markers_bypass} else {&text_buffer.markers.search_cursors};
+            if let Some(minimap) = &mut self.minimap {
+                minimap.draw_minimap(cx, rect, text_buffer, &self.colors, search_cursors, viewport_rows);
+            }
+        }
+        if let Some(find_bar) = &mut self.find_bar {
+            let rect = cx.get_turtle_rect();
+            find_bar.draw_find_bar(cx, rect);
+        }
+        if let Some(undo_history) = &mut self.undo_history {
+            let rect = cx.get_turtle_rect();
+            undo_history.draw_undo_history(cx, rect, text_buffer);
+        }
+        if let Some(completion) = &mut self.completion {
+            if let Some(last_cursor) = self._draw_cursors.last_cursor {
+                let rc = self._draw_cursors.cursors[last_cursor];
+                let scroll_pos = self.view.get_scroll_pos(cx);
+                completion.draw_completion_popup(cx, Rect {x: rc.x - scroll_pos.x, y: rc.y - scroll_pos.y, w: rc.w, h: rc.h});
+            }
+        }
+        if let Some(ime_preedit) = &mut self.ime_preedit {
+            if let Some(last_cursor) = self._draw_cursors.last_cursor {
+                let rc = self._draw_cursors.cursors[last_cursor];
+                let scroll_pos = self.view.get_scroll_pos(cx);
+                let rect = Rect {x: rc.x - scroll_pos.x, y: rc.y - scroll_pos.y, w: rc.w, h: rc.h};
+                ime_preedit.draw_ime_preedit(cx, rect, &self.text.text_style, self.bg.color, self.colors.identifier);
+            }
+        }
+        
         self.view.end_view(cx);
         
         if let Some(((head, tail), at_top)) = self._set_last_cursor {
@@ -1840,8 +2654,35 @@ impl TextEditor {
             self.view.set_scroll_pos(cx, scroll_pos_on_load);
             self._scroll_pos_on_load = None;
         }
+        
+        if text_buffer.is_loaded {
+            if let Some(mut cursors) = self._set_cursors_on_load.take() {
+                // the buffer may have changed underneath a restored cursor set
+                let total_char_count = text_buffer.calc_char_count();
+                for cursor in &mut cursors.set {
+                    cursor.head = cursor.head.min(total_char_count);
+                    cursor.tail = cursor.tail.min(total_char_count);
+                }
+                if cursors.set.len() > 0 {
+                    cursors.last_cursor = cursors.last_cursor.min(cursors.set.len() - 1);
+                    cursors.last_clamp_range = None;
+                    self.cursors = cursors;
+                    self.view.redraw_view_area(cx);
+                }
+            }
+        }
     }
 
+    // folding that is still animating counts as where it is heading
+    pub fn is_folded(&self) -> bool {
+        self._anim_folding.state.is_folded()
+    }
+    
+    // folds or opens without animating, for restoring an editor
+    pub fn set_folded(&mut self, folded: bool) {
+        self._anim_folding.state = if folded {AnimFoldingState::Folded} else {AnimFoldingState::Open};
+    }
+    
     pub fn set_last_cursor(&mut self, cx: &mut Cx, cursor:(usize, usize), at_top:bool) {
         self._set_last_cursor = Some((cursor, at_top));
         self.view.redraw_view_area(cx);
@@ -1873,6 +2714,10 @@ impl TextEditor {
         });
         
         self.shadow.draw_shadow_top(cx);
+        let view_area = self.view.get_view_area(cx);
+        if let Some(focus_ring) = &mut self.focus_ring {
+            focus_ring.draw_focus_ring(cx, view_area);
+        }
     }
     
     fn draw_message_markers(&mut self, cx: &mut Cx, text_buffer: &TextBuffer) {
@@ -1887,7 +2732,9 @@ impl TextEditor {
                 TextBufferMessageLevel::Error => self.colors.message_marker_error,
                 TextBufferMessageLevel::Log => self.colors.message_marker_log,
             };
-            self.message_marker.draw_quad_rel(cx, Rect {x: mark.rc.x - origin.x, y: mark.rc.y - origin.y, w: mark.rc.w, h: mark.rc.h});
+            let inst = self.message_marker.draw_quad_rel(cx, Rect {x: mark.rc.x - origin.x, y: mark.rc.y - origin.y, w: mark.rc.w, h: mark.rc.h});
+            // the message shows when the mouse rests on it
+            cx.set_tooltip_text(inst.into(), &body.body);
         }
     }
     
@@ -1899,6 +2746,25 @@ impl TextEditor {
         }
     }
     
+    fn draw_remote_markers(&mut self, cx: &mut Cx, text_buffer: &TextBuffer) {
+        if self.remote_colors.len() == 0 {
+            return
+        }
+        let origin = cx.get_turtle_origin();
+        let peers = &text_buffer.markers.remote_cursor_peers;
+        let colors = &self.remote_colors;
+        let color = | index: usize | colors[peers.get(index).cloned().unwrap_or(0) % colors.len()];
+        for mark in &self._draw_remote.selections {
+            self.remote_marker.color = color(mark.index);
+            self.remote_marker.color.a = 0.35;
+            self.remote_marker.draw_quad_rel(cx, Rect {x: mark.rc.x - origin.x, y: mark.rc.y - origin.y, w: mark.rc.w, h: mark.rc.h});
+        }
+        for rc in &self._draw_remote.cursors {
+            self.remote_marker.color = color(rc.index);
+            self.remote_marker.draw_quad_rel(cx, Rect {x: rc.x - origin.x, y: rc.y - origin.y, w: 2., h: rc.h});
+        }
+    }
+    
     pub fn draw_selections(&mut self, cx: &mut Cx) {
         let origin = cx.get_turtle_origin();
         let sel = &mut self._draw_cursors.selections;
@@ -1948,7 +2814,7 @@ impl TextEditor {
             }
             if cx.has_key_focus(self.view.get_view_area(cx)) {
                 let scroll_pos = self.view.get_scroll_pos(cx);
-                cx.show_text_ime(rc.x - scroll_pos.x, rc.y - scroll_pos.y);
+                cx.show_text_ime_rect(Rect {x: rc.x - scroll_pos.x, y: rc.y - scroll_pos.y, w: rc.w, h: rc.h});
             }
             else {
                 cx.hide_text_ime();
@@ -2072,9 +2938,16 @@ impl TextEditor {
             let geom = &self._line_geometry[row];
             let mono_size = Vec2 {x: self._monospace_base.x * geom.font_size, y: self._monospace_base.y * geom.font_size};
             //self.text.get_monospace_size(cx, geom.font_size);
+            let (col, walk_y) = if self._visual_rows.len() > 0 {
+                let index = self._visual_rows.partition_point( | start | start.row < pos.row || start.row == pos.row && start.col <= pos.col).max(1) - 1;
+                (pos.col - self._visual_rows[index].col.min(pos.col), self._visual_row_walks[index].y)
+            }
+            else {
+                (pos.col, geom.walk.y)
+            };
             let rect = Rect {
-                x: (pos.col as f32) * mono_size.x, // - self.line_number_width,
-                y: geom.walk.y - mono_size.y * 1. - 0.5 * height_pad,
+                x: (col as f32) * mono_size.x, // - self.line_number_width,
+                y: walk_y - mono_size.y * 1. - 0.5 * height_pad,
                 w: mono_size.x * 4. + self.line_number_width,
                 h: mono_size.y * 4. + height_pad
             };
@@ -2084,6 +2957,26 @@ impl TextEditor {
         }
     }
     
+    // the first row and the row after the last with geometry inside the viewport
+    fn visible_row_range(&self, scroll_y: f32, height: f32) -> (usize, usize) {
+        let base_y = self._monospace_base.y;
+        let geometry = &self._line_geometry;
+        let first = geometry.partition_point( | geom | geom.walk.y + base_y * geom.font_size * (geom.visual_rows as f32) <= scroll_y);
+        let end = geometry.partition_point( | geom | geom.walk.y < scroll_y + height);
+        (first, end.max(first))
+    }
+    
+    pub fn scroll_to_row(&mut self, cx: &mut Cx, row: usize) {
+        if self._line_geometry.len() == 0 {
+            return
+        }
+        let geom = &self._line_geometry[row.min(self._line_geometry.len() - 1)];
+        let scroll_pos = self.view.get_scroll_pos(cx);
+        if self.view.set_scroll_pos(cx, Vec2 {x: scroll_pos.x, y: geom.walk.y - self.top_padding}) {
+            self.view.redraw_view_area(cx);
+        }
+    }
+    
     fn scroll_last_cursor_top(&mut self, cx: &mut Cx, text_buffer: &TextBuffer) {
         // so we have to compute (approximately) the rect of our cursor
         if self.cursors.last_cursor >= self.cursors.set.len() {
@@ -2117,7 +3010,10 @@ impl TextEditor {
         for (row, geom) in self._line_geometry.iter().enumerate() {
             //let geom = &self._line_geometry[pos.row];
             mono_size = Vec2 {x: self._monospace_base.x * geom.font_size, y: self._monospace_base.y * geom.font_size};
-            if rel.y < geom.walk.y || rel.y >= geom.walk.y && rel.y <= geom.walk.y + mono_size.y { // its on the right line
+            if rel.y < geom.walk.y || rel.y >= geom.walk.y && rel.y <= geom.walk.y + mono_size.y * (geom.visual_rows as f32) { // its on the right line
+                if let Some(index) = self.visual_row_index(rel.y, row) {
+                    return self.visual_row_text_pos(index, rel.x, mono_size.x)
+                }
                 let col = ((rel.x - self.line_number_width).max(0.) / mono_size.x) as usize;
                 // do a dumb calc
                 return TextPos {row: row, col: col};
@@ -2127,6 +3023,32 @@ impl TextEditor {
         TextPos {row: self._line_geometry.len() - 1, col: (rel.x.max(0.) / mono_size.x) as usize}
     }
     
+    // the visual row of a wrapped line a y position is on
+    fn visual_row_index(&self, rel_y: f32, row: usize) -> Option<usize> {
+        if self._visual_rows.len() == 0 {
+            return None
+        }
+        let mut index = self._visual_rows.partition_point( | pos | pos.row < row);
+        if index >= self._visual_rows.len() || self._visual_rows[index].row != row {
+            return None
+        }
+        while index + 1 < self._visual_rows.len() && self._visual_rows[index + 1].row == row && self._visual_row_walks[index + 1].y <= rel_y {
+            index += 1;
+        }
+        Some(index)
+    }
+    
+    fn visual_row_text_pos(&self, index: usize, rel_x: f32, mono_x: f32) -> TextPos {
+        let start = self._visual_rows[index];
+        let mut col = start.col + ((rel_x - self._visual_row_walks[index].x).max(0.) / mono_x) as usize;
+        if let Some(next) = self._visual_rows.get(index + 1) {
+            if next.row == start.row && col >= next.col {
+                col = next.col - 1;
+            }
+        }
+        TextPos {row: start.row, col: col}
+    }
+    
     fn compute_offset_from_ypos(&mut self, cx: &Cx, ypos_abs: f32, text_buffer: &TextBuffer, end: bool) -> usize {
         let rel = self.view.get_view_area(cx).abs_to_rel(cx, Vec2 {x: 0.0, y: ypos_abs});
         let mut mono_size;
@@ -2135,7 +3057,7 @@ impl TextEditor {
         for (row, geom) in self._line_geometry.iter().enumerate() {
             //let geom = &self._line_geometry[pos.row];
             mono_size = Vec2 {x: self._monospace_base.x * geom.font_size, y: self._monospace_base.y * geom.font_size};
-            if rel.y < geom.walk.y || rel.y >= geom.walk.y && rel.y <= geom.walk.y + mono_size.y { // its on the right line
+            if rel.y < geom.walk.y || rel.y >= geom.walk.y && rel.y <= geom.walk.y + mono_size.y * (geom.visual_rows as f32) { // its on the right line
                 return text_buffer.text_pos_to_offset(TextPos {row: row, col: end_col})
             }
         }
@@ -2208,6 +3130,14 @@ impl TextEditor {
         last_scroll_none
     }
     
+    // the visual rows of the lines still readable when folded, and of the line the cursor is on
+    fn unfolded_visual_rows(&self, text_buffer: &TextBuffer) -> Vec<TextPos> {
+        let cursor_row = self.cursors.get_last_cursor_text_pos(text_buffer).row;
+        self._visual_rows.iter().filter( | start | {
+            start.row == cursor_row || self._line_geometry.get(start.row).map_or(true, | geom | !geom.was_folded)
+        }).cloned().collect()
+    }
+    
     fn compute_next_unfolded_line_up(&self, text_buffer: &TextBuffer) -> usize {
         let pos = self.cursors.get_last_cursor_text_pos(text_buffer);
         let mut delta = 1;
@@ -2301,10 +3231,20 @@ pub struct AnimSelect {
     pub time: f64
 }
 
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TextWrap {
+    None,
+    // wrap at the width of the view
+    Viewport,
+    // wrap after this many columns
+    Column(usize)
+}
+
 #[derive(Clone, Default)]
 pub struct LineGeom {
     walk: Vec2,
     was_folded: bool,
+    visual_rows: usize,
     font_size: f32,
     indent_id: f32
 }
@@ -1,8 +1,10 @@
 use makepad_render::*;
 use crate::texteditor::*;
 use crate::textbuffer::*;
+use crate::textlines::*;
 use crate::tokentype::*;
 use crate::widgetstyle::*;
+use crate::focusring::*;
 #[derive(Clone)]
 pub struct TextInput {
     pub text_editor: TextEditor,
@@ -29,6 +31,7 @@ impl TextInput {
                 highlight_area_on: false,
                 mark_unmatched_parens: false,
                 folding_depth: 3,
+                focus_ring: Some(FocusRing::new(cx)),
                 ..TextEditor::new(cx)
             },
             empty_message: opt.empty_message,
@@ -93,10 +96,11 @@ impl TextInput {
             let mut state = TokenizerState::new(&text_buffer.lines);
             let mut tokenizer = TextInputTokenizer::new();
             let mut pair_stack = Vec::new();
+            let mut flat_text = Vec::new();
             loop {
-                let offset = text_buffer.flat_text.len();
-                let token_type = tokenizer.next_token(&mut state, &mut text_buffer.flat_text, &text_buffer.token_chunks);
-                TokenChunk::push_with_pairing(&mut text_buffer.token_chunks, &mut pair_stack, state.next, offset, text_buffer.flat_text.len(), token_type);
+                let offset = flat_text.len();
+                let token_type = tokenizer.next_token(&mut state, &mut flat_text, &text_buffer.token_chunks);
+                TokenChunk::push_with_pairing(&mut text_buffer.token_chunks, &mut pair_stack, state.next, offset, flat_text.len(), token_type);
                 if token_type == TokenType::Eof {
                     break
                 }
@@ -104,16 +108,26 @@ impl TextInput {
         }
         
         if self.text_editor.begin_text_editor(cx, text_buffer).is_err() {return cx.end_style();}
+        cx.add_focusable(self.text_editor._view_area);
+        cx.add_a11y_node(A11yNode {
+            value: text_buffer.get_as_string(),
+            actions: vec![A11yAction::Focus],
+            ..A11yNode::new(A11yRole::TextInput, &self.empty_message, self.text_editor._view_area)
+        });
         
-        if text_buffer.is_empty() {
+        let is_composing = self.text_editor.ime_preedit.as_ref().map_or(false, | ime_preedit | ime_preedit.is_composing());
+        if text_buffer.is_empty() && !is_composing {
             let pos = cx.get_turtle_pos();
             self.text_editor.text.color = pick!(#666).get(cx);
             self.text_editor.text.draw_text(cx, &self.empty_message);
             cx.set_turtle_pos(pos);
         }
         
-        for (index, token_chunk) in text_buffer.token_chunks.iter_mut().enumerate() {
-            self.text_editor.draw_chunk(cx, index, &text_buffer.flat_text, token_chunk, &text_buffer.markers);
+        let mut reader = TextLinesReader::new(&text_buffer.lines);
+        let mut chunk = Vec::new();
+        for (index, token_chunk) in text_buffer.token_chunks.iter().enumerate() {
+            reader.chars_into(token_chunk.offset, token_chunk.len, &mut chunk);
+            self.text_editor.draw_chunk(cx, index, &chunk, token_chunk, &text_buffer.markers);
         }
         
         self.text_editor.end_text_editor(cx, text_buffer);
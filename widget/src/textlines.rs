@@ -0,0 +1,445 @@
+use std::ops::Index;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+// The lines of a TextBuffer. Lines are kept in leaves of a few hundred lines,
+// with a fenwick tree over the line and char counts of the leaves on top.
+// Mapping rows and char offsets is O(log n), and editing a line only touches its leaf
+// so files with many lines stay responsive. A single line is still one Vec<char>, so editing
+// a huge single-line file (minified js, a 100MB log line) moves the rest of that line on every
+// edit. Those are out of scope, tokenizing them is cheap again through the mid row checkpoints.
+// Every line also keeps its hash, so diffing against another version doesn't rehash the file.
+
+const LEAF_LINES: usize = 128;
+const LEAF_MAX_LINES: usize = 256;
+
+#[derive(Clone, Default)]
+pub struct TextLines {
+    leaves: Vec<Vec<Vec<char>>>,
+    // the hash of every line, in the same leaves as the lines
+    leaf_hashes: Vec<Vec<u64>>,
+    line_counts: CountTree,
+    // every line counts its newline, also the last one
+    char_counts: CountTree,
+    len: usize,
+}
+
+impl TextLines {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_lines(lines: Vec<Vec<char>>) -> Self {
+        let mut ret = Self::default();
+        ret.len = lines.len();
+        ret.leaf_hashes = Self::chunk_leaves(lines.iter().map( | line | Self::hash_line(line)).collect());
+        ret.leaves = Self::chunk_leaves(lines);
+        ret.rebuild();
+        ret
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Vec<char>> {
+        self.leaves.iter().flatten()
+    }
+
+    pub fn to_vec(&self) -> Vec<Vec<char>> {
+        self.iter().cloned().collect()
+    }
+
+    // the hashes of all lines, kept up to date by the splices so this doesn't hash anything
+    pub fn line_hashes(&self) -> Vec<u64> {
+        self.leaf_hashes.iter().flatten().cloned().collect()
+    }
+
+    pub fn hash_line(line: &[char]) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        line.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    // the number of chars including the newlines between lines
+    pub fn char_count(&self) -> usize {
+        self.char_counts.prefix(self.leaves.len()).max(1) - 1
+    }
+
+    // the char offset of the first char of row
+    pub fn row_offset(&self, row: usize) -> usize {
+        if row >= self.len {
+            return self.char_count() + 1
+        }
+        let (leaf, index) = self.locate(row);
+        let mut offset = self.char_counts.prefix(leaf);
+        for line in &self.leaves[leaf][0..index] {
+            offset += line.len() + 1;
+        }
+        offset
+    }
+
+    // returns the row holding offset and the offset that row starts at, None past the end
+    pub fn offset_to_row(&self, offset: usize) -> Option<(usize, usize)> {
+        let (leaf, mut row_offset) = self.char_counts.find(offset);
+        if leaf >= self.leaves.len() {
+            return None
+        }
+        let mut row = self.line_counts.prefix(leaf);
+        for line in &self.leaves[leaf] {
+            let next_offset = row_offset + line.len() + 1;
+            if next_offset > offset {
+                return Some((row, row_offset))
+            }
+            row_offset = next_offset;
+            row += 1;
+        }
+        None
+    }
+
+    // the char at offset, the newline between rows included. '\0' past the end
+    pub fn char_at(&self, offset: usize) -> char {
+        match self.offset_to_row(offset) {
+            Some((row, row_offset)) => match self[row].get(offset - row_offset) {
+                Some(ch) => *ch,
+                None if row + 1 < self.len() => '\n',
+                None => '\0'
+            },
+            None => '\0'
+        }
+    }
+
+    // replaces start..end of a single line, returns what was replaced
+    pub fn splice_line(&mut self, row: usize, start: usize, end: usize, rep_line: Vec<char>) -> Vec<char> {
+        let (leaf, index) = self.locate(row);
+        let delta = rep_line.len() as isize - (end - start) as isize;
+        let old = self.leaves[leaf][index].splice(start..end, rep_line).collect();
+        self.leaf_hashes[leaf][index] = Self::hash_line(&self.leaves[leaf][index]);
+        self.char_counts.add(leaf, delta);
+        old
+    }
+
+    // replaces the rows start_row..end_row with rep_lines, returns the replaced rows
+    pub fn splice_lines(&mut self, start_row: usize, end_row: usize, rep_lines: Vec<Vec<char>>) -> Vec<Vec<char>> {
+        if self.leaves.len() == 0 {
+            *self = Self::from_lines(rep_lines);
+            return Vec::new()
+        }
+        let (leaf, index) = if start_row >= self.len {
+            (self.leaves.len() - 1, self.leaves[self.leaves.len() - 1].len())
+        }
+        else {
+            self.locate(start_row)
+        };
+        let count = end_row - start_row;
+        self.len = self.len + rep_lines.len() - count;
+        let rep_hashes: Vec<u64> = rep_lines.iter().map( | line | Self::hash_line(line)).collect();
+
+        if index + count <= self.leaves[leaf].len() { // all in one leaf
+            let old: Vec<Vec<char>> = self.leaves[leaf].splice(index..(index + count), rep_lines).collect();
+            self.leaf_hashes[leaf].splice(index..(index + count), rep_hashes);
+            let leaf_len = self.leaves[leaf].len();
+            if leaf_len == 0 || leaf_len > LEAF_MAX_LINES {
+                let lines = self.leaves.remove(leaf);
+                let new_leaves = Self::chunk_leaves(lines);
+                self.leaves.splice(leaf..leaf, new_leaves);
+                let hashes = self.leaf_hashes.remove(leaf);
+                let new_hashes = Self::chunk_leaves(hashes);
+                self.leaf_hashes.splice(leaf..leaf, new_hashes);
+                self.rebuild();
+            }
+            else {
+                let delta_chars = Self::count_chars(&self.leaves[leaf]) as isize - self.char_counts.get(leaf) as isize;
+                let delta_lines = leaf_len as isize - self.line_counts.get(leaf) as isize;
+                self.char_counts.add(leaf, delta_chars);
+                self.line_counts.add(leaf, delta_lines);
+            }
+            return old
+        }
+
+        // the rows span leaves, merge them and chunk them up again
+        let (end_leaf, _) = self.locate(end_row - 1);
+        let mut lines: Vec<Vec<char>> = self.leaves.drain(leaf..(end_leaf + 1)).flatten().collect();
+        let old = lines.splice(index..(index + count), rep_lines).collect();
+        let new_leaves = Self::chunk_leaves(lines);
+        self.leaves.splice(leaf..leaf, new_leaves);
+        let mut hashes: Vec<u64> = self.leaf_hashes.drain(leaf..(end_leaf + 1)).flatten().collect();
+        hashes.splice(index..(index + count), rep_hashes);
+        let new_hashes = Self::chunk_leaves(hashes);
+        self.leaf_hashes.splice(leaf..leaf, new_hashes);
+        self.rebuild();
+        old
+    }
+
+    fn locate(&self, row: usize) -> (usize, usize) {
+        let (leaf, first_row) = self.line_counts.find(row);
+        (leaf, row - first_row)
+    }
+
+    fn count_chars(lines: &Vec<Vec<char>>) -> usize {
+        lines.iter().map( | line | line.len() + 1).sum()
+    }
+
+    fn chunk_leaves<T>(mut lines: Vec<T>) -> Vec<Vec<T>> {
+        let mut leaves = Vec::new();
+        while lines.len() > LEAF_LINES {
+            let rest = lines.split_off(LEAF_LINES);
+            leaves.push(lines);
+            lines = rest;
+        }
+        if lines.len() > 0 {
+            leaves.push(lines);
+        }
+        leaves
+    }
+
+    fn rebuild(&mut self) {
+        self.line_counts = CountTree::new(self.leaves.iter().map( | leaf | leaf.len()));
+        self.char_counts = CountTree::new(self.leaves.iter().map( | leaf | Self::count_chars(leaf)));
+    }
+}
+
+// reads the chars of token chunks, moving forward from the last read instead of looking up every offset
+pub struct TextLinesReader<'a> {
+    lines: &'a TextLines,
+    row: usize,
+    row_offset: usize,
+}
+
+impl<'a> TextLinesReader<'a> {
+    pub fn new(lines: &'a TextLines) -> Self {
+        Self {lines: lines, row: 0, row_offset: 0}
+    }
+
+    // the len chars at offset into out. past the end it gives spaces, which is what the eof token holds
+    pub fn chars_into(&mut self, offset: usize, len: usize, out: &mut Vec<char>) {
+        out.truncate(0);
+        if offset < self.row_offset || self.row >= self.lines.len() {
+            match self.lines.offset_to_row(offset) {
+                Some((row, row_offset)) => {
+                    self.row = row;
+                    self.row_offset = row_offset;
+                },
+                None => {
+                    out.resize(len, ' ');
+                    return
+                }
+            }
+        }
+        while self.row < self.lines.len() && offset > self.row_offset + self.lines[self.row].len() {
+            self.row_offset += self.lines[self.row].len() + 1;
+            self.row += 1;
+        }
+        let mut row = self.row;
+        let mut col = offset - self.row_offset;
+        while out.len() < len && row < self.lines.len() {
+            let line = &self.lines[row];
+            let end = line.len().min(col + len - out.len());
+            if col < end {
+                out.extend_from_slice(&line[col..end]);
+            }
+            if out.len() < len {
+                out.push(if row + 1 < self.lines.len() {'\n'} else {' '});
+            }
+            row += 1;
+            col = 0;
+        }
+        out.resize(len, ' ');
+    }
+}
+
+impl Index<usize> for TextLines {
+    type Output = Vec<char>;
+    fn index(&self, row: usize) -> &Vec<char> {
+        let (leaf, index) = self.locate(row);
+        &self.leaves[leaf][index]
+    }
+}
+
+// a fenwick tree, prefix sums and updates are O(log n)
+#[derive(Clone, Default)]
+struct CountTree {
+    tree: Vec<usize>
+}
+
+impl CountTree {
+    fn new(counts: impl Iterator<Item = usize>) -> Self {
+        let mut tree: Vec<usize> = counts.collect();
+        let len = tree.len();
+        for i in 1..=len {
+            let parent = i + (i & i.wrapping_neg());
+            if parent <= len {
+                tree[parent - 1] += tree[i - 1];
+            }
+        }
+        Self {tree}
+    }
+
+    fn add(&mut self, index: usize, delta: isize) {
+        let mut i = index + 1;
+        while i <= self.tree.len() {
+            self.tree[i - 1] = (self.tree[i - 1] as isize + delta) as usize;
+            i += i & i.wrapping_neg();
+        }
+    }
+
+    // the sum of the first len counts
+    fn prefix(&self, len: usize) -> usize {
+        let mut sum = 0;
+        let mut i = len;
+        while i > 0 {
+            sum += self.tree[i - 1];
+            i -= i & i.wrapping_neg();
+        }
+        sum
+    }
+
+    fn get(&self, index: usize) -> usize {
+        self.prefix(index + 1) - self.prefix(index)
+    }
+
+    // the index whose range holds value, and the prefix sum before it.
+    // the index is the count length when value is past the end
+    fn find(&self, value: usize) -> (usize, usize) {
+        let len = self.tree.len();
+        let mut index = 0;
+        let mut sum = 0;
+        let mut step = len.next_power_of_two();
+        while step > 0 {
+            if index + step <= len && sum + self.tree[index + step - 1] <= value {
+                index += step;
+                sum += self.tree[index - 1];
+            }
+            step >>= 1;
+        }
+        (index, sum)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_lines(count: usize, tag: &str) -> Vec<Vec<char>> {
+        (0..count).map( | i | format!("{}{}", tag, i).chars().collect()).collect()
+    }
+
+    // compares every lookup against the plain lines, and checks the leaves and counts stay consistent
+    fn check(text_lines: &TextLines, lines: &Vec<Vec<char>>) {
+        assert_eq!(text_lines.len(), lines.len());
+        assert_eq!(text_lines.to_vec(), *lines);
+        let flat: Vec<char> = lines.iter().enumerate().flat_map( | (row, line) | {
+            let mut line = line.clone();
+            if row + 1 < lines.len() {
+                line.push('\n');
+            }
+            line
+        }).collect();
+        assert_eq!(text_lines.char_count(), flat.len());
+        for (offset, ch) in flat.iter().enumerate() {
+            assert_eq!(text_lines.char_at(offset), *ch);
+        }
+        assert_eq!(text_lines.char_at(flat.len() + 1), '\0');
+        let mut row_offset = 0;
+        for (row, line) in lines.iter().enumerate() {
+            assert_eq!(text_lines.row_offset(row), row_offset);
+            for col in 0..=line.len() {
+                assert_eq!(text_lines.offset_to_row(row_offset + col), Some((row, row_offset)));
+            }
+            row_offset += line.len() + 1;
+        }
+        assert_eq!(text_lines.offset_to_row(row_offset), None);
+        for (leaf_index, leaf) in text_lines.leaves.iter().enumerate() {
+            assert!(leaf.len() > 0 && leaf.len() <= LEAF_MAX_LINES);
+            assert_eq!(text_lines.line_counts.get(leaf_index), leaf.len());
+            assert_eq!(text_lines.char_counts.get(leaf_index), TextLines::count_chars(leaf));
+        }
+        let hashes: Vec<u64> = lines.iter().map( | line | TextLines::hash_line(line)).collect();
+        assert_eq!(text_lines.line_hashes(), hashes);
+    }
+
+    #[test]
+    fn splice_line_across_leaves() {
+        let mut lines = make_lines(600, "line");
+        let mut text_lines = TextLines::from_lines(lines.clone());
+        assert_eq!(text_lines.leaves.len(), 5);
+        for row in &[0, 127, 128, 300, 599] {
+            let old = text_lines.splice_line(*row, 1, 3, "XYZW".chars().collect());
+            let expect: Vec<char> = lines[*row].splice(1..3, "XYZW".chars()).collect();
+            assert_eq!(old, expect);
+            check(&text_lines, &lines);
+        }
+    }
+
+    #[test]
+    fn splice_lines_within_a_leaf_splits_when_full() {
+        let mut lines = make_lines(300, "a");
+        let mut text_lines = TextLines::from_lines(lines.clone());
+        // grows the second leaf past LEAF_MAX_LINES so it gets chunked again
+        let rep = make_lines(200, "b");
+        text_lines.splice_lines(130, 131, rep.clone());
+        lines.splice(130..131, rep);
+        check(&text_lines, &lines);
+        assert!(text_lines.leaves.len() > 3);
+    }
+
+    #[test]
+    fn splice_lines_across_leaves_merges_them() {
+        let mut lines = make_lines(700, "a");
+        let mut text_lines = TextLines::from_lines(lines.clone());
+        let old = text_lines.splice_lines(100, 500, make_lines(3, "c"));
+        let expect: Vec<Vec<char>> = lines.splice(100..500, make_lines(3, "c")).collect();
+        assert_eq!(old, expect);
+        check(&text_lines, &lines);
+    }
+
+    #[test]
+    fn deleting_a_whole_leaf_drops_it() {
+        let mut lines = make_lines(384, "a");
+        let mut text_lines = TextLines::from_lines(lines.clone());
+        assert_eq!(text_lines.leaves.len(), 3);
+        text_lines.splice_lines(128, 256, Vec::new());
+        lines.splice(128..256, Vec::new());
+        check(&text_lines, &lines);
+        assert_eq!(text_lines.leaves.len(), 2);
+        // and everything, then build up again from nothing
+        text_lines.splice_lines(0, 256, Vec::new());
+        lines.truncate(0);
+        check(&text_lines, &lines);
+        text_lines.splice_lines(0, 0, make_lines(5, "d"));
+        lines = make_lines(5, "d");
+        check(&text_lines, &lines);
+    }
+
+    #[test]
+    fn random_edits_match_plain_lines() {
+        let mut lines = make_lines(1000, "r");
+        let mut text_lines = TextLines::from_lines(lines.clone());
+        let mut seed = 12345u64;
+        let mut rand = | max: usize | {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            ((seed >> 33) as usize) % max.max(1)
+        };
+        for step in 0..200 {
+            let start = rand(lines.len() + 1);
+            let end = (start + rand(300)).min(lines.len());
+            let rep = make_lines(rand(300), &format!("s{}_", step));
+            text_lines.splice_lines(start, end, rep.clone());
+            lines.splice(start..end, rep);
+            if lines.len() > 0 {
+                let row = rand(lines.len());
+                let col = rand(lines[row].len());
+                text_lines.splice_line(row, col, lines[row].len(), vec!['x']);
+                lines[row].splice(col.., vec!['x']);
+            }
+            if step % 20 == 0 {
+                check(&text_lines, &lines);
+            }
+        }
+        check(&text_lines, &lines);
+    }
+}
@@ -1,10 +1,11 @@
 // the 'makepad rust' tokenizer
+use crate::textlines::*;
 
 pub struct TokenizerState<'a> {
     pub prev: char,
     pub cur: char,
     pub next: char,
-    pub lines: &'a Vec<Vec<char>>,
+    pub lines: &'a TextLines,
     pub line_start: usize,
     pub line_counter: usize,
     pub offset: usize,
@@ -12,7 +13,7 @@ pub struct TokenizerState<'a> {
 }
 
 impl<'a> TokenizerState<'a> {
-    pub fn new(lines: &'a Vec<Vec<char>>) -> Self {
+    pub fn new(lines: &'a TextLines) -> Self {
         let mut ret = Self {
             lines: lines,
             line_start: 0,
@@ -27,6 +28,22 @@ impl<'a> TokenizerState<'a> {
         ret
     }
     
+    // starts tokenizing at col of row, offset being the char offset there
+    pub fn new_at(lines: &'a TextLines, row: usize, col: usize, offset: usize, prev: char, cur: char) -> Self {
+        let mut ret = Self {
+            lines: lines,
+            line_start: (offset - col).max(1) - 1,
+            line_counter: row,
+            offset: offset,
+            prev: prev,
+            cur: cur,
+            next: '\n',
+            iter: lines[row][col..].iter()
+        };
+        ret.advance();
+        ret
+    }
+    
     pub fn advance(&mut self) {
         if let Some(next) = self.iter.next() {
             self.next = *next;
@@ -97,6 +114,148 @@ impl<'a> TokenizerState<'a> {
         }
         return true
     }
+
+    fn chunk_whitespace(&mut self, chunk: &mut Vec<char>) -> TokenType {
+        while self.next == ' ' || self.next == '\t' {
+            chunk.push(self.next);
+            self.advance();
+        }
+        TokenType::Whitespace
+    }
+
+    // continues a line comment word by word, returns None at the end of the line
+    pub fn comment_line_chunk(&mut self, chunk: &mut Vec<char>) -> Option<TokenType> {
+        if self.next == ' ' || self.next == '\t' {
+            return Some(self.chunk_whitespace(chunk))
+        }
+        let start = chunk.len();
+        while self.next != ' ' && self.next != '\t' && self.next != '\n' && self.next != '\0' {
+            chunk.push(self.next);
+            self.advance();
+        }
+        if chunk.len() > start {Some(TokenType::CommentChunk)} else {None}
+    }
+
+    // continues a /* */ comment word by word, depth counts the open comments for languages that nest them
+    pub fn comment_multi_chunk(&mut self, chunk: &mut Vec<char>, depth: &mut usize, nested: bool) -> Option<TokenType> {
+        if self.next == ' ' || self.next == '\t' {
+            return Some(self.chunk_whitespace(chunk))
+        }
+        let start = chunk.len();
+        loop {
+            match self.next {
+                ' ' | '\t' | '\n' | '\0' => {
+                    return if chunk.len() > start {Some(TokenType::CommentChunk)} else {None}
+                },
+                '*' => {
+                    chunk.push(self.next);
+                    self.advance();
+                    if self.next == '/' {
+                        chunk.push(self.next);
+                        self.advance();
+                        *depth -= 1;
+                        if *depth == 0 {
+                            return Some(TokenType::CommentMultiEnd)
+                        }
+                    }
+                },
+                '/' => {
+                    chunk.push(self.next);
+                    self.advance();
+                    if nested && self.next == '*' {
+                        chunk.push(self.next);
+                        self.advance();
+                        *depth += 1;
+                    }
+                },
+                _ => {
+                    chunk.push(self.next);
+                    self.advance();
+                }
+            }
+        }
+    }
+
+    // continues a string spanning lines that ends with end_count times end_char, like a python """
+    pub fn string_multi_chunk(&mut self, chunk: &mut Vec<char>, end_char: char, end_count: usize, escapes: bool) -> Option<TokenType> {
+        if self.next == ' ' || self.next == '\t' {
+            return Some(self.chunk_whitespace(chunk))
+        }
+        let start = chunk.len();
+        loop {
+            match self.next {
+                ' ' | '\t' | '\n' | '\0' => {
+                    return if chunk.len() > start {Some(TokenType::StringChunk)} else {None}
+                },
+                '\\' if escapes => {
+                    chunk.push(self.next);
+                    self.advance();
+                    if self.next != '\n' && self.next != '\0' {
+                        chunk.push(self.next);
+                        self.advance();
+                    }
+                },
+                _ => if self.next == end_char {
+                    let mut count = 0;
+                    while self.next == end_char {
+                        chunk.push(self.next);
+                        self.advance();
+                        count += 1;
+                        if count == end_count {
+                            return Some(TokenType::StringMultiEnd)
+                        }
+                    }
+                }
+                else {
+                    chunk.push(self.next);
+                    self.advance();
+                }
+            }
+        }
+    }
+
+    // single line string, the opening quote is in state.cur
+    pub fn string_rest(&mut self, chunk: &mut Vec<char>, end_char: char, escapes: bool) {
+        while self.next != '\0' && self.next != '\n' {
+            if escapes && self.next == '\\' {
+                chunk.push(self.next);
+                self.advance();
+                if self.next != '\n' && self.next != '\0' {
+                    chunk.push(self.next);
+                    self.advance();
+                }
+            }
+            else if self.next == end_char {
+                chunk.push(self.next);
+                self.advance();
+                return
+            }
+            else {
+                chunk.push(self.next);
+                self.advance();
+            }
+        }
+    }
+
+    pub fn ident_rest(&mut self, chunk: &mut Vec<char>, extra: &str) {
+        while self.next_is_letter() || self.next_is_digit() || self.next == '_' || extra.contains(self.next) {
+            chunk.push(self.next);
+            self.advance();
+        }
+    }
+
+    // digits, hex/binary/octal prefixes, fractions, exponents and suffixes like 1.0f or 10u
+    pub fn number_rest(&mut self, chunk: &mut Vec<char>) {
+        while self.next_is_letter() || self.next_is_digit() || self.next == '_' || self.next == '.' {
+            let was_exp = (self.next == 'e' || self.next == 'E') && chunk.last().map_or(false, | c | c.is_ascii_digit());
+            chunk.push(self.next);
+            self.advance();
+            if was_exp && (self.next == '+' || self.next == '-') {
+                chunk.push(self.next);
+                self.advance();
+            }
+        }
+    }
 }
 
 #[derive(Clone)]
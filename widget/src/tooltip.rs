@@ -0,0 +1,157 @@
+use makepad_render::*;
+use crate::widgetstyle::*;
+
+// Shows what was registered with cx.set_tooltip for the area under the mouse once the mouse
+// rests on it for delay seconds. It sits below the mouse, or above it at the bottom of the window.
+// Moving the mouse away from where it showed, a press, a scroll or a key hides it again.
+// The DesktopWindow owns one, it sees the events before the ui does and draws it over everything.
+
+#[derive(Clone)]
+pub struct Tooltip {
+    pub popup: Popup,
+    pub bg: Quad,
+    pub text: Text,
+    pub delay: f64,
+    pub _timer: Timer,
+    // under the mouse, shown when the timer fires. The rect tells it apart,
+    // the area changes every time what registered it draws again
+    pub _hover: Option<CxTooltip>,
+    pub _hover_rect: Rect,
+    pub _shown: Option<CxTooltip>,
+    pub _mouse: Vec2,
+    pub _shown_at: Vec2,
+}
+
+impl Tooltip {
+    pub fn new(cx: &mut Cx) -> Self {
+        Self {
+            popup: Popup {
+                gap: 4.,
+                ..Popup::new(cx)
+            },
+            bg: Quad::new(cx),
+            text: Text {
+                wrapping: Wrapping::Line,
+                ..Text::new(cx)
+            },
+            delay: 0.6,
+            _timer: Timer::empty(),
+            _hover: None,
+            _hover_rect: Rect::default(),
+            _shown: None,
+            _mouse: Vec2::default(),
+            _shown_at: Vec2::default(),
+        }
+    }
+
+    pub fn layout_bg() -> LayoutId {uid!()}
+    pub fn text_style_tooltip() -> TextStyleId {uid!()}
+    pub fn color_bg() -> ColorId {uid!()}
+    pub fn color_text() -> ColorId {uid!()}
+    pub fn shader_bg() -> ShaderId {uid!()}
+
+    pub fn style(cx: &mut Cx, _opt: &StyleOptions) {
+        Self::layout_bg().set(cx, Layout {
+            walk: Walk::wh(Width::Compute, Height::Compute),
+            padding: Padding {l: 6., t: 4., r: 6., b: 4.},
+            ..Layout::default()
+        });
+        Self::text_style_tooltip().set(cx, Theme::text_style_normal().get(cx));
+        Self::color_bg().set(cx, pick!(#3a3a3a).get(cx));
+        Self::color_text().set(cx, Theme::color_text_selected_focus().get(cx));
+
+        Self::shader_bg().set(cx, Quad::def_quad_shader().compose(shader!{"
+            fn pixel() -> vec4 {
+                let df = Df::viewport(pos * vec2(w, h));
+                df.box(0.5, 0.5, w - 1., h - 1., 2.);
+                df.fill_keep(color);
+                return df.stroke(pick!(#5), 1.);
+            }
+        "}));
+    }
+
+    pub fn hide(&mut self, cx: &mut Cx) {
+        cx.stop_timer(&mut self._timer);
+        self._shown = None;
+        self.popup.close(cx);
+    }
+
+    fn rest(&mut self, cx: &mut Cx) {
+        cx.stop_timer(&mut self._timer);
+        if self._hover.is_some() {
+            self._timer = cx.start_timer(self.delay, false);
+        }
+    }
+
+    pub fn handle_tooltip(&mut self, cx: &mut Cx, event: &mut Event, window_id: usize) {
+        match event {
+            Event::FingerHover(fe) => {
+                self._mouse = fe.abs;
+                let hover = cx.tooltip_at(window_id, fe.abs);
+                let rect = hover.as_ref().map_or(Rect::default(), | hover | hover.area.get_rect(cx));
+                if hover.is_some() != self._hover.is_some() || rect != self._hover_rect {
+                    self.hide(cx);
+                    self._hover = hover;
+                    self._hover_rect = rect;
+                    self.rest(cx);
+                }
+                else if self._shown.is_some() {
+                    // moving a bit away from where it showed hides it
+                    let dx = fe.abs.x - self._shown_at.x;
+                    let dy = fe.abs.y - self._shown_at.y;
+                    if dx * dx + dy * dy > 64. {
+                        self.hide(cx);
+                        self.rest(cx);
+                    }
+                }
+                else {
+                    // it shows when the mouse stops
+                    self.rest(cx);
+                }
+            },
+            Event::Timer(te) => if self._timer.is_timer(te) {
+                self._timer = Timer::empty();
+                if self._hover.is_some() {
+                    // what is there now, it may have been drawn again in the meantime
+                    let rect = self._hover_rect;
+                    self._hover = cx.tooltip_at(window_id, self._mouse).filter( | now | now.area.get_rect(cx) == rect);
+                    if self._hover.is_some() {
+                        self._shown = self._hover.clone();
+                        self._shown_at = self._mouse;
+                        // the anchor is about the size of the mouse cursor
+                        self.popup.open(cx, Rect {x: self._mouse.x, y: self._mouse.y, w: 0., h: 16.});
+                    }
+                }
+            },
+            Event::FingerDown(_) | Event::FingerScroll(_) | Event::KeyDown(_) => {
+                self.hide(cx);
+            },
+            _ => ()
+        }
+    }
+
+    pub fn draw_tooltip(&mut self, cx: &mut Cx) {
+        let content = if let Some(shown) = &self._shown {shown.content.clone()} else {
+            return
+        };
+        if self.popup.begin_popup(cx, Layout {
+            walk: Walk::wh(Width::Compute, Height::Compute),
+            ..Layout::default()
+        }).is_err() {
+            return
+        };
+        self.bg.shader = Self::shader_bg().get(cx);
+        self.bg.color = Self::color_bg().get(cx);
+        let inst = self.bg.begin_quad(cx, Self::layout_bg().get(cx));
+        match content {
+            TooltipContent::Text(text) => {
+                self.text.text_style = Self::text_style_tooltip().get(cx);
+                self.text.color = Self::color_text().get(cx);
+                self.text.draw_text(cx, &text);
+            },
+            TooltipContent::Draw(draw) => draw(cx)
+        }
+        self.bg.end_quad(cx, &inst);
+        self.popup.end_popup(cx);
+    }
+}
@@ -0,0 +1,1109 @@
+use makepad_render::*;
+use crate::scrollview::*;
+use crate::buttonlogic::*;
+use crate::scrollbar::*;
+use crate::scrollshadow::*;
+use crate::focusring::*;
+use crate::widgetstyle::*;
+
+// A tree of items that come from a TreeSource, like an outline, a scene graph or a json document.
+// The children of a node are asked for the first time it is opened. A source that is still loading
+// them returns None, the node then shows a loading row and the source redraws the tree when they are in.
+// Rows are selected with the mouse or the keys and can be dragged onto other rows, Drop says where
+// they went. The caller moves its data around and calls reload, open nodes and the selection stay.
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TreeIcon {
+    None,
+    Folder,
+    Leaf
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TreeDropPos {
+    Before,
+    Into,
+    After
+}
+
+pub trait TreeSource<T> {
+    // None while the children are still loading
+    fn children(&mut self, node: &T) -> Option<Vec<T>>;
+    fn label(&self, node: &T) -> String;
+    fn is_leaf(&self, node: &T) -> bool;
+
+    fn icon(&self, node: &T) -> TreeIcon {
+        if self.is_leaf(node) {TreeIcon::Leaf} else {TreeIcon::Folder}
+    }
+
+    // dropping nodes onto themselves or into their own children never gets here
+    fn can_drop(&self, _nodes: &[T], _target: &T, _pos: TreeDropPos) -> bool {
+        true
+    }
+}
+
+#[derive(Clone)]
+pub enum TreeNodeState {
+    Open,
+    Opening(f64),
+    Closing(f64),
+    Closed
+}
+
+#[derive(Clone)]
+pub struct TreeNode<T> {
+    pub item: T,
+    pub state: TreeNodeState,
+    // None until the node is opened and the source has them
+    pub children: Option<Vec<TreeNode<T>>>,
+    pub is_leaf: bool,
+    pub marked: bool,
+    pub animator: Animator,
+}
+
+impl<T> TreeNode<T> {
+    fn new(item: T) -> Self {
+        Self {
+            item: item,
+            state: TreeNodeState::Closed,
+            children: None,
+            is_leaf: false,
+            marked: false,
+            animator: Animator::default()
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        match self.state {
+            TreeNodeState::Open | TreeNodeState::Opening(_) => true,
+            _ => false
+        }
+    }
+}
+
+// a drawn row, the path is the index of the node at every depth
+#[derive(Clone)]
+pub struct TreeRow {
+    pub path: Vec<usize>,
+    pub counter: usize,
+}
+
+#[derive(Clone, PartialEq)]
+pub enum TreeViewEvent<T> {
+    None,
+    Select(Vec<T>),
+    Activate(T),
+    // opened or closed with the keys or by the accessibility layer
+    Toggle {item: T, open: bool},
+    ContextMenu {item: T, abs: Vec2},
+    Drop {nodes: Vec<T>, target: T, pos: TreeDropPos},
+    DragMove {fe: FingerMoveEvent, nodes: Vec<T>},
+    DragCancel,
+    DragEnd {fe: FingerUpEvent, nodes: Vec<T>},
+}
+
+#[derive(Clone)]
+pub struct TreeItemDraw {
+    pub node_bg: Quad,
+    pub filler: Quad,
+    pub drop_marker: Quad,
+    pub tree_text: Text,
+    pub shadow: ScrollShadow,
+    pub focus_ring: FocusRing,
+
+    pub node_layout: Layout,
+    pub row_height: f32,
+    pub filler_walk: Walk,
+    pub icon_walk: Walk,
+    pub color_tree_folder: Color,
+    pub color_tree_leaf: Color,
+    pub color_loading: Color
+}
+
+// what draw_nodes carries along while it goes down the tree
+struct TreeDrawWalk {
+    path: Vec<usize>,
+    lasts: Vec<bool>,
+    counter: usize,
+    rows: Vec<TreeRow>,
+    reload: bool,
+    animating: bool,
+    drop: Option<(Vec<usize>, TreeDropPos)>,
+}
+
+impl TreeItemDraw {
+    fn new(cx: &mut Cx) -> Self {
+        Self {
+            node_bg: Quad::new(cx),
+            filler: Quad {
+                z: 0.001,
+                ..Quad::new(cx)
+            },
+            drop_marker: Quad {
+                z: 0.002,
+                ..Quad::new(cx)
+            },
+            tree_text: Text {z: 0.001, ..Text::new(cx)},
+            shadow: ScrollShadow {
+                z: 0.01,
+                ..ScrollShadow::new(cx)
+            },
+            focus_ring: FocusRing {
+                z: 0.01,
+                ..FocusRing::new(cx)
+            },
+            node_layout: Layout::default(),
+            row_height: 0.,
+            filler_walk: Walk::default(),
+            icon_walk: Walk::default(),
+            color_tree_folder: Color::default(),
+            color_tree_leaf: Color::default(),
+            color_loading: Color::default()
+        }
+    }
+
+    pub fn layout_drag_bg() -> LayoutId {uid!()}
+    pub fn layout_node() -> LayoutId {uid!()}
+    pub fn text_style_label() -> TextStyleId {uid!()}
+
+    pub fn color_tree_folder() -> ColorId {uid!()}
+    pub fn color_tree_leaf() -> ColorId {uid!()}
+    pub fn color_loading() -> ColorId {uid!()}
+    pub fn color_filler() -> ColorId {uid!()}
+    pub fn color_drag_bg() -> ColorId {uid!()}
+    pub fn color_drop_marker() -> ColorId {uid!()}
+
+    pub fn walk_filler() -> WalkId {uid!()}
+    pub fn walk_icon() -> WalkId {uid!()}
+
+    pub fn line_vec() -> Vec2Id {uid!()}
+    pub fn icon() -> FloatId {uid!()}
+    pub fn drop_into() -> FloatId {uid!()}
+
+    pub fn shader_filler() -> ShaderId {uid!()}
+    pub fn shader_drag_bg() -> ShaderId {uid!()}
+    pub fn shader_drop_marker() -> ShaderId {uid!()}
+
+    pub fn style(cx: &mut Cx, opt: &StyleOptions) {
+        Self::color_tree_folder().set(cx, Theme::color_text_selected_focus().get(cx));
+        Self::color_tree_leaf().set(cx, Theme::color_text_deselected_focus().get(cx));
+        Self::color_loading().set(cx, Theme::color_text_deselected_defocus().get(cx));
+        Self::color_filler().set(cx, Theme::color_icon().get(cx));
+        Self::color_drag_bg().set(cx, Theme::color_bg_marked().get(cx));
+        Self::color_drop_marker().set(cx, Theme::color_text_focus().get(cx));
+
+        Self::layout_drag_bg().set(cx, Layout {
+            padding: Padding {l: 5., t: 5., r: 5., b: 5.},
+            walk: Walk {
+                width: Width::Compute,
+                height: Height::Compute,
+                margin: Margin {l: 0., t: 0., r: 0., b: 1.}
+            },
+            ..Default::default()
+        });
+
+        Self::layout_node().set(cx, Layout {
+            walk: Walk::wh(Width::Fill, Height::Fix(20. * opt.scale)),
+            align: Align::left_center(),
+            padding: Padding {l: 5., t: 0., r: 0., b: 1.},
+            ..Default::default()
+        });
+
+        Self::text_style_label().set(cx, TextStyle {
+            top_drop: 1.3,
+            ..Theme::text_style_normal().get(cx)
+        });
+
+        Self::walk_filler().set(cx, Walk {
+            width: Width::Fix(10. * opt.scale),
+            height: Height::Fill,
+            margin: Margin {l: 1., t: 0., r: 4., b: 0.}
+        });
+
+        Self::walk_icon().set(cx, Walk {
+            width: Width::Fix(14. * opt.scale),
+            height: Height::Fill,
+            margin: Margin {l: 0., t: 0., r: 2., b: 0.}
+        });
+
+        Self::shader_filler().set(cx, Quad::def_quad_shader().compose(shader!{"
+
+            instance line_vec: Self::line_vec();
+            instance icon: Self::icon();
+
+            fn pixel() -> vec4 {
+                let df = Df::viewport(pos * vec2(w, h));
+                if icon < 0.5 {
+                    df.move_to(0.5 * w, line_vec.x * h);
+                    df.line_to(0.5 * w, line_vec.y * h);
+                    return df.stroke(color * 0.5, 1.);
+                }
+                else if icon < 1.5 { // a folder
+                    df.box(0. * w, 0.35 * h, 0.87 * w, 0.39 * h, 0.75);
+                    df.box(0. * w, 0.28 * h, 0.5 * w, 0.3 * h, 1.);
+                    df.union();
+                    return df.fill(color);
+                }
+                else { // a leaf
+                    df.box(0.15 * w, 0.28 * h, 0.55 * w, 0.46 * h, 1.);
+                    return df.stroke(color, 1.);
+                }
+            }
+        "}));
+
+        Self::shader_drag_bg().set(cx, Quad::def_quad_shader().compose(shader!{"
+            fn pixel() -> vec4 {
+                let df = Df::viewport(pos * vec2(w, h));
+                df.box(0., 0., w, h, 2.);
+                return df.fill(color);
+            }
+        "}));
+
+        Self::shader_drop_marker().set(cx, Quad::def_quad_shader().compose(shader!{"
+
+            instance drop_into: Self::drop_into();
+
+            fn pixel() -> vec4 {
+                let df = Df::viewport(pos * vec2(w, h));
+                if drop_into > 0.5 {
+                    df.box(1., 1., w - 2., h - 2., 2.);
+                    return df.stroke(color, 1.);
+                }
+                else {
+                    df.box(0., 0., w, h, 1.);
+                    return df.fill(color);
+                }
+            }
+        "}));
+    }
+
+    pub fn apply_style(&mut self, cx: &mut Cx) {
+        self.filler.color = Self::color_filler().get(cx);
+        self.filler.shader = Self::shader_filler().get(cx);
+        self.drop_marker.color = Self::color_drop_marker().get(cx);
+        self.drop_marker.shader = Self::shader_drop_marker().get(cx);
+        self.node_layout = Self::layout_node().get(cx);
+        self.row_height = self.node_layout.walk.height.fixed();
+        self.filler_walk = Self::walk_filler().get(cx);
+        self.icon_walk = Self::walk_icon().get(cx);
+        self.color_tree_folder = Self::color_tree_folder().get(cx);
+        self.color_tree_leaf = Self::color_tree_leaf().get(cx);
+        self.color_loading = Self::color_loading().get(cx);
+        self.tree_text.text_style = Self::text_style_label().get(cx);
+    }
+
+    pub fn get_default_anim(cx: &Cx, counter: usize, marked: bool) -> Anim {
+        Anim::new(Play::Chain {duration: 0.01}, vec![
+            Track::color(Quad::color(), Ease::Lin, vec![
+                (1.0, if marked {
+                    Theme::color_bg_marked().get(cx)
+                } else if counter & 1 == 0 {
+                    Theme::color_bg_selected().get(cx)
+                } else {
+                    Theme::color_bg_odd().get(cx)
+                })
+            ])
+        ])
+    }
+
+    pub fn get_over_anim(cx: &Cx, counter: usize, marked: bool) -> Anim {
+        let over_color = if marked {
+            Theme::color_bg_marked_over().get(cx)
+        } else if counter & 1 == 0 {
+            Theme::color_bg_selected_over().get(cx)
+        } else {
+            Theme::color_bg_odd_over().get(cx)
+        };
+        Anim::new(Play::Cut {duration: 0.02}, vec![
+            Track::color(Quad::color(), Ease::Lin, vec![
+                (0., over_color),
+                (1., over_color)
+            ])
+        ])
+    }
+
+    // the x where the label starts, a press in front of it opens or closes the node
+    fn label_x(&self, depth: usize) -> f32 {
+        let filler = self.filler_walk.width.fixed() + self.filler_walk.margin.l + self.filler_walk.margin.r;
+        let icon = self.icon_walk.width.fixed() + self.icon_walk.margin.l + self.icon_walk.margin.r;
+        self.node_layout.padding.l + depth as f32 * filler + icon
+    }
+
+    fn draw_fillers(&mut self, cx: &mut Cx, depth: usize, is_first: bool, is_last: bool, lasts: &[bool]) {
+        for i in 0..depth {
+            if i == depth - 1 { // our own thread
+                let line_vec = if is_last {
+                    if is_first {Vec2 {x: 0.3, y: 0.7}} else {Vec2 {x: -0.2, y: 0.7}}
+                }
+                else if is_first {
+                    Vec2 {x: -0.3, y: 1.2}
+                }
+                else {
+                    Vec2 {x: -0.2, y: 1.2}
+                };
+                let inst = self.filler.draw_quad(cx, self.filler_walk);
+                inst.push_vec2(cx, line_vec);
+                inst.push_float(cx, 0.);
+            }
+            else if lasts[i + 1] {
+                cx.walk_turtle(self.filler_walk);
+            }
+            else {
+                let inst = self.filler.draw_quad(cx, self.filler_walk);
+                inst.push_vec2(cx, Vec2 {x: -0.2, y: 1.2});
+                inst.push_float(cx, 0.);
+            }
+        }
+    }
+
+    fn draw_icon(&mut self, cx: &mut Cx, icon: TreeIcon) {
+        let icon = match icon {
+            TreeIcon::None => {
+                cx.walk_turtle(self.icon_walk);
+                return
+            },
+            TreeIcon::Folder => 1.,
+            TreeIcon::Leaf => 2.
+        };
+        let inst = self.filler.draw_quad(cx, self.icon_walk);
+        inst.push_vec2(cx, Vec2::default());
+        inst.push_float(cx, icon);
+    }
+
+    fn draw_drop_marker(&mut self, cx: &mut Cx, origin: Vec2, depth: usize, height: f32, pos: TreeDropPos) {
+        let x = origin.x + self.label_x(depth) - self.icon_walk.width.fixed();
+        let w = (cx.get_width_total() - (x - origin.x)).max(0.);
+        let (rect, into) = match pos {
+            TreeDropPos::Before => (Rect {x: x, y: origin.y - 1., w: w, h: 2.}, 0.),
+            TreeDropPos::After => (Rect {x: x, y: origin.y + height - 1., w: w, h: 2.}, 0.),
+            TreeDropPos::Into => (Rect {x: origin.x, y: origin.y, w: cx.get_width_total(), h: height}, 1.)
+        };
+        let inst = self.drop_marker.draw_quad_abs(cx, rect);
+        inst.push_float(cx, into);
+    }
+
+    fn draw_loading(&mut self, cx: &mut Cx, walk: &mut TreeDrawWalk, scale: f64) {
+        self.node_bg.color = if walk.counter & 1 == 0 {Theme::color_bg_selected().get(cx)} else {Theme::color_bg_odd().get(cx)};
+        let mut node_layout = self.node_layout.clone();
+        node_layout.walk.height = Height::Fix(self.row_height * scale as f32);
+        let inst = self.node_bg.begin_quad(cx, node_layout);
+        self.draw_fillers(cx, walk.path.len(), true, true, &walk.lasts);
+        self.draw_icon(cx, TreeIcon::None);
+        cx.turtle_align_y();
+        self.tree_text.font_scale = scale as f32;
+        self.tree_text.color = self.color_loading;
+        self.tree_text.wrapping = Wrapping::Ellipsis(cx.get_width_left() - 10.);
+        self.tree_text.draw_text(cx, "loading...");
+        self.node_bg.end_quad(cx, &inst);
+        cx.turtle_new_line();
+        walk.counter += 1;
+    }
+
+    fn draw_nodes<T>(&mut self, cx: &mut Cx, source: &mut dyn TreeSource<T>, nodes: &mut Vec<TreeNode<T>>, walk: &mut TreeDrawWalk, scale: f64, closing: bool)
+    where T: Clone + PartialEq
+    {
+        let len = nodes.len();
+        for (index, node) in nodes.iter_mut().enumerate() {
+            let depth = walk.path.len();
+            let is_first = index == 0;
+            let is_last = index == len - 1;
+            walk.path.push(index);
+
+            node.is_leaf = source.is_leaf(&node.item);
+            let counter = walk.counter;
+            let marked = node.marked;
+            node.animator.init(cx, | cx | TreeItemDraw::get_default_anim(cx, counter, marked));
+            self.node_bg.color = node.animator.last_color(cx, Quad::color());
+
+            let height = self.row_height * scale as f32;
+            let mut node_layout = self.node_layout.clone();
+            node_layout.walk.height = Height::Fix(height);
+            let inst = self.node_bg.begin_quad(cx, node_layout);
+            node.animator.set_area(cx, inst.clone().into());
+            let origin = cx.get_turtle_origin();
+
+            self.draw_fillers(cx, depth, is_first, is_last, &walk.lasts);
+            self.draw_icon(cx, source.icon(&node.item));
+
+            cx.turtle_align_y();
+            self.tree_text.font_scale = scale as f32;
+            self.tree_text.color = if !node.is_leaf || marked {self.color_tree_folder} else {self.color_tree_leaf};
+            self.tree_text.wrapping = Wrapping::Ellipsis(cx.get_width_left() - 10.);
+            self.tree_text.draw_text(cx, &source.label(&node.item));
+
+            if let Some((drop_path, pos)) = &walk.drop {
+                if *drop_path == walk.path {
+                    self.draw_drop_marker(cx, origin, depth, height, *pos);
+                }
+            }
+
+            self.node_bg.end_quad(cx, &inst);
+            cx.turtle_new_line();
+
+            walk.rows.push(TreeRow {path: walk.path.clone(), counter: counter});
+            // the rows of a closing node don't count for the alternating colors
+            if !closing {
+                walk.counter += 1;
+            }
+
+            let (child_scale, new_state) = match node.state {
+                TreeNodeState::Opening(fac) => {
+                    walk.animating = true;
+                    if fac < 0.001 {
+                        (1.0, TreeNodeState::Open)
+                    }
+                    else {
+                        (1.0 - fac, TreeNodeState::Opening(fac * 0.6))
+                    }
+                },
+                TreeNodeState::Closing(fac) => {
+                    walk.animating = true;
+                    if fac < 0.001 {
+                        (0.0, TreeNodeState::Closed)
+                    }
+                    else {
+                        (fac, TreeNodeState::Closing(fac * 0.6))
+                    }
+                },
+                TreeNodeState::Open => (1.0, TreeNodeState::Open),
+                TreeNodeState::Closed => (1.0, TreeNodeState::Closed)
+            };
+            let child_closing = closing || if let TreeNodeState::Closing(_) = new_state {true} else {false};
+            node.state = new_state;
+
+            if node.is_leaf {
+                node.children = None;
+            }
+            else if let TreeNodeState::Closed = node.state {
+                // closed nodes ask again when they open after a reload
+                if walk.reload {
+                    node.children = None;
+                }
+            }
+            else {
+                if node.children.is_none() || walk.reload {
+                    if let Some(items) = source.children(&node.item) {
+                        let old = node.children.take().unwrap_or(Vec::new());
+                        node.children = Some(TreeView::<T>::sync_nodes(old, items));
+                    }
+                }
+                walk.lasts.push(is_last);
+                if let Some(children) = &mut node.children {
+                    self.draw_nodes(cx, source, children, walk, scale * child_scale, child_closing);
+                }
+                else {
+                    self.draw_loading(cx, walk, scale * child_scale);
+                }
+                walk.lasts.pop();
+            }
+            walk.path.pop();
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct TreeView<T> {
+    pub view: ScrollView,
+    pub drag_view: View,
+    pub item_draw: TreeItemDraw,
+    pub drag_bg: Quad,
+    pub roots: Vec<TreeNode<T>>,
+    pub multi_select: bool,
+    // a press anywhere on a row opens or closes it, not only in front of the label
+    pub open_on_click: bool,
+    pub key_button: ButtonLogic,
+    pub _rows: Vec<TreeRow>,
+    // the node the keys move from, and the one a shift selection starts at
+    pub _cursor: Option<T>,
+    pub _anchor: Option<T>,
+    // a press on a selected node picks it alone on release, unless it turned into a drag
+    pub _down_select: Option<T>,
+    pub _drag_move: Option<FingerMoveEvent>,
+    pub _drop: Option<(Vec<usize>, TreeDropPos)>,
+    pub _reload: bool,
+}
+
+impl<T> TreeView<T> where T: Clone + PartialEq {
+    pub fn new(cx: &mut Cx) -> Self {
+        Self {
+            view: ScrollView {
+                scroll_v: Some(ScrollBar {
+                    smoothing: Some(0.25),
+                    ..ScrollBar::new(cx)
+                }),
+                ..ScrollView::new(cx)
+            },
+            drag_view: View {
+                is_overlay: true,
+                ..View::new(cx)
+            },
+            item_draw: TreeItemDraw::new(cx),
+            drag_bg: Quad::new(cx),
+            roots: Vec::new(),
+            multi_select: true,
+            open_on_click: false,
+            key_button: ButtonLogic::default(),
+            _rows: Vec::new(),
+            _cursor: None,
+            _anchor: None,
+            _down_select: None,
+            _drag_move: None,
+            _drop: None,
+            _reload: false,
+        }
+    }
+
+    // keeps the nodes that are still there so they stay open and selected
+    fn sync_nodes(old: Vec<TreeNode<T>>, items: Vec<T>) -> Vec<TreeNode<T>> {
+        let mut old: Vec<Option<TreeNode<T>>> = old.into_iter().map( | node | Some(node)).collect();
+        items.into_iter().map( | item | {
+            if let Some(pos) = old.iter().position( | node | node.as_ref().map_or(false, | node | node.item == item)) {
+                let mut node = old[pos].take().unwrap();
+                node.item = item;
+                node
+            }
+            else {
+                TreeNode::new(item)
+            }
+        }).collect()
+    }
+
+    pub fn set_roots(&mut self, cx: &mut Cx, roots: Vec<T>) {
+        let old = std::mem::replace(&mut self.roots, Vec::new());
+        self.roots = Self::sync_nodes(old, roots);
+        self.reload(cx);
+    }
+
+    // asks the source for the children of the open nodes again on the next draw
+    pub fn reload(&mut self, cx: &mut Cx) {
+        self._reload = true;
+        self.view.redraw_view_area(cx);
+    }
+
+    pub fn redraw_tree_view(&mut self, cx: &mut Cx) {
+        self.view.redraw_view_area(cx);
+    }
+
+    fn node_at<'a>(nodes: &'a mut [TreeNode<T>], path: &[usize]) -> Option<&'a mut TreeNode<T>> {
+        let (first, rest) = path.split_first() ?;
+        let node = nodes.get_mut(*first) ?;
+        if rest.len() == 0 {
+            return Some(node)
+        }
+        Self::node_at(node.children.as_mut() ?, rest)
+    }
+
+    fn node_ref<'a>(nodes: &'a [TreeNode<T>], path: &[usize]) -> Option<&'a TreeNode<T>> {
+        let (first, rest) = path.split_first() ?;
+        let node = nodes.get(*first) ?;
+        if rest.len() == 0 {
+            return Some(node)
+        }
+        Self::node_ref(node.children.as_ref() ?, rest)
+    }
+
+    fn walk_visible(nodes: &[TreeNode<T>], path: &mut Vec<usize>, out: &mut Vec<Vec<usize>>) {
+        for (index, node) in nodes.iter().enumerate() {
+            path.push(index);
+            out.push(path.clone());
+            if node.is_open() {
+                if let Some(children) = &node.children {
+                    Self::walk_visible(children, path, out);
+                }
+            }
+            path.pop();
+        }
+    }
+
+    // the paths of the nodes that are not inside a closed node, top to bottom
+    pub fn visible_paths(&self) -> Vec<Vec<usize>> {
+        let mut out = Vec::new();
+        Self::walk_visible(&self.roots, &mut Vec::new(), &mut out);
+        out
+    }
+
+    fn walk_open(nodes: &[TreeNode<T>], out: &mut Vec<T>) {
+        for node in nodes {
+            if !node.is_leaf && node.is_open() {
+                out.push(node.item.clone());
+                if let Some(children) = &node.children {
+                    Self::walk_open(children, out);
+                }
+            }
+        }
+    }
+
+    // the open nodes whose parents are open too, to keep them for next time
+    pub fn open_items(&self) -> Vec<T> {
+        let mut out = Vec::new();
+        Self::walk_open(&self.roots, &mut out);
+        out
+    }
+
+    fn open_nodes(nodes: &mut [TreeNode<T>], source: &mut dyn TreeSource<T>, items: &[T]) {
+        for node in nodes {
+            node.is_leaf = source.is_leaf(&node.item);
+            if node.is_leaf || !items.contains(&node.item) {
+                node.state = TreeNodeState::Closed;
+                continue;
+            }
+            node.state = TreeNodeState::Open;
+            if let Some(children) = source.children(&node.item) {
+                let old = node.children.take().unwrap_or(Vec::new());
+                node.children = Some(Self::sync_nodes(old, children));
+            }
+            if let Some(children) = &mut node.children {
+                Self::open_nodes(children, source, items);
+            }
+        }
+    }
+
+    // opens the nodes in items without animating and closes the others, like open_items returned them
+    pub fn set_open_items(&mut self, cx: &mut Cx, source: &mut dyn TreeSource<T>, items: &[T]) {
+        Self::open_nodes(&mut self.roots, source, items);
+        self.view.redraw_view_area(cx);
+    }
+
+    fn walk_marked(nodes: &[TreeNode<T>], path: &mut Vec<usize>, out: &mut Vec<Vec<usize>>) {
+        for (index, node) in nodes.iter().enumerate() {
+            path.push(index);
+            if node.marked {
+                out.push(path.clone());
+            }
+            if let Some(children) = &node.children {
+                Self::walk_marked(children, path, out);
+            }
+            path.pop();
+        }
+    }
+
+    fn marked_paths(&self) -> Vec<Vec<usize>> {
+        let mut out = Vec::new();
+        Self::walk_marked(&self.roots, &mut Vec::new(), &mut out);
+        out
+    }
+
+    pub fn selected(&self) -> Vec<T> {
+        self.marked_paths().iter().filter_map( | path | Self::node_ref(&self.roots, path)).map( | node | node.item.clone()).collect()
+    }
+
+    fn unmark(nodes: &mut [TreeNode<T>]) {
+        for node in nodes {
+            node.marked = false;
+            if let Some(children) = &mut node.children {
+                Self::unmark(children);
+            }
+        }
+    }
+
+    fn find_path(&self, item: &T) -> Option<Vec<usize>> {
+        self.visible_paths().into_iter().find( | path | Self::node_ref(&self.roots, path).map_or(false, | node | node.item == *item))
+    }
+
+    // replays the row colors after the selection changed
+    fn update_marks(&mut self, cx: &mut Cx) -> TreeViewEvent<T> {
+        for row in &self._rows {
+            if let Some(node) = Self::node_at(&mut self.roots, &row.path) {
+                node.animator.play_anim(cx, TreeItemDraw::get_default_anim(cx, row.counter, node.marked));
+            }
+        }
+        self.view.redraw_view_area(cx);
+        TreeViewEvent::Select(self.selected())
+    }
+
+    pub fn select_only(&mut self, cx: &mut Cx, path: &[usize]) -> TreeViewEvent<T> {
+        Self::unmark(&mut self.roots);
+        if let Some(node) = Self::node_at(&mut self.roots, path) {
+            node.marked = true;
+            self._cursor = Some(node.item.clone());
+            self._anchor = self._cursor.clone();
+        }
+        self.update_marks(cx)
+    }
+
+    fn select_range(&mut self, cx: &mut Cx, path: &[usize]) -> TreeViewEvent<T> {
+        let visible = self.visible_paths();
+        let end = if let Some(end) = visible.iter().position( | p | p.as_slice() == path) {end} else {
+            return TreeViewEvent::None
+        };
+        let start = self._anchor.as_ref().and_then( | anchor | {
+            visible.iter().position( | p | Self::node_ref(&self.roots, p).map_or(false, | node | node.item == *anchor))
+        }).unwrap_or(end);
+        Self::unmark(&mut self.roots);
+        for path in &visible[start.min(end)..= start.max(end)] {
+            if let Some(node) = Self::node_at(&mut self.roots, path) {
+                node.marked = true;
+            }
+        }
+        self._cursor = Self::node_ref(&self.roots, path).map( | node | node.item.clone());
+        self.update_marks(cx)
+    }
+
+    fn toggle_mark(&mut self, cx: &mut Cx, path: &[usize]) -> TreeViewEvent<T> {
+        if let Some(node) = Self::node_at(&mut self.roots, path) {
+            node.marked = !node.marked;
+            self._cursor = Some(node.item.clone());
+            self._anchor = self._cursor.clone();
+        }
+        self.update_marks(cx)
+    }
+
+    pub fn set_open(&mut self, cx: &mut Cx, path: &[usize], open: bool) {
+        if let Some(node) = Self::node_at(&mut self.roots, path) {
+            if node.is_leaf || node.is_open() == open {
+                return
+            }
+            node.state = match node.state {
+                TreeNodeState::Opening(fac) => TreeNodeState::Closing(1.0 - fac),
+                TreeNodeState::Closing(fac) => TreeNodeState::Opening(1.0 - fac),
+                TreeNodeState::Open => TreeNodeState::Closing(1.0),
+                TreeNodeState::Closed => TreeNodeState::Opening(1.0)
+            };
+            // what gets hidden is not selected anymore
+            if !open {
+                if let Some(children) = &mut node.children {
+                    Self::unmark(children);
+                }
+            }
+            self.view.redraw_view_area(cx);
+        }
+    }
+
+    fn find_drop(&self, cx: &Cx, abs: Vec2, source: &dyn TreeSource<T>) -> Option<(Vec<usize>, TreeDropPos)> {
+        let dragged = self.marked_paths();
+        for row in &self._rows {
+            let node = Self::node_ref(&self.roots, &row.path) ?;
+            let rect = node.animator.area.get_rect(cx);
+            if !rect.contains(abs.x, abs.y) {
+                continue;
+            }
+            if dragged.iter().any( | path | row.path.starts_with(path)) {
+                return None
+            }
+            let rel_y = abs.y - rect.y;
+            let pos = if node.is_leaf {
+                if rel_y < rect.h * 0.5 {TreeDropPos::Before} else {TreeDropPos::After}
+            }
+            else if rel_y < rect.h * 0.25 {
+                TreeDropPos::Before
+            }
+            else if rel_y > rect.h * 0.75 && !node.is_open() {
+                TreeDropPos::After
+            }
+            else {
+                TreeDropPos::Into
+            };
+            let nodes: Vec<T> = dragged.iter().filter_map( | path | Self::node_ref(&self.roots, path)).map( | node | node.item.clone()).collect();
+            if !source.can_drop(&nodes, &node.item, pos) {
+                return None
+            }
+            return Some((row.path.clone(), pos))
+        }
+        None
+    }
+
+    fn handle_key(&mut self, cx: &mut Cx, ke: &KeyEvent) -> TreeViewEvent<T> {
+        let visible = self.visible_paths();
+        if visible.len() == 0 {
+            return TreeViewEvent::None
+        }
+        let last = visible.len() - 1;
+        let cursor = self._cursor.as_ref().and_then( | cursor | {
+            visible.iter().position( | path | Self::node_ref(&self.roots, path).map_or(false, | node | node.item == *cursor))
+        });
+        let page = ((self.view.get_rect(cx).h / self.item_draw.row_height.max(1.)) as usize).max(1);
+        let next = match ke.key_code {
+            KeyCode::ArrowUp => cursor.map_or(0, | c | c.max(1) - 1),
+            KeyCode::ArrowDown => cursor.map_or(0, | c | (c + 1).min(last)),
+            KeyCode::PageUp => cursor.map_or(0, | c | c.max(page) - page),
+            KeyCode::PageDown => cursor.map_or(0, | c | (c + page).min(last)),
+            KeyCode::Home => 0,
+            KeyCode::End => last,
+            KeyCode::ArrowLeft => {
+                let c = if let Some(c) = cursor {c} else {return TreeViewEvent::None};
+                let path = visible[c].clone();
+                let node = Self::node_ref(&self.roots, &path).unwrap();
+                if !node.is_leaf && node.is_open() {
+                    let item = node.item.clone();
+                    self.set_open(cx, &path, false);
+                    return TreeViewEvent::Toggle {item: item, open: false}
+                }
+                if path.len() < 2 {
+                    return TreeViewEvent::None
+                }
+                visible.iter().position( | p | p.as_slice() == &path[0..path.len() - 1]).unwrap_or(c)
+            },
+            KeyCode::ArrowRight => {
+                let c = if let Some(c) = cursor {c} else {return TreeViewEvent::None};
+                let path = visible[c].clone();
+                let node = Self::node_ref(&self.roots, &path).unwrap();
+                if node.is_leaf {
+                    return TreeViewEvent::None
+                }
+                if !node.is_open() {
+                    let item = node.item.clone();
+                    self.set_open(cx, &path, true);
+                    return TreeViewEvent::Toggle {item: item, open: true}
+                }
+                if c < last && visible[c + 1].len() > path.len() {c + 1} else {c}
+            },
+            KeyCode::KeyA if self.multi_select && (ke.modifiers.control || ke.modifiers.logo) => {
+                for path in &visible {
+                    if let Some(node) = Self::node_at(&mut self.roots, path) {
+                        node.marked = true;
+                    }
+                }
+                return self.update_marks(cx)
+            },
+            _ => return TreeViewEvent::None
+        };
+        let row_height = self.item_draw.row_height;
+        self.view.scroll_into_view(cx, Rect {x: 0., y: next as f32 * row_height, w: 0., h: row_height});
+        if self.multi_select && ke.modifiers.shift {
+            self.select_range(cx, &visible[next])
+        }
+        else {
+            self.select_only(cx, &visible[next])
+        }
+    }
+
+    pub fn handle_tree_view(&mut self, cx: &mut Cx, event: &mut Event, source: &dyn TreeSource<T>) -> TreeViewEvent<T> {
+        self.view.handle_scroll_view(cx, event);
+
+        let view_area = self.view.get_view_area(cx);
+        match self.key_button.handle_key_activation(cx, event, view_area) {
+            ButtonEvent::Clicked => if let Some(cursor) = &self._cursor {
+                return TreeViewEvent::Activate(cursor.clone())
+            },
+            ButtonEvent::Down => return TreeViewEvent::None,
+            _ => ()
+        }
+        match event.hits(cx, view_area, HitOpt::default()) {
+            Event::KeyDown(ke) => return self.handle_key(cx, &ke),
+            Event::KeyFocus(_) | Event::KeyFocusLost(_) => self.view.redraw_view_area(cx),
+            _ => ()
+        }
+
+        let mut finger_down = None;
+        let mut finger_move = None;
+        let mut finger_up = None;
+        let mut a11y_action = None;
+        for row in &self._rows {
+            let node = if let Some(node) = Self::node_at(&mut self.roots, &row.path) {node} else {continue};
+            let area = node.animator.area;
+            match event.hits(cx, area, HitOpt::default()) {
+                Event::Animate(ae) => {
+                    node.animator.calc_area(cx, area, ae.time);
+                },
+                Event::AnimEnded(_) => {
+                    node.animator.end();
+                },
+                Event::FingerHover(fe) => {
+                    cx.set_hover_mouse_cursor(MouseCursor::Hand);
+                    match fe.hover_state {
+                        HoverState::In => {
+                            node.animator.play_anim(cx, TreeItemDraw::get_over_anim(cx, row.counter, node.marked));
+                        },
+                        HoverState::Out => {
+                            node.animator.play_anim(cx, TreeItemDraw::get_default_anim(cx, row.counter, node.marked));
+                        },
+                        _ => ()
+                    }
+                },
+                Event::FingerDown(fe) => finger_down = Some((row.path.clone(), fe)),
+                Event::FingerMove(fe) => finger_move = Some(fe),
+                Event::FingerUp(fe) => finger_up = Some(fe),
+                Event::A11yAction(ae) => a11y_action = Some((row.path.clone(), ae.action)),
+                _ => ()
+            }
+        }
+
+        if let Some((path, action)) = a11y_action {
+            match action {
+                A11yAction::Select => return self.select_only(cx, &path),
+                A11yAction::Expand | A11yAction::Collapse => if let Some(node) = Self::node_ref(&self.roots, &path) {
+                    let (item, open) = (node.item.clone(), action == A11yAction::Expand);
+                    self.set_open(cx, &path, open);
+                    return TreeViewEvent::Toggle {item: item, open: open}
+                },
+                A11yAction::Press => if let Some(node) = Self::node_ref(&self.roots, &path) {
+                    return TreeViewEvent::Activate(node.item.clone())
+                },
+                _ => ()
+            }
+            return TreeViewEvent::None
+        }
+
+        if let Some((path, fe)) = finger_down {
+            cx.set_key_focus(view_area);
+            let node = Self::node_ref(&self.roots, &path).unwrap();
+            let (item, is_leaf, is_open, marked) = (node.item.clone(), node.is_leaf, node.is_open(), node.marked);
+            if fe.is_context_click() {
+                // a right click selects what the menu is for, it doesn't open or drag it
+                cx.captured_fingers[fe.digit] = Area::Empty;
+                if !marked {
+                    self.select_only(cx, &path);
+                }
+                return TreeViewEvent::ContextMenu {item: item, abs: fe.abs}
+            }
+            if !is_leaf && (self.open_on_click || fe.tap_count > 1 || fe.rel.x < self.item_draw.label_x(path.len() - 1)) {
+                self.set_open(cx, &path, !is_open);
+            }
+            if fe.tap_count > 1 {
+                self.select_only(cx, &path);
+                return TreeViewEvent::Activate(item)
+            }
+            if self.multi_select && (fe.modifiers.control || fe.modifiers.logo) {
+                return self.toggle_mark(cx, &path)
+            }
+            if self.multi_select && fe.modifiers.shift {
+                return self.select_range(cx, &path)
+            }
+            if marked {
+                self._down_select = Some(item);
+                return TreeViewEvent::None
+            }
+            return self.select_only(cx, &path)
+        }
+
+        if let Some(fe) = finger_move {
+            cx.set_down_mouse_cursor(MouseCursor::Hand);
+            if self._drag_move.is_none() && fe.move_distance() <= 10. {
+                return TreeViewEvent::None
+            }
+            self._down_select = None;
+            self._drag_move = Some(fe.clone());
+            let drop = self.find_drop(cx, fe.abs, source);
+            if drop != self._drop {
+                self._drop = drop;
+                self.view.redraw_view_area(cx);
+            }
+            self.drag_view.redraw_view_area(cx);
+            if !view_area.get_rect(cx).contains(fe.abs.x, fe.abs.y) {
+                return TreeViewEvent::DragMove {fe: fe, nodes: self.selected()}
+            }
+            return TreeViewEvent::DragCancel
+        }
+
+        if let Some(fe) = finger_up {
+            if self._drag_move.take().is_some() {
+                self.drag_view.redraw_view_area(cx);
+                self.view.redraw_view_area(cx);
+                let drop = self._drop.take();
+                if !view_area.get_rect(cx).contains(fe.abs.x, fe.abs.y) {
+                    return TreeViewEvent::DragEnd {fe: fe, nodes: self.selected()}
+                }
+                if let Some((path, pos)) = drop {
+                    if let Some(target) = Self::node_ref(&self.roots, &path) {
+                        return TreeViewEvent::Drop {nodes: self.selected(), target: target.item.clone(), pos: pos}
+                    }
+                }
+                return TreeViewEvent::None
+            }
+            if let Some(item) = self._down_select.take() {
+                if let Some(path) = self.find_path(&item) {
+                    return self.select_only(cx, &path)
+                }
+            }
+        }
+        TreeViewEvent::None
+    }
+
+    pub fn draw_tree_view(&mut self, cx: &mut Cx, source: &mut dyn TreeSource<T>) {
+        if self.view.begin_view(cx, Layout::default()).is_err() {return}
+        cx.add_focusable(self.view.get_view_area(cx));
+
+        self.item_draw.apply_style(cx);
+        let mut walk = TreeDrawWalk {
+            path: Vec::new(),
+            lasts: Vec::new(),
+            counter: 0,
+            rows: Vec::new(),
+            reload: self._reload,
+            animating: false,
+            drop: self._drop.clone(),
+        };
+        self._reload = false;
+        self.item_draw.draw_nodes(cx, source, &mut self.roots, &mut walk, 1.0, false);
+        self._rows = walk.rows;
+        if walk.animating {
+            self.view.redraw_view_area(cx);
+        }
+
+        // the rows nest by the length of their path
+        cx.begin_a11y_node(A11yNode::new(A11yRole::Tree, "", self.view.get_view_area(cx)));
+        let mut depth = 0;
+        for row in &self._rows {
+            let node = if let Some(node) = Self::node_ref(&self.roots, &row.path) {node} else {continue};
+            while depth >= row.path.len() && depth > 0 {
+                cx.end_a11y_node();
+                depth -= 1;
+            }
+            let mut actions = vec![A11yAction::Select, A11yAction::Press];
+            if !node.is_leaf {
+                actions.push(if node.is_open() {A11yAction::Collapse} else {A11yAction::Expand});
+            }
+            cx.begin_a11y_node(A11yNode {
+                expanded: if node.is_leaf {None} else {Some(node.is_open())},
+                selected: node.marked,
+                actions: actions,
+                ..A11yNode::new(A11yRole::TreeItem, &source.label(&node.item), node.animator.area)
+            });
+            depth += 1;
+        }
+        for _ in 0..depth {
+            cx.end_a11y_node();
+        }
+        cx.end_a11y_node();
+
+        // draw filler nodes
+        let mut counter = walk.counter;
+        let row_height = self.item_draw.row_height;
+        if row_height > 0. {
+            let view_total = cx.get_turtle_bounds();
+            let rect_now = cx.get_turtle_rect();
+            let mut y = view_total.y;
+            while y < rect_now.h {
+                self.item_draw.node_bg.color = if counter & 1 == 0 {Theme::color_bg_selected().get(cx)} else {Theme::color_bg_odd().get(cx)};
+                self.item_draw.node_bg.draw_quad(
+                    cx,
+                    Walk::wh(Width::Fill, Height::Fix((rect_now.h - y).min(row_height))),
+                );
+                cx.turtle_new_line();
+                y += row_height;
+                counter += 1;
+            }
+        }
+
+        // the labels of the dragged nodes follow the mouse
+        if let Some(mv) = &self._drag_move {
+            if let Ok(()) = self.drag_view.begin_view(cx, Layout {
+                abs_origin: Some(Vec2 {x: mv.abs.x + 5., y: mv.abs.y + 5.}),
+                direction: Direction::Down,
+                ..Default::default()
+            }) {
+                self.drag_bg.shader = TreeItemDraw::shader_drag_bg().get(cx);
+                self.drag_bg.color = TreeItemDraw::color_drag_bg().get(cx);
+                self.drag_bg.z = 10.0;
+                self.item_draw.tree_text.z = 10.0;
+                self.item_draw.tree_text.font_scale = 1.0;
+                self.item_draw.tree_text.wrapping = Wrapping::None;
+                self.item_draw.tree_text.color = self.item_draw.color_tree_folder;
+                for path in self.marked_paths() {
+                    let node = if let Some(node) = Self::node_ref(&self.roots, &path) {node} else {continue};
+                    let inst = self.drag_bg.begin_quad(cx, TreeItemDraw::layout_drag_bg().get(cx));
+                    self.item_draw.tree_text.draw_text(cx, &source.label(&node.item));
+                    self.drag_bg.end_quad(cx, &inst);
+                    cx.turtle_new_line();
+                }
+                self.item_draw.tree_text.z = 0.001;
+                self.drag_view.end_view(cx);
+            }
+        }
+
+        self.item_draw.shadow.draw_shadow_top(cx);
+        self.item_draw.focus_ring.draw_focus_ring(cx, self.view.get_view_area(cx));
+
+        self.view.end_view(cx);
+    }
+}
@@ -0,0 +1,347 @@
+use makepad_render::*;
+use crate::textbuffer::*;
+use crate::undotree::*;
+use crate::linediff::*;
+use crate::widgetstyle::*;
+
+// A panel over the editor that lists the states in the undo tree, newest first with
+// the branches indented, and previews what jumping to the selected one would change.
+
+#[derive(Clone)]
+pub struct UndoHistoryEntry {
+    pub node: usize,
+    pub depth: usize,
+    pub label: String,
+    pub time: f64,
+    pub on_path: bool,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum UndoPreviewLine {
+    Header,
+    Context,
+    Removed,
+    Added,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum UndoHistoryEvent {
+    None,
+    Jump(usize),
+    Close
+}
+
+#[derive(Clone)]
+pub struct UndoHistory {
+    pub view: View,
+    pub bg: Quad,
+    pub item_bg: Quad,
+    pub text: Text,
+    pub is_open: bool,
+    pub entries: Vec<UndoHistoryEntry>,
+    pub selected: usize,
+    pub max_visible: usize,
+    pub max_preview_lines: usize,
+    pub preview: Vec<(UndoPreviewLine, String)>,
+    pub _entries_for: (usize, usize),
+    pub _preview_for: Option<(usize, u32)>,
+    pub _item_areas: Vec<(usize, Area)>,
+    pub _bg_area: Area,
+}
+
+impl UndoHistory {
+    pub fn new(cx: &mut Cx) -> Self {
+        Self::default_keymap(cx);
+        Self {
+            view: View::proto_overlay(cx),
+            bg: Quad::new(cx),
+            item_bg: Quad::new(cx),
+            text: Text::new(cx),
+            is_open: false,
+            entries: Vec::new(),
+            selected: 0,
+            max_visible: 12,
+            max_preview_lines: 24,
+            preview: Vec::new(),
+            _entries_for: (0, 0),
+            _preview_for: None,
+            _item_areas: Vec::new(),
+            _bg_area: Area::Empty,
+        }
+    }
+
+    pub fn layout_bg() -> LayoutId {uid!()}
+    pub fn layout_item() -> LayoutId {uid!()}
+    pub fn text_style_item() -> TextStyleId {uid!()}
+    pub fn color_bg() -> ColorId {uid!()}
+    pub fn color_item_selected() -> ColorId {uid!()}
+    pub fn color_label() -> ColorId {uid!()}
+    pub fn color_off_path() -> ColorId {uid!()}
+    pub fn color_header() -> ColorId {uid!()}
+    pub fn color_removed() -> ColorId {uid!()}
+    pub fn color_added() -> ColorId {uid!()}
+    pub fn width() -> FloatId {uid!()}
+    pub fn item_height() -> FloatId {uid!()}
+
+    pub fn style(cx: &mut Cx, opt: &StyleOptions) {
+        Self::width().set(cx, 460. * opt.scale);
+        Self::item_height().set(cx, 20. * opt.scale);
+        Self::layout_bg().set(cx, Layout {
+            walk: Walk::wh(Width::Fill, Height::Compute),
+            padding: Padding::all(4.),
+            ..Layout::default()
+        });
+        Self::layout_item().set(cx, Layout {
+            walk: Walk::wh(Width::Fill, Height::Fix(Self::item_height().get(cx))),
+            align: Align::left_center(),
+            padding: Padding {l: 6., t: 0., r: 6., b: 0.},
+            line_wrap: LineWrap::None,
+            ..Layout::default()
+        });
+        Self::text_style_item().set(cx, Theme::text_style_fixed().get(cx));
+        Self::color_bg().set(cx, Theme::color_bg_normal().get(cx));
+        Self::color_item_selected().set(cx, Theme::color_bg_marked().get(cx));
+        Self::color_label().set(cx, Theme::color_text_selected_focus().get(cx));
+        Self::color_off_path().set(cx, Theme::color_text_deselected_defocus().get(cx));
+        Self::color_header().set(cx, pick!(#6a9fb5).get(cx));
+        Self::color_removed().set(cx, pick!(#c66).get(cx));
+        Self::color_added().set(cx, pick!(#8b6).get(cx));
+    }
+
+    pub fn default_keymap(cx: &mut Cx) {
+        let ctx = Some("undohistory");
+        let km = &mut cx.keymap;
+        km.register_command("undohistory.next", "Next Undo State", &["down"], ctx);
+        km.register_command("undohistory.prev", "Previous Undo State", &["up"], ctx);
+        km.register_command("undohistory.jump", "Go to Undo State", &["return"], ctx);
+        km.register_command("undohistory.close", "Close Undo History", &["escape"], ctx);
+    }
+
+    pub fn open(&mut self, cx: &mut Cx) {
+        self.is_open = true;
+        self._entries_for = (0, 0);
+        self.view.redraw_view_area(cx);
+    }
+
+    pub fn close(&mut self, cx: &mut Cx) {
+        if self.is_open {
+            self.is_open = false;
+            self.entries.truncate(0);
+            self.preview.truncate(0);
+            self._preview_for = None;
+            self.view.redraw_view_area(cx);
+        }
+    }
+
+    fn grouping_label(grouping: &TextUndoGrouping) -> &'static str {
+        match grouping {
+            TextUndoGrouping::Space | TextUndoGrouping::Character(_) => "Typing",
+            TextUndoGrouping::LiveEdit(_) => "Live edit",
+            TextUndoGrouping::Newline => "Newline",
+            TextUndoGrouping::Backspace(_) | TextUndoGrouping::Delete(_) => "Delete",
+            TextUndoGrouping::Block => "Replace",
+            TextUndoGrouping::Tab => "Indent",
+            TextUndoGrouping::Cut => "Cut",
+            TextUndoGrouping::Format => "Format",
+            TextUndoGrouping::Remote(_) => "Remote edit",
+            TextUndoGrouping::Other => "Edit",
+        }
+    }
+
+    pub fn format_age(now: f64, time: f64) -> String {
+        if time <= 0. {
+            return String::new()
+        }
+        let secs = (now - time).max(0.) as u64;
+        if secs < 60 {format!("{}s ago", secs)}
+        else if secs < 3600 {format!("{}m ago", secs / 60)}
+        else if secs < 86400 {format!("{}h ago", secs / 3600)}
+        else {format!("{}d ago", secs / 86400)}
+    }
+
+    // long lines are cut so the panel keeps its width
+    fn preview_line(prefix: &str, line: &[char]) -> String {
+        let mut out = prefix.to_string();
+        out.extend(line.iter().take(72));
+        if line.len() > 72 {
+            out.push_str("..");
+        }
+        out
+    }
+
+    // rebuilds the list when the tree grew or we moved in it, selecting the current state
+    fn update_entries(&mut self, tree: &TextUndoTree) {
+        if self._entries_for == (tree.nodes.len(), tree.current) && self.entries.len() > 0 {
+            return
+        }
+        self._entries_for = (tree.nodes.len(), tree.current);
+        let path = tree.path_to(tree.current);
+        self.entries.truncate(0);
+        for node in (0..tree.nodes.len()).rev() {
+            if !tree.is_undo_stop(node) && node != tree.current {
+                continue
+            }
+            let label = match tree.grouping(node) {
+                Some(grouping) => Self::grouping_label(grouping),
+                None => "Original"
+            };
+            self.entries.push(UndoHistoryEntry {
+                node: node,
+                depth: tree.branch_depth(node),
+                label: label.to_string(),
+                time: tree.nodes[node].time,
+                on_path: path.contains(&node),
+            });
+        }
+        self.selected = self.entries.iter().position( | entry | entry.node == tree.current).unwrap_or(0);
+    }
+
+    // the diff from the current text to the selected state, a few lines of context around each change
+    fn update_preview(&mut self, text_buffer: &TextBuffer) {
+        let node = match self.entries.get(self.selected) {
+            Some(entry) => entry.node,
+            None => return
+        };
+        if self._preview_for == Some((node, text_buffer.mutation_id)) {
+            return
+        }
+        self._preview_for = Some((node, text_buffer.mutation_id));
+        self.preview.truncate(0);
+        let target = text_buffer.lines_at_undo_node(node);
+        let target = TextBuffer {lines: target, ..TextBuffer::default()};
+        let old_lines: Vec<&Vec<char>> = text_buffer.lines.iter().collect();
+        let new_lines: Vec<&Vec<char>> = target.lines.iter().collect();
+        let hunks = diff_line_hunks(&text_buffer.line_hashes(), &target.line_hashes());
+        if hunks.len() == 0 {
+            self.preview.push((UndoPreviewLine::Header, "no changes".to_string()));
+            return
+        }
+        let context = 2;
+        for hunk in hunks {
+            if self.preview.len() >= self.max_preview_lines {
+                break;
+            }
+            let before = hunk.old_start.saturating_sub(context);
+            self.preview.push((UndoPreviewLine::Header, format!("line {}", hunk.new_start + 1)));
+            for line in &old_lines[before..hunk.old_start] {
+                self.preview.push((UndoPreviewLine::Context, Self::preview_line("  ", line)));
+            }
+            for line in &old_lines[hunk.old_start..hunk.old_start + hunk.old_len] {
+                self.preview.push((UndoPreviewLine::Removed, Self::preview_line("- ", line)));
+            }
+            for line in &new_lines[hunk.new_start..hunk.new_start + hunk.new_len] {
+                self.preview.push((UndoPreviewLine::Added, Self::preview_line("+ ", line)));
+            }
+            let after = (hunk.old_start + hunk.old_len + context).min(old_lines.len());
+            for line in &old_lines[hunk.old_start + hunk.old_len..after] {
+                self.preview.push((UndoPreviewLine::Context, Self::preview_line("  ", line)));
+            }
+        }
+        self.preview.truncate(self.max_preview_lines);
+    }
+
+    // keyboard commands come from the editor, which keeps the key focus
+    pub fn handle_command(&mut self, cx: &mut Cx, command: &str) -> UndoHistoryEvent {
+        let len = self.entries.len();
+        if len == 0 {
+            return UndoHistoryEvent::None
+        }
+        match command {
+            "undohistory.next" => self.selected = (self.selected + 1).min(len - 1),
+            "undohistory.prev" => self.selected = self.selected.max(1) - 1,
+            "undohistory.jump" => return UndoHistoryEvent::Jump(self.entries[self.selected].node),
+            "undohistory.close" => return UndoHistoryEvent::Close,
+            _ => ()
+        }
+        self.view.redraw_view_area(cx);
+        UndoHistoryEvent::None
+    }
+
+    pub fn handle_undo_history(&mut self, cx: &mut Cx, event: &mut Event) -> UndoHistoryEvent {
+        if !self.is_open {
+            return UndoHistoryEvent::None
+        }
+        for (index, area) in &self._item_areas {
+            match event.hits(cx, *area, HitOpt::default()) {
+                Event::FingerDown(fe) => {
+                    self.selected = *index;
+                    self.view.redraw_view_area(cx);
+                    if fe.tap_count > 1 {
+                        return UndoHistoryEvent::Jump(self.entries[*index].node)
+                    }
+                    return UndoHistoryEvent::None
+                },
+                Event::FingerHover(_) => {
+                    cx.set_hover_mouse_cursor(MouseCursor::Hand);
+                },
+                _ => ()
+            }
+        }
+        // the panel sits on top of the editor, keep clicks from falling through
+        match event.hits(cx, self._bg_area, HitOpt::default()) {
+            Event::FingerHover(_) => {
+                cx.set_hover_mouse_cursor(MouseCursor::Default);
+            },
+            _ => ()
+        }
+        UndoHistoryEvent::None
+    }
+
+    pub fn draw_undo_history(&mut self, cx: &mut Cx, rect: Rect, text_buffer: &TextBuffer) {
+        if !self.is_open {
+            return
+        }
+        self.update_entries(&text_buffer.undo_tree);
+        self.update_preview(text_buffer);
+
+        let width = Self::width().get(cx).min(rect.w);
+        if self.view.begin_view(cx, Layout {
+            abs_origin: Some(Vec2 {x: rect.x + rect.w - width - 16., y: rect.y}),
+            walk: Walk::wh(Width::Fix(width), Height::Compute),
+            ..Layout::default()
+        }).is_err() {
+            return
+        };
+
+        self.bg.color = Self::color_bg().get(cx);
+        let bg_inst = self.bg.begin_quad(cx, Self::layout_bg().get(cx));
+        self.text.text_style = Self::text_style_item().get(cx);
+
+        let now = undo_time_now();
+        let start = if self.selected >= self.max_visible {self.selected + 1 - self.max_visible} else {0};
+        let end = (start + self.max_visible).min(self.entries.len());
+        self._item_areas.truncate(0);
+        for index in start..end {
+            let entry = &self.entries[index];
+            self.item_bg.color = if index == self.selected {Self::color_item_selected().get(cx)} else {Self::color_bg().get(cx)};
+            let item_inst = self.item_bg.begin_quad(cx, Self::layout_item().get(cx));
+            self.text.color = if entry.on_path {Self::color_label().get(cx)} else {Self::color_off_path().get(cx)};
+            let marker = if entry.node == text_buffer.undo_tree.current {"> "} else {"  "};
+            self.text.draw_text(cx, &format!("{}{}{}", "  ".repeat(entry.depth), marker, entry.label));
+            let age = Self::format_age(now, entry.time);
+            if age.len() > 0 {
+                cx.move_turtle(12., 0.);
+                self.text.color = Self::color_off_path().get(cx);
+                self.text.draw_text(cx, &age);
+            }
+            let item_area = self.item_bg.end_quad(cx, &item_inst);
+            self._item_areas.push((index, item_area));
+            cx.turtle_new_line();
+        }
+
+        cx.turtle_new_line_min_height(8.);
+        for (kind, line) in &self.preview {
+            self.text.color = match kind {
+                UndoPreviewLine::Header => Self::color_header().get(cx),
+                UndoPreviewLine::Context => Self::color_off_path().get(cx),
+                UndoPreviewLine::Removed => Self::color_removed().get(cx),
+                UndoPreviewLine::Added => Self::color_added().get(cx),
+            };
+            self.text.draw_text(cx, line);
+            cx.turtle_new_line();
+        }
+
+        self._bg_area = self.bg.end_quad(cx, &bg_inst);
+        self.view.end_view(cx);
+    }
+}
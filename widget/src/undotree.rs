@@ -0,0 +1,145 @@
+use makepad_microserde::*;
+use crate::textbuffer::*;
+
+// Undo history as a tree, an edit after an undo starts a new branch instead of dropping the redo.
+// Node 0 is the state the history starts from. A node on the path from the root to current holds
+// the edit that takes it back to its parent, any other node the edit that takes its parent to it.
+// TextBuffer applies the edits, this only keeps the shape.
+
+#[derive(Clone, SerBin, DeBin)]
+pub struct TextUndoNode {
+    pub parent: usize,
+    pub children: Vec<usize>,
+    // the branch redo follows, the last one made or visited
+    pub redo_child: Option<usize>,
+    pub edit: Option<TextUndo>,
+    // wall clock seconds, 0 where there is no clock
+    pub time: f64,
+}
+
+#[derive(Clone, SerBin, DeBin)]
+pub struct TextUndoTree {
+    pub nodes: Vec<TextUndoNode>,
+    pub current: usize,
+}
+
+impl Default for TextUndoTree {
+    fn default() -> Self {
+        TextUndoTree {
+            nodes: vec![TextUndoNode {parent: 0, children: Vec::new(), redo_child: None, edit: None, time: 0.}],
+            current: 0
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn undo_time_now() -> f64 {
+    match std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs_f64(),
+        Err(_) => 0.
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn undo_time_now() -> f64 {
+    0.
+}
+
+impl TextUndoTree {
+    pub fn can_undo(&self) -> bool {
+        self.current != 0
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.nodes[self.current].redo_child.is_some()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.len() <= 1
+    }
+
+    pub fn grouping(&self, node: usize) -> Option<&TextUndoGrouping> {
+        self.nodes[node].edit.as_ref().map( | edit | &edit.grouping)
+    }
+
+    pub fn add_child(&mut self, edit: TextUndo, time: f64) {
+        let index = self.nodes.len();
+        self.nodes.push(TextUndoNode {
+            parent: self.current,
+            children: Vec::new(),
+            redo_child: None,
+            edit: Some(edit),
+            time: time
+        });
+        let current = &mut self.nodes[self.current];
+        current.children.push(index);
+        current.redo_child = Some(index);
+        self.current = index;
+    }
+
+    // the nodes from the root down to node, both included
+    pub fn path_to(&self, mut node: usize) -> Vec<usize> {
+        let mut path = vec![node];
+        while node != 0 {
+            node = self.nodes[node].parent;
+            path.push(node);
+        }
+        path.reverse();
+        path
+    }
+
+    // the nodes to step up through from current, then the nodes to step down into to reach target
+    pub fn route_to(&self, target: usize) -> (Vec<usize>, Vec<usize>) {
+        let from = self.path_to(self.current);
+        let to = self.path_to(target);
+        let mut common = 0;
+        while common < from.len() && common < to.len() && from[common] == to[common] {
+            common += 1;
+        }
+        let up = from[common..].iter().rev().cloned().collect();
+        let down = to[common..].to_vec();
+        (up, down)
+    }
+
+    // where an undo stops: the root, a branch point, or the end of a run of grouped edits
+    pub fn is_undo_stop(&self, node: usize) -> bool {
+        let children = &self.nodes[node].children;
+        if node == 0 || children.len() != 1 {
+            return true
+        }
+        let child_grouping = self.grouping(children[0]).unwrap();
+        !child_grouping.wants_grouping() || Some(child_grouping) != self.grouping(node)
+    }
+
+    // how many times the way down from the root turns off the first branch
+    pub fn branch_depth(&self, node: usize) -> usize {
+        let path = self.path_to(node);
+        path.windows(2).filter( | pair | self.nodes[pair[0]].children[0] != pair[1]).count()
+    }
+
+    // keeps only the path to current and the redo chain below it, what a linear undo stack holds
+    pub fn current_branch(&self) -> TextUndoTree {
+        let mut keep = self.path_to(self.current);
+        let mut node = self.current;
+        while let Some(child) = self.nodes[node].redo_child {
+            keep.push(child);
+            node = child;
+        }
+        let mut tree = TextUndoTree {nodes: Vec::new(), current: 0};
+        for (index, node) in keep.iter().enumerate() {
+            let source = &self.nodes[*node];
+            let child = if index + 1 < keep.len() {Some(index + 1)} else {None};
+            tree.nodes.push(TextUndoNode {
+                parent: index.max(1) - 1,
+                children: child.into_iter().collect(),
+                redo_child: child,
+                edit: source.edit.clone(),
+                time: source.time
+            });
+            if *node == self.current {
+                tree.current = index;
+            }
+        }
+        tree
+    }
+}
@@ -1,15 +1,33 @@
 use makepad_render::*;
 use makepad_microserde::*;
 use crate::normalbutton::*;
+use crate::checkbox::*;
+use crate::toggle::*;
+use crate::radiogroup::*;
+use crate::dropdown::*;
+use crate::numberstepper::*;
+use crate::floatslider::*;
+use crate::colorpicker::*;
+use crate::datatable::*;
+use crate::treeview::*;
 use crate::tab::*;
 use crate::desktopwindow::*;
 use crate::windowmenu::*;
+use crate::contextmenu::*;
+use crate::tooltip::*;
+use crate::richtext::*;
 use crate::tabclose::*;
 use crate::texteditor::*;
 use crate::textinput::*;
 use crate::scrollbar::*;
 use crate::scrollshadow::*;
+use crate::focusring::*;
 use crate::desktopbutton::*;
+use crate::commandpalette::*;
+use crate::findbar::*;
+use crate::completion::*;
+use crate::minimap::*;
+use crate::undohistory::*;
 use crate::splitter::*;
 use crate::tabcontrol::*;
 
@@ -53,6 +71,7 @@ impl Theme {
     pub fn color_over_border() -> ColorId {uid!()}
     pub fn color_icon() -> ColorId {uid!()}
     pub fn color_drop_quad() -> ColorId {uid!()}
+    pub fn color_focus_ring() -> ColorId {uid!()}
     
     pub fn color_text_focus() -> ColorId {uid!()}
     pub fn color_text_defocus() -> ColorId {uid!()}
@@ -81,6 +100,7 @@ pub fn set_widget_style(cx: &mut Cx, opt: &StyleOptions) {
         Theme::color_bg_marked_over().set(cx, pick!(17, 70, 110).get(cx));
         Theme::color_over_border().set(cx, pick!(255, 255, 255).get(cx));
         Theme::color_drop_quad().set(cx, pick!(#a).get(cx));
+        Theme::color_focus_ring().set(cx, pick!(0, 122, 204).get(cx));
         Theme::color_text_defocus().set(cx, pick!(#9).get(cx));
         Theme::color_text_focus().set(cx, pick!(#b).get(cx));
         Theme::color_icon().set(cx, pick!(127, 127, 127).get(cx));
@@ -111,6 +131,10 @@ pub fn set_widget_style(cx: &mut Cx, opt: &StyleOptions) {
         TextEditor::color_message_marker_log().set(cx, pick!(200, 200, 200).get(cx));
 
         TextEditor::color_search_marker().set(cx, pick!(128, 64, 0).get(cx));
+        TextEditor::color_remote_1().set(cx, pick!(#e5a03d).get(cx));
+        TextEditor::color_remote_2().set(cx, pick!(#4fb8a6).get(cx));
+        TextEditor::color_remote_3().set(cx, pick!(#c86fd1).get(cx));
+        TextEditor::color_remote_4().set(cx, pick!(#8fbf4a).get(cx));
 
         TextEditor::color_line_number_normal().set(cx, pick!(136, 136, 136).get(cx));
         TextEditor::color_line_number_highlight().set(cx, pick!(212, 212, 212).get(cx));
@@ -170,13 +194,31 @@ pub fn set_widget_style(cx: &mut Cx, opt: &StyleOptions) {
     TabClose::style(cx, opt);
     DesktopWindow::style(cx, opt);
     NormalButton::style(cx, opt);
+    CheckBox::style(cx, opt);
+    Toggle::style(cx, opt);
+    RadioGroup::style(cx, opt);
+    DropDown::style(cx, opt);
+    NumberStepper::style(cx, opt);
+    FloatSlider::style(cx, opt);
+    ColorPicker::style(cx, opt);
+    DataTable::style(cx, opt);
+    TreeItemDraw::style(cx, opt);
     Tab::style(cx, opt);
     MenuItemDraw::style(cx, opt);
+    ContextMenu::style(cx, opt);
+    Tooltip::style(cx, opt);
+    RichText::style(cx, opt);
     TextEditor::style(cx, opt);
     TextInput::style(cx, opt);
     ScrollBar::style(cx, opt);
     ScrollShadow::style(cx, opt);
+    FocusRing::style(cx, opt);
     DesktopButton::style(cx, opt);
     Splitter::style(cx, opt);
     TabControl::style(cx, opt);
+    CommandPalette::style(cx, opt);
+    FindBar::style(cx, opt);
+    CompletionPopup::style(cx, opt);
+    Minimap::style(cx, opt);
+    UndoHistory::style(cx, opt);
 }